@@ -30,6 +30,7 @@ type DropdownOption = string | { value: string; label: string } | undefined | nu
 interface DropdownCellProps {
   readonly kind: "dropdown-cell";
   readonly value: string | undefined | null;
+  readonly display?: string | undefined | null;
   readonly entry: any | null;
   readonly row: number | null;
   readonly column: string | null;
@@ -196,8 +197,8 @@ const renderer: CustomRenderer<DropdownCell> = {
   isMatch: (c): c is DropdownCell => (c.data as any).kind === "dropdown-cell",
   draw: (args, cell) => {
     const { ctx, theme, rect } = args;
-    const { value } = cell.data;
-    const displayText = value;
+    const { value, display } = cell.data;
+    const displayText = display ?? value;
     if (displayText) {
       ctx.fillStyle = theme.textDark;
       ctx.fillText(
@@ -209,8 +210,9 @@ const renderer: CustomRenderer<DropdownCell> = {
     return true;
   },
   measure: (ctx, cell, theme) => {
-    const { value } = cell.data;
-    return (value ? ctx.measureText(value).width : 0) + theme.cellHorizontalPadding * 2;
+    const { value, display } = cell.data;
+    const displayText = display ?? value;
+    return (displayText ? ctx.measureText(displayText).width : 0) + theme.cellHorizontalPadding * 2;
   },
   provideEditor: () => ({
     editor: Editor,
@@ -28,6 +28,7 @@ import DropdownCell from "./Dropdown.tsx";
 type Cell = {
   value: any,
   text: string,
+  display?: string,
 }
 type Row = {
   id: number,
@@ -266,6 +267,7 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
     const params = new URLSearchParams(document.location.search);
     params.set("limit", String(limit));
     params.set("offset", String(first));
+    params.set("display", "true");
     const url = `${site.root}/${rltbl.page.path}/${table}.json?${params.toString()}`;
     console.log("getRowData: " + url);
     try {
@@ -298,6 +300,7 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
     params.set("_change_id", `gt.${change_id.current}`);
     params.delete("limit");
     params.delete("offset");
+    params.set("display", "true");
     const url = `${site.root}/${rltbl.page.path}/${table}.json?${params.toString()}`;
     console.log("pollData: " + url);
     var rows: Row[] = [];
@@ -367,6 +370,7 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
         data: {
           kind: "dropdown-cell",
           value: value,
+          display: rowData.cells[column_name].display,
           row: rowData.id,
           column: column_name,
           entry: null,
@@ -384,7 +388,7 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
       kind: GridCellKind.Text,
       data: String(value),
       allowOverlay: site.editable,
-      displayData: String(rowData.cells[column_name].text),
+      displayData: String(rowData.cells[column_name].display ?? rowData.cells[column_name].text),
     };
   }, [site, columns]);
 
@@ -400,6 +404,8 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
     if (value === "UNDEFINED") return;
     rowData.cells[columns[col].id].value = value;
     rowData.cells[columns[col].id].text = value;
+    // The label of the new value is not known until the row is fetched again:
+    delete rowData.cells[columns[col].id].display;
 
     return rowData;
   }, [columns]);
@@ -502,19 +508,32 @@ export default function Grid(grid_args: { rltbl: any, height: number }) {
       changes: changes
     };
     // console.log("onCellsEdited body", body);
-    try {
-      fetch(`${site.root}/table/${table}`, {
-        method: "POST",
-        headers: {
-          "Content-Type": "application/json",
-        },
-        body: JSON.stringify(body),
-      });
-    } catch (error) {
-      console.error(error.message);
-    }
+    // The response includes the current messages of every cell whose messages were changed by
+    // the edit, including cells other than the edited ones, so we update those that are loaded:
+    fetch(`${site.root}/table/${table}`, {
+      method: "POST",
+      headers: {
+        "Content-Type": "application/json",
+      },
+      body: JSON.stringify(body),
+    })
+      .then((response) => response.json())
+      .then((result) => {
+        const damageList: { cell: [number, number] }[] = [];
+        for (const cell of result.messages || []) {
+          if (cell.table !== table) { continue; }
+          const r = idRowRef.current[cell.row];
+          const c = columnIndex.get(cell.column);
+          const data = r === undefined ? undefined : dataRef.current[r];
+          if (!data || c === undefined || !data.cells[cell.column]) { continue; }
+          data.cells[cell.column].messages = cell.messages;
+          damageList.push({ cell: [c, r] });
+        }
+        gridRef.current?.updateCells(damageList);
+      })
+      .catch((error) => console.error(error.message));
 
-  }, [rltbl, site, user, table, columns, dataRef, onCellEdited]);
+  }, [rltbl, site, user, table, columns, columnIndex, dataRef, idRowRef, gridRef, onCellEdited]);
 
   // const onRowMoved = React.useCallback((from: number, to: number) => {
   //   console.log("ROW MOVED", from, to);
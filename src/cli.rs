@@ -4,23 +4,36 @@
 
 use crate as rltbl;
 use rltbl::{
-    core::{Change, ChangeAction, ChangeSet, Relatable, ValidationLevel},
-    select::{Format, Select},
+    core::{
+        blame_to_tsv, column_stats_to_tsv, BatchOperation, Change, ChangeAction, ChangeCaptureMode,
+        ChangeSet, ConfigProblem, Diagnosis, Drift, Encoding, LoadStatus, Relatable,
+        RelatableError, ResultSet, TableLoad, ValidationLevel, MAX_LIMIT,
+    },
+    remote::Remote,
+    select::{Format, Limit, Select},
     sql,
     sql::{CachingStrategy, JsonRow, SqlParam, VecInto},
-    table::Table,
+    table::{Locale, MappedColumn, Mapping, Table, Transform, MAPPING_TRANSFORMS},
     web::{serve, serve_cgi},
 };
 
 use ansi_term::Style;
 use anyhow::Result;
-use clap::{ArgAction, Parser, Subcommand};
+use clap::{error::ErrorKind, ArgAction, ArgMatches, CommandFactory as _, Parser, Subcommand};
+use clap_complete::{
+    engine::{ArgValueCandidates, CompletionCandidate},
+    env::Shells,
+    CompleteEnv,
+};
 use clap_verbosity_flag::Verbosity;
 use colored::Colorize;
+use futures::future::BoxFuture;
+use indexmap::IndexMap;
 use promptly::prompt_opt;
 use regex::Regex;
-use serde_json::{json, to_string_pretty, Value as JsonValue};
-use std::{io, io::Write, path::Path};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, to_string_pretty, Map as JsonMap, Value as JsonValue};
+use std::{io, io::IsTerminal as _, io::Read as _, io::Write, path::Path, str::FromStr};
 use tabwriter::TabWriter;
 
 static COLUMN_HELP: &str = "A column name or label";
@@ -28,6 +41,32 @@ static ROW_HELP: &str = "A row number";
 static TABLE_HELP: &str = "A table name";
 static VALUE_HELP: &str = "A value for a cell";
 static VALIDATION_LEVEL_HELP: &str = "One of 'none', 'sql_type', 'full'";
+static MESSAGE_HELP: &str = "A human-readable description of the change";
+
+/// The format in which commands print their results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Human-readable text
+    #[default]
+    Text,
+    /// JSON, using the structures documented in doc/output.md
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(format: &str) -> Result<Self> {
+        tracing::trace!("OutputFormat::from_str({format:?})");
+        match format.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(
+                RelatableError::InputError(format!("Unrecognized output format: {format}")).into(),
+            ),
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[command(version,
@@ -47,6 +86,10 @@ pub struct Cli {
     #[arg(long, action = ArgAction::Set, env = "RLTBL_INPUT")]
     pub input: Option<String>,
 
+    /// One of: text, json. The format in which to print the results of the command.
+    #[arg(long, default_value = "text", action = ArgAction::Set, env = "RLTBL_OUTPUT")]
+    pub output: OutputFormat,
+
     #[command(flatten)]
     verbose: Verbosity,
 
@@ -54,6 +97,21 @@ pub struct Cli {
     #[arg(long, default_value = "trigger", action = ArgAction::Set)]
     pub caching: CachingStrategy,
 
+    /// One of: iso, en_US, en_GB, de, fr, es, it, nl. The locale of the files that are loaded
+    /// and saved, for columns that do not specify their own.
+    #[arg(long, default_value = "iso", action = ArgAction::Set, env = "RLTBL_LOCALE")]
+    pub locale: Locale,
+
+    /// One of: tab, comma, semicolon, or any single character. The delimiter of the fields of
+    /// the files that are loaded, which is otherwise detected from their first lines.
+    #[arg(long, value_parser = parse_delimiter, action = ArgAction::Set, env = "RLTBL_DELIMITER")]
+    pub delimiter: Option<u8>,
+
+    /// One of: utf-8, utf-16le, utf-16be, latin-1. The character encoding of the files that are
+    /// loaded, which is otherwise detected from their first bytes.
+    #[arg(long, action = ArgAction::Set, env = "RLTBL_ENCODING")]
+    pub encoding: Option<Encoding>,
+
     // Subcommand:
     #[command(subcommand)]
     pub command: Command,
@@ -68,6 +126,11 @@ pub enum Command {
         /// Overwrite an existing database
         #[arg(long, action = ArgAction::SetTrue)]
         force: bool,
+
+        /// One of: trigger, write_path. How changes to the tables are captured; use write_path
+        /// for databases in which triggers cannot be created
+        #[arg(long, default_value = "trigger", action = ArgAction::Set)]
+        change_capture: ChangeCaptureMode,
     },
 
     /// Get data from the database
@@ -106,8 +169,46 @@ pub enum Command {
         subcommand: DeleteSubcommand,
     },
 
+    /// Move the rows of a table that match all of the given filters, along with their history and
+    /// messages, to archive tables, which are left out of reads of the table unless archived rows
+    /// are included, e.g., with `include_archived=true` in a URL
+    Archive {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+
+        #[arg(long, value_name = "FILTER", action = ArgAction::Append, required = true,
+              help = "A filter, e.g., 'year < 2020', that the rows to archive must match (can be \
+                      given more than once)")]
+        filter: Vec<String>,
+    },
+
+    /// Read edits from STDIN, either as commands of the form `set value TABLE ROW COLUMN VALUE`,
+    /// `add row TABLE JSON`, `delete row TABLE ROW`, or `move row TABLE ROW AFTER`, one per
+    /// line, or as a JSON array of operations, and apply them all at once, as a single change
+    Batch {
+        #[arg(long,
+              default_value = "full",
+              action = ArgAction::Set,
+              help = VALIDATION_LEVEL_HELP)
+        ]
+        validation_level: ValidationLevel,
+
+        #[arg(long, value_name = "MESSAGE", action = ArgAction::Set, help = MESSAGE_HELP)]
+        message: Option<String>,
+
+        /// Apply the edits to an in-memory copy of the database instead, and print the validation
+        /// messages that they would add and remove
+        #[arg(long, action = ArgAction::SetTrue)]
+        preview: bool,
+    },
+
     /// Undo changes to the database
-    Undo {},
+    Undo {
+        /// Print the changes that the undo would make, without making them
+        #[arg(long, action = ArgAction::SetTrue)]
+        preview: bool,
+    },
 
     /// Redo changes to the database that have been undone
     Redo {},
@@ -122,10 +223,45 @@ pub enum Command {
 
     /// Load data into the datanase
     Load {
+        #[arg(long, value_name = "MESSAGE", action = ArgAction::Set, help = MESSAGE_HELP)]
+        message: Option<String>,
+
         #[command(subcommand)]
         subcommand: LoadSubcommand,
     },
 
+    /// Build a reusable mapping from the columns of a source file, such as a recurring feed, to
+    /// those of a table, and save it as YAML for use with `load table --mapping`. Columns are
+    /// matched by name, and then by the given flags, after which, if STDIN is a terminal, the
+    /// mapping of each column is confirmed interactively.
+    Map {
+        #[arg(value_name = "PATH", action = ArgAction::Set,
+              help = "The source file (TSV, or CSV if its extension is .csv)")]
+        path: String,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+
+        #[arg(long, value_name = "TARGET=SOURCE", action = ArgAction::Append,
+              help = "Map the TARGET column of the table to the SOURCE column of the file")]
+        column: Vec<String>,
+
+        #[arg(long, value_name = "TARGET=VALUE", action = ArgAction::Append,
+              help = "Give the TARGET column of the table the constant VALUE")]
+        constant: Vec<String>,
+
+        #[arg(long, value_name = "TARGET=TRANSFORMS", action = ArgAction::Append,
+              help = "Apply the comma-separated TRANSFORMS (e.g., trim, lowercase, uppercase, \
+                      strip_prefix('ID:'), date('%m/%d/%Y')) to the source value of the TARGET \
+                      column")]
+        transform: Vec<String>,
+
+        #[arg(long, value_name = "OUTPUT", action = ArgAction::Set,
+              help = "The YAML file to save the mapping to (defaults to STDOUT)")]
+        output: Option<String>,
+    },
+
     /// Save the data
     Save {
         /// The directory to which to save the table .TSVs (defaults to the value of "path" from
@@ -134,6 +270,83 @@ pub enum Command {
         save_dir: Option<String>,
     },
 
+    /// Check the database's indexes, creating any that are missing
+    Maintain {},
+
+    /// Diagnose problems with the database and suggest how to fix them
+    Doctor {},
+
+    /// Compare the contents of a table with the file that it is loaded from and saved to, and
+    /// report whether either of them has changed since the table was last loaded or saved
+    Verify {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table to verify")]
+        table: String,
+    },
+
+    /// Check the configuration stored in the meta tables
+    Check {
+        #[command(subcommand)]
+        subcommand: CheckSubcommand,
+    },
+
+    /// Change how the database is set up
+    Config {
+        #[command(subcommand)]
+        subcommand: ConfigSubcommand,
+    },
+
+    /// Print a script that enables completion of rltbl commands, including the names of tables
+    /// and columns in the current database, for the given shell
+    Completions {
+        #[arg(value_name = "SHELL", action = ArgAction::Set,
+              help = "One of: bash, elvish, fish, powershell, zsh")]
+        shell: String,
+    },
+
+    /// Run a canonical scenario against SQLite and, optionally, PostgreSQL, and report any
+    /// differences in behaviour between them
+    Selftest {
+        #[arg(long, value_name = "PATH", action = ArgAction::Set,
+              default_value = "build/selftest.db",
+              help = "The SQLite database to run the scenario against (it will be overwritten)")]
+        sqlite: String,
+
+        #[arg(long, value_name = "URL", action = ArgAction::Set,
+              help = "The PostgreSQL database to run the scenario against (it will be overwritten)")]
+        postgres: Option<String>,
+    },
+
+    /// Synchronize the operation log with that of another relatable database
+    Sync {
+        #[arg(value_name = "PEER", action = ArgAction::Set,
+              help = "The location of the peer database")]
+        peer: String,
+    },
+
+    /// Run commands against a remote rltbl server, using its HTTP API, rather than a local
+    /// database. Changes are validated and committed by the server, according to its own settings.
+    Remote {
+        #[arg(value_name = "URL", action = ArgAction::Set,
+              help = "The root URL of the remote rltbl server")]
+        url: String,
+
+        #[command(subcommand)]
+        subcommand: RemoteSubcommand,
+    },
+
+    /// Keep read-only local copies of tables from remote rltbl servers
+    Mirror {
+        #[command(subcommand)]
+        subcommand: MirrorSubcommand,
+    },
+
+    /// Manage the full-text search indexes of tables
+    Search {
+        #[command(subcommand)]
+        subcommand: SearchSubcommand,
+    },
+
     /// Drop database tables
     Drop {
         #[command(subcommand)]
@@ -153,6 +366,21 @@ pub enum Command {
         /// Instruct the server to exit after this many seconds. Defaults to 0, i.e., no timeout.
         #[arg(long, default_value="0", action = ArgAction::Set)]
         timeout: usize,
+
+        /// Cancel the queries made to answer a request after this many milliseconds, responding
+        /// with 504 Gateway Timeout. Defaults to 0, i.e., no timeout.
+        #[arg(long, default_value="0", action = ArgAction::Set)]
+        query_timeout: u64,
+
+        /// The most rows that may be asked for at once in a request, for any table, or 0 for no
+        /// maximum. Tables may have lower maximums of their own.
+        #[arg(long, default_value_t = MAX_LIMIT, action = ArgAction::Set)]
+        max_limit: usize,
+
+        /// Once asked to stop, e.g., by SIGTERM, wait at most this many seconds for the requests
+        /// in progress to finish before exiting, with an error, without them.
+        #[arg(long, default_value="30", action = ArgAction::Set)]
+        drain_timeout: u64,
     },
 
     /// Run Relatable as a CGI script
@@ -169,26 +397,52 @@ pub enum Command {
               default_value_t = 1000)]
         size: usize,
     },
+
+    /// Run a subcommand provided by a [Plugin], or else by an executable named `rltbl-NAME`
+    #[command(external_subcommand)]
+    External(Vec<String>),
+}
+
+/// A custom subcommand, e.g., for a domain-specific project, that can be added to the
+/// command-line interface of a program that calls [process_command_with_plugins()] in place of
+/// [process_command()], and that reuses its [Relatable] instance and global options.
+pub trait Plugin: Send + Sync {
+    /// The subcommand, whose name is the name by which it is invoked, e.g., `ontology`, and
+    /// whose arguments and subcommands are parsed from the rest of the command line
+    fn command(&self) -> clap::Command;
+
+    /// Run the subcommand with the given matches, using the given instance
+    fn run<'a>(
+        &'a self,
+        cli: &'a Cli,
+        rltbl: &'a Relatable,
+        matches: &'a ArgMatches,
+    ) -> BoxFuture<'a, Result<()>>;
 }
 
 #[derive(Subcommand, Debug)]
 pub enum GetSubcommand {
     /// Get the column header and rows from a given table.
     Table {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
-        /// Zero or more filters
+        /// Zero or more filters, e.g., 'sample_number > 5' or 'island in (Dream, Biscoe)'
         #[arg(value_name = "FILTERS", action = ArgAction::Set)]
         filters: Vec<String>,
 
-        /// Output format: text, vertical, JSON, TSV
+        /// Output format: text, vertical, JSON, TSV, Arrow (an Arrow IPC stream)
         #[arg(long, default_value="", action = ArgAction::Set)]
         format: String,
 
-        /// Limit to this many rows
+        /// Only get the rows that contain all of these words, using the table's search index
+        #[arg(long, action = ArgAction::Set)]
+        search: Option<String>,
+
+        /// Limit to this many rows, or 'none' for all of them
         #[arg(long, default_value="100", action = ArgAction::Set)]
-        limit: usize,
+        limit: Limit,
 
         /// Offset by this many rows
         #[arg(long, default_value="0", action = ArgAction::Set)]
@@ -197,12 +451,13 @@ pub enum GetSubcommand {
 
     /// Get the rows from a given table.
     Rows {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
-        /// Limit to this many rows
+        /// Limit to this many rows, or 'none' for all of them
         #[arg(long, default_value="100", action = ArgAction::Set)]
-        limit: usize,
+        limit: Limit,
 
         /// Offset by this many rows
         #[arg(long, default_value="0", action = ArgAction::Set)]
@@ -211,28 +466,50 @@ pub enum GetSubcommand {
 
     /// Get the value of a given column of a given row from a given table.
     Value {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
         row: u64,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
+        column: String,
+    },
+
+    /// Get the last change to a given column of each row of a given table.
+    Blame {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: String,
     },
+
+    /// Get statistics about the values of each of the columns of a given table.
+    Stats {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 pub enum SetSubcommand {
     /// Set the value of a given column of a given row from a given table.
     Value {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
         row: u64,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: String,
 
         #[arg(value_name = "VALUE", action = ArgAction::Set, help = VALUE_HELP)]
@@ -244,6 +521,34 @@ pub enum SetSubcommand {
               help = VALIDATION_LEVEL_HELP)
         ]
         validation_level: ValidationLevel,
+
+        #[arg(long, value_name = "MESSAGE", action = ArgAction::Set, help = MESSAGE_HELP)]
+        message: Option<String>,
+    },
+
+    /// Fill a given column of a range of rows of a given table with the value of its first row,
+    /// or with the series that its first two rows begin.
+    Fill {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
+        column: String,
+
+        #[arg(value_name = "FROM_ROW", action = ArgAction::Set,
+              help = "The first row of the range, whose value is filled down")]
+        from_row: u64,
+
+        #[arg(value_name = "TO_ROW", action = ArgAction::Set,
+              help = "The last row of the range, in the table's order")]
+        to_row: u64,
+
+        /// Extend the series of numbers or dates begun by the first two rows, instead of copying
+        /// the value of the first row
+        #[arg(long, action = ArgAction::SetTrue)]
+        series: bool,
     },
 }
 
@@ -253,7 +558,13 @@ pub enum AddSubcommand {
         #[arg(long, action = ArgAction::Set)]
         after_id: Option<u64>,
 
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(long, value_name = "TEMPLATE", action = ArgAction::Set,
+              help = "The name of a row template whose default values are to be used for any \
+                      columns that are not given a value")]
+        template: Option<String>,
+
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(long,
@@ -267,21 +578,37 @@ pub enum AddSubcommand {
     /// Read a JSON-formatted string representing a row (of the form: { "level": LEVEL,
     /// "rule": RULE, "message": MESSAGE}) from STDIN and add it to the message table.
     Message {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
         row: u64,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: String,
     },
+
+    /// Read a JSON-formatted string representing a row (of the form: { "column": VALUE, ...})
+    /// from STDIN and save it as the row template with the given name for the given table,
+    /// replacing any existing template with that name.
+    Template {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+
+        #[arg(value_name = "TEMPLATE", action = ArgAction::Set,
+              help = "The name of the template")]
+        template: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 pub enum MoveSubcommand {
     Row {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
@@ -297,13 +624,15 @@ pub enum MoveSubcommand {
 pub enum ValidateSubcommand {
     /// Validate the data in the given table
     Table {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
     },
 
     /// Validate the given row from the given table.
     Row {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
@@ -312,30 +641,50 @@ pub enum ValidateSubcommand {
 
     /// Validate the data in the given column of the given table
     Column {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: String,
     },
 
     /// Validate the value of the given column of the given row from the given table.
     Value {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
         row: u64,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: String,
     },
+
+    /// Revalidate exactly those columns whose validation depends on the given datatype, e.g.,
+    /// after its condition has been changed.
+    Datatype {
+        #[arg(value_name = "DATATYPE", action = ArgAction::Set,
+              help = "The name of the datatype")]
+        datatype: String,
+    },
+
+    /// Revalidate the rules defined for the given table in the rule table
+    Rules {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
+        table: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
 pub enum DeleteSubcommand {
     Row {
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
@@ -356,13 +705,15 @@ pub enum DeleteSubcommand {
               help = "Only delete messages from the given row or column that were added by USER.")]
         user: Option<String>,
 
-        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP,
+              add = ArgValueCandidates::new(complete_table))]
         table: String,
 
         #[arg(value_name = "ROW", action = ArgAction::Set, help = ROW_HELP)]
         row: Option<u64>,
 
-        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP)]
+        #[arg(value_name = "COLUMN", action = ArgAction::Set, help = COLUMN_HELP,
+              add = ArgValueCandidates::new(complete_column))]
         column: Option<String>,
     },
 }
@@ -380,11 +731,240 @@ pub enum LoadSubcommand {
         ]
         validation_level: ValidationLevel,
 
+        #[arg(long, action = ArgAction::SetTrue,
+              help = "Maintain a materialized _change_id column on the loaded table(s)")]
+        materialize_change_id: bool,
+
+        #[arg(long, action = ArgAction::SetTrue,
+              help = "Identify the rows of the loaded table(s) by UUID, in a _uuid column")]
+        uuid_row_ids: bool,
+
+        #[arg(long, value_name = "MAPPING", action = ArgAction::Set,
+              help = "Add the rows of the file(s) to an existing table using the given mapping \
+                      (see `rltbl map`)")]
+        mapping: Option<String>,
+
+        #[arg(long, action = ArgAction::SetTrue,
+              help = "Resume the interrupted load(s) of the table(s), starting after the last \
+                      rows that were loaded")]
+        resume: bool,
+
+        #[arg(long, value_name = "OLD=NEW", value_delimiter = ',', action = ArgAction::Append,
+              help = "Load the given columns of the file(s) under new names, e.g., \
+                      `--rename 'Sample No=sample_number,Island Name=island'`")]
+        rename: Vec<String>,
+
+        #[arg(long, value_name = "PATH", action = ArgAction::Set,
+              help = "Load the columns of the file(s) named in the 'old' column of the given \
+                      TSV file under the names in its 'new' column")]
+        rename_file: Option<String>,
+
+        #[arg(long, value_name = "COLUMN", action = ArgAction::Set,
+              help = "Partition the loaded table(s) by the values of the given column, storing \
+                      the rows with each value in a separate table")]
+        partition_by: Option<String>,
+
         #[arg(value_name = "PATH", num_args=1..,
               action = ArgAction::Set,
               help = "The path(s) to load from")]
         paths: Vec<String>,
     },
+
+    /// Load the tables at the given paths, or, if none are given, reload every table that has a
+    /// path in the table table, in parallel, loading each table only after the tables it depends
+    /// on
+    All {
+        #[arg(long, action = ArgAction::SetTrue)]
+        force: bool,
+
+        #[arg(long,
+              default_value = "full",
+              action = ArgAction::Set,
+              help = VALIDATION_LEVEL_HELP)
+        ]
+        validation_level: ValidationLevel,
+
+        #[arg(long, value_name = "JOBS", action = ArgAction::Set,
+              help = "The number of tables to load at once (defaults to the number of CPUs). \
+                      Ignored for SQLite, which loads one table at a time")]
+        jobs: Option<usize>,
+
+        #[arg(value_name = "PATH", action = ArgAction::Set,
+              help = "The path(s) to load from")]
+        paths: Vec<String>,
+    },
+
+    /// Load records from a TSV fixture into the change, history, or message table, remapping
+    /// their IDs so as not to collide with the records already in the database
+    Meta {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "One of: change, history, message")]
+        table: String,
+
+        #[arg(value_name = "PATH", action = ArgAction::Set, help = "The path to load from")]
+        path: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteSubcommand {
+    /// Get data from the remote server
+    Get {
+        #[command(subcommand)]
+        subcommand: GetSubcommand,
+    },
+
+    /// Set data on the remote server
+    Set {
+        #[command(subcommand)]
+        subcommand: SetSubcommand,
+    },
+
+    /// Upload TSV files to the remote server and load them into tables there
+    Load {
+        #[command(subcommand)]
+        subcommand: RemoteLoadSubcommand,
+    },
+
+    /// Validate data on the remote server
+    Validate {
+        #[command(subcommand)]
+        subcommand: RemoteValidateSubcommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteLoadSubcommand {
+    /// Load one or more small TSV files, each into the table named after the file
+    Table {
+        #[arg(long, action = ArgAction::SetTrue)]
+        force: bool,
+
+        #[arg(long, value_name = "MESSAGE", action = ArgAction::Set, help = MESSAGE_HELP)]
+        message: Option<String>,
+
+        #[arg(value_name = "PATHS", num_args = 1.., action = ArgAction::Set,
+              help = "The paths of the TSV files to load")]
+        paths: Vec<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RemoteValidateSubcommand {
+    /// Validate the data in the given table
+    Table {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        table: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MirrorSubcommand {
+    /// Mirror the given tables of the rltbl server at the given URL, under the given name
+    Add {
+        #[arg(value_name = "NAME", action = ArgAction::Set, help = "A name for the mirror")]
+        name: String,
+
+        #[arg(value_name = "URL", action = ArgAction::Set,
+              help = "The URL of the remote rltbl server")]
+        url: String,
+
+        #[arg(value_name = "TABLE", num_args = 1.., action = ArgAction::Set,
+              help = "The name(s) of the remote table(s) to mirror")]
+        tables: Vec<String>,
+    },
+
+    /// Pull the latest changes to the mirrored tables into their local copies
+    Pull {
+        #[arg(value_name = "NAME", action = ArgAction::Set,
+              help = "The mirror to pull from (defaults to all of them)")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SearchSubcommand {
+    /// Add a full-text search index to the given table, replacing any that it already has
+    Add {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        table: String,
+
+        #[arg(value_name = "COLUMN", action = ArgAction::Set,
+              help = "The column(s) to index (defaults to all of them)")]
+        columns: Vec<String>,
+    },
+
+    /// Remove the full-text search index from the given table
+    Remove {
+        #[arg(value_name = "TABLE", action = ArgAction::Set, help = TABLE_HELP)]
+        table: String,
+    },
+
+    /// Rebuild the full-text search index of the given table
+    Rebuild {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table whose index to rebuild (defaults to all of them)")]
+        table: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CheckSubcommand {
+    /// Check that the tables, columns, and datatypes configured in the meta tables are consistent
+    /// with each other and with the database, recording any problems as messages
+    Config {},
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSubcommand {
+    /// Change a setting of the database
+    Set {
+        #[command(subcommand)]
+        subcommand: ConfigSetSubcommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigSetSubcommand {
+    /// Drop and create the triggers that clear the cache when tables change, as the given
+    /// caching strategy requires, and clear the cache
+    Caching {
+        #[arg(value_name = "STRATEGY", action = ArgAction::Set,
+              help = "One of: none, truncate, truncate_all, trigger, memory")]
+        strategy: CachingStrategy,
+    },
+    /// Set whether a table is saved to its path automatically whenever it is changed
+    Autosave {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table to save automatically, or not")]
+        table: String,
+
+        #[arg(value_name = "ENABLED", action = ArgAction::Set,
+              help = "Whether to save the table automatically (true or false)")]
+        enabled: bool,
+    },
+    /// Set the most rows of a table that may be asked for at once in a request
+    MaxLimit {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table to set the maximum page size of")]
+        table: String,
+
+        #[arg(value_name = "LIMIT", action = ArgAction::Set,
+              help = "The maximum number of rows, or 0 to use the server's maximum")]
+        limit: usize,
+    },
+    /// Set whether a table is in wide mode, in which its rows' history and messages are only
+    /// looked up one row at a time
+    Wide {
+        #[arg(value_name = "TABLE", action = ArgAction::Set,
+              help = "The table to put in wide mode, or not")]
+        table: String,
+
+        #[arg(value_name = "ENABLED", action = ArgAction::Set,
+              help = "Whether the table is in wide mode (true or false), or, if omitted, \
+                      whether it has more columns than the limit")]
+        enabled: Option<bool>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -392,16 +972,21 @@ pub enum DropSubcommand {
     Database {},
 }
 
-pub async fn init(cli: &Cli, force: &bool, path: Option<&str>) {
-    tracing::trace!("init({cli:?}, {force}, {path:?})");
-    match Relatable::init(force, path, &cli.caching).await {
-        Ok(_) => println!(
-            "Initialized a relatable database in '{}'",
-            match path {
-                None => rltbl::core::RLTBL_DEFAULT_DB,
-                Some(db) => db,
-            }
-        ),
+pub async fn init(
+    cli: &Cli,
+    force: &bool,
+    path: Option<&str>,
+    change_capture_mode: &ChangeCaptureMode,
+) {
+    tracing::trace!("init({cli:?}, {force}, {path:?}, {change_capture_mode:?})");
+    match Relatable::init_with_change_capture(force, path, &cli.caching, change_capture_mode).await
+    {
+        Ok(_) => {
+            let database = path.unwrap_or(rltbl::core::RLTBL_DEFAULT_DB);
+            print_output(cli, &json!({ "database": database }), |_| {
+                println!("Initialized a relatable database in '{database}'")
+            });
+        }
         Err(err) => panic!("{err:?}"),
     }
 }
@@ -427,41 +1012,196 @@ pub fn print_tsv(rows: Vec<Vec<String>>) {
     }
 }
 
-/// Print a table with its column header.
-pub async fn print_table(
-    cli: &Cli,
-    table_name: &str,
-    filters: &Vec<String>,
-    format: &str,
-    limit: &usize,
-    offset: &usize,
-) {
-    tracing::trace!("print_table({cli:?}, {table_name}, {filters:?}, {format}, {limit}, {offset})");
-
-    // Initiate a rltbl instance, get the retrieve the Table struct corresponding to the given
-    // table name and ensure that the text view on the table has been created.
-    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
-        .await
-        .expect("Error initializing a relatable instance");
+/// Print the result of a command in the output format given on the command line. For JSON
+/// output, print the given value as JSON; otherwise use `print_text` to print it as text.
+pub fn print_output<T: Serialize>(cli: &Cli, value: &T, print_text: impl FnOnce(&T)) {
+    match cli.output {
+        OutputFormat::Text => print_text(value),
+        OutputFormat::Json => println!(
+            "{}",
+            to_string_pretty(value).expect("Error serializing output to JSON")
+        ),
+    }
+}
 
-    // Initialize a Select struct using the table struct:
-    let mut select = Select::from(table_name)
-        .filters(filters)
-        .unwrap()
-        .limit(limit)
-        .offset(offset);
+/// A change in a user's history, as output by the `history` command.
+#[derive(Debug, Serialize)]
+pub struct HistoryRecord {
+    pub change_id: u64,
+    /// One of 'do', 'undo', or 'redo'
+    pub action: String,
+    pub table: String,
+    pub description: String,
+    pub message: Option<String>,
+    pub change_group: Option<u64>,
+    pub changes: Vec<Change>,
+    /// Whether the change can be undone ('done') or redone ('undone')
+    pub state: String,
+    /// Whether this is the change that the next undo or redo will apply to
+    pub next: bool,
+}
 
-    // We will use the default view to retrieve the data:
-    select.view_name = format!("{table_name}_default_view");
+impl HistoryRecord {
+    /// Convert a change from the user's history to a record, given its state and whether it is
+    /// next in line to be undone or redone.
+    fn from_json_row(change: &JsonRow, state: &str, next: bool) -> Self {
+        let content = change.get_string("content").expect("No content found");
+        Self {
+            change_id: change
+                .get_unsigned("change_id")
+                .expect("No change_id found"),
+            action: change.get_string("action").expect("No action found"),
+            table: change.get_string("table").unwrap_or_default(),
+            description: change.get_string("description").unwrap_or_default(),
+            message: change.get_optional_string("message").unwrap_or_default(),
+            change_group: change
+                .get_optional_string("change_group")
+                .unwrap_or_default()
+                .and_then(|group| group.parse::<u64>().ok()),
+            changes: Change::many_from_str(&content).expect("Could not parse content"),
+            state: state.to_string(),
+            next,
+        }
+    }
 
-    match format.to_lowercase().as_str() {
+    /// Render the record as a line of text.
+    fn to_line(&self) -> String {
+        let content = self
+            .changes
+            .iter()
+            .map(|c| c.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let group = match self.change_group {
+            Some(change_group) => format!(", group #{change_group}"),
+            None => String::new(),
+        };
+        let message = match &self.message {
+            Some(message) => format!(": {message}"),
+            None => String::new(),
+        };
+        format!(
+            "{content} (action #{change_id}, {action}{group}){message}",
+            change_id = self.change_id,
+            action = self.action
+        )
+    }
+}
+
+/// A summary of the messages for the part of a table that has been validated, as output by the
+/// `validate` commands.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ValidationSummary {
+    pub table: String,
+    pub row: Option<u64>,
+    pub column: Option<String>,
+    pub messages: Vec<JsonValue>,
+}
+
+impl ValidationSummary {
+    /// Collect the messages for the given table, optionally restricted to the given row and
+    /// column.
+    pub async fn collect(
+        rltbl: &Relatable,
+        table: &str,
+        row: Option<u64>,
+        column: Option<&str>,
+    ) -> Self {
+        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
+        let mut sql = format!(
+            r#"SELECT "row", "column", "value", "level", "rule", "message"
+               FROM "message"
+               WHERE "table" = {}"#,
+            sql_param_gen.next()
+        );
+        let mut params = vec![json!(table)];
+        if let Some(row) = row {
+            sql.push_str(&format!(r#" AND "row" = {}"#, sql_param_gen.next()));
+            params.push(json!(row));
+        }
+        if let Some(column) = column {
+            sql.push_str(&format!(r#" AND "column" = {}"#, sql_param_gen.next()));
+            params.push(json!(column));
+        }
+        sql.push_str(r#" ORDER BY "row", "column", "message_id""#);
+        let messages = rltbl
+            .connection
+            .query(&sql, Some(&json!(params)))
+            .await
+            .expect("Error getting messages")
+            .into_iter()
+            .map(|message| json!(message.content))
+            .collect();
+        Self {
+            table: table.to_string(),
+            row,
+            column: column.map(|c| c.to_string()),
+            messages,
+        }
+    }
+}
+
+/// Print a table with its column header.
+pub async fn print_table(
+    cli: &Cli,
+    table_name: &str,
+    filters: &Vec<String>,
+    search: &Option<String>,
+    format: &str,
+    limit: &Limit,
+    offset: &usize,
+) {
+    tracing::trace!(
+        "print_table({cli:?}, {table_name}, {filters:?}, {search:?}, {format}, {limit}, {offset})"
+    );
+
+    // Initiate a rltbl instance, get the retrieve the Table struct corresponding to the given
+    // table name and ensure that the text view on the table has been created.
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Error initializing a relatable instance");
+
+    // Initialize a Select struct using the table struct:
+    let mut select = match Select::from(table_name).filters(filters) {
+        Ok(mut select) => {
+            select.limit = *limit;
+            select.offset(offset)
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(query) = search {
+        select = select.search(query);
+    }
+
+    // We will use the default view to retrieve the data:
+    select.view_name = format!("{table_name}_default_view");
+
+    print_result(cli, table_name, rltbl.fetch(&select).await.unwrap(), format);
+
+    tracing::debug!("Processed: {}", {
+        let format = Format::try_from(&format.to_string()).unwrap();
+        select.to_url("/table", &format).unwrap()
+    });
+}
+
+/// Print the given result of a query on the given table in the given format.
+fn print_result(cli: &Cli, table_name: &str, result: ResultSet, format: &str) {
+    // JSON output, if requested, takes precedence over the given format:
+    let format = match cli.output {
+        OutputFormat::Json => "json",
+        OutputFormat::Text => format,
+    };
+    match format.to_lowercase().as_str() {
         "json" => {
-            let json = json!(rltbl.fetch(&select).await.unwrap());
+            let json = json!(result);
             print!("{}", to_string_pretty(&json).unwrap());
         }
         "vertical" => {
             println!("{table_name}\n-----");
-            for row in rltbl.fetch(&select).await.unwrap().rows {
+            for row in result.rows {
                 for (column, cell) in row.cells.iter() {
                     let messages = cell
                         .messages
@@ -478,27 +1218,28 @@ pub async fn print_table(
             }
         }
         "text" | "" => {
-            println!("{}", rltbl.fetch(&select).await.unwrap().to_console());
+            println!("{}", result.to_console());
+        }
+        "arrow" => {
+            let arrow = result.to_arrow().expect("Error writing Arrow stream");
+            io::stdout()
+                .write_all(&arrow)
+                .expect("Error writing to STDOUT");
         }
         _ => unimplemented!("output format {format}"),
     };
-
-    tracing::debug!("Processed: {}", {
-        let format = Format::try_from(&format.to_string()).unwrap();
-        let url = select.to_url("/table", &format).unwrap();
-        url
-    });
 }
 
 /// Print rows of a table, without column header.
-pub async fn print_rows(cli: &Cli, table_name: &str, limit: &usize, offset: &usize) {
+pub async fn print_rows(cli: &Cli, table_name: &str, limit: &Limit, offset: &usize) {
     tracing::trace!("print_rows({cli:?}, {table_name}, {limit}, {offset})");
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let select = Select::from(table_name).limit(limit).offset(offset);
-    let rows = rltbl.fetch_rows(&select).await.unwrap().vec_into();
-    print_text(&rows);
+    let mut select = Select::from(table_name).offset(offset);
+    select.limit = *limit;
+    let rows: Vec<Vec<String>> = rltbl.fetch_rows(&select).await.unwrap().vec_into();
+    print_output(cli, &rows, print_text);
 }
 
 /// Print the value of the given column of the given row of the given table
@@ -512,37 +1253,127 @@ pub async fn print_value(cli: &Cli, table: &str, row: u64, column: &str) {
         sql_param = sql::SqlParam::new(&rltbl.connection.kind()).next(),
     );
     let params = json!([row]);
-    if let Some(value) = rltbl
+    let value = rltbl
         .connection
         .query_value(&statement, Some(&params))
         .await
-        .unwrap()
-    {
-        let text = match value {
-            JsonValue::String(value) => value.to_string(),
-            value => format!("{value}"),
-        };
-        println!("{text}");
+        .unwrap();
+    let output = json!({"table": table, "row": row, "column": column, "value": value});
+    print_output(cli, &output, |_| {
+        if let Some(value) = value {
+            let text = match value {
+                JsonValue::String(value) => value.to_string(),
+                value => format!("{value}"),
+            };
+            println!("{text}");
+        }
+    });
+}
+
+/// Print the last change to the given column of each row of the given table (see
+/// [Relatable::blame()]).
+pub async fn print_blame(cli: &Cli, table: &str, column: &str) {
+    tracing::trace!("print_blame({cli:?}, {table}, {column})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let blame = rltbl
+        .blame(table, column)
+        .await
+        .expect("Error getting blame");
+    print_output(cli, &blame, |blame| {
+        print!("{}", blame_to_tsv(blame).expect("Error writing TSV"))
+    });
+}
+
+/// Print statistics about the values of each of the columns of the given table, as TSV.
+pub async fn print_column_stats(cli: &Cli, table: &str) {
+    tracing::trace!("print_column_stats({cli:?}, {table})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let stats = rltbl
+        .column_stats(table)
+        .await
+        .expect("Error getting statistics");
+    print_output(cli, &stats, |stats| {
+        print!("{}", column_stats_to_tsv(stats).expect("Error writing TSV"))
+    });
+}
+
+/// The options of the `get table` command that determine which of a table's rows are printed,
+/// and how.
+#[derive(Debug)]
+pub struct TableOptions<'a> {
+    pub filters: &'a Vec<String>,
+    pub search: &'a Option<String>,
+    pub format: &'a str,
+    pub limit: &'a Limit,
+    pub offset: &'a usize,
+}
+
+/// Print a table with its column header, fetched from the given remote server, according to the
+/// given options.
+pub fn remote_print_table(cli: &Cli, remote: &Remote, table_name: &str, options: &TableOptions) {
+    tracing::trace!("remote_print_table({cli:?}, {remote:?}, {table_name}, {options:?})");
+    let mut select = match Select::from(table_name).filters(options.filters) {
+        Ok(mut select) => {
+            select.limit = *options.limit;
+            select.offset(options.offset)
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(1);
+        }
+    };
+    if let Some(query) = options.search {
+        select = select.search(query);
     }
+    let result = remote.fetch(&select).expect("Error fetching table");
+    print_result(cli, table_name, result, options.format);
+}
+
+/// Print rows of a table, without column header, fetched from the given remote server.
+pub fn remote_print_rows(
+    cli: &Cli,
+    remote: &Remote,
+    table_name: &str,
+    limit: &Limit,
+    offset: &usize,
+) {
+    tracing::trace!("remote_print_rows({cli:?}, {remote:?}, {table_name}, {limit}, {offset})");
+    let mut select = Select::from(table_name).offset(offset);
+    select.limit = *limit;
+    let rows: Vec<Vec<String>> = remote
+        .fetch(&select)
+        .expect("Error fetching rows")
+        .rows
+        .vec_into();
+    print_output(cli, &rows, print_text);
+}
+
+/// Print the value of the given column of the given row of the given table, fetched from the
+/// given remote server.
+pub fn remote_print_value(cli: &Cli, remote: &Remote, table: &str, row: u64, column: &str) {
+    tracing::trace!("remote_print_value({cli:?}, {remote:?}, {table}, {row}, {column})");
+    let value = remote
+        .get_value(table, row, column)
+        .expect("Error getting value");
+    let output = json!({"table": table, "row": row, "column": column, "value": value});
+    print_output(cli, &output, |_| match &value {
+        JsonValue::Null => (),
+        JsonValue::String(value) => println!("{value}"),
+        value => println!("{value}"),
+    });
 }
 
 /// Print the change history for the user associated with the given context
 pub async fn print_history(cli: &Cli, context: usize) {
     tracing::trace!("print_history({cli:?}, {context})");
-    fn get_content_as_string(change_json: &JsonRow) -> String {
-        let content = change_json.get_string("content").expect("No content found");
-        let content = Change::many_from_str(&content).expect("Could not parse content");
-        content
-            .iter()
-            .map(|c| c.to_string())
-            .collect::<Vec<_>>()
-            .join(", ")
-    }
-
-    let user = get_username(&cli);
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
+    let user = get_username(cli);
     let history = rltbl
         .get_user_history(
             &user,
@@ -559,6 +1390,7 @@ pub async fn print_history(cli: &Cli, context: usize) {
         history.changes_undone_stack.clone(),
     );
 
+    let mut records = vec![];
     let next_redo = match redoable_changes.len() {
         0 => 0,
         _ => redoable_changes[0]
@@ -573,14 +1405,11 @@ pub async fn print_history(cli: &Cli, context: usize) {
         let change_id = change
             .get_unsigned("change_id")
             .expect("No change_id found");
-        let action = change.get_string("action").expect("No action found");
-        if change_id == next_redo {
-            let change_content = get_content_as_string(change);
-            println!("▲ {change_content} (action #{change_id}, {action})");
-        } else {
-            let change_content = get_content_as_string(change);
-            println!("  {change_content} (action #{change_id}, {action})");
-        }
+        records.push(HistoryRecord::from_json_row(
+            change,
+            "undone",
+            change_id == next_redo,
+        ));
     }
     let next_undo = match undoable_changes.len() {
         0 => 0,
@@ -595,20 +1424,26 @@ pub async fn print_history(cli: &Cli, context: usize) {
         let change_id = change
             .get_unsigned("change_id")
             .expect("No change_id found");
-        let action = change.get_string("action").expect("No action found");
-        if change_id == next_undo {
-            let change_content = get_content_as_string(change);
-            let line = format!("▼ {change_content} (action #{change_id}, {action})");
-            println!("{}", Style::new().bold().paint(line));
-        } else {
-            let change_content = get_content_as_string(change);
-            println!("  {change_content} (action #{change_id}, {action})");
-        }
+        records.push(HistoryRecord::from_json_row(
+            change,
+            "done",
+            change_id == next_undo,
+        ));
     }
+
+    print_output(cli, &records, |records| {
+        for record in records {
+            let line = record.to_line();
+            match (record.state.as_str(), record.next) {
+                ("undone", true) => println!("▲ {line}"),
+                ("done", true) => println!("{}", Style::new().bold().paint(format!("▼ {line}"))),
+                _ => println!("  {line}"),
+            }
+        }
+    });
 }
 
-// Get the user from the CLI, RLTBL_USER environment variable,
-// or the general environment.
+// Get the user from the CLI, RLTBL_USER environment variable, or the general environment.
 pub fn get_username(cli: &Cli) -> String {
     tracing::trace!("get_username({cli:?})");
     match &cli.user {
@@ -618,7 +1453,8 @@ pub fn get_username(cli: &Cli) -> String {
 }
 
 /// Set the value of the given column of the given row of the given table. Use the given
-/// validation_level to determine how to validate the value while updating.
+/// validation_level to determine how to validate the value while updating, and record the given
+/// message, if any, on the change.
 pub async fn set_value(
     cli: &Cli,
     table: &str,
@@ -626,8 +1462,11 @@ pub async fn set_value(
     column: &str,
     value: &str,
     validation_level: &ValidationLevel,
+    message: Option<&str>,
 ) {
-    tracing::trace!("set_value({cli:?}, {table}, {row}, {column}, {value}, {validation_level:?})");
+    tracing::trace!(
+        "set_value({cli:?}, {table}, {row}, {column}, {value}, {validation_level:?}, {message:?})"
+    );
     let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
@@ -648,12 +1487,13 @@ pub async fn set_value(
     let after = serde_json::from_str::<JsonValue>(value).unwrap_or(json!(value));
 
     // Apply the change to the new value:
-    let num_changes = rltbl
+    let changeset = rltbl
         .set_values(&ChangeSet {
-            user: get_username(&cli),
+            user: get_username(cli),
             action: ChangeAction::Do,
             table: table.to_string(),
             description: "Set one value".to_string(),
+            message: message.map(|m| m.to_string()),
             changes: vec![Change::Update {
                 row,
                 column: column.to_string(),
@@ -662,11 +1502,76 @@ pub async fn set_value(
             }],
         })
         .await
-        .unwrap()
-        .changes
-        .len();
+        .unwrap();
+    print_output(cli, &changeset, |_| ());
+
+    if changeset.changes.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Fill the given column of the given range of rows of the given table, copying the value of the
+/// first row or, if `series` is set, extending the series that the first two rows begin.
+pub async fn fill_down(
+    cli: &Cli,
+    table: &str,
+    column: &str,
+    from_row: u64,
+    to_row: u64,
+    series: bool,
+) {
+    tracing::trace!("fill_down({cli:?}, {table}, {column}, {from_row}, {to_row}, {series})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let user = get_username(cli);
+    let changeset = rltbl
+        .fill_down(table, &user, column, from_row, to_row, series)
+        .await
+        .expect("Error filling down");
+    print_output(cli, &changeset, |changeset| {
+        for change in &changeset.changes {
+            println!("{change}");
+        }
+    });
+}
+
+/// Set the value of the given column of the given row of the given table on the given remote
+/// server, recording the given message, if any, on the change.
+pub fn remote_set_value(
+    cli: &Cli,
+    remote: &Remote,
+    table: &str,
+    row: u64,
+    column: &str,
+    value: &str,
+    message: Option<&str>,
+) {
+    tracing::trace!(
+        "remote_set_value({cli:?}, {remote:?}, {table}, {row}, {column}, {value}, {message:?})"
+    );
+    let before = remote
+        .get_value(table, row, column)
+        .expect("Error getting value");
+    let after = serde_json::from_str::<JsonValue>(value).unwrap_or(json!(value));
+    let changeset = remote
+        .set_values(&ChangeSet {
+            user: get_username(cli),
+            action: ChangeAction::Do,
+            table: table.to_string(),
+            description: "Set one value".to_string(),
+            message: message.map(|m| m.to_string()),
+            changes: vec![Change::Update {
+                row,
+                column: column.to_string(),
+                before,
+                after,
+            }],
+        })
+        .expect("Error setting value");
+    print_output(cli, &changeset, |_| ());
 
-    if num_changes < 1 {
+    if changeset.changes.is_empty() {
         std::process::exit(1);
     }
 }
@@ -697,6 +1602,175 @@ pub fn prompt_for_column_value(column: &str) -> JsonValue {
     }
 }
 
+/// Build a [Mapping] from the columns of the source file at the given path to those of the given
+/// table. Columns are first matched by name, and then mapped according to the given column,
+/// constant, and transform assignments, each of the form `TARGET=VALUE`. If STDIN is a terminal,
+/// the user is then asked to confirm or change the mapping of each column of the table. The
+/// mapping is saved, as YAML, to the given output path, or printed to STDOUT if none is given.
+pub async fn map_source(
+    cli: &Cli,
+    path: &str,
+    table: &str,
+    columns: &[String],
+    constants: &[String],
+    transforms: &[String],
+    output: Option<&str>,
+) {
+    tracing::trace!(
+        "map_source({cli:?}, {path}, {table}, {columns:?}, {constants:?}, {transforms:?}, \
+         {output:?})"
+    );
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let targets = rltbl
+        .fetch_columns(table)
+        .await
+        .expect("Error getting columns")
+        .iter()
+        .map(|c| c.name.to_string())
+        .collect::<Vec<_>>();
+    let (headers, _) = Mapping::read_source(path).expect("Error reading source file");
+
+    let parse_assignment = |assignment: &String| match assignment.split_once('=') {
+        Some((target, value)) if targets.contains(&target.to_string()) => {
+            (target.to_string(), value.to_string())
+        }
+        Some((target, _)) => panic!("No column '{target}' in table '{table}'"),
+        None => panic!("Invalid assignment '{assignment}': expected TARGET=VALUE"),
+    };
+    let mut mapped = Mapping::infer(table, &targets, &headers)
+        .columns
+        .into_iter()
+        .map(|column| (column.target.to_string(), column))
+        .collect::<IndexMap<_, _>>();
+    for (target, source) in columns.iter().map(parse_assignment) {
+        if !headers.contains(&source) {
+            panic!("No column '{source}' in '{path}'");
+        }
+        mapped.insert(
+            target.to_string(),
+            MappedColumn {
+                target,
+                source: Some(source),
+                ..Default::default()
+            },
+        );
+    }
+    for (target, constant) in constants.iter().map(parse_assignment) {
+        mapped.insert(
+            target.to_string(),
+            MappedColumn {
+                target,
+                constant: Some(constant),
+                ..Default::default()
+            },
+        );
+    }
+    for (target, value) in transforms.iter().map(parse_assignment) {
+        match mapped.get_mut(&target) {
+            Some(column) if column.source.is_some() => {
+                column.transforms = Transform::split_list(&value);
+            }
+            _ => panic!("Column '{target}' must be mapped to a source column to be transformed"),
+        }
+    }
+
+    if io::stdin().is_terminal() {
+        for target in &targets {
+            let column = prompt_for_mapped_column(target, mapped.get(target), &headers);
+            match column {
+                Some(column) => mapped.insert(target.to_string(), column),
+                None => mapped.shift_remove(target),
+            };
+        }
+    }
+
+    // Keep the mapped columns in the same order as the columns of the table:
+    let mapping = Mapping {
+        table: table.to_string(),
+        columns: targets
+            .iter()
+            .filter_map(|target| mapped.get(target).cloned())
+            .collect(),
+    };
+    let yaml = mapping.to_yaml().expect("Invalid mapping");
+    if let Some(output) = output {
+        std::fs::write(output, &yaml).unwrap_or_else(|_| panic!("Error writing to '{output}'"));
+        tracing::info!("Saved mapping for '{table}' to '{output}'");
+    }
+    print_output(cli, &mapping, |_| {
+        if output.is_none() {
+            print!("{yaml}");
+        }
+    });
+}
+
+/// Prompt the user for the mapping of the given target column, whose current mapping, if any, is
+/// offered as the default, to one of the given source headers or to a constant. Returns None if
+/// the column is to be left unmapped.
+pub fn prompt_for_mapped_column(
+    target: &str,
+    current: Option<&MappedColumn>,
+    headers: &[String],
+) -> Option<MappedColumn> {
+    tracing::trace!("prompt_for_mapped_column({target}, {current:?}, {headers:?})");
+    let default = match current {
+        Some(MappedColumn {
+            source: Some(source),
+            ..
+        }) => source.to_string(),
+        Some(MappedColumn {
+            constant: Some(constant),
+            ..
+        }) => format!("={constant}"),
+        _ => "-".to_string(),
+    };
+    let mut column = loop {
+        let answer: Option<String> = prompt_opt(format!(
+            r#"Source column for '{target}' ("=VALUE" for a constant, "-" for none) [{default}]"#
+        ))
+        .expect("Error getting mapping from user input");
+        let answer = answer.unwrap_or(default.to_string());
+        if answer == default {
+            if let Some(current) = current {
+                break current.clone();
+            }
+        }
+        match answer.strip_prefix("=") {
+            _ if answer == "-" => return None,
+            Some(constant) => {
+                break MappedColumn {
+                    target: target.to_string(),
+                    constant: Some(constant.to_string()),
+                    ..Default::default()
+                }
+            }
+            None if headers.contains(&answer) => {
+                break MappedColumn {
+                    target: target.to_string(),
+                    source: Some(answer),
+                    ..Default::default()
+                }
+            }
+            None => println!("No such source column: '{answer}'"),
+        }
+    };
+
+    if column.source.is_some() {
+        let answer: Option<String> = prompt_opt(format!(
+            "Transforms for '{target}' (comma-separated, any of: {}) [{}]",
+            MAPPING_TRANSFORMS.join(", "),
+            column.transforms.join(",")
+        ))
+        .expect("Error getting transforms from user input");
+        if let Some(answer) = answer {
+            column.transforms = Transform::split_list(&answer);
+        }
+    }
+    Some(column)
+}
+
 /// Prompt the user for a message associated with the given column, row, and table.
 pub async fn prompt_for_json_message(
     rltbl: &Relatable,
@@ -792,12 +1866,13 @@ pub async fn add_message(cli: &Cli, table: &str, row: u64, column: &str) {
         .and_then(|m| m.as_str())
         .expect("The field 'message' (type: string) is required.");
 
-    let user = get_username(&cli);
+    let user = get_username(cli);
     let (mid, message) = rltbl
         .add_message(&user, table, row, column, &value, &level, &rule, &message)
         .await
         .expect("Error adding row");
     tracing::info!("Added message (ID: {mid}) {message:?}");
+    print_output(cli, &json!({"message_id": mid, "message": message}), |_| ());
 }
 
 /// Add a row to the given table after the row with _id `after_id`. Use the given validation_level
@@ -806,9 +1881,10 @@ pub async fn add_row(
     cli: &Cli,
     table: &str,
     after_id: Option<u64>,
+    template: Option<&str>,
     validation_level: &ValidationLevel,
 ) {
-    tracing::trace!("add_row({cli:?}, {table}, {after_id:?}, {validation_level:?})");
+    tracing::trace!("add_row({cli:?}, {table}, {after_id:?}, {template:?}, {validation_level:?})");
     let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
@@ -821,17 +1897,57 @@ pub async fn add_row(
             .await
             .expect("Error getting user input"),
     };
+    let json_row = match template {
+        Some(template) => rltbl
+            .apply_template(table, template, &json_row)
+            .await
+            .expect("Error applying template"),
+        None => json_row,
+    };
 
     if json_row.content.is_empty() {
         panic!("Cannot insert an empty row to the database");
     }
 
-    let user = get_username(&cli);
+    let user = get_username(cli);
     let row = rltbl
         .add_row(table, &user, after_id, &json_row)
         .await
         .expect("Error adding row");
     tracing::info!("Added row {}", row.order);
+    print_output(cli, &row, |_| ());
+}
+
+/// Read the default values of a row template, either interactively or in JSON format, from
+/// STDIN and save them as the template with the given name for the given table.
+pub async fn add_template(cli: &Cli, table: &str, template: &str) {
+    tracing::trace!("add_template({cli:?}, {table}, {template})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+
+    let mut json_row = match &cli.input {
+        Some(s) if s == "JSON" => input_json_row(),
+        Some(s) => panic!("Unsupported input type '{s}'"),
+        None => prompt_for_json_row(&rltbl, table)
+            .await
+            .expect("Error getting user input"),
+    };
+    // Columns without a value are left out of the template:
+    json_row
+        .content
+        .retain(|_, value| !value.is_null() && *value != json!(""));
+
+    rltbl
+        .set_template(table, template, &json_row)
+        .await
+        .expect("Error saving template");
+    tracing::info!("Saved template '{template}' for table '{table}'");
+    print_output(
+        cli,
+        &json!({"table": table, "template": template, "values": json_row.content}),
+        |_| (),
+    );
 }
 
 /// Move the given row after the row whose id is `after_id`.
@@ -840,11 +1956,13 @@ pub async fn move_row(cli: &Cli, table: &str, row: u64, after_id: u64) {
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let user = get_username(&cli);
+    let user = get_username(cli);
     let new_order = rltbl
         .move_row(table, &user, row, after_id)
         .await
         .expect("Failed to move row");
+    let output = json!({"table": table, "row": row, "after": after_id, "order": new_order});
+    print_output(cli, &output, |_| ());
     if new_order > 0 {
         tracing::info!("Moved row {row} after row {after_id}");
     } else {
@@ -868,6 +1986,17 @@ pub async fn validate_row(cli: &Cli, table_name: &str, row: &u64) {
         .await
         .expect("Error while validating row");
     tracing::info!("Validated row {row} of table '{table_name}'");
+    let summary = ValidationSummary::collect(&rltbl, table_name, Some(*row), None).await;
+    print_output(cli, &summary, |_| ());
+}
+
+/// Validate the given table on the given remote server
+pub fn remote_validate_table(cli: &Cli, remote: &Remote, table_name: &str) {
+    tracing::trace!("remote_validate_table({cli:?}, {remote:?}, {table_name})");
+    let summary = remote
+        .validate_table(table_name)
+        .expect("Error while validating table");
+    print_output(cli, &summary, |_| ());
 }
 
 /// Validate the given table
@@ -886,9 +2015,51 @@ pub async fn validate_table(cli: &Cli, table_name: &str) {
         .await
         .expect("Error while validating table");
     tracing::info!("Validated table '{table_name}'");
+    let summary = ValidationSummary::collect(&rltbl, table_name, None, None).await;
+    print_output(cli, &summary, |_| ());
 }
 
 /// Validate the given column
+/// Revalidate the columns whose validation depends on the given datatype, and output a summary
+/// of the messages for each of them.
+pub async fn validate_datatype(cli: &Cli, datatype: &str) {
+    tracing::trace!("validate_datatype({cli:?}, {datatype})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+
+    let columns = rltbl
+        .revalidate_for_datatype(datatype)
+        .await
+        .expect("Error while revalidating datatype");
+    let mut summaries = vec![];
+    for column in &columns {
+        summaries.push(
+            ValidationSummary::collect(&rltbl, &column.table, None, Some(&column.name)).await,
+        );
+    }
+    print_output(cli, &summaries, |_| ());
+}
+
+/// Revalidate the rules defined for the given table and output a summary of its messages.
+pub async fn validate_rules(cli: &Cli, table_name: &str) {
+    tracing::trace!("validate_rules({cli:?}, {table_name})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+
+    let table = Table::get_table(table_name, &rltbl)
+        .await
+        .expect("Error getting table");
+
+    rltbl
+        .revalidate_rules(&table)
+        .await
+        .expect("Error while validating rules");
+    let summary = ValidationSummary::collect(&rltbl, table_name, None, None).await;
+    print_output(cli, &summary, |_| ());
+}
+
 pub async fn validate_column(cli: &Cli, table_name: &str, column_name: &str) {
     tracing::trace!("validate_column({cli:?}, {table_name}, {column_name})");
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
@@ -907,6 +2078,8 @@ pub async fn validate_column(cli: &Cli, table_name: &str, column_name: &str) {
         .await
         .expect("Error while validating column");
     tracing::info!("Validated column '{column_name}' of table '{table_name}'");
+    let summary = ValidationSummary::collect(&rltbl, table_name, None, Some(column_name)).await;
+    print_output(cli, &summary, |_| ());
 }
 
 /// Validate the value of the given column, row, and table
@@ -928,6 +2101,9 @@ pub async fn validate_value(cli: &Cli, table_name: &str, row: &u64, column_name:
         .await
         .expect("Error while validating value");
     tracing::info!("Validated value of column '{column_name}' of table '{table_name}'");
+    let summary =
+        ValidationSummary::collect(&rltbl, table_name, Some(*row), Some(column_name)).await;
+    print_output(cli, &summary, |_| ());
 }
 
 /// Delete the given row in the given table
@@ -936,11 +2112,13 @@ pub async fn delete_row(cli: &Cli, table: &str, row: u64) {
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let user = get_username(&cli);
+    let user = get_username(cli);
     let num_deleted = rltbl
         .delete_row(table, &user, row)
         .await
         .expect("Failed to delete row");
+    let output = json!({"table": table, "row": row, "deleted": num_deleted});
+    print_output(cli, &output, |_| ());
     if num_deleted > 0 {
         tracing::info!("Deleted row {row}");
     } else {
@@ -948,41 +2126,224 @@ pub async fn delete_row(cli: &Cli, table: &str, row: u64) {
     }
 }
 
-/// Delete messages from the message table for the given table, optionally filtering by rule,
-/// user, row, and column
-pub async fn delete_message(
-    cli: &Cli,
-    target_rule: Option<&str>,
-    target_user: Option<&str>,
-    table: &str,
-    row: Option<u64>,
-    column: Option<&str>,
-) {
-    tracing::trace!(
-        "delete_message({cli:?}, {target_rule:?}, {target_user:?}, {table}, {row:?}, {column:?})"
-    );
+/// Archive the rows of the given table that match all of the given filters.
+pub async fn archive_rows(cli: &Cli, table: &str, filters: &Vec<String>) {
+    tracing::trace!("archive_rows({cli:?}, {table}, {filters:?})");
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let num_deleted = rltbl
-        .delete_message(table, row, column, target_rule, target_user)
+    let num_archived = rltbl
+        .archive_rows(table, filters)
         .await
-        .expect("Failed to delete message");
-    if num_deleted > 0 {
-        tracing::info!("Deleted {num_deleted} message(s)");
-    } else {
-        std::process::exit(1);
-    }
+        .expect("Failed to archive rows");
+    print_output(
+        cli,
+        &json!({"table": table, "archived": num_archived}),
+        |_| println!("Archived {num_archived} rows of '{table}'"),
+    );
+}
+
+/// Run the subcommand given by the first of the given arguments, using the plugin with that name,
+/// if there is one, or else the executable `rltbl-NAME` on the PATH, which is passed the rest of
+/// the arguments, and the global options in its environment (see [external_command()]).
+pub async fn run_external(cli: &Cli, plugins: &Vec<Box<dyn Plugin>>, args: &Vec<String>) {
+    tracing::trace!("run_external({cli:?}, {} plugins, {args:?})", plugins.len());
+    let name = &args[0];
+    match plugins
+        .iter()
+        .find(|plugin| plugin.command().get_name() == name)
+    {
+        Some(plugin) => {
+            let matches = plugin.command().get_matches_from(args);
+            let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+                .await
+                .unwrap();
+            if let Err(err) = plugin.run(cli, &rltbl, &matches).await {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        None => match external_command(cli, args).status() {
+            Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => unknown_subcommand(name).exit(),
+            Err(err) => panic!("Failed to run 'rltbl-{name}': {err}"),
+        },
+    }
+}
+
+/// Build the command that runs the executable `rltbl-NAME`, where NAME is the first of the given
+/// arguments, with the rest of the arguments, and the global options in its environment as
+/// `RLTBL_CONNECTION`, `RLTBL_USER`, `RLTBL_INPUT`, and `RLTBL_OUTPUT`.
+pub fn external_command(cli: &Cli, args: &[String]) -> std::process::Command {
+    tracing::trace!("external_command({cli:?}, {args:?})");
+    let mut command = std::process::Command::new(format!("rltbl-{}", args[0]));
+    command.args(&args[1..]);
+    for (var, value) in [
+        ("RLTBL_CONNECTION", cli.database.clone()),
+        ("RLTBL_USER", cli.user.clone()),
+        ("RLTBL_INPUT", cli.input.clone()),
+    ] {
+        if let Some(value) = value {
+            command.env(var, value);
+        }
+    }
+    command.env(
+        "RLTBL_OUTPUT",
+        match cli.output {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+        },
+    );
+    command
+}
+
+/// The error given for a subcommand that is neither built in, nor provided by a plugin or an
+/// executable named `rltbl-NAME`
+fn unknown_subcommand(name: &str) -> clap::Error {
+    tracing::trace!("unknown_subcommand({name:?})");
+    Cli::command().error(
+        ErrorKind::InvalidSubcommand,
+        format!("unrecognized subcommand '{name}'"),
+    )
+}
+
+/// Build the command-line interface, with the subcommands of the given plugins added to the
+/// built-in ones, so that they are listed in the help and offered as completions.
+pub fn build_command(plugins: &[Box<dyn Plugin>]) -> clap::Command {
+    tracing::trace!("build_command({} plugins)", plugins.len());
+    plugins.iter().fold(Cli::command(), |command, plugin| {
+        command.subcommand(plugin.command())
+    })
+}
+
+/// Read a batch of edits from STDIN and apply them within a single transaction, as a single
+/// change group, using the given validation_level and recording the given message, if any. If
+/// `preview` is set, apply them to a sandbox instead and print the resulting message delta.
+pub async fn batch(
+    cli: &Cli,
+    validation_level: &ValidationLevel,
+    message: Option<&str>,
+    preview: bool,
+) {
+    tracing::trace!("batch({cli:?}, {validation_level:?}, {message:?}, {preview})");
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl.validation_level = *validation_level;
+
+    let mut content = String::new();
+    io::stdin()
+        .read_to_string(&mut content)
+        .expect("Error reading from STDIN");
+    let operations = BatchOperation::many_from_str(&content).expect("Error parsing batch");
+
+    let user = get_username(cli);
+    if preview {
+        let mut sandbox = rltbl.sandbox().await.expect("Error creating sandbox");
+        sandbox
+            .batch(&user, message, &operations)
+            .await
+            .expect("Error applying batch");
+        let delta = sandbox
+            .message_delta()
+            .await
+            .expect("Error comparing messages");
+        sandbox.discard();
+        let output = json!({
+            "operations": operations.len(),
+            "added": delta.added,
+            "removed": delta.removed,
+        });
+        print_output(cli, &output, |_| {
+            let print_message = |sign: &str, message: &JsonMap<String, JsonValue>| {
+                let field = |name: &str| match message.get(name) {
+                    Some(JsonValue::String(value)) => value.to_string(),
+                    Some(JsonValue::Null) | None => String::new(),
+                    Some(value) => value.to_string(),
+                };
+                println!(
+                    "{sign} {} {}:{}:{} ({}): {}",
+                    field("level").to_uppercase(),
+                    field("table"),
+                    field("row"),
+                    field("column"),
+                    field("rule"),
+                    field("message")
+                );
+            };
+            for message in &delta.added {
+                print_message("+", message);
+            }
+            for message in &delta.removed {
+                print_message("-", message);
+            }
+        });
+        return;
+    }
+    let change_group = rltbl
+        .batch(&user, message, &operations)
+        .await
+        .expect("Error applying batch");
+    let output = json!({"operations": operations.len(), "change_group": change_group});
+    print_output(cli, &output, |_| ());
+    tracing::info!("Applied {} operation(s) from the batch", operations.len());
+}
+
+/// Delete messages from the message table for the given table, optionally filtering by rule,
+/// user, row, and column
+pub async fn delete_message(
+    cli: &Cli,
+    target_rule: Option<&str>,
+    target_user: Option<&str>,
+    table: &str,
+    row: Option<u64>,
+    column: Option<&str>,
+) {
+    tracing::trace!(
+        "delete_message({cli:?}, {target_rule:?}, {target_user:?}, {table}, {row:?}, {column:?})"
+    );
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let num_deleted = rltbl
+        .delete_message(table, row, column, target_rule, target_user)
+        .await
+        .expect("Failed to delete message");
+    let output = json!({"table": table, "row": row, "column": column, "deleted": num_deleted});
+    print_output(cli, &output, |_| ());
+    if num_deleted > 0 {
+        tracing::info!("Deleted {num_deleted} message(s)");
+    } else {
+        std::process::exit(1);
+    }
 }
 
-/// Undo the last change
-pub async fn undo(cli: &Cli) {
-    tracing::trace!("undo({cli:?})");
+/// Undo the last change, or, if `preview` is set, print the changes that undoing it would make.
+pub async fn undo(cli: &Cli, preview: bool) {
+    tracing::trace!("undo({cli:?}, {preview})");
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let user = get_username(&cli);
+    let user = get_username(cli);
+    if preview {
+        let changesets = rltbl
+            .preview_undo(&user)
+            .await
+            .expect("Failed to preview undo");
+        print_output(cli, &changesets, |changesets| {
+            for changeset in changesets {
+                for change in &changeset.changes {
+                    println!("{}: {change}", changeset.table);
+                }
+            }
+        });
+        if changesets.is_empty() {
+            std::process::exit(1);
+        }
+        return;
+    }
     let changeset = rltbl.undo(&user).await.expect("Failed to undo");
+    print_output(cli, &changeset, |_| ());
     if let None = changeset {
         std::process::exit(1);
     }
@@ -995,37 +2356,322 @@ pub async fn redo(cli: &Cli) {
     let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
-    let user = get_username(&cli);
+    let user = get_username(cli);
     let changeset = rltbl.redo(&user).await.expect("Failed to redo");
+    print_output(cli, &changeset, |_| ());
     if let None = changeset {
         std::process::exit(1);
     }
     tracing::info!("Last operation redone");
 }
 
-/// Load the tables at the given paths. Use validation_level to determine how to validate rows
-/// as they are being loaded.
+/// Parse the delimiter given by the `--delimiter` option: one of `tab`, `comma`, `semicolon`, or
+/// any single ASCII character.
+fn parse_delimiter(delimiter: &str) -> Result<u8> {
+    match delimiter {
+        "tab" | "\\t" => Ok(b'\t'),
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        _ if delimiter.len() == 1 && delimiter.is_ascii() => Ok(delimiter.as_bytes()[0]),
+        _ => Err(RelatableError::InputError(format!("Invalid delimiter: '{delimiter}'")).into()),
+    }
+}
+
+/// Get the column renames given by the `--rename` option, as a list of `OLD=NEW` pairs, and by
+/// the 'old' and 'new' columns of the TSV file given by the `--rename-file` option, if any.
+/// Note that this function may panic.
+pub fn get_column_renames(
+    renames: &Vec<String>,
+    rename_file: Option<&str>,
+) -> IndexMap<String, String> {
+    tracing::trace!("get_column_renames({renames:?}, {rename_file:?})");
+    let mut column_renames = IndexMap::new();
+    if let Some(path) = rename_file {
+        let (headers, records) = Mapping::read_source(path).expect("Error reading rename file");
+        let (old, new) = match (
+            headers.iter().position(|header| header == "old"),
+            headers.iter().position(|header| header == "new"),
+        ) {
+            (Some(old), Some(new)) => (old, new),
+            _ => panic!("The rename file '{path}' must have 'old' and 'new' columns"),
+        };
+        for record in &records {
+            column_renames.insert(record[old].to_string(), record[new].to_string());
+        }
+    }
+    for rename in renames {
+        match rename.split_once("=") {
+            Some((old, new)) if !old.trim().is_empty() && !new.trim().is_empty() => {
+                column_renames.insert(old.trim().to_string(), new.trim().to_string());
+            }
+            _ => panic!("Invalid rename '{rename}': expected OLD=NEW"),
+        }
+    }
+    column_renames
+}
+
+/// The options of the `load table` command that determine how the tables are loaded: how rows
+/// are validated as they are being loaded, whether each table is given a materialized
+/// _change_id column and a _uuid column, how the columns of the files are renamed, and the
+/// column, if any, by which the tables are partitioned.
+#[derive(Debug)]
+pub struct LoadOptions<'a> {
+    pub validation_level: &'a ValidationLevel,
+    pub materialize_change_id: bool,
+    pub uuid_row_ids: bool,
+    pub column_renames: &'a IndexMap<String, String>,
+    pub partition_by: Option<&'a str>,
+}
+
+/// Load the tables at the given paths according to the given options. The given message, if any,
+/// describes the load.
 pub async fn load_tables(
+    cli: &Cli,
+    paths: &Vec<String>,
+    force: bool,
+    options: &LoadOptions<'_>,
+    message: Option<&str>,
+) {
+    tracing::trace!("load_tables({cli:?}, {paths:?}, {force}, {options:?}, {message:?})");
+
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl.validation_level = *options.validation_level;
+    rltbl.locale = cli.locale;
+    rltbl.delimiter = cli.delimiter;
+    rltbl.encoding = cli.encoding;
+    rltbl.materialize_change_id = options.materialize_change_id;
+    rltbl.uuid_row_ids = options.uuid_row_ids;
+    rltbl.column_renames = options.column_renames.clone();
+    rltbl.partition_by = options.partition_by.map(|column| column.to_string());
+
+    let mut tables = vec![];
+    for path in paths {
+        tables.push(load_table(cli, path, force, &rltbl, message).await);
+    }
+    print_output(cli, &json!({ "tables": tables }), |_| ());
+}
+
+/// Resume the interrupted loads of the tables at the given paths. Use validation_level to
+/// determine how to validate the rows that remain to be loaded. The given message, if any,
+/// describes the load. The columns of the files are renamed according to the given column
+/// renames, as they were when the loads began, and once they are complete, the tables are
+/// partitioned by the values of the partition_by column, if one is given.
+pub async fn resume_loads(
+    cli: &Cli,
+    paths: &Vec<String>,
+    validation_level: &ValidationLevel,
+    column_renames: &IndexMap<String, String>,
+    partition_by: Option<&str>,
+    message: Option<&str>,
+) {
+    tracing::trace!(
+        "resume_loads({cli:?}, {paths:?}, {validation_level:?}, {column_renames:?}, \
+         {partition_by:?}, {message:?})"
+    );
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl.validation_level = *validation_level;
+    rltbl.locale = cli.locale;
+    rltbl.delimiter = cli.delimiter;
+    rltbl.encoding = cli.encoding;
+    rltbl.column_renames = column_renames.clone();
+    rltbl.partition_by = partition_by.map(|column| column.to_string());
+
+    let mut tables = vec![];
+    for path in paths {
+        let table = table_name_from_path(path);
+        rltbl.resume_load_table(&table, path, message).await;
+        tracing::info!("Loaded table '{table}'");
+        tables.push(table);
+    }
+    print_output(cli, &json!({ "tables": tables }), |_| ());
+}
+
+/// Use the mapping in the YAML file at the given path to add the rows of the files at the given
+/// paths to the end of the mapping's table. Use validation_level to determine how to validate the
+/// rows as they are being added. The columns of the files are renamed according to the given
+/// column renames before the mapping is applied. The given message, if any, describes the load.
+pub async fn load_mapped(
+    cli: &Cli,
+    paths: &Vec<String>,
+    mapping: &str,
+    validation_level: &ValidationLevel,
+    column_renames: &IndexMap<String, String>,
+    message: Option<&str>,
+) {
+    tracing::trace!(
+        "load_mapped({cli:?}, {paths:?}, {mapping}, {validation_level:?}, {column_renames:?}, \
+         {message:?})"
+    );
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl.validation_level = *validation_level;
+    rltbl.column_renames = column_renames.clone();
+    let mapping = Mapping::from_path(mapping).expect("Error reading mapping");
+
+    let user = get_username(cli);
+    let mut rows = 0;
+    for path in paths {
+        let count = rltbl
+            .load_mapped(&mapping, path, &user, message)
+            .await
+            .unwrap_or_else(|_| panic!("Error loading '{path}'"));
+        tracing::info!("Added {count} rows from '{path}' to '{}'", mapping.table);
+        rows += count;
+    }
+    print_output(
+        cli,
+        &json!({ "tables": [mapping.table], "rows": rows }),
+        |_| (),
+    );
+}
+
+/// Load the tables at the given paths, or, if no paths are given, reload all of the tables that
+/// have paths in the table table, using the given number of jobs, or, if not given, one job per
+/// CPU. Each table is loaded only after the tables that it depends on. Exit with an error status
+/// if any table could not be loaded.
+pub async fn load_all(
     cli: &Cli,
     paths: &Vec<String>,
     force: bool,
     validation_level: &ValidationLevel,
+    jobs: Option<usize>,
+    message: Option<&str>,
 ) {
-    tracing::trace!("load_tables({cli:?}, {paths:?}, {force}, {validation_level:?})");
+    tracing::trace!(
+        "load_all({cli:?}, {paths:?}, {force}, {validation_level:?}, {jobs:?}, {message:?})"
+    );
 
     let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
     rltbl.validation_level = *validation_level;
+    rltbl.locale = cli.locale;
+    rltbl.delimiter = cli.delimiter;
+    rltbl.encoding = cli.encoding;
 
+    let tables = match paths.is_empty() {
+        false => paths
+            .iter()
+            .map(|path| (table_name_from_path(path), path.to_string()))
+            .collect::<Vec<_>>(),
+        true => {
+            let sql = format!(
+                r#"SELECT "table", "path" FROM "table" WHERE "path" {is_not} NULL ORDER BY "_order""#,
+                is_not = sql::is_not_clause(&rltbl.connection.kind())
+            );
+            rltbl
+                .connection
+                .query(&sql, None)
+                .await
+                .expect("Error reading the table table")
+                .iter()
+                .map(|row| {
+                    (
+                        row.get_string("table").expect("No 'table' found"),
+                        row.get_string("path").expect("No 'path' found"),
+                    )
+                })
+                .collect()
+        }
+    };
+    let jobs = jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism()
+            .map(|jobs| jobs.get())
+            .unwrap_or(1)
+    });
+
+    let start = std::time::Instant::now();
+    let loads = rltbl
+        .load_tables(&tables, force, jobs, message)
+        .await
+        .unwrap_or_else(|err| panic!("Error loading tables: {err}"));
+    let count = |status: LoadStatus| loads.iter().filter(|load| load.status == status).count();
+    let (loaded, failed, skipped) = (
+        count(LoadStatus::Loaded),
+        count(LoadStatus::Failed),
+        count(LoadStatus::Skipped),
+    );
+    tracing::info!(
+        "Loaded {loaded} of {} table(s) in {:.2}s",
+        loads.len(),
+        start.elapsed().as_secs_f64()
+    );
+    print_output(
+        cli,
+        &json!({
+            "tables": loads,
+            "loaded": loaded,
+            "failed": failed,
+            "skipped": skipped,
+            "seconds": start.elapsed().as_secs_f64(),
+        }),
+        |_| (),
+    );
+    if failed + skipped > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Upload the given TSV files to the given remote server, loading each into the table named after
+/// the file, and replacing any existing table if `force` is set. Each load is committed to git by
+/// the server using the given message, if any. Exit with an error status if any table could not
+/// be loaded.
+pub fn remote_load_tables(
+    cli: &Cli,
+    remote: &Remote,
+    paths: &Vec<String>,
+    force: bool,
+    message: Option<&str>,
+) {
+    tracing::trace!("remote_load_tables({cli:?}, {remote:?}, {paths:?}, {force}, {message:?})");
+    let start = std::time::Instant::now();
+    let mut loads = vec![];
     for path in paths {
-        load_table(cli, &path, force, &rltbl).await;
+        let table = table_name_from_path(path);
+        let load = std::fs::read_to_string(path)
+            .map_err(|err| err.into())
+            .and_then(|content| remote.load_table(&table, &content, force, message));
+        loads.push(match load {
+            Ok(load) => load,
+            Err(err) => TableLoad {
+                table,
+                path: path.to_string(),
+                status: LoadStatus::Failed,
+                error: Some(err.to_string()),
+                ..Default::default()
+            },
+        });
+    }
+    let loaded = loads
+        .iter()
+        .filter(|load| load.status == LoadStatus::Loaded)
+        .count();
+    let failed = loads.len() - loaded;
+    print_output(
+        cli,
+        &json!({
+            "tables": loads,
+            "loaded": loaded,
+            "failed": failed,
+            "skipped": 0,
+            "seconds": start.elapsed().as_secs_f64(),
+        }),
+        |_| (),
+    );
+    if failed > 0 {
+        std::process::exit(1);
     }
 }
 
-/// Load the table at the given path
-pub async fn load_table(cli: &Cli, path: &str, force: bool, rltbl: &Relatable) {
-    tracing::trace!("load_table({cli:?}, {path}, {force}, {rltbl:?})");
+/// Get the name of the table to load from the given path, which is the file stem of the path with
+/// any runs of characters other than letters, digits, and underscores replaced by underscores, and
+/// any leading or trailing underscores removed.
+fn table_name_from_path(path: &str) -> String {
     // We will use this pattern to normalize the table name:
     let pattern = Regex::new(r#"[^0-9a-zA-Z_]+"#).expect("Invalid regex pattern");
     let table = Path::new(path)
@@ -1036,18 +2682,473 @@ pub async fn load_table(cli: &Cli, path: &str, force: bool, rltbl: &Relatable) {
     // Now replace any trailing or leading underscores:
     let table = table.trim_end_matches("_");
     let table = table.trim_start_matches("_");
+    table.to_string()
+}
 
-    rltbl.load_table(&table, path, force).await;
+/// Load the table at the given path and return the name of the table
+pub async fn load_table(
+    cli: &Cli,
+    path: &str,
+    force: bool,
+    rltbl: &Relatable,
+    message: Option<&str>,
+) -> String {
+    tracing::trace!("load_table({cli:?}, {path}, {force}, {rltbl:?}, {message:?})");
+    let table = table_name_from_path(path);
+    rltbl.load_table(&table, path, force, message).await;
     tracing::info!("Loaded table '{table}'");
+    table
+}
+
+/// Load the records in the fixture at the given path into the given meta table
+pub async fn load_meta_table(cli: &Cli, table: &str, path: &str) {
+    tracing::trace!("load_meta_table({cli:?}, {table}, {path})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let num_loaded = rltbl
+        .load_meta_table(table, path)
+        .await
+        .unwrap_or_else(|err| panic!("Error loading '{path}' into the {table} table: {err}"));
+    tracing::info!("Loaded {num_loaded} record(s) into the {table} table");
+    print_output(cli, &json!({"table": table, "loaded": num_loaded}), |_| ());
 }
 
 /// Save all of the tables to their configured locations, or to save_dir if it is given.
 pub async fn save_all(cli: &Cli, save_dir: Option<&str>) {
     tracing::trace!("save_all({cli:?})");
-    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
         .await
         .unwrap();
+    rltbl.locale = cli.locale;
     rltbl.save_all(save_dir).await.expect("Error saving all");
+    print_output(cli, &json!({ "saved": true }), |_| ());
+}
+
+/// Merge the operation log of the database with that of the given peer database, in both
+/// directions.
+pub async fn sync(cli: &Cli, peer: &str) {
+    tracing::trace!("sync({cli:?}, {peer})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let peer_rltbl = Relatable::connect(Some(peer), &cli.caching)
+        .await
+        .unwrap_or_else(|err| panic!("Error connecting to peer '{peer}': {err}"));
+    let (pulled, pushed) = rltbl.sync(&peer_rltbl).await.expect("Error syncing");
+    print_output(cli, &json!({"pulled": pulled, "pushed": pushed}), |_| {
+        println!("Pulled {pulled} and pushed {pushed} operation(s)")
+    });
+}
+
+/// Mirror the given tables of the rltbl server at the given URL under the given name.
+pub async fn add_mirror(cli: &Cli, name: &str, url: &str, tables: &[String]) {
+    tracing::trace!("add_mirror({cli:?}, {name}, {url}, {tables:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl
+        .add_mirror(name, url, tables)
+        .await
+        .expect("Error adding mirror");
+    print_output(
+        cli,
+        &json!({"mirror": name, "url": url, "tables": tables}),
+        |_| println!("Mirroring {} from '{name}' ({url})", tables.join(", ")),
+    );
+}
+
+/// Pull the latest changes to the tables mirrored from the given mirror, or from all of them if
+/// none is given.
+pub async fn pull_mirrors(cli: &Cli, name: Option<&str>) {
+    tracing::trace!("pull_mirrors({cli:?}, {name:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let pulled = rltbl
+        .pull_mirrors(name)
+        .await
+        .expect("Error pulling from mirror");
+    print_output(cli, &json!({ "pulled": pulled }), |_| {
+        for (table, rows) in &pulled {
+            println!("Pulled {rows} changed row(s) of '{table}'");
+        }
+    });
+}
+
+/// Add a full-text search index on the given columns of the given table, or on all of its
+/// columns if none are given.
+pub async fn add_search(cli: &Cli, table: &str, columns: &[String]) {
+    tracing::trace!("add_search({cli:?}, {table}, {columns:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let columns = rltbl
+        .add_search(table, columns)
+        .await
+        .expect("Error adding search index");
+    print_output(cli, &json!({"table": table, "columns": columns}), |_| {
+        println!("Indexed {} of '{table}' for search", columns.join(", "))
+    });
+}
+
+/// Remove the full-text search index from the given table.
+pub async fn remove_search(cli: &Cli, table: &str) {
+    tracing::trace!("remove_search({cli:?}, {table})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    rltbl
+        .remove_search(table)
+        .await
+        .expect("Error removing search index");
+    print_output(cli, &json!({ "table": table }), |_| {
+        println!("Removed the search index of '{table}'")
+    });
+}
+
+/// Rebuild the full-text search index of the given table, or of all of the indexed tables if none
+/// is given.
+pub async fn rebuild_search(cli: &Cli, table: Option<&str>) {
+    tracing::trace!("rebuild_search({cli:?}, {table:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let tables = rltbl
+        .rebuild_search(table)
+        .await
+        .expect("Error rebuilding search index");
+    print_output(cli, &json!({ "tables": tables }), |_| {
+        for table in &tables {
+            println!("Rebuilt the search index of '{table}'");
+        }
+    });
+}
+
+/// Check the database's indexes, creating any that are missing
+pub async fn maintain(cli: &Cli) {
+    tracing::trace!("maintain({cli:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .unwrap();
+    let created = rltbl.maintain().await.expect("Error maintaining database");
+    print_output(cli, &json!({ "created": created }), |_| {
+        match created.is_empty() {
+            true => println!("All indexes are present"),
+            false => {
+                for index in &created {
+                    println!("Created missing index '{index}'");
+                }
+            }
+        }
+    });
+}
+
+/// Diagnose problems with the database and print suggested fixes. Exit with an error status if
+/// any of the checks fail.
+pub async fn doctor(cli: &Cli) {
+    tracing::trace!("doctor({cli:?})");
+    let diagnoses = match Relatable::connect(cli.database.as_deref(), &cli.caching).await {
+        Ok(rltbl) => rltbl.doctor().await.expect("Error diagnosing database"),
+        Err(err) => vec![Diagnosis::fail(
+            "connection",
+            &format!("Could not connect to the database: {err}"),
+            "Check the --database option or the RLTBL_CONNECTION environment variable, or \
+             create a database with `rltbl init`",
+        )],
+    };
+    print_output(cli, &diagnoses, |diagnoses| {
+        for diagnosis in diagnoses {
+            match diagnosis.ok {
+                true => println!("{} {}: {}", "ok".green(), diagnosis.check, diagnosis.detail),
+                false => {
+                    println!("{} {}: {}", "FAIL".red(), diagnosis.check, diagnosis.detail);
+                    if let Some(fix) = &diagnosis.fix {
+                        println!("     fix: {fix}");
+                    }
+                }
+            }
+        }
+    });
+    if diagnoses.iter().any(|diagnosis| !diagnosis.ok) {
+        std::process::exit(1);
+    }
+}
+
+/// Verify that the given table and the file that it is loaded from and saved to have not drifted
+/// apart (see [Relatable::verify_table()]). Exit with an error status if they have.
+pub async fn verify(cli: &Cli, table: &str) {
+    tracing::trace!("verify({cli:?}, {table:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    let verification = rltbl
+        .verify_table(table)
+        .await
+        .expect("Error verifying table");
+    print_output(cli, &verification, |verification| {
+        let path = verification.path.as_deref().unwrap_or("no file");
+        match verification.drift {
+            Drift::None => println!("{} {table} ({path}): {}", "ok".green(), verification.drift),
+            _ => println!("{} {table} ({path}): {}", "DRIFT".red(), verification.drift),
+        }
+    });
+    if verification.drift != Drift::None {
+        std::process::exit(1);
+    }
+}
+
+/// Apply the given caching strategy to the database (see [Relatable::apply_caching_strategy()]).
+pub async fn set_caching(cli: &Cli, strategy: &CachingStrategy) {
+    tracing::trace!("set_caching({cli:?}, {strategy:?})");
+    let mut rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    let tables = rltbl
+        .apply_caching_strategy(strategy)
+        .await
+        .expect("Error applying caching strategy");
+    print_output(
+        cli,
+        &json!({ "caching": strategy.to_string(), "tables": tables }),
+        |_| match tables.is_empty() {
+            true => println!("The triggers of all tables are up to date for '{strategy}'"),
+            false => println!(
+                "Updated the triggers of table(s) for '{strategy}': {}",
+                tables.join(", ")
+            ),
+        },
+    );
+}
+
+/// Set whether the given table is saved to its path automatically whenever it is changed (see
+/// [Relatable::autosave()]).
+pub async fn set_autosave(cli: &Cli, table: &str, enabled: bool) {
+    tracing::trace!("set_autosave({cli:?}, {table:?}, {enabled})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    rltbl
+        .set_autosave(table, enabled)
+        .await
+        .expect("Error setting autosave");
+    print_output(
+        cli,
+        &json!({ "table": table, "autosave": enabled }),
+        |_| match enabled {
+            true => println!("Table '{table}' will be saved automatically when it changes"),
+            false => println!("Table '{table}' will no longer be saved automatically"),
+        },
+    );
+}
+
+/// Set the most rows of the given table that may be asked for at once in a request, or, given
+/// zero, remove the table's own maximum (see [Relatable::get_max_limit()]).
+pub async fn set_max_limit(cli: &Cli, table: &str, limit: usize) {
+    tracing::trace!("set_max_limit({cli:?}, {table:?}, {limit})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    let max_limit = Some(limit).filter(|limit| *limit > 0);
+    rltbl
+        .set_max_limit(table, max_limit)
+        .await
+        .expect("Error setting maximum limit");
+    print_output(
+        cli,
+        &json!({ "table": table, "max_limit": max_limit }),
+        |_| match max_limit {
+            Some(limit) => println!("At most {limit} rows of '{table}' may be requested at once"),
+            None => println!("Table '{table}' has no maximum page size of its own"),
+        },
+    );
+}
+
+/// Set whether the given table is in wide mode, or, given `None`, have it be in wide mode only when
+/// it has more columns than the limit (see [Relatable::set_wide_mode()]).
+pub async fn set_wide_mode(cli: &Cli, table: &str, enabled: Option<bool>) {
+    tracing::trace!("set_wide_mode({cli:?}, {table:?}, {enabled:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    rltbl
+        .set_wide_mode(table, enabled)
+        .await
+        .expect("Error setting wide mode");
+    print_output(
+        cli,
+        &json!({ "table": table, "wide": enabled }),
+        |_| match enabled {
+            Some(true) => println!("Table '{table}' is in wide mode"),
+            Some(false) => println!("Table '{table}' is not in wide mode"),
+            None => println!("Table '{table}' is in wide mode when it has too many columns"),
+        },
+    );
+}
+
+/// Check the configuration in the meta tables and print the problems found. Exit with an error
+/// status if any of them is an error.
+pub async fn check_config(cli: &Cli) {
+    tracing::trace!("check_config({cli:?})");
+    let rltbl = Relatable::connect(cli.database.as_deref(), &cli.caching)
+        .await
+        .expect("Could not connect to relatable database");
+    let problems: Vec<ConfigProblem> = rltbl
+        .check_config()
+        .await
+        .expect("Error checking configuration");
+    print_output(cli, &problems, |problems| {
+        if problems.is_empty() {
+            println!("{} No problems found", "ok".green());
+        }
+        for problem in problems {
+            let level = match problem.level.as_str() {
+                "error" => problem.level.to_uppercase().red(),
+                _ => problem.level.to_uppercase().yellow(),
+            };
+            let location = match problem.row {
+                Some(row) => format!("{}:{row}:{}", problem.table, problem.column),
+                None => format!("{}:{}", problem.table, problem.column),
+            };
+            println!("{level} {location} ({}): {}", problem.rule, problem.message);
+        }
+    });
+    if problems.iter().any(|problem| problem.level == "error") {
+        std::process::exit(1);
+    }
+}
+
+/// Run the self-test scenario against the given SQLite database and, if given, the given
+/// PostgreSQL database, and report any differences between them. Exit with an error status if the
+/// scenario fails or the backends behave differently.
+pub async fn selftest(cli: &Cli, sqlite: &str, postgres: Option<&str>) {
+    tracing::trace!("selftest({cli:?}, {sqlite}, {postgres:?})");
+    let sqlite_steps = Relatable::selftest(sqlite)
+        .await
+        .unwrap_or_else(|err| panic!("Self-test failed for SQLite: {err}"));
+    let postgres_steps = match postgres {
+        None => None,
+        Some(url) => Some(
+            Relatable::selftest(url)
+                .await
+                .unwrap_or_else(|err| panic!("Self-test failed for PostgreSQL: {err}")),
+        ),
+    };
+
+    let steps = sqlite_steps
+        .iter()
+        .map(|(step, sqlite_observed)| {
+            let postgres_observed = postgres_steps
+                .as_ref()
+                .map(|steps| steps.get(step).cloned().unwrap_or_default());
+            json!({
+                "step": step,
+                "ok": postgres_observed.is_none()
+                    || postgres_observed.as_ref() == Some(sqlite_observed),
+                "sqlite": sqlite_observed,
+                "postgres": postgres_observed,
+            })
+        })
+        .collect::<Vec<_>>();
+    let differences = steps
+        .iter()
+        .filter(|step| step["ok"] == json!(false))
+        .count();
+
+    print_output(cli, &steps, |steps| {
+        for step in steps {
+            let name = step["step"].as_str().unwrap_or_default();
+            if step["ok"] == json!(true) {
+                println!("{} {name}", "ok".green());
+            } else {
+                let indent = |observed: &JsonValue| {
+                    observed
+                        .as_str()
+                        .unwrap_or_default()
+                        .replace("\n", "\n    ")
+                };
+                println!("{} {name}", "DIFF".red());
+                println!("  sqlite:\n    {}", indent(&step["sqlite"]));
+                println!("  postgres:\n    {}", indent(&step["postgres"]));
+            }
+        }
+        if differences > 0 {
+            println!("{differences} step(s) behaved differently");
+        }
+    });
+    if differences > 0 {
+        std::process::exit(1);
+    }
+}
+
+/// Print the script that registers dynamic completions for rltbl with the given shell.
+pub fn print_completions(shell: &str) {
+    tracing::trace!("print_completions({shell})");
+    let shells = Shells::builtins();
+    let completer = shells.completer(shell).unwrap_or_else(|| {
+        panic!(
+            "Unsupported shell '{shell}'. Supported shells are: {}",
+            shells.names().collect::<Vec<_>>().join(", ")
+        )
+    });
+    completer
+        .write_registration("COMPLETE", "rltbl", "rltbl", "rltbl", &mut io::stdout())
+        .expect("Error writing completion script");
+}
+
+/// Connect to the database for the purpose of completing a command line. The database is taken
+/// from the --database option of the command line being completed, if given, and otherwise
+/// determined in the usual way.
+fn connect_for_completion() -> Option<Relatable> {
+    let args = std::env::args().collect::<Vec<_>>();
+    let database =
+        args.iter()
+            .enumerate()
+            .find_map(|(i, arg)| match arg.strip_prefix("--database=") {
+                Some(database) => Some(database.to_string()),
+                None if arg == "--database" => args.get(i + 1).cloned(),
+                None => None,
+            });
+    async_std::task::block_on(Relatable::connect(
+        database.as_deref(),
+        &CachingStrategy::None,
+    ))
+    .ok()
+}
+
+/// Complete the name of a table in the current database.
+fn complete_table() -> Vec<CompletionCandidate> {
+    let Some(rltbl) = connect_for_completion() else {
+        return vec![];
+    };
+    async_std::task::block_on(rltbl.list_tables())
+        .unwrap_or_default()
+        .into_iter()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Complete the name of a column of the table named on the command line being completed, or, if
+/// no table has been named, of any table in the current database.
+fn complete_column() -> Vec<CompletionCandidate> {
+    let Some(rltbl) = connect_for_completion() else {
+        return vec![];
+    };
+    let tables = async_std::task::block_on(rltbl.list_tables()).unwrap_or_default();
+    let args = std::env::args().collect::<Vec<_>>();
+    let tables = match tables.iter().find(|table| args.contains(table)) {
+        Some(table) => vec![table.clone()],
+        None => tables,
+    };
+    let mut columns = vec![];
+    for table in tables {
+        for column in async_std::task::block_on(rltbl.fetch_columns(&table)).unwrap_or_default() {
+            if !columns.contains(&column.name) {
+                columns.push(column.name);
+            }
+        }
+    }
+    columns.into_iter().map(CompletionCandidate::new).collect()
 }
 
 /// Drop all of the data tables and meta tables from the database
@@ -1060,6 +3161,7 @@ pub async fn drop_database(cli: &Cli) {
         .drop_database()
         .await
         .expect("Error dropping database");
+    print_output(cli, &json!({ "dropped": true }), |_| ());
 }
 
 /// Build a demonstration database
@@ -1068,17 +3170,24 @@ pub async fn build_demo(cli: &Cli, force: &bool, size: usize) {
     Relatable::build_demo(cli.database.as_deref(), force, size, &cli.caching)
         .await
         .expect("Error building demonstration database");
-    println!(
-        "Created a demonstration database in '{}'",
-        match &cli.database {
-            None => rltbl::core::RLTBL_DEFAULT_DB,
-            Some(db) => db,
-        }
-    );
+    let database = cli
+        .database
+        .as_deref()
+        .unwrap_or(rltbl::core::RLTBL_DEFAULT_DB);
+    print_output(cli, &json!({ "database": database }), |_| {
+        println!("Created a demonstration database in '{database}'")
+    });
 }
 
 pub async fn process_command() {
     tracing::trace!("process_command()");
+    process_command_with_plugins(&vec![]).await
+}
+
+/// Process the command line, as [process_command()] does, but also accept the subcommands of the
+/// given plugins.
+pub async fn process_command_with_plugins(plugins: &Vec<Box<dyn Plugin>>) {
+    tracing::trace!("process_command_with_plugins({} plugins)", plugins.len());
     // Handle a CGI request, instead of normal CLI input.
     match std::env::var_os("GATEWAY_INTERFACE").and_then(|p| Some(p.into_string())) {
         Some(Ok(s)) if s == "CGI/1.1" => {
@@ -1087,6 +3196,13 @@ pub async fn process_command() {
         _ => (),
     };
 
+    // Handle a request for completions from the shell, instead of normal CLI input:
+    let command = build_command(plugins);
+    CompleteEnv::with_factory(|| command.clone()).complete();
+
+    // Check the command line against the subcommands of the plugins, too, so that they are listed
+    // in the help. They are then parsed as external subcommands (see [run_external()]):
+    command.get_matches();
     let cli = Cli::parse();
 
     // Initialize tracing using --verbose flags
@@ -1099,15 +3215,19 @@ pub async fn process_command() {
     tracing::debug!("CLI {cli:?}");
 
     match &cli.command {
-        Command::Init { force } => init(&cli, force, cli.database.as_deref()).await,
+        Command::Init {
+            force,
+            change_capture,
+        } => init(&cli, force, cli.database.as_deref(), change_capture).await,
         Command::Get { subcommand } => match subcommand {
             GetSubcommand::Table {
                 table,
                 filters,
+                search,
                 format,
                 limit,
                 offset,
-            } => print_table(&cli, table, filters, format, limit, offset).await,
+            } => print_table(&cli, table, filters, search, format, limit, offset).await,
             GetSubcommand::Rows {
                 table,
                 limit,
@@ -1116,6 +3236,8 @@ pub async fn process_command() {
             GetSubcommand::Value { table, row, column } => {
                 print_value(&cli, table, *row, column).await
             }
+            GetSubcommand::Blame { table, column } => print_blame(&cli, table, column).await,
+            GetSubcommand::Stats { table } => print_column_stats(&cli, table).await,
         },
         Command::Set { subcommand } => match subcommand {
             SetSubcommand::Value {
@@ -1124,17 +3246,49 @@ pub async fn process_command() {
                 column,
                 value,
                 validation_level,
-            } => set_value(&cli, table, *row, column, value, validation_level).await,
+                message,
+            } => {
+                set_value(
+                    &cli,
+                    table,
+                    *row,
+                    column,
+                    value,
+                    validation_level,
+                    message.as_deref(),
+                )
+                .await
+            }
+            SetSubcommand::Fill {
+                table,
+                column,
+                from_row,
+                to_row,
+                series,
+            } => fill_down(&cli, table, column, *from_row, *to_row, *series).await,
         },
         Command::Add { subcommand } => match subcommand {
             AddSubcommand::Row {
                 table,
                 after_id,
+                template,
                 validation_level,
-            } => add_row(&cli, table, *after_id, validation_level).await,
+            } => {
+                add_row(
+                    &cli,
+                    table,
+                    *after_id,
+                    template.as_deref(),
+                    validation_level,
+                )
+                .await
+            }
             AddSubcommand::Message { table, row, column } => {
                 add_message(&cli, table, *row, column).await
             }
+            AddSubcommand::Template { table, template } => {
+                add_template(&cli, table, template).await
+            }
         },
         Command::Move { subcommand } => match subcommand {
             MoveSubcommand::Row { table, row, after } => move_row(&cli, table, *row, *after).await,
@@ -1148,6 +3302,8 @@ pub async fn process_command() {
             ValidateSubcommand::Value { table, row, column } => {
                 validate_value(&cli, table, row, column).await
             }
+            ValidateSubcommand::Datatype { datatype } => validate_datatype(&cli, datatype).await,
+            ValidateSubcommand::Rules { table } => validate_rules(&cli, table).await,
         },
         Command::Delete { subcommand } => match subcommand {
             DeleteSubcommand::Row { table, row } => delete_row(&cli, table, *row).await,
@@ -1169,17 +3325,255 @@ pub async fn process_command() {
                 .await
             }
         },
-        Command::Undo {} => undo(&cli).await,
+        Command::Archive { table, filter } => archive_rows(&cli, table, filter).await,
+        Command::Batch {
+            validation_level,
+            message,
+            preview,
+        } => batch(&cli, validation_level, message.as_deref(), *preview).await,
+        Command::Undo { preview } => undo(&cli, *preview).await,
         Command::Redo {} => redo(&cli).await,
         Command::History { context } => print_history(&cli, *context).await,
-        Command::Load { subcommand } => match subcommand {
+        Command::Load {
+            message,
+            subcommand,
+        } => match subcommand {
             LoadSubcommand::Table {
                 paths,
                 force,
                 validation_level,
-            } => load_tables(&cli, paths, *force, validation_level).await,
+                materialize_change_id,
+                uuid_row_ids,
+                mapping: Some(mapping),
+                resume,
+                rename,
+                rename_file,
+                partition_by,
+            } => {
+                if *force
+                    || *materialize_change_id
+                    || *uuid_row_ids
+                    || *resume
+                    || partition_by.is_some()
+                {
+                    panic!("--mapping cannot be combined with options for creating tables");
+                }
+                load_mapped(
+                    &cli,
+                    paths,
+                    mapping,
+                    validation_level,
+                    &get_column_renames(rename, rename_file.as_deref()),
+                    message.as_deref(),
+                )
+                .await
+            }
+            LoadSubcommand::Table {
+                paths,
+                force,
+                validation_level,
+                materialize_change_id,
+                uuid_row_ids,
+                mapping: None,
+                resume: true,
+                rename,
+                rename_file,
+                partition_by,
+            } => {
+                if *force || *materialize_change_id || *uuid_row_ids {
+                    panic!("--resume cannot be combined with options for creating tables");
+                }
+                resume_loads(
+                    &cli,
+                    paths,
+                    validation_level,
+                    &get_column_renames(rename, rename_file.as_deref()),
+                    partition_by.as_deref(),
+                    message.as_deref(),
+                )
+                .await
+            }
+            LoadSubcommand::Table {
+                paths,
+                force,
+                validation_level,
+                materialize_change_id,
+                uuid_row_ids,
+                mapping: None,
+                resume: false,
+                rename,
+                rename_file,
+                partition_by,
+            } => {
+                let column_renames = get_column_renames(rename, rename_file.as_deref());
+                let options = LoadOptions {
+                    validation_level,
+                    materialize_change_id: *materialize_change_id,
+                    uuid_row_ids: *uuid_row_ids,
+                    column_renames: &column_renames,
+                    partition_by: partition_by.as_deref(),
+                };
+                load_tables(&cli, paths, *force, &options, message.as_deref()).await
+            }
+            LoadSubcommand::All {
+                paths,
+                force,
+                validation_level,
+                jobs,
+            } => {
+                load_all(
+                    &cli,
+                    paths,
+                    *force,
+                    validation_level,
+                    *jobs,
+                    message.as_deref(),
+                )
+                .await
+            }
+            LoadSubcommand::Meta { table, path } => load_meta_table(&cli, table, path).await,
         },
+        Command::Map {
+            path,
+            table,
+            column,
+            constant,
+            transform,
+            output,
+        } => {
+            map_source(
+                &cli,
+                path,
+                table,
+                column,
+                constant,
+                transform,
+                output.as_deref(),
+            )
+            .await
+        }
         Command::Save { save_dir } => save_all(&cli, save_dir.as_deref()).await,
+        Command::Maintain {} => maintain(&cli).await,
+        Command::Doctor {} => doctor(&cli).await,
+        Command::Verify { table } => verify(&cli, table).await,
+        Command::Check { subcommand } => match subcommand {
+            CheckSubcommand::Config {} => check_config(&cli).await,
+        },
+        Command::Config { subcommand } => match subcommand {
+            ConfigSubcommand::Set { subcommand } => match subcommand {
+                ConfigSetSubcommand::Caching { strategy } => set_caching(&cli, strategy).await,
+                ConfigSetSubcommand::Autosave { table, enabled } => {
+                    set_autosave(&cli, table, *enabled).await
+                }
+                ConfigSetSubcommand::MaxLimit { table, limit } => {
+                    set_max_limit(&cli, table, *limit).await
+                }
+                ConfigSetSubcommand::Wide { table, enabled } => {
+                    set_wide_mode(&cli, table, *enabled).await
+                }
+            },
+        },
+        Command::Completions { shell } => print_completions(shell),
+        Command::Selftest { sqlite, postgres } => selftest(&cli, sqlite, postgres.as_deref()).await,
+        Command::Sync { peer } => sync(&cli, peer).await,
+        Command::External(args) => run_external(&cli, plugins, args).await,
+        Command::Remote { url, subcommand } => {
+            let remote = Remote::new(url);
+            match subcommand {
+                RemoteSubcommand::Get { subcommand } => match subcommand {
+                    GetSubcommand::Table {
+                        table,
+                        filters,
+                        search,
+                        format,
+                        limit,
+                        offset,
+                    } => remote_print_table(
+                        &cli,
+                        &remote,
+                        table,
+                        &TableOptions {
+                            filters,
+                            search,
+                            format,
+                            limit,
+                            offset,
+                        },
+                    ),
+                    GetSubcommand::Rows {
+                        table,
+                        limit,
+                        offset,
+                    } => remote_print_rows(&cli, &remote, table, limit, offset),
+                    GetSubcommand::Value { table, row, column } => {
+                        remote_print_value(&cli, &remote, table, *row, column)
+                    }
+                    GetSubcommand::Blame { table, column } => {
+                        let blame = remote.blame(table, column).expect("Error getting blame");
+                        print_output(&cli, &blame, |blame| {
+                            print!("{}", blame_to_tsv(blame).expect("Error writing TSV"))
+                        })
+                    }
+                    GetSubcommand::Stats { table } => {
+                        let stats = remote
+                            .column_stats(table)
+                            .expect("Error getting statistics");
+                        print_output(&cli, &stats, |stats| {
+                            print!("{}", column_stats_to_tsv(stats).expect("Error writing TSV"))
+                        })
+                    }
+                },
+                RemoteSubcommand::Set { subcommand } => match subcommand {
+                    SetSubcommand::Value {
+                        table,
+                        row,
+                        column,
+                        value,
+                        validation_level,
+                        message,
+                    } => {
+                        if *validation_level != ValidationLevel::Full {
+                            panic!("--validation-level is not supported by remote servers");
+                        }
+                        remote_set_value(
+                            &cli,
+                            &remote,
+                            table,
+                            *row,
+                            column,
+                            value,
+                            message.as_deref(),
+                        )
+                    }
+                    SetSubcommand::Fill { .. } => {
+                        panic!("Filling down is not supported by remote servers")
+                    }
+                },
+                RemoteSubcommand::Load { subcommand } => match subcommand {
+                    RemoteLoadSubcommand::Table {
+                        force,
+                        message,
+                        paths,
+                    } => remote_load_tables(&cli, &remote, paths, *force, message.as_deref()),
+                },
+                RemoteSubcommand::Validate { subcommand } => match subcommand {
+                    RemoteValidateSubcommand::Table { table } => {
+                        remote_validate_table(&cli, &remote, table)
+                    }
+                },
+            }
+        }
+        Command::Mirror { subcommand } => match subcommand {
+            MirrorSubcommand::Add { name, url, tables } => {
+                add_mirror(&cli, name, url, tables).await
+            }
+            MirrorSubcommand::Pull { name } => pull_mirrors(&cli, name.as_deref()).await,
+        },
+        Command::Search { subcommand } => match subcommand {
+            SearchSubcommand::Add { table, columns } => add_search(&cli, table, columns).await,
+            SearchSubcommand::Remove { table } => remove_search(&cli, table).await,
+            SearchSubcommand::Rebuild { table } => rebuild_search(&cli, table.as_deref()).await,
+        },
         Command::Drop { subcommand } => match subcommand {
             DropSubcommand::Database {} => drop_database(&cli).await,
         },
@@ -1187,10 +3581,130 @@ pub async fn process_command() {
             host,
             port,
             timeout,
-        } => serve(&cli, host, port, timeout)
-            .await
-            .expect("Operation: 'serve' failed"),
+            query_timeout,
+            max_limit,
+            drain_timeout,
+        } => {
+            let served = serve(
+                &cli,
+                host,
+                port,
+                timeout,
+                query_timeout,
+                max_limit,
+                drain_timeout,
+            )
+            .await;
+            if let Err(err) = served {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
         Command::Cgi {} => serve_cgi().await,
         Command::Demo { force, size } => build_demo(&cli, force, *size).await,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+    use pretty_assertions::assert_eq;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// A plugin that counts the rows of the table given to its subcommand
+    struct CountPlugin {
+        counted: Arc<Mutex<Option<u64>>>,
+    }
+
+    impl Plugin for CountPlugin {
+        fn command(&self) -> clap::Command {
+            clap::Command::new("count").arg(clap::Arg::new("table").required(true))
+        }
+
+        fn run<'a>(
+            &'a self,
+            _cli: &'a Cli,
+            rltbl: &'a Relatable,
+            matches: &'a ArgMatches,
+        ) -> BoxFuture<'a, Result<()>> {
+            Box::pin(async move {
+                let table = matches.get_one::<String>("table").unwrap();
+                let sql = format!(r#"SELECT COUNT(1) FROM "{table}""#);
+                let count = rltbl.connection.query_value(&sql, None).await?;
+                *self.counted.lock().unwrap() = count.and_then(|count| count.as_u64());
+                Ok(())
+            })
+        }
+    }
+
+    #[test]
+    fn test_plugins() {
+        let database = "build/test_plugins.db";
+        block_on(Relatable::build_demo(
+            Some(database),
+            &true,
+            5,
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        let counted = Arc::new(Mutex::new(None));
+        let plugins: Vec<Box<dyn Plugin>> = vec![Box::new(CountPlugin {
+            counted: counted.clone(),
+        })];
+
+        // The subcommands of plugins are listed in the help:
+        let help = build_command(&plugins).render_help().to_string();
+        assert!(help
+            .lines()
+            .any(|line| line.trim_start().starts_with("count")));
+        assert!(!Cli::command()
+            .render_help()
+            .to_string()
+            .lines()
+            .any(|line| line.trim_start().starts_with("count")));
+
+        // A plugin's subcommand is run with an instance connected to the given database:
+        let args = [
+            "rltbl",
+            "--database",
+            database,
+            "--user",
+            "alice",
+            "count",
+            "penguin",
+        ];
+        let cli = Cli::parse_from(args);
+        let external = match &cli.command {
+            Command::External(external) => external.clone(),
+            command => panic!("Unexpected command {command:?}"),
+        };
+        assert_eq!(external, ["count", "penguin"]);
+        block_on(run_external(&cli, &plugins, &external));
+        assert_eq!(*counted.lock().unwrap(), Some(5));
+
+        // Other subcommands are run by an executable named after them, which is given the global
+        // options in its environment:
+        let command = external_command(&cli, &["lint".to_string(), "--fix".to_string()]);
+        assert_eq!(command.get_program(), "rltbl-lint");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["--fix"]);
+        let envs = command
+            .get_envs()
+            .map(|(var, value)| {
+                (
+                    var.to_string_lossy().to_string(),
+                    value.unwrap_or_default().to_string_lossy().to_string(),
+                )
+            })
+            .collect::<IndexMap<_, _>>();
+        assert_eq!(envs["RLTBL_CONNECTION"], database);
+        assert_eq!(envs["RLTBL_USER"], "alice");
+        assert_eq!(envs["RLTBL_OUTPUT"], "text");
+
+        // Subcommands for which there is no executable either are reported as unrecognized:
+        let error = unknown_subcommand("lint");
+        assert_eq!(error.kind(), ErrorKind::InvalidSubcommand);
+        assert!(error.to_string().contains("unrecognized subcommand 'lint'"));
+    }
+}
@@ -5,33 +5,46 @@
 use crate::{self as rltbl};
 use rltbl::{
     git,
-    select::{Select, SelectField},
+    select::{DisplayColumn, Limit, PageCursor, Select, SelectField},
     sql::{
-        self, CachingStrategy, DbActiveConnection, DbConnection, DbKind, DbTransaction, JsonRow,
-        MemoryCacheKey, SqlParam, VecInto as _,
+        self, is_memory_db, CachingStrategy, DbActiveConnection, DbConnection, DbKind,
+        DbTransaction, IsolationLevel, JsonRow, MemoryCacheKey, SqlParam, VecInto as _,
+    },
+    table::{
+        Cell, Collation, Column, Datatype, ForeignKey, Locale, Mapping, Message, Partition, Row,
+        Rule, SeverityOverride, Structure, Table, Transform,
     },
-    table::{Cell, Column, Datatype, Message, Row, Table},
 };
 
 use anyhow::Result;
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, RecordBatch, RecordBatchOptions,
+    StringArray,
+};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{DataType as ArrowType, Field, Schema};
+use async_std::task::block_on;
 use colored::Colorize;
 use csv::{QuoteStyle, ReaderBuilder, Writer, WriterBuilder};
+use futures::future::{BoxFuture, Future};
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use minijinja::{path_loader, Environment};
 use rand::{rngs::StdRng, seq::IteratorRandom as _, Rng as _, SeedableRng as _};
 use regex::Regex;
-use serde::{Deserialize, Serialize};
-use serde_json::{json, to_value, Value as JsonValue};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, to_value, Map as JsonMap, Value as JsonValue};
+use sha2::{Digest as _, Sha256};
 use sprintf::sprintf;
 use std::{
     collections::{HashMap, HashSet},
     fmt::Display,
     fs::File,
-    io::Write,
+    io::{Read, Write},
     path::Path as FilePath,
     str::FromStr,
-    sync::Mutex,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
 };
 use tabwriter::TabWriter;
 
@@ -48,13 +61,46 @@ pub static HISTORY_MAX: usize = 1000;
 /// The default limit on the number of rows to return in a fetch.
 pub static DEFAULT_LIMIT: usize = 100;
 
-/// THe maximum number of rows to return in a fetch.
+/// The default maximum number of rows that may be asked for at once in a request (see
+/// [Relatable::max_limit]).
 pub static MAX_LIMIT: usize = 1000;
 
+/// The default number of columns above which a table is reported to be wide (see
+/// [Relatable::column_warning_limit]).
+pub static DEFAULT_COLUMN_WARNING_LIMIT: usize = 100;
+
+/// The default number of columns above which a table is in wide mode, unless it has been set to be
+/// in wide mode or not (see [Relatable::wide_column_limit]).
+pub static DEFAULT_WIDE_COLUMN_LIMIT: usize = 200;
+
+/// The default time, in milliseconds, that a server waits after the last change to a table
+/// before saving it automatically (see [Relatable::autosave_interval]).
+pub static DEFAULT_AUTOSAVE_INTERVAL: u64 = 2000;
+
+/// The number of rows above which a random sample of a whole table is chosen, in PostgreSQL, from
+/// a sample of the table's blocks, rather than from all of its rows.
+pub static TABLESAMPLE_MIN_ROWS: u64 = 100_000;
+
 lazy_static! {
     pub static ref CACHE: Mutex<HashMap<MemoryCacheKey, Vec<JsonRow>>> = Mutex::new(HashMap::new());
 }
 
+tokio::task_local! {
+    /// The user and idempotency key of the operation that is being run by
+    /// [Relatable::idempotent()], which are recorded on the change records that the operation
+    /// makes, in the same transaction
+    static IDEMPOTENCY_KEY: (String, String);
+
+    /// Set while [Relatable::merge_operations()] applies the operations of other instances, whose
+    /// changes are recorded as usual but are not logged again as operations of this instance
+    static MERGING_OPERATIONS: ();
+
+    /// The change group to which the changes being recorded by [Relatable::_in_change_group()]
+    /// are assigned, which is identified by the change_id of the first of them, once it has been
+    /// recorded
+    static CHANGE_GROUP: std::cell::Cell<Option<u64>>;
+}
+
 /// Various errors generated by [relatable](crate)
 #[derive(Debug)]
 pub enum RelatableError {
@@ -86,6 +132,8 @@ pub enum RelatableError {
     RegexError(regex::Error),
     /// An error when a table cannot be found.
     TableError(String),
+    /// An error when a query runs for longer than it is allowed to
+    TimeoutError(String),
     /// An error that occurred because of a user's action
     UserError(String),
 }
@@ -98,6 +146,231 @@ impl Display for RelatableError {
 
 impl std::error::Error for RelatableError {}
 
+/// A future returned by one of the callbacks registered in [Hooks]
+pub type HookFuture<T> = BoxFuture<'static, Result<T>>;
+
+type PreInsertHook = Arc<dyn Fn(String, JsonRow) -> HookFuture<JsonRow> + Send + Sync>;
+type PreDeleteHook = Arc<dyn Fn(String, u64) -> HookFuture<()> + Send + Sync>;
+type ChangeSetHook = Arc<dyn Fn(ChangeSet) -> HookFuture<()> + Send + Sync>;
+
+/// The callbacks that library users have registered to be run before or after the operations of
+/// a [Relatable] instance (see [Relatable::on_pre_insert()], [Relatable::on_post_update()],
+/// [Relatable::on_pre_delete()], and [Relatable::on_post_change()]). Every callback is run
+/// within the operation's transaction, so that a callback that returns an error vetoes the
+/// operation, which is then rolled back.
+///
+/// Since the operations write to the database synchronously, within their transactions, the
+/// future returned by a callback is driven to completion with [block_on()], which blocks the
+/// thread that is running the operation until the callback is finished. A callback should
+/// therefore be quick, and must not wait on anything that needs that thread, or, in a
+/// single-threaded async runtime, that runtime, to make progress. In particular, it must not run
+/// another operation on the same [Relatable] instance, which may have to wait for the
+/// transaction of the callback's own operation to finish.
+#[derive(Clone, Default)]
+pub struct Hooks {
+    pre_insert: Vec<PreInsertHook>,
+    post_update: Vec<ChangeSetHook>,
+    pre_delete: Vec<PreDeleteHook>,
+    post_change: Vec<ChangeSetHook>,
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Hooks")
+            .field("pre_insert", &self.pre_insert.len())
+            .field("post_update", &self.post_update.len())
+            .field("pre_delete", &self.pre_delete.len())
+            .field("post_change", &self.post_change.len())
+            .finish()
+    }
+}
+
+/// The ways in which the changes that are made to the tables of a [relatable](crate) database
+/// can be captured, i.e., how the _order of new rows is filled in and how the cache is cleared
+/// when a table is changed. The mode is chosen when the database is initialized, and recorded in
+/// the meta table.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChangeCaptureMode {
+    /// Changes are captured by triggers in the database (see [TriggerCapture])
+    #[default]
+    Trigger,
+    /// Changes are captured by rltbl's own write paths, for databases, such as some managed
+    /// PostgreSQL services, in which triggers cannot be created (see [WritePathCapture])
+    WritePath,
+}
+
+impl ChangeCaptureMode {
+    /// Returns the [ChangeCapture] that implements this mode
+    pub fn change_capture(&self) -> Arc<dyn ChangeCapture> {
+        match self {
+            ChangeCaptureMode::Trigger => Arc::new(TriggerCapture),
+            ChangeCaptureMode::WritePath => Arc::new(WritePathCapture),
+        }
+    }
+}
+
+impl FromStr for ChangeCaptureMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self> {
+        tracing::trace!("ChangeCaptureMode::from_str({mode:?})");
+        match mode.to_lowercase().as_str() {
+            "trigger" => Ok(ChangeCaptureMode::Trigger),
+            "write_path" => Ok(ChangeCaptureMode::WritePath),
+            _ => Err(RelatableError::InputError(format!(
+                "Unrecognized change capture mode: {mode}"
+            ))
+            .into()),
+        }
+    }
+}
+
+impl Display for ChangeCaptureMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeCaptureMode::Trigger => write!(f, "trigger"),
+            ChangeCaptureMode::WritePath => write!(f, "write_path"),
+        }
+    }
+}
+
+/// Captures the changes that are made to the tables of a [relatable](crate) database, either in
+/// the database itself or in rltbl's write paths, depending on its [ChangeCaptureMode].
+pub trait ChangeCapture: std::fmt::Debug + Send + Sync {
+    /// The mode that this change capture implements
+    fn mode(&self) -> ChangeCaptureMode;
+
+    /// Add the DDL needed to maintain the meta columns, _id and _order, of the given table to the
+    /// given DDL.
+    fn add_metacolumn_ddl(&self, ddl: &mut Vec<String>, table: &str, db_kind: &DbKind);
+
+    /// Add the DDL needed to clear the cache whenever the given table is changed, in accordance
+    /// with the given caching strategy, to the given DDL.
+    fn add_caching_ddl(
+        &self,
+        ddl: &mut Vec<String>,
+        table: &str,
+        db_kind: &DbKind,
+        caching_strategy: &CachingStrategy,
+    );
+
+    /// Returns the statements to run after rows have been inserted into the given table, which
+    /// has meta columns, without necessarily being given an _order, or with an explicit _id.
+    fn after_insert_sql(&self, table: &str, db_kind: &DbKind) -> Vec<String>;
+
+    /// Delete the dirty entries from the cache, in accordance with the caching strategy of the
+    /// given instance, after the given table has been changed using the given transaction.
+    fn after_change(
+        &self,
+        rltbl: &Relatable,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+    ) -> Result<()>;
+}
+
+/// Captures changes using triggers in the database, which fill in the _order of every new row
+/// and, if the caching strategy is [CachingStrategy::Trigger], clear the cache whenever a table
+/// is changed.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TriggerCapture;
+
+impl ChangeCapture for TriggerCapture {
+    fn mode(&self) -> ChangeCaptureMode {
+        ChangeCaptureMode::Trigger
+    }
+
+    fn add_metacolumn_ddl(&self, ddl: &mut Vec<String>, table: &str, db_kind: &DbKind) {
+        sql::add_metacolumn_trigger_ddl(ddl, table, db_kind);
+    }
+
+    fn add_caching_ddl(
+        &self,
+        ddl: &mut Vec<String>,
+        table: &str,
+        db_kind: &DbKind,
+        caching_strategy: &CachingStrategy,
+    ) {
+        if let CachingStrategy::Trigger = caching_strategy {
+            sql::add_caching_trigger_ddl(ddl, table, db_kind);
+        }
+    }
+
+    fn after_insert_sql(&self, _table: &str, _db_kind: &DbKind) -> Vec<String> {
+        vec![]
+    }
+
+    fn after_change(
+        &self,
+        rltbl: &Relatable,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+    ) -> Result<()> {
+        match rltbl.caching_strategy {
+            // Trigger has the same behaviour as None here, since the database will be triggering
+            // this step automatically every time the table is edited in that case.
+            CachingStrategy::None | CachingStrategy::Trigger => (),
+            CachingStrategy::Memory(_) => rltbl.clear_mem_cache(table),
+            CachingStrategy::TruncateAll => Relatable::clear_cache(tx, None)?,
+            CachingStrategy::Truncate => Relatable::clear_cache(tx, Some(table))?,
+        };
+        Ok(())
+    }
+}
+
+/// Captures changes without creating any triggers: the _order of new rows, and, in PostgreSQL,
+/// the sequence that their _ids are drawn from, are brought up to date after rltbl inserts rows,
+/// and the cache is cleared by rltbl itself when the caching strategy is
+/// [CachingStrategy::Trigger]. Changes that are made to the tables by other programs are not
+/// captured. Features that depend on triggers, i.e., materialized _change_id columns, full-text
+/// search indexes, and partitioned tables in SQLite, are unavailable in this mode.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WritePathCapture;
+
+impl ChangeCapture for WritePathCapture {
+    fn mode(&self) -> ChangeCaptureMode {
+        ChangeCaptureMode::WritePath
+    }
+
+    fn add_metacolumn_ddl(&self, _ddl: &mut Vec<String>, _table: &str, _db_kind: &DbKind) {}
+
+    fn add_caching_ddl(
+        &self,
+        _ddl: &mut Vec<String>,
+        _table: &str,
+        _db_kind: &DbKind,
+        _caching_strategy: &CachingStrategy,
+    ) {
+    }
+
+    fn after_insert_sql(&self, table: &str, db_kind: &DbKind) -> Vec<String> {
+        let mut statements = vec![format!(
+            r#"UPDATE "{table}" SET "_order" = ({NEW_ORDER_MULTIPLIER} * "_id")
+               WHERE "_order" IS NULL"#
+        )];
+        // Rows that are inserted with explicit _ids do not advance the sequence in PostgreSQL:
+        if let DbKind::Postgres = db_kind {
+            statements.push(format!(
+                r#"SELECT setval('{table}__id_seq', MAX("_id")) FROM "{table}"
+                   HAVING MAX("_id") > (SELECT "last_value" FROM "{table}__id_seq")"#
+            ));
+        }
+        statements
+    }
+
+    fn after_change(
+        &self,
+        rltbl: &Relatable,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+    ) -> Result<()> {
+        match rltbl.caching_strategy {
+            // There are no triggers to clear the cache, so it is done here instead:
+            CachingStrategy::Trigger => Relatable::clear_cache(tx, Some(table)),
+            _ => TriggerCapture.after_change(rltbl, tx, table),
+        }
+    }
+}
+
 /// The main [rltbl](crate) struct.
 #[derive(Debug)]
 pub struct Relatable {
@@ -106,17 +379,174 @@ pub struct Relatable {
     pub connection: DbConnection,
     // pub minijinja: Environment<'static>,
     pub default_limit: usize,
+    /// The most rows that may be asked for at once in the selects made from the paths and query
+    /// parameters of requests, for any table, or zero for no maximum (see
+    /// [Relatable::get_max_limit()])
     pub max_limit: usize,
+    /// The number of columns above which a warning is given that a table is wide, when it is
+    /// loaded or diagnosed (see [Relatable::doctor()]), or zero for no warning
+    pub column_warning_limit: usize,
+    /// The number of columns above which a table is in wide mode, unless it has been set to be in
+    /// wide mode or not (see [Table::is_wide()]), or zero for no tables to be in wide mode unless
+    /// they have been set to be
+    pub wide_column_limit: usize,
+    /// The longest that the queries of the selects that are made from the paths and query
+    /// parameters of requests may run for (see [Select::timeout]), if there is a limit
+    pub query_timeout: Option<Duration>,
     pub caching_strategy: CachingStrategy,
     /// The validation level, which defaults to 'full'
     pub validation_level: ValidationLevel,
     pub memory_cache_size: usize,
+    /// Whether tables loaded through this instance should have a materialized _change_id column,
+    /// maintained by a trigger on the history table.
+    pub materialize_change_id: bool,
+    /// Whether tables loaded through this instance should have a _uuid column, identifying each
+    /// row by a UUID that is stable across databases.
+    pub uuid_row_ids: bool,
+    /// The new names of the columns of the files loaded through this instance, keyed on their
+    /// names in the files, e.g., for files exported from systems with other naming conventions.
+    pub column_renames: IndexMap<String, String>,
+    /// The column, if any, by which the tables loaded through this instance should be partitioned
+    /// (see [Relatable::partition_table()]).
+    pub partition_by: Option<String>,
+    /// The locale in which the files loaded and saved through this instance are written, for the
+    /// columns that have no locale of their own (see [Locale]). CSV files are also delimited as
+    /// is usual in the locale.
+    pub locale: Locale,
+    /// The delimiter of the fields of the files loaded through this instance. If none is given,
+    /// it is detected from the first lines of each file (see [open_delimited()]).
+    pub delimiter: Option<u8>,
+    /// The character encoding of the files loaded through this instance. If none is given, it is
+    /// detected from the first bytes of each file (see [open_delimited()]).
+    pub encoding: Option<Encoding>,
+    /// The user to attribute the changes made through the web server to when the request gives no
+    /// other user
+    pub default_user: Option<String>,
+    /// Whether this instance is connected to the copy of a database made by
+    /// [Relatable::sandbox()], whose changes are never saved to the tables' paths or committed
+    /// to git.
+    pub sandboxed: bool,
+    /// The callbacks to run before or after this instance's operations
+    pub hooks: Hooks,
+    /// How the changes made to the tables of the database are captured, which is read from the
+    /// meta table when connecting (see [ChangeCaptureMode])
+    pub change_capture: Arc<dyn ChangeCapture>,
+    /// How long to wait after the last change to a table that is saved automatically before
+    /// saving it (see [Relatable::autosave()]). It is zero, i.e., tables are saved as soon as they
+    /// are changed, unless it is set, e.g., by a server, to [DEFAULT_AUTOSAVE_INTERVAL].
+    pub autosave_interval: Duration,
+    /// The isolation level of the transactions of operations that read and then write several
+    /// tables, such as undoing, redoing, and deleting rows, which defaults to serializable so
+    /// that concurrent editors cannot interleave them. Those that fail to serialize are run again
+    /// (see [Relatable::retry_serialization_failures()]).
+    pub write_isolation: IsolationLevel,
+    /// The tables whose automatic saves are pending, and when they were last changed, shared
+    /// with the clones of this instance
+    autosaves: Arc<Mutex<Autosaves>>,
+    /// The users and idempotency keys of the operations in progress through this instance and
+    /// its clones (see [Relatable::idempotent()])
+    idempotency_keys: Arc<Mutex<HashSet<(String, String)>>>,
+}
+
+/// Collects the options used to connect to a [relatable](crate) database, which can then be
+/// connected to either immediately, using [RelatableBuilder::connect], or on first use, using
+/// [RelatableBuilder::build].
+#[derive(Clone, Debug, Default)]
+pub struct RelatableBuilder {
+    path: Option<String>,
+    caching_strategy: Option<CachingStrategy>,
+    readonly: Option<bool>,
+    default_user: Option<String>,
+}
+
+impl RelatableBuilder {
+    /// Create a new builder with the default options.
+    pub fn new() -> Self {
+        tracing::trace!("RelatableBuilder::new()");
+        Self::default()
+    }
+
+    /// Connect to the database at the given path or URL. If not given, the location indicated
+    /// by the environment variable RLTBL_CONNECTION, or else [RLTBL_DEFAULT_DB], is used.
+    pub fn connection(mut self, path: &str) -> Self {
+        tracing::trace!("RelatableBuilder::connection({path:?})");
+        self.path = Some(path.to_string());
+        self
+    }
+
+    /// Use the given caching strategy. If not given, no caching is done.
+    pub fn caching_strategy(mut self, caching_strategy: &CachingStrategy) -> Self {
+        tracing::trace!("RelatableBuilder::caching_strategy({caching_strategy:?})");
+        self.caching_strategy = Some(*caching_strategy);
+        self
+    }
+
+    /// Set whether the database is read-only. If not given, the environment variable
+    /// RLTBL_READONLY is used.
+    pub fn readonly(mut self, readonly: bool) -> Self {
+        tracing::trace!("RelatableBuilder::readonly({readonly})");
+        self.readonly = Some(readonly);
+        self
+    }
+
+    /// Attribute the changes made through the web server to the given user when the request gives
+    /// no other user.
+    pub fn default_user(mut self, user: &str) -> Self {
+        tracing::trace!("RelatableBuilder::default_user({user:?})");
+        self.default_user = Some(user.to_string());
+        self
+    }
+
+    /// Connect to the database immediately using the options collected by this builder.
+    pub async fn connect(&self) -> Result<Relatable> {
+        tracing::trace!("RelatableBuilder::connect({self:?})");
+        let mut rltbl = Relatable::connect(
+            self.path.as_deref(),
+            &self.caching_strategy.unwrap_or(CachingStrategy::None),
+        )
+        .await?;
+        if let Some(readonly) = self.readonly {
+            rltbl.readonly = readonly;
+        }
+        rltbl.default_user = self.default_user.clone();
+        Ok(rltbl)
+    }
+
+    /// Returns a [LazyRelatable] that will connect to the database, using the options collected
+    /// by this builder, the first time that it is used.
+    pub fn build(self) -> LazyRelatable {
+        tracing::trace!("RelatableBuilder::build({self:?})");
+        LazyRelatable {
+            builder: self,
+            relatable: tokio::sync::OnceCell::new(),
+        }
+    }
+}
+
+/// A [Relatable] instance that is only connected to the database the first time it is used.
+#[derive(Debug)]
+pub struct LazyRelatable {
+    builder: RelatableBuilder,
+    relatable: tokio::sync::OnceCell<Relatable>,
+}
+
+impl LazyRelatable {
+    /// Returns the [Relatable] instance, connecting to the database first if this has not already
+    /// been done. If connecting fails, the error is returned and connecting will be tried again
+    /// the next time.
+    pub async fn get(&self) -> Result<&Relatable> {
+        tracing::trace!("LazyRelatable::get()");
+        self.relatable
+            .get_or_try_init(|| self.builder.connect())
+            .await
+    }
 }
 
 impl Relatable {
     /// Connect to a relatable database at the given path, or, if not given, at the location
     /// indicated by the environment variable RLTBL_CONNECTION, or, if that is not given,
-    /// at [RLTBL_DEFAULT_DB]
+    /// at [RLTBL_DEFAULT_DB]. The path [MEMORY_DB](sql::MEMORY_DB) connects to a fresh, in-memory
+    /// SQLite database that lasts as long as this instance (and its clones) does.
     pub async fn connect(path: Option<&str>, caching_strategy: &CachingStrategy) -> Result<Self> {
         tracing::trace!("Relatable::connect({path:?}, {caching_strategy:?})");
         let root = std::env::var("RLTBL_ROOT").unwrap_or_default();
@@ -134,7 +564,7 @@ impl Relatable {
                 }
             }
         };
-        if !path.starts_with("postgresql://") {
+        if !path.starts_with("postgresql://") && !is_memory_db(&path) {
             let file = FilePath::new(&path);
             if !file.exists() {
                 return Err(RelatableError::InitError(
@@ -144,15 +574,33 @@ impl Relatable {
             }
         }
         let (connection, _) = DbConnection::connect(&path).await?;
-        Ok(Self {
+        let mut rltbl = Self {
             root,
             readonly,
             connection,
             // minijinja: env,
             default_limit: DEFAULT_LIMIT,
             max_limit: MAX_LIMIT,
+            column_warning_limit: DEFAULT_COLUMN_WARNING_LIMIT,
+            wide_column_limit: DEFAULT_WIDE_COLUMN_LIMIT,
+            query_timeout: None,
             caching_strategy: *caching_strategy,
             validation_level: ValidationLevel::Full,
+            materialize_change_id: false,
+            uuid_row_ids: false,
+            column_renames: IndexMap::new(),
+            partition_by: None,
+            default_user: None,
+            sandboxed: false,
+            hooks: Hooks::default(),
+            change_capture: ChangeCaptureMode::default().change_capture(),
+            locale: Locale::default(),
+            delimiter: None,
+            encoding: None,
+            autosave_interval: Duration::ZERO,
+            write_isolation: IsolationLevel::Serializable,
+            autosaves: Arc::new(Mutex::new(Autosaves::default())),
+            idempotency_keys: Arc::new(Mutex::new(HashSet::new())),
             memory_cache_size: match caching_strategy {
                 CachingStrategy::Memory(size) => {
                     let mut cache = CACHE.lock().expect("Could not lock cache");
@@ -164,7 +612,122 @@ impl Relatable {
                 }
                 _ => 0,
             },
-        })
+        };
+        rltbl.change_capture = rltbl.get_change_capture_mode().await?.change_capture();
+        Ok(rltbl)
+    }
+
+    /// Returns a new instance, with the same options, that is connected to the same database
+    /// through a connection of its own. This can be used to share an instance across server
+    /// workers.
+    pub fn try_clone(&self) -> Result<Self> {
+        tracing::trace!("Relatable::try_clone()");
+        Ok(self.clone_with_connection(self.connection.try_clone()?))
+    }
+
+    /// Returns a new instance, with the same options and shared state as this one, that uses the
+    /// given connection.
+    fn clone_with_connection(&self, connection: DbConnection) -> Self {
+        tracing::trace!("Relatable::clone_with_connection({connection:?})");
+        Self {
+            root: self.root.clone(),
+            readonly: self.readonly,
+            connection,
+            default_limit: self.default_limit,
+            max_limit: self.max_limit,
+            column_warning_limit: self.column_warning_limit,
+            wide_column_limit: self.wide_column_limit,
+            query_timeout: self.query_timeout,
+            caching_strategy: self.caching_strategy,
+            validation_level: self.validation_level,
+            memory_cache_size: self.memory_cache_size,
+            materialize_change_id: self.materialize_change_id,
+            uuid_row_ids: self.uuid_row_ids,
+            column_renames: self.column_renames.clone(),
+            partition_by: self.partition_by.clone(),
+            default_user: self.default_user.clone(),
+            sandboxed: self.sandboxed,
+            hooks: self.hooks.clone(),
+            change_capture: self.change_capture.clone(),
+            locale: self.locale,
+            delimiter: self.delimiter,
+            encoding: self.encoding,
+            autosave_interval: self.autosave_interval,
+            write_isolation: self.write_isolation,
+            autosaves: self.autosaves.clone(),
+            idempotency_keys: self.idempotency_keys.clone(),
+        }
+    }
+
+    /// Register a callback to be run before a row is added to a table, e.g., by
+    /// [Relatable::add_row()], other than by an undo or a redo. The callback is given the name of
+    /// the table and the row to be inserted, and returns the row that should actually be inserted,
+    /// e.g., with a timestamp column filled in, or an error to veto the insertion.
+    pub fn on_pre_insert<F, T>(&mut self, hook: F)
+    where
+        F: Fn(String, JsonRow) -> T + Send + Sync + 'static,
+        T: Future<Output = Result<JsonRow>> + Send + 'static,
+    {
+        self.hooks
+            .pre_insert
+            .push(Arc::new(move |table, row| Box::pin(hook(table, row))));
+    }
+
+    /// Register a callback to be run after the values of a table have been updated, e.g., by
+    /// [Relatable::set_values()]. The callback is given the changeset that was actually applied,
+    /// and may return an error to veto the update.
+    pub fn on_post_update<F, T>(&mut self, hook: F)
+    where
+        F: Fn(ChangeSet) -> T + Send + Sync + 'static,
+        T: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks
+            .post_update
+            .push(Arc::new(move |changeset| Box::pin(hook(changeset))));
+    }
+
+    /// Register a callback to be run before a row is deleted from a table, e.g., by
+    /// [Relatable::delete_row()], other than by an undo or a redo. The callback is given the name
+    /// of the table and the ID of the row to be deleted, and may return an error to veto the
+    /// deletion.
+    pub fn on_pre_delete<F, T>(&mut self, hook: F)
+    where
+        F: Fn(String, u64) -> T + Send + Sync + 'static,
+        T: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks
+            .pre_delete
+            .push(Arc::new(move |table, row| Box::pin(hook(table, row))));
+    }
+
+    /// Register a callback to be run after any changeset, including an undo or a redo, has been
+    /// recorded to the history table. The callback is given the changeset, and may return an
+    /// error to veto the change.
+    pub fn on_post_change<F, T>(&mut self, hook: F)
+    where
+        F: Fn(ChangeSet) -> T + Send + Sync + 'static,
+        T: Future<Output = Result<()>> + Send + 'static,
+    {
+        self.hooks
+            .post_change
+            .push(Arc::new(move |changeset| Box::pin(hook(changeset))));
+    }
+
+    /// Run the pre-insert hooks on the given row, returning the row to be inserted
+    fn run_pre_insert_hooks(&self, table_name: &str, row: &JsonRow) -> Result<JsonRow> {
+        let mut row = row.clone();
+        for hook in &self.hooks.pre_insert {
+            row = block_on(hook(table_name.to_string(), row))?;
+        }
+        Ok(row)
+    }
+
+    /// Run the given changeset hooks on the given changeset
+    fn run_changeset_hooks(&self, hooks: &Vec<ChangeSetHook>, changeset: &ChangeSet) -> Result<()> {
+        for hook in hooks {
+            block_on(hook(changeset.clone()))?;
+        }
+        Ok(())
     }
 
     /// Initialize a [relatable](crate) database at the given path, or, if not given, at
@@ -176,6 +739,28 @@ impl Relatable {
         caching_strategy: &CachingStrategy,
     ) -> Result<Self> {
         tracing::trace!("Relatable::init({force:?}, {path:?}, {caching_strategy:?})");
+        Relatable::init_with_change_capture(
+            force,
+            path,
+            caching_strategy,
+            &ChangeCaptureMode::default(),
+        )
+        .await
+    }
+
+    /// Initialize a [relatable](crate) database as [Relatable::init()] does, in which the changes
+    /// that are made to its tables are captured in the given mode. The mode of an existing
+    /// database can only be changed by overwriting it.
+    pub async fn init_with_change_capture(
+        force: &bool,
+        path: Option<&str>,
+        caching_strategy: &CachingStrategy,
+        change_capture_mode: &ChangeCaptureMode,
+    ) -> Result<Self> {
+        tracing::trace!(
+            "Relatable::init_with_change_capture({force:?}, {path:?}, {caching_strategy:?}, \
+             {change_capture_mode:?})"
+        );
         let path = match path {
             Some(path) => path.to_string(),
             None => {
@@ -185,7 +770,8 @@ impl Relatable {
                 }
             }
         };
-        if !path.starts_with("postgresql://") {
+        let mut file_exists = false;
+        if !path.starts_with("postgresql://") && !is_memory_db(&path) {
             let dir: &std::path::Path =
                 FilePath::new(&path)
                     .parent()
@@ -202,25 +788,158 @@ impl Relatable {
                     std::fs::remove_file(&file)?;
                     tracing::info!("Removed '{file:?}' file");
                 } else {
+                    file_exists = true;
+                }
+            }
+            if !file_exists {
+                File::create(&path)?;
+            }
+        }
+        let mut rltbl = Relatable::connect(Some(&path), caching_strategy).await?;
+
+        // If this is an existing relatable database, bring it up to date instead of recreating it:
+        if !*force {
+            if Table::table_exists("table", &rltbl).await? {
+                rltbl.migrate().await?;
+                let mode = rltbl.change_capture.mode();
+                if mode != *change_capture_mode {
                     return Err(RelatableError::InitError(format!(
-                        "File {file:?} already exists. Use --force to overwrite"
+                        "Database '{path}' captures changes in mode '{mode}'. \
+                         Use --force to overwrite it"
                     ))
                     .into());
                 }
+                return Ok(rltbl);
+            } else if file_exists {
+                return Err(RelatableError::InitError(format!(
+                    "File '{path}' already exists. Use --force to overwrite"
+                ))
+                .into());
             }
-            File::create(&path)?;
         }
 
-        // Create the meta tables:
-        let rltbl = Relatable::connect(Some(&path), caching_strategy).await?;
-        let ddl = sql::generate_meta_tables_ddl(*force, &rltbl.connection.kind());
+        // Create the meta tables, and record the mode in which changes are captured:
+        rltbl.change_capture = change_capture_mode.change_capture();
+        let ddl = sql::generate_meta_tables_ddl(
+            *force,
+            &rltbl.connection.kind(),
+            rltbl.change_capture.as_ref(),
+        );
         for sql in ddl {
             rltbl.connection.query(&sql, None).await?;
         }
+        let sql = format!(
+            r#"INSERT INTO "meta" ("key", "value") VALUES ('change_capture', {sql_param})"#,
+            sql_param = SqlParam::new(&rltbl.connection.kind()).next()
+        );
+        let params = json!([change_capture_mode.to_string()]);
+        rltbl.connection.query(&sql, Some(&params)).await?;
 
         Ok(rltbl)
     }
 
+    /// Run a canonical scenario (initialize, load, edit, validate, undo, and export) against a
+    /// fresh demonstration database at the given location, which is overwritten. Returns a map
+    /// from each step of the scenario to a textual summary of what was observed, so that the
+    /// behaviour of different database backends can be compared.
+    pub async fn selftest(database: &str) -> Result<IndexMap<String, String>> {
+        tracing::trace!("Relatable::selftest({database:?})");
+        let mut observed = IndexMap::new();
+        let user = "rltbl";
+
+        // Initialize and load:
+        let rltbl =
+            Relatable::build_demo(Some(database), &true, 10, &CachingStrategy::None).await?;
+        observed.insert("init".to_string(), rltbl.list_tables().await?.join(", "));
+        observed.insert(
+            "load".to_string(),
+            rltbl
+                .selftest_query(r#"SELECT COUNT(1) AS "count" FROM "penguin""#)
+                .await?,
+        );
+
+        // Edit:
+        let update = |row: u64, column: &str, before: JsonValue, after: JsonValue| ChangeSet {
+            user: user.to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row,
+                column: column.to_string(),
+                before,
+                after,
+            }],
+        };
+        let species_sql = r#"SELECT "species" FROM "penguin" WHERE "_id" = 1"#;
+        let species = rltbl.connection.query_value(species_sql, None).await?;
+        rltbl
+            .set_values(&update(
+                1,
+                "species",
+                species.unwrap_or_default(),
+                json!("Pygoscelis papua"),
+            ))
+            .await?;
+        observed.insert("edit".to_string(), rltbl.selftest_query(species_sql).await?);
+
+        // Validate, after introducing an invalid value:
+        let body_mass_sql = r#"SELECT "body_mass" FROM "penguin" WHERE "_id" = 2"#;
+        let body_mass = rltbl.connection.query_value(body_mass_sql, None).await?;
+        rltbl
+            .set_values(&update(
+                2,
+                "body_mass",
+                body_mass.unwrap_or_default(),
+                json!("heavy"),
+            ))
+            .await?;
+        let table = Table::get_table("penguin", &rltbl).await?;
+        rltbl.validate_table(&table).await?;
+        let messages_sql = r#"SELECT "row", "column", "value", "level", "rule"
+                              FROM "message"
+                              WHERE "table" = 'penguin'
+                              ORDER BY "row", "column", "rule""#;
+        observed.insert(
+            "validate".to_string(),
+            rltbl.selftest_query(messages_sql).await?,
+        );
+
+        // Undo both edits:
+        rltbl.undo(user).await?;
+        rltbl.undo(user).await?;
+        observed.insert(
+            "undo".to_string(),
+            [
+                rltbl.selftest_query(species_sql).await?,
+                rltbl.selftest_query(body_mass_sql).await?,
+                rltbl.selftest_query(messages_sql).await?,
+            ]
+            .join("\n"),
+        );
+
+        // Export:
+        let result = rltbl.fetch(&Select::from("penguin")).await?;
+        observed.insert("export".to_string(), result.to_tsv());
+
+        Ok(observed)
+    }
+
+    /// Run the given query for [Relatable::selftest] and summarize its results as text, with one
+    /// line per row and tab-separated values.
+    async fn selftest_query(&self, sql: &str) -> Result<String> {
+        tracing::trace!("Relatable::selftest_query({sql:?})");
+        Ok(self
+            .connection
+            .query(sql, None)
+            .await?
+            .iter()
+            .map(|row| row.to_strings().join("\t"))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
     /// Build a demonstration database. Based on <https://github.com/allisonhorst/palmerpenguins>.
     pub async fn build_demo(
         database: Option<&str>,
@@ -264,6 +983,7 @@ impl Relatable {
         let sql =
             format!(r#"INSERT INTO "table" ("table", "path") VALUES ('{table}', '{table}.tsv')"#);
         self.connection.query(&sql, None).await?;
+        self.capture_inserts("table").await?;
 
         let pkey_clause = match self.connection.kind() {
             DbKind::Sqlite => "INTEGER PRIMARY KEY AUTOINCREMENT",
@@ -288,10 +1008,11 @@ impl Relatable {
         self.connection.query(&sql, None).await?;
 
         let mut ddl = vec![];
-        sql::add_metacolumn_trigger_ddl(&mut ddl, table, &self.connection.kind());
-        if let CachingStrategy::Trigger = self.caching_strategy {
-            sql::add_caching_trigger_ddl(&mut ddl, table, &self.connection.kind());
-        }
+        let kind = self.connection.kind();
+        self.change_capture
+            .add_metacolumn_ddl(&mut ddl, table, &kind);
+        self.change_capture
+            .add_caching_ddl(&mut ddl, table, &kind, &self.caching_strategy);
         for sql in ddl {
             self.connection.query(&sql, None).await?;
         }
@@ -353,6 +1074,7 @@ impl Relatable {
             let param_values = json!(param_values);
             self.connection.query(&sql, Some(&param_values)).await?;
         }
+        self.capture_inserts(table).await?;
 
         Ok(())
     }
@@ -376,6 +1098,7 @@ impl Relatable {
         let sql =
             format!(r#"INSERT INTO "table" ("table", "path") VALUES ('{table}', '{table}.tsv')"#);
         self.connection.query(&sql, None).await?;
+        self.capture_inserts("table").await?;
 
         let pkey_clause = match self.connection.kind() {
             DbKind::Sqlite => "INTEGER PRIMARY KEY AUTOINCREMENT",
@@ -394,10 +1117,11 @@ impl Relatable {
         self.connection.query(&sql, None).await?;
 
         let mut ddl = vec![];
-        sql::add_metacolumn_trigger_ddl(&mut ddl, table, &self.connection.kind());
-        if let CachingStrategy::Trigger = self.caching_strategy {
-            sql::add_caching_trigger_ddl(&mut ddl, table, &self.connection.kind());
-        }
+        let kind = self.connection.kind();
+        self.change_capture
+            .add_metacolumn_ddl(&mut ddl, table, &kind);
+        self.change_capture
+            .add_caching_ddl(&mut ddl, table, &kind, &self.caching_strategy);
         for sql in ddl {
             self.connection.query(&sql, None).await?;
         }
@@ -408,6 +1132,7 @@ impl Relatable {
         );
 
         self.connection.query(&sql, None).await?;
+        self.capture_inserts(table).await?;
         Ok(())
     }
 
@@ -442,7 +1167,8 @@ impl Relatable {
         self.connection.query(&sql, None).await?;
 
         let mut ddl = vec![];
-        sql::add_metacolumn_trigger_ddl(&mut ddl, "datatype", &self.connection.kind());
+        self.change_capture
+            .add_metacolumn_ddl(&mut ddl, "datatype", &self.connection.kind());
         for sql in ddl {
             self.connection.query(&sql, None).await?;
         }
@@ -502,6 +1228,7 @@ impl Relatable {
         );
         let param_values = json!(param_values);
         self.connection.query(&sql, Some(&param_values)).await?;
+        self.capture_inserts("datatype").await?;
         Ok(())
     }
 
@@ -537,7 +1264,8 @@ impl Relatable {
         self.connection.query(&sql, None).await?;
 
         let mut ddl = vec![];
-        sql::add_metacolumn_trigger_ddl(&mut ddl, "column", &self.connection.kind());
+        self.change_capture
+            .add_metacolumn_ddl(&mut ddl, "column", &self.connection.kind());
         for sql in ddl {
             self.connection.query(&sql, None).await?;
         }
@@ -634,6 +1362,7 @@ impl Relatable {
         );
         let param_values = json!(param_values);
         self.connection.query(&sql, Some(&param_values)).await?;
+        self.capture_inserts("column").await?;
         Ok(())
     }
 
@@ -709,6 +1438,7 @@ impl Relatable {
         let sql = r#"INSERT INTO egg VALUES
         (0, 0, 'E1', 'N1')"#;
         self.connection.query(sql, None).await.unwrap();
+        self.capture_inserts("table").await?;
 
         Ok(())
     }
@@ -736,7 +1466,21 @@ impl Relatable {
     pub async fn drop_meta_tables(&self) -> Result<()> {
         tracing::trace!("Relatable::drop_meta_tables({self:?})");
         for table_name in [
-            "cache", "history", "change", "user", "message", "datatype", "column", "table",
+            "meta",
+            "mirror",
+            "search",
+            "template",
+            "clock",
+            "operation",
+            "origin",
+            "cache",
+            "history",
+            "change",
+            "user",
+            "message",
+            "datatype",
+            "column",
+            "table",
         ] {
             let mut table = Table {
                 name: table_name.to_string(),
@@ -747,3241 +1491,12295 @@ impl Relatable {
         Ok(())
     }
 
-    // Drop all of the data tables and metatables in the database
-    pub async fn drop_database(&self) -> Result<()> {
-        tracing::trace!("Relatable::drop_database({self:?})");
-        self.drop_data_tables().await?;
-        self.drop_meta_tables().await?;
-        Ok(())
+    /// Bring the meta tables of the database up to date with [sql::SCHEMA_VERSION] by running, in
+    /// order, each of the migrations that have not yet been applied to it. Databases created
+    /// before the schema version was tracked are treated as being at version 0. Returns the
+    /// version that the database was at before it was migrated.
+    pub async fn migrate(&self) -> Result<u64> {
+        tracing::trace!("Relatable::migrate()");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let version = self._migrate(&mut tx)?;
+        tx.commit()?;
+        Ok(version)
     }
 
-    /// Render this relatable instance in HTML according to the given template and context
-    pub fn render<T: Serialize>(&self, template: &str, context: T) -> Result<String> {
-        tracing::trace!("Relatable::render({template:?}, context)");
-        // TODO: Optionally we should set up the environment once and store it,
-        // but during development it's very convenient to rebuild every time.
-        let mut env = Environment::new();
+    /// Bring the meta tables of the database up to date with [sql::SCHEMA_VERSION], using the
+    /// given transaction. Returns the version that the database was at before it was migrated.
+    pub fn _migrate(&self, tx: &mut DbTransaction<'_>) -> Result<u64> {
+        tracing::trace!("Relatable::_migrate(tx)");
+        let version = Relatable::_get_schema_version(tx)?;
+        if version > sql::SCHEMA_VERSION {
+            return Err(RelatableError::DataError(format!(
+                "Database schema version {version} is newer than the version supported by this \
+                 release of rltbl ({})",
+                sql::SCHEMA_VERSION
+            ))
+            .into());
+        }
+        if version == sql::SCHEMA_VERSION {
+            return Ok(version);
+        }
 
-        // Load default template strings at compile time.
-        let templates = IndexMap::from([
-            ("page.html", include_str!("templates/page.html")),
-            ("table.html", include_str!("templates/table.html")),
-            ("row_menu.html", include_str!("templates/row_menu.html")),
-            (
-                "column_menu.html",
-                include_str!("templates/column_menu.html"),
-            ),
-            ("cell_menu.html", include_str!("templates/cell_menu.html")),
-        ]);
+        for next_version in (version + 1)..=sql::SCHEMA_VERSION {
+            Relatable::_run_migration(next_version, tx)?;
+            tracing::info!("Migrated database to schema version {next_version}");
+        }
 
-        // Load templates dynamically if src/templates/ exists,
-        // otherwise use strings from compile time.
-        // TODO: This should be a configuration option.
-        let dir = std::env::var("RLTBL_TEMPLATES").unwrap_or("src/templates/".to_string());
-        if FilePath::new(&dir).is_dir() {
-            env.set_loader(path_loader(dir));
-        };
-        for (name, content) in templates {
-            match env.get_template(name) {
-                Ok(_) => (),
-                Err(_) => env.add_template(name, content).unwrap(),
+        if !Table::_table_exists("meta", tx)? {
+            for sql in sql::generate_meta_table_ddl(false, &tx.kind()) {
+                tx.query(&sql, None)?;
             }
+        } else {
+            let sql = format!(
+                r#"UPDATE "meta" SET "value" = {sql_param} WHERE "key" = 'schema_version'"#,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            tx.query(&sql, Some(&json!([sql::SCHEMA_VERSION.to_string()])))?;
         }
-
-        env.get_template(template)?
-            .render(context)
-            .map_err(|e| e.into())
+        Ok(version)
     }
 
-    /// Use the given [Select] to fetch data from the database.
-    pub async fn fetch(&self, select: &Select) -> Result<ResultSet> {
-        tracing::trace!("Relatable::fetch({select:?})");
-
-        // Get the table and columns information and use the given select to set the table's view:
-        let mut table = Table::get_table(select.table_name.as_str(), self).await?;
-        if select.view_name == format!("{}_default_view", table.name) || select.view_name == "" {
-            table.set_view(self, "default").await?;
-        } else if select.view_name == format!("{}_text_view", table.name) {
-            table.set_view(self, "text").await?;
-        } else {
-            tracing::warn!(
-                "Unsupported view name: '{}'. Falling back to default view",
-                select.view_name
-            );
-            table.set_view(self, "default").await?;
+    /// Get the schema version of the database using the given transaction. Databases without a
+    /// meta table are at version 0.
+    pub fn _get_schema_version(tx: &mut DbTransaction<'_>) -> Result<u64> {
+        tracing::trace!("Relatable::_get_schema_version(tx)");
+        if !Table::_table_exists("meta", tx)? {
+            return Ok(0);
         }
-        let mut columns = table.columns.values().cloned().collect::<Vec<_>>();
+        let sql = r#"SELECT "value" FROM "meta" WHERE "key" = 'schema_version'"#;
+        match tx.query_value(sql, None)? {
+            None => Ok(0),
+            Some(value) => sql::json_to_string(&value).parse::<u64>().map_err(|err| {
+                RelatableError::DataError(format!("Invalid schema version '{value}': {err}")).into()
+            }),
+        }
+    }
 
-        // Fetch the data
-        let (statement, parameters) = select.to_sql(&self.connection.kind())?;
-        let json_params = json!(parameters);
-        let json_rows = self
-            .connection
-            .query(&statement, Some(&json_params))
-            .await?;
-        let count = json_rows.len();
-        tracing::info!("Fetched {count} rows");
+    /// Get the mode in which the changes that are made to the tables of the database are
+    /// captured. Databases that have not recorded one use [ChangeCaptureMode::Trigger].
+    pub async fn get_change_capture_mode(&self) -> Result<ChangeCaptureMode> {
+        tracing::trace!("Relatable::get_change_capture_mode()");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let mode = Relatable::_get_change_capture_mode(&mut tx)?;
+        tx.commit()?;
+        Ok(mode)
+    }
 
-        // Filter out the table's columns that do not occur in the select:
-        if select.select.len() > 0 {
-            columns = columns
-                .iter()
-                .filter(|column| {
-                    select.select.iter().any(|sel| match sel {
-                        SelectField::Column {
-                            table: select_table,
-                            column: select_column,
-                            ..
-                        } => {
-                            *select_column == column.name
-                                && (select_table == "" || *select_table == table.name)
-                        }
-                        SelectField::Expression { alias, .. } => *alias == column.name,
-                    })
-                })
-                .map(|c| c.clone())
-                .collect();
+    /// Get the mode in which the changes that are made to the tables of the database are
+    /// captured, using the given transaction.
+    pub fn _get_change_capture_mode(tx: &mut DbTransaction<'_>) -> Result<ChangeCaptureMode> {
+        tracing::trace!("Relatable::_get_change_capture_mode(tx)");
+        if !Table::_table_exists("meta", tx)? {
+            return Ok(ChangeCaptureMode::default());
         }
+        let sql = r#"SELECT "value" FROM "meta" WHERE "key" = 'change_capture'"#;
+        match tx.query_value(sql, None)? {
+            None => Ok(ChangeCaptureMode::default()),
+            Some(value) => sql::json_to_string(&value).parse(),
+        }
+    }
 
-        // Return the data:
-        let rows: Vec<Row> = json_rows.clone().vec_into();
-        let total = self.count(&select).await?;
-        Ok(ResultSet {
-            select: select.clone(),
-            statement,
-            parameters,
-            range: Range {
-                count,
-                total,
-                start: (select.offset + 1) as u64,
-                end: (select.offset + count) as u64,
-            },
-            table,
-            columns,
-            rows,
-        })
+    /// Run the migration that brings the meta tables from the previous schema version to the
+    /// given one, using the given transaction. Each migration checks for what it adds, since
+    /// databases created before the schema version was tracked may already have some of it.
+    fn _run_migration(version: u64, tx: &mut DbTransaction<'_>) -> Result<()> {
+        tracing::trace!("Relatable::_run_migration({version}, tx)");
+        let add_column = |table: &str, column: &str, sql_type: &str, tx: &mut DbTransaction<'_>| {
+            let (columns, meta_columns) = Table::_collect_column_info(table, tx)?;
+            if !columns
+                .iter()
+                .chain(meta_columns.iter())
+                .any(|col| col.name == column)
+            {
+                tx.query(
+                    &format!(r#"ALTER TABLE "{table}" ADD COLUMN "{column}" {sql_type}"#),
+                    None,
+                )?;
+            }
+            Ok::<(), anyhow::Error>(())
+        };
+        match version {
+            // Version 1 adds the user-supplied message to the change table:
+            1 => add_column("change", "message", "TEXT", tx)?,
+            // Version 2 adds change groups to the change table:
+            2 => add_column("change", "change_group", "INTEGER", tx)?,
+            // Version 3 adds the origin and operation tables:
+            3 => {
+                if !Table::_table_exists("origin", tx)? {
+                    for sql in sql::generate_origin_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+                if !Table::_table_exists("operation", tx)? {
+                    for sql in sql::generate_operation_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+            }
+            // Version 4 adds indexes on the history and message tables (those on the operation
+            // table are added by version 17):
+            4 => {
+                for (index, table, columns) in sql::META_TABLE_INDEXES {
+                    if table != "operation" {
+                        tx.query(&sql::generate_index_ddl(index, table, columns), None)?;
+                    }
+                }
+            }
+            // Version 5 adds the template table:
+            5 => {
+                if !Table::_table_exists("template", tx)? {
+                    for sql in sql::generate_template_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+            }
+            // Version 6 adds content hashes and timestamps to the message table. Note that
+            // SQLite does not allow a column with a non-constant default to be added to an
+            // existing table, so existing messages are stamped explicitly:
+            6 => {
+                add_column("message", "_hash", "TEXT", tx)?;
+                add_column("message", "_datetime", "TIMESTAMP", tx)?;
+                tx.query(
+                    r#"UPDATE "message" SET "_datetime" = CURRENT_TIMESTAMP
+                       WHERE "_datetime" IS NULL"#,
+                    None,
+                )?;
+                Relatable::_hash_messages(tx)?;
+            }
+            // Version 7 adds the mirror table:
+            7 => {
+                if !Table::_table_exists("mirror", tx)? {
+                    for sql in sql::generate_mirror_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+            }
+            // Version 8 adds the number of rows loaded so far to the table table, so that
+            // interrupted loads can be resumed:
+            8 => add_column("table", "loaded", "BIGINT", tx)?,
+            // Version 9 adds the search table:
+            9 => {
+                if !Table::_table_exists("search", tx)? {
+                    for sql in sql::generate_search_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+            }
+            // Version 10 adds the partition table:
+            10 => {
+                if !Table::_table_exists("partition", tx)? {
+                    for sql in sql::generate_partition_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                }
+            }
+            // Version 11 adds the checksums of the contents of each table and of its file, as of
+            // when the table was last loaded or saved, to the table table (see
+            // [Relatable::verify_table()]):
+            11 => {
+                add_column("table", "checksum", "TEXT", tx)?;
+                add_column("table", "file_checksum", "TEXT", tx)?;
+            }
+            // Version 12 adds whether each table is saved automatically to the table table (see
+            // [Relatable::autosave()]):
+            12 => add_column("table", "autosave", "BOOLEAN", tx)?,
+            // Version 13 adds the idempotency keys of the operations that made the changes to
+            // the change table (see [Relatable::idempotent()]):
+            13 => add_column("change", "idempotency_key", "TEXT", tx)?,
+            // Version 14 adds the maximum page size of each table to the table table (see
+            // [Relatable::get_max_limit()]):
+            14 => add_column("table", "max_limit", "BIGINT", tx)?,
+            // Version 15 adds the metrics of each change, i.e., the rows that it affected, the
+            // messages that it added and removed, and how long it took, to the change table (see
+            // [ChangeMetrics]):
+            15 => {
+                for column in [
+                    "rows_affected",
+                    "messages_added",
+                    "messages_removed",
+                    "elapsed_ms",
+                ] {
+                    add_column("change", column, "BIGINT", tx)?;
+                }
+            }
+            // Version 16 adds whether each table has been set to be in wide mode to the table
+            // table (see [Relatable::set_wide_mode()]):
+            16 => add_column("table", "wide", "BOOLEAN", tx)?,
+            // Version 17 adds the kind of each operation and the identity of its row across
+            // instances to the operation table, along with the clock table that caches its vector
+            // clock (see [Relatable::merge_operations()]):
+            17 => {
+                add_column("operation", "kind", "TEXT", tx)?;
+                add_column("operation", "row_origin", "TEXT", tx)?;
+                add_column("operation", "local_row", "BIGINT", tx)?;
+                tx.query(
+                    r#"UPDATE "operation" SET "kind" = 'update', "row_origin" = '',
+                              "local_row" = "row"
+                        WHERE "kind" IS NULL"#,
+                    None,
+                )?;
+                for (index, table, columns) in sql::META_TABLE_INDEXES {
+                    if table == "operation" {
+                        tx.query(&sql::generate_index_ddl(index, table, columns), None)?;
+                    }
+                }
+                if !Table::_table_exists("clock", tx)? {
+                    for sql in sql::generate_clock_table_ddl(false, &tx.kind()) {
+                        tx.query(&sql, None)?;
+                    }
+                    tx.query(
+                        r#"INSERT INTO "clock" ("origin", "counter")
+                           SELECT "origin", MAX("counter") FROM "operation" GROUP BY "origin""#,
+                        None,
+                    )?;
+                }
+            }
+            _ => {
+                return Err(RelatableError::InputError(format!(
+                    "No migration to version {version}"
+                ))
+                .into())
+            }
+        };
+        Ok(())
     }
 
-    /// Use the given [Select] to fetch data from the database.
-    pub async fn fetch_rows(&self, select: &Select) -> Result<Vec<JsonRow>> {
-        tracing::trace!("Relatable::fetch_rows({select:?})");
-        let (statement, params) = select.to_sql(&self.connection.kind())?;
-        let params = json!(params);
-        self.connection.query(&statement, Some(&params)).await
+    /// Check that the indexes on the meta tables are present, creating any that are missing.
+    /// Returns the names of the indexes that were created.
+    pub async fn maintain(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::maintain()");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let created = self._maintain(&mut tx)?;
+        tx.commit()?;
+        Ok(created)
     }
 
-    /// Get the number of rows returned by this [Select] using the given caching strategy.
-    pub async fn count(&self, select: &Select) -> Result<u64> {
-        tracing::trace!("Relatable::count({select:?})");
-        let (statement, params) = select.to_sql_count(&self.connection.kind())?;
-        let params = json!(params);
-        let json_rows = self
-            .connection
-            .cache(
-                &statement,
-                Some(&params),
-                &select.get_tables().into_iter().collect(),
-                &self.caching_strategy,
-            )
-            .await?;
-        match json_rows.get(0) {
-            Some(json_row) => json_row.get_unsigned("count"),
-            None => Ok(0),
+    /// Check that the indexes on the meta tables are present, creating any that are missing, using
+    /// the given transaction. Returns the names of the indexes that were created.
+    pub fn _maintain(&self, tx: &mut DbTransaction<'_>) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::_maintain(tx)");
+        let mut created = vec![];
+        for (index, table, columns) in sql::META_TABLE_INDEXES {
+            if !Table::_table_exists(table, tx)? || Table::_index_exists(index, tx)? {
+                continue;
+            }
+            tx.query(&sql::generate_index_ddl(index, table, columns), None)?;
+            tracing::info!("Created missing index '{index}' on table '{table}'");
+            created.push(index.to_string());
         }
+        Ok(created)
     }
 
-    /// Loads the given table from the given path. When `force` is set to true, deletes any
-    /// existing table of the same name in the database first. When `validate` is set to true,
-    /// Validates each row before loading it. Note that this function may panic.
-    pub async fn load_table(&self, table_name: &str, path: &str, force: bool) {
-        tracing::trace!("Relatable::load_table({table_name:?}, {path:?}, {force})");
-        // Read the records from the given TSV file:
-        let mut rdr = ReaderBuilder::new()
-            .has_headers(false)
-            .delimiter(b'\t')
-            .from_reader(File::open(path).expect(&format!("Unable to open '{path}'")));
-        let mut records = rdr.records();
+    /// Use the given caching strategy from now on, dropping and creating the triggers that clear
+    /// the cache whenever a table listed in the table table is changed, so that each table has
+    /// them if and only if the strategy is [CachingStrategy::Trigger]. The cache is cleared, since
+    /// the entries cached under the previous strategy may be stale. Returns the names of the
+    /// tables whose triggers were dropped or created.
+    pub async fn apply_caching_strategy(
+        &mut self,
+        caching_strategy: &CachingStrategy,
+    ) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::apply_caching_strategy({caching_strategy:?})");
+        let kind = self.connection.kind();
+        let with_triggers = matches!(caching_strategy, CachingStrategy::Trigger)
+            && self.change_capture.mode() == ChangeCaptureMode::Trigger;
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let mut changed = vec![];
+        for row in tx.query(r#"SELECT "table" FROM "table""#, None)? {
+            let table_name = row.get_string("table")?;
+            if !Table::_table_exists(&table_name, &mut tx)? {
+                continue;
+            }
+            let table = Table::_get_table(&table_name, &mut tx)?;
+            let mut ddl = vec![];
+            if kind == DbKind::Sqlite && !table.partitions.is_empty() {
+                // The triggers on the view that combines the partitions also clear the cache:
+                let sql = r#"SELECT "sql" FROM "sqlite_master"
+                              WHERE "type" = 'trigger' AND "name" = ?"#;
+                let params = json!([format!("{table_name}_insert")]);
+                let has_triggers = tx
+                    .query_value(sql, Some(&params))?
+                    .is_some_and(|sql| sql.to_string().contains(r#"DELETE FROM \"cache\""#));
+                if has_triggers != with_triggers {
+                    for operation in ["insert", "update", "delete"] {
+                        ddl.push(format!(
+                            r#"DROP TRIGGER IF EXISTS "{table_name}_{operation}""#
+                        ));
+                    }
+                    ddl.append(&mut sql::generate_sqlite_partition_trigger_ddl(
+                        &table,
+                        caching_strategy,
+                    )?);
+                }
+            } else {
+                let mut existing = 0;
+                for event in ["insert", "update", "delete"] {
+                    let trigger = format!("{table_name}_cache_after_{event}");
+                    if Table::_trigger_exists(&trigger, &mut tx)? {
+                        existing += 1;
+                    }
+                }
+                match with_triggers {
+                    true if existing < 3 => {
+                        sql::drop_caching_trigger_ddl(&mut ddl, &table_name, &kind);
+                        sql::add_caching_trigger_ddl(&mut ddl, &table_name, &kind);
+                    }
+                    false if existing > 0 => {
+                        sql::drop_caching_trigger_ddl(&mut ddl, &table_name, &kind);
+                    }
+                    _ => (),
+                };
+            }
+            if !ddl.is_empty() {
+                for sql in ddl {
+                    tx.query(&sql, None)?;
+                }
+                tracing::info!("Updated the caching triggers of table '{table_name}'");
+                changed.push(table_name);
+            }
+        }
+        tx.query(r#"DELETE FROM "cache""#, None)?;
+        tx.commit()?;
 
-        // Extract the headers from the first line of the file, which we will need for the CREATE
-        // TABLE statement:
-        let headers = {
-            let headers = match records.next() {
-                None => panic!("'{path}' is empty"),
-                Some(record) => match record {
-                    Err(err) => panic!("Error reading from '{path}': {err}"),
-                    Ok(headers) => headers.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+        let mut cache = CACHE.lock().expect("Could not lock cache");
+        cache.clear();
+        self.caching_strategy = *caching_strategy;
+        self.memory_cache_size = match caching_strategy {
+            CachingStrategy::Memory(size) => {
+                cache.reserve(*size);
+                *size
+            }
+            _ => 0,
+        };
+        Ok(changed)
+    }
+
+    /// Diagnose common problems with the database: an outdated schema version, missing triggers
+    /// and indexes, views that can no longer be read, and messages about rows that no longer
+    /// exist. Returns one [Diagnosis] per check.
+    pub async fn doctor(&self) -> Result<Vec<Diagnosis>> {
+        tracing::trace!("Relatable::doctor()");
+        let mut diagnoses = vec![];
+
+        // Connectivity:
+        match self.connection.query("SELECT 1", None).await {
+            Ok(_) => diagnoses.push(Diagnosis::pass(
+                "connection",
+                match self.connection.kind() {
+                    DbKind::Sqlite => "Connected to a SQLite database",
+                    DbKind::Postgres => "Connected to a PostgreSQL database",
                 },
-            };
-            for header in &headers {
-                if header.trim().is_empty() {
-                    panic!("One or more of the header fields is empty for table '{table_name}'");
-                }
+            )),
+            Err(err) => {
+                diagnoses.push(Diagnosis::fail(
+                    "connection",
+                    &format!("Could not query the database: {err}"),
+                    "Check the --database option or the RLTBL_CONNECTION environment variable",
+                ));
+                return Ok(diagnoses);
             }
-            headers
         };
 
-        let db_kind = self.connection.kind();
+        // Feature flags, and the driver that is serving the connection:
+        let mut features = vec![];
+        if cfg!(feature = "rusqlite") {
+            features.push("rusqlite");
+        }
+        if cfg!(feature = "sqlx") {
+            features.push("sqlx");
+        }
+        let driver = match &self.connection {
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(..) => "sqlx",
+            #[cfg(feature = "rusqlite")]
+            DbConnection::Rusqlite(..) => "rusqlite",
+        };
+        let backend = match self.connection.kind() {
+            DbKind::Sqlite => "SQLite",
+            DbKind::Postgres => "PostgreSQL",
+        };
+        diagnoses.push(Diagnosis::pass(
+            "features",
+            &format!(
+                "Built with {}; connected to {backend} using {driver}",
+                features.join(", ")
+            ),
+        ));
 
-        // Add an entry corresponding to the table being loaded to the table table:
-        if force {
-            // Delete any messages associated with the table and then delete the table:
-            self.delete_message(table_name, None, None, None, None)
-                .await
-                .expect("Error deleting messages");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        if !Table::_table_exists("table", &mut tx)? {
+            diagnoses.push(Diagnosis::fail(
+                "schema",
+                "The meta tables are missing",
+                "Create them with `rltbl init`",
+            ));
+            tx.commit()?;
+            return Ok(diagnoses);
+        }
+
+        // Schema version:
+        let version = Relatable::_get_schema_version(&mut tx)?;
+        if version == sql::SCHEMA_VERSION {
+            diagnoses.push(Diagnosis::pass(
+                "schema",
+                &format!("Schema version is {version}"),
+            ));
+        } else if version < sql::SCHEMA_VERSION {
+            diagnoses.push(Diagnosis::fail(
+                "schema",
+                &format!(
+                    "Schema version is {version}, but this release of rltbl expects {}",
+                    sql::SCHEMA_VERSION
+                ),
+                "Migrate the database with `rltbl init`",
+            ));
+        } else {
+            diagnoses.push(Diagnosis::fail(
+                "schema",
+                &format!(
+                    "Schema version is {version}, which is newer than this release of rltbl \
+                     supports ({})",
+                    sql::SCHEMA_VERSION
+                ),
+                "Upgrade rltbl",
+            ));
+        }
+
+        // Indexes:
+        let mut missing_indexes = vec![];
+        for (index, table, _) in sql::META_TABLE_INDEXES {
+            if Table::_table_exists(table, &mut tx)? && !Table::_index_exists(index, &mut tx)? {
+                missing_indexes.push(format!("'{index}'"));
+            }
+        }
+        match missing_indexes.is_empty() {
+            true => diagnoses.push(Diagnosis::pass("indexes", "All indexes are present")),
+            false => diagnoses.push(Diagnosis::fail(
+                "indexes",
+                &format!("Missing index(es): {}", missing_indexes.join(", ")),
+                "Create them with `rltbl maintain`",
+            )),
+        };
 
+        // Interrupted loads, which can only have been recorded since version 8:
+        if version >= 8 {
             let sql = format!(
-                r#"DELETE FROM "table" WHERE "table" = {sql_param}"#,
-                sql_param = SqlParam::new(&db_kind).next(),
+                r#"SELECT "table", "loaded" FROM "table" WHERE "loaded" {is_not} NULL"#,
+                is_not = sql::is_not_clause(&tx.kind())
             );
-            let params = json!([table_name]);
-            self.connection
-                .query(&sql, Some(&params))
-                .await
-                .expect("Error deleting from table table");
+            let interrupted = tx
+                .query(&sql, None)?
+                .iter()
+                .map(|row| {
+                    Ok(format!(
+                        "'{}' ({} rows)",
+                        row.get_string("table")?,
+                        row.get_unsigned("loaded")?
+                    ))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            match interrupted.is_empty() {
+                true => diagnoses.push(Diagnosis::pass("loads", "All loads were completed")),
+                false => diagnoses.push(Diagnosis::fail(
+                    "loads",
+                    &format!(
+                        "Table(s) were only partly loaded: {}",
+                        interrupted.join(", ")
+                    ),
+                    "Finish loading them with `rltbl load table --resume PATH`, or reload them \
+                     with `rltbl load table --force PATH`",
+                )),
+            };
         }
-        let sql = format!(
-            r#"INSERT INTO "table" ("table", "path") VALUES ({sql_params})"#,
-            sql_params = SqlParam::new(&db_kind).get_as_list(2)
-        );
-        let params = json!([table_name, path]);
-        self.connection
-            .query(&sql, Some(&params))
-            .await
-            .expect("Error inserting to table table");
-        tracing::debug!("Table {table_name} (path: {path}) added to table table");
 
-        // Initialize a new table struct and collect its columns configuration:
-        let table = {
-            let mut table = Table {
-                name: table_name.to_string(),
-                ..Default::default()
-            };
-            let table_columns = Table::get_column_table_columns(table_name, self)
-                .await
-                .expect(&format!("Error getting columns for table '{table_name}'"));
-            for column_name in headers.iter() {
-                let datatype = match table_columns.get(column_name) {
-                    None => Datatype {
-                        name: "text".to_string(),
-                        ..Default::default()
-                    },
-                    Some(col) => col.datatype.clone(),
+        // Data tables and their triggers:
+        let rows = tx.query(r#"SELECT "table" FROM "table""#, None)?;
+        let mut tables = vec![];
+        let mut missing_tables = vec![];
+        let mut missing_triggers = vec![];
+        let mut stale_triggers = vec![];
+        let mut orphans = vec![];
+        let mut wide_tables = vec![];
+        for row in rows {
+            let table = row.get_string("table")?;
+            if !Table::_table_exists(&table, &mut tx)? {
+                missing_tables.push(format!("'{table}'"));
+                continue;
+            }
+            let (columns, meta_columns) = Table::_collect_column_info(&table, &mut tx)?;
+
+            // Tables that are too wide to view quickly, unless they are already in wide mode:
+            if self.column_warning_limit > 0 && columns.len() > self.column_warning_limit {
+                let wide = match Table::_get_wide_mode(&table, &mut tx)? {
+                    Some(wide) => wide,
+                    None => self.wide_column_limit > 0 && columns.len() > self.wide_column_limit,
                 };
-                let column = Column {
-                    name: column_name.to_string(),
-                    table: table_name.to_string(),
-                    datatype_hierarchy: datatype.get_all_ancestors(self).await.expect(&format!(
-                        "Error getting datatype hierarchy for '{}'",
-                        datatype.name
-                    )),
-                    datatype: datatype,
-                    nulltype: table_columns
-                        .get(column_name)
-                        .and_then(|col| col.nulltype.clone()),
-                    structure: table_columns
-                        .get(column_name)
-                        .and_then(|col| col.structure.clone()),
-                    ..Default::default()
+                if !wide {
+                    wide_tables.push(format!("'{table}' ({} columns)", columns.len()));
+                }
+            }
+            let with_triggers = self.change_capture.mode() == ChangeCaptureMode::Trigger;
+            let mut triggers = vec![];
+            if with_triggers && meta_columns.iter().any(|column| column.name == "_order") {
+                triggers.push(format!("{table}_order"));
+            }
+            if meta_columns
+                .iter()
+                .any(|column| column.name == "_change_id")
+            {
+                triggers.push(format!("{table}_change_id"));
+            }
+            for event in ["insert", "update", "delete"] {
+                let trigger = format!("{table}_cache_after_{event}");
+                match self.caching_strategy {
+                    CachingStrategy::Trigger if with_triggers => triggers.push(trigger),
+                    _ if Table::_trigger_exists(&trigger, &mut tx)? => {
+                        stale_triggers.push(format!("'{trigger}'"))
+                    }
+                    _ => (),
                 };
-                table.columns.insert(column_name.to_string(), column);
             }
-            table
-        };
+            for trigger in triggers {
+                if !Table::_trigger_exists(&trigger, &mut tx)? {
+                    missing_triggers.push(format!("'{trigger}'"));
+                }
+            }
 
-        // Generate the SQL statements needed to create the table and execute them:
-        for sql in sql::generate_table_ddl(&table, force, &db_kind, &self.caching_strategy)
-            .expect("Error getting DDL")
-        {
-            self.connection
-                .query(&sql, None)
-                .await
-                .expect("Error creating table");
+            // Messages about rows that no longer exist:
+            if meta_columns.iter().any(|column| column.name == "_id") {
+                let sql = format!(
+                    r#"SELECT COUNT(1) FROM "message"
+                       WHERE "table" = {sql_param}
+                         AND "row" NOT IN (SELECT "_id" FROM "{table}")"#,
+                    sql_param = SqlParam::new(&tx.kind()).next()
+                );
+                let count = match tx.query_value(&sql, Some(&json!([table])))? {
+                    Some(value) => value.as_u64().unwrap_or_default(),
+                    None => 0,
+                };
+                if count > 0 {
+                    orphans.push(format!("{count} for '{table}'"));
+                }
+            }
+            tables.push(table);
+        }
+        match missing_tables.is_empty() {
+            true => diagnoses.push(Diagnosis::pass(
+                "tables",
+                &format!("All {} table(s) in the table table exist", tables.len()),
+            )),
+            false => diagnoses.push(Diagnosis::fail(
+                "tables",
+                &format!(
+                    "Table(s) listed in the table table do not exist: {}",
+                    missing_tables.join(", ")
+                ),
+                "Load the table(s) again with `rltbl load table`, or remove them from the table \
+                 table",
+            )),
+        };
+        if self.column_warning_limit > 0 {
+            match wide_tables.is_empty() {
+                true => diagnoses.push(Diagnosis::pass(
+                    "columns",
+                    &format!(
+                        "No table has more than {} columns outside of wide mode",
+                        self.column_warning_limit
+                    ),
+                )),
+                false => diagnoses.push(Diagnosis::fail(
+                    "columns",
+                    &format!(
+                        "Table(s) have more than {} columns: {}",
+                        self.column_warning_limit,
+                        wide_tables.join(", ")
+                    ),
+                    "Put them in wide mode with `rltbl config set wide TABLE true`",
+                )),
+            };
         }
+        match missing_triggers.is_empty() {
+            true => diagnoses.push(Diagnosis::pass("triggers", "All triggers are present")),
+            false => diagnoses.push(Diagnosis::fail(
+                "triggers",
+                &format!("Missing trigger(s): {}", missing_triggers.join(", ")),
+                "Reload the affected table(s) with `rltbl load table --force`, or, for caching \
+                 triggers, run `rltbl config set caching trigger`",
+            )),
+        };
+        match stale_triggers.is_empty() {
+            true => diagnoses.push(Diagnosis::pass(
+                "caching",
+                "No triggers are left from another caching strategy",
+            )),
+            false => diagnoses.push(Diagnosis::fail(
+                "caching",
+                &format!(
+                    "Caching trigger(s) are left from another caching strategy: {}",
+                    stale_triggers.join(", ")
+                ),
+                &format!(
+                    "Drop them with `rltbl config set caching {}`",
+                    self.caching_strategy
+                ),
+            )),
+        };
 
-        // Insert the data into the table:
-        let mut columns = vec!["_id".to_string(), "_order".to_string()];
-        columns.append(
-            &mut headers
-                .iter()
-                .map(|k| format!(r#"{k}"#))
-                .collect::<Vec<_>>(),
-        );
-        let columns_line = columns
-            .iter()
-            .map(|k| format!(r#""{k}""#))
-            .collect::<Vec<_>>()
-            .join(", ");
-        let mut id: u64 = 1;
-        let mut order = id * NEW_ORDER_MULTIPLIER as u64;
-        let sql_first_part = format!(r#"INSERT INTO "{table_name}" ({columns_line}) VALUES "#);
-        let mut sql_value_parts = vec![];
-        let mut sql_param_gen = SqlParam::new(&self.connection.kind());
-        let mut param_values = vec![];
-        let max_params = match db_kind {
-            DbKind::Sqlite => sql::MAX_PARAMS_SQLITE,
-            DbKind::Postgres => sql::MAX_PARAMS_POSTGRES,
+        match orphans.is_empty() {
+            true => diagnoses.push(Diagnosis::pass(
+                "orphans",
+                "No messages refer to rows that do not exist",
+            )),
+            false => diagnoses.push(Diagnosis::fail(
+                "orphans",
+                &format!(
+                    "Found messages about rows that do not exist: {}",
+                    orphans.join(", ")
+                ),
+                "Delete them with `rltbl delete message TABLE ROW`",
+            )),
         };
-        while let Some(row) = records.next() {
-            let row = row.expect("Error processing row");
-            // We add 2 here because of _id and _order:
-            if (param_values.len() + row.len() + 2) >= max_params {
-                let sql = format!(
-                    "{sql_first_part} {sql_value_part}",
-                    sql_value_part = sql_value_parts.join(", ")
-                );
-                let values_so_far = json!(param_values);
-                self.connection
-                    .query(&sql, Some(&values_so_far))
-                    .await
-                    .expect("Error inserting to table");
-                tracing::info!(
-                    "{num_rows} rows loaded to table {table_name}",
-                    num_rows = id - 1
-                );
-                param_values.clear();
-                sql_value_parts.clear();
-                sql_param_gen.reset()
+        tx.commit()?;
+
+        // Views. These are read outside of the transaction since, in PostgreSQL, a failed
+        // statement aborts the transaction that it belongs to:
+        let mut broken_views = vec![];
+        for table in &tables {
+            for view_type in ["default", "text"] {
+                let view = format!("{table}_{view_type}_view");
+                let table = Table {
+                    name: table.to_string(),
+                    ..Default::default()
+                };
+                if table.view_exists(view_type, self).await?
+                    && self
+                        .connection
+                        .query(&format!(r#"SELECT * FROM "{view}" LIMIT 1"#), None)
+                        .await
+                        .is_err()
+                {
+                    broken_views.push(format!("'{view}'"));
+                }
             }
+        }
+        match broken_views.is_empty() {
+            true => diagnoses.push(Diagnosis::pass("views", "All views can be read")),
+            false => diagnoses.push(Diagnosis::fail(
+                "views",
+                &format!("View(s) cannot be read: {}", broken_views.join(", ")),
+                "Drop the view(s); they are recreated the next time their table is read",
+            )),
+        };
 
-            let mut sql_params = vec![];
-            param_values.push(json!(id));
-            sql_params.push(sql_param_gen.next());
-            param_values.push(json!(order));
-            sql_params.push(sql_param_gen.next());
-            let sql_params = {
-                for (i, value) in row.iter().enumerate() {
-                    let (column, nulltype) = {
-                        // We add 2 here because of _id and _order:
-                        let column = match columns.get(i + 2) {
-                            Some(column) => column,
-                            None => panic!("Unable to retrieve column {}", i + 2),
-                        };
-                        let nulltype = table
-                            .columns
-                            .get(column)
-                            .expect(&format!("Column '{column}' not found"))
-                            .nulltype
-                            .to_owned();
-                        (column, nulltype)
-                    };
-                    match nulltype {
-                        Some(nulltype) if nulltype.name == "empty" && value == "" => {
-                            sql_params.push("NULL".to_string());
-                        }
-                        _ => {
-                            if let Some(nulltype) = nulltype {
-                                if nulltype.name != "empty" {
-                                    tracing::warn!("Nulltype '{}' not supported", nulltype.name);
-                                }
-                            }
-                            // Use the value to create a cell:
-                            let mut cell = {
-                                let value = match serde_json::from_str::<JsonValue>(value) {
-                                    Ok(JsonValue::Number(num)) => JsonValue::Number(num),
-                                    _ => json!(value),
-                                };
-                                let value = JsonRow::nullify_value(&table, column, &value);
-                                Cell {
-                                    text: sql::json_to_string(&value),
-                                    value: value,
-                                    ..Default::default()
-                                }
-                            };
+        Ok(diagnoses)
+    }
 
-                            // Validate the cell and add any messages to the message table:
-                            if self.validation_level != ValidationLevel::None {
-                                cell.validate_sql_type(&table.get_config_for_column(column))
-                                    .expect("Error validating cell");
-                                for message in cell.messages.iter() {
-                                    let (msg_id, msg) = self
-                                        .add_message(
-                                            "rltbl",
-                                            &table.name,
-                                            id,
-                                            column,
-                                            &cell.value,
-                                            &message.level,
-                                            &message.rule,
-                                            &message.message,
-                                        )
-                                        .await
-                                        .expect("Error adding message");
-                                    tracing::debug!("Added message (ID {msg_id}): {msg:?}");
-                                }
-                            }
+    /// Validate the configuration in the meta tables: check that the tables listed in the table
+    /// table exist and that the tables in the database are listed there, that the datatypes,
+    /// nulltypes, and structures of the columns in the column table refer to datatypes, tables,
+    /// and columns that exist, that their foreign keys are valid, that no datatype is its own
+    /// ancestor, and that no column is configured more than once. The problems found are returned and, where the meta table that
+    /// they concern is itself listed in the table table (as it is when it has been loaded using
+    /// [Relatable::load_table()]), recorded as messages with rules of the form `config:...`,
+    /// replacing any recorded by a previous check.
+    pub async fn check_config(&self) -> Result<Vec<ConfigProblem>> {
+        tracing::trace!("Relatable::check_config()");
+        let mut problems = vec![];
+        let problem = |table: &str,
+                       row: Option<u64>,
+                       column: &str,
+                       value: &str,
+                       level: &str,
+                       rule: &str,
+                       message: String| ConfigProblem {
+            table: table.to_string(),
+            row,
+            column: column.to_string(),
+            value: value.to_string(),
+            level: level.to_string(),
+            rule: format!("config:{rule}"),
+            message,
+        };
 
-                            // Add the parameter for the value to the SQL insert statement:
-                            if cell.has_sql_type_error() || cell.value == JsonValue::Null {
-                                sql_params.push("NULL".to_string());
-                            } else {
-                                sql_params.push(sql_param_gen.next());
-                                param_values.push(cell.value);
-                            }
-                        }
-                    };
-                }
-                sql_params.join(", ")
-            };
-            // Add two extra SQL_PARAM for _id and _order:
-            sql_value_parts.push(format!("({sql_params})"));
-            id += 1;
-            order += NEW_ORDER_MULTIPLIER as u64;
+        // The tables listed in the table table, and those that exist in the database:
+        let mut listed = HashSet::new();
+        let sql = r#"SELECT "_id", "table" FROM "table" ORDER BY "_order""#;
+        for row in self.connection.query(sql, None).await? {
+            let table = row.get_string("table")?;
+            if !Table::table_exists(&table, self).await? {
+                problems.push(problem(
+                    "table",
+                    Some(row.get_unsigned("_id")?),
+                    "table",
+                    &table,
+                    "error",
+                    "missing-table",
+                    format!("Table '{table}' is listed in the table table but does not exist"),
+                ));
+            }
+            listed.insert(table);
         }
-        if param_values.len() > 0 {
-            let sql = format!(
-                "{sql_first_part} {sql_value_part}",
-                sql_value_part = sql_value_parts.join(", ")
-            );
-            let param_values = json!(param_values);
-            self.connection
-                .query(&sql, Some(&param_values))
-                .await
-                .expect(&format!("Error inserting to {table_name}"));
-            tracing::info!(
-                "{num_rows} rows loaded to table {table_name}",
-                num_rows = id - 1
-            );
+        let sql = match self.connection.kind() {
+            DbKind::Sqlite => {
+                r#"SELECT "name" AS "table" FROM "sqlite_master"
+                   WHERE "type" = 'table' AND "name" NOT LIKE 'sqlite_%'
+                   ORDER BY "name""#
+            }
+            DbKind::Postgres => {
+                r#"SELECT "table_name" AS "table" FROM "information_schema"."tables"
+                   WHERE "table_schema" = CURRENT_SCHEMA() AND "table_type" = 'BASE TABLE'
+                   ORDER BY "table_name""#
+            }
+        };
+        // The search indexes of the listed tables, and, in SQLite, their shadow tables:
+        let is_search_index = |table: &str| {
+            listed.iter().any(|listed| {
+                let index = sql::search_index_name(listed);
+                table == index || table.starts_with(&format!("{index}_"))
+            })
+        };
+        for row in self.connection.query(sql, None).await? {
+            let table = row.get_string("table")?;
+            if !listed.contains(&table)
+                && !sql::META_TABLES.contains(&table.as_str())
+                && !sql::CONFIG_TABLES.contains(&table.as_str())
+                && !is_search_index(&table)
+            {
+                problems.push(problem(
+                    "table",
+                    None,
+                    "table",
+                    &table,
+                    "warning",
+                    "unlisted-table",
+                    format!("Table '{table}' exists but is not listed in the table table"),
+                ));
+            }
         }
 
-        if self.validation_level == ValidationLevel::Full {
-            self.validate_table(&table)
-                .await
-                .expect("Error validating table");
-            let dependent_tables = table
-                .get_dependent_tables(None, &self)
-                .await
-                .expect("Error getting dependent tables");
-            for table in &dependent_tables {
-                tracing::debug!("Validating dependent table '{}'", table.name);
-                self.validate_structure_for_table(&table)
-                    .await
-                    .expect("Error validating table");
+        // The datatypes, and their parents:
+        let mut parents = Datatype::builtin_datatypes()
+            .into_iter()
+            .map(|(name, datatype)| (name, datatype.parent))
+            .collect::<HashMap<_, _>>();
+        let mut datatype_rows = vec![];
+        if Table::table_exists("datatype", self).await? {
+            let sql = r#"SELECT "_id", "datatype", "parent" FROM "datatype" ORDER BY "_order""#;
+            for row in self.connection.query(sql, None).await? {
+                let id = row.get_unsigned("_id")?;
+                let name = row.get_string("datatype")?;
+                let parent = row.get_string("parent").unwrap_or_default();
+                parents.entry(name.clone()).or_insert(parent.clone());
+                datatype_rows.push((id, name, parent));
+            }
+        }
+        for (id, name, parent) in &datatype_rows {
+            if parent.is_empty() {
+                continue;
+            }
+            if !parents.contains_key(parent) {
+                problems.push(problem(
+                    "datatype",
+                    Some(*id),
+                    "parent",
+                    parent,
+                    "error",
+                    "unknown-datatype",
+                    format!("The parent of datatype '{name}' is not a datatype: '{parent}'"),
+                ));
+                continue;
+            }
+            let mut ancestor = parent;
+            let mut seen = HashSet::new();
+            while !ancestor.is_empty() && seen.insert(ancestor) {
+                if ancestor == name {
+                    problems.push(problem(
+                        "datatype",
+                        Some(*id),
+                        "parent",
+                        parent,
+                        "error",
+                        "datatype-cycle",
+                        format!("Datatype '{name}' is its own ancestor"),
+                    ));
+                    break;
+                }
+                ancestor = match parents.get(ancestor) {
+                    Some(ancestor) => ancestor,
+                    None => break,
+                };
             }
         }
 
-        self.commit_to_git().await.expect("Error committing to git");
-    }
+        // The columns:
+        if Table::table_exists("column", self).await? {
+            let mut configured: HashMap<(String, String), u64> = HashMap::new();
+            let sql = r#"SELECT * FROM "column" ORDER BY "_order""#;
+            for row in self.connection.query(sql, None).await? {
+                let id = row.get_unsigned("_id")?;
+                let table = row.get_string("table")?;
+                let column = row.get_string("column")?;
+                if let Some(first) = configured.get(&(table.clone(), column.clone())) {
+                    problems.push(problem(
+                        "column",
+                        Some(id),
+                        "column",
+                        &column,
+                        "error",
+                        "duplicate-column",
+                        format!(
+                            "Column '{column}' of table '{table}' is already configured in \
+                             row {first}"
+                        ),
+                    ));
+                } else {
+                    configured.insert((table.clone(), column.clone()), id);
+                }
 
-    /// Save all of the tables that have entries in the table table to the path indicated for each
-    /// table there, unless `save_dir` has been given, in which case save them all there instead.
-    pub async fn save_all(&self, save_dir: Option<&str>) -> Result<()> {
-        tracing::trace!("Relatable::save_all({save_dir:?})");
-        let sql = format!(
-            r#"SELECT "table", "path" FROM "table" WHERE "path" {is_not} NULL"#,
-            is_not = sql::is_not_clause(&self.connection.kind())
-        );
-        let table_rows = self.connection.query(&sql, None).await?;
-        for table_row in table_rows {
-            let table_name = table_row.get_string("table")?;
-            let mut table = Table::get_table(&table_name, self).await?;
-            table.set_view(self, "text").await?;
+                for attribute in ["datatype", "nulltype"] {
+                    let datatype = row.get_string(attribute).unwrap_or_default();
+                    if !datatype.is_empty() && !parents.contains_key(&datatype) {
+                        problems.push(problem(
+                            "column",
+                            Some(id),
+                            attribute,
+                            &datatype,
+                            "error",
+                            &format!("unknown-{attribute}"),
+                            format!(
+                                "The {attribute} of column '{column}' of table '{table}' is not \
+                                 a datatype: '{datatype}'"
+                            ),
+                        ));
+                    }
+                }
 
-            let path = match save_dir {
-                Some(save_dir) => format!("{save_dir}/{table_name}.tsv"),
-                None => table_row.get_string("path")?,
-            };
-            let mut writer = WriterBuilder::new()
-                .delimiter(b'\t')
-                .quote_style(QuoteStyle::Never)
-                .from_path(path)?;
-            let header_row = self
-                .fetch_columns(&table_name)
-                .await?
-                .iter()
-                .map(|c| c.name.to_string())
-                .collect::<Vec<_>>();
-            writer.write_record(header_row.clone())?;
+                let locale = row.get_string("locale").unwrap_or_default();
+                if !locale.is_empty() {
+                    if let Err(err) = Locale::from_str(&locale) {
+                        problems.push(problem(
+                            "column",
+                            Some(id),
+                            "locale",
+                            &locale,
+                            "error",
+                            "invalid-locale",
+                            format!(
+                                "The locale of column '{column}' of table '{table}' is invalid: \
+                                 {err}"
+                            ),
+                        ));
+                    }
+                }
 
-            let sql = format!(
-                r#"SELECT {columns} FROM "{table_name}_text_view" ORDER BY "_order""#,
-                columns = header_row
-                    .iter()
-                    .map(|c| format!(r#""{c}""#))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            );
-            let data_rows = self.connection.query(&sql, None).await?;
-            for data_row in data_rows {
-                let values = {
-                    let mut str_values = vec![];
-                    for (column, value) in data_row.content.iter() {
-                        match value {
-                            JsonValue::String(s) => str_values.push(s.to_string()),
-                            JsonValue::Number(n) => str_values.push(n.to_string()),
-                            JsonValue::Null => {
-                                match &table
-                                    .columns
-                                    .get(column)
-                                    .ok_or(RelatableError::InputError(format!(
-                                        "Column '{column}' not found"
-                                    )))?
-                                    .nulltype
-                                {
-                                    // Note that the behaviour for the 'empty' nulltype happens
-                                    // to be the same as that for no nulltype, but in general
-                                    // that won't be true for every nulltype.
-                                    Some(nulltype) if nulltype.name == "empty" => {
-                                        str_values.push("".to_string());
-                                    }
-                                    Some(unsup) => {
-                                        tracing::warn!("Unsupported nulltype: '{}'", unsup.name);
-                                        str_values.push("".to_string());
-                                    }
-                                    None => {
-                                        str_values.push("".to_string());
-                                    }
-                                };
-                            }
-                            _ => {
-                                return Err(RelatableError::DataError(format!(
-                                    "Value {value} is not a string, number or NULL"
-                                ))
-                                .into());
-                            }
+                let collation = row.get_string("collation").unwrap_or_default();
+                if !collation.is_empty() {
+                    if let Err(err) = Collation::from_str(&collation) {
+                        problems.push(problem(
+                            "column",
+                            Some(id),
+                            "collation",
+                            &collation,
+                            "error",
+                            "invalid-collation",
+                            format!(
+                                "The collation of column '{column}' of table '{table}' is \
+                                 invalid: {err}"
+                            ),
+                        ));
+                    }
+                }
+
+                let transform = row.get_string("transform").unwrap_or_default();
+                if let Err(err) = Transform::parse_list(&transform) {
+                    problems.push(problem(
+                        "column",
+                        Some(id),
+                        "transform",
+                        &transform,
+                        "error",
+                        "invalid-transform",
+                        format!(
+                            "The transform of column '{column}' of table '{table}' is invalid: \
+                             {err}"
+                        ),
+                    ));
+                }
+
+                let structure = row.get_string("structure").unwrap_or_default();
+                let foreign_key = row.get_string("foreign_key").unwrap_or_default();
+                if !foreign_key.is_empty() {
+                    let message = match ForeignKey::from_str(&foreign_key) {
+                        Err(err) => Some(format!("{err}")),
+                        Ok(_) if !structure.starts_with("from(") => {
+                            Some("A foreign key requires a from() structure".to_string())
                         }
+                        Ok(_) => None,
+                    };
+                    if let Some(message) = message {
+                        problems.push(problem(
+                            "column",
+                            Some(id),
+                            "foreign_key",
+                            &foreign_key,
+                            "error",
+                            "invalid-foreign-key",
+                            format!(
+                                "The foreign key of column '{column}' of table '{table}' is \
+                                 invalid: {message}"
+                            ),
+                        ));
+                    }
+                }
+                if structure.is_empty() {
+                    continue;
+                }
+                match Structure::from_str(&structure) {
+                    Err(_) => problems.push(problem(
+                        "column",
+                        Some(id),
+                        "structure",
+                        &structure,
+                        "error",
+                        "invalid-structure",
+                        format!(
+                            "The structure of column '{column}' of table '{table}' is invalid: \
+                             '{structure}'"
+                        ),
+                    )),
+                    Ok(Structure::From(structure_table, structure_column)) => {
+                        let structure_table = structure_table.unwrap_or(table.clone());
+                        let known_column = match Table::table_exists(&structure_table, self).await?
+                        {
+                            false => None,
+                            true => Some(
+                                Table::collect_column_info(&structure_table, self)
+                                    .await?
+                                    .0
+                                    .iter()
+                                    .any(|column| column.name == structure_column),
+                            ),
+                        };
+                        let message = match known_column {
+                            None => format!("Table '{structure_table}' does not exist"),
+                            Some(false) => format!(
+                                "Table '{structure_table}' has no column '{structure_column}'"
+                            ),
+                            Some(true) => continue,
+                        };
+                        problems.push(problem(
+                            "column",
+                            Some(id),
+                            "structure",
+                            &structure,
+                            "error",
+                            "unknown-structure",
+                            format!(
+                                "The structure of column '{column}' of table '{table}' refers \
+                                 to a column that does not exist: {message}"
+                            ),
+                        ));
                     }
-                    str_values
+                    Ok(_) => (),
                 };
-                writer.write_record(values)?;
             }
         }
 
-        Ok(())
+        // Record the problems as messages, replacing those recorded by the previous check:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        for table in ["table", "column", "datatype"] {
+            if listed.contains(table) {
+                self._delete_message(&mut tx, table, None, None, Some("config:%"), None)?;
+            }
+        }
+        for problem in &problems {
+            if let (Some(row), true) = (problem.row, listed.contains(&problem.table)) {
+                Relatable::_add_message(
+                    "rltbl",
+                    &problem.table,
+                    &row,
+                    &problem.column,
+                    &json!(problem.value),
+                    &problem.level,
+                    &problem.rule,
+                    &problem.message,
+                    &mut tx,
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(problems)
     }
 
-    /// Save all of the tables and commit the changes to git.
-    pub async fn commit_to_git(&self) -> Result<()> {
-        tracing::trace!("Relatable::commit_to_git()");
-        let author = match std::env::var("RLTBL_GIT_AUTHOR") {
-            Err(err) => match err {
-                std::env::VarError::NotPresent => {
-                    tracing::debug!("Not committing to git because RLTBL_GIT_AUTHOR not defined");
-                    return Ok(());
-                }
-                _ => {
-                    return Err(RelatableError::InputError(format!(
-                        "Could not read from the environment: {err}"
-                    ))
-                    .into())
-                }
-            },
-            Ok(author) => author,
-        };
-        tracing::info!("Committing to git on behalf of RLTBL_GIT_AUTHOR: '{author}'");
+    // Drop all of the data tables and metatables in the database
+    pub async fn drop_database(&self) -> Result<()> {
+        tracing::trace!("Relatable::drop_database({self:?})");
+        self.drop_data_tables().await?;
+        self.drop_meta_tables().await?;
+        Ok(())
+    }
 
-        // Save all the tables:
-        self.save_all(None).await?;
+    /// Render this relatable instance in HTML according to the given template and context
+    pub fn render<T: Serialize>(&self, template: &str, context: T) -> Result<String> {
+        tracing::trace!("Relatable::render({template:?}, context)");
+        // TODO: Optionally we should set up the environment once and store it,
+        // but during development it's very convenient to rebuild every time.
+        let mut env = Environment::new();
 
-        // Get the git status:
-        let status = git::get_status()?;
-        if status.behind != 0 {
-            return Err(RelatableError::GitError(
-                "Refusing to commit to a local repository that is behind the remote".to_string(),
-            )
-            .into());
+        // Load default template strings at compile time.
+        let templates = IndexMap::from([
+            ("page.html", include_str!("templates/page.html")),
+            ("table.html", include_str!("templates/table.html")),
+            ("row_menu.html", include_str!("templates/row_menu.html")),
+            (
+                "column_menu.html",
+                include_str!("templates/column_menu.html"),
+            ),
+            ("cell_menu.html", include_str!("templates/cell_menu.html")),
+            ("blame.html", include_str!("templates/blame.html")),
+        ]);
+
+        // Load templates dynamically if src/templates/ exists,
+        // otherwise use strings from compile time.
+        // TODO: This should be a configuration option.
+        let dir = std::env::var("RLTBL_TEMPLATES").unwrap_or("src/templates/".to_string());
+        if FilePath::new(&dir).is_dir() {
+            env.set_loader(path_loader(dir));
+        };
+        for (name, content) in templates {
+            match env.get_template(name) {
+                Ok(_) => (),
+                Err(_) => env.add_template(name, content).unwrap(),
+            }
         }
 
-        // Possibly only amend the last commit, if it is by the same author and performed
-        // on the same day:
-        let (last_commit_author, days_ago) = git::get_last_commit_info()?;
-        let is_amendment = (last_commit_author == author) && (days_ago < 1);
+        env.get_template(template)?
+            .render(context)
+            .map_err(|e| e.into())
+    }
 
-        // Stage any modified table files that have a path in the table table:
-        let sql = format!(
-            r#"SELECT "path" FROM "table" WHERE "path" {is_not} NULL"#,
-            is_not = sql::is_not_clause(&self.connection.kind()),
-        );
-        let paths = self
+    /// Use the given [Select] to fetch data from the database.
+    pub async fn fetch(&self, select: &Select) -> Result<ResultSet> {
+        tracing::trace!("Relatable::fetch({select:?})");
+
+        // Get the table and columns information and use the given select to set the table's view:
+        let mut table = Table::get_table(select.table_name.as_str(), self).await?;
+        if select.view_name == format!("{}_default_view", table.name) || select.view_name == "" {
+            table.set_view(self, "default").await?;
+        } else if select.view_name == format!("{}_text_view", table.name) {
+            table.set_view(self, "text").await?;
+        } else {
+            tracing::warn!(
+                "Unsupported view name: '{}'. Falling back to default view",
+                select.view_name
+            );
+            table.set_view(self, "default").await?;
+        }
+        let mut columns = table.columns.values().cloned().collect::<Vec<_>>();
+
+        // Use the table's materialized _change_id column, if it has one:
+        let mut select = select.clone();
+        select.materialized_change_id = table.has_change_id;
+
+        // Only read the partitions of a partitioned table that can match the select's filters:
+        select.partitions = table.partitions.clone();
+
+        // Archived rows can only be included when some of the table's rows have been archived:
+        select.include_archived &= table.has_archive;
+
+        // Empty values are those allowed by the nulltypes of the columns, as well as NULL:
+        select.set_nulltypes(self).await;
+
+        // A random sample of a large table is chosen, in PostgreSQL, from enough of its blocks to
+        // hold ten times as many rows as are wanted, rather than from all of its rows:
+        if let (Some(sample), DbKind::Postgres) = (select.sample, self.connection.kind()) {
+            if let Some(rows) = self.estimate_count(&select).await? {
+                let percent = 1000.0 * sample as f64 / rows as f64;
+                if rows > TABLESAMPLE_MIN_ROWS && percent < 100.0 {
+                    select.sample_percent = Some(percent);
+                }
+            }
+        }
+
+        // Order the columns that have a collation according to it:
+        select.collations = table
+            .columns
+            .values()
+            .filter_map(|column| {
+                column
+                    .collation
+                    .map(|collation| (column.name.clone(), collation))
+            })
+            .collect();
+
+        // Look up the labels of the values of columns that refer to tables with a label column:
+        if select.display {
+            select.display_columns = self.get_display_columns(&table).await?;
+        }
+
+        // Fetch the data
+        let (statement, parameters) = select.to_sql_cached(&self.connection.kind())?;
+        let json_params = json!(parameters);
+        let (json_rows, types) = match self
             .connection
-            .query(&sql, None)
-            .await?
-            .iter()
-            .map(|row| row.get_string("path").expect("No 'path' found"))
-            .collect::<Vec<_>>();
-        git::add(&paths)?;
+            .query_with_types_and_timeout(&statement, Some(&json_params), select.timeout)
+            .await
+        {
+            Ok(result) => result,
+            // Explain the error in terms of the select, rather than its SQL, if possible:
+            Err(error) => {
+                if let Some(RelatableError::TimeoutError(_)) = error.downcast_ref() {
+                    return Err(error);
+                }
+                let problems = select.validate(self).await.unwrap_or_default();
+                if problems.is_empty() {
+                    return Err(error);
+                }
+                return Err(RelatableError::InputError(
+                    problems
+                        .iter()
+                        .map(|problem| problem.message.to_string())
+                        .collect::<Vec<_>>()
+                        .join("; "),
+                )
+                .into());
+            }
+        };
+        let count = json_rows.len();
+        tracing::info!("Fetched {count} rows");
 
-        // Finally, commit to git:
-        git::commit("commit by rltbl", &author, is_amendment)?;
-        Ok(())
+        // Filter out the table's columns that do not occur in the select:
+        if select.select.len() > 0 {
+            columns = columns
+                .iter()
+                .filter(|column| {
+                    select.select.iter().any(|sel| match sel {
+                        SelectField::Column {
+                            table: select_table,
+                            column: select_column,
+                            ..
+                        } => {
+                            *select_column == column.name
+                                && (select_table == "" || *select_table == table.name)
+                        }
+                        SelectField::Expression { alias, .. } => *alias == column.name,
+                        SelectField::Aggregate { .. } | SelectField::Window { .. } => false,
+                    })
+                })
+                .map(|c| c.clone())
+                .collect();
+        }
+
+        // Return the data. A select that pages backwards fetches its rows in descending order:
+        let mut rows: Vec<Row> = json_rows.clone().vec_into();
+        if select.before.is_some() {
+            rows.reverse();
+        }
+        let (total, estimated) = match select.estimate_count {
+            true => match self.estimate_count(&select).await? {
+                Some(total) => (total, true),
+                None => (self.count(&select).await?, false),
+            },
+            false => (self.count(&select).await?, false),
+        };
+
+        // When paging by cursor, the number of rows that precede the page must be counted:
+        let skipped = match (rows.first(), &select.after, &select.before) {
+            _ if select.sample.is_some() => 0,
+            (_, None, None) => select.offset as u64,
+            (Some(row), _, _) => {
+                self.count(&select.clone().lt("_order", &row.order)?)
+                    .await?
+            }
+            (None, Some(after), _) => {
+                self.count(&select.clone().lte("_order", &after.order)?)
+                    .await?
+            }
+            (None, None, Some(before)) => {
+                self.count(&select.clone().lt("_order", &before.order)?)
+                    .await?
+            }
+        };
+
+        // The cursors for the pages before and after this one, if there are any:
+        let (mut previous, mut next) = (None, None);
+        if select.has_default_order() {
+            if let (Some(first), Some(last)) = (rows.first(), rows.last()) {
+                if skipped > 0 && first.id > 0 {
+                    previous = Some(PageCursor::from_row(first).to_token());
+                }
+                if skipped + (count as u64) < total && last.id > 0 {
+                    next = Some(PageCursor::from_row(last).to_token());
+                }
+            }
+        }
+
+        // An estimate may be out of date, but there are at least as many rows as were fetched:
+        let total = match estimated {
+            true => total.max(skipped + count as u64),
+            false => total,
+        };
+
+        let totals = match select.totals {
+            true => self.totals(&select).await?,
+            false => IndexMap::new(),
+        };
+
+        Ok(ResultSet {
+            select: select.clone(),
+            statement,
+            parameters,
+            range: Range {
+                count,
+                total,
+                start: skipped + 1,
+                end: skipped + count as u64,
+                previous,
+                next,
+                estimated,
+                limited: select.limited,
+            },
+            table,
+            columns,
+            types,
+            rows,
+            totals,
+        })
     }
 
-    /// Get the details of the last change made by the user from the change table.
-    fn _get_last_change_for_user(
-        &self,
-        tx: &mut DbTransaction<'_>,
-        user: &str,
-        action: &ChangeAction,
-    ) -> Result<Option<(u64, ChangeSet)>> {
-        tracing::trace!("Relatable::_get_last_change_for_user(tx, {user:?}, {action:?})");
-        let mut sql_param = SqlParam::new(&tx.kind());
-        let sql = format!(
-            r#"SELECT "change_id", "user", "table", "description", "content"
-               FROM "change"
-               WHERE "user" = {sql_param_1} AND "action" = {sql_param_2}
-               ORDER BY "change_id" DESC LIMIT 1"#,
-            sql_param_1 = sql_param.next(),
-            sql_param_2 = sql_param.next(),
-        );
-        let params = json!([user, format!("{action}")]);
-        let records = tx.query(&sql, Some(&params))?;
-        match records.len() {
-            0 => Ok(None),
-            _ => {
-                let change_id = records[0].get_unsigned("change_id")?;
-                let user = records[0].get_string("user")?;
-                let table = records[0].get_string("table")?;
-                let description = records[0].get_string("description")?;
-                let content = records[0].get_string("content")?;
-                let changes = Change::many_from_str(&content)?;
-                Ok(Some((
-                    change_id,
-                    ChangeSet {
-                        action: *action,
-                        table: table,
-                        user: user,
-                        description: description,
-                        changes: changes,
-                    },
-                )))
+    /// Get the columns of the given table that have a `from()` structure referring to a table that
+    /// has a `label` column, whose labels can be displayed in place of their values.
+    pub async fn get_display_columns(&self, table: &Table) -> Result<Vec<DisplayColumn>> {
+        tracing::trace!("Relatable::get_display_columns({})", table.name);
+        let mut display_columns = vec![];
+        for column in table.columns.values() {
+            let Some(Structure::From(key_table, key_column)) = &column.structure else {
+                continue;
+            };
+            let key_table = key_table.as_deref().unwrap_or(&table.name);
+            let has_label = match key_table == table.name {
+                true => table.columns.contains_key("label"),
+                false => match Table::get_table(key_table, self).await {
+                    Ok(key_table) => key_table.columns.contains_key("label"),
+                    Err(_) => false,
+                },
+            };
+            if has_label {
+                display_columns.push(DisplayColumn {
+                    column: column.name.clone(),
+                    table: key_table.to_string(),
+                    key_column: key_column.clone(),
+                    label_column: "label".to_string(),
+                });
             }
         }
+        Ok(display_columns)
     }
 
-    /// Record the given [ChangeSet] to the change and history tables.
-    pub fn record_changeset(
-        &self,
-        changeset: &ChangeSet,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!("Relatable::record_changeset({changeset:?}, tx)");
-        let user = changeset.user.clone();
-        let action = changeset.action.to_string();
-        let table = changeset.table.clone();
-        let description = changeset.description.clone();
+    /// Use the given [Select] to fetch data from the database, as [Relatable::fetch()] does, but
+    /// reuse the result that was cached for the request with the given (normalized) URL, in
+    /// accordance with the caching strategy, as long as none of the tables that it reads from has
+    /// changed since. Entries are removed from the cache in the same way as query results are,
+    /// and also when messages about one of their tables are added or deleted (see
+    /// [Relatable::_after_message_change()]), so that a result is never reused once one of its
+    /// tables has a new change or message.
+    pub async fn fetch_cached(&self, select: &Select, url: &str) -> Result<ResultSet> {
+        tracing::trace!("Relatable::fetch_cached({select:?}, {url})");
+        // A random sample is different every time, as are the rows from a relative date on:
+        if select.sample.is_some() || select.get_all_filters().iter().any(|f| f.is_relative()) {
+            return self.fetch(select).await;
+        }
+        let tables = select.get_tables().into_iter().collect::<Vec<_>>();
+        // The future is boxed, since fetching a result is deeply nested, and the stack of the
+        // caller would otherwise have to hold all of it:
+        let compute = Box::pin(async {
+            let result = to_value(self.fetch(select).await?)?;
+            Ok(vec![JsonRow {
+                content: JsonMap::from_iter([("result".to_string(), result)]),
+            }])
+        });
+        let json_rows = Box::pin(self.connection.cache_with(
+            &format!("GET {url}"),
+            None,
+            &tables,
+            &self.caching_strategy,
+            compute,
+        ))
+        .await?;
+        match json_rows.first() {
+            Some(json_row) => Ok(serde_json::from_value(json_row.get_value("result")?)?),
+            None => Err(RelatableError::DataError(format!("No cached result for {url}")).into()),
+        }
+    }
 
-        // Begin by getting the current last change_id for this user, which we may need to look
-        // up previous values of the row's columns in the history table later:
-        let old_change_id = match &changeset.action {
-            ChangeAction::Undo => {
-                let (change_id, _) = self
-                    ._get_last_change_for_user(tx, &changeset.user, &ChangeAction::Do)?
-                    .ok_or(RelatableError::DataError(
-                        "No action for user found".to_string(),
-                    ))?;
-                Some(change_id)
+    /// Use the given [Select] to fetch data from the database.
+    pub async fn fetch_rows(&self, select: &Select) -> Result<Vec<JsonRow>> {
+        tracing::trace!("Relatable::fetch_rows({select:?})");
+        let (statement, params) = select.to_sql_cached(&self.connection.kind())?;
+        let params = json!(params);
+        self.connection
+            .query_with_timeout(&statement, Some(&params), select.timeout)
+            .await
+    }
+
+    /// Use the given [Select] to fetch data from the database, deserializing each of the rows into
+    /// a value of the given type, e.g., a struct with a field for each of the columns that are of
+    /// interest (see [JsonRow::deserialize()]).
+    pub async fn fetch_as<T: DeserializeOwned>(&self, select: &Select) -> Result<Vec<T>> {
+        tracing::trace!("Relatable::fetch_as({select:?})");
+        self.fetch_rows(select)
+            .await?
+            .iter()
+            .map(|row| row.deserialize())
+            .collect()
+    }
+
+    /// Get the number of rows returned by this [Select] using the given caching strategy.
+    pub async fn count(&self, select: &Select) -> Result<u64> {
+        tracing::trace!("Relatable::count({select:?})");
+        let (statement, params) = select.to_sql_count(&self.connection.kind())?;
+        let params = json!(params);
+        let json_rows = self
+            .connection
+            .cache_with_timeout(
+                &statement,
+                Some(&params),
+                &select.get_tables().into_iter().collect(),
+                &self.caching_strategy,
+                select.timeout,
+            )
+            .await?;
+        match json_rows.get(0) {
+            Some(json_row) => json_row.get_unsigned("count"),
+            None => Ok(0),
+        }
+    }
+
+    /// Estimate the number of rows returned by this [Select] from the statistics that the
+    /// database keeps about its table: `reltuples` in PostgreSQL's `pg_class`, or, in SQLite, the
+    /// row counts in `sqlite_stat1`, which are only there once `ANALYZE` has been run. Returns
+    /// `None` when there is no estimate, including for a select that does not return all of the
+    /// rows of its table (see [Select::selects_whole_table()]), which must be counted instead
+    /// (see [Relatable::count()]).
+    pub async fn estimate_count(&self, select: &Select) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::estimate_count({select:?})");
+        if !select.selects_whole_table() {
+            return Ok(None);
+        }
+        let sql_param = SqlParam::new(&self.connection.kind()).next();
+        let estimates = match self.connection.kind() {
+            DbKind::Postgres => {
+                // A table that has never been analyzed has -1 reltuples:
+                let sql = format!(
+                    r#"SELECT "reltuples"::BIGINT AS "estimate" FROM "pg_class"
+                       WHERE "relname" = {sql_param} AND "relkind" = 'r'
+                         AND "reltuples" >= 0"#
+                );
+                self.connection
+                    .query(&sql, Some(&json!([select.table_name])))
+                    .await?
+                    .iter()
+                    .map(|row| row.get_unsigned("estimate"))
+                    .collect::<Result<Vec<_>>>()?
             }
-            ChangeAction::Redo => {
-                let (change_id, _) = self
-                    ._get_last_change_for_user(tx, &changeset.user, &ChangeAction::Undo)?
-                    .ok_or(RelatableError::DataError(
-                        "No undo for user found".to_string(),
-                    ))?;
-                Some(change_id)
+            DbKind::Sqlite => {
+                if !Table::table_exists("sqlite_stat1", self).await? {
+                    return Ok(None);
+                }
+                // The first of the numbers in the "stat" column is the number of rows in the
+                // table (or in the index, which is the same for any index that is not partial):
+                let sql = format!(r#"SELECT "stat" FROM "sqlite_stat1" WHERE "tbl" = {sql_param}"#);
+                self.connection
+                    .query(&sql, Some(&json!([select.table_name])))
+                    .await?
+                    .iter()
+                    .filter_map(|row| {
+                        row.get_string("stat")
+                            .ok()?
+                            .split_whitespace()
+                            .next()?
+                            .parse::<u64>()
+                            .ok()
+                    })
+                    .collect()
             }
-            ChangeAction::Do => None,
         };
+        Ok(estimates.into_iter().max())
+    }
 
-        // Now write the current change, which will generate a new last change_id:
-        let statement = format!(
-            r#"INSERT INTO change("user", "action", "table", "description", "content")
-               VALUES ({sql_params})
-               RETURNING change_id"#,
-            sql_params = SqlParam::new(&tx.kind()).get_as_list(5)
-        );
-        let content = to_value(&changeset.changes).unwrap_or_default();
-        let params = json!([user, action, table, description, content]);
-        let change_id = tx.query_value(&statement, Some(&params))?;
-        let change_id = change_id
-            .ok_or(RelatableError::DataError(
-                "Expected a change_id".to_string(),
-            ))?
-            .as_u64()
-            .ok_or(RelatableError::DataError("Expected an integer".to_string()))?;
-
-        for change in &changeset.changes {
-            match change {
-                Change::Update {
-                    row,
-                    column,
-                    before,
-                    after,
-                } => {
-                    let sql = format!(
-                        r#"INSERT INTO "history"
-                           ("change_id", "table", "row", "before", "after")
-                           VALUES ({sql_params})
-                           RETURNING "history_id""#,
-                        sql_params = SqlParam::new(&tx.kind()).get_as_list(5)
-                    );
-                    let before = json!({column: before}).to_string();
-                    let after = json!({column: after}).to_string();
-                    let params = json!([change_id, table, row, before, after]);
-                    tx.query_value(&sql, Some(&params))?;
-                }
-                Change::Add { row, after: _ } => {
-                    // If the row has just been newly added, it will be found in the table,
-                    // otherwise we will use the old_change_id to look for it in the history
-                    // table:
-                    let json_row = match Table::_get_row(&table, *row, tx)? {
-                        Some(json_row) => json_row,
-                        None => match old_change_id {
-                            Some(change_id) => {
-                                let sql = format!(
-                                    r#"SELECT "before"
-                                         FROM "history"
-                                        WHERE "change_id" = {sql_param}"#,
-                                    sql_param = SqlParam::new(&tx.kind()).next()
-                                );
-                                let params = json!([change_id]);
-                                let before = tx
-                                    .query_one(&sql, Some(&params))?
-                                    .ok_or(RelatableError::DataError(format!(
-                                        "No history row found with change_id {change_id}"
-                                    )))?
-                                    .get_string("before")?;
-                                let before = match serde_json::from_str::<JsonValue>(&before) {
-                                    Err(err) => return Err(err.into()),
-                                    Ok(JsonValue::Object(o)) => o,
-                                    Ok(_) => {
-                                        return Err(RelatableError::InputError(
-                                            "The content parameter is not an object".to_string(),
-                                        )
-                                        .into());
-                                    }
-                                };
-                                JsonRow { content: before }
-                            }
-                            None => {
-                                return Err(RelatableError::DataError(format!(
-                                    "Row {row} not found"
-                                ))
-                                .into())
-                            }
-                        },
-                    };
-                    let sql = format!(
-                        r#"INSERT INTO "history"
-                           ("change_id", "table", "row", "after")
-                           VALUES ({sql_params})
-                           RETURNING "history_id""#,
-                        sql_params = SqlParam::new(&tx.kind()).get_as_list(4)
-                    );
-                    let json_row_str = json!(json_row.content).to_string();
-                    let params = json!([change_id, table, row, json_row_str]);
-                    tx.query_value(&sql, Some(&params))?;
-                }
-                Change::Move {
-                    row,
-                    from_after: _,
-                    to_after: _,
-                } => {
-                    let sql = format!(
-                        r#"INSERT INTO "history"
-                           ("change_id", "table", "row")
-                           VALUES ({sql_params})
-                           RETURNING "history_id""#,
-                        sql_params = SqlParam::new(&tx.kind()).get_as_list(3)
-                    );
-                    let params = json!([change_id, table, row]);
-                    tx.query_value(&sql, Some(&params))?;
-                }
-                Change::Delete { row, after: _ } => {
-                    let json_row = match Table::_get_row(&table, *row, tx)? {
-                        Some(json_row) => json_row,
-                        None => {
-                            // It must be there since we supposedly just added it, so if it is
-                            // not found return an error.
-                            return Err(
-                                RelatableError::DataError(format!("Row {row} not found")).into()
-                            );
+    /// Compute the sum and the mean of each of the numeric columns that the given [Select]
+    /// returns, over all of the rows that match its filters, with a single query, e.g., for a
+    /// footer row below a page of the results. The select's order and paging do not apply, and
+    /// neither do its aggregates. Columns of any other SQL type are left out.
+    pub async fn totals(&self, select: &Select) -> Result<IndexMap<String, Total>> {
+        tracing::trace!("Relatable::totals({select:?})");
+        let table = Table::get_table(&select.table_name, self).await?;
+        let kind = self.connection.kind();
+        let columns = table
+            .columns
+            .values()
+            .filter(|column| {
+                matches!(
+                    sql::canonical_sql_type(&column.get_sql_type()),
+                    Some("INTEGER" | "NUMERIC")
+                )
+            })
+            .filter(|column| {
+                select.select.is_empty()
+                    || select.select.iter().any(|field| match field {
+                        SelectField::Column {
+                            table: select_table,
+                            column: select_column,
+                            ..
+                        } => {
+                            *select_column == column.name
+                                && (select_table.is_empty() || *select_table == table.name)
                         }
-                    };
-                    let sql = format!(
-                        r#"INSERT INTO "history"
-                           ("change_id", "table", "row", "before")
-                           VALUES ({sql_params})
-                           RETURNING "history_id""#,
-                        sql_params = SqlParam::new(&tx.kind()).get_as_list(4)
-                    );
-                    let json_row_str = json!(json_row.content).to_string();
-                    let params = json!([change_id, table, row, json_row_str]);
-                    tx.query_value(&sql, Some(&params))?;
-                }
-            };
+                        _ => false,
+                    })
+            })
+            .map(|column| column.name.as_str())
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            return Ok(IndexMap::new());
         }
 
-        // Possibly delete dirty entries from the cache in accordance with our caching strategy:
-        match self.caching_strategy {
-            // Trigger has the same behaviour as None here, since the database will be triggering
-            // this step automatically every time the table is edited in that case.
-            CachingStrategy::None | CachingStrategy::Trigger => (),
-            CachingStrategy::Memory(_) => self.clear_mem_cache(&table),
-            CachingStrategy::TruncateAll => Relatable::clear_cache(tx, None)?,
-            CachingStrategy::Truncate => Relatable::clear_cache(tx, Some(&table))?,
+        // All of the rows that match the select are totalled, on any page:
+        let mut rows = select.clone();
+        rows.select = vec![];
+        rows.order_by = vec![];
+        rows.limit = Limit::Unlimited;
+        rows.offset = 0;
+        rows.after = None;
+        rows.before = None;
+        rows.set_nulltypes(self).await;
+        rows.sample = None;
+        rows.display = false;
+        rows.display_columns = vec![];
+        rows.materialized_change_id = table.has_change_id;
+        rows.partitions = table.partitions.clone();
+        rows.include_archived &= table.has_archive;
+        let (rows_sql, params) = rows.to_sql(&kind)?;
+
+        let mut fields = vec![];
+        for (i, column) in columns.iter().enumerate() {
+            fields.push(format!(r#"SUM("rows"."{column}") AS "sum_{i}""#));
+            match kind {
+                DbKind::Sqlite => fields.push(format!(r#"AVG("rows"."{column}") AS "avg_{i}""#)),
+                DbKind::Postgres => fields.push(format!(
+                    r#"AVG("rows"."{column}")::DOUBLE PRECISION AS "avg_{i}""#
+                )),
+            }
+        }
+        let sql = format!(
+            r#"SELECT {fields} FROM ({rows_sql}) AS "rows""#,
+            fields = fields.join(",\n       ")
+        );
+        let row = self
+            .connection
+            .query_with_timeout(&sql, Some(&json!(params)), select.timeout)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or(RelatableError::DataError(format!(
+                "No totals for table '{}'",
+                select.table_name
+            )))?;
+        columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                Ok((
+                    column.to_string(),
+                    Total {
+                        sum: row.get_value(&format!("sum_{i}"))?,
+                        avg: row.get_value(&format!("avg_{i}"))?.as_f64(),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Count the distinct values of each of the given columns of the given table among the rows
+    /// that the given [Select] returns, e.g., to show how many rows each of the options of a
+    /// filter would match. The select's filters apply, but its selected columns, order, and
+    /// paging do not. If no columns are given, each of the table's columns is counted. The values
+    /// of each column are sorted from the most to the least common, and at most
+    /// [Relatable::default_limit] of them are returned.
+    pub async fn facets(
+        &self,
+        table_name: &str,
+        columns: &[&str],
+        select: &Select,
+    ) -> Result<Vec<Facet>> {
+        tracing::trace!("Relatable::facets({table_name:?}, {columns:?}, {select:?})");
+        if select.table_name != table_name {
+            return Err(RelatableError::InputError(format!(
+                "Cannot count the values of '{table_name}' with a select of '{}'",
+                select.table_name
+            ))
+            .into());
+        }
+        let table = Table::get_table(table_name, self).await?;
+        let columns = match columns.is_empty() {
+            true => table.columns.keys().map(|c| c.as_str()).collect::<Vec<_>>(),
+            false => columns.to_vec(),
         };
+        if let Some(column) = columns.iter().find(|c| !table.columns.contains_key(**c)) {
+            return Err(RelatableError::InputError(format!(
+                "No column '{column}' in table '{table_name}'"
+            ))
+            .into());
+        }
 
-        Ok(())
+        // Only the rows that the select returns are counted, on any page:
+        let mut rows = select.clone();
+        rows.select = vec![];
+        rows.order_by = vec![];
+        rows.limit = Limit::Unlimited;
+        rows.offset = 0;
+        rows.after = None;
+        rows.before = None;
+        rows.set_nulltypes(self).await;
+        rows.display = false;
+        rows.display_columns = vec![];
+        rows.materialized_change_id = table.has_change_id;
+        rows.partitions = table.partitions.clone();
+        rows.include_archived &= table.has_archive;
+
+        let mut facets = vec![];
+        for column in columns {
+            let mut rows = rows.clone();
+            rows.select_column(column);
+            let (rows_sql, params) = rows.to_sql(&self.connection.kind())?;
+            // One more value than is returned is fetched, to tell whether there are any more:
+            let sql = format!(
+                r#"SELECT "rows"."{column}" AS "value", COUNT(1) AS "count"
+                   FROM ({rows_sql}) AS "rows"
+                   GROUP BY "rows"."{column}"
+                   ORDER BY "count" DESC, "value"
+                   LIMIT {}"#,
+                self.default_limit + 1
+            );
+            let mut values = self
+                .connection
+                .query_with_timeout(&sql, Some(&json!(params)), select.timeout)
+                .await?
+                .iter()
+                .map(|row| row.deserialize())
+                .collect::<Result<Vec<FacetValue>>>()?;
+            let truncated = values.len() > self.default_limit;
+            values.truncate(self.default_limit);
+            facets.push(Facet {
+                column: column.to_string(),
+                values,
+                truncated,
+            });
+        }
+        Ok(facets)
     }
 
-    /// Get information about the given user from the database and return it as an [Account]. If
-    /// there is no user with the given username, return a default Account.
-    pub async fn get_user(&self, username: &str) -> Account {
-        tracing::trace!("Relatable::get_user({username:?})");
-        let statement = format!(
-            r#"SELECT "name", "color", "cursor", "datetime"
-               FROM "user" WHERE name = '{username}' LIMIT 1"#
+    /// Like [Relatable::facets()], but reuse the facets that were cached for the same columns and
+    /// select, in the same way as [Relatable::fetch_cached()] reuses results, i.e., as long as
+    /// none of the tables that the select reads from has a new change or message.
+    pub async fn facets_cached(
+        &self,
+        table_name: &str,
+        columns: &[&str],
+        select: &Select,
+    ) -> Result<Vec<Facet>> {
+        tracing::trace!("Relatable::facets_cached({table_name:?}, {columns:?}, {select:?})");
+        // A random sample is different every time, as are the rows from a relative date on:
+        if select.sample.is_some() || select.get_all_filters().iter().any(|f| f.is_relative()) {
+            return self.facets(table_name, columns, select).await;
+        }
+        let tables = select.get_tables().into_iter().collect::<Vec<_>>();
+        let key = format!(
+            "FACETS {table_name} {} {}",
+            columns.join(","),
+            serde_json::to_string(select)?
         );
-        let user = self.connection.query_one(&statement, None).await;
-        match user {
-            Ok(user) => match user {
-                Some(user) => Account {
-                    name: username.to_string(),
-                    color: user.get_string("color").expect("No 'color' found"),
-                },
-                None => Account {
-                    ..Default::default()
-                },
-            },
-            Err(err) => {
-                tracing::warn!("Error while querying user table: '{err}'");
-                Account {
-                    ..Default::default()
+        let compute = Box::pin(async {
+            let facets = to_value(self.facets(table_name, columns, select).await?)?;
+            Ok(vec![JsonRow {
+                content: JsonMap::from_iter([("facets".to_string(), facets)]),
+            }])
+        });
+        let json_rows = Box::pin(self.connection.cache_with(
+            &key,
+            None,
+            &tables,
+            &self.caching_strategy,
+            compute,
+        ))
+        .await?;
+        match json_rows.first() {
+            Some(json_row) => Ok(serde_json::from_value(json_row.get_value("facets")?)?),
+            None => Err(RelatableError::DataError(format!("No cached facets for {key}")).into()),
+        }
+    }
+
+    /// Compute statistics for each of the columns of the given table, i.e., its least and
+    /// greatest values, the mean of its values, if it is numeric, and the numbers of its values
+    /// that are NULL and that are distinct, for every column at once with a single query. In
+    /// PostgreSQL, the values of columns that are neither numeric nor text, e.g., dates and JSONB,
+    /// are compared, and returned, as text.
+    pub async fn column_stats(&self, table_name: &str) -> Result<Vec<ColumnStats>> {
+        tracing::trace!("Relatable::column_stats({table_name:?})");
+        if !Table::table_exists(table_name, self).await? {
+            return Err(
+                RelatableError::MissingError(format!("No table named '{table_name}'")).into(),
+            );
+        }
+        let table = Table::get_table(table_name, self).await?;
+        let kind = self.connection.kind();
+        let mut fields = vec![];
+        for (i, column) in table.columns.values().enumerate() {
+            let name = &column.name;
+            let sql_type = sql::canonical_sql_type(&column.get_sql_type());
+            let numeric = matches!(sql_type, Some("INTEGER" | "NUMERIC"));
+            let ordered = match kind {
+                DbKind::Sqlite => format!(r#""{name}""#),
+                DbKind::Postgres if numeric || sql_type == Some("TEXT") => format!(r#""{name}""#),
+                DbKind::Postgres => format!(r#""{name}"::TEXT"#),
+            };
+            fields.push(format!(r#"MIN({ordered}) AS "min_{i}""#));
+            fields.push(format!(r#"MAX({ordered}) AS "max_{i}""#));
+            match (numeric, &kind) {
+                (false, _) => fields.push(format!(r#"NULL AS "mean_{i}""#)),
+                (true, DbKind::Sqlite) => fields.push(format!(r#"AVG("{name}") AS "mean_{i}""#)),
+                (true, DbKind::Postgres) => {
+                    fields.push(format!(r#"AVG("{name}")::DOUBLE PRECISION AS "mean_{i}""#))
                 }
             }
+            fields.push(format!(r#"COUNT(1) - COUNT("{name}") AS "nulls_{i}""#));
+            fields.push(format!(r#"COUNT(DISTINCT "{name}") AS "distinct_{i}""#));
+        }
+        if fields.is_empty() {
+            return Ok(vec![]);
         }
+        let sql = format!(
+            r#"SELECT {fields} FROM "{table_name}""#,
+            fields = fields.join(",\n       ")
+        );
+        let row = self
+            .connection
+            .query_one(&sql, None)
+            .await?
+            .ok_or(RelatableError::DataError(format!(
+                "No statistics for table '{table_name}'"
+            )))?;
+        table
+            .columns
+            .values()
+            .enumerate()
+            .map(|(i, column)| {
+                Ok(ColumnStats {
+                    column: column.name.clone(),
+                    min: row.get_value(&format!("min_{i}"))?,
+                    max: row.get_value(&format!("max_{i}"))?,
+                    mean: row.get_value(&format!("mean_{i}"))?.as_f64(),
+                    null_count: row.get_unsigned(&format!("nulls_{i}"))?,
+                    distinct_count: row.get_unsigned(&format!("distinct_{i}"))?,
+                })
+            })
+            .collect()
     }
 
-    /// Returns a map with information about all of the users who have corresponding records in
-    /// the user table.
-    pub async fn get_users(&self) -> Result<IndexMap<String, UserCursor>> {
-        tracing::trace!("Relatable::get_users()");
-        let mut users = IndexMap::new();
-        // let statement = format!(
-        //     r#"SELECT "name", color", "cursor", "datetime" FROM "user" WHERE cursor IS NOT NULL
-        //        AND "datetime" >= DATETIME('now', '-10 minutes')"#
-        // );
+    /// Fetch the rows returned by the given fragment of SQL, with the given parameters, which
+    /// are written as usual for the database (`?` in SQLite, `$1`, `$2`, etc. in PostgreSQL).
+    /// The fragment must be a [single](sql::is_single_query) query, and is run as a subquery, at
+    /// most [Relatable::default_limit] of whose rows are returned, in a read-only transaction
+    /// (see [DbTransaction::set_read_only()]), so that it cannot change the database.
+    pub async fn query_select_raw(
+        &self,
+        sql_fragment: &str,
+        params: &Vec<JsonValue>,
+    ) -> Result<Vec<Row>> {
+        tracing::trace!("Relatable::query_select_raw({sql_fragment:?}, {params:?})");
+        sql::is_single_query(sql_fragment).map_err(RelatableError::InputError)?;
         let statement = format!(
-            r#"SELECT "name", "color", "cursor", "datetime"
-               FROM "user"
-               WHERE cursor {is_not} NULL"#,
-            is_not = sql::is_not_clause(&self.connection.kind()),
+            "SELECT *\nFROM ({sql_fragment}) AS \"raw\"\nLIMIT {}",
+            self.default_limit
         );
-        let rows = self.connection.query(&statement, None).await?;
-        for row in rows {
-            let name = row.get_string("name")?;
-            if name.trim() == "" {
-                continue;
-            }
-            users.insert(
-                name.clone(),
-                UserCursor {
-                    name: name.clone(),
-                    color: row.get_string("color")?,
-                    cursor: serde_json::from_str(&row.get_string("cursor")?)?,
-                    datetime: row.get_string("datetime")?,
-                },
-            );
-        }
-        Ok(users)
+        let json_rows = self.query_read_only(&statement, params).await?;
+        Ok(json_rows.vec_into())
     }
 
-    /// Returns a list of the given table's columns, not including metacolumns
-    pub async fn fetch_columns(&self, table_name: &str) -> Result<Vec<Column>> {
-        tracing::trace!("Relatable::fetch_columns({table_name:?})");
-        let table = Table::get_table(table_name, self).await?;
-        Ok(table.columns.values().cloned().collect::<Vec<_>>())
+    /// Get the number of rows returned by the given fragment of SQL, with the given parameters
+    /// (see [Relatable::query_select_raw()]).
+    pub async fn count_select_raw(
+        &self,
+        sql_fragment: &str,
+        params: &Vec<JsonValue>,
+    ) -> Result<u64> {
+        tracing::trace!("Relatable::count_select_raw({sql_fragment:?}, {params:?})");
+        sql::is_single_query(sql_fragment).map_err(RelatableError::InputError)?;
+        let statement = format!("SELECT COUNT(1) AS \"count\"\nFROM ({sql_fragment}) AS \"raw\"");
+        match self.query_read_only(&statement, params).await?.first() {
+            Some(json_row) => json_row.get_unsigned("count"),
+            None => Ok(0),
+        }
     }
 
-    /// Returns a list of the given table's columns, including metacolumns
-    pub async fn fetch_all_columns(&self, table_name: &str) -> Result<Vec<Column>> {
-        tracing::trace!("Relatable::fetch_all_columns({table_name:?})");
+    /// Run the given statement, with the given parameters, in a read-only transaction, which is
+    /// then rolled back, so that the database rejects the statement if it would change anything.
+    async fn query_read_only(
+        &self,
+        statement: &str,
+        params: &Vec<JsonValue>,
+    ) -> Result<Vec<JsonRow>> {
+        tracing::trace!("Relatable::query_read_only({statement:?}, {params:?})");
         let mut conn = self.connection.reconnect()?;
-        // Begin a transaction:
         let mut tx = self.connection.begin(&mut conn).await?;
+        tx.set_read_only(true)?;
+        let json_rows = tx.query(statement, Some(&json!(params)));
+        tx.set_read_only(false)?;
+        tx.rollback()?;
+        json_rows
+    }
 
-        let columns = {
-            let (mut normal_columns, meta_columns) =
-                Table::_collect_column_info(table_name, &mut tx)?;
-            let mut all_columns = meta_columns;
-            all_columns.append(&mut normal_columns);
-            all_columns
+    /// Loads the given table from the given path. When `force` is set to true, deletes any
+    /// existing table of the same name in the database first. When `validate` is set to true,
+    /// Validates each row before loading it. The optional `message` describes the load and is
+    /// used as the git commit message. Note that this function may panic.
+    pub async fn load_table(
+        &self,
+        table_name: &str,
+        path: &str,
+        force: bool,
+        message: Option<&str>,
+    ) {
+        tracing::trace!("Relatable::load_table({table_name:?}, {path:?}, {force}, {message:?})");
+        let rows = self.load_table_data(table_name, path, force, false).await;
+        let message = match self.column_renames.is_empty() {
+            true => message.map(|message| message.to_string()),
+            false => {
+                let headers = self.read_headers(path).expect("Error reading headers");
+                let (_, renames) = self
+                    .rename_headers(&headers)
+                    .expect("Error renaming headers");
+                Relatable::describe_column_renames(message, &renames)
+            }
         };
+        self.record_load("rltbl", table_name, rows, message.as_deref())
+            .await
+            .expect("Error recording load");
+        self.commit_to_git(message.as_deref())
+            .await
+            .expect("Error committing to git");
+    }
 
-        // Commit the transaction:
+    /// Record the load of the given number of rows to the given table, with the given message,
+    /// if there is one, in a change record made by the given user, so that the message is kept
+    /// in the change table along with those of edits. The record has no changes, and so it does
+    /// not appear in any user's history.
+    async fn record_load(
+        &self,
+        user: &str,
+        table_name: &str,
+        rows: u64,
+        message: Option<&str>,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::record_load({user:?}, {table_name:?}, {rows}, {message:?})");
+        let Some(message) = message else {
+            return Ok(());
+        };
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.begin_write(&mut conn).await?;
+
+        // Make sure that the user is present in the user table:
+        let sql = format!(
+            r#"SELECT 1 FROM "user" WHERE "name" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        if tx.query_value(&sql, Some(&json!([user])))?.is_none() {
+            let sql = format!(
+                r#"INSERT INTO "user" ("name", "color") VALUES ({sql_params})"#,
+                sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
+            );
+            let color = random_color::RandomColor::new().to_hex();
+            tx.query(&sql, Some(&json!([user, color])))?;
+        }
+
+        let sql = format!(
+            r#"INSERT INTO "change" ("user", "action", "table", "description", "message",
+                                     "content")
+               VALUES ({sql_params})"#,
+            sql_params = SqlParam::new(&tx.kind()).get_as_list(6)
+        );
+        let params = json!([
+            user,
+            ChangeAction::Do.to_string(),
+            table_name,
+            format!("Load {rows} rows"),
+            message,
+            "[]"
+        ]);
+        tx.query(&sql, Some(&params))?;
         tx.commit()?;
+        Ok(())
+    }
 
-        Ok(columns)
+    /// Rename the given headers of a file that is being loaded according to this instance's
+    /// [column_renames](Relatable::column_renames). Returns the renamed headers, along with the
+    /// renames that were applied to them, or an error if two of the renamed headers are the same.
+    pub fn rename_headers(
+        &self,
+        headers: &[String],
+    ) -> Result<(Vec<String>, IndexMap<String, String>)> {
+        tracing::trace!("Relatable::rename_headers({headers:?})");
+        let mut renamed = vec![];
+        let mut applied = IndexMap::new();
+        for header in headers {
+            let new_header = match self.column_renames.get(header) {
+                Some(new_header) => {
+                    applied.insert(header.to_string(), new_header.to_string());
+                    new_header.to_string()
+                }
+                None => header.to_string(),
+            };
+            if renamed.contains(&new_header) {
+                return Err(RelatableError::InputError(format!(
+                    "Renaming the headers results in more than one column named '{new_header}'"
+                ))
+                .into());
+            }
+            renamed.push(new_header);
+        }
+        Ok((renamed, applied))
     }
 
-    /// Returns a vector of the names of the tables that have entries in the table table
-    pub async fn list_tables(&self) -> Result<Vec<String>> {
-        tracing::trace!("Relatable::list_tables({self:?})");
-        let statement = format!(r#"SELECT "table" FROM "table" ORDER BY _order"#);
-        let rows = self.connection.query(&statement, None).await?;
-        rows.iter().map(|row| row.get_string("table")).collect()
+    /// Read the headers of the TSV (or CSV) file at the given path
+    fn read_headers(&self, path: &str) -> Result<Vec<String>> {
+        let (reader, delimiter) = self.open_table_file(path)?;
+        let mut rdr = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        Ok(rdr.headers()?.iter().map(|h| h.to_string()).collect())
     }
 
-    /// Returns all of the tables that have entries in the table table as a map from table names
-    /// to Table structs.
-    pub async fn get_tables(&self) -> Result<IndexMap<String, Table>> {
-        tracing::trace!("Relatable::get_tables({self:?})");
-        let mut tables = IndexMap::new();
-        let statement = format!(
-            r#"SELECT "_id", "_order", "table", "path",
-                 (SELECT MAX(change_id)
-                  FROM "history"
-                  WHERE "history"."table" = "table"."table"
-                 ) AS "_change_id"
-               FROM "table""#
-        );
+    /// Open the TSV (or CSV) file at the given path for loading, returning a reader of its text,
+    /// decoded as UTF-8, and the delimiter of its fields, using the delimiter and encoding of this
+    /// instance, if it has them, or else detecting them (see [open_delimited()]).
+    fn open_table_file(&self, path: &str) -> Result<(Box<dyn Read + Send>, u8)> {
+        open_delimited(
+            path,
+            self.delimiter,
+            self.encoding,
+            self.get_delimiter(path),
+        )
+    }
 
-        let rows = self.connection.query(&statement, None).await?;
-        for row in rows {
-            let name = row.get_string("table")?;
-            tables.insert(
-                name.clone(),
-                Table {
-                    name: name.clone(),
-                    change_id: row
-                        .content
-                        .get("_change_id")
-                        .and_then(|i| i.as_u64())
-                        .unwrap_or_default() as u64,
-                    columns: self
-                        .fetch_columns(&name)
-                        .await?
-                        .into_iter()
-                        .map(|column| (name.clone(), column))
-                        .collect::<IndexMap<_, _>>(),
-                    ..Default::default()
-                },
-            );
+    /// Get the usual delimiter of the fields of the file at the given path: a tab, or, for a CSV
+    /// file, the delimiter that is usual in this instance's locale
+    fn get_delimiter(&self, path: &str) -> u8 {
+        match path.to_lowercase().ends_with(".csv") {
+            true => self.locale.csv_delimiter(),
+            false => b'\t',
         }
-        Ok(tables)
     }
 
-    /// Returns a [Site] corresponding to the given username.
-    pub async fn get_site(&self, username: &str) -> Site {
-        tracing::trace!("Relatable::get_site({username:?})");
-        let mut users = self.get_users().await.unwrap_or_default();
-        users.shift_remove(username);
-        Site {
-            title: "RLTBL".to_string(),
-            root: self.root.clone(),
-            editable: !self.readonly,
-            user: self.get_user(username).await,
-            users,
-            tables: self.list_tables().await.unwrap_or_default(),
+    /// Add a note of the given column renames, applied to the headers of a file that was loaded,
+    /// to the given message describing the load.
+    fn describe_column_renames(
+        message: Option<&str>,
+        renames: &IndexMap<String, String>,
+    ) -> Option<String> {
+        if renames.is_empty() {
+            return message.map(|message| message.to_string());
+        }
+        let note = format!(
+            "Renamed columns: {}",
+            renames
+                .iter()
+                .map(|(header, new_header)| format!("'{header}' to '{new_header}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        match message {
+            Some(message) => Some(format!("{message}\n\n{note}")),
+            None => Some(note),
         }
     }
 
-    /// Updates the cursor field in the user table for the user associated with the given
-    /// changeset.
-    pub fn prepare_user_cursor(
+    /// Loads the given tables, each given as a pair of a table name and the path to load it from,
+    /// using up to `jobs` worker threads (or only one, for SQLite). A table is not loaded until every other given table that
+    /// it depends on (see [Relatable::get_load_order()]) has been loaded, and it is skipped if any
+    /// of them fails to load. When `force` is set to true, existing tables of the same names are
+    /// replaced. Once all of the tables have been tried, the loaded tables are committed to git
+    /// using the optional `message`. Returns the outcome of loading each table, in the order
+    /// given.
+    pub async fn load_tables(
         &self,
-        changeset: &ChangeSet,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!("Relatable::prepare_user_cursor({changeset:?}, tx)");
-        // Make sure the user is present in the user table
-        let user = changeset.user.clone();
-        let color = random_color::RandomColor::new().to_hex();
-        let statement = format!(
-            r#"SELECT 1 FROM "user" WHERE "name" = {sql_param}"#,
-            sql_param = SqlParam::new(&tx.kind()).next()
+        tables: &Vec<(String, String)>,
+        force: bool,
+        jobs: usize,
+        message: Option<&str>,
+    ) -> Result<Vec<TableLoad>> {
+        tracing::trace!("Relatable::load_tables({tables:?}, {force}, {jobs}, {message:?})");
+        self.load_tables_as("rltbl", tables, force, jobs, message)
+            .await
+    }
+
+    /// Loads the given tables as [Relatable::load_tables()] does, recording the loads on behalf
+    /// of the given user rather than the `rltbl` user.
+    pub async fn load_tables_as(
+        &self,
+        user: &str,
+        tables: &Vec<(String, String)>,
+        force: bool,
+        jobs: usize,
+        message: Option<&str>,
+    ) -> Result<Vec<TableLoad>> {
+        tracing::trace!(
+            "Relatable::load_tables_as({user:?}, {tables:?}, {force}, {jobs}, {message:?})"
         );
-        let params = json!([user]);
-        if let None = tx.query_value(&statement, Some(&params))? {
-            let statement = format!(
-                r#"INSERT INTO "user" ("name", "color") VALUES ({sql_params})"#,
-                sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
-            );
-            let params = json!([user, color]);
-            tx.query(&statement, Some(&params))?;
+        let loads = self
+            .load_tables_uncommitted(user, tables, force, jobs, message)
+            .await?;
+        if loads.iter().any(|load| load.status == LoadStatus::Loaded) {
+            self.commit_to_git(message).await?;
         }
+        Ok(loads)
+    }
 
-        // Update the user's cursor position.
-        let mut cursor = changeset.to_cursor()?;
-        match changeset.action {
-            ChangeAction::Undo | ChangeAction::Redo => match changeset.changes.first() {
-                Some(Change::Delete { row, after: _ }) => {
-                    cursor.row = Table::_get_previous_row_id(&changeset.table, *row, tx)?;
-                }
-                _ => (),
-            },
-            ChangeAction::Do => (),
+    /// Load the given table from the given TSV content, e.g., uploaded by a client that has no
+    /// access to the database, on behalf of the given user. Only the tables that are listed in
+    /// the table table, other than the meta tables, can be loaded in this way, and an existing
+    /// table is only replaced when `force` is set to true. The content is written to a temporary
+    /// file next to the path that the table was loaded from, or else TABLE.tsv, which that file
+    /// replaces only once the table has been loaded.
+    pub async fn load_table_content(
+        &self,
+        user: &str,
+        table: &str,
+        content: &str,
+        force: bool,
+        message: Option<&str>,
+    ) -> Result<TableLoad> {
+        tracing::trace!(
+            "Relatable::load_table_content({user:?}, {table:?}, content, {force}, {message:?})"
+        );
+        if sql::META_TABLES.contains(&table) {
+            return Err(RelatableError::InputError(format!(
+                "The meta table '{table}' cannot be loaded"
+            ))
+            .into());
+        }
+        let sql = format!(
+            r#"SELECT "path" FROM "table" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        let path = match self
+            .connection
+            .query_value(&sql, Some(&json!([table])))
+            .await?
+        {
+            None => {
+                return Err(RelatableError::InputError(format!("No table named '{table}'")).into())
+            }
+            Some(JsonValue::String(path)) if !path.is_empty() => path,
+            Some(_) => format!("{table}.tsv"),
         };
+        if !force && Table::table_exists(table, self).await? {
+            return Err(RelatableError::InputError(format!(
+                "Table '{table}' already exists; use force to replace it"
+            ))
+            .into());
+        }
 
-        let mut sql_param = SqlParam::new(&tx.kind());
-        let statement = format!(
-            r#"UPDATE "user"
-               SET "cursor" = {sql_param_1}, "datetime" = CURRENT_TIMESTAMP
-               WHERE "name" = {sql_param_2}"#,
+        let upload_path = {
+            let path = std::path::Path::new(&path);
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!(".upload.{file_name}"))
+                .to_string_lossy()
+                .to_string()
+        };
+        std::fs::write(&upload_path, content)?;
+        let loads = self
+            .load_tables_uncommitted(
+                user,
+                &vec![(table.to_string(), upload_path.clone())],
+                force,
+                1,
+                message,
+            )
+            .await;
+
+        // The table table should refer to the table's own file rather than the temporary one:
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let sql = format!(
+            r#"UPDATE "table" SET "path" = {sql_param_1}
+                WHERE "table" = {sql_param_2} AND "path" = {sql_param_3}"#,
             sql_param_1 = sql_param.next(),
             sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
         );
-        let params = json!([to_value(cursor).unwrap_or_default(), user]);
-        tx.query_value(&statement, Some(&params))?;
-
-        Ok(())
+        let params = json!([path, table, upload_path]);
+        let updated = self.connection.query(&sql, Some(&params)).await;
+
+        let load = match (loads, updated) {
+            (Ok(mut loads), Ok(_)) => loads.pop(),
+            (Err(error), _) | (_, Err(error)) => {
+                let _ = std::fs::remove_file(&upload_path);
+                return Err(error);
+            }
+        };
+        match load {
+            Some(load) if load.status == LoadStatus::Loaded => {
+                std::fs::rename(&upload_path, &path)?;
+                self.commit_to_git(message).await?;
+                Ok(TableLoad { path, ..load })
+            }
+            load => {
+                let _ = std::fs::remove_file(&upload_path);
+                let error = load.and_then(|load| load.error);
+                Err(RelatableError::DataError(error.unwrap_or("Nothing loaded".to_string())).into())
+            }
+        }
     }
 
-    /// Get the last set of changes that can be redone for the given user
-    pub async fn get_last_redoable_changeset_for_user(
+    /// Load the given tables as [Relatable::load_tables()] does, recording the loads on behalf of
+    /// the given user, but without committing them to git.
+    async fn load_tables_uncommitted(
         &self,
         user: &str,
-    ) -> Result<Option<(u64, ChangeSet)>> {
-        tracing::trace!("Relatable::get_last_redoable_changeset_for_user({user:?})");
-        let history = self.get_user_history(user, Some(1)).await?;
-        match history.changes_undone_stack.first() {
-            None => Ok(None),
-            Some(change) => {
-                let change_id = change.get_unsigned("change_id")?;
-                let content = change.get_string("content")?;
-                let changes = Change::many_from_str(&content)?;
-                Ok(Some((
-                    change_id,
-                    ChangeSet {
-                        action: ChangeAction::from_str(&change.get_string("action")?)?,
-                        table: change.get_string("table")?,
-                        user: change.get_string("user")?,
-                        description: change.get_string("user")?,
-                        changes: changes,
-                    },
-                )))
+        tables: &Vec<(String, String)>,
+        force: bool,
+        jobs: usize,
+        message: Option<&str>,
+    ) -> Result<Vec<TableLoad>> {
+        tracing::trace!(
+            "Relatable::load_tables_uncommitted({user:?}, {tables:?}, {force}, {jobs}, {message:?})"
+        );
+        let paths = tables.iter().cloned().collect::<HashMap<_, _>>();
+        let names = tables
+            .iter()
+            .map(|(table, _)| table.to_string())
+            .collect::<Vec<_>>();
+        let mut pending = self.get_load_order(&names).await?;
+        let total = pending.len();
+        // SQLite allows only one writer at a time, so there is no point in loading more than one
+        // table at a time:
+        let jobs = match self.connection.kind() {
+            DbKind::Sqlite => 1,
+            DbKind::Postgres => std::cmp::max(jobs, 1),
+        };
+        let mut loads: HashMap<String, TableLoad> = HashMap::new();
+
+        let (sender, receiver) = std::sync::mpsc::channel::<TableLoad>();
+        std::thread::scope(|scope| -> Result<()> {
+            let mut running = 0;
+            loop {
+                // Skip any pending tables that depend on a table that wasn't loaded. Since the
+                // pending tables are in dependency order, one pass is enough:
+                for (table, dependencies) in pending.clone().iter() {
+                    let missing = dependencies.iter().find(|dependency| {
+                        loads
+                            .get(*dependency)
+                            .is_some_and(|load| load.status != LoadStatus::Loaded)
+                    });
+                    if let Some(missing) = missing {
+                        tracing::warn!(
+                            "Skipping table '{table}' because '{missing}' wasn't loaded"
+                        );
+                        pending.shift_remove(table);
+                        loads.insert(
+                            table.to_string(),
+                            TableLoad {
+                                table: table.to_string(),
+                                path: paths[table].to_string(),
+                                status: LoadStatus::Skipped,
+                                error: Some(format!("Table '{missing}' wasn't loaded")),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+
+                // Start loading as many of the tables whose dependencies have all been loaded as
+                // there are free workers:
+                for (table, dependencies) in pending.clone().iter() {
+                    if running >= jobs {
+                        break;
+                    }
+                    if !dependencies
+                        .iter()
+                        .all(|dependency| loads.contains_key(dependency))
+                    {
+                        continue;
+                    }
+                    pending.shift_remove(table);
+                    running += 1;
+                    let table = table.to_string();
+                    let path = paths[&table].to_string();
+                    let sender = sender.clone();
+                    scope.spawn(move || {
+                        let start = std::time::Instant::now();
+                        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                            block_on(self.load_table_data(&table, &path, force, false))
+                        }));
+                        let (status, rows, error) = match result {
+                            Ok(rows) => (LoadStatus::Loaded, rows, None),
+                            Err(panic) => {
+                                let error = match panic.downcast_ref::<String>() {
+                                    Some(error) => error.to_string(),
+                                    None => match panic.downcast_ref::<&str>() {
+                                        Some(error) => error.to_string(),
+                                        None => "Unknown error".to_string(),
+                                    },
+                                };
+                                (LoadStatus::Failed, 0, Some(error))
+                            }
+                        };
+                        // The receiver only goes away if the scheduler has already failed:
+                        let _ = sender.send(TableLoad {
+                            table,
+                            path,
+                            status,
+                            rows,
+                            seconds: start.elapsed().as_secs_f64(),
+                            error,
+                        });
+                    });
+                }
+
+                if running == 0 {
+                    return Ok(());
+                }
+                let load = receiver.recv()?;
+                running -= 1;
+                match load.status {
+                    LoadStatus::Loaded => tracing::info!(
+                        "Loaded table '{}' ({} rows in {:.2}s) [{}/{total}]",
+                        load.table,
+                        load.rows,
+                        load.seconds,
+                        loads.len() + 1,
+                    ),
+                    _ => tracing::error!(
+                        "Failed to load table '{}': {} [{}/{total}]",
+                        load.table,
+                        load.error.as_deref().unwrap_or_default(),
+                        loads.len() + 1,
+                    ),
+                };
+                loads.insert(load.table.to_string(), load);
+            }
+        })?;
+
+        for table in &names {
+            if let Some(load) = loads.get(table) {
+                if load.status == LoadStatus::Loaded {
+                    self.record_load(user, table, load.rows, message).await?;
+                }
             }
         }
+        Ok(names
+            .iter()
+            .filter_map(|table| loads.remove(table))
+            .collect())
     }
 
-    /// Get the last set of changes that can be undone for the given user
-    pub async fn get_last_undoable_changeset_for_user(
+    /// Sort the given tables so that every table comes after the other given tables that it
+    /// depends on, i.e., the tables that one or more of its columns have a `from()` structure
+    /// referring to, according to the column table. Returns each table with the given tables that
+    /// it depends on. Returns an error if the dependencies are circular.
+    pub async fn get_load_order(
         &self,
-        user: &str,
-    ) -> Result<Option<(u64, ChangeSet)>> {
-        tracing::trace!("Relatable::get_last_undoable_changeset_for_user({user:?})");
-        let history = self.get_user_history(user, Some(1)).await?;
-        match history.changes_done_stack.first() {
-            None => Ok(None),
-            Some(change) => {
-                let change_id = change.get_unsigned("change_id")?;
-                let content = change.get_string("content")?;
-                let changes = Change::many_from_str(&content)?;
-                Ok(Some((
-                    change_id,
-                    ChangeSet {
-                        action: ChangeAction::from_str(&change.get_string("action")?)?,
-                        table: change.get_string("table")?,
-                        user: change.get_string("user")?,
-                        description: change.get_string("user")?,
-                        changes: changes,
-                    },
-                )))
+        tables: &Vec<String>,
+    ) -> Result<IndexMap<String, Vec<String>>> {
+        tracing::trace!("Relatable::get_load_order({tables:?})");
+        let mut dependencies: IndexMap<String, Vec<String>> = tables
+            .iter()
+            .map(|table| (table.to_string(), vec![]))
+            .collect();
+        if Table::table_exists("column", self).await? {
+            let sql = format!(
+                r#"SELECT "table", "structure" FROM "column" WHERE "structure" {is_not} NULL"#,
+                is_not = sql::is_not_clause(&self.connection.kind())
+            );
+            for row in self.connection.query(&sql, None).await? {
+                let table = row.get_string("table")?;
+                let Ok(Structure::From(Some(structure_table), _)) =
+                    Structure::from_str(&row.get_string("structure")?)
+                else {
+                    continue;
+                };
+                if structure_table == table || !dependencies.contains_key(&structure_table) {
+                    continue;
+                }
+                if let Some(table_dependencies) = dependencies.get_mut(&table) {
+                    if !table_dependencies.contains(&structure_table) {
+                        table_dependencies.push(structure_table);
+                    }
+                }
+            }
+        }
+
+        let mut sorted = IndexMap::new();
+        while sorted.len() < dependencies.len() {
+            let ready = dependencies
+                .iter()
+                .filter(|(table, table_dependencies)| {
+                    !sorted.contains_key(*table)
+                        && table_dependencies
+                            .iter()
+                            .all(|dependency| sorted.contains_key(dependency))
+                })
+                .map(|(table, table_dependencies)| (table.clone(), table_dependencies.clone()))
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                let circular = dependencies
+                    .keys()
+                    .filter(|table| !sorted.contains_key(*table))
+                    .map(|table| format!("'{table}'"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(RelatableError::InputError(format!(
+                    "Circular dependencies among the tables: {circular}"
+                ))
+                .into());
             }
+            sorted.extend(ready);
         }
+        Ok(sorted)
     }
 
-    /// Return a [History] for the given user with at most `context` (or [HISTORY_MAX] if this
-    /// is not given) undoable and/or redoable previous changes.
-    pub async fn get_user_history(&self, user: &str, context: Option<usize>) -> Result<History> {
-        tracing::trace!("Relatable::get_user_history({user:?}, {context:?})");
-        fn content_to_json_row(content: &str) -> Result<JsonRow> {
-            tracing::debug!("Entering content_to_json_row(content: {content})");
-            match serde_json::from_str::<JsonValue>(content) {
-                Ok(content) => match content
-                    .as_array()
-                    .and_then(|a| a.first())
-                    .and_then(|o| o.as_object())
-                {
-                    Some(object) => Ok(JsonRow {
-                        content: object.clone(),
-                    }),
-                    None => {
-                        return Err(RelatableError::InputError(format!(
-                            "Received invalid or empty content: {content}. Expected a non-empty \
-                             object array."
-                        ))
-                        .into())
-                    }
+    /// Resumes the interrupted load of the given table from the given path, skipping the rows of
+    /// the file that were loaded before the load was interrupted. The optional `message` describes
+    /// the load and is used as the git commit message. Note that this function may panic, in
+    /// particular if the table has no interrupted load from the given path.
+    pub async fn resume_load_table(&self, table_name: &str, path: &str, message: Option<&str>) {
+        tracing::trace!("Relatable::resume_load_table({table_name:?}, {path:?}, {message:?})");
+        let rows = self.load_table_data(table_name, path, false, true).await;
+        self.record_load("rltbl", table_name, rows, message)
+            .await
+            .expect("Error recording load");
+        self.commit_to_git(message)
+            .await
+            .expect("Error committing to git");
+    }
+
+    /// Loads the given table from the given path, without committing to git, and returns the
+    /// number of rows in the table. The rows are inserted in chunks, each in its own transaction,
+    /// which also records the number of rows loaded so far in the table table. If `resume` is set
+    /// to true, the rows that were loaded by an earlier, interrupted load are skipped. See
+    /// [Relatable::load_table()]. Note that this function may panic.
+    async fn load_table_data(
+        &self,
+        table_name: &str,
+        path: &str,
+        force: bool,
+        resume: bool,
+    ) -> u64 {
+        tracing::trace!("Relatable::load_table_data({table_name:?}, {path:?}, {force}, {resume})");
+        // Read the records from the given TSV (or CSV) file:
+        let (reader, delimiter) = self
+            .open_table_file(path)
+            .unwrap_or_else(|error| panic!("Unable to open '{path}': {error}"));
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(false)
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let mut records = rdr.records();
+
+        // Extract the headers from the first line of the file, which we will need for the CREATE
+        // TABLE statement:
+        let headers = {
+            let headers = match records.next() {
+                None => panic!("'{path}' is empty"),
+                Some(record) => match record {
+                    Err(err) => panic!("Error reading from '{path}': {err}"),
+                    Ok(headers) => headers.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
                 },
-                Err(err) => {
-                    return Err(
-                        RelatableError::InputError(format!("Error reading content: {err}")).into(),
-                    )
+            };
+            for header in &headers {
+                if header.trim().is_empty() {
+                    panic!("One or more of the header fields is empty for table '{table_name}'");
                 }
             }
-        }
-
-        fn on_the_same_target(change1: &JsonRow, change2: &JsonRow) -> Result<bool> {
-            tracing::trace!("Relatable::on_the_same_target({change1:?}, {change2:?})");
-            let change1 = content_to_json_row(&change1.get_string("content")?)?;
-            let change2 = content_to_json_row(&change2.get_string("content")?)?;
-            let row1 = change1.get_unsigned("row")?;
-            let row2 = change2.get_unsigned("row")?;
-            if row1 != row2 {
-                return Ok(false);
+            let (headers, renames) = self
+                .rename_headers(&headers)
+                .unwrap_or_else(|e| panic!("Error renaming the headers of '{path}': {e}"));
+            for (header, new_header) in &renames {
+                tracing::info!("Loading column '{header}' of '{path}' as '{new_header}'");
             }
-            if let Ok(column1) = change1.get_string("column") {
-                if let Ok(column2) = change2.get_string("column") {
-                    return Ok(column1 == column2);
-                }
+            if self.column_warning_limit > 0 && headers.len() > self.column_warning_limit {
+                tracing::warn!(
+                    "Table '{table_name}' has {} columns, more than {}, which may be slow to \
+                     view; consider putting it in wide mode",
+                    headers.len(),
+                    self.column_warning_limit
+                );
             }
-            Ok(true)
-        }
+            headers
+        };
 
-        fn prune_stacks(
-            changes_done_stack: &Vec<JsonRow>,
-            changes_undone_stack: &Vec<JsonRow>,
-        ) -> (Vec<JsonRow>, Vec<JsonRow>) {
-            tracing::trace!(
-                "Relatable::prune_stacks({changes_done_stack:?}, {changes_undone_stack:?})"
-            );
-            let mut pruned_dones = vec![];
-            let mut pruned_undones = vec![];
-            for change in changes_done_stack.iter() {
-                if !pruned_dones.iter().any(|done: &JsonRow| {
-                    on_the_same_target(&done, &change).expect("Error looking for a common target")
-                }) {
-                    pruned_dones.push(change.clone());
+        let db_kind = self.connection.kind();
+
+        // When resuming, find out how many rows were loaded before the load was interrupted:
+        let loaded = match resume {
+            false => 0,
+            true => {
+                let sql = format!(
+                    r#"SELECT "path", "loaded" FROM "table" WHERE "table" = {sql_param}"#,
+                    sql_param = SqlParam::new(&db_kind).next(),
+                );
+                let rows = self
+                    .connection
+                    .query(&sql, Some(&json!([table_name])))
+                    .await
+                    .expect("Error reading from table table");
+                let row = match rows.first() {
+                    Some(row) => row,
+                    None => panic!("Table '{table_name}' has not been loaded"),
+                };
+                if row.get_string("path").ok().as_deref() != Some(path) {
+                    panic!("Table '{table_name}' was not loaded from '{path}'");
                 }
-            }
-            for change in changes_undone_stack.iter() {
-                if !pruned_undones.iter().any(|undone: &JsonRow| {
-                    on_the_same_target(&undone, &change).expect("Error looking for a common target")
-                }) {
-                    pruned_undones.push(change.clone());
+                match row.content.get("loaded") {
+                    Some(JsonValue::Null) | None => {
+                        panic!("Table '{table_name}' has no interrupted load to resume")
+                    }
+                    Some(_) => row.get_unsigned("loaded").expect("Invalid 'loaded' value"),
                 }
             }
-            (pruned_dones, pruned_undones)
+        };
+        if resume {
+            tracing::info!("Resuming the load of table {table_name} after {loaded} rows");
+            for _ in 0..loaded {
+                records.next();
+            }
         }
 
-        // TODO: Think about paging when there are a lot of change records to go through.
-        let sql = format!(
-            r#"SELECT "change_id", "user", "table", "description", "action", "content"
-                 FROM "change"
-                WHERE "user" = {sql_param}
-                ORDER BY "change_id" DESC"#,
-            sql_param = SqlParam::new(&self.connection.kind()).next()
-        );
-        let params = json!([user]);
-        let history = self.connection.query(&sql, Some(&params)).await?;
+        // Add an entry corresponding to the table being loaded to the table table:
+        if force && !resume {
+            // A partitioned table must be dropped along with its partitions:
+            let mut table = Table::get_table(table_name, self)
+                .await
+                .unwrap_or_else(|_| panic!("Error getting table '{table_name}'"));
+            if !table.partitions.is_empty() {
+                table
+                    .drop_table(self)
+                    .await
+                    .unwrap_or_else(|_| panic!("Error dropping table '{table_name}'"));
+            }
 
-        // Initialize the stacks to be returned and counters:
-        let mut changes_done_stack = vec![];
-        let mut changes_undone_stack = vec![];
-        let mut do_redo_count: usize;
-        let mut undo_count: usize;
+            // Delete any messages associated with the table and then delete the table:
+            self.delete_message(table_name, None, None, None, None)
+                .await
+                .expect("Error deleting messages");
 
-        // Begin with the last change that was made:
-        let (final_change, final_action) = match history.first() {
-            None => return Ok(History::default()),
-            Some(final_change) => {
-                let final_action = ChangeAction::from_str(&final_change.get_string("action")?)?;
-                (final_change, final_action)
+            let sql = format!(
+                r#"DELETE FROM "table" WHERE "table" = {sql_param}"#,
+                sql_param = SqlParam::new(&db_kind).next(),
+            );
+            let params = json!([table_name]);
+            self.connection
+                .query(&sql, Some(&params))
+                .await
+                .expect("Error deleting from table table");
+        }
+        if !resume {
+            let sql = format!(
+                r#"INSERT INTO "table" ("table", "path", "loaded") VALUES ({sql_params})"#,
+                sql_params = SqlParam::new(&db_kind).get_as_list(3)
+            );
+            let params = json!([table_name, path, 0]);
+            self.connection
+                .query(&sql, Some(&params))
+                .await
+                .expect("Error inserting to table table");
+            self.capture_inserts("table")
+                .await
+                .expect("Error inserting to table table");
+            tracing::debug!("Table {table_name} (path: {path}) added to table table");
+        }
+
+        // Initialize a new table struct and collect its columns configuration:
+        let table = {
+            let mut table = Table {
+                name: table_name.to_string(),
+                has_change_id: self.materialize_change_id,
+                has_uuid: self.uuid_row_ids,
+                ..Default::default()
+            };
+            let table_columns = Table::get_column_table_columns(table_name, self)
+                .await
+                .expect(&format!("Error getting columns for table '{table_name}'"));
+            for column_name in headers.iter() {
+                let datatype = match table_columns.get(column_name) {
+                    None => Datatype {
+                        name: "text".to_string(),
+                        ..Default::default()
+                    },
+                    Some(col) => col.datatype.clone(),
+                };
+                let column = Column {
+                    name: column_name.to_string(),
+                    table: table_name.to_string(),
+                    datatype_hierarchy: datatype.get_all_ancestors(self).await.expect(&format!(
+                        "Error getting datatype hierarchy for '{}'",
+                        datatype.name
+                    )),
+                    datatype: datatype,
+                    nulltype: table_columns
+                        .get(column_name)
+                        .and_then(|col| col.nulltype.clone()),
+                    structure: table_columns
+                        .get(column_name)
+                        .and_then(|col| col.structure.clone()),
+                    sql_type: table_columns
+                        .get(column_name)
+                        .and_then(|col| col.sql_type.clone()),
+                    foreign_key: table_columns
+                        .get(column_name)
+                        .and_then(|col| col.foreign_key.clone()),
+                    locale: table_columns.get(column_name).and_then(|col| col.locale),
+                    transforms: table_columns
+                        .get(column_name)
+                        .map(|col| col.transforms.clone())
+                        .unwrap_or_default(),
+                    ..Default::default()
+                };
+                table.columns.insert(column_name.to_string(), column);
             }
+            table
         };
-        match final_action {
-            ChangeAction::Do | ChangeAction::Redo => {
-                do_redo_count = 1;
-                undo_count = 0;
-            }
-            ChangeAction::Undo => {
-                do_redo_count = 0;
-                undo_count = 1;
+
+        // Generate the SQL statements needed to create the table and execute them, unless the
+        // table was already created by the load that is being resumed:
+        if !resume {
+            let mut ddl = sql::generate_table_ddl(
+                &table,
+                force,
+                &db_kind,
+                &self.caching_strategy,
+                self.change_capture.as_ref(),
+            )
+            .expect("Error getting DDL");
+            ddl.append(
+                &mut self
+                    .get_referenced_index_ddl(table_name)
+                    .await
+                    .expect("Error getting DDL"),
+            );
+            ddl.append(
+                &mut self
+                    .get_search_index_ddl(table_name, &headers)
+                    .await
+                    .expect("Error getting DDL"),
+            );
+            for sql in ddl {
+                self.connection
+                    .query(&sql, None)
+                    .await
+                    .expect("Error creating table");
             }
-        };
-        let mut change_to_push = final_change;
-        let mut action_to_push = final_action;
-        tracing::debug!("Setting the change to push ({action_to_push}) to: {change_to_push:?}");
-        tracing::debug!(
-            "The do/redo count is now {do_redo_count}, and the undo count is \
-             {undo_count}."
+        }
+
+        // Insert the data into the table:
+        let mut columns = vec!["_id".to_string(), "_order".to_string()];
+        columns.append(
+            &mut headers
+                .iter()
+                .map(|k| format!(r#"{k}"#))
+                .collect::<Vec<_>>(),
         );
+        let columns_line = columns
+            .iter()
+            .map(|k| format!(r#""{k}""#))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut id: u64 = loaded + 1;
+        let mut order = id * NEW_ORDER_MULTIPLIER as u64;
+        let mut messages = vec![];
+        let sql_first_part = format!(r#"INSERT INTO "{table_name}" ({columns_line}) VALUES "#);
+        let mut sql_value_parts = vec![];
+        let mut sql_param_gen = SqlParam::new(&self.connection.kind());
+        let mut param_values = vec![];
+        let max_params = match db_kind {
+            DbKind::Sqlite => sql::MAX_PARAMS_SQLITE,
+            DbKind::Postgres => sql::MAX_PARAMS_POSTGRES,
+        };
+        let severity_overrides = SeverityOverride::get_overrides(&table.name, self)
+            .await
+            .expect("Error getting severity overrides");
+        while let Some(row) = records.next() {
+            let row = row.expect("Error processing row");
+            // We add 2 here because of _id and _order:
+            if (param_values.len() + row.len() + 2) >= max_params {
+                let sql = format!(
+                    "{sql_first_part} {sql_value_part}",
+                    sql_value_part = sql_value_parts.join(", ")
+                );
+                let values_so_far = json!(param_values);
+                self.load_chunk(table_name, &sql, &values_so_far, &messages, id - 1)
+                    .await
+                    .expect("Error inserting to table");
+                messages.clear();
+                tracing::info!(
+                    "{num_rows} rows loaded to table {table_name}",
+                    num_rows = id - 1
+                );
+                param_values.clear();
+                sql_value_parts.clear();
+                sql_param_gen.reset()
+            }
 
-        // For each action, find the point where it began, and then place it onto changes_done_stack
-        // or changes_undone_stack, as appropriate:
-        for prior_change in &history[1..] {
-            let prior_action = ChangeAction::from_str(&prior_change.get_string("action")?)?;
-            tracing::debug!("The change prior to it is a {prior_action}: {prior_change:?}.");
-            match action_to_push {
-                ChangeAction::Do => match prior_action {
-                    ChangeAction::Do | ChangeAction::Redo => {
-                        tracing::debug!(
-                            "Pushing change {cid} to changes_done",
-                            cid = change_to_push.get_string("change_id")?
-                        );
-                        changes_done_stack.push(change_to_push.clone());
-                        change_to_push = prior_change;
-                        action_to_push =
-                            ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                        do_redo_count = 1;
-                        undo_count = 0;
-                        tracing::debug!(
-                            "The next change to push is a {action_to_push}: {change_to_push:?}"
-                        );
-                    }
-                    ChangeAction::Undo => {
-                        tracing::debug!(
-                            "Pushing change {cid} to changes_done",
-                            cid = change_to_push.get_string("change_id")?
-                        );
-                        changes_done_stack.push(change_to_push.clone());
-                        change_to_push = prior_change;
-                        action_to_push =
-                            ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                        do_redo_count = 0;
-                        undo_count = 1;
-                        tracing::debug!(
-                            "The next change to push is a {action_to_push}: {change_to_push:?}"
-                        );
-                    }
-                },
-                ChangeAction::Undo => match prior_action {
-                    ChangeAction::Undo => {
-                        if do_redo_count == 0 {
-                            tracing::debug!(
-                                "Pushing change {cid} to changes_undone",
-                                cid = change_to_push.get_string("change_id")?
-                            );
-                            changes_undone_stack.push(change_to_push.clone());
-                            change_to_push = prior_change;
-                            action_to_push =
-                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                            tracing::debug!(
-                                "The next change to push is a {action_to_push}: {change_to_push:?}"
-                            );
-                            undo_count += 1;
-                        } else {
-                            do_redo_count -= 1;
-                            undo_count += 1;
-                        }
-                    }
-                    ChangeAction::Do | ChangeAction::Redo => {
-                        if undo_count == 0 {
-                            tracing::debug!(
-                                "Pushing change {cid} to changes_undone",
-                                cid = change_to_push.get_string("change_id")?
-                            );
-                            changes_undone_stack.push(change_to_push.clone());
-                            change_to_push = prior_change;
-                            action_to_push =
-                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                            do_redo_count = 1;
-                            tracing::debug!(
-                                "The next change to push is a {action_to_push}: {change_to_push:?}"
-                            );
-                        } else {
-                            do_redo_count += 1;
-                            undo_count -= 1;
-                        }
-                    }
-                },
-                ChangeAction::Redo => match prior_action {
-                    ChangeAction::Redo => {
-                        if undo_count == 0 {
-                            tracing::debug!(
-                                "Pushing change {cid} to changes_done",
-                                cid = change_to_push.get_string("change_id")?
-                            );
-                            changes_done_stack.push(change_to_push.clone());
-                            change_to_push = prior_change;
-                            action_to_push =
-                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                            tracing::debug!(
-                                "The next change to push is a {action_to_push}: {change_to_push:?}"
-                            );
-                            do_redo_count += 1;
-                        } else {
-                            do_redo_count += 1;
-                            undo_count -= 1;
-                        }
+            let mut sql_params = vec![];
+            param_values.push(json!(id));
+            sql_params.push(sql_param_gen.next());
+            param_values.push(json!(order));
+            sql_params.push(sql_param_gen.next());
+            let sql_params = {
+                for (i, value) in row.iter().enumerate() {
+                    let (column, nulltype) = {
+                        // We add 2 here because of _id and _order:
+                        let column = match columns.get(i + 2) {
+                            Some(column) => column,
+                            None => panic!("Unable to retrieve column {}", i + 2),
+                        };
+                        let nulltype = table
+                            .columns
+                            .get(column)
+                            .expect(&format!("Column '{column}' not found"))
+                            .nulltype
+                            .to_owned();
+                        (column, nulltype)
+                    };
+                    // Transform the value before it is validated, keeping the raw value in a
+                    // message if it changes:
+                    let (value, provenance) = Transform::apply_all(
+                        &table.get_config_for_column(column).transforms,
+                        value,
+                    );
+                    if let Some(message) = provenance {
+                        messages.push((id, column.to_string(), message.value.clone(), message));
                     }
-                    ChangeAction::Do => {
-                        if undo_count == 0 {
-                            tracing::debug!(
-                                "Pushing change {cid} to changes_done",
-                                cid = change_to_push.get_string("change_id")?
-                            );
-                            changes_done_stack.push(change_to_push.clone());
-                            change_to_push = prior_change;
-                            action_to_push =
-                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                            tracing::debug!(
-                                "The next change to push is a {action_to_push}: {change_to_push:?}"
-                            );
-                            // Dos begin anew.
-                            do_redo_count = 1;
-                        } else {
-                            do_redo_count += 1;
-                            undo_count -= 1;
+                    let value = value.as_str();
+                    match nulltype {
+                        Some(nulltype) if nulltype.name == "empty" && value == "" => {
+                            sql_params.push("NULL".to_string());
                         }
-                    }
-                    ChangeAction::Undo => {
-                        if do_redo_count == 0 {
-                            tracing::debug!(
-                                "Pushing change {cid} to changes_done",
-                                cid = change_to_push.get_string("change_id")?
-                            );
-                            changes_done_stack.push(change_to_push.clone());
-                            change_to_push = prior_change;
-                            action_to_push =
-                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
-                            tracing::debug!(
-                                "The next change to push is a {action_to_push}: {change_to_push:?}"
-                            );
-                            undo_count = 1;
-                        } else {
-                            do_redo_count -= 1;
-                            undo_count += 1;
+                        _ => {
+                            if let Some(nulltype) = nulltype {
+                                if nulltype.name != "empty" {
+                                    tracing::warn!("Nulltype '{}' not supported", nulltype.name);
+                                }
+                            }
+                            // Use the value, read in the column's locale, to create a cell:
+                            let column_config = table.get_config_for_column(column);
+                            let sql_type = column_config.get_sql_type();
+                            let value = &column_config
+                                .locale
+                                .unwrap_or(self.locale)
+                                .parse(value, &sql_type);
+                            let mut cell = {
+                                let value = match serde_json::from_str::<JsonValue>(value) {
+                                    Ok(JsonValue::Number(num)) => JsonValue::Number(num),
+                                    // Binary values are saved in hexadecimal:
+                                    _ if sql::canonical_sql_type(&sql_type) == Some("BLOB") => {
+                                        match hex::decode(value) {
+                                            Ok(bytes) => json!(sql::encode_binary(&bytes)),
+                                            Err(_) => json!(value),
+                                        }
+                                    }
+                                    _ => json!(value),
+                                };
+                                let value = JsonRow::nullify_value(&table, column, &value);
+                                Cell {
+                                    text: sql::json_to_string(&value),
+                                    value: value,
+                                    ..Default::default()
+                                }
+                            };
+
+                            // Validate the cell and add any messages to the message table:
+                            if self.validation_level != ValidationLevel::None {
+                                cell.validate_sql_type(&table.get_config_for_column(column))
+                                    .expect("Error validating cell");
+                                cell.apply_severity_overrides(&severity_overrides);
+                                for message in cell.messages.iter() {
+                                    messages.push((
+                                        id,
+                                        column.to_string(),
+                                        cell.value.clone(),
+                                        message.clone(),
+                                    ));
+                                }
+                            }
+
+                            // Add the parameter for the value to the SQL insert statement:
+                            if cell.has_sql_type_error() || cell.value == JsonValue::Null {
+                                sql_params.push("NULL".to_string());
+                            } else {
+                                sql_params.push(sql_param_gen.next());
+                                param_values.push(sql::to_sql_param(&cell.value, &sql_type));
+                            }
                         }
-                    }
-                },
+                    };
+                }
+                sql_params.join(", ")
             };
+            // Add two extra SQL_PARAM for _id and _order:
+            sql_value_parts.push(format!("({sql_params})"));
+            id += 1;
+            order += NEW_ORDER_MULTIPLIER as u64;
+        }
+        if param_values.len() > 0 {
+            let sql = format!(
+                "{sql_first_part} {sql_value_part}",
+                sql_value_part = sql_value_parts.join(", ")
+            );
+            let param_values = json!(param_values);
+            self.load_chunk(table_name, &sql, &param_values, &messages, id - 1)
+                .await
+                .expect(&format!("Error inserting to {table_name}"));
+            tracing::info!(
+                "{num_rows} rows loaded to table {table_name}",
+                num_rows = id - 1
+            );
+        }
 
-            tracing::debug!(
-                "Updated the do/redo count to {do_redo_count}, and the undo count to \
-                 {undo_count}."
+        if self.validation_level == ValidationLevel::Full {
+            self.validate_table(&table)
+                .await
+                .expect("Error validating table");
+            let dependent_tables = table
+                .get_dependent_tables(None, &self)
+                .await
+                .expect("Error getting dependent tables");
+            for table in &dependent_tables {
+                tracing::debug!("Validating dependent table '{}'", table.name);
+                self.validate_structure_for_table(&table)
+                    .await
+                    .expect("Error validating table");
+            }
+        }
+
+        self.capture_inserts(table_name)
+            .await
+            .expect("Error inserting to table");
+
+        // The load is complete, so there is nothing more to resume:
+        let sql = format!(
+            r#"UPDATE "table" SET "loaded" = NULL WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&db_kind).next(),
+        );
+        self.connection
+            .query(&sql, Some(&json!([table_name])))
+            .await
+            .expect("Error updating table table");
+
+        // Record the checksums of the table and of its file as of this load:
+        let checksum = self
+            .table_checksum(table_name)
+            .await
+            .unwrap_or_else(|e| panic!("Error computing the checksum of '{table_name}': {e}"));
+        let file_checksum = self
+            .file_checksum(path)
+            .unwrap_or_else(|e| panic!("Error computing the checksum of '{path}': {e}"));
+        self.set_checksums(table_name, &checksum, &file_checksum)
+            .await
+            .expect("Error updating table table");
+
+        // Rows that are added to a table once it has been partitioned are routed to their
+        // partitions by the database, so a table only needs to be partitioned once:
+        if let Some(column) = &self.partition_by {
+            let table = Table::get_table(table_name, self)
+                .await
+                .unwrap_or_else(|_| panic!("Error getting table '{table_name}'"));
+            if table.partitions.is_empty() {
+                self.partition_table(table_name, column)
+                    .await
+                    .unwrap_or_else(|_| panic!("Error partitioning table '{table_name}'"));
+            }
+        }
+
+        id - 1
+    }
+
+    /// Partition the given table by the values of the given column, so that the rows with each
+    /// value are held in a table of their own, and the rows with no value in a default partition,
+    /// to which rows with values that have no partition of their own are also added later on.
+    /// The table can still be read and edited as a whole, while reads that are filtered on the
+    /// column only read the partitions that can match (see [Select::partitions]). In PostgreSQL,
+    /// the table becomes a partitioned table, while in SQLite it becomes a view on the tables that
+    /// hold its partitions. Returns the partitions that were created.
+    pub async fn partition_table(&self, table_name: &str, column: &str) -> Result<Vec<Partition>> {
+        tracing::trace!("Relatable::partition_table({table_name}, {column})");
+        if self.get_search_columns(table_name).await?.is_some() {
+            return Err(RelatableError::InputError(format!(
+                "Table '{table_name}' has a search index, which must be dropped before the table \
+                 can be partitioned"
+            ))
+            .into());
+        }
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let partitions = self._partition_table(table_name, column, &mut tx)?;
+        tx.commit()?;
+        Ok(partitions)
+    }
+
+    /// Partition the given table by the values of the given column, using the given transaction
+    /// (see [Relatable::partition_table()]).
+    pub fn _partition_table(
+        &self,
+        table_name: &str,
+        column: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Vec<Partition>> {
+        tracing::trace!("Relatable::_partition_table({table_name}, {column}, tx)");
+        if !Table::_table_exists(table_name, tx)? {
+            return Err(
+                RelatableError::MissingError(format!("No table named '{table_name}'")).into(),
             );
+        }
+        let mut table = Table::_get_table(table_name, tx)?;
+        if !table.partitions.is_empty() {
+            return Err(RelatableError::InputError(format!(
+                "Table '{table_name}' is already partitioned"
+            ))
+            .into());
+        }
+        if !table.columns.contains_key(column) {
+            return Err(RelatableError::InputError(format!(
+                "Table '{table_name}' has no column '{column}'"
+            ))
+            .into());
+        }
+        if !Table::_table_exists("partition", tx)? {
+            for sql in sql::generate_partition_table_ddl(false, &tx.kind()) {
+                tx.query(&sql, None)?;
+            }
+        }
+
+        // Each value of the column gets a partition of its own:
+        let sql = format!(
+            r#"SELECT DISTINCT "{column}" AS "value" FROM "{table_name}"
+               WHERE "{column}" IS NOT NULL
+               ORDER BY "{column}""#
+        );
+        let mut values = tx
+            .query(&sql, None)?
+            .iter()
+            .map(|row| row.get_value("value").map(Some))
+            .collect::<Result<Vec<_>>>()?;
+        values.push(None);
+        for (i, value) in values.into_iter().enumerate() {
+            let name = match value {
+                Some(_) => format!("{table_name}__{}", i + 1),
+                None => format!("{table_name}__default"),
+            };
+            if Table::_table_exists(&name, tx)? {
+                return Err(RelatableError::InputError(format!(
+                    "Cannot partition '{table_name}': a table named '{name}' already exists"
+                ))
+                .into());
+            }
+            if let Some(value) = &value {
+                sql::partition_literal(value)?;
+            }
+            table.partitions.push(Partition {
+                name,
+                table: table_name.to_string(),
+                column: column.to_string(),
+                value,
+            });
+        }
+
+        // Set the rows of the table aside, and then recreate it with its partitions:
+        let unpartitioned = format!("{table_name}__unpartitioned");
+        tx.query(
+            &format!(r#"CREATE TABLE "{unpartitioned}" AS SELECT * FROM "{table_name}""#),
+            None,
+        )?;
+        let drop_sql = match tx.kind() {
+            DbKind::Sqlite => format!(r#"DROP TABLE "{table_name}""#),
+            DbKind::Postgres => format!(r#"DROP TABLE "{table_name}" CASCADE"#),
+        };
+        tx.query(&drop_sql, None)?;
+        for sql in sql::generate_table_ddl(
+            &table,
+            true,
+            &tx.kind(),
+            &self.caching_strategy,
+            self.change_capture.as_ref(),
+        )? {
+            tx.query(&sql, None)?;
+        }
+        let mut columns = vec!["_id", "_order"];
+        if table.has_change_id {
+            columns.push("_change_id");
+        }
+        if table.has_uuid {
+            columns.push("_uuid");
+        }
+        columns.extend(table.columns.keys().map(|column| column.as_str()));
+        let column_list = columns
+            .iter()
+            .map(|column| format!(r#""{column}""#))
+            .collect::<Vec<_>>()
+            .join(", ");
+        for partition in &table.partitions {
+            let condition = sql::partition_condition(partition, &table.partitions, "")?;
+            tx.query(
+                &format!(
+                    r#"INSERT INTO "{partition}" ({column_list})
+                       SELECT {column_list} FROM "{unpartitioned}" WHERE {condition}"#,
+                    partition = partition.name
+                ),
+                None,
+            )?;
+            let mut sql_param = SqlParam::new(&tx.kind());
+            let mut params = vec![json!(partition.name), json!(table_name), json!(column)];
+            let mut sql_params = vec![sql_param.next(), sql_param.next(), sql_param.next()];
+            match &partition.value {
+                Some(value) => {
+                    params.push(json!(value.to_string()));
+                    sql_params.push(sql_param.next());
+                }
+                None => sql_params.push("NULL".to_string()),
+            };
+            let sql = format!(
+                r#"INSERT INTO "partition" ("partition", "table", "column", "value")
+                   VALUES ({})"#,
+                sql_params.join(", ")
+            );
+            tx.query(&sql, Some(&json!(params)))?;
+        }
+        tx.query(&format!(r#"DROP TABLE "{unpartitioned}""#), None)?;
+        self._capture_inserts(table_name, tx)?;
+        tracing::info!(
+            "Partitioned table '{table_name}' by '{column}' into {} partitions",
+            table.partitions.len()
+        );
+        Ok(table.partitions)
+    }
+
+    /// Returns the DDL needed to recreate the full-text search index on the given table, if it
+    /// has one, when the table is reloaded with the given columns. Columns that were indexed but
+    /// are no longer in the table are left out of the index.
+    async fn get_search_index_ddl(
+        &self,
+        table_name: &str,
+        columns: &[String],
+    ) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::get_search_index_ddl({table_name}, {columns:?})");
+        let indexed = match self.get_search_columns(table_name).await? {
+            None => return Ok(vec![]),
+            Some(indexed) => indexed
+                .into_iter()
+                .filter(|column| columns.contains(column))
+                .collect::<Vec<_>>(),
+        };
+        if indexed.is_empty() {
+            tracing::warn!("None of the indexed columns of '{table_name}' remain to be searched");
+            return Ok(sql::generate_drop_search_index_ddl(
+                table_name,
+                &self.connection.kind(),
+            ));
+        }
+        Ok(sql::generate_search_index_ddl(
+            table_name,
+            &indexed,
+            &self.connection.kind(),
+        ))
+    }
+
+    /// Returns the DDL needed to create the unique indexes on the columns of the given table that
+    /// are referenced by the foreign keys of other tables, which are dropped along with the table
+    /// when it is reloaded.
+    async fn get_referenced_index_ddl(&self, table_name: &str) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::get_referenced_index_ddl({table_name})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        if !Table::_table_exists("column", &mut tx)?
+            || !Table::_has_column("column", "foreign_key", &mut tx)?
+        {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            r#"SELECT "structure" FROM "column"
+               WHERE "table" != {sql_param} AND COALESCE("foreign_key", '') != ''"#,
+            sql_param = SqlParam::new(&tx.kind()).next(),
+        );
+        let mut ddl = vec![];
+        for row in tx.query(&sql, Some(&json!([table_name])))? {
+            if let Ok(Structure::From(Some(s_table), s_column)) =
+                Structure::from_str(&row.get_string("structure").unwrap_or_default())
+            {
+                if s_table == table_name {
+                    ddl.push(sql::generate_referenced_index_ddl(&s_table, &s_column));
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(ddl)
+    }
+
+    /// Inserts a chunk of the rows of the given table, using the given SQL statement and
+    /// parameters, adds the given messages about them, each given as a tuple of a row ID, a column
+    /// name, a value, and a [Message], and records in the table table that `loaded` rows of the
+    /// table have now been loaded, all in a single transaction.
+    async fn load_chunk(
+        &self,
+        table_name: &str,
+        sql: &str,
+        params: &JsonValue,
+        messages: &Vec<(u64, String, JsonValue, Message)>,
+        loaded: u64,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::load_chunk({table_name:?}, {sql:?}, {params:?}, {messages:?}, {loaded})"
+        );
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        tx.query(sql, Some(params))?;
+        for (row, column, value, message) in messages {
+            let (msg_id, msg) = Relatable::_add_message(
+                "rltbl",
+                table_name,
+                row,
+                column,
+                value,
+                &message.level,
+                &message.rule,
+                &message.message,
+                &mut tx,
+            )?;
+            tracing::debug!("Added message (ID {msg_id}): {msg:?}");
+        }
+        let mut sql_param_gen = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"UPDATE "table" SET "loaded" = {loaded_param} WHERE "table" = {table_param}"#,
+            loaded_param = sql_param_gen.next(),
+            table_param = sql_param_gen.next(),
+        );
+        tx.query(&sql, Some(&json!([loaded, table_name])))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Use the given [Mapping] to add the records of the source file at the given path to the end
+    /// of the mapping's table, as a single change attributed to the given user and described by
+    /// the given message, which can be undone all at once. Returns the number of rows added.
+    pub async fn load_mapped(
+        &self,
+        mapping: &Mapping,
+        path: &str,
+        user: &str,
+        message: Option<&str>,
+    ) -> Result<usize> {
+        tracing::trace!("Relatable::load_mapped({mapping:?}, {path:?}, {user:?}, {message:?})");
+        mapping.check()?;
+        let columns = self
+            .fetch_columns(&mapping.table)
+            .await?
+            .into_iter()
+            .map(|column| column.name)
+            .collect::<Vec<_>>();
+        if let Some(column) = mapping
+            .columns
+            .iter()
+            .find(|column| !columns.contains(&column.target))
+        {
+            return Err(RelatableError::InputError(format!(
+                "No column '{target}' in table '{table}'",
+                target = column.target,
+                table = mapping.table
+            ))
+            .into());
+        }
+
+        let (headers, records) = Mapping::read_source(path)?;
+        let (headers, renames) = self.rename_headers(&headers)?;
+        let message = Relatable::describe_column_renames(message, &renames);
+        let message = message.as_deref();
+        let mut operations = vec![];
+        for record in &records {
+            operations.push(BatchOperation::AddRow {
+                table: mapping.table.to_string(),
+                after: None,
+                values: mapping.apply(&headers, record)?.content,
+            });
+        }
+        self.batch(user, message, &operations).await?;
+        Ok(operations.len())
+    }
+
+    /// Load the records in the TSV file at the given path into the given meta table, which must
+    /// be one of "change", "history", or "message", so that a test scenario or demo can start from
+    /// a pre-existing edit history. Returns the number of records loaded. See
+    /// [Relatable::_load_meta_table()] for how the IDs in the file are remapped.
+    pub async fn load_meta_table(&self, table: &str, path: &str) -> Result<usize> {
+        tracing::trace!("Relatable::load_meta_table({table:?}, {path:?})");
+        let mut rdr = ReaderBuilder::new()
+            .delimiter(b'\t')
+            .from_reader(File::open(path)?);
+        let headers = rdr
+            .headers()?
+            .iter()
+            .map(|h| h.to_string())
+            .collect::<Vec<_>>();
+        let mut records = vec![];
+        for record in rdr.records() {
+            let record = record?;
+            let mut json_row = JsonRow::new();
+            for (header, value) in headers.iter().zip(record.iter()) {
+                if !value.is_empty() {
+                    json_row.content.insert(header.to_string(), json!(value));
+                }
+            }
+            records.push(json_row);
+        }
+
+        self.retry_serialization_failures(|| async {
+            let mut conn = self.connection.reconnect()?;
+            let mut tx = self.begin_write(&mut conn).await?;
+            let num_loaded = self._load_meta_table(table, &headers, &records, &mut tx)?;
+            tx.commit()?;
+            Ok(num_loaded)
+        })
+        .await
+    }
+
+    /// Load the given records into the given meta table using the given transaction. The IDs in
+    /// the records are remapped so as not to collide with those already in the database: message
+    /// and history records are assigned new IDs; the change_id and change_group of each change
+    /// record are offset by the largest change_id already in the change table; and the change_id
+    /// of each history record is offset so that the largest of them refers to the last change in
+    /// the change table, so that a change fixture followed by the history fixture that refers to
+    /// it can be loaded into a database that already has an edit history. Every history record
+    /// must refer to a change record, for the same table, that has no history yet.
+    pub fn _load_meta_table(
+        &self,
+        table: &str,
+        headers: &[String],
+        records: &[JsonRow],
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<usize> {
+        tracing::trace!("Relatable::_load_meta_table({table:?}, {headers:?}, {records:?}, tx)");
+        let (columns, ignored) = match table {
+            "change" => (
+                vec![
+                    "change_id",
+                    "datetime",
+                    "user",
+                    "action",
+                    "table",
+                    "description",
+                    "message",
+                    "change_group",
+                    "idempotency_key",
+                    "content",
+                ],
+                None,
+            ),
+            "history" => (
+                vec!["change_id", "table", "row", "before", "after"],
+                Some("history_id"),
+            ),
+            "message" => (
+                vec![
+                    "added_by", "table", "row", "column", "value", "level", "rule", "message",
+                ],
+                Some("message_id"),
+            ),
+            _ => {
+                return Err(RelatableError::InputError(format!(
+                    "Cannot load '{table}': only the change, history, and message tables \
+                     can be loaded from a fixture"
+                ))
+                .into())
+            }
+        };
+        for header in headers {
+            if !columns.contains(&header.as_str()) && Some(header.as_str()) != ignored {
+                return Err(RelatableError::InputError(format!(
+                    "Unrecognized column '{header}' for the {table} table"
+                ))
+                .into());
+            }
+        }
+
+        let get_offset = |tx: &mut DbTransaction<'_>| -> Result<u64> {
+            match table {
+                "change" => {
+                    let sql = r#"SELECT COALESCE(MAX("change_id"), 0) FROM "change""#;
+                    match tx.query_value(sql, None)? {
+                        Some(offset) => sql::json_to_unsigned(&offset),
+                        None => Ok(0),
+                    }
+                }
+                "history" => {
+                    // The history refers to the change records that were loaded last:
+                    let sql = r#"SELECT COALESCE(MAX("change_id"), 0) FROM "change""#;
+                    let last_change_id = match tx.query_value(sql, None)? {
+                        Some(change_id) => sql::json_to_unsigned(&change_id)?,
+                        None => 0,
+                    };
+                    let mut last_fixture_change_id = 0;
+                    for record in records {
+                        if let Some(change_id) = record.content.get("change_id") {
+                            let change_id = sql::json_to_unsigned(change_id)?;
+                            last_fixture_change_id = last_fixture_change_id.max(change_id);
+                        }
+                    }
+                    Ok(last_change_id.saturating_sub(last_fixture_change_id))
+                }
+                _ => Ok(0),
+            }
+        };
+        let offset = get_offset(tx)?;
+
+        if table == "history" {
+            // Make sure that each history record refers to a change record for the same table
+            // that does not already have a history:
+            let mut checked = HashSet::new();
+            for record in records {
+                let change_id = match record.content.get("change_id") {
+                    Some(change_id) => sql::json_to_unsigned(change_id)? + offset,
+                    None => {
+                        return Err(RelatableError::InputError(
+                            "Every history record must have a change_id".to_string(),
+                        )
+                        .into())
+                    }
+                };
+                let table = record.get_string("table")?;
+                if !checked.insert((change_id, table.clone())) {
+                    continue;
+                }
+                let mut sql_param = SqlParam::new(&tx.kind());
+                let sql = format!(
+                    r#"SELECT 1 FROM "change"
+                        WHERE "change_id" = {sql_param_1}
+                          AND "table" = {sql_param_2}
+                          AND NOT EXISTS (
+                            SELECT 1 FROM "history" WHERE "change_id" = {sql_param_3}
+                          )"#,
+                    sql_param_1 = sql_param.next(),
+                    sql_param_2 = sql_param.next(),
+                    sql_param_3 = sql_param.next(),
+                );
+                let params = json!([change_id, table, change_id]);
+                if tx.query_value(&sql, Some(&params))?.is_none() {
+                    return Err(RelatableError::InputError(format!(
+                        "No change record without a history matches the history record for \
+                         change {id} to table '{table}'",
+                        id = change_id - offset
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        let mut tables = HashSet::new();
+        for record in records {
+            let mut record = record.clone();
+            for id_column in ["change_id", "change_group"] {
+                if let Some(id) = record.content.get(id_column) {
+                    let id = sql::json_to_unsigned(id)?;
+                    record
+                        .content
+                        .insert(id_column.to_string(), json!(id + offset));
+                }
+            }
+            if let Some(row) = record.content.get("row") {
+                let row = sql::json_to_unsigned(row)?;
+                record.content.insert("row".to_string(), json!(row));
+            }
+            if let Some(ignored) = ignored {
+                record.content.remove(ignored);
+            }
+            if let Some(JsonValue::String(table)) = record.content.get("table") {
+                tables.insert(table.to_string());
+            }
+
+            // Make sure that the user who made the change is present in the user table:
+            if let Some(JsonValue::String(user)) = record.content.get("user") {
+                let sql = format!(
+                    r#"SELECT 1 FROM "user" WHERE "name" = {sql_param}"#,
+                    sql_param = SqlParam::new(&tx.kind()).next()
+                );
+                let params = json!([user]);
+                if tx.query_value(&sql, Some(&params))?.is_none() {
+                    let sql = format!(
+                        r#"INSERT INTO "user" ("name", "color") VALUES ({sql_params})"#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
+                    );
+                    let color = random_color::RandomColor::new().to_hex();
+                    let params = json!([user, color]);
+                    tx.query(&sql, Some(&params))?;
+                }
+            }
+
+            let columns = record.content.keys().cloned().collect::<Vec<_>>();
+            let values = record.content.values().cloned().collect::<Vec<_>>();
+            let sql = format!(
+                r#"INSERT INTO "{table}" ({columns}) VALUES ({sql_params})"#,
+                columns = columns
+                    .iter()
+                    .map(|column| format!(r#""{column}""#))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                sql_params = SqlParam::new(&tx.kind()).get_as_list(columns.len())
+            );
+            tx.query(&sql, Some(&json!(values)))?;
+        }
+
+        if table == "change" {
+            // Explicitly assigned IDs do not advance the sequence in PostgreSQL:
+            if let DbKind::Postgres = tx.kind() {
+                let sql = r#"SELECT setval(pg_get_serial_sequence('"change"', 'change_id'),
+                                           (SELECT MAX("change_id") FROM "change"))"#;
+                tx.query(sql, None)?;
+            }
+        }
+
+        // The history determines the _change_id of each row, which may have been cached:
+        if table == "history" {
+            match self.caching_strategy {
+                CachingStrategy::None => (),
+                CachingStrategy::Memory(_) => {
+                    for table in &tables {
+                        self.clear_mem_cache(table);
+                    }
+                }
+                _ => Relatable::clear_cache(tx, None)?,
+            };
+        }
+
+        // Loaded messages need content hashes in order to be recognized when revalidating:
+        if table == "message" {
+            Relatable::_hash_messages(tx)?;
+        }
+
+        Ok(records.len())
+    }
+
+    /// Save all of the tables that have entries in the table table to the path indicated for each
+    /// table there, unless `save_dir` has been given, in which case save them all there instead.
+    pub async fn save_all(&self, save_dir: Option<&str>) -> Result<()> {
+        tracing::trace!("Relatable::save_all({save_dir:?})");
+        if self.sandboxed && save_dir.is_none() {
+            return Err(RelatableError::InputError(
+                "The tables in a sandbox can only be saved to a given directory".to_string(),
+            )
+            .into());
+        }
+        let sql = format!(
+            r#"SELECT "table", "path" FROM "table" WHERE "path" {is_not} NULL"#,
+            is_not = sql::is_not_clause(&self.connection.kind())
+        );
+        let table_rows = self.connection.query(&sql, None).await?;
+        for table_row in table_rows {
+            let table_name = table_row.get_string("table")?;
+            self.save_table(&table_name, save_dir).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Save the given table to the path indicated for it in the table table, unless `save_dir` has
+    /// been given, in which case save it there instead (see [Relatable::save_all()]).
+    pub async fn save_table(&self, table_name: &str, save_dir: Option<&str>) -> Result<()> {
+        tracing::trace!("Relatable::save_table({table_name:?}, {save_dir:?})");
+        let path = match save_dir {
+            Some(save_dir) => format!("{save_dir}/{table_name}.tsv"),
+            None => {
+                let sql = format!(
+                    r#"SELECT "path" FROM "table" WHERE "table" = {}"#,
+                    SqlParam::new(&self.connection.kind()).next()
+                );
+                match self
+                    .connection
+                    .query_value(&sql, Some(&json!([table_name])))
+                    .await?
+                {
+                    Some(JsonValue::String(path)) => path,
+                    _ => {
+                        return Err(RelatableError::InputError(format!(
+                            "Table '{table_name}' has no path to save it to"
+                        ))
+                        .into())
+                    }
+                }
+            }
+        };
+        let mut writer = match path.to_lowercase().ends_with(".csv") {
+            true => WriterBuilder::new()
+                .delimiter(self.get_delimiter(&path))
+                .from_path(path)?,
+            false => WriterBuilder::new()
+                .delimiter(b'\t')
+                .quote_style(QuoteStyle::Never)
+                .from_path(path)?,
+        };
+        let (header_row, rows) = self.get_saved_rows(table_name).await?;
+        writer.write_record(header_row)?;
+        for values in &rows {
+            writer.write_record(values)?;
+        }
+        writer.flush()?;
+
+        // The table and its file are now the same:
+        if save_dir.is_none() {
+            let checksum = checksum_rows(&rows);
+            self.set_checksums(table_name, &checksum, &checksum).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the header and the rows of the given table as they are written to its file when it is
+    /// saved (see [Relatable::save_all()]).
+    async fn get_saved_rows(&self, table_name: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+        tracing::trace!("Relatable::get_saved_rows({table_name:?})");
+        let mut table = Table::get_table(table_name, self).await?;
+        table.set_view(self, "text").await?;
+        let header_row = self
+            .fetch_columns(table_name)
+            .await?
+            .iter()
+            .map(|c| c.name.to_string())
+            .collect::<Vec<_>>();
+        let sql = format!(
+            r#"SELECT {columns} FROM "{table_name}_text_view" ORDER BY "_order""#,
+            columns = header_row
+                .iter()
+                .map(|c| format!(r#""{c}""#))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let mut rows = vec![];
+        for data_row in self.connection.query(&sql, None).await? {
+            let values = {
+                let mut str_values = vec![];
+                for (column, value) in data_row.content.iter() {
+                    // The values of the columns that have no locale of their own are written
+                    // in this instance's locale:
+                    let localize = |text: String| match table.columns.get(column) {
+                        Some(config) if config.locale.is_none() => {
+                            self.locale.format(&text, &config.get_sql_type())
+                        }
+                        _ => text,
+                    };
+                    match value {
+                        JsonValue::String(s) => str_values.push(localize(s.to_string())),
+                        JsonValue::Number(n) => str_values.push(localize(n.to_string())),
+                        JsonValue::Null => {
+                            match &table
+                                .columns
+                                .get(column)
+                                .ok_or(RelatableError::InputError(format!(
+                                    "Column '{column}' not found"
+                                )))?
+                                .nulltype
+                            {
+                                // Note that the behaviour for the 'empty' nulltype happens
+                                // to be the same as that for no nulltype, but in general
+                                // that won't be true for every nulltype.
+                                Some(nulltype) if nulltype.name == "empty" => {
+                                    str_values.push("".to_string());
+                                }
+                                Some(unsup) => {
+                                    tracing::warn!("Unsupported nulltype: '{}'", unsup.name);
+                                    str_values.push("".to_string());
+                                }
+                                None => {
+                                    str_values.push("".to_string());
+                                }
+                            };
+                        }
+                        _ => {
+                            return Err(RelatableError::DataError(format!(
+                                "Value {value} is not a string, number or NULL"
+                            ))
+                            .into());
+                        }
+                    }
+                }
+                str_values
+            };
+            rows.push(values);
+        }
+        Ok((header_row, rows))
+    }
+
+    /// Record the given checksums of the contents of the given table and of its file, as of now,
+    /// in the table table (see [Relatable::verify_table()]).
+    async fn set_checksums(
+        &self,
+        table_name: &str,
+        checksum: &str,
+        file_checksum: &str,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::set_checksums({table_name:?}, {checksum:?}, {file_checksum:?})"
+        );
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let sql = format!(
+            r#"UPDATE "table" SET "checksum" = {sql_param_1}, "file_checksum" = {sql_param_2}
+               WHERE "table" = {sql_param_3}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+        );
+        let params = json!([checksum, file_checksum, table_name]);
+        self.connection.query(&sql, Some(&params)).await?;
+        Ok(())
+    }
+
+    /// Compute the checksum of the contents of the given table, as they would be saved (see
+    /// [checksum_rows()]).
+    pub async fn table_checksum(&self, table_name: &str) -> Result<String> {
+        tracing::trace!("Relatable::table_checksum({table_name:?})");
+        let (_, rows) = self.get_saved_rows(table_name).await?;
+        Ok(checksum_rows(&rows))
+    }
+
+    /// Compute the checksum of the rows of the TSV (or CSV) file at the given path, not including
+    /// its header (see [checksum_rows()]).
+    pub fn file_checksum(&self, path: &str) -> Result<String> {
+        tracing::trace!("Relatable::file_checksum({path:?})");
+        let (reader, delimiter) = self.open_table_file(path)?;
+        let mut rdr = ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let rows = rdr
+            .records()
+            .map(|record| Ok(record?.iter().map(|value| value.to_string()).collect()))
+            .collect::<Result<Vec<Vec<String>>>>()?;
+        Ok(checksum_rows(&rows))
+    }
+
+    /// Compare the checksums of the contents of the given table and of the file that it is loaded
+    /// from and saved to with the checksums recorded when the table was last loaded or saved, to
+    /// find out whether the table has been changed without being saved, or the file without
+    /// being loaded.
+    pub async fn verify_table(&self, table_name: &str) -> Result<Verification> {
+        tracing::trace!("Relatable::verify_table({table_name:?})");
+        let sql = format!(
+            r#"SELECT "path", "checksum", "file_checksum" FROM "table" WHERE "table" = {}"#,
+            SqlParam::new(&self.connection.kind()).next()
+        );
+        let Some(row) = self
+            .connection
+            .query_one(&sql, Some(&json!([table_name])))
+            .await?
+        else {
+            return Err(RelatableError::InputError(format!("No table '{table_name}'")).into());
+        };
+        let path = row.get_optional_string("path")?;
+        let checksum = self.table_checksum(table_name).await?;
+        let file_checksum = match &path {
+            Some(path) if FilePath::new(path).exists() => Some(self.file_checksum(path)?),
+            _ => None,
+        };
+        let loaded_checksum = row.get_optional_string("checksum")?;
+        let loaded_file_checksum = row.get_optional_string("file_checksum")?;
+        let drift = match (&loaded_checksum, &loaded_file_checksum, &file_checksum) {
+            (_, _, None) => match loaded_checksum == Some(checksum.clone()) {
+                true => Drift::None,
+                false => Drift::Unknown,
+            },
+            (Some(loaded_checksum), Some(loaded_file_checksum), Some(file_checksum)) => {
+                match (
+                    *loaded_checksum != checksum,
+                    loaded_file_checksum != file_checksum,
+                ) {
+                    (false, false) => Drift::None,
+                    (true, false) => Drift::Table,
+                    (false, true) => Drift::File,
+                    (true, true) => Drift::Both,
+                }
+            }
+            // Without the checksums from the last load or save, the table and the file can only
+            // be compared with each other:
+            (_, _, Some(file_checksum)) => match *file_checksum == checksum {
+                true => Drift::None,
+                false => Drift::Unknown,
+            },
+        };
+        Ok(Verification {
+            table: table_name.to_string(),
+            path,
+            checksum,
+            file_checksum,
+            loaded_checksum,
+            loaded_file_checksum,
+            drift,
+        })
+    }
+
+    /// Set whether the given table is saved to its path automatically whenever it is changed
+    /// (see [Relatable::autosave()]).
+    pub async fn set_autosave(&self, table_name: &str, autosave: bool) -> Result<()> {
+        tracing::trace!("Relatable::set_autosave({table_name:?}, {autosave})");
+        let sql = format!(
+            r#"SELECT "path" FROM "table" WHERE "table" = {}"#,
+            SqlParam::new(&self.connection.kind()).next()
+        );
+        let Some(row) = self
+            .connection
+            .query_one(&sql, Some(&json!([table_name])))
+            .await?
+        else {
+            return Err(RelatableError::InputError(format!("No table '{table_name}'")).into());
+        };
+        if autosave && row.get_optional_string("path")?.is_none() {
+            return Err(RelatableError::InputError(format!(
+                "Table '{table_name}' has no path to save it to"
+            ))
+            .into());
+        }
+        let sql = format!(
+            r#"UPDATE "table" SET "autosave" = {autosave} WHERE "table" = {sql_param}"#,
+            autosave = match autosave {
+                true => "TRUE",
+                false => "FALSE",
+            },
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        self.connection
+            .query(&sql, Some(&json!([table_name])))
+            .await?;
+        Ok(())
+    }
+
+    /// Get the names of the tables that are saved to their paths automatically whenever they are
+    /// changed.
+    pub async fn get_autosave_tables(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::get_autosave_tables()");
+        let sql = format!(
+            r#"SELECT "table" FROM "table" WHERE "autosave" = TRUE AND "path" {is_not} NULL
+               ORDER BY "_id""#,
+            is_not = sql::is_not_clause(&self.connection.kind())
+        );
+        self.connection
+            .query(&sql, None)
+            .await?
+            .iter()
+            .map(|row| row.get_string("table"))
+            .collect()
+    }
+
+    /// Set the maximum number of rows of the given table that may be asked for at once in a
+    /// request, or remove it, so that only [Relatable::max_limit] applies (see
+    /// [Relatable::get_max_limit()]).
+    pub async fn set_max_limit(&self, table_name: &str, max_limit: Option<usize>) -> Result<()> {
+        tracing::trace!("Relatable::set_max_limit({table_name:?}, {max_limit:?})");
+        if !Table::table_exists(table_name, self).await? {
+            return Err(RelatableError::InputError(format!("No table '{table_name}'")).into());
+        }
+        let sql = format!(
+            r#"UPDATE "table" SET "max_limit" = {max_limit} WHERE "table" = {sql_param}"#,
+            max_limit = match max_limit {
+                Some(max_limit) => max_limit.to_string(),
+                None => "NULL".to_string(),
+            },
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        self.connection
+            .query(&sql, Some(&json!([table_name])))
+            .await?;
+        Ok(())
+    }
+
+    /// Get the maximum number of rows of the given table that may be asked for at once in a
+    /// request: the lesser of [Relatable::max_limit] and the table's own maximum, if it has one,
+    /// where zero means no maximum (see [Select::max_limit()]).
+    pub async fn get_max_limit(&self, table_name: &str) -> Result<usize> {
+        tracing::trace!("Relatable::get_max_limit({table_name:?})");
+        let sql = format!(
+            r#"SELECT "max_limit" FROM "table" WHERE "table" = {}"#,
+            SqlParam::new(&self.connection.kind()).next()
+        );
+        let table_max = match self
+            .connection
+            .query_value(&sql, Some(&json!([table_name])))
+            .await?
+        {
+            None | Some(JsonValue::Null) => 0,
+            Some(value) => sql::json_to_unsigned(&value)? as usize,
+        };
+        Ok(match (self.max_limit, table_max) {
+            (0, max) | (max, 0) => max,
+            (global, table) => global.min(table),
+        })
+    }
+
+    /// Set whether the given table is in wide mode, or, given `None`, have it be in wide mode
+    /// only when it has more than [wide_column_limit](Relatable::wide_column_limit) columns (see
+    /// [Table::is_wide()]). The table's default view is created again accordingly.
+    pub async fn set_wide_mode(&self, table_name: &str, wide: Option<bool>) -> Result<()> {
+        tracing::trace!("Relatable::set_wide_mode({table_name:?}, {wide:?})");
+        if !Table::table_exists(table_name, self).await? {
+            return Err(RelatableError::InputError(format!("No table '{table_name}'")).into());
+        }
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let sql = format!(
+            r#"UPDATE "table" SET "wide" = {wide} WHERE "table" = {sql_param}"#,
+            wide = match wide {
+                Some(true) => "TRUE",
+                Some(false) => "FALSE",
+                None => "NULL",
+            },
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        tx.query(&sql, Some(&json!([table_name])))?;
+        // The cached results of the table's default view no longer have the right columns:
+        Relatable::clear_cache(&mut tx, Some(table_name))?;
+        tx.commit()?;
+        self.clear_mem_cache(table_name);
+
+        let mut table = Table::get_table(table_name, self).await?;
+        table.ensure_default_view_created(self).await?;
+        Ok(())
+    }
+
+    /// Look up the _history and _message columns of the given row of the given table, read from
+    /// its default view, if the table is in wide mode, since the view leaves them empty (see
+    /// [Table::is_wide()]). Rows of other tables are left as they are.
+    pub async fn get_row_annotations(&self, table: &Table, row: &mut JsonRow) -> Result<()> {
+        tracing::trace!("Relatable::get_row_annotations({table:?}, {row:?})");
+        if !table.is_wide(self) {
+            return Ok(());
+        }
+        let row_id = row.get_unsigned("_id")?;
+        let sql = sql::generate_row_annotation_sql(&table.name, row_id, &self.connection.kind());
+        if let Some(annotations) = self.connection.query_one(&sql, None).await? {
+            for (column, value) in annotations.content {
+                row.content.insert(column, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Save each of the tables that are saved automatically to its path, after a change has been
+    /// committed. The saves are debounced: each of the tables is marked as pending, and is only
+    /// saved once [autosave_interval](Relatable::autosave_interval) has passed without another
+    /// change, by this function or by a later call to [Relatable::flush_autosaves()], so that a
+    /// table that is being edited is not saved over and over. When the interval is zero, the
+    /// tables are saved at once. Returns the names of the tables that were saved.
+    pub async fn autosave(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::autosave()");
+        let tables = self.get_autosave_tables().await?;
+        {
+            let mut autosaves = self.autosaves.lock().expect("Could not lock autosaves");
+            for table in tables {
+                autosaves.pending.insert(table, Instant::now());
+            }
+        }
+        self.flush_autosaves().await
+    }
+
+    /// Save each of the tables whose automatic save is pending (see [Relatable::autosave()]) and
+    /// that has not been changed for [autosave_interval](Relatable::autosave_interval). This
+    /// should be called periodically, e.g., by a server, so that the last changes to a table are
+    /// saved even when no other change follows them. Returns the names of the tables that were
+    /// saved.
+    pub async fn flush_autosaves(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::flush_autosaves()");
+        let due = {
+            let autosaves = self.autosaves.lock().expect("Could not lock autosaves");
+            autosaves
+                .pending
+                .iter()
+                .filter(|(_, changed)| changed.elapsed() >= self.autosave_interval)
+                .map(|(table, _)| table.clone())
+                .collect::<Vec<_>>()
+        };
+        self.save_autosave_tables(&due).await?;
+        Ok(due)
+    }
+
+    /// Save each of the tables whose automatic save is pending (see [Relatable::autosave()]),
+    /// however recently it was last changed. Returns the names of the tables that were saved.
+    pub async fn save_pending_autosaves(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::save_pending_autosaves()");
+        let pending = {
+            let autosaves = self.autosaves.lock().expect("Could not lock autosaves");
+            autosaves.pending.keys().cloned().collect::<Vec<_>>()
+        };
+        self.save_autosave_tables(&pending).await?;
+        Ok(pending)
+    }
+
+    /// Prepare this instance to stop, e.g., when a server is shutting down: save each of the
+    /// tables whose automatic save is pending (see [Relatable::save_pending_autosaves()]), empty
+    /// the in-memory cache of query results, and close the database's pool of connections, if it
+    /// has one. Returns the names of the tables that were saved.
+    pub async fn close(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::close()");
+        let saved = self.save_pending_autosaves().await;
+        CACHE.lock().expect("Could not lock cache").clear();
+        self.connection.close().await;
+        saved
+    }
+
+    /// Save the given tables to their paths, so that they are no longer pending. A table is no
+    /// longer pending while it is being saved, so that a change that is made in the meantime marks
+    /// it as pending again, as does a failure to save it.
+    async fn save_autosave_tables(&self, tables: &[String]) -> Result<()> {
+        tracing::trace!("Relatable::save_autosave_tables({tables:?})");
+        for table in tables {
+            tracing::debug!("Saving table '{table}' automatically");
+            let changed = self
+                .autosaves
+                .lock()
+                .expect("Could not lock autosaves")
+                .pending
+                .remove(table);
+            if let Err(err) = self.save_table(table, None).await {
+                if let Some(changed) = changed {
+                    let mut autosaves = self.autosaves.lock().expect("Could not lock autosaves");
+                    autosaves
+                        .pending
+                        .entry(table.to_string())
+                        .or_insert(changed);
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Save all of the tables and commit the changes to git, using the given message, if any, as
+    /// the commit message. If the changes are not committed to git, only the tables that are saved
+    /// automatically are saved (see [Relatable::autosave()]).
+    pub async fn commit_to_git(&self, message: Option<&str>) -> Result<()> {
+        tracing::trace!("Relatable::commit_to_git({message:?})");
+        // A sandbox's tables are neither committed nor saved automatically, since its changes
+        // are only meant to be previewed:
+        if self.sandboxed {
+            tracing::debug!("Not committing to git because this is a sandbox");
+            return Ok(());
+        }
+        let author = match std::env::var("RLTBL_GIT_AUTHOR") {
+            Err(err) => match err {
+                std::env::VarError::NotPresent => {
+                    tracing::debug!("Not committing to git because RLTBL_GIT_AUTHOR not defined");
+                    self.autosave().await?;
+                    return Ok(());
+                }
+                _ => {
+                    return Err(RelatableError::InputError(format!(
+                        "Could not read from the environment: {err}"
+                    ))
+                    .into())
+                }
+            },
+            Ok(author) => author,
+        };
+        tracing::info!("Committing to git on behalf of RLTBL_GIT_AUTHOR: '{author}'");
+
+        // Save all the tables:
+        self.save_all(None).await?;
+
+        // Get the git status:
+        let status = git::get_status()?;
+        if status.behind != 0 {
+            return Err(RelatableError::GitError(
+                "Refusing to commit to a local repository that is behind the remote".to_string(),
+            )
+            .into());
+        }
+
+        // Possibly only amend the last commit, if it is by the same author and performed
+        // on the same day:
+        let (last_commit_author, days_ago) = git::get_last_commit_info()?;
+        let is_amendment = (last_commit_author == author) && (days_ago < 1);
+
+        // Stage any modified table files that have a path in the table table:
+        let sql = format!(
+            r#"SELECT "path" FROM "table" WHERE "path" {is_not} NULL"#,
+            is_not = sql::is_not_clause(&self.connection.kind()),
+        );
+        let paths = self
+            .connection
+            .query(&sql, None)
+            .await?
+            .iter()
+            .map(|row| row.get_string("path").expect("No 'path' found"))
+            .collect::<Vec<_>>();
+        git::add(&paths)?;
+
+        // Finally, commit to git:
+        git::commit(message.unwrap_or("commit by rltbl"), &author, is_amendment)?;
+        Ok(())
+    }
+
+    /// Get the details of the last change made by the user from the change table.
+    fn _get_last_change_for_user(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        user: &str,
+        action: &ChangeAction,
+    ) -> Result<Option<(u64, ChangeSet)>> {
+        tracing::trace!("Relatable::_get_last_change_for_user(tx, {user:?}, {action:?})");
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT "change_id", "user", "table", "description", "message", "content"
+               FROM "change"
+               WHERE "user" = {sql_param_1} AND "action" = {sql_param_2}
+                 AND "content" <> '[]'
+               ORDER BY "change_id" DESC LIMIT 1"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([user, format!("{action}")]);
+        let records = tx.query(&sql, Some(&params))?;
+        match records.len() {
+            0 => Ok(None),
+            _ => {
+                let change_id = records[0].get_unsigned("change_id")?;
+                let user = records[0].get_string("user")?;
+                let table = records[0].get_string("table")?;
+                let description = records[0].get_string("description")?;
+                let message = records[0].get_optional_string("message")?;
+                let content = records[0].get_string("content")?;
+                let changes = Change::many_from_str(&content)?;
+                Ok(Some((
+                    change_id,
+                    ChangeSet {
+                        action: *action,
+                        table: table,
+                        user: user,
+                        description: description,
+                        message,
+                        changes: changes,
+                    },
+                )))
+            }
+        }
+    }
+
+    /// Record the given [ChangeSet] to the change and history tables. Returns the change_id of
+    /// the new change record.
+    pub fn record_changeset(
+        &self,
+        changeset: &ChangeSet,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<u64> {
+        tracing::trace!("Relatable::record_changeset({changeset:?}, tx)");
+        let user = changeset.user.clone();
+        let action = changeset.action.to_string();
+        let table = changeset.table.clone();
+        let description = changeset.description.clone();
+        let message = changeset.message.clone().unwrap_or_default();
+        let idempotency_key = IDEMPOTENCY_KEY
+            .try_with(|(key_user, key)| (*key_user == user).then(|| key.to_string()))
+            .ok()
+            .flatten();
+
+        // Begin by getting the current last change_id for this user, which we may need to look
+        // up previous values of the row's columns in the history table later:
+        let old_change_id = match &changeset.action {
+            ChangeAction::Undo => {
+                let (change_id, _) = self
+                    ._get_last_change_for_user(tx, &changeset.user, &ChangeAction::Do)?
+                    .ok_or(RelatableError::DataError(
+                        "No action for user found".to_string(),
+                    ))?;
+                Some(change_id)
+            }
+            ChangeAction::Redo => {
+                let (change_id, _) = self
+                    ._get_last_change_for_user(tx, &changeset.user, &ChangeAction::Undo)?
+                    .ok_or(RelatableError::DataError(
+                        "No undo for user found".to_string(),
+                    ))?;
+                Some(change_id)
+            }
+            ChangeAction::Do => None,
+        };
+
+        // Now write the current change, which will generate a new last change_id:
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let statement = format!(
+            r#"INSERT INTO change("user", "action", "table", "description", "message",
+                                  "idempotency_key", "content")
+               VALUES ({sql_params_1}, NULLIF({sql_param_2}, ''), {sql_param_3}, {sql_param_4})
+               RETURNING change_id"#,
+            sql_params_1 = sql_param.get_as_list(4),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+            sql_param_4 = sql_param.next(),
+        );
+        let content = to_value(&changeset.changes).unwrap_or_default();
+        let params = json!([
+            user,
+            action,
+            table,
+            description,
+            message,
+            idempotency_key,
+            content
+        ]);
+        let change_id = tx.query_value(&statement, Some(&params))?;
+        let change_id = change_id
+            .ok_or(RelatableError::DataError(
+                "Expected a change_id".to_string(),
+            ))?
+            .as_u64()
+            .ok_or(RelatableError::DataError("Expected an integer".to_string()))?;
+
+        // Assign the change to the change group that is being recorded, if any:
+        let change_group = CHANGE_GROUP
+            .try_with(|change_group| {
+                let group = change_group.get().unwrap_or(change_id);
+                change_group.set(Some(group));
+                group
+            })
+            .ok();
+        if let Some(change_group) = change_group {
+            let mut sql_param = SqlParam::new(&tx.kind());
+            let sql = format!(
+                r#"UPDATE "change" SET "change_group" = {sql_param_1}
+                    WHERE "change_id" = {sql_param_2}"#,
+                sql_param_1 = sql_param.next(),
+                sql_param_2 = sql_param.next(),
+            );
+            tx.query(&sql, Some(&json!([change_group, change_id])))?;
+        }
+
+        for change in &changeset.changes {
+            match change {
+                Change::Update {
+                    row,
+                    column,
+                    before,
+                    after,
+                } => {
+                    let sql = format!(
+                        r#"INSERT INTO "history"
+                           ("change_id", "table", "row", "before", "after")
+                           VALUES ({sql_params})
+                           RETURNING "history_id""#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(5)
+                    );
+                    Self::_record_operation(&user, &table, &changeset.action, change, None, tx)?;
+                    let before = json!({column: before}).to_string();
+                    let after = json!({column: after}).to_string();
+                    let params = json!([change_id, table, row, before, after]);
+                    tx.query_value(&sql, Some(&params))?;
+                }
+                Change::Add { row, after: _ } => {
+                    // If the row has just been newly added, it will be found in the table,
+                    // otherwise we will use the old_change_id to look for it in the history
+                    // table:
+                    let json_row = match Table::_get_row(&table, *row, tx)? {
+                        Some(json_row) => json_row,
+                        None => match old_change_id {
+                            Some(change_id) => {
+                                let sql = format!(
+                                    r#"SELECT "before"
+                                         FROM "history"
+                                        WHERE "change_id" = {sql_param}"#,
+                                    sql_param = SqlParam::new(&tx.kind()).next()
+                                );
+                                let params = json!([change_id]);
+                                let before = tx
+                                    .query_one(&sql, Some(&params))?
+                                    .ok_or(RelatableError::DataError(format!(
+                                        "No history row found with change_id {change_id}"
+                                    )))?
+                                    .get_string("before")?;
+                                let before = match serde_json::from_str::<JsonValue>(&before) {
+                                    Err(err) => return Err(err.into()),
+                                    Ok(JsonValue::Object(o)) => o,
+                                    Ok(_) => {
+                                        return Err(RelatableError::InputError(
+                                            "The content parameter is not an object".to_string(),
+                                        )
+                                        .into());
+                                    }
+                                };
+                                JsonRow { content: before }
+                            }
+                            None => {
+                                return Err(RelatableError::DataError(format!(
+                                    "Row {row} not found"
+                                ))
+                                .into())
+                            }
+                        },
+                    };
+                    let sql = format!(
+                        r#"INSERT INTO "history"
+                           ("change_id", "table", "row", "after")
+                           VALUES ({sql_params})
+                           RETURNING "history_id""#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(4)
+                    );
+                    let json_row_str = json!(json_row.content).to_string();
+                    let params = json!([change_id, table, row, json_row_str]);
+                    tx.query_value(&sql, Some(&params))?;
+                    Self::_record_operation(
+                        &user,
+                        &table,
+                        &changeset.action,
+                        change,
+                        Some(&json_row),
+                        tx,
+                    )?;
+                }
+                Change::Move {
+                    row,
+                    from_after: _,
+                    to_after: _,
+                } => {
+                    let sql = format!(
+                        r#"INSERT INTO "history"
+                           ("change_id", "table", "row")
+                           VALUES ({sql_params})
+                           RETURNING "history_id""#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(3)
+                    );
+                    let params = json!([change_id, table, row]);
+                    tx.query_value(&sql, Some(&params))?;
+                    Self::_record_operation(&user, &table, &changeset.action, change, None, tx)?;
+                }
+                Change::Delete { row, after: _ } => {
+                    let json_row = match Table::_get_row(&table, *row, tx)? {
+                        Some(json_row) => json_row,
+                        None => {
+                            // It must be there since we supposedly just added it, so if it is
+                            // not found return an error.
+                            return Err(
+                                RelatableError::DataError(format!("Row {row} not found")).into()
+                            );
+                        }
+                    };
+                    let sql = format!(
+                        r#"INSERT INTO "history"
+                           ("change_id", "table", "row", "before")
+                           VALUES ({sql_params})
+                           RETURNING "history_id""#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(4)
+                    );
+                    let json_row_str = json!(json_row.content).to_string();
+                    let params = json!([change_id, table, row, json_row_str]);
+                    tx.query_value(&sql, Some(&params))?;
+                    Self::_record_operation(&user, &table, &changeset.action, change, None, tx)?;
+                }
+            };
+        }
+
+        // Give any post-change hooks the chance to veto the changeset:
+        self.run_changeset_hooks(&self.hooks.post_change, changeset)?;
+
+        // Possibly delete dirty entries from the cache in accordance with our caching strategy:
+        self.change_capture.after_change(self, tx, &table)?;
+
+        Ok(change_id)
+    }
+
+    /// Get information about the given user from the database and return it as an [Account]. If
+    /// there is no user with the given username, return a default Account.
+    pub async fn get_user(&self, username: &str) -> Account {
+        tracing::trace!("Relatable::get_user({username:?})");
+        let statement = format!(
+            r#"SELECT "name", "color", "cursor", "datetime"
+               FROM "user" WHERE name = '{username}' LIMIT 1"#
+        );
+        let user = self.connection.query_one(&statement, None).await;
+        match user {
+            Ok(user) => match user {
+                Some(user) => Account {
+                    name: username.to_string(),
+                    color: user.get_string("color").expect("No 'color' found"),
+                },
+                None => Account {
+                    ..Default::default()
+                },
+            },
+            Err(err) => {
+                tracing::warn!("Error while querying user table: '{err}'");
+                Account {
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// Returns a map with information about all of the users who have corresponding records in
+    /// the user table.
+    pub async fn get_users(&self) -> Result<IndexMap<String, UserCursor>> {
+        tracing::trace!("Relatable::get_users()");
+        let mut users = IndexMap::new();
+        // let statement = format!(
+        //     r#"SELECT "name", color", "cursor", "datetime" FROM "user" WHERE cursor IS NOT NULL
+        //        AND "datetime" >= DATETIME('now', '-10 minutes')"#
+        // );
+        let statement = format!(
+            r#"SELECT "name", "color", "cursor", "datetime"
+               FROM "user"
+               WHERE cursor {is_not} NULL"#,
+            is_not = sql::is_not_clause(&self.connection.kind()),
+        );
+        let rows = self.connection.query(&statement, None).await?;
+        for row in rows {
+            let name = row.get_string("name")?;
+            if name.trim() == "" {
+                continue;
+            }
+            users.insert(
+                name.clone(),
+                UserCursor {
+                    name: name.clone(),
+                    color: row.get_string("color")?,
+                    cursor: serde_json::from_str(&row.get_string("cursor")?)?,
+                    datetime: row.get_string("datetime")?,
+                },
+            );
+        }
+        Ok(users)
+    }
+
+    /// Returns a list of the given table's columns, not including metacolumns
+    pub async fn fetch_columns(&self, table_name: &str) -> Result<Vec<Column>> {
+        tracing::trace!("Relatable::fetch_columns({table_name:?})");
+        let table = Table::get_table(table_name, self).await?;
+        Ok(table.columns.values().cloned().collect::<Vec<_>>())
+    }
+
+    /// Returns a list of the given table's columns, including metacolumns
+    pub async fn fetch_all_columns(&self, table_name: &str) -> Result<Vec<Column>> {
+        tracing::trace!("Relatable::fetch_all_columns({table_name:?})");
+        let mut conn = self.connection.reconnect()?;
+        // Begin a transaction:
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        let columns = {
+            let (mut normal_columns, meta_columns) =
+                Table::_collect_column_info(table_name, &mut tx)?;
+            let mut all_columns = meta_columns;
+            all_columns.append(&mut normal_columns);
+            all_columns
+        };
+
+        // Commit the transaction:
+        tx.commit()?;
+
+        Ok(columns)
+    }
+
+    /// Returns a vector of the names of the tables that have entries in the table table
+    pub async fn list_tables(&self) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::list_tables({self:?})");
+        let statement = format!(r#"SELECT "table" FROM "table" ORDER BY _order"#);
+        let rows = self.connection.query(&statement, None).await?;
+        rows.iter().map(|row| row.get_string("table")).collect()
+    }
+
+    /// Returns all of the tables that have entries in the table table as a map from table names
+    /// to Table structs.
+    pub async fn get_tables(&self) -> Result<IndexMap<String, Table>> {
+        tracing::trace!("Relatable::get_tables({self:?})");
+        let mut tables = IndexMap::new();
+        let statement = format!(
+            r#"SELECT "_id", "_order", "table", "path",
+                 (SELECT MAX(change_id)
+                  FROM "history"
+                  WHERE "history"."table" = "table"."table"
+                 ) AS "_change_id"
+               FROM "table""#
+        );
+
+        let rows = self.connection.query(&statement, None).await?;
+        for row in rows {
+            let name = row.get_string("table")?;
+            tables.insert(
+                name.clone(),
+                Table {
+                    name: name.clone(),
+                    change_id: row
+                        .content
+                        .get("_change_id")
+                        .and_then(|i| i.as_u64())
+                        .unwrap_or_default() as u64,
+                    columns: self
+                        .fetch_columns(&name)
+                        .await?
+                        .into_iter()
+                        .map(|column| (name.clone(), column))
+                        .collect::<IndexMap<_, _>>(),
+                    ..Default::default()
+                },
+            );
+        }
+        Ok(tables)
+    }
+
+    /// Returns a [Site] corresponding to the given username.
+    pub async fn get_site(&self, username: &str) -> Site {
+        tracing::trace!("Relatable::get_site({username:?})");
+        let mut users = self.get_users().await.unwrap_or_default();
+        users.shift_remove(username);
+        Site {
+            title: "RLTBL".to_string(),
+            root: self.root.clone(),
+            editable: !self.readonly,
+            user: self.get_user(username).await,
+            users,
+            tables: self.list_tables().await.unwrap_or_default(),
+        }
+    }
+
+    /// Updates the cursor field in the user table for the user associated with the given
+    /// changeset.
+    pub fn prepare_user_cursor(
+        &self,
+        changeset: &ChangeSet,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::prepare_user_cursor({changeset:?}, tx)");
+        // Make sure the user is present in the user table
+        let user = changeset.user.clone();
+        let color = random_color::RandomColor::new().to_hex();
+        let statement = format!(
+            r#"SELECT 1 FROM "user" WHERE "name" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([user]);
+        if let None = tx.query_value(&statement, Some(&params))? {
+            let statement = format!(
+                r#"INSERT INTO "user" ("name", "color") VALUES ({sql_params})"#,
+                sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
+            );
+            let params = json!([user, color]);
+            tx.query(&statement, Some(&params))?;
+        }
+
+        // Update the user's cursor position.
+        let mut cursor = changeset.to_cursor()?;
+        match changeset.action {
+            ChangeAction::Undo | ChangeAction::Redo => match changeset.changes.first() {
+                Some(Change::Delete { row, after: _ }) => {
+                    cursor.row = Table::_get_previous_row_id(&changeset.table, *row, tx)?;
+                }
+                _ => (),
+            },
+            ChangeAction::Do => (),
+        };
+
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let statement = format!(
+            r#"UPDATE "user"
+               SET "cursor" = {sql_param_1}, "datetime" = CURRENT_TIMESTAMP
+               WHERE "name" = {sql_param_2}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([to_value(cursor).unwrap_or_default(), user]);
+        tx.query_value(&statement, Some(&params))?;
+
+        Ok(())
+    }
+
+    /// Get the last set of changes that can be redone for the given user
+    pub async fn get_last_redoable_changeset_for_user(
+        &self,
+        user: &str,
+    ) -> Result<Option<(u64, ChangeSet)>> {
+        tracing::trace!("Relatable::get_last_redoable_changeset_for_user({user:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let changeset = Self::_get_last_redoable_changeset_for_user(user, &mut tx)?;
+        tx.commit()?;
+        Ok(changeset)
+    }
+
+    /// Get the last set of changes that can be redone for the given user, using the given
+    /// transaction.
+    fn _get_last_redoable_changeset_for_user(
+        user: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<(u64, ChangeSet)>> {
+        tracing::trace!("Relatable::_get_last_redoable_changeset_for_user({user:?}, tx)");
+        let history = Self::_get_user_history(user, Some(1), tx)?;
+        match history.changes_undone_stack.first() {
+            None => Ok(None),
+            Some(change) => Ok(Some(Self::changeset_from_record(change)?)),
+        }
+    }
+
+    /// Get the last set of changes that can be undone for the given user
+    pub async fn get_last_undoable_changeset_for_user(
+        &self,
+        user: &str,
+    ) -> Result<Option<(u64, ChangeSet)>> {
+        tracing::trace!("Relatable::get_last_undoable_changeset_for_user({user:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let changeset = Self::_get_last_undoable_changeset_for_user(user, &mut tx)?;
+        tx.commit()?;
+        Ok(changeset)
+    }
+
+    /// Get the last set of changes that can be undone for the given user, using the given
+    /// transaction.
+    fn _get_last_undoable_changeset_for_user(
+        user: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<(u64, ChangeSet)>> {
+        tracing::trace!("Relatable::_get_last_undoable_changeset_for_user({user:?}, tx)");
+        let history = Self::_get_user_history(user, Some(1), tx)?;
+        match history.changes_done_stack.first() {
+            None => Ok(None),
+            Some(change) => Ok(Some(Self::changeset_from_record(change)?)),
+        }
+    }
+
+    /// Convert a record of the change table, as found in a [History], to its change_id and the
+    /// [ChangeSet] that it records.
+    fn changeset_from_record(change: &JsonRow) -> Result<(u64, ChangeSet)> {
+        let change_id = change.get_unsigned("change_id")?;
+        let content = change.get_string("content")?;
+        let changes = Change::many_from_str(&content)?;
+        Ok((
+            change_id,
+            ChangeSet {
+                action: ChangeAction::from_str(&change.get_string("action")?)?,
+                table: change.get_string("table")?,
+                user: change.get_string("user")?,
+                description: change.get_string("description")?,
+                message: change.get_optional_string("message")?,
+                changes,
+            },
+        ))
+    }
+
+    /// Return, for every row of the given table, in order, the last change that modified the
+    /// value of the given column of the row, if there is one (see [Blame]). Rows whose value in
+    /// the column has not changed since they were loaded have no change. The history of each row
+    /// is read using the index on the table, row, and change_id columns of the history table.
+    pub async fn blame(&self, table_name: &str, column: &str) -> Result<Vec<Blame>> {
+        tracing::trace!("Relatable::blame({table_name:?}, {column:?})");
+        let table = Table::get_table(table_name, self).await?;
+        if !table.columns.contains_key(column) {
+            return Err(RelatableError::InputError(format!(
+                "No column '{column}' in table '{table_name}'"
+            ))
+            .into());
+        }
+
+        // A change modified the column of a row if the column is among those that it recorded
+        // the value of, before or after the change:
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let (table_param, column_param) = (sql_param.next(), sql_param.next());
+        let has_column = |side: &str| match self.connection.kind() {
+            DbKind::Sqlite => format!(
+                r#"json_type("history"."{side}", '$.' || json_quote({column_param})) IS NOT NULL"#
+            ),
+            DbKind::Postgres => {
+                format!(r#"jsonb_exists("history"."{side}"::jsonb, {column_param}::TEXT)"#)
+            }
+        };
+        let sql = format!(
+            r#"SELECT "{table_name}"."_id" AS "row",
+                      "{table_name}"."{column}" AS "value",
+                      "change"."change_id",
+                      "change"."user",
+                      "change"."action",
+                      CAST("change"."datetime" AS TEXT) AS "datetime",
+                      "change"."message",
+                      "change"."rows_affected",
+                      "change"."messages_added",
+                      "change"."messages_removed",
+                      "change"."elapsed_ms"
+               FROM "{table_name}"
+               LEFT JOIN (
+                 SELECT "history"."row", MAX("history"."change_id") AS "change_id"
+                 FROM "history"
+                 WHERE "history"."table" = {table_param}
+                   AND ({after} OR {before})
+                 GROUP BY "history"."row"
+               ) AS "blame" ON "blame"."row" = "{table_name}"."_id"
+               LEFT JOIN "change" ON "change"."change_id" = "blame"."change_id"
+               ORDER BY "{table_name}"."_order""#,
+            after = has_column("after"),
+            before = has_column("before"),
+        );
+        // PostgreSQL's parameters are numbered, so the column's can be used twice, but SQLite's
+        // are not:
+        let params = match self.connection.kind() {
+            DbKind::Sqlite => json!([table_name, column, column]),
+            DbKind::Postgres => json!([table_name, column]),
+        };
+        self.connection
+            .query(&sql, Some(&params))
+            .await?
+            .iter()
+            .map(|row| {
+                let mut blame: Blame = row.deserialize()?;
+                blame.metrics = ChangeMetrics::from_record(row);
+                Ok(blame)
+            })
+            .collect()
+    }
+
+    /// Return a [History] for the given user with at most `context` (or [HISTORY_MAX] if this
+    /// is not given) undoable and/or redoable previous changes.
+    pub async fn get_user_history(&self, user: &str, context: Option<usize>) -> Result<History> {
+        tracing::trace!("Relatable::get_user_history({user:?}, {context:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let history = Self::_get_user_history(user, context, &mut tx)?;
+        tx.commit()?;
+        Ok(history)
+    }
+
+    /// Get the history of the given user (see [Relatable::get_user_history()]) using the given
+    /// transaction.
+    fn _get_user_history(
+        user: &str,
+        context: Option<usize>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<History> {
+        tracing::trace!("Relatable::_get_user_history({user:?}, {context:?}, tx)");
+        fn content_to_json_row(content: &str) -> Result<JsonRow> {
+            tracing::debug!("Entering content_to_json_row(content: {content})");
+            match serde_json::from_str::<JsonValue>(content) {
+                Ok(content) => match content
+                    .as_array()
+                    .and_then(|a| a.first())
+                    .and_then(|o| o.as_object())
+                {
+                    Some(object) => Ok(JsonRow {
+                        content: object.clone(),
+                    }),
+                    None => {
+                        return Err(RelatableError::InputError(format!(
+                            "Received invalid or empty content: {content}. Expected a non-empty \
+                             object array."
+                        ))
+                        .into())
+                    }
+                },
+                Err(err) => {
+                    return Err(
+                        RelatableError::InputError(format!("Error reading content: {err}")).into(),
+                    )
+                }
+            }
+        }
+
+        fn on_the_same_target(change1: &JsonRow, change2: &JsonRow) -> Result<bool> {
+            tracing::trace!("Relatable::on_the_same_target({change1:?}, {change2:?})");
+            let change1 = content_to_json_row(&change1.get_string("content")?)?;
+            let change2 = content_to_json_row(&change2.get_string("content")?)?;
+            let row1 = change1.get_unsigned("row")?;
+            let row2 = change2.get_unsigned("row")?;
+            if row1 != row2 {
+                return Ok(false);
+            }
+            if let Ok(column1) = change1.get_string("column") {
+                if let Ok(column2) = change2.get_string("column") {
+                    return Ok(column1 == column2);
+                }
+            }
+            Ok(true)
+        }
+
+        fn prune_stacks(
+            changes_done_stack: &Vec<JsonRow>,
+            changes_undone_stack: &Vec<JsonRow>,
+        ) -> (Vec<JsonRow>, Vec<JsonRow>) {
+            tracing::trace!(
+                "Relatable::prune_stacks({changes_done_stack:?}, {changes_undone_stack:?})"
+            );
+            let mut pruned_dones = vec![];
+            let mut pruned_undones = vec![];
+            for change in changes_done_stack.iter() {
+                if !pruned_dones.iter().any(|done: &JsonRow| {
+                    on_the_same_target(&done, &change).expect("Error looking for a common target")
+                }) {
+                    pruned_dones.push(change.clone());
+                }
+            }
+            for change in changes_undone_stack.iter() {
+                if !pruned_undones.iter().any(|undone: &JsonRow| {
+                    on_the_same_target(&undone, &change).expect("Error looking for a common target")
+                }) {
+                    pruned_undones.push(change.clone());
+                }
+            }
+            (pruned_dones, pruned_undones)
+        }
+
+        // TODO: Think about paging when there are a lot of change records to go through.
+        let sql = format!(
+            r#"SELECT "change_id", "change_group", "user", "table", "description", "message",
+                      "action", "content", "rows_affected", "messages_added",
+                      "messages_removed", "elapsed_ms"
+                 FROM "change"
+                WHERE "user" = {sql_param} AND "content" <> '[]'
+                ORDER BY "change_id" DESC"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([user]);
+        let history = tx.query(&sql, Some(&params))?;
+
+        // Initialize the stacks to be returned and counters:
+        let mut changes_done_stack = vec![];
+        let mut changes_undone_stack = vec![];
+        let mut do_redo_count: usize;
+        let mut undo_count: usize;
+
+        // Begin with the last change that was made:
+        let (final_change, final_action) = match history.first() {
+            None => return Ok(History::default()),
+            Some(final_change) => {
+                let final_action = ChangeAction::from_str(&final_change.get_string("action")?)?;
+                (final_change, final_action)
+            }
+        };
+        match final_action {
+            ChangeAction::Do | ChangeAction::Redo => {
+                do_redo_count = 1;
+                undo_count = 0;
+            }
+            ChangeAction::Undo => {
+                do_redo_count = 0;
+                undo_count = 1;
+            }
+        };
+        let mut change_to_push = final_change;
+        let mut action_to_push = final_action;
+        tracing::debug!("Setting the change to push ({action_to_push}) to: {change_to_push:?}");
+        tracing::debug!(
+            "The do/redo count is now {do_redo_count}, and the undo count is \
+             {undo_count}."
+        );
+
+        // For each action, find the point where it began, and then place it onto changes_done_stack
+        // or changes_undone_stack, as appropriate:
+        for prior_change in &history[1..] {
+            let prior_action = ChangeAction::from_str(&prior_change.get_string("action")?)?;
+            tracing::debug!("The change prior to it is a {prior_action}: {prior_change:?}.");
+            match action_to_push {
+                ChangeAction::Do => match prior_action {
+                    ChangeAction::Do | ChangeAction::Redo => {
+                        tracing::debug!(
+                            "Pushing change {cid} to changes_done",
+                            cid = change_to_push.get_string("change_id")?
+                        );
+                        changes_done_stack.push(change_to_push.clone());
+                        change_to_push = prior_change;
+                        action_to_push =
+                            ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                        do_redo_count = 1;
+                        undo_count = 0;
+                        tracing::debug!(
+                            "The next change to push is a {action_to_push}: {change_to_push:?}"
+                        );
+                    }
+                    ChangeAction::Undo => {
+                        tracing::debug!(
+                            "Pushing change {cid} to changes_done",
+                            cid = change_to_push.get_string("change_id")?
+                        );
+                        changes_done_stack.push(change_to_push.clone());
+                        change_to_push = prior_change;
+                        action_to_push =
+                            ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                        do_redo_count = 0;
+                        undo_count = 1;
+                        tracing::debug!(
+                            "The next change to push is a {action_to_push}: {change_to_push:?}"
+                        );
+                    }
+                },
+                ChangeAction::Undo => match prior_action {
+                    ChangeAction::Undo => {
+                        if do_redo_count == 0 {
+                            tracing::debug!(
+                                "Pushing change {cid} to changes_undone",
+                                cid = change_to_push.get_string("change_id")?
+                            );
+                            changes_undone_stack.push(change_to_push.clone());
+                            change_to_push = prior_change;
+                            action_to_push =
+                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                            tracing::debug!(
+                                "The next change to push is a {action_to_push}: {change_to_push:?}"
+                            );
+                            undo_count += 1;
+                        } else {
+                            do_redo_count -= 1;
+                            undo_count += 1;
+                        }
+                    }
+                    ChangeAction::Do | ChangeAction::Redo => {
+                        if undo_count == 0 {
+                            tracing::debug!(
+                                "Pushing change {cid} to changes_undone",
+                                cid = change_to_push.get_string("change_id")?
+                            );
+                            changes_undone_stack.push(change_to_push.clone());
+                            change_to_push = prior_change;
+                            action_to_push =
+                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                            do_redo_count = 1;
+                            tracing::debug!(
+                                "The next change to push is a {action_to_push}: {change_to_push:?}"
+                            );
+                        } else {
+                            do_redo_count += 1;
+                            undo_count -= 1;
+                        }
+                    }
+                },
+                ChangeAction::Redo => match prior_action {
+                    ChangeAction::Redo => {
+                        if undo_count == 0 {
+                            tracing::debug!(
+                                "Pushing change {cid} to changes_done",
+                                cid = change_to_push.get_string("change_id")?
+                            );
+                            changes_done_stack.push(change_to_push.clone());
+                            change_to_push = prior_change;
+                            action_to_push =
+                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                            tracing::debug!(
+                                "The next change to push is a {action_to_push}: {change_to_push:?}"
+                            );
+                            do_redo_count += 1;
+                        } else {
+                            do_redo_count += 1;
+                            undo_count -= 1;
+                        }
+                    }
+                    ChangeAction::Do => {
+                        if undo_count == 0 {
+                            tracing::debug!(
+                                "Pushing change {cid} to changes_done",
+                                cid = change_to_push.get_string("change_id")?
+                            );
+                            changes_done_stack.push(change_to_push.clone());
+                            change_to_push = prior_change;
+                            action_to_push =
+                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                            tracing::debug!(
+                                "The next change to push is a {action_to_push}: {change_to_push:?}"
+                            );
+                            // Dos begin anew.
+                            do_redo_count = 1;
+                        } else {
+                            do_redo_count += 1;
+                            undo_count -= 1;
+                        }
+                    }
+                    ChangeAction::Undo => {
+                        if do_redo_count == 0 {
+                            tracing::debug!(
+                                "Pushing change {cid} to changes_done",
+                                cid = change_to_push.get_string("change_id")?
+                            );
+                            changes_done_stack.push(change_to_push.clone());
+                            change_to_push = prior_change;
+                            action_to_push =
+                                ChangeAction::from_str(&change_to_push.get_string("action")?)?;
+                            tracing::debug!(
+                                "The next change to push is a {action_to_push}: {change_to_push:?}"
+                            );
+                            undo_count = 1;
+                        } else {
+                            do_redo_count -= 1;
+                            undo_count += 1;
+                        }
+                    }
+                },
+            };
+
+            tracing::debug!(
+                "Updated the do/redo count to {do_redo_count}, and the undo count to \
+                 {undo_count}."
+            );
+
+            // Remove duplicate entries from the stacks. These can result when a row is repeatedly
+            // undone and redone. These are harmless, logically speaking, but they may potentially
+            // confuse the user if they are included in the output.
+            (changes_done_stack, changes_undone_stack) =
+                prune_stacks(&changes_done_stack, &changes_undone_stack);
+
+            // Check if we have exceeded the (max) context, and if so, stop looking for more
+            // actions:
+            let mut done_len = changes_done_stack.len();
+            let mut undone_len = changes_undone_stack.len();
+            match action_to_push {
+                ChangeAction::Do | ChangeAction::Redo => done_len += 1,
+                ChangeAction::Undo => undone_len += 1,
+            };
+            if let Some(context) = context {
+                if done_len >= context && undone_len >= context {
+                    break;
+                }
+            } else if done_len >= HISTORY_MAX || undone_len >= HISTORY_MAX {
+                break;
+            }
+        }
+
+        // Once we have finished iterating, there will be one action left over to push, which we
+        // do now:
+        match action_to_push {
+            ChangeAction::Do | ChangeAction::Redo => {
+                tracing::debug!("Pushing the last change to changes_done: {change_to_push:?}");
+                changes_done_stack.push(change_to_push.clone());
+            }
+            ChangeAction::Undo => {
+                tracing::debug!("Pushing the last change to changes_undone: {change_to_push:?}");
+                changes_undone_stack.push(change_to_push.clone());
+            }
+        };
+
+        // Don't return the contents of changes_undone if the last change was a do. Dos can never be
+        // redone. If the last change was an undo or a redo, the logic will take care of itself and
+        // it should never be possible to undo or redo inappropriately.
+        let mut changes_undone_stack = match final_action {
+            ChangeAction::Do => vec![],
+            _ => changes_undone_stack,
+        };
+
+        // Prune the stacks one last time, in case by adding the final action we created a situation
+        // in which one of the stacks contains duplicates:
+        (changes_done_stack, changes_undone_stack) =
+            prune_stacks(&changes_done_stack, &changes_undone_stack);
+
+        // Similarly, crop for context one last time if a context has been defined:
+        let history = match context {
+            None => History {
+                changes_done_stack,
+                changes_undone_stack,
+            },
+            Some(context) => {
+                let mut done_len = changes_done_stack.len();
+                if done_len > context {
+                    done_len = context;
+                }
+                let changes_done_stack = changes_done_stack[..done_len].to_vec();
+
+                let mut undone_len = changes_undone_stack.len();
+                if undone_len > context {
+                    undone_len = context;
+                }
+                let changes_undone_stack = changes_undone_stack[..undone_len].to_vec();
+                History {
+                    changes_done_stack,
+                    changes_undone_stack,
+                }
+            }
+        };
+        tracing::debug!("Returning history: {history:#?}");
+        Ok(history)
+    }
+
+    /// Reverse the given changeset in the database
+    fn _revert(
+        &self,
+        change_id: u64,
+        changeset: &ChangeSet,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::_revert({change_id}, {changeset:?}, tx)");
+        match changeset.changes.first() {
+            None => Ok(None),
+            Some(change) => {
+                if let Change::Update { .. } = change {
+                    Ok(Some(self._set_values(changeset, tx)?))
+                } else {
+                    let mut actual_changes = vec![];
+                    for change in changeset.changes.iter() {
+                        match change {
+                            Change::Update { .. } => (), // Change::Update already handled above.
+                            Change::Add { row, after: _ } => {
+                                // Changes to the history and change tables are recorded before
+                                // the row is deleted, so make sure that there is a row to delete:
+                                if Table::_get_row(&changeset.table, *row, tx)?.is_none() {
+                                    continue;
+                                }
+                                let num_deleted = self._delete_row(
+                                    &changeset.action,
+                                    &changeset.table,
+                                    &changeset.user,
+                                    *row,
+                                    tx,
+                                )?;
+                                if num_deleted > 0 {
+                                    actual_changes.push(change.clone());
+                                }
+                            }
+                            Change::Move {
+                                row,
+                                from_after,
+                                to_after: _,
+                            } => {
+                                let new_order = self._move_and_record_row(
+                                    &changeset.action,
+                                    &changeset.table,
+                                    &changeset.user,
+                                    *row,
+                                    *from_after,
+                                    tx,
+                                )?;
+                                if new_order > 0 {
+                                    actual_changes.push(change.clone());
+                                }
+                            }
+                            Change::Delete { row, after } => {
+                                // Get the row, as it was before it was deleted, from the history
+                                // table:
+                                let sql = format!(
+                                    r#"SELECT "before" FROM "history"
+                                       WHERE "change_id" = {sql_param}"#,
+                                    sql_param = SqlParam::new(&tx.kind()).next()
+                                );
+                                let params = json!([change_id]);
+                                let before = tx
+                                    .query_one(&sql, Some(&params))?
+                                    .ok_or(RelatableError::DataError(format!(
+                                        "No history row found with change_id {change_id}"
+                                    )))?
+                                    .get_string("before")?;
+                                let before = match serde_json::from_str::<JsonValue>(&before) {
+                                    Err(err) => return Err(err.into()),
+                                    Ok(JsonValue::Object(o)) => o,
+                                    Ok(_) => {
+                                        return Err(RelatableError::InputError(
+                                            "The content parameter is not an object".to_string(),
+                                        )
+                                        .into());
+                                    }
+                                };
+                                let before = JsonRow { content: before };
+                                tracing::debug!(
+                                    "Re-adding row '{before}' to table '{}'",
+                                    changeset.table
+                                );
+                                self._add_row(
+                                    &changeset.action,
+                                    &changeset.table,
+                                    &changeset.user,
+                                    Some(*row),
+                                    Some(*after),
+                                    &before,
+                                    tx,
+                                )?;
+                                actual_changes.push(change.clone());
+                            }
+                        };
+                    }
+                    Ok(Some(ChangeSet {
+                        action: changeset.action,
+                        table: changeset.table.clone(),
+                        user: changeset.user.clone(),
+                        description: changeset.description.clone(),
+                        message: changeset.message.clone(),
+                        changes: actual_changes,
+                    }))
+                }
+            }
+        }
+    }
+
+    /// Revert the last change made (if action is [ChangeAction::Undo]) or undone (if action is
+    /// [ChangeAction::Redo]) by the given user, along with the rest of its change group, if any,
+    /// using the given transaction.
+    fn _revert_last(
+        &self,
+        user: &str,
+        action: ChangeAction,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::_revert_last({user:?}, {action:?}, tx)");
+        let get_last = |tx: &mut DbTransaction<'_>| match action {
+            ChangeAction::Redo => Self::_get_last_redoable_changeset_for_user(user, tx),
+            _ => Self::_get_last_undoable_changeset_for_user(user, tx),
+        };
+        let (change_id, mut changeset) = match get_last(tx)? {
+            None => {
+                tracing::warn!("Nothing to {action} for '{user}'");
+                return Ok(None);
+            }
+            Some(changeset) => changeset,
+        };
+        tracing::debug!("Last change to {action} (ID {change_id}) for {user} was {changeset:?}");
+        changeset.action = action;
+        let change_group = match Self::_get_change_group(change_id, tx)? {
+            None => return self._revert(change_id, &changeset, tx),
+            Some(change_group) => change_group,
+        };
+        // The changes that revert the group are linked into a change group of their own as they
+        // are recorded:
+        let (changeset, _) = Self::_in_change_group(|| {
+            let changeset = self._revert(change_id, &changeset, tx)?;
+            while let Some((change_id, mut changeset)) = get_last(tx)? {
+                if Self::_get_change_group(change_id, tx)? != Some(change_group) {
+                    break;
+                }
+                changeset.action = action;
+                self._revert(change_id, &changeset, tx)?;
+            }
+            Ok(changeset)
+        })?;
+        Ok(changeset)
+    }
+
+    /// Undo the last change made by the given user. If the change belongs to a change group,
+    /// undo every change in the group.
+    pub async fn undo(&self, user: &str) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::undo({user:?})");
+        let changeset = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let changeset = self._undo(user, &mut tx)?;
+                tx.commit()?;
+                Ok(changeset)
+            })
+            .await?;
+        if let Some(changeset) = &changeset {
+            self.commit_to_git(changeset.message.as_deref()).await?;
+        }
+        Ok(changeset)
+    }
+
+    /// Undo the last change made by the given user, and the rest of its change group, if any,
+    /// using the given transaction.
+    fn _undo(&self, user: &str, tx: &mut DbTransaction<'_>) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::_undo({user:?}, tx)");
+        self._revert_last(user, ChangeAction::Undo, tx)
+    }
+
+    /// Describe what the next [Relatable::undo()] by the given user would change, without changing
+    /// anything. Returns a [ChangeSet] for each change that would be undone, beginning with the
+    /// last, in which each of the changes that was recorded is [reversed](Change::reverse()), so
+    /// that its before and after describe the effect of the undo. The before of an update is the
+    /// current value of the cell. Returns an empty list if there is nothing to undo.
+    pub async fn preview_undo(&self, user: &str) -> Result<Vec<ChangeSet>> {
+        tracing::trace!("Relatable::preview_undo({user:?})");
+        let history = self.get_user_history(user, None).await?;
+        let get_change_group = |change: &JsonRow| match change.get_value("change_group")? {
+            JsonValue::Null => Ok(None),
+            change_group => sql::json_to_unsigned(&change_group).map(Some),
+        };
+
+        // The changes in the same group as the last are undone along with it:
+        let mut records = vec![];
+        let mut change_group = None;
+        for (i, change) in history.changes_done_stack.iter().enumerate() {
+            if i == 0 {
+                change_group = get_change_group(change)?;
+            } else if change_group.is_none() || get_change_group(change)? != change_group {
+                break;
+            }
+            records.push(change);
+        }
+
+        let mut changesets = vec![];
+        for record in records {
+            let (_, mut changeset) = Self::changeset_from_record(record)?;
+            changeset.action = ChangeAction::Undo;
+            let mut changes = vec![];
+            for change in changeset.changes.iter().map(|change| change.reverse()) {
+                match change {
+                    Change::Update {
+                        row,
+                        column,
+                        before,
+                        after,
+                    } => {
+                        let sql = format!(
+                            r#"SELECT "{column}" FROM "{table}" WHERE "_id" = {sql_param}"#,
+                            table = changeset.table,
+                            sql_param = SqlParam::new(&self.connection.kind()).next()
+                        );
+                        let params = json!([row]);
+                        let current = self.connection.query_value(&sql, Some(&params)).await?;
+                        changes.push(Change::Update {
+                            row,
+                            column,
+                            before: current.unwrap_or(before),
+                            after,
+                        });
+                    }
+                    _ => changes.push(change),
+                }
+            }
+            changeset.changes = changes;
+            changesets.push(changeset);
+        }
+        Ok(changesets)
+    }
+
+    /// Redo the last change undone by the given user. If the change belongs to a change group,
+    /// redo every change in the group.
+    pub async fn redo(&self, user: &str) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::redo({user:?})");
+        let changeset = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let changeset = self._redo(user, &mut tx)?;
+                tx.commit()?;
+                Ok(changeset)
+            })
+            .await?;
+        if let Some(changeset) = &changeset {
+            self.commit_to_git(changeset.message.as_deref()).await?;
+        }
+        Ok(changeset)
+    }
+
+    /// Redo the last change undone by the given user, and the rest of its change group, if any,
+    /// using the given transaction.
+    fn _redo(&self, user: &str, tx: &mut DbTransaction<'_>) -> Result<Option<ChangeSet>> {
+        tracing::trace!("Relatable::_redo({user:?}, tx)");
+        self._revert_last(user, ChangeAction::Redo, tx)
+    }
+
+    /// Begin a transaction, on the given connection, with the isolation level of this instance's
+    /// multi-step write operations (see [Relatable::write_isolation]).
+    pub async fn begin_write<'a>(
+        &self,
+        conn: &'a mut Option<DbActiveConnection>,
+    ) -> Result<DbTransaction<'a>> {
+        tracing::trace!("Relatable::begin_write({conn:?})");
+        self.connection.begin_with(conn, self.write_isolation).await
+    }
+
+    /// Run the given operation, and run it again, after a short and growing delay, for as long as
+    /// it fails because one of its transactions could not be serialized with those of other
+    /// editors (see [sql::is_serialization_failure()]), up to [sql::SERIALIZATION_RETRIES] times.
+    /// A failed transaction is rolled back, so that the operation is begun afresh.
+    pub async fn retry_serialization_failures<T, F, Fut>(&self, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        tracing::trace!("Relatable::retry_serialization_failures(operation)");
+        let mut retries = 0;
+        loop {
+            match operation().await {
+                Err(error)
+                    if retries < sql::SERIALIZATION_RETRIES
+                        && sql::is_serialization_failure(&error) =>
+                {
+                    retries += 1;
+                    tracing::info!("Retrying after a serialization failure ({retries}): {error}");
+                    async_std::task::sleep(Duration::from_millis(10 << retries)).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Get the change group, if any, to which the change record with the given change_id belongs.
+    pub async fn get_change_group(&self, change_id: u64) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::get_change_group({change_id})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let change_group = Self::_get_change_group(change_id, &mut tx)?;
+        tx.commit()?;
+        Ok(change_group)
+    }
+
+    /// Get the change group, if any, to which the change record with the given change_id belongs,
+    /// using the given transaction.
+    fn _get_change_group(change_id: u64, tx: &mut DbTransaction<'_>) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::_get_change_group({change_id}, tx)");
+        let sql = format!(
+            r#"SELECT "change_group" FROM "change" WHERE "change_id" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([change_id]);
+        match tx.query_value(&sql, Some(&params))? {
+            Some(JsonValue::Null) | None => Ok(None),
+            Some(change_group) => Ok(Some(sql::json_to_unsigned(&change_group)?)),
+        }
+    }
+
+    /// Run the given function, which records changes using a single transaction, so that the
+    /// change records that it makes are linked into a single change group, identified by the
+    /// smallest change_id in the group. Returns the function's result along with the change group,
+    /// or None if no change records were made.
+    fn _in_change_group<T>(function: impl FnOnce() -> Result<T>) -> Result<(T, Option<u64>)> {
+        tracing::trace!("Relatable::_in_change_group(function)");
+        CHANGE_GROUP.sync_scope(std::cell::Cell::new(None), || {
+            let result = function()?;
+            Ok((result, CHANGE_GROUP.with(|change_group| change_group.get())))
+        })
+    }
+
+    /// Run the given operation on behalf of the given user and record the given idempotency key
+    /// on the change records that it makes, unless changes have already been recorded under the
+    /// same key for the same user, in which case the operation is not run again and None is
+    /// returned (see [Relatable::get_idempotent_changes()]). This allows a client to retry a
+    /// request that it is unsure of, e.g., after a network failure, without adding the same row
+    /// or applying the same edit twice. Running an operation under a key while another is still
+    /// in progress under it, through this instance or one of its clones, is an error.
+    pub async fn idempotent<T, F, Fut>(
+        &self,
+        user: &str,
+        key: &str,
+        operation: F,
+    ) -> Result<Option<T>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        tracing::trace!("Relatable::idempotent({user:?}, {key:?}, operation)");
+
+        // Release the key when the operation is finished, or abandoned:
+        struct InProgress<'a>(&'a Mutex<HashSet<(String, String)>>, (String, String));
+        impl Drop for InProgress<'_> {
+            fn drop(&mut self) {
+                if let Ok(mut keys) = self.0.lock() {
+                    keys.remove(&self.1);
+                }
+            }
+        }
+        let in_progress = (user.to_string(), key.to_string());
+        if !self
+            .idempotency_keys
+            .lock()
+            .expect("Could not lock idempotency keys")
+            .insert(in_progress.clone())
+        {
+            return Err(RelatableError::InputError(format!(
+                "An operation with the idempotency key '{key}' is already in progress"
+            ))
+            .into());
+        }
+        let _in_progress = InProgress(&self.idempotency_keys, in_progress);
+
+        if !self.get_idempotent_changes(user, key).await?.is_empty() {
+            return Ok(None);
+        }
+        // The key is recorded by record_changeset() along with each change that the operation
+        // makes, including those made before the operation failed, if it did:
+        IDEMPOTENCY_KEY
+            .scope((user.to_string(), key.to_string()), operation())
+            .await
+            .map(Some)
+    }
+
+    /// Get the changes that were recorded under the given idempotency key for the given user
+    /// (see [Relatable::idempotent()]), in the order in which they were made.
+    pub async fn get_idempotent_changes(&self, user: &str, key: &str) -> Result<Vec<ChangeSet>> {
+        tracing::trace!("Relatable::get_idempotent_changes({user:?}, {key:?})");
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let sql = format!(
+            r#"SELECT "change_id", "user", "action", "table", "description", "message", "content"
+                 FROM "change"
+                WHERE "user" = {sql_param_1} AND "idempotency_key" = {sql_param_2}
+                ORDER BY "change_id""#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([user, key]);
+        self.connection
+            .query(&sql, Some(&params))
+            .await?
+            .iter()
+            .map(|change| Ok(Self::changeset_from_record(change)?.1))
+            .collect()
+    }
+
+    /// Apply each of the given [ChangeSet]s, which may target different tables, and link the
+    /// resulting change records into a single change group, so that they are undone and redone
+    /// as one unit. All of the changesets must belong to the same user.
+    pub async fn set_values_in_group(&self, changesets: &Vec<ChangeSet>) -> Result<Vec<ChangeSet>> {
+        tracing::trace!("Relatable::set_values_in_group({changesets:?})");
+        let user = match changesets.first() {
+            None => return Ok(vec![]),
+            Some(changeset) => changeset.user.clone(),
+        };
+        if changesets.iter().any(|changeset| changeset.user != user) {
+            return Err(RelatableError::InputError(
+                "All of the changesets in a change group must belong to the same user".to_string(),
+            )
+            .into());
+        }
+
+        let (actual_changesets, change_group) = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let result = Self::_in_change_group(|| {
+                    let mut actual_changesets = vec![];
+                    for changeset in changesets {
+                        let changeset = self._set_values(changeset, &mut tx)?;
+                        if !changeset.changes.is_empty() {
+                            actual_changesets.push(changeset);
+                        }
+                    }
+                    Ok(actual_changesets)
+                })?;
+                tx.commit()?;
+                Ok(result)
+            })
+            .await?;
+        if change_group.is_some() {
+            let message = changesets.iter().find_map(|c| c.message.as_deref());
+            self.commit_to_git(message).await?;
+        }
+        Ok(actual_changesets)
+    }
+
+    /// Apply the given [BatchOperation]s on behalf of the given user within a single transaction,
+    /// so that either all of them are applied or, if any of them fails, none of them are. The
+    /// resulting change records are linked into a single change group, which is returned, and
+    /// the given message, if any, is recorded on each of them.
+    pub async fn batch(
+        &self,
+        user: &str,
+        message: Option<&str>,
+        operations: &[BatchOperation],
+    ) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::batch({user:?}, {message:?}, {operations:?})");
+        let change_group = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let (_, change_group) = Self::_in_change_group(|| {
+                    for (i, operation) in operations.iter().enumerate() {
+                        self._apply_batch_operation(user, message, operation, &mut tx)
+                            .map_err(|err| match sql::is_serialization_failure(&err) {
+                                // Serialization failures are passed on so that they are retried:
+                                true => err,
+                                false => RelatableError::InputError(format!(
+                                    "Operation {n} of the batch failed: {err}",
+                                    n = i + 1
+                                ))
+                                .into(),
+                            })?;
+                    }
+                    Ok(())
+                })?;
+                if let (Some(change_group), Some(message)) = (change_group, message) {
+                    let mut sql_param = SqlParam::new(&tx.kind());
+                    let sql = format!(
+                        r#"UPDATE "change" SET "message" = {sql_param_1}
+                            WHERE "change_group" = {sql_param_2}"#,
+                        sql_param_1 = sql_param.next(),
+                        sql_param_2 = sql_param.next(),
+                    );
+                    tx.query(&sql, Some(&json!([message, change_group])))?;
+                }
+                tx.commit()?;
+                Ok(change_group)
+            })
+            .await?;
+        if change_group.is_some() {
+            self.commit_to_git(message).await?;
+        }
+        Ok(change_group)
+    }
+
+    /// Returns a [Sandbox] containing an in-memory copy of this database, in which batches of
+    /// edits can be tried out, and their effect on the validation messages previewed, without
+    /// affecting this database. Only SQLite databases are supported.
+    pub async fn sandbox(&self) -> Result<Sandbox> {
+        tracing::trace!("Relatable::sandbox()");
+        let rltbl = Relatable {
+            // The memory cache is shared by every instance, and so cannot tell the sandbox and
+            // the original database apart:
+            caching_strategy: match self.caching_strategy {
+                CachingStrategy::Memory(_) => CachingStrategy::None,
+                caching_strategy => caching_strategy,
+            },
+            sandboxed: true,
+            autosaves: Arc::new(Mutex::new(Autosaves::default())),
+            idempotency_keys: Arc::new(Mutex::new(HashSet::new())),
+            ..self.clone_with_connection(self.connection.copy_to_memory().await?)
+        };
+        let messages = Sandbox::get_messages(&rltbl).await?;
+        Ok(Sandbox {
+            rltbl,
+            batches: vec![],
+            messages,
+        })
+    }
+
+    /// Apply the given [BatchOperation] using the given transaction. It is an error for the
+    /// operation to have no effect.
+    fn _apply_batch_operation(
+        &self,
+        user: &str,
+        message: Option<&str>,
+        operation: &BatchOperation,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_apply_batch_operation({user:?}, {message:?}, {operation:?}, tx)"
+        );
+        match operation {
+            BatchOperation::SetValue {
+                table,
+                row,
+                column,
+                value,
+            } => {
+                let table = Table::_get_table(table, tx)?;
+                if !table.columns.contains_key(column) {
+                    return Err(RelatableError::InputError(format!(
+                        "No column '{column}' in table '{}'",
+                        table.name
+                    ))
+                    .into());
+                }
+                let sql = format!(
+                    r#"SELECT "{column}" FROM "{table}" WHERE "_id" = {sql_param}"#,
+                    table = table.name,
+                    sql_param = SqlParam::new(&tx.kind()).next()
+                );
+                let before = tx.query_value(&sql, Some(&json!([row])))?.ok_or(
+                    RelatableError::InputError(format!("No row {row} in table '{}'", table.name)),
+                )?;
+                self._set_values(
+                    &ChangeSet {
+                        user: user.to_string(),
+                        action: ChangeAction::Do,
+                        table: table.name.clone(),
+                        description: "Set one value".to_string(),
+                        message: message.map(|m| m.to_string()),
+                        changes: vec![Change::Update {
+                            row: *row,
+                            column: column.clone(),
+                            before,
+                            after: value.clone(),
+                        }],
+                    },
+                    tx,
+                )?;
+            }
+            BatchOperation::AddRow {
+                table,
+                after,
+                values,
+            } => {
+                if values.is_empty() {
+                    return Err(RelatableError::InputError(
+                        "Cannot insert an empty row to the database".to_string(),
+                    )
+                    .into());
+                }
+                let row = JsonRow {
+                    content: values.clone(),
+                };
+                self._add_row(&ChangeAction::Do, table, user, None, *after, &row, tx)?;
+            }
+            BatchOperation::DeleteRow { table, row } => {
+                if self._delete_row(&ChangeAction::Do, table, user, *row, tx)? < 1 {
+                    return Err(RelatableError::InputError(format!(
+                        "No row {row} in table '{table}'"
+                    ))
+                    .into());
+                }
+            }
+            BatchOperation::MoveRow { table, row, after } => {
+                if self._move_and_record_row(&ChangeAction::Do, table, user, *row, *after, tx)? == 0
+                {
+                    return Err(RelatableError::InputError(format!(
+                        "Could not move row {row} after row {after} in table '{table}'"
+                    ))
+                    .into());
+                }
+            }
+        };
+        Ok(())
+    }
+
+    /// Update the database using the given [ChangeSet]
+    fn _set_values(&self, changeset: &ChangeSet, tx: &mut DbTransaction<'_>) -> Result<ChangeSet> {
+        tracing::trace!("Relatable::_set_values({changeset:?}, tx)");
+        // Update the user cursor
+        self.prepare_user_cursor(changeset, tx)?;
+
+        // Actually make the changes:
+        let table = Table::_get_table(&changeset.table, tx)?;
+        if !table.editable {
+            return Err(
+                RelatableError::InputError(format!("{} is not editable.", table.name)).into(),
+            );
+        }
+        let meter = ChangeMeter::_start(&table.name, tx)?;
+        let severity_overrides = SeverityOverride::_get_overrides(&table.name, tx)?;
+        let mut actual_changes = vec![];
+        for change in &changeset.changes {
+            match change {
+                Change::Update {
+                    row,
+                    column,
+                    before,
+                    after,
+                } => {
+                    // Delete any existing messages associated with this row and column:
+                    tracing::debug!(
+                        "Deleting existing messages for column '{}.{}'",
+                        table.name,
+                        column
+                    );
+                    self._delete_message(tx, &table.name, Some(*row), Some(column), None, None)?;
+
+                    // Depending on whether this is an undo/redo or an original action, the
+                    // new value will be taken from either `before` or `after`.
+                    let before = JsonRow::nullify_value(&table, column, before);
+                    let after = JsonRow::nullify_value(&table, column, after);
+                    let mut cell = match &changeset.action {
+                        ChangeAction::Undo | ChangeAction::Redo => Cell {
+                            value: before.clone(),
+                            text: sql::json_to_string(&before),
+                            ..Default::default()
+                        },
+                        ChangeAction::Do => Cell {
+                            value: after.clone(),
+                            text: sql::json_to_string(&after),
+                            ..Default::default()
+                        },
+                    };
+
+                    // Validate the cell's SQL type and add any messages to the message table:
+                    let column_config = table.get_config_for_column(column);
+                    let mut sql_value = cell.value.clone();
+                    if self.validation_level != ValidationLevel::None {
+                        cell.validate_sql_type(&column_config)
+                            .expect("Error validating cell");
+                        cell.apply_severity_overrides(&severity_overrides);
+                        for message in cell.messages.iter() {
+                            let (msg_id, msg) = Relatable::_add_message(
+                                "rltbl",
+                                &table.name,
+                                &row,
+                                column,
+                                &cell.value,
+                                &message.level,
+                                &message.rule,
+                                &message.message,
+                                tx,
+                            )?;
+                            tracing::debug!("Added message (ID {msg_id}): {msg:?}");
+                        }
+
+                        // If the cell is invalid, insert a NULL instead of its actual value
+                        if cell.has_sql_type_error() {
+                            sql_value = JsonValue::Null;
+                        }
+                    }
+
+                    // Generate the UPDATE statement:
+                    let (sql, params) = {
+                        let mut sql_param = SqlParam::new(&self.connection.kind());
+                        let sql = format!(
+                            r#"UPDATE "{table}"
+                               SET "{column}" = {sql_value}
+                               WHERE _id = {sql_param}
+                               RETURNING 1 AS "updated""#,
+                            table = changeset.table,
+                            sql_value = match sql_value {
+                                JsonValue::Null => "NULL".to_string(),
+                                _ => sql_param.next(),
+                            },
+                            sql_param = sql_param.next()
+                        );
+                        let params = match sql_value {
+                            JsonValue::Null => json!([row]),
+                            _ => json!([
+                                sql::to_sql_param(&sql_value, &column_config.get_sql_type()),
+                                row
+                            ]),
+                        };
+                        (sql, params)
+                    };
+
+                    tracing::debug!(
+                        "Updating value of row {row} in {table}.{column} to {sql_value:?}",
+                        table = table.name
+                    );
+
+                    // Execute the UPDATE statement.
+                    if tx.query(&sql, Some(&params))?.len() < 1 {
+                        tracing::warn!("No row with _id {row} found to update");
+                    } else {
+                        actual_changes.push(Change::Update {
+                            row: *row,
+                            column: column.clone(),
+                            before: match &changeset.action {
+                                ChangeAction::Undo | ChangeAction::Redo => after.clone(),
+                                ChangeAction::Do => before.clone(),
+                            },
+                            after: match &changeset.action {
+                                ChangeAction::Undo | ChangeAction::Redo => before.clone(),
+                                ChangeAction::Do => after.clone(),
+                            },
+                        });
+                    }
+
+                    // Optionally do full validation on the newly updated cell and add further
+                    // messages to the message table:
+                    if self.validation_level == ValidationLevel::Full {
+                        self._validate_column_optionally_for_row(&column_config, Some(row), tx)?;
+                        self._validate_rules_for_table_optionally_for_row(&table, Some(row), tx)?;
+                        for column in &column_config._get_dependent_columns(tx)? {
+                            tracing::debug!("Validating dependent column '{}'", column.name);
+                            self._validate_structure_for_column_and_optionally_for_row(
+                                column, None, tx,
+                            )?;
+                        }
+                    }
+                }
+                _ => {
+                    return Err(RelatableError::InputError(format!(
+                        "Invalid change in changeset argument to set_values(): {change:?}"
+                    ))
+                    .into());
+                }
+            };
+        }
+
+        let num_changes = actual_changes.len();
+        let actual_changeset = ChangeSet {
+            action: changeset.action,
+            table: changeset.table.clone(),
+            user: changeset.user.clone(),
+            description: changeset.description.clone(),
+            message: changeset.message.clone(),
+            changes: actual_changes,
+        };
+        if num_changes > 0 {
+            // Record the changes to the change and history tables:
+            let change_id = self.record_changeset(&actual_changeset, tx)?;
+            let rows = actual_changeset
+                .changes
+                .iter()
+                .filter_map(|change| match change {
+                    Change::Update { row, .. } => Some(row),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>();
+            meter._finish(change_id, rows.len(), tx)?;
+            self.run_changeset_hooks(&self.hooks.post_update, &actual_changeset)?;
+        }
+
+        Ok(actual_changeset)
+    }
+
+    /// Update the database using the given [ChangeSet]
+    pub async fn set_values(&self, changeset: &ChangeSet) -> Result<ChangeSet> {
+        tracing::trace!("Relatable::set_values({changeset:?})");
+        let mut conn = self.connection.reconnect()?;
+        let changeset = {
+            let mut tx = self.connection.begin(&mut conn).await?;
+            let changeset = self._set_values(changeset, &mut tx)?;
+            tx.commit()?;
+            changeset
+        };
+        if changeset.changes.len() > 0 {
+            self.commit_to_git(changeset.message.as_deref()).await?;
+        }
+        Ok(changeset)
+    }
+
+    /// Update the database using the given [ChangeSet], as [Relatable::set_values()] does, and
+    /// also return the current messages of every cell whose messages were changed by the update,
+    /// whether it is one of the updated cells or another cell in the same table or in a table with
+    /// a column whose structure refers to an updated column, e.g., the other cell in a newly
+    /// created pair of duplicates. Clients can use these to update the affected cells without
+    /// fetching the tables again.
+    pub async fn set_values_with_messages(
+        &self,
+        changeset: &ChangeSet,
+    ) -> Result<(ChangeSet, Vec<CellMessages>)> {
+        tracing::trace!("Relatable::set_values_with_messages({changeset:?})");
+        let mut conn = self.connection.reconnect()?;
+        let (changeset, cell_messages) = {
+            let mut tx = self.connection.begin(&mut conn).await?;
+            let mut tables = vec![changeset.table.clone()];
+            let table = Table::_get_table(&changeset.table, &mut tx)?;
+            for change in &changeset.changes {
+                if let Change::Update { column, .. } = change {
+                    let column = table.get_config_for_column(column);
+                    for dependent in column._get_dependent_columns(&mut tx)? {
+                        if !tables.contains(&dependent.table) {
+                            tables.push(dependent.table);
+                        }
+                    }
+                }
+            }
+
+            let before = Self::_get_message_cells(&tables, &mut tx)?;
+            let changeset = self._set_values(changeset, &mut tx)?;
+            let after = Self::_get_message_cells(&tables, &mut tx)?;
+
+            // The cells that have gained, lost, or changed a message:
+            let mut cells = vec![];
+            for (key, cell) in before.iter().chain(after.iter()) {
+                if before.contains_key(key) != after.contains_key(key) && !cells.contains(cell) {
+                    cells.push(cell.clone());
+                }
+            }
+            let mut cell_messages = vec![];
+            for (table, row, column) in cells {
+                let mut sql_param = SqlParam::new(&tx.kind());
+                let sql = format!(
+                    r#"SELECT "value", "level", "rule", "message" FROM "message"
+                       WHERE "table" = {sql_param_1} AND "row" = {sql_param_2}
+                         AND "column" = {sql_param_3}
+                       ORDER BY "message_id""#,
+                    sql_param_1 = sql_param.next(),
+                    sql_param_2 = sql_param.next(),
+                    sql_param_3 = sql_param.next(),
+                );
+                let messages = tx
+                    .query(&sql, Some(&json!([table, row, column])))?
+                    .iter()
+                    .map(|message| {
+                        Ok(Message {
+                            value: message.get_value("value")?,
+                            level: message.get_string("level")?,
+                            rule: message.get_string("rule")?,
+                            message: message.get_string("message")?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                cell_messages.push(CellMessages {
+                    table,
+                    row,
+                    column,
+                    messages,
+                });
+            }
+            tx.commit()?;
+            (changeset, cell_messages)
+        };
+        if !changeset.changes.is_empty() {
+            self.commit_to_git(changeset.message.as_deref()).await?;
+        }
+        Ok((changeset, cell_messages))
+    }
+
+    /// Get the table, row, and column of each of the messages about the given tables, using the
+    /// given transaction. The messages are keyed by their content hash, level, and text, since the
+    /// text of a message, e.g., one that lists the other rows with the same value, can change
+    /// while its content hash stays the same.
+    fn _get_message_cells(
+        tables: &[String],
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<IndexMap<String, (String, u64, String)>> {
+        tracing::trace!("Relatable::_get_message_cells({tables:?}, tx)");
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT "table", "row", "column", "level", "message", "_hash" FROM "message"
+               WHERE "table" IN ({sql_params})"#,
+            sql_params = sql_param.get_as_list(tables.len()),
+        );
+        let mut cells = IndexMap::new();
+        for row in tx.query(&sql, Some(&json!(tables)))? {
+            let key = json!([
+                row.get_string("_hash")?,
+                row.get_string("level")?,
+                row.get_string("message")?
+            ]);
+            cells.insert(
+                key.to_string(),
+                (
+                    row.get_string("table")?,
+                    row.get_unsigned("row")?,
+                    row.get_string("column")?,
+                ),
+            );
+        }
+        Ok(cells)
+    }
+
+    /// Get the identifier used to tag the operations recorded by this instance, generating and
+    /// storing a new one if none has been assigned yet.
+    fn _get_origin(tx: &mut DbTransaction<'_>) -> Result<String> {
+        tracing::trace!("Relatable::_get_origin(tx)");
+        let sql = r#"SELECT "origin" FROM "origin" LIMIT 1"#;
+        if let Some(origin) = tx.query_value(sql, None)? {
+            return Ok(sql::json_to_string(&origin));
+        }
+        let origin = format!("{:016x}", rand::random::<u64>());
+        let sql = format!(
+            r#"INSERT INTO "origin" VALUES ({sql_param})"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([origin]);
+        tx.query(&sql, Some(&params))?;
+        tracing::info!("Assigned origin '{origin}' to this instance");
+        Ok(origin)
+    }
+
+    /// Get the vector clock of this instance, i.e., the highest counter that has been recorded in
+    /// the operation table for each origin, which is cached in the clock table.
+    fn _get_clock(tx: &mut DbTransaction<'_>) -> Result<IndexMap<String, u64>> {
+        tracing::trace!("Relatable::_get_clock(tx)");
+        let sql = r#"SELECT "origin", "counter" FROM "clock" ORDER BY "origin""#;
+        let mut clock = IndexMap::new();
+        for row in tx.query(sql, None)? {
+            clock.insert(row.get_string("origin")?, row.get_unsigned("counter")?);
+        }
+        Ok(clock)
+    }
+
+    /// Get the latest operation of one of the given kinds that applied, in this instance, to the
+    /// row of the given table with the given _id, if any.
+    fn _get_latest_operation(
+        table: &str,
+        row: u64,
+        kinds: &[OperationKind],
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<Operation>> {
+        tracing::trace!("Relatable::_get_latest_operation({table:?}, {row}, {kinds:?}, tx)");
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT * FROM "operation"
+                WHERE "table" = {sql_param_1} AND "local_row" = {sql_param_2}
+                  AND "kind" IN ({kinds})
+                ORDER BY "operation_id" DESC
+                LIMIT 1"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            kinds = kinds
+                .iter()
+                .map(|kind| format!("'{kind}'"))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        let params = json!([table, row]);
+        tx.query_one(&sql, Some(&params))?
+            .map(|json_row| Operation::from_json_row(&json_row))
+            .transpose()
+    }
+
+    /// Get the origin and _id by which the row with the given _id in the given table is identified
+    /// in the operations of every instance (see [Operation]).
+    fn _get_row_key(table: &str, row: u64, tx: &mut DbTransaction<'_>) -> Result<(String, u64)> {
+        tracing::trace!("Relatable::_get_row_key({table:?}, {row}, tx)");
+        match row {
+            0 => Ok((String::new(), 0)),
+            _ => match Self::_get_latest_operation(table, row, &[OperationKind::Add], tx)? {
+                Some(operation) => Ok((operation.row_origin, operation.row)),
+                None => Ok((String::new(), row)),
+            },
+        }
+    }
+
+    /// Get the _id, in this instance, of the row of the given table that is identified in the
+    /// operations of every instance by the given origin and _id (see [Operation]), or None if no
+    /// such row has been added to this instance.
+    fn _get_local_row(
+        table: &str,
+        row_origin: &str,
+        row: u64,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::_get_local_row({table:?}, {row_origin:?}, {row}, tx)");
+        if row_origin.is_empty() && row == 0 {
+            return Ok(Some(0));
+        }
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT "local_row" FROM "operation"
+                WHERE "table" = {sql_param_1} AND "row_origin" = {sql_param_2}
+                  AND "row" = {sql_param_3} AND "column" = '' AND "kind" = 'add'
+                  AND "local_row" {is_not} NULL
+                ORDER BY "operation_id" DESC
+                LIMIT 1"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+            is_not = sql::is_not_clause(&tx.kind()),
+        );
+        let params = json!([table, row_origin, row]);
+        match tx.query_one(&sql, Some(&params))? {
+            Some(json_row) => Ok(Some(json_row.get_unsigned("local_row")?)),
+            None if row_origin.is_empty() => Ok(Some(row)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the _id, in this instance, of the row of the given table that comes before the
+    /// position given by an operation (see [Operation]), or None if there is no such row.
+    fn _get_local_position(
+        table: &str,
+        position: Option<&JsonValue>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<u64>> {
+        tracing::trace!("Relatable::_get_local_position({table:?}, {position:?}, tx)");
+        match position
+            .cloned()
+            .map(serde_json::from_value::<(String, u64)>)
+        {
+            Some(Ok((row_origin, row))) => Self::_get_local_row(table, &row_origin, row, tx),
+            _ => Ok(None),
+        }
+    }
+
+    /// Get the _id, in this instance, of the row of the given table after which a row that is
+    /// being added or moved by the given operation is to be placed, or None if the row that the
+    /// operation places it after has not been added to this instance. Rows that have been placed
+    /// after the same row concurrently are ordered by the operations that placed them, the latest
+    /// first, so that every instance orders them in the same way whatever the order in which it
+    /// applies the operations. The row being moved, if any, is skipped over.
+    fn _get_merged_position(
+        table: &str,
+        operation: &Operation,
+        moving: Option<u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<u64>> {
+        tracing::trace!(
+            "Relatable::_get_merged_position({table:?}, {operation:?}, {moving:?}, tx)"
+        );
+        let position = operation.value.get("after");
+        let mut after = match Self::_get_local_position(table, position, tx)? {
+            Some(after) => after,
+            None => return Ok(None),
+        };
+        let mut cursor = after;
+        while let Some(next) = Table::_get_next_row_id(table, cursor, tx)? {
+            cursor = next;
+            if Some(next) == moving {
+                continue;
+            }
+            let kinds = [OperationKind::Add, OperationKind::Move];
+            match Self::_get_latest_operation(table, next, &kinds, tx)? {
+                Some(placed)
+                    if placed.value.get("after") == position
+                        && placed.sort_key() > operation.sort_key() =>
+                {
+                    after = next
+                }
+                _ => break,
+            };
+        }
+        Ok(Some(after))
+    }
+
+    /// Record, in the operation table, the given change that the given user has made to the given
+    /// table with the given action, and, for an addition, the given contents of the added row.
+    /// This is a no-op if there is no operation table, or if the change is being made while
+    /// merging the operations of another instance.
+    fn _record_operation(
+        user: &str,
+        table: &str,
+        action: &ChangeAction,
+        change: &Change,
+        json_row: Option<&JsonRow>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_record_operation({user:?}, {table:?}, {action:?}, {change:?}, \
+             {json_row:?}, tx)"
+        );
+        if MERGING_OPERATIONS.try_with(|_| ()).is_ok() || !Table::_table_exists("operation", tx)? {
+            return Ok(());
+        }
+        let origin = Self::_get_origin(tx)?;
+        let (kind, local_row, column, value) = match change {
+            Change::Update {
+                row, column, after, ..
+            } => (
+                OperationKind::Update,
+                *row,
+                column.to_string(),
+                after.clone(),
+            ),
+            Change::Add { row, after } => {
+                let cells = json_row
+                    .map(|json_row| {
+                        json_row
+                            .content
+                            .iter()
+                            .filter(|(column, _)| !column.starts_with('_'))
+                            .map(|(column, value)| (column.clone(), value.clone()))
+                            .collect::<JsonMap<_, _>>()
+                    })
+                    .unwrap_or_default();
+                let after = Self::_get_row_key(table, *after, tx)?;
+                let value = json!({"after": after, "cells": cells});
+                (OperationKind::Add, *row, String::new(), value)
+            }
+            Change::Move { row, to_after, .. } => {
+                let after = Self::_get_row_key(table, *to_after, tx)?;
+                let value = json!({ "after": after });
+                (OperationKind::Move, *row, "_order".to_string(), value)
+            }
+            Change::Delete { row, .. } => {
+                (OperationKind::Delete, *row, String::new(), JsonValue::Null)
+            }
+        };
+        // A row that is newly added by this instance is identified by its origin and its _id:
+        let (row_origin, row) = match (kind, action) {
+            (OperationKind::Add, ChangeAction::Do) => (origin.clone(), local_row),
+            _ => Self::_get_row_key(table, local_row, tx)?,
+        };
+        let mut clock = Self::_get_clock(tx)?;
+        let counter = clock.get(&origin).unwrap_or(&0) + 1;
+        clock.insert(origin.clone(), counter);
+        let operation = Operation {
+            origin,
+            counter,
+            clock,
+            datetime: chrono::Utc::now()
+                .format("%Y-%m-%d %H:%M:%S%.3f")
+                .to_string(),
+            user: user.to_string(),
+            table: table.to_string(),
+            kind,
+            row_origin,
+            row,
+            column,
+            value,
+        };
+        Self::_insert_operation(&operation, Some(local_row), tx)
+    }
+
+    /// Insert the given [Operation], which applies to the row with the given _id in this
+    /// instance, if any, into the operation table, and advance the clock accordingly.
+    fn _insert_operation(
+        operation: &Operation,
+        local_row: Option<u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::_insert_operation({operation:?}, {local_row:?}, tx)");
+        let sql = format!(
+            r#"INSERT INTO "operation"
+               ("origin", "counter", "clock", "datetime", "user", "table", "kind", "row_origin",
+                "row", "column", "value", "local_row")
+               VALUES ({sql_params})"#,
+            sql_params = SqlParam::new(&tx.kind()).get_as_list(12)
+        );
+        let params = json!([
+            operation.origin,
+            operation.counter,
+            json!(operation.clock).to_string(),
+            operation.datetime,
+            operation.user,
+            operation.table,
+            operation.kind.to_string(),
+            operation.row_origin,
+            operation.row,
+            operation.column,
+            operation.value.to_string(),
+            local_row,
+        ]);
+        tx.query(&sql, Some(&params))?;
+        let sql = format!(
+            r#"INSERT INTO "clock" ("origin", "counter") VALUES ({sql_params})
+               ON CONFLICT ("origin") DO UPDATE SET "counter" = EXCLUDED."counter"
+               WHERE "clock"."counter" < EXCLUDED."counter""#,
+            sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
+        );
+        let params = json!([operation.origin, operation.counter]);
+        tx.query(&sql, Some(&params))?;
+        Ok(())
+    }
+
+    /// Get all of the operations in the operation table, in the order in which they were recorded.
+    pub async fn get_operations(&self) -> Result<Vec<Operation>> {
+        tracing::trace!("Relatable::get_operations()");
+        let sql = r#"SELECT * FROM "operation" ORDER BY "operation_id""#;
+        self.connection
+            .query(sql, None)
+            .await?
+            .iter()
+            .map(Operation::from_json_row)
+            .collect()
+    }
+
+    /// Merge the given operations, which may have been recorded by other instances, into the
+    /// operation table, skipping those that are already present, and apply them to this instance
+    /// in one transaction, recording the changes that they make as usual, so that they can be
+    /// undone. For each cell, the value of the latest operation wins: an operation supersedes those
+    /// that it causally follows, and when two operations are concurrent, the one with the later
+    /// datetime (or, if these are the same, the greater origin) wins and a conflict message is
+    /// added to the message table. The same goes for the position of a row that is moved, while
+    /// the deletion of a row wins over any concurrent changes to it. Returns the number of
+    /// operations that were merged.
+    pub async fn merge_operations(&self, operations: &[Operation]) -> Result<usize> {
+        tracing::trace!("Relatable::merge_operations({operations:?})");
+        let mut operations = operations.iter().collect::<Vec<_>>();
+        operations.sort_by(|a, b| a.apply_key().cmp(&b.apply_key()));
+
+        let num_merged = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let mut num_merged = 0;
+                for operation in &operations {
+                    if MERGING_OPERATIONS
+                        .sync_scope((), || self._merge_operation(operation, &mut tx))?
+                    {
+                        num_merged += 1;
+                    }
+                }
+                tx.commit()?;
+                Ok(num_merged)
+            })
+            .await?;
+        if num_merged > 0 {
+            self.commit_to_git(Some("sync by rltbl")).await?;
+        }
+        Ok(num_merged)
+    }
+
+    /// Merge the given operation into the operation table and apply it to this instance (see
+    /// [Relatable::merge_operations()]), unless it is already present, in which case return false.
+    fn _merge_operation(&self, operation: &Operation, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Relatable::_merge_operation({operation:?}, tx)");
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT 1 FROM "operation"
+                WHERE "origin" = {sql_param_1} AND "counter" = {sql_param_2}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([operation.origin, operation.counter]);
+        if tx.query_one(&sql, Some(&params))?.is_some() {
+            return Ok(false);
+        }
+        let table = &operation.table;
+        if !Table::_table_exists(table, tx)? {
+            tracing::warn!("Not applying operation to table '{table}', which does not exist");
+            Self::_insert_operation(operation, None, tx)?;
+            return Ok(true);
+        }
+
+        let local_row = Self::_get_local_row(table, &operation.row_origin, operation.row, tx)?;
+        let local_row = match operation.kind {
+            OperationKind::Add => {
+                let after = Self::_get_merged_position(table, operation, None, tx)?;
+                let cells = match operation.value.get("cells") {
+                    Some(JsonValue::Object(cells)) => cells.clone(),
+                    _ => JsonMap::new(),
+                };
+                let new_row = self._add_row(
+                    &ChangeAction::Do,
+                    table,
+                    &operation.user,
+                    None,
+                    after,
+                    &JsonRow { content: cells },
+                    tx,
+                )?;
+                Some(new_row.id)
+            }
+            OperationKind::Delete => {
+                if let Some(local_row) = local_row {
+                    self._delete_row(&ChangeAction::Do, table, &operation.user, local_row, tx)?;
+                }
+                local_row
+            }
+            OperationKind::Update | OperationKind::Move => {
+                let mut sql_param = SqlParam::new(&tx.kind());
+                let sql = format!(
+                    r#"SELECT * FROM "operation"
+                        WHERE "table" = {sql_param_1} AND "row_origin" = {sql_param_2}
+                          AND "row" = {sql_param_3} AND "column" = {sql_param_4}"#,
+                    sql_param_1 = sql_param.next(),
+                    sql_param_2 = sql_param.next(),
+                    sql_param_3 = sql_param.next(),
+                    sql_param_4 = sql_param.next(),
+                );
+                let params = json!([table, operation.row_origin, operation.row, operation.column]);
+                let latest = tx
+                    .query(&sql, Some(&params))?
+                    .iter()
+                    .map(Operation::from_json_row)
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .reduce(|latest, op| if op.supersedes(&latest) { op } else { latest });
+                let (apply, conflict) = match &latest {
+                    None => (true, None),
+                    Some(latest) if latest.happened_before(operation) => (true, None),
+                    Some(latest) if operation.happened_before(latest) => (false, None),
+                    Some(latest) => match operation.supersedes(latest) {
+                        true => (true, Some((operation, latest))),
+                        false => (false, Some((latest, operation))),
+                    },
+                };
+                let current = match local_row {
+                    Some(local_row) => Table::_get_row(table, local_row, tx)?,
+                    None => None,
+                };
+                if let Some(current) = current.filter(|_| apply) {
+                    let local_row = current.get_unsigned("_id")?;
+                    match operation.kind {
+                        OperationKind::Move => {
+                            let moving = Some(local_row);
+                            if let Some(after) =
+                                Self::_get_merged_position(table, operation, moving, tx)?
+                            {
+                                self._move_and_record_row(
+                                    &ChangeAction::Do,
+                                    table,
+                                    &operation.user,
+                                    local_row,
+                                    after,
+                                    tx,
+                                )?;
+                            }
+                        }
+                        _ => {
+                            let changeset = ChangeSet {
+                                action: ChangeAction::Do,
+                                table: table.to_string(),
+                                user: operation.user.clone(),
+                                description: "Set one value".to_string(),
+                                message: None,
+                                changes: vec![Change::Update {
+                                    row: local_row,
+                                    column: operation.column.clone(),
+                                    before: current
+                                        .content
+                                        .get(&operation.column)
+                                        .cloned()
+                                        .unwrap_or_default(),
+                                    after: operation.value.clone(),
+                                }],
+                            };
+                            self._set_values(&changeset, tx)?;
+                        }
+                    };
+                }
+
+                // The conflict message is added once the value has been set, which clears the
+                // cell's existing messages:
+                if let (Some((kept, discarded)), Some(local_row), OperationKind::Update) =
+                    (conflict, local_row, operation.kind)
+                {
+                    let (message_id, _) = Relatable::_add_message(
+                        "rltbl",
+                        table,
+                        &local_row,
+                        &operation.column,
+                        &discarded.value,
+                        "warning",
+                        "sync:conflict",
+                        &format!(
+                            "Conflicting edits from origins '{}' and '{}': kept '{}', \
+                             discarded '{}'",
+                            kept.origin,
+                            discarded.origin,
+                            sql::json_to_string(&kept.value),
+                            sql::json_to_string(&discarded.value),
+                        ),
+                        tx,
+                    )?;
+                    tracing::debug!("Added conflict message (ID {message_id})");
+                    self._after_message_change(tx, table)?;
+                }
+                local_row
+            }
+        };
+        Self::_insert_operation(operation, local_row, tx)?;
+        Ok(true)
+    }
+
+    /// Synchronize the operation log of this instance with that of the given peer, merging the
+    /// peer's operations into this instance and this instance's operations into the peer. Returns
+    /// the number of operations pulled from and pushed to the peer, respectively.
+    pub async fn sync(&self, peer: &Relatable) -> Result<(usize, usize)> {
+        tracing::trace!("Relatable::sync({peer:?})");
+        let pulled = self.merge_operations(&peer.get_operations().await?).await?;
+        let pushed = peer.merge_operations(&self.get_operations().await?).await?;
+        Ok((pulled, pushed))
+    }
+
+    /// Get the raw contents of the cell in the given column of the given row of the given table,
+    /// or None if the cell is NULL. Binary values are decoded from their base64 representation,
+    /// while any other value is returned as the bytes of its text.
+    pub async fn get_cell_bytes(
+        &self,
+        table_name: &str,
+        row: &u64,
+        column: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        tracing::trace!("Relatable::get_cell_bytes({table_name:?}, {row}, {column:?})");
+        let table = Table::get_table(table_name, self).await?;
+        let column = match table.columns.get(column) {
+            Some(column) => column,
+            None => {
+                return Err(RelatableError::InputError(format!(
+                    "No column '{column}' in table '{table_name}'"
+                ))
+                .into())
+            }
+        };
+        let sql = format!(
+            r#"SELECT "{column}" FROM "{table_name}" WHERE "_id" = {sql_param}"#,
+            column = column.name,
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        let value = match self.connection.query_one(&sql, Some(&json!([row]))).await? {
+            Some(json_row) => json_row.get_value(&column.name)?,
+            None => {
+                return Err(RelatableError::InputError(format!(
+                    "No row {row} in table '{table_name}'"
+                ))
+                .into())
+            }
+        };
+        match value {
+            JsonValue::Null => Ok(None),
+            JsonValue::String(text)
+                if sql::canonical_sql_type(&column.get_sql_type()) == Some("BLOB") =>
+            {
+                Ok(Some(sql::decode_binary(&text)?))
+            }
+            value => Ok(Some(sql::json_to_string(&value).into_bytes())),
+        }
+    }
+
+    /// Get the [ChangeFeed] for the given table: the current contents of the rows that have been
+    /// changed since the change with the given ID, along with the IDs of the changed rows that no
+    /// longer exist, or, if no ID is given, the current contents of all of the rows of the table.
+    pub async fn get_change_feed(
+        &self,
+        table_name: &str,
+        after: Option<u64>,
+    ) -> Result<ChangeFeed> {
+        tracing::trace!("Relatable::get_change_feed({table_name:?}, {after:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        if !Table::_table_exists(table_name, &mut tx)? {
+            return Err(
+                RelatableError::InputError(format!("No table '{table_name}' found")).into(),
+            );
+        }
+        let table = Table::_get_table(table_name, &mut tx)?;
+        let mut feed = ChangeFeed {
+            table: table.name.to_string(),
+            change_id: table.change_id,
+            columns: table
+                .columns
+                .values()
+                .map(|column| (column.name.to_string(), column.get_sql_type()))
+                .collect(),
+            ..Default::default()
+        };
+        // Only the _id and _order meta columns are copied:
+        let keep = |row: JsonRow| {
+            row.content
+                .into_iter()
+                .filter(|(column, _)| {
+                    column == "_id" || column == "_order" || feed.columns.contains_key(column)
+                })
+                .collect::<JsonMap<_, _>>()
+        };
+
+        if let Some(after) = after {
+            let mut sql_param = SqlParam::new(&tx.kind());
+            let sql = format!(
+                r#"SELECT DISTINCT "row" FROM "history"
+                    WHERE "table" = {sql_param_1} AND "change_id" > {sql_param_2}
+                    ORDER BY "row""#,
+                sql_param_1 = sql_param.next(),
+                sql_param_2 = sql_param.next(),
+            );
+            let params = json!([table_name, after]);
+            for row in tx.query(&sql, Some(&params))? {
+                let row = row.get_unsigned("row")?;
+                match Table::_get_row(table_name, row, &mut tx)? {
+                    Some(json_row) => feed.rows.push(keep(json_row)),
+                    None => feed.deleted.push(row),
+                };
+            }
+
+            let mut sql_param = SqlParam::new(&tx.kind());
+            let sql = format!(
+                r#"SELECT "change_id", "rows_affected", "messages_added", "messages_removed",
+                          "elapsed_ms"
+                     FROM "change"
+                    WHERE "table" = {sql_param_1} AND "change_id" > {sql_param_2}
+                    ORDER BY "change_id""#,
+                sql_param_1 = sql_param.next(),
+                sql_param_2 = sql_param.next(),
+            );
+            for change in tx.query(&sql, Some(&params))? {
+                if let Some(metrics) = ChangeMetrics::from_record(&change) {
+                    feed.metrics
+                        .insert(change.get_unsigned("change_id")?, metrics);
+                }
+            }
+        } else {
+            let sql = format!(r#"SELECT * FROM "{table_name}" ORDER BY "_order""#);
+            feed.rows = tx.query(&sql, None)?.into_iter().map(keep).collect();
+        }
+        tx.commit()?;
+        Ok(feed)
+    }
+
+    /// Apply the given [ChangeFeed], pulled from the remote instance mirrored under the given
+    /// name, to the local copy of its table, creating the copy if it does not yet exist, and
+    /// record the feed's change ID as the last change pulled. Returns the number of rows that were
+    /// updated or deleted.
+    pub async fn apply_change_feed(&self, mirror: &str, feed: &ChangeFeed) -> Result<usize> {
+        tracing::trace!("Relatable::apply_change_feed({mirror:?}, {feed:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let table_name = &feed.table;
+        if !Table::_table_exists(table_name, &mut tx)? {
+            let table = Table {
+                name: table_name.to_string(),
+                columns: feed
+                    .columns
+                    .iter()
+                    .map(|(name, sql_type)| {
+                        let column = Column {
+                            name: name.to_string(),
+                            table: table_name.to_string(),
+                            datatype: Datatype {
+                                name: "text".to_string(),
+                                sql_type: sql_type.to_string(),
+                                ..Default::default()
+                            },
+                            ..Default::default()
+                        };
+                        (name.to_string(), column)
+                    })
+                    .collect(),
+                ..Default::default()
+            };
+            for sql in sql::generate_table_ddl(
+                &table,
+                false,
+                &tx.kind(),
+                &self.caching_strategy,
+                self.change_capture.as_ref(),
+            )? {
+                tx.query(&sql, None)?;
+            }
+            let sql = format!(
+                r#"INSERT INTO "table" ("table") VALUES ({sql_param})"#,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            tx.query(&sql, Some(&json!([table_name])))?;
+            self._capture_inserts("table", &mut tx)?;
+            tracing::info!("Created mirrored table '{table_name}'");
+        }
+
+        let ids = feed
+            .rows
+            .iter()
+            .map(|row| match row.get("_id") {
+                Some(id) => sql::json_to_unsigned(id),
+                None => Err(RelatableError::DataError("Row has no _id".to_string()).into()),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for id in ids.iter().chain(feed.deleted.iter()) {
+            let sql = format!(
+                r#"DELETE FROM "{table_name}" WHERE "_id" = {sql_param}"#,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            tx.query(&sql, Some(&json!([id])))?;
+        }
+        for row in &feed.rows {
+            let mut sql_param = SqlParam::new(&tx.kind());
+            let mut sql_values = vec![];
+            let mut params = vec![];
+            for (column, value) in row.iter() {
+                match value {
+                    JsonValue::Null => sql_values.push("NULL".to_string()),
+                    _ => {
+                        let sql_type = feed.columns.get(column).map_or("", |t| t.as_str());
+                        sql_values.push(sql_param.next());
+                        params.push(sql::to_sql_param(value, sql_type));
+                    }
+                };
+            }
+            let sql = format!(
+                r#"INSERT INTO "{table_name}" ({columns}) VALUES ({sql_values})"#,
+                columns = row
+                    .keys()
+                    .map(|column| format!(r#""{column}""#))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                sql_values = sql_values.join(", "),
+            );
+            tx.query(&sql, Some(&json!(params)))?;
+        }
+        self._capture_inserts(table_name, &mut tx)?;
+
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"UPDATE "mirror" SET "change_id" = {sql_param_1}
+                WHERE "mirror" = {sql_param_2} AND "table" = {sql_param_3}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+        );
+        tx.query(&sql, Some(&json!([feed.change_id, mirror, table_name])))?;
+
+        // Possibly delete dirty entries from the cache in accordance with our caching strategy:
+        self.change_capture
+            .after_change(self, &mut tx, table_name)?;
+        tx.commit()?;
+        Ok(ids.len() + feed.deleted.len())
+    }
+
+    /// Mirror the given tables of the remote rltbl server at the given URL, under the given name.
+    /// The local copies of the tables are read-only, and are created and kept up to date by
+    /// [Relatable::pull_mirrors()]. None of the tables may already exist locally, unless as a
+    /// copy of the same remote table.
+    pub async fn add_mirror(&self, mirror: &str, url: &str, tables: &[String]) -> Result<()> {
+        tracing::trace!("Relatable::add_mirror({mirror:?}, {url:?}, {tables:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        for table in tables {
+            let sql = format!(
+                r#"SELECT "mirror" FROM "mirror" WHERE "table" = {sql_param}"#,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            match tx.query_value(&sql, Some(&json!([table])))? {
+                Some(other) if sql::json_to_string(&other) != mirror => {
+                    return Err(RelatableError::InputError(format!(
+                        "Table '{table}' is already mirrored by '{other}'",
+                        other = sql::json_to_string(&other)
+                    ))
+                    .into())
+                }
+                Some(_) => {
+                    let mut sql_param = SqlParam::new(&tx.kind());
+                    let sql = format!(
+                        r#"UPDATE "mirror" SET "url" = {sql_param_1} WHERE "table" = {sql_param_2}"#,
+                        sql_param_1 = sql_param.next(),
+                        sql_param_2 = sql_param.next(),
+                    );
+                    tx.query(&sql, Some(&json!([url, table])))?;
+                }
+                None if Table::_table_exists(table, &mut tx)? => {
+                    return Err(RelatableError::InputError(format!(
+                        "Table '{table}' already exists and cannot be mirrored"
+                    ))
+                    .into())
+                }
+                None => {
+                    let sql = format!(
+                        r#"INSERT INTO "mirror" ("mirror", "url", "table") VALUES ({sql_params})"#,
+                        sql_params = SqlParam::new(&tx.kind()).get_as_list(3)
+                    );
+                    tx.query(&sql, Some(&json!([mirror, url, table])))?;
+                }
+            };
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Returns the columns of the given table that are indexed for full-text search, or None if
+    /// the table has no search index.
+    pub async fn get_search_columns(&self, table: &str) -> Result<Option<Vec<String>>> {
+        tracing::trace!("Relatable::get_search_columns({table:?})");
+        if !Table::table_exists("search", self).await? {
+            return Ok(None);
+        }
+        let sql = format!(
+            r#"SELECT "columns" FROM "search" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        match self
+            .connection
+            .query_value(&sql, Some(&json!([table])))
+            .await?
+        {
+            None => Ok(None),
+            Some(columns) => Ok(Some(serde_json::from_str(&sql::json_to_string(&columns))?)),
+        }
+    }
+
+    /// Add a full-text search index on the given columns of the given table, or on all of its
+    /// columns if none are given, replacing any index that the table already has. The index is
+    /// kept up to date by triggers on the table, and is recreated whenever the table is reloaded.
+    /// Returns the columns that were indexed.
+    pub async fn add_search(&self, table: &str, columns: &[String]) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::add_search({table:?}, {columns:?})");
+        if let ChangeCaptureMode::WritePath = self.change_capture.mode() {
+            return Err(RelatableError::InputError(
+                "Search indexes are maintained by triggers, which this database does not use"
+                    .to_string(),
+            )
+            .into());
+        }
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        if !Table::_table_exists(table, &mut tx)? {
+            return Err(RelatableError::InputError(format!("No table '{table}'")).into());
+        }
+        let (table_columns, _) = Table::_collect_column_info(table, &mut tx)?;
+        let columns = match columns.is_empty() {
+            true => table_columns
+                .iter()
+                .map(|column| column.name.clone())
+                .collect::<Vec<_>>(),
+            false => columns.to_vec(),
+        };
+        for column in &columns {
+            if !table_columns.iter().any(|c| c.name == *column) {
+                return Err(RelatableError::InputError(format!(
+                    "No column '{column}' in table '{table}'"
+                ))
+                .into());
+            }
+        }
+        for sql in sql::generate_search_index_ddl(table, &columns, &tx.kind()) {
+            tx.query(&sql, None)?;
+        }
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"DELETE FROM "search" WHERE "table" = {sql_param}"#,
+            sql_param = sql_param.next()
+        );
+        tx.query(&sql, Some(&json!([table])))?;
+        let sql = format!(
+            r#"INSERT INTO "search" ("table", "columns") VALUES ({sql_params})"#,
+            sql_params = SqlParam::new(&tx.kind()).get_as_list(2)
+        );
+        tx.query(&sql, Some(&json!([table, json!(columns).to_string()])))?;
+        tx.commit()?;
+        Ok(columns)
+    }
+
+    /// Remove the full-text search index from the given table.
+    pub async fn remove_search(&self, table: &str) -> Result<()> {
+        tracing::trace!("Relatable::remove_search({table:?})");
+        if self.get_search_columns(table).await?.is_none() {
+            return Err(
+                RelatableError::InputError(format!("Table '{table}' has no search index")).into(),
+            );
+        }
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        for sql in sql::generate_drop_search_index_ddl(table, &tx.kind()) {
+            tx.query(&sql, None)?;
+        }
+        let sql = format!(
+            r#"DELETE FROM "search" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        tx.query(&sql, Some(&json!([table])))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Rebuild the full-text search index of the given table, or of every table that has one if
+    /// no table is given, e.g., after the table has been changed outside of [rltbl](crate).
+    /// Returns the tables whose indexes were rebuilt.
+    pub async fn rebuild_search(&self, table: Option<&str>) -> Result<Vec<String>> {
+        tracing::trace!("Relatable::rebuild_search({table:?})");
+        let tables = match table {
+            Some(table) => vec![table.to_string()],
+            None => {
+                if !Table::table_exists("search", self).await? {
+                    return Ok(vec![]);
+                }
+                self.connection
+                    .query(r#"SELECT "table" FROM "search" ORDER BY "table""#, None)
+                    .await?
+                    .iter()
+                    .map(|row| row.get_string("table"))
+                    .collect::<Result<Vec<_>>>()?
+            }
+        };
+        for table in &tables {
+            match self.get_search_columns(table).await? {
+                None => {
+                    return Err(RelatableError::InputError(format!(
+                        "Table '{table}' has no search index"
+                    ))
+                    .into())
+                }
+                Some(columns) => {
+                    self.add_search(table, &columns).await?;
+                }
+            };
+        }
+        Ok(tables)
+    }
+
+    /// Pull the changes made since the last pull to each of the tables mirrored from the remote
+    /// instance with the given name, or from every mirrored instance if no name is given, and
+    /// apply them to the local copies. Returns the number of rows updated or deleted, by table.
+    pub async fn pull_mirrors(&self, mirror: Option<&str>) -> Result<IndexMap<String, usize>> {
+        tracing::trace!("Relatable::pull_mirrors({mirror:?})");
+        let (sql, params) = match mirror {
+            Some(mirror) => (
+                format!(
+                    r#"SELECT * FROM "mirror" WHERE "mirror" = {sql_param} ORDER BY "table""#,
+                    sql_param = SqlParam::new(&self.connection.kind()).next()
+                ),
+                Some(json!([mirror])),
+            ),
+            None => (
+                r#"SELECT * FROM "mirror" ORDER BY "mirror", "table""#.to_string(),
+                None,
+            ),
+        };
+        let rows = self.connection.query(&sql, params.as_ref()).await?;
+        if let (Some(mirror), true) = (mirror, rows.is_empty()) {
+            return Err(RelatableError::InputError(format!("No mirror '{mirror}'")).into());
+        }
+
+        let mut pulled = IndexMap::new();
+        for row in &rows {
+            let mirror = row.get_string("mirror")?;
+            let table = row.get_string("table")?;
+            let url = format!(
+                "{url}/changes/{table}{query}",
+                url = row.get_string("url")?.trim_end_matches('/'),
+                query = match row.get_value("change_id")? {
+                    JsonValue::Null => String::new(),
+                    change_id => format!("?after={}", sql::json_to_unsigned(&change_id)?),
+                },
+            );
+            tracing::debug!("Pulling '{table}' from mirror '{mirror}' at {url}");
+            let feed: ChangeFeed = ureq::get(&url).call()?.into_json()?;
+            if feed.table != table {
+                return Err(RelatableError::DataError(format!(
+                    "Expected changes to '{table}' from {url}, got changes to '{}'",
+                    feed.table
+                ))
+                .into());
+            }
+            let count = self.apply_change_feed(&mirror, &feed).await?;
+            pulled.insert(table, count);
+        }
+        Ok(pulled)
+    }
+
+    /// Add a message to the message table using the given [DbTransaction]
+    pub fn _add_message(
+        user: &str,
+        table_name: &str,
+        row: &u64,
+        column: &str,
+        value: &JsonValue,
+        level: &str,
+        rule: &str,
+        message: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<(u64, Message)> {
+        tracing::trace!(
+            "Relatable::add_message({user:?}, {table_name:?}, {row}, \
+             {column:?}, {value:?}, {level:?}, {rule:?}, {message:?}, tx)"
+        );
+
+        let sql = format!(
+            r#"INSERT INTO "message"
+               ("added_by", "table", "row", "column", "value",
+                "level", "rule", "message", "_hash", "_datetime")
+               VALUES
+               ({sql_params}, CURRENT_TIMESTAMP)
+               RETURNING "message_id""#,
+            sql_params = SqlParam::new(&tx.kind()).get_as_list(9)
+        );
+        let hash = Relatable::message_hash(table_name, *row, column, rule, value);
+        let params = json!([user, table_name, row, column, value, level, rule, message, hash]);
+        let message_id = tx
+            .query_one(&sql, Some(&params))?
+            .ok_or(RelatableError::DataError(
+                "Error inserting message".to_string(),
+            ))?
+            .get_unsigned("message_id")?;
+
+        Ok((
+            message_id,
+            Message {
+                value: value.clone(),
+                level: level.to_string(),
+                rule: rule.to_string(),
+                message: message.to_string(),
+            },
+        ))
+    }
+
+    /// Compute the content hash that identifies a message about the given value of the given
+    /// column and row of the given table, that was generated by the given rule. Two messages with
+    /// the same content hash are considered to be the same message.
+    pub fn message_hash(
+        table_name: &str,
+        row: u64,
+        column: &str,
+        rule: &str,
+        value: &JsonValue,
+    ) -> String {
+        let content = json!([table_name, row, column, rule, sql::json_to_string(value)]);
+        format!("{:x}", Sha256::digest(content.to_string().as_bytes()))
+    }
+
+    /// Compute and store the content hash of every message in the message table that does not
+    /// already have one, using the given transaction.
+    fn _hash_messages(tx: &mut DbTransaction<'_>) -> Result<()> {
+        tracing::trace!("Relatable::_hash_messages(tx)");
+        let sql = format!(
+            r#"SELECT "message_id", "table", "row", "column", "value", "rule"
+               FROM "message" WHERE "_hash" {is} NULL"#,
+            is = sql::is_clause(&tx.kind())
+        );
+        for message in tx.query(&sql, None)? {
+            let mut sql_param_gen = SqlParam::new(&tx.kind());
+            let sql = format!(
+                r#"UPDATE "message" SET "_hash" = {sql_param_1} WHERE "message_id" = {sql_param_2}"#,
+                sql_param_1 = sql_param_gen.next(),
+                sql_param_2 = sql_param_gen.next(),
+            );
+            let hash = Relatable::message_hash(
+                &message.get_string("table")?,
+                message.get_unsigned("row")?,
+                &message.get_string("column")?,
+                &message.get_string("rule").unwrap_or_default(),
+                &message.get_value("value")?,
+            );
+            let params = json!([hash, message.get_unsigned("message_id")?]);
+            tx.query(&sql, Some(&params))?;
+        }
+        Ok(())
+    }
+
+    /// Add a message to the message table.
+    pub async fn add_message(
+        &self,
+        user: &str,
+        table_name: &str,
+        row: u64,
+        column: &str,
+        value: &JsonValue,
+        level: &str,
+        rule: &str,
+        message: &str,
+    ) -> Result<(u64, Message)> {
+        tracing::trace!(
+            "Relatable::add_message({self:?},  {user:?}, {table_name:?}, {row}, \
+             {column:?}, {value:?}, {level:?}, {rule:?}, {message:?})"
+        );
+
+        // Begin a transaction:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        let (message_id, message) = Relatable::_add_message(
+            user, table_name, &row, column, value, level, rule, message, &mut tx,
+        )?;
+        self._after_message_change(&mut tx, table_name)?;
+
+        // Commit the transaction:
+        tx.commit()?;
+
+        Ok((message_id, message))
+    }
+
+    /// Add a row to the given table
+    #[allow(clippy::too_many_arguments)]
+    fn _add_row(
+        &self,
+        action: &ChangeAction,
+        table_name: &str,
+        user: &str,
+        new_row_id: Option<u64>,
+        after_id: Option<u64>,
+        row: &JsonRow,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Row> {
+        tracing::trace!(
+            "Relatable::_add_row({action:?}, {user:?}, {new_row_id:?}, \
+                         {after_id:?}, {row:?}, tx)"
+        );
+
+        // Get the current database information for the table:
+        let table = Table::_get_table(table_name, tx)?;
+        if !table.editable {
+            return Err(
+                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
+            );
+        }
+
+        let meter = ChangeMeter::_start(table_name, tx)?;
+
+        // Give any pre-insert hooks the chance to veto or augment an original insertion:
+        let row = match action {
+            ChangeAction::Do => self.run_pre_insert_hooks(table_name, row)?,
+            ChangeAction::Undo | ChangeAction::Redo => row.clone(),
+        };
+
+        // Nullify the JSON row by setting any column values whose content matches the column's
+        // nulltype to Null:
+        let row = JsonRow::nullify(&row, &table);
+
+        // Prepare a new row to be inserted using the JSON row as a base:
+        let mut new_row = Row::prepare_new(&table, Some(&row), tx)?;
+
+        // A new_row_id will have been passed if the row is being added as part of an undo/redo.
+        // In that case an after_id must have been passed as well but we leave the row order as
+        // is for now, since we are not assured that the old row order is actually still free in
+        // the table (recall that there is a unique constraint on _order). However the row_order
+        // currently assigned is at the end of the table so there should not be any conflicts.
+        if let Some(new_row_id) = new_row_id {
+            tracing::debug!("Changing new row ID to {new_row_id}");
+            new_row.id = new_row_id;
+        }
+
+        // Validate the row and add it to the table:
+        if self.validation_level != ValidationLevel::None {
+            new_row.validate_sql_types(&table, tx)?;
+            for (_column, cell) in new_row.cells.iter_mut() {
+                if cell.has_sql_type_error() {
+                    cell.value = JsonValue::Null;
+                    cell.text = "".to_string(); // Should it be "null" instead of blank?
+                }
+            }
+        }
+        let (sql, params) = new_row.as_insert(&table, &tx.kind());
+        tx.query(&sql, Some(&params))?;
+        self._capture_inserts(table_name, tx)?;
+
+        // Optionally do full validation on the row after it has been inserted:
+        if self.validation_level == ValidationLevel::Full {
+            self._validate_row(&table, &new_row.id, tx)?;
+            for table in &table._get_dependent_tables(None, tx)? {
+                tracing::debug!("Validating dependent table '{}'", table.name);
+                self._validate_structure_for_table(table, tx)?;
+            }
+        }
+
+        let after_id = match after_id {
+            None => Table::_get_previous_row_id(&table.name, new_row.id, tx)?,
+            Some(after_id) => {
+                // Move the row to its assigned spot within the table:
+                tracing::debug!(
+                    "Moving new row {id} to after row {after_id} in '{table}'",
+                    id = new_row.id,
+                    table = table.name
+                );
+                let new_order = self._move_row(tx, &table, new_row.id, after_id)?;
+                new_row.order = new_order;
+                after_id
+            }
+        };
+
+        tracing::debug!(
+            "Added new row {id} to table '{table}' after row {after_id}",
+            id = new_row.id,
+            table = table.name
+        );
+
+        // Prepare a changeset to be recorded, consisting of a single change record indicating
+        // the addition of one new row with the new_row's id and position in the table:
+        let changeset = ChangeSet {
+            action: *action,
+            table: table_name.to_string(),
+            user: user.to_string(),
+            description: "Add one row".to_string(),
+            message: None,
+            changes: vec![Change::Add {
+                row: new_row.id,
+                after: after_id,
+            }],
+        };
+
+        // Use the changeset to prepare the user cursor:
+        self.prepare_user_cursor(&changeset, tx)?;
+
+        // Record the changes to the history table:
+        let change_id = self.record_changeset(&changeset, tx)?;
+        meter._finish(change_id, 1, tx)?;
+
+        Ok(new_row)
+    }
+
+    /// Add a row to the given table
+    pub async fn add_row(
+        &self,
+        table_name: &str,
+        user: &str,
+        after_id: Option<u64>,
+        row: &JsonRow,
+    ) -> Result<Row> {
+        tracing::trace!("Relatable::add_row({table_name:?}, {user:?}, {after_id:?}, {row:?})");
+        let mut conn = self.connection.reconnect()?;
+        let new_row = {
+            let mut tx = self.connection.begin(&mut conn).await?;
+            let new_row = self._add_row(
+                &ChangeAction::Do,
+                table_name,
+                user,
+                None,
+                after_id,
+                row,
+                &mut tx,
+            )?;
+            tx.commit()?;
+            new_row
+        };
+        self.commit_to_git(None).await?;
+        Ok(new_row)
+    }
+
+    /// Get the row templates defined for the given table, as a map from the name of each template
+    /// to the default values that it assigns to the table's columns.
+    pub async fn get_templates(&self, table_name: &str) -> Result<IndexMap<String, JsonRow>> {
+        tracing::trace!("Relatable::get_templates({table_name:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let templates = Self::_get_templates(table_name, &mut tx)?;
+        tx.commit()?;
+        Ok(templates)
+    }
+
+    /// Get the row templates defined for the given table using the given transaction.
+    pub fn _get_templates(
+        table_name: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<IndexMap<String, JsonRow>> {
+        tracing::trace!("Relatable::_get_templates({table_name:?}, tx)");
+        let mut templates = IndexMap::new();
+        if !Table::_table_exists("template", tx)? {
+            return Ok(templates);
+        }
+        let sql = format!(
+            r#"SELECT "template", "column", "value" FROM "template"
+                WHERE "table" = {sql_param}
+                ORDER BY "template", "column""#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([table_name]);
+        for row in tx.query(&sql, Some(&params))? {
+            let template = templates
+                .entry(row.get_string("template")?)
+                .or_insert_with(JsonRow::new);
+            template
+                .content
+                .insert(row.get_string("column")?, json!(row.get_string("value")?));
+        }
+        Ok(templates)
+    }
+
+    /// Define the row template with the given name for the given table, which assigns the given
+    /// default values to the table's columns, replacing any existing template with that name.
+    pub async fn set_template(
+        &self,
+        table_name: &str,
+        template: &str,
+        values: &JsonRow,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::set_template({table_name:?}, {template:?}, {values:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        let table = Table::_get_table(table_name, &mut tx)?;
+        for column in values.content.keys() {
+            if !table.columns.contains_key(column) {
+                return Err(RelatableError::InputError(format!(
+                    "No column '{column}' in table '{table_name}'"
+                ))
+                .into());
+            }
+        }
+
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"DELETE FROM "template" WHERE "template" = {sql_param_1} AND "table" = {sql_param_2}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        tx.query(&sql, Some(&json!([template, table_name])))?;
+        for (column, value) in values.content.iter() {
+            let sql = format!(
+                r#"INSERT INTO "template" ("template", "table", "column", "value")
+                   VALUES ({sql_params})"#,
+                sql_params = SqlParam::new(&tx.kind()).get_as_list(4)
+            );
+            let params = json!([template, table_name, column, sql::json_to_string(value)]);
+            tx.query(&sql, Some(&params))?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Fill in the given row using the row template with the given name for the given table:
+    /// every column for which the row has no value, or an empty one, is assigned the template's
+    /// default value for that column, if it has one.
+    pub async fn apply_template(
+        &self,
+        table_name: &str,
+        template: &str,
+        row: &JsonRow,
+    ) -> Result<JsonRow> {
+        tracing::trace!("Relatable::apply_template({table_name:?}, {template:?}, {row:?})");
+        let templates = self.get_templates(table_name).await?;
+        let defaults = templates
+            .get(template)
+            .ok_or(RelatableError::InputError(format!(
+                "No template '{template}' for table '{table_name}'"
+            )))?;
+        let mut row = row.clone();
+        for (column, default) in defaults.content.iter() {
+            match row.content.get(column) {
+                None | Some(JsonValue::Null) => (),
+                Some(JsonValue::String(value)) if value.is_empty() => (),
+                Some(_) => continue,
+            };
+            row.content.insert(column.to_string(), default.clone());
+        }
+        Ok(row)
+    }
+
+    /// Delete a row from the table. Returns the number of rows deleted.
+    fn _delete_row(
+        &self,
+        action: &ChangeAction,
+        table_name: &str,
+        user: &str,
+        row: u64,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<usize> {
+        tracing::trace!("Relatable::_delete_row({action:?}, {table_name:?}, {user:?}, {row}, tx)");
+
+        // Get the current database information for the table:
+        let table = Table::_get_table(table_name, tx)?;
+        if !table.editable {
+            return Err(
+                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
+            );
+        }
+
+        let meter = ChangeMeter::_start(table_name, tx)?;
+
+        // Give any pre-delete hooks the chance to veto an original deletion:
+        if let ChangeAction::Do = action {
+            for hook in &self.hooks.pre_delete {
+                block_on(hook(table_name.to_string(), row))?;
+            }
+        }
+
+        // Prepare a changeset to be recorded, consisting of a single change record indicating
+        // that a row with the given row number at the given table position has been deleted:
+        let changeset = ChangeSet {
+            action: *action,
+            table: table_name.to_string(),
+            user: user.to_string(),
+            description: "Delete one row".to_string(),
+            message: None,
+            changes: vec![Change::Delete {
+                row: row,
+                after: Table::_get_previous_row_id(table_name, row, tx)?,
+            }],
+        };
+
+        // Use the changeset to prepare the user cursor:
+        self.prepare_user_cursor(&changeset, tx)?;
+
+        // Delete the row:
+        let sql = format!(
+            r#"DELETE FROM "{}" WHERE "_id" = {sql_param} RETURNING 1 AS "deleted""#,
+            table.name,
+            sql_param = SqlParam::new(&self.connection.kind()).next()
+        );
+        let params = json!([row]);
+        tracing::debug!("Deleted row {row} from table {table_name}");
+
+        // Delete any messages associated with the row
+        self._delete_message(tx, table_name, Some(row), None, None, None)?;
+        tracing::debug!("Deleted messages for deleted row {row} of table {table_name}");
+
+        // Record the change to the history table:
+        let change_id = self.record_changeset(&changeset, tx)?;
+
+        let num_deleted = tx.query(&sql, Some(&params))?.len();
+        if num_deleted < 1 {
+            tracing::warn!("No row found with _id {row} to delete");
+        }
+        meter._finish(change_id, num_deleted, tx)?;
+
+        Ok(num_deleted)
+    }
+
+    /// Delete a row from a given table
+    pub async fn delete_row(&self, table_name: &str, user: &str, row: u64) -> Result<usize> {
+        tracing::trace!("Relatable::delete_row({table_name:?}, {user:?}, {row})");
+        // Deleting a row may cascade to the rows that refer to it, which are read and changed in
+        // the same transaction:
+        let num_deleted = self
+            .retry_serialization_failures(|| async {
+                let mut conn = self.connection.reconnect()?;
+                let mut tx = self.begin_write(&mut conn).await?;
+                let num_deleted =
+                    self._delete_row(&ChangeAction::Do, table_name, user, row, &mut tx)?;
+                if num_deleted > 0 {
+                    tx.commit()?;
+                } else {
+                    // Roll back the changes to the history and change table. The reason we made
+                    // these prior to the actual delete was so that we could record the row's
+                    // position in the table before it was deleted.
+                    tx.rollback()?;
+                }
+                Ok(num_deleted)
+            })
+            .await?;
+        if num_deleted > 0 {
+            self.commit_to_git(None).await?;
+        }
+        Ok(num_deleted)
+    }
+
+    /// Move the rows of the given table that match all of the given filters (see
+    /// [Select::filters()]), along with their history and messages, to the archives of the table
+    /// and of the history and message tables (see [sql::archive_name()]), which are created if
+    /// necessary. The change records that are left without any history are moved to the archive
+    /// of the change table, so that they can no longer be undone or redone. Archived rows are left out of reads of the table unless they are explicitly
+    /// included (see [Select::include_archived()]), and can no longer be edited. Returns the
+    /// number of rows archived.
+    pub async fn archive_rows(&self, table_name: &str, filters: &Vec<String>) -> Result<usize> {
+        tracing::trace!("Relatable::archive_rows({table_name:?}, {filters:?})");
+        if filters.is_empty() {
+            return Err(RelatableError::InputError(
+                "At least one filter is required to archive rows".to_string(),
+            )
+            .into());
+        }
+        let mut select = Select::from(table_name).filters(filters)?.unlimited();
+        select.select_column("_id");
+        let (ids_sql, ids_params) = select.to_sql(&self.connection.kind())?;
+        let ids_params = json!(ids_params);
+
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        if sql::META_TABLES.contains(&table_name) || !Table::_table_exists(table_name, &mut tx)? {
+            return Err(
+                RelatableError::InputError(format!("No data table named '{table_name}'")).into(),
+            );
+        }
+        let count = tx
+            .query_value(
+                &format!(r#"SELECT COUNT(1) FROM ({ids_sql}) AS "ids""#),
+                Some(&ids_params),
+            )?
+            .and_then(|count| count.as_u64())
+            .unwrap_or_default() as usize;
+        if count == 0 {
+            return Ok(0);
+        }
+
+        // The archives have the same columns as the tables whose rows they hold:
+        let archive = sql::archive_name(table_name);
+        for table in [table_name, "change", "history", "message"] {
+            let archive = sql::archive_name(table);
+            if !Table::_table_exists(&archive, &mut tx)? {
+                tx.query(
+                    &format!(r#"CREATE TABLE "{archive}" AS SELECT * FROM "{table}" WHERE 1 = 0"#),
+                    None,
+                )?;
+            }
+        }
+        tx.query(
+            &format!(
+                r#"INSERT INTO "{archive}" SELECT * FROM "{table_name}" WHERE "_id" IN ({ids_sql})"#
+            ),
+            Some(&ids_params),
+        )?;
+
+        // Move the history and messages of all of the archived rows, and then the rows themselves:
+        let sql_param = SqlParam::new(&self.connection.kind()).next();
+        let archived =
+            format!(r#""table" = {sql_param} AND "row" IN (SELECT "_id" FROM "{archive}")"#);
+        for table in ["history", "message"] {
+            tx.query(
+                &format!(
+                    r#"INSERT INTO "{}" SELECT * FROM "{table}" WHERE {archived}"#,
+                    sql::archive_name(table)
+                ),
+                Some(&json!([table_name])),
+            )?;
+            tx.query(
+                &format!(r#"DELETE FROM "{table}" WHERE {archived}"#),
+                Some(&json!([table_name])),
+            )?;
+        }
+
+        // Move the change records that no longer have any history, so that they are no longer
+        // undone or redone:
+        let orphaned = format!(
+            r#""change_id" IN (SELECT "change_id" FROM "{history_archive}" WHERE {archived})
+               AND "change_id" NOT IN (SELECT "change_id" FROM "history")"#,
+            history_archive = sql::archive_name("history"),
+        );
+        tx.query(
+            &format!(
+                r#"INSERT INTO "{}" SELECT * FROM "change" WHERE {orphaned}"#,
+                sql::archive_name("change")
+            ),
+            Some(&json!([table_name])),
+        )?;
+        tx.query(
+            &format!(r#"DELETE FROM "change" WHERE {orphaned}"#),
+            Some(&json!([table_name])),
+        )?;
+        tx.query(
+            &format!(
+                r#"DELETE FROM "{table_name}" WHERE "_id" IN (SELECT "_id" FROM "{archive}")"#
+            ),
+            None,
+        )?;
+
+        // Possibly delete dirty entries from the cache in accordance with our caching strategy:
+        self.change_capture
+            .after_change(self, &mut tx, table_name)?;
+        tx.commit()?;
+        tracing::info!("Archived {count} rows of table '{table_name}'");
+        self.commit_to_git(None).await?;
+        Ok(count)
+    }
+
+    /// Delete messages from the message table. Returns the number of messages deleted.
+    pub async fn delete_message(
+        &self,
+        table: &str,
+        row: Option<u64>,
+        column: Option<&str>,
+        target_rule: Option<&str>,
+        target_user: Option<&str>,
+    ) -> Result<usize> {
+        tracing::trace!(
+            "Relatable::delete_message({self:?}, {table:?}, {row:?}, {column:?}, \
+             {target_rule:?}, {target_user:?})"
+        );
+
+        // Begin a transaction:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        // Delete the messages using the transaction
+        let num_deleted =
+            self._delete_message(&mut tx, table, row, column, target_rule, target_user)?;
+
+        // Commit the transaction:
+        tx.commit()?;
+
+        Ok(num_deleted)
+    }
+
+    /// Delete messages from the message table using the given transaction. Returns the
+    /// number of messages deleted.
+    fn _delete_message(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+        row: Option<u64>,
+        column: Option<&str>,
+        target_rule: Option<&str>,
+        target_user: Option<&str>,
+    ) -> Result<usize> {
+        tracing::trace!(
+            "Relatable::_delete_message({self:?}, tx, {table:?}, {row:?}, {column:?}, \
+             {target_rule:?}, {target_user:?})"
+        );
+
+        let (filter, params) =
+            Relatable::message_filter(&tx.kind(), table, row, column, target_rule, target_user);
+        let sql = format!(r#"DELETE FROM "message" WHERE {filter} RETURNING 1 AS "deleted""#);
+        let num_deleted = tx.query(&sql, Some(&json!(params)))?.len();
+        self._after_message_change(tx, table)?;
+        Ok(num_deleted)
+    }
+
+    /// Generate a WHERE clause, and its parameters, that matches the messages in the message table
+    /// for the given table, optionally restricted to the given row, column, rule (which may
+    /// contain SQL wildcards), and user.
+    fn message_filter(
+        db_kind: &DbKind,
+        table: &str,
+        row: Option<u64>,
+        column: Option<&str>,
+        target_rule: Option<&str>,
+        target_user: Option<&str>,
+    ) -> (String, Vec<JsonValue>) {
+        let mut sql_param = SqlParam::new(db_kind);
+        let mut filter = format!(r#""table" = {sql_param}"#, sql_param = sql_param.next());
+        let mut params = vec![json!(table)];
+
+        if let Some(row) = row {
+            filter.push_str(&format!(
+                r#" AND "row" = {sql_param}"#,
+                sql_param = sql_param.next(),
+            ));
+            params.push(json!(row));
+        }
+        if let Some(column) = column {
+            filter.push_str(&format!(
+                r#" AND "column" = {sql_param}"#,
+                sql_param = sql_param.next()
+            ));
+            params.push(json!(column));
+        }
+        if let Some(target_rule) = target_rule {
+            filter.push_str(&format!(
+                r#" AND "rule" LIKE {sql_param}"#,
+                sql_param = sql_param.next()
+            ));
+            params.push(json!(target_rule));
+        }
+        if let Some(target_user) = target_user {
+            filter.push_str(&format!(
+                r#" AND "added_by" = {sql_param}"#,
+                sql_param = sql_param.next()
+            ));
+            params.push(json!(target_user));
+        }
+        (filter, params)
+    }
+
+    /// Replace the messages added by rltbl for the given table, optionally restricted to the
+    /// given row and column, whose rules match the given rule (which may contain SQL wildcards),
+    /// with those added by the given validation function, using the given transaction. Messages
+    /// that are still valid keep their message IDs and have their timestamps refreshed, messages
+    /// that have been resolved are deleted, and only genuinely new messages are added.
+    fn _revalidate_messages<F>(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+        row: Option<u64>,
+        column: Option<&str>,
+        rule: &str,
+        validate: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut DbTransaction<'_>) -> Result<()>,
+    {
+        tracing::trace!(
+            "Relatable::_revalidate_messages(tx, {table:?}, {row:?}, {column:?}, {rule:?})"
+        );
+        let (filter, params) =
+            Relatable::message_filter(&tx.kind(), table, row, column, Some(rule), Some("rltbl"));
+        let sql = format!(
+            r#"SELECT "message_id", "table", "row", "column", "value", "rule"
+               FROM "message" WHERE {filter}"#
+        );
+        let params = json!(params);
+        let hash_of = |message: &JsonRow| -> Result<String> {
+            Ok(Relatable::message_hash(
+                &message.get_string("table")?,
+                message.get_unsigned("row")?,
+                &message.get_string("column")?,
+                &message.get_string("rule").unwrap_or_default(),
+                &message.get_value("value")?,
+            ))
+        };
+
+        // Set the existing messages aside, remembering their IDs by content hash, so that the
+        // validation function sees a clean slate:
+        let mut existing = HashMap::new();
+        for message in tx.query(&sql, Some(&params))? {
+            existing.insert(hash_of(&message)?, message.get_unsigned("message_id")?);
+        }
+        self._delete_message(tx, table, row, column, Some(rule), Some("rltbl"))?;
+
+        validate(tx)?;
+
+        // Give each of the resulting messages that was there before its old ID back:
+        for message in tx.query(&sql, Some(&params))? {
+            let hash = hash_of(&message)?;
+            let message_id = message.get_unsigned("message_id")?;
+            let mut sql_param_gen = SqlParam::new(&tx.kind());
+            let update = format!(
+                r#"UPDATE "message"
+                   SET "message_id" = {sql_param_1},
+                       "_hash" = {sql_param_2},
+                       "_datetime" = CURRENT_TIMESTAMP
+                   WHERE "message_id" = {sql_param_3}"#,
+                sql_param_1 = sql_param_gen.next(),
+                sql_param_2 = sql_param_gen.next(),
+                sql_param_3 = sql_param_gen.next(),
+            );
+            let new_id = existing.remove(&hash).unwrap_or(message_id);
+            tx.query(&update, Some(&json!([new_id, hash, message_id])))?;
+        }
+        Ok(())
+    }
+
+    /// Move a row and record the change in the change table
+    fn _move_and_record_row(
+        &self,
+        action: &ChangeAction,
+        table_name: &str,
+        user: &str,
+        id: u64,
+        after_id: u64,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<u64> {
+        tracing::trace!(
+            "Relatable::_move_and_record_row({action:?}, {table_name:?}, \
+                         {user:?}, {id}, {after_id}, tx)"
+        );
+
+        // Get the current database information for the table:
+        let table = Table::_get_table(table_name, tx)?;
+        if !table.editable {
+            return Err(
+                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
+            );
+        }
+
+        let meter = ChangeMeter::_start(table_name, tx)?;
+
+        // Prepare a changeset to be recorded, consisting of a single change record indicating
+        // that a row has been displaced from somewhere to somewhere else.
+        let changeset = ChangeSet {
+            action: *action,
+            table: table_name.to_string(),
+            user: user.to_string(),
+            description: "Move one row".to_string(),
+            message: None,
+            changes: vec![Change::Move {
+                row: id,
+                from_after: Table::_get_previous_row_id(table_name, id, tx)?,
+                to_after: after_id,
+            }],
+        };
+
+        // Use the changeset to prepare the user cursor:
+        self.prepare_user_cursor(&changeset, tx)?;
+
+        // Move the row within the table:
+        let new_order = self._move_row(tx, &table, id, after_id)?;
+
+        if new_order != 0 {
+            // Record the change to the history table:
+            let change_id = self.record_changeset(&changeset, tx)?;
+            meter._finish(change_id, 1, tx)?;
+        }
+
+        Ok(new_order)
+    }
+
+    /// Move a row to a different position in a given table
+    fn _move_row(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        table: &Table,
+        id: u64,
+        after_id: u64,
+    ) -> Result<u64> {
+        tracing::trace!("Relatable::_move_row(tx, {table:?}, {id}, {after_id})");
+        fn get_row_order(tx: &mut DbTransaction<'_>, table: &Table, row_id: u64) -> Result<u64> {
+            let sql = format!(
+                r#"SELECT "_order" FROM "{}" WHERE "_id" = {sql_param}"#,
+                table.name,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            let params = json!([row_id]);
+            let rows = tx.query(&sql, Some(&params))?;
+            if rows.is_empty() {
+                return Err(RelatableError::DataError(format!(
+                    "Unable to fetch _order for row {row_id} of table '{table}'",
+                    table = table.name
+                ))
+                .into());
+            }
+            match rows[0].content.get("_order").and_then(|o| o.as_u64()) {
+                Some(order) => Ok(order as u64),
+                None => {
+                    return Err(
+                        RelatableError::DataError("No integer '_order' in row".to_string()).into(),
+                    )
+                }
+            }
+        }
+
+        // Get the order, (A), of `after_id`:
+        let order_prev = {
+            if after_id > 0 {
+                let mut id_to_try = after_id;
+                let mut result = get_row_order(tx, table, id_to_try);
+                // This handles the case in which the after row has been deleted for some reason
+                // (this might happen if we are redoing).
+                while let Err(_) = result {
+                    if id_to_try == 0 {
+                        break;
+                    }
+                    tracing::debug!("Could not obtain _order for row {id_to_try}");
+                    id_to_try -= 1;
+                    tracing::debug!("Trying to find the _order of row {id_to_try}");
+                    result = get_row_order(tx, table, id_to_try);
+                }
+                result?
+            } else {
+                // It is not possible for a row to be assigned a order of zero. We allow it as a
+                // possible value of `after_id`, however, which is used as a special value that we
+                // should move the row identified by `id` to the beginning of the table.
+                0
+            }
+        };
+
+        // Run a query to get the minimum order, (B), that is greater than (A).
+        let order_next = {
+            let sql = format!(
+                r#"SELECT MIN("_order") AS "_order" FROM "{}" WHERE "_order" > {sql_param}"#,
+                table.name,
+                sql_param = SqlParam::new(&tx.kind()).next()
+            );
+            let params = json!([order_prev]);
+            let rows = tx.query(&sql, Some(&params))?;
+            if rows.is_empty() {
+                return Err(RelatableError::DataError(format!(
+                    "Could not determine the minimum row order greater than {order_prev}"
+                ))
+                .into());
+            }
+
+            match rows[0].content.get("_order") {
+                Some(value) => match value {
+                    JsonValue::Null => {
+                        // The row_order will be null if we ask Relatable to move a row to
+                        // a position after the last row in the table.
+                        order_prev + NEW_ORDER_MULTIPLIER as u64
+                    }
+                    _ => match value.as_u64() {
+                        Some(order) => order as u64,
+                        None => {
+                            return Err(RelatableError::DataError(
+                                "Field '_order' in row is not an integer".to_string(),
+                            )
+                            .into());
+                        }
+                    },
+                },
+                None => {
+                    return Err(RelatableError::DataError("No '_order' in row".to_string()).into());
+                }
+            }
+        };
+
+        let mut new_order = {
+            if order_prev + 1 < order_next {
+                // If the next order is not occupied just use it:
+                order_prev + 1
+            } else {
+                // Otherwise, get all the orders that need to be moved. We sort the results in
+                // descending order so that when we later update each value, no duplicate key
+                // violations will ensue:
+                let upper_bound = (order_next as f32 / NEW_ORDER_MULTIPLIER as f32).ceil() as u64
+                    * NEW_ORDER_MULTIPLIER as u64;
+                let mut sql_param = SqlParam::new(&tx.kind());
+                let sql = format!(
+                    r#"SELECT "_order"
+                         FROM "{}"
+                        WHERE "_order" >= {sql_param_1} AND "_order" < {sql_param_2}
+                     ORDER BY "_order" DESC"#,
+                    table.name,
+                    sql_param_1 = sql_param.next(),
+                    sql_param_2 = sql_param.next()
+                );
+                let params = json!([order_next, upper_bound]);
+                let rows = tx.query(&sql, Some(&params))?;
+                if rows.is_empty() {
+                    return Err(RelatableError::DataError(
+                        "Could not determine the highest row order".to_string(),
+                    )
+                    .into());
+                }
+                let highest_order = match rows[0].content.get("_order").and_then(|o| o.as_u64()) {
+                    Some(order) => order as u64,
+                    None => {
+                        return Err(RelatableError::DataError(
+                            "No field '_order' in row or it is not an integer".to_string(),
+                        )
+                        .into())
+                    }
+                };
+                if highest_order + 1 >= upper_bound {
+                    // Return an error
+                    return Err(RelatableError::DataError(format!(
+                        "Impossible to move row {} after row {}: No more room",
+                        id, after_id
+                    ))
+                    .into());
+                }
+
+                for row in rows {
+                    let current_order = match row.content.get("_order").and_then(|o| o.as_u64()) {
+                        Some(order) => order as u64,
+                        None => {
+                            return Err(RelatableError::DataError(
+                                "No field '_order' in row or it is not an integer".to_string(),
+                            )
+                            .into())
+                        }
+                    };
+                    let sql = format!(
+                        r#"UPDATE "{}"
+                              SET "_order" = "_order" + 1
+                            WHERE "_order" = {sql_param}"#,
+                        table.name,
+                        sql_param = SqlParam::new(&tx.kind()).next()
+                    );
+                    let params = json!([current_order]);
+                    tx.query(&sql, Some(&params))?;
+                }
+                // Now that we have made some room, we can use order_prev + 1,
+                // which should no longer be occupied:
+                order_prev + 1
+            }
+        };
+
+        tracing::debug!(
+            "Updating _order in table '{table}' for row {id} to {new_order}",
+            table = table.name
+        );
+
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"UPDATE "{}" SET "_order" = {sql_param_1}
+               WHERE "_id" = {sql_param_2}
+               RETURNING 1 AS "moved""#,
+            table.name,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([new_order, id]);
+        if tx.query(&sql, Some(&params))?.len() < 1 {
+            tracing::warn!("Now row with _id {id} found to move");
+            // It is not possible for a row to have an order of zero. It is used here to
+            // represent the case where no row was actually moved to the caller.
+            new_order = 0;
+        }
+        Ok(new_order)
+    }
+
+    /// Change the _id of the given row in the given table.
+    fn _change_row_id(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        table: &Table,
+        id: u64,
+        new_id: u64,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::_change_row_id(tx, {table:?}, {id}, {new_id})");
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"UPDATE "{table}"
+                  SET "_id" = {sql_param_1}, "_order" = {sql_param_2}
+                WHERE "_id" = {sql_param_3}
+            RETURNING "_id" AS "_id""#,
+            table = table.name,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+        );
+        let params = json!([new_id, id, id * NEW_ORDER_MULTIPLIER as u64]);
+        tx.query_one(&sql, Some(&params))?
+            .ok_or(RelatableError::DataError(format!("No row with _id = {id}")))?
+            .get_unsigned("_id")?;
+        Ok(())
+    }
+
+    /// Fill the given column of the given range of rows of the given table, from the row with
+    /// the first id to the row with the second, inclusive, in the table's order, as a single
+    /// change by the given user, as a spreadsheet's fill-down does. The value of the first row is
+    /// copied to the others, unless `series` is set, in which case the series of numbers or dates
+    /// that the first two rows begin is extended, by the difference between their values, or by
+    /// one (day) if the second row does not have a value of the same kind. Only the cells whose
+    /// values change are updated, each with its own history, and the changes that were made are
+    /// returned.
+    pub async fn fill_down(
+        &self,
+        table_name: &str,
+        user: &str,
+        column: &str,
+        from_row: u64,
+        to_row: u64,
+        series: bool,
+    ) -> Result<ChangeSet> {
+        tracing::trace!(
+            "Relatable::fill_down({table_name:?}, {user:?}, {column:?}, {from_row}, {to_row}, \
+             {series})"
+        );
+        let table = Table::get_table(table_name, self).await?;
+        if !table.columns.contains_key(column) {
+            return Err(RelatableError::InputError(format!(
+                "No column '{column}' in table '{table_name}'"
+            ))
+            .into());
+        }
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let sql = format!(
+            r#"SELECT "_id", "{column}" AS "value" FROM "{table_name}"
+               WHERE "_order" >= (SELECT "_order" FROM "{table_name}" WHERE "_id" = {from})
+                 AND "_order" <= (SELECT "_order" FROM "{table_name}" WHERE "_id" = {to})
+               ORDER BY "_order""#,
+            from = sql_param.next(),
+            to = sql_param.next(),
+        );
+        let rows = self
+            .connection
+            .query(&sql, Some(&json!([from_row, to_row])))
+            .await?
+            .iter()
+            .map(|row| Ok((row.get_unsigned("_id")?, row.get_value("value")?)))
+            .collect::<Result<Vec<_>>>()?;
+        if rows.first().map(|(id, _)| *id) != Some(from_row) {
+            return Err(RelatableError::InputError(format!(
+                "Cannot fill down from row {from_row} to row {to_row} of '{table_name}'"
+            ))
+            .into());
+        }
+
+        let first = &rows[0].1;
+        let values = match series {
+            false => vec![first.clone(); rows.len()],
+            true => fill_series(first, rows.get(1).map(|(_, value)| value), rows.len())?,
+        };
+        let changes = rows
+            .iter()
+            .zip(values)
+            .filter(|((_, before), after)| {
+                sql::json_to_string(before) != sql::json_to_string(after)
+            })
+            .map(|((row, before), after)| Change::Update {
+                row: *row,
+                column: column.to_string(),
+                before: before.clone(),
+                after,
+            })
+            .collect::<Vec<_>>();
+        self.set_values(&ChangeSet {
+            user: user.to_string(),
+            action: ChangeAction::Do,
+            table: table_name.to_string(),
+            description: format!("Fill down {} values", changes.len()),
+            message: None,
+            changes,
+        })
+        .await
+    }
+
+    /// Paste the given block of tab-separated values, as copied from a spreadsheet, into the
+    /// given table by the given user, with its top-left cell at the given row and column and its
+    /// other cells in the rows and columns that follow them in the table's display order. The
+    /// block must be rectangular and must fit within the table. Each value is converted to the
+    /// SQL type of its column and checked against it, and all of the cells whose values change
+    /// are updated as a single change group. The result of each cell of the block is returned,
+    /// row by row.
+    pub async fn paste(
+        &self,
+        table_name: &str,
+        user: &str,
+        row: u64,
+        column: &str,
+        tsv: &str,
+    ) -> Result<Vec<PastedCell>> {
+        tracing::trace!("Relatable::paste({table_name:?}, {user:?}, {row}, {column:?}, {tsv:?})");
+        let block = tsv
+            .lines()
+            .map(|line| {
+                line.trim_end_matches('\r')
+                    .split('\t')
+                    .map(|text| text.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let width = match block.first() {
+            None => return Ok(vec![]),
+            Some(first) => first.len(),
+        };
+        if let Some(line) = block.iter().position(|cells| cells.len() != width) {
+            return Err(RelatableError::InputError(format!(
+                "Line {} of the pasted block has {} values, but the first line has {width}",
+                line + 1,
+                block[line].len()
+            ))
+            .into());
+        }
+
+        let table = Table::get_table(table_name, self).await?;
+        let columns = table
+            .columns
+            .values()
+            .skip_while(|c| c.name != column)
+            .take(width)
+            .collect::<Vec<_>>();
+        if columns.is_empty() {
+            return Err(RelatableError::InputError(format!(
+                "No column '{column}' in table '{table_name}'"
+            ))
+            .into());
+        }
+        if columns.len() < width {
+            return Err(RelatableError::InputError(format!(
+                "The pasted block is {width} columns wide, but only {} columns of '{table_name}' \
+                 start at '{column}'",
+                columns.len()
+            ))
+            .into());
+        }
+
+        let mut sql_param = SqlParam::new(&self.connection.kind());
+        let sql = format!(
+            r#"SELECT "_id", {columns} FROM "{table_name}"
+               WHERE "_order" >= (SELECT "_order" FROM "{table_name}" WHERE "_id" = {row})
+               ORDER BY "_order"
+               LIMIT {height}"#,
+            columns = columns
+                .iter()
+                .map(|c| format!(r#""{}""#, c.name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            row = sql_param.next(),
+            height = block.len(),
+        );
+        let rows = self.connection.query(&sql, Some(&json!([row]))).await?;
+        if rows.first().map(|r| r.get_unsigned("_id")).transpose()? != Some(row) {
+            return Err(RelatableError::InputError(format!(
+                "No row {row} in table '{table_name}'"
+            ))
+            .into());
+        }
+        if rows.len() < block.len() {
+            return Err(RelatableError::InputError(format!(
+                "The pasted block is {} rows high, but only {} rows of '{table_name}' start at \
+                 row {row}",
+                block.len(),
+                rows.len()
+            ))
+            .into());
+        }
+
+        let mut cells = vec![];
+        let mut changes = vec![];
+        for (texts, current) in block.iter().zip(&rows) {
+            let id = current.get_unsigned("_id")?;
+            for (text, column) in texts.iter().zip(&columns) {
+                let before = current.get_value(&column.name)?;
+                let after = pasted_value(text, column);
+                let mut cell = Cell::from(&after);
+                cell.validate_sql_type(column)?;
+                let changed = sql::json_to_string(&before) != sql::json_to_string(&after);
+                if changed {
+                    changes.push(Change::Update {
+                        row: id,
+                        column: column.name.to_string(),
+                        before: before.clone(),
+                        after: after.clone(),
+                    });
+                }
+                cells.push(PastedCell {
+                    row: id,
+                    column: column.name.to_string(),
+                    before,
+                    after,
+                    changed,
+                    messages: cell.messages,
+                });
+            }
+        }
+        self.set_values_in_group(&vec![ChangeSet {
+            user: user.to_string(),
+            action: ChangeAction::Do,
+            table: table_name.to_string(),
+            description: format!("Paste {} values", changes.len()),
+            message: None,
+            changes,
+        }])
+        .await?;
+        Ok(cells)
+    }
+
+    /// Move a row to a different position in a given table.
+    pub async fn move_row(
+        &self,
+        table_name: &str,
+        user: &str,
+        id: u64,
+        after_id: u64,
+    ) -> Result<u64> {
+        tracing::trace!("Relatable::move_row({table_name:?}, {user:?}, {after_id:?})");
+        let mut conn = self.connection.reconnect()?;
+        let new_order = {
+            let mut tx = self.connection.begin(&mut conn).await?;
+            let new_order = self._move_and_record_row(
+                &ChangeAction::Do,
+                table_name,
+                user,
+                id,
+                after_id,
+                &mut tx,
+            )?;
+            tx.commit()?;
+            new_order
+        };
+        if new_order != 0 {
+            self.commit_to_git(None).await?;
+        }
+        Ok(new_order)
+    }
+
+    /// Validate all of the data in the given database table
+    pub async fn validate_table(&self, table: &Table) -> Result<()> {
+        tracing::trace!("Relatable::validate_table({self:?}, {table:?})");
+
+        // Reconnect and begin a transaction:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        self._validate_table(table, &mut tx)?;
+
+        // Commit the transaction
+        tx.commit()?;
+
+        tracing::info!("Validated table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Validate all of the data in the given database table using the given transaction
+    fn _validate_table(&self, table: &Table, tx: &mut DbTransaction<'_>) -> Result<()> {
+        tracing::trace!("Relatable::_validate_table({self:?}, {table:?}, tx)");
+
+        // Validate each table column
+        for (_, column) in table.columns.iter() {
+            self._validate_column_optionally_for_row(column, None, tx)?;
+        }
+
+        // Validate the table's rules:
+        self._validate_rules_for_table_optionally_for_row(table, None, tx)?;
+        self._after_message_change(tx, &table.name)?;
+
+        tracing::debug!("Validated table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Do datatype validation on all of the data in the given database table
+    pub async fn validate_datatype_for_table(&self, table: &Table) -> Result<()> {
+        tracing::trace!("Relatable::validate_datatype_for_table({self:?}, {table:?})");
+
+        // Reconnect and begin a transaction:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        self._validate_datatype_for_table(table, &mut tx)?;
+
+        // Commit the transaction
+        tx.commit()?;
+
+        tracing::info!("Validated datatype for table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Do datatype validation on all of the data in the given table using the given database
+    /// transaction
+    fn _validate_datatype_for_table(
+        &self,
+        table: &Table,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::_validate_datatype_for_table({self:?}, {table:?}, tx)");
+
+        // Validate each table column
+        for (_, column) in table.columns.iter() {
+            self._validate_datatype_for_column_and_optionally_for_row(column, None, tx)?;
+        }
+
+        tracing::debug!("Validated datatype for table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Do structure validation on all of the data in the given database table
+    pub async fn validate_structure_for_table(&self, table: &Table) -> Result<()> {
+        tracing::trace!("Relatable::validate_structure_for_table({self:?}, {table:?})");
+
+        // Reconnect and begin a transaction:
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+
+        self._validate_structure_for_table(table, &mut tx)?;
+
+        // Commit the transaction
+        tx.commit()?;
+
+        tracing::info!("Validated structure for table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Do structure validation on all of the data in the given database table using the given
+    /// database transation
+    fn _validate_structure_for_table(
+        &self,
+        table: &Table,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!("Relatable::_validate_structure_for_table({self:?}, {table:?}, tx)");
+
+        // Validate each table column
+        for (_, column) in table.columns.iter() {
+            self._validate_structure_for_column_and_optionally_for_row(column, None, tx)?;
+        }
+
+        tracing::debug!("Validated structure for table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Re-run the datatype validation of exactly those columns whose validation depends on the
+    /// given datatype, e.g., after its condition has been changed. Returns the columns that were
+    /// revalidated.
+    pub async fn revalidate_for_datatype(&self, datatype: &str) -> Result<Vec<Column>> {
+        tracing::trace!("Relatable::revalidate_for_datatype({self:?}, {datatype})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        let columns = self._revalidate_for_datatype(datatype, &mut tx)?;
+        tx.commit()?;
+        tracing::info!(
+            "Revalidated {} column(s) for datatype '{datatype}'",
+            columns.len()
+        );
+        Ok(columns)
+    }
+
+    /// Re-run the datatype validation of exactly those columns whose validation depends on the
+    /// given datatype, using the given transaction. Returns the columns that were revalidated.
+    fn _revalidate_for_datatype(
+        &self,
+        datatype: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Vec<Column>> {
+        tracing::trace!("Relatable::_revalidate_for_datatype({self:?}, {datatype}, tx)");
+        let columns = Datatype::_get_dependent_columns(datatype, tx)?;
+        for column in &columns {
+            self._validate_datatype_for_column_and_optionally_for_row(column, None, tx)?;
+        }
+        Ok(columns)
+    }
+
+    /// Re-run the validation of the rules defined for the given table in the rule table, e.g.,
+    /// after a rule has been added or changed, without revalidating its columns.
+    pub async fn revalidate_rules(&self, table: &Table) -> Result<()> {
+        tracing::trace!("Relatable::revalidate_rules({self:?}, {table:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        self._validate_rules_for_table_optionally_for_row(table, None, &mut tx)?;
+        tx.commit()?;
+        tracing::info!("Revalidated rules for table '{}'", table.name);
+        Ok(())
+    }
+
+    /// Validate the data in the given column associated with a table in the database
+    pub async fn validate_column(&self, column: &Column) -> Result<()> {
+        tracing::trace!("Relatable::validate_column({self:?}, {column:?})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        self._validate_column_optionally_for_row(column, None, &mut tx)?;
+        tx.commit()?;
+        tracing::info!("Validated column '{}.{}'", column.table, column.name);
+        Ok(())
+    }
+
+    /// Validate the value of the given column in the given row in the associated database
+    /// table
+    pub async fn validate_value(&self, column: &Column, row: &u64) -> Result<()> {
+        tracing::trace!("Relatable::validate_value({self:?}, {column:?}, {row})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        self._validate_column_optionally_for_row(column, Some(row), &mut tx)?;
+        tx.commit()?;
+        tracing::info!(
+            "Validated value at row {}, column '{}.{}'",
+            row,
+            column.table,
+            column.name
+        );
+        Ok(())
+    }
+
+    /// Validate the given row of the given table
+    pub async fn validate_row(&self, table: &Table, row: &u64) -> Result<()> {
+        tracing::trace!("Relatable::validate_row({self:?}, {table:?}, {row})");
+        let mut conn = self.connection.reconnect()?;
+        let mut tx = self.connection.begin(&mut conn).await?;
+        self._validate_row(table, row, &mut tx)?;
+        tx.commit()?;
+        tracing::info!("Validated row {} of table '{}'", row, table.name);
+        Ok(())
+    }
+
+    /// Validate the given row of the given table using the given database transaction
+    fn _validate_row(&self, table: &Table, row: &u64, tx: &mut DbTransaction<'_>) -> Result<()> {
+        tracing::trace!("Relatable::_validate_row({self:?}, {table:?}, {row}, tx)");
+        for (_, column) in table.columns.iter() {
+            self._validate_column_optionally_for_row(column, Some(row), tx)?;
+        }
+        self._validate_rules_for_table_optionally_for_row(table, Some(row), tx)?;
+        self._after_message_change(tx, &table.name)?;
+        tracing::debug!("Validated row {} of table '{}'", row, table.name);
+        Ok(())
+    }
+
+    /// Validate the datatype of the given column in its associated database table using the
+    /// given transaction. If `row` is given, only validate the column for that row.
+    fn _validate_datatype_for_column_and_optionally_for_row(
+        &self,
+        column: &Column,
+        row: Option<&u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_validate_datatype_for_column_and_optionally_for_row(\
+             {self:?}, {column:?}, {row:?}, tx)"
+        );
+
+        let table_name = column.table.as_str();
+
+        // Gather the datatypes to check: The column's datatype, plus any further datatypes in
+        // the datatype hierarchy:
+        let mut datatypes_to_check = vec![column.datatype.clone()];
+        datatypes_to_check.append(&mut column.datatype_hierarchy.clone());
+
+        // Replace the pre-existing datatype validation messages for this column with those that
+        // result from validating the datatype conditions for each datatype in the hierarchy:
+        self._revalidate_messages(
+            tx,
+            table_name,
+            row.copied(),
+            Some(&column.name),
+            "datatype:%",
+            |tx| {
+                // Validate the column against each datatype in the hierarchy, beginning with the
+                // most general. A value is only flagged for the most general datatype that it
+                // fails, and the message names that datatype's parent, i.e., the most specific
+                // datatype that it passes.
+                for (i, datatype) in datatypes_to_check.iter().enumerate().rev() {
+                    datatype.validate(column, row, datatypes_to_check.get(i + 1), tx)?;
+                }
+                Ok(())
+            },
+        )?;
+
+        tracing::debug!(
+            "Validated datatype for column: '{}.{}'{}",
+            column.table,
+            column.name,
+            match row {
+                None => "".to_string(),
+                Some(row) => format!(", row: {row}"),
+            }
+        );
+        Ok(())
+    }
+
+    /// Validate the structure of the given column in its associated database table using the
+    /// given transaction. If `row` is given, only validate the column for that row.
+    fn _validate_structure_for_column_and_optionally_for_row(
+        &self,
+        column: &Column,
+        row: Option<&u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_validate_structure_for_column_and_optionally_for_row(\
+             {self:?}, {column:?}, {row:?}, tx)"
+        );
+
+        let table_name = column.table.as_str();
+
+        // Uniqueness messages refer to the other rows that share a value, so a change to one row
+        // can affect the messages for other rows. In that case we validate the whole column:
+        let row = match &column.structure {
+            Some(Structure::Unique) | Some(Structure::Primary) => None,
+            _ => row,
+        };
+
+        // Replace the pre-existing structure validation messages for this column with those that
+        // result from re-validating the structure condition for this column and (optionally) row:
+        self._revalidate_messages(
+            tx,
+            table_name,
+            row.copied(),
+            Some(&column.name),
+            "key:%",
+            |tx| {
+                // Validate the cell's structure condition:
+                if let Some(structure) = &column.structure {
+                    structure.validate(column, row, tx)?;
+                }
+                Ok(())
+            },
+        )?;
+
+        tracing::debug!(
+            "Validated structure for column: '{}.{}'{}",
+            column.table,
+            column.name,
+            match row {
+                None => "".to_string(),
+                Some(row) => format!(", row: {row}"),
+            }
+        );
+        Ok(())
+    }
+
+    /// Validate the given table against the rules defined for it in the rule table, using the
+    /// given transaction. If `row` is given, only validate that row.
+    fn _validate_rules_for_table_optionally_for_row(
+        &self,
+        table: &Table,
+        row: Option<&u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_validate_rules_for_table_optionally_for_row(\
+             {self:?}, {table:?}, {row:?}, tx)"
+        );
+
+        // Replace the pre-existing rule validation messages for this table (and optionally row)
+        // with those that result from re-validating each of the table's rules:
+        self._revalidate_messages(tx, &table.name, row.copied(), None, "rule:%", |tx| {
+            for rule in Rule::_get_rules(&table.name, tx)? {
+                rule.validate(row, tx)?;
+            }
+            Ok(())
+        })?;
+
+        tracing::debug!(
+            "Validated rules for table: '{}'{}",
+            table.name,
+            match row {
+                None => "".to_string(),
+                Some(row) => format!(", row: {row}"),
+            }
+        );
+        Ok(())
+    }
+
+    /// Validate the given column in its associated database table using the given transaction.
+    /// If `row` is given, only validate the column for that row.
+    fn _validate_column_optionally_for_row(
+        &self,
+        column: &Column,
+        row: Option<&u64>,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<()> {
+        tracing::trace!(
+            "Relatable::_validate_column_optionally_for_row({self:?}, {column:?}, {row:?}, tx)"
+        );
+        self._validate_datatype_for_column_and_optionally_for_row(column, row, tx)?;
+        self._validate_structure_for_column_and_optionally_for_row(column, row, tx)?;
+        tracing::debug!(
+            "Validated column: '{}.{}'{}",
+            column.table,
+            column.name,
+            match row {
+                None => "".to_string(),
+                Some(row) => format!(", row: {row}"),
+            }
+        );
+        Ok(())
+    }
 
-            // Remove duplicate entries from the stacks. These can result when a row is repeatedly
-            // undone and redone. These are harmless, logically speaking, but they may potentially
-            // confuse the user if they are included in the output.
-            (changes_done_stack, changes_undone_stack) =
-                prune_stacks(&changes_done_stack, &changes_undone_stack);
+    /// Run the statements that the [ChangeCapture] of this instance requires after rows have been
+    /// inserted into the given table.
+    async fn capture_inserts(&self, table: &str) -> Result<()> {
+        tracing::trace!("Relatable::capture_inserts({table:?})");
+        for sql in self
+            .change_capture
+            .after_insert_sql(table, &self.connection.kind())
+        {
+            self.connection.query(&sql, None).await?;
+        }
+        Ok(())
+    }
 
-            // Check if we have exceeded the (max) context, and if so, stop looking for more
-            // actions:
-            let mut done_len = changes_done_stack.len();
-            let mut undone_len = changes_undone_stack.len();
-            match action_to_push {
-                ChangeAction::Do | ChangeAction::Redo => done_len += 1,
-                ChangeAction::Undo => undone_len += 1,
-            };
-            if let Some(context) = context {
-                if done_len >= context && undone_len >= context {
-                    break;
+    /// Run the statements that the [ChangeCapture] of this instance requires after rows have been
+    /// inserted into the given table, using the given transaction.
+    fn _capture_inserts(&self, table: &str, tx: &mut DbTransaction<'_>) -> Result<()> {
+        tracing::trace!("Relatable::_capture_inserts({table:?}, tx)");
+        for sql in self.change_capture.after_insert_sql(table, &tx.kind()) {
+            tx.query(&sql, None)?;
+        }
+        Ok(())
+    }
+
+    /// Delete all entries from the cache corresponding to the given table, or clear it completely
+    /// if no table is given.
+    pub(crate) fn clear_cache(tx: &mut DbTransaction<'_>, table: Option<&str>) -> Result<()> {
+        let mut sql = r#"DELETE FROM "cache""#.to_string();
+        if let Some(table) = table {
+            let mut table = table.to_string();
+            tracing::debug!("Deleting entries for table '{table}' from cache");
+            match tx.kind() {
+                DbKind::Postgres => {
+                    // Note that the '?' is *not* being used as a parameter placeholder here
+                    // but a JSONB operator.
+                    sql.push_str(&format!(
+                        r#" WHERE "tables" ? {}"#,
+                        SqlParam::new(&tx.kind()).next()
+                    ));
                 }
-            } else if done_len >= HISTORY_MAX || undone_len >= HISTORY_MAX {
-                break;
-            }
+                DbKind::Sqlite => {
+                    sql.push_str(&format!(
+                        r#" WHERE "tables" LIKE {}"#,
+                        SqlParam::new(&tx.kind()).next()
+                    ));
+                    table = format!(r#"%"{table}"%"#);
+                }
+            };
+            let params = json!([table]);
+            tx.query(&sql, Some(&params))?;
+        } else {
+            tracing::debug!("Truncating cache");
+            tx.query(&sql, None)?;
         }
 
-        // Once we have finished iterating, there will be one action left over to push, which we
-        // do now:
-        match action_to_push {
-            ChangeAction::Do | ChangeAction::Redo => {
-                tracing::debug!("Pushing the last change to changes_done: {change_to_push:?}");
-                changes_done_stack.push(change_to_push.clone());
-            }
-            ChangeAction::Undo => {
-                tracing::debug!("Pushing the last change to changes_undone: {change_to_push:?}");
-                changes_undone_stack.push(change_to_push.clone());
+        Ok(())
+    }
+
+    /// Delete the entries for the given table from the cache, in accordance with the caching
+    /// strategy, after messages about it have been added or deleted using the given transaction.
+    /// Messages can be added or deleted without the table itself being changed, e.g., when it is
+    /// validated, in which case the caching triggers on the table, if any, are not fired.
+    pub(crate) fn _after_message_change(
+        &self,
+        tx: &mut DbTransaction<'_>,
+        table: &str,
+    ) -> Result<()> {
+        match self.caching_strategy {
+            CachingStrategy::None => (),
+            CachingStrategy::Memory(_) => self.clear_mem_cache(table),
+            CachingStrategy::TruncateAll => Relatable::clear_cache(tx, None)?,
+            CachingStrategy::Truncate | CachingStrategy::Trigger => {
+                Relatable::clear_cache(tx, Some(table))?
             }
         };
+        Ok(())
+    }
 
-        // Don't return the contents of changes_undone if the last change was a do. Dos can never be
-        // redone. If the last change was an undo or a redo, the logic will take care of itself and
-        // it should never be possible to undo or redo inappropriately.
-        let mut changes_undone_stack = match final_action {
-            ChangeAction::Do => vec![],
-            _ => changes_undone_stack,
-        };
+    /// Delete all entries from the in-memory cache corresponding to the given table
+    pub(crate) fn clear_mem_cache(&self, table: &str) {
+        let table = format!("\"{table}\"");
+        let mut cache = CACHE.lock().expect("Could not lock cache");
+        let keys = cache
+            .keys()
+            .map(|k| k)
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+        for key in keys.iter() {
+            if key.tables.contains(&table) {
+                tracing::debug!("Removing {key:?} from cache");
+                cache.remove(key);
+            }
+        }
+    }
+}
 
-        // Prune the stacks one last time, in case by adding the final action we created a situation
-        // in which one of the stacks contains duplicates:
-        (changes_done_stack, changes_undone_stack) =
-            prune_stacks(&changes_done_stack, &changes_undone_stack);
+// Validation
 
-        // Similarly, crop for context one last time if a context has been defined:
-        let history = match context {
-            None => History {
-                changes_done_stack,
-                changes_undone_stack,
-            },
-            Some(context) => {
-                let mut done_len = changes_done_stack.len();
-                if done_len > context {
-                    done_len = context;
-                }
-                let changes_done_stack = changes_done_stack[..done_len].to_vec();
+/// The level at which Relatable will perform validation when adding to or modifying data in the
+/// database
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ValidationLevel {
+    /// Perform no validateion
+    None,
+    /// Perform only SQL type validation
+    SqlType,
+    /// Perform full validation
+    Full,
+}
 
-                let mut undone_len = changes_undone_stack.len();
-                if undone_len > context {
-                    undone_len = context;
-                }
-                let changes_undone_stack = changes_undone_stack[..undone_len].to_vec();
-                History {
-                    changes_done_stack,
-                    changes_undone_stack,
-                }
+impl FromStr for ValidationLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(level: &str) -> Result<Self> {
+        tracing::trace!("ValidationLevel::from_str({level:?})");
+        match level.to_lowercase().as_str() {
+            "none" => Ok(ValidationLevel::None),
+            "sql_type" => Ok(ValidationLevel::SqlType),
+            "full" => Ok(ValidationLevel::Full),
+            _ => {
+                return Err(
+                    RelatableError::InputError(format!("Unrecognized level: {level}")).into(),
+                );
             }
+        }
+    }
+}
+
+/// The character encoding of a file that is loaded
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, in which each byte is the character with that code point
+    Latin1,
+}
+
+impl Encoding {
+    /// Detect the encoding of the text that begins with the given bytes: from its byte order
+    /// mark, if it has one, or else from the positions of its zero bytes, as in UTF-16 encoded
+    /// ASCII, or else UTF-8 if the bytes are valid UTF-8, and Latin-1 otherwise.
+    pub fn detect(bytes: &[u8]) -> Self {
+        tracing::trace!("Encoding::detect()");
+        if bytes.starts_with(&[0xFF, 0xFE]) {
+            return Encoding::Utf16Le;
+        }
+        if bytes.starts_with(&[0xFE, 0xFF]) {
+            return Encoding::Utf16Be;
+        }
+        let zeros = |parity: usize| {
+            bytes
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .filter(|byte| **byte == 0)
+                .count()
         };
-        tracing::debug!("Returning history: {history:#?}");
-        Ok(history)
+        if bytes.len() >= 2 && zeros(1) > bytes.len() / 4 {
+            return Encoding::Utf16Le;
+        }
+        if bytes.len() >= 2 && zeros(0) > bytes.len() / 4 {
+            return Encoding::Utf16Be;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Encoding::Utf8,
+            Err(_) => Encoding::Latin1,
+        }
     }
 
-    /// Reverse the given changeset in the database
-    async fn _revert(&self, change_id: u64, changeset: &ChangeSet) -> Result<Option<ChangeSet>> {
-        tracing::trace!("Relatable::_revert({change_id}, {changeset:?})");
-        match changeset.changes.first() {
-            None => Ok(None),
-            Some(change) => {
-                if let Change::Update { .. } = change {
-                    let conn = self.connection.reconnect()?;
-                    let actual_changes = self._set_values(conn, &changeset).await?;
-                    Ok(Some(actual_changes))
-                } else {
-                    let mut actual_changes = vec![];
-                    for change in changeset.changes.iter() {
-                        let conn = self.connection.reconnect()?;
-                        match change {
-                            Change::Update { .. } => (), // Change::Update already handled above.
-                            Change::Add { row, after: _ } => {
-                                let num_deleted = self
-                                    ._delete_row(
-                                        conn,
-                                        &changeset.action,
-                                        &changeset.table,
-                                        &changeset.user,
-                                        *row,
-                                    )
-                                    .await?;
-                                if num_deleted > 0 {
-                                    actual_changes.push(change.clone());
-                                }
-                            }
-                            Change::Move {
-                                row,
-                                from_after,
-                                to_after: _,
-                            } => {
-                                let new_order = self
-                                    ._move_and_record_row(
-                                        conn,
-                                        &changeset.action,
-                                        &changeset.table,
-                                        &changeset.user,
-                                        *row,
-                                        *from_after,
-                                    )
-                                    .await?;
-                                if new_order > 0 {
-                                    actual_changes.push(change.clone());
-                                }
-                            }
-                            Change::Delete { row, after } => {
-                                // Get the row, as it was before it was deleted, from the history
-                                // table:
-                                let sql = format!(
-                                    r#"SELECT "before" FROM "history"
-                                       WHERE "change_id" = {sql_param}"#,
-                                    sql_param = SqlParam::new(&self.connection.kind()).next()
-                                );
-                                let params = json!([change_id]);
-                                let before = self
-                                    .connection
-                                    .query_one(&sql, Some(&params))
-                                    .await?
-                                    .ok_or(RelatableError::DataError(format!(
-                                        "No history row found with change_id {change_id}"
-                                    )))?
-                                    .get_string("before")?;
-                                let before = match serde_json::from_str::<JsonValue>(&before) {
-                                    Err(err) => return Err(err.into()),
-                                    Ok(JsonValue::Object(o)) => o,
-                                    Ok(_) => {
-                                        return Err(RelatableError::InputError(
-                                            "The content parameter is not an object".to_string(),
-                                        )
-                                        .into());
-                                    }
-                                };
-                                let before = JsonRow { content: before };
-                                tracing::debug!(
-                                    "Re-adding row '{before}' to table '{}'",
-                                    changeset.table
-                                );
-                                self._add_row(
-                                    conn,
-                                    &changeset.action,
-                                    &changeset.table,
-                                    &changeset.user,
-                                    Some(*row),
-                                    Some(*after),
-                                    &before,
-                                )
-                                .await?;
-                                actual_changes.push(change.clone());
-                            }
-                        };
-                    }
-                    Ok(Some(ChangeSet {
-                        action: changeset.action,
-                        table: changeset.table.clone(),
-                        user: changeset.user.clone(),
-                        description: changeset.description.clone(),
-                        changes: actual_changes,
-                    }))
-                }
+    /// Decode the given bytes in this encoding, skipping any byte order mark and replacing any
+    /// invalid characters.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        let utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+            let units = bytes
+                .chunks_exact(2)
+                .map(|pair| from_bytes([pair[0], pair[1]]))
+                .collect::<Vec<_>>();
+            let text = char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect::<String>();
+            text.strip_prefix('\u{FEFF}')
+                .map(|text| text.to_string())
+                .unwrap_or(text)
+        };
+        match self {
+            Encoding::Utf8 => {
+                let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+                String::from_utf8_lossy(bytes).to_string()
+            }
+            Encoding::Utf16Le => utf16(bytes, u16::from_le_bytes),
+            Encoding::Utf16Be => utf16(bytes, u16::from_be_bytes),
+            Encoding::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+        }
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = anyhow::Error;
+
+    fn from_str(encoding: &str) -> Result<Self> {
+        tracing::trace!("Encoding::from_str({encoding:?})");
+        match encoding.to_lowercase().replace(['-', '_'], "").as_str() {
+            "utf8" => Ok(Encoding::Utf8),
+            "utf16" | "utf16le" => Ok(Encoding::Utf16Le),
+            "utf16be" => Ok(Encoding::Utf16Be),
+            "latin1" | "iso88591" => Ok(Encoding::Latin1),
+            _ => {
+                Err(RelatableError::InputError(format!("Unrecognized encoding: {encoding}")).into())
             }
         }
     }
+}
+
+impl Display for Encoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Encoding::Utf8 => write!(f, "utf-8"),
+            Encoding::Utf16Le => write!(f, "utf-16le"),
+            Encoding::Utf16Be => write!(f, "utf-16be"),
+            Encoding::Latin1 => write!(f, "latin-1"),
+        }
+    }
+}
 
-    /// Undo the last change made by the given user
-    pub async fn undo(&self, user: &str) -> Result<Option<ChangeSet>> {
-        tracing::trace!("Relatable::undo({user:?})");
-        let (change_id, mut changeset) =
-            match self.get_last_undoable_changeset_for_user(user).await? {
-                None => {
-                    tracing::warn!("Nothing to undo for '{user}'");
-                    return Ok(None);
-                }
-                Some(changeset) => changeset,
+/// The number of bytes at the beginning of a delimited file from which its encoding and delimiter
+/// are detected
+const DIALECT_SAMPLE_SIZE: usize = 64 * 1024;
+
+/// Open the delimited file at the given path, returning a reader of its text, decoded as UTF-8,
+/// and the delimiter of its fields. If no encoding is given, it is detected from the beginning of
+/// the file (see [Encoding::detect()]). If no delimiter is given, it is the one of tab, comma, or
+/// semicolon that occurs the same number of times, outside of double quotes, in each of the first
+/// lines of the file, preferring the given default delimiter, and then the most frequent one. If
+/// there is none, it is the default delimiter.
+pub fn open_delimited(
+    path: &str,
+    delimiter: Option<u8>,
+    encoding: Option<Encoding>,
+    default_delimiter: u8,
+) -> Result<(Box<dyn Read + Send>, u8)> {
+    tracing::trace!("open_delimited({path:?}, {delimiter:?}, {encoding:?}, {default_delimiter:?})");
+    let mut file = File::open(path)?;
+    let mut sample = vec![];
+    (&mut file)
+        .take(DIALECT_SAMPLE_SIZE as u64)
+        .read_to_end(&mut sample)?;
+    let truncated = sample.len() == DIALECT_SAMPLE_SIZE;
+    // A truncated sample may end in the middle of a character:
+    let encoding = encoding.unwrap_or_else(|| match truncated {
+        true => Encoding::detect(&sample[..DIALECT_SAMPLE_SIZE - 3]),
+        false => Encoding::detect(&sample),
+    });
+    let (reader, text): (Box<dyn Read + Send>, String) = match encoding {
+        // UTF-8 files, which may be large, are streamed rather than read into memory:
+        Encoding::Utf8 => {
+            let text = encoding.decode(&sample);
+            let start = match sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+                true => 3,
+                false => 0,
             };
-        changeset.action = ChangeAction::Undo;
-        let changeset = self._revert(change_id, &changeset).await?;
-        if let Some(_) = changeset {
-            self.commit_to_git().await?;
+            sample.drain(..start);
+            (Box::new(std::io::Cursor::new(sample).chain(file)), text)
         }
-        Ok(changeset)
+        _ => {
+            file.read_to_end(&mut sample)?;
+            let text = encoding.decode(&sample);
+            let sample_text = text.chars().take(DIALECT_SAMPLE_SIZE).collect::<String>();
+            (
+                Box::new(std::io::Cursor::new(text.into_bytes())),
+                sample_text,
+            )
+        }
+    };
+    if encoding != Encoding::Utf8 {
+        tracing::info!("Reading '{path}' as {encoding}");
     }
-
-    /// Redo the last change undone by the given user
-    pub async fn redo(&self, user: &str) -> Result<Option<ChangeSet>> {
-        tracing::trace!("Relatable::redo({user:?})");
-        let (change_id, mut changeset) =
-            match self.get_last_redoable_changeset_for_user(user).await? {
-                None => {
-                    tracing::warn!("Nothing to redo for '{user}'");
-                    return Ok(None);
-                }
-                Some(changeset) => changeset,
+    let delimiter = match delimiter {
+        Some(delimiter) => delimiter,
+        None => {
+            // The last line of a truncated sample may be incomplete:
+            let mut lines = text
+                .lines()
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>();
+            if truncated && lines.len() > 1 {
+                lines.pop();
+            }
+            let lines = lines.into_iter().take(20).collect::<Vec<_>>();
+            let count = |line: &str, delimiter: u8| {
+                let mut quoted = false;
+                line.chars()
+                    .filter(|c| {
+                        if *c == '"' {
+                            quoted = !quoted;
+                        }
+                        !quoted && *c == delimiter as char
+                    })
+                    .count()
+            };
+            let mut candidates = vec![default_delimiter];
+            candidates.extend(
+                [b'\t', b',', b';']
+                    .iter()
+                    .filter(|c| **c != default_delimiter),
+            );
+            let consistent = candidates
+                .into_iter()
+                .filter_map(|candidate| {
+                    let counts = lines
+                        .iter()
+                        .map(|line| count(line, candidate))
+                        .collect::<Vec<_>>();
+                    match counts.first() {
+                        Some(first) if *first > 0 && counts.iter().all(|c| c == first) => {
+                            Some((candidate, *first))
+                        }
+                        _ => None,
+                    }
+                })
+                .collect::<Vec<_>>();
+            let detected = match consistent.iter().find(|(c, _)| *c == default_delimiter) {
+                Some((candidate, _)) => *candidate,
+                None => consistent
+                    .iter()
+                    .max_by_key(|(_, count)| *count)
+                    .map(|(candidate, _)| *candidate)
+                    .unwrap_or(default_delimiter),
             };
-        tracing::debug!("Last redoable action (ID {change_id}) for user {user} was {changeset:?}");
-        changeset.action = ChangeAction::Redo;
-        let changeset = self._revert(change_id, &changeset).await?;
-        if let Some(_) = changeset {
-            self.commit_to_git().await?;
+            if detected != default_delimiter {
+                tracing::info!("Reading '{path}' as delimited by {:?}", detected as char);
+            }
+            detected
         }
-        Ok(changeset)
-    }
-
-    /// Update the database using the given [ChangeSet]
-    async fn _set_values(
-        &self,
-        mut conn: Option<DbActiveConnection>,
-        changeset: &ChangeSet,
-    ) -> Result<ChangeSet> {
-        tracing::trace!("Relatable::set_values(conn, {changeset:?})");
-        // Begin a transaction:
-        let mut tx = self.connection.begin(&mut conn).await?;
-
-        // Update the user cursor
-        self.prepare_user_cursor(changeset, &mut tx)?;
-
-        // Actually make the changes:
-        let table = Table::_get_table(&changeset.table, &mut tx)?;
-        let mut actual_changes = vec![];
-        for change in &changeset.changes {
-            match change {
-                Change::Update {
-                    row,
-                    column,
-                    before,
-                    after,
-                } => {
-                    // Delete any existing messages associated with this row and column:
-                    tracing::debug!(
-                        "Deleting existing messages for column '{}.{}'",
-                        table.name,
-                        column
-                    );
-                    self._delete_message(
-                        &mut tx,
-                        &table.name,
-                        Some(*row),
-                        Some(column),
-                        None,
-                        None,
-                    )?;
+    };
+    Ok((reader, delimiter))
+}
 
-                    // Depending on whether this is an undo/redo or an original action, the
-                    // new value will be taken from either `before` or `after`.
-                    let before = JsonRow::nullify_value(&table, column, before);
-                    let after = JsonRow::nullify_value(&table, column, after);
-                    let mut cell = match &changeset.action {
-                        ChangeAction::Undo | ChangeAction::Redo => Cell {
-                            value: before.clone(),
-                            text: sql::json_to_string(&before),
-                            ..Default::default()
-                        },
-                        ChangeAction::Do => Cell {
-                            value: after.clone(),
-                            text: sql::json_to_string(&after),
-                            ..Default::default()
-                        },
-                    };
+// Changes and History
 
-                    // Validate the cell's SQL type and add any messages to the message table:
-                    let column_config = table.get_config_for_column(column);
-                    let mut sql_value = cell.value.clone();
-                    if self.validation_level != ValidationLevel::None {
-                        cell.validate_sql_type(&column_config)
-                            .expect("Error validating cell");
-                        for message in cell.messages.iter() {
-                            let (msg_id, msg) = Relatable::_add_message(
-                                "rltbl",
-                                &table.name,
-                                &row,
-                                column,
-                                &cell.value,
-                                &message.level,
-                                &message.rule,
-                                &message.message,
-                                &mut tx,
-                            )?;
-                            tracing::debug!("Added message (ID {msg_id}): {msg:?}");
-                        }
+/// The metrics of a change, recorded along with it in the change table, so that operations that
+/// are unusually large or slow can be spotted.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChangeMetrics {
+    /// The number of rows that were updated, added, moved, or deleted
+    pub rows_affected: u64,
+    /// The number of validation messages that were added, to any table
+    pub messages_added: u64,
+    /// The number of validation messages of the changed table that were removed
+    pub messages_removed: u64,
+    /// How long the change took to make, including its validation, in milliseconds
+    pub elapsed_ms: u64,
+}
 
-                        // If the cell is invalid, insert a NULL instead of its actual value
-                        if cell.has_sql_type_error() {
-                            sql_value = JsonValue::Null;
-                        }
-                    }
+impl ChangeMetrics {
+    /// Read the metrics of a change from the given record of the change table, if they were
+    /// recorded, which they are not for changes made before schema version 15.
+    pub fn from_record(record: &JsonRow) -> Option<Self> {
+        let get = |column: &str| {
+            record
+                .content
+                .get(column)
+                .and_then(|value| sql::json_to_unsigned(value).ok())
+        };
+        Some(Self {
+            rows_affected: get("rows_affected")?,
+            messages_added: get("messages_added")?,
+            messages_removed: get("messages_removed")?,
+            elapsed_ms: get("elapsed_ms")?,
+        })
+    }
+}
 
-                    // Generate the UPDATE statement:
-                    let (sql, params) = {
-                        let mut sql_param = SqlParam::new(&self.connection.kind());
-                        let sql = format!(
-                            r#"UPDATE "{table}"
-                               SET "{column}" = {sql_value}
-                               WHERE _id = {sql_param}
-                               RETURNING 1 AS "updated""#,
-                            table = changeset.table,
-                            sql_value = match sql_value {
-                                JsonValue::Null => "NULL".to_string(),
-                                _ => sql_param.next(),
-                            },
-                            sql_param = sql_param.next()
-                        );
-                        let params = match sql_value {
-                            JsonValue::Null => json!([row]),
-                            _ => json!([sql_value, row]),
-                        };
-                        (sql, params)
-                    };
+impl Display for ChangeMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{rows} row{s}, +{added}/-{removed} messages, {elapsed}ms",
+            rows = self.rows_affected,
+            s = if self.rows_affected == 1 { "" } else { "s" },
+            added = self.messages_added,
+            removed = self.messages_removed,
+            elapsed = self.elapsed_ms,
+        )
+    }
+}
 
-                    tracing::debug!(
-                        "Updating value of row {row} in {table}.{column} to {sql_value:?}",
-                        table = table.name
-                    );
+/// Measures a change as it is made, from when it is started until it is finished, when its
+/// [ChangeMetrics] are recorded on its change record.
+struct ChangeMeter {
+    started: Instant,
+    table: String,
+    /// The largest message_id when the change was started
+    last_message_id: u64,
+    /// The number of messages of the table when the change was started
+    messages: u64,
+}
 
-                    // Execute the UPDATE statement.
-                    if tx.query(&sql, Some(&params))?.len() < 1 {
-                        tracing::warn!("No row with _id {row} found to update");
-                    } else {
-                        actual_changes.push(Change::Update {
-                            row: *row,
-                            column: column.clone(),
-                            before: match &changeset.action {
-                                ChangeAction::Undo | ChangeAction::Redo => after.clone(),
-                                ChangeAction::Do => before.clone(),
-                            },
-                            after: match &changeset.action {
-                                ChangeAction::Undo | ChangeAction::Redo => before.clone(),
-                                ChangeAction::Do => after.clone(),
-                            },
-                        });
-                    }
+impl ChangeMeter {
+    /// Start measuring a change to the given table, using the given transaction.
+    fn _start(table: &str, tx: &mut DbTransaction<'_>) -> Result<Self> {
+        tracing::trace!("ChangeMeter::_start({table:?}, tx)");
+        let last_message_id = tx
+            .query_value(
+                r#"SELECT COALESCE(MAX("message_id"), 0) FROM "message""#,
+                None,
+            )?
+            .map(|value| sql::json_to_unsigned(&value))
+            .transpose()?
+            .unwrap_or_default();
+        let mut meter = Self {
+            started: Instant::now(),
+            table: table.to_string(),
+            last_message_id,
+            messages: 0,
+        };
+        meter.messages = meter._count_messages(tx)?;
+        Ok(meter)
+    }
 
-                    // Optionally do full validation on the newly updated cell and add further
-                    // messages to the message table:
-                    if self.validation_level == ValidationLevel::Full {
-                        self._validate_column_optionally_for_row(
-                            &column_config,
-                            Some(row),
-                            &mut tx,
-                        )?;
-                        for column in &column_config._get_dependent_columns(&mut tx)? {
-                            tracing::debug!("Validating dependent column '{}'", column.name);
-                            self._validate_structure_for_column_and_optionally_for_row(
-                                column, None, &mut tx,
-                            )?;
-                        }
-                    }
-                }
-                _ => {
-                    return Err(RelatableError::InputError(format!(
-                        "Invalid change in changeset argument to set_values(): {change:?}"
-                    ))
-                    .into());
-                }
-            };
+    /// Count the messages of the table that existed when the change was started, and still do.
+    fn _count_messages(&self, tx: &mut DbTransaction<'_>) -> Result<u64> {
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"SELECT COUNT(1) FROM "message"
+                WHERE "table" = {sql_param_1} AND "message_id" <= {sql_param_2}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+        );
+        let params = json!([self.table, self.last_message_id]);
+        match tx.query_value(&sql, Some(&params))? {
+            Some(count) => sql::json_to_unsigned(&count),
+            None => Ok(0),
         }
+    }
 
-        let num_changes = actual_changes.len();
-        let actual_changeset = ChangeSet {
-            action: changeset.action,
-            table: changeset.table.clone(),
-            user: changeset.user.clone(),
-            description: changeset.description.clone(),
-            changes: actual_changes,
+    /// Finish measuring the change, which affected the given number of rows, and record its
+    /// metrics on the change record with the given change_id, using the given transaction.
+    fn _finish(
+        self,
+        change_id: u64,
+        rows_affected: usize,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<ChangeMetrics> {
+        tracing::trace!("ChangeMeter::_finish({change_id}, {rows_affected}, tx)");
+        let sql = format!(
+            r#"SELECT COUNT(1) FROM "message" WHERE "message_id" > {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let messages_added = match tx.query_value(&sql, Some(&json!([self.last_message_id])))? {
+            Some(count) => sql::json_to_unsigned(&count)?,
+            None => 0,
         };
-        if num_changes > 0 {
-            // Record the changes to the change and history tables:
-            self.record_changeset(&actual_changeset, &mut tx)?;
+        let metrics = ChangeMetrics {
+            rows_affected: rows_affected as u64,
+            messages_added,
+            messages_removed: self.messages.saturating_sub(self._count_messages(tx)?),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+        };
+        let mut sql_param = SqlParam::new(&tx.kind());
+        let sql = format!(
+            r#"UPDATE "change"
+                  SET "rows_affected" = {sql_param_1}, "messages_added" = {sql_param_2},
+                      "messages_removed" = {sql_param_3}, "elapsed_ms" = {sql_param_4}
+                WHERE "change_id" = {sql_param_5}"#,
+            sql_param_1 = sql_param.next(),
+            sql_param_2 = sql_param.next(),
+            sql_param_3 = sql_param.next(),
+            sql_param_4 = sql_param.next(),
+            sql_param_5 = sql_param.next(),
+        );
+        let params = json!([
+            metrics.rows_affected,
+            metrics.messages_added,
+            metrics.messages_removed,
+            metrics.elapsed_ms,
+            change_id
+        ]);
+        tx.query(&sql, Some(&params))?;
+        Ok(metrics)
+    }
+}
+
+/// A set of changes made by a user to a table.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub action: ChangeAction,
+    pub table: String,
+    pub user: String,
+    pub description: String,
+    /// An optional, human-readable message supplied by the user to describe the change.
+    #[serde(default)]
+    pub message: Option<String>,
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    /// Given a change, returns the a [Cursor] representing where the user's cursor
+    /// should be placed in the frontend.
+    fn to_cursor(&self) -> Result<Cursor> {
+        tracing::trace!("ChangeSet::to_cursor()");
+        let table = self.table.clone();
+        match self.changes.first() {
+            Some(change) => match change {
+                Change::Update {
+                    row,
+                    column,
+                    before: _,
+                    after: _,
+                } => Ok(Cursor {
+                    table,
+                    row: *row,
+                    column: column.to_string(),
+                }),
+                Change::Add { row, after: _ } => Ok(Cursor {
+                    table,
+                    row: *row,
+                    column: "".to_string(),
+                }),
+                Change::Move {
+                    row,
+                    from_after: _,
+                    to_after: _,
+                } => Ok(Cursor {
+                    table,
+                    row: *row,
+                    column: "".to_string(),
+                }),
+                Change::Delete { row, after: _ } => Ok(Cursor {
+                    table,
+                    row: *row,
+                    column: "".to_string(),
+                }),
+            },
+            None => Err(RelatableError::ChangeError("No changes in set".into()).into()),
         }
+    }
+}
 
-        // Commit the transaction:
-        tx.commit()?;
+/// The kind of action that is performed by a change
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChangeAction {
+    Do,
+    Undo,
+    Redo,
+}
 
-        Ok(actual_changeset)
-    }
+impl FromStr for ChangeAction {
+    type Err = anyhow::Error;
 
-    /// Update the database using the given [ChangeSet]
-    pub async fn set_values(&self, changeset: &ChangeSet) -> Result<ChangeSet> {
-        tracing::trace!("Relatable::set_values({changeset:?})");
-        let conn = self.connection.reconnect()?;
-        let changeset = self._set_values(conn, changeset).await?;
-        if changeset.changes.len() > 0 {
-            self.commit_to_git().await?;
+    fn from_str(action: &str) -> Result<Self> {
+        tracing::trace!("ChangeAction::from_str({action:?})");
+        match action.to_lowercase().as_str() {
+            "do" => Ok(ChangeAction::Do),
+            "undo" => Ok(ChangeAction::Undo),
+            "redo" => Ok(ChangeAction::Redo),
+            _ => {
+                return Err(
+                    RelatableError::InputError(format!("Unrecognized action: {action}")).into(),
+                );
+            }
         }
-        Ok(changeset)
     }
+}
 
-    /// Add a message to the message table using the given [DbTransaction]
-    pub fn _add_message(
-        user: &str,
-        table_name: &str,
-        row: &u64,
-        column: &str,
-        value: &JsonValue,
-        level: &str,
-        rule: &str,
-        message: &str,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<(u64, Message)> {
-        tracing::trace!(
-            "Relatable::add_message({user:?}, {table_name:?}, {row}, \
-             {column:?}, {value:?}, {level:?}, {rule:?}, {message:?}, tx)"
-        );
-
-        let sql = format!(
-            r#"INSERT INTO "message"
-               ("added_by", "table", "row", "column", "value",
-                "level", "rule", "message")
-               VALUES
-               ({sql_params})
-               RETURNING "message_id""#,
-            sql_params = SqlParam::new(&tx.kind()).get_as_list(8)
-        );
-        let params = json!([user, table_name, row, column, value, level, rule, message]);
-        let message_id = tx
-            .query_one(&sql, Some(&params))?
-            .ok_or(RelatableError::DataError(
-                "Error inserting message".to_string(),
-            ))?
-            .get_unsigned("message_id")?;
-
-        Ok((
-            message_id,
-            Message {
-                value: value.clone(),
-                level: level.to_string(),
-                rule: rule.to_string(),
-                message: message.to_string(),
-            },
-        ))
+impl Display for ChangeAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChangeAction::Do => write!(f, "do"),
+            ChangeAction::Undo => write!(f, "undo"),
+            ChangeAction::Redo => write!(f, "redo"),
+        }
     }
+}
 
-    /// Add a message to the message table.
-    pub async fn add_message(
-        &self,
-        user: &str,
-        table_name: &str,
+/// A change to a table in the database
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum Change {
+    Update {
+        /// The id of the row that was updated
         row: u64,
-        column: &str,
-        value: &JsonValue,
-        level: &str,
-        rule: &str,
-        message: &str,
-    ) -> Result<(u64, Message)> {
-        tracing::trace!(
-            "Relatable::add_message({self:?},  {user:?}, {table_name:?}, {row}, \
-             {column:?}, {value:?}, {level:?}, {rule:?}, {message:?})"
-        );
+        /// The column whose value was updated
+        column: String,
+        /// The value of the column before the change
+        before: JsonValue,
+        /// The value of the column after the change
+        after: JsonValue,
+    },
+    Add {
+        /// The id of the row that was added
+        row: u64,
+        /// The _id of the row whose _order this comes immediately after in the table
+        after: u64,
+    },
+    Move {
+        /// The id of the row that was moved
+        row: u64,
+        /// The row that this row came after before the change
+        from_after: u64,
+        /// The row that this row came after after the change
+        to_after: u64,
+    },
+    Delete {
+        /// The id of the row that was deleted
+        row: u64,
+        /// The _id of the row whose _order this row came immediately after in the table before
+        /// being deleted.
+        after: u64,
+    },
+}
 
-        // Begin a transaction:
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
+impl Change {
+    /// The change that reverses this one, e.g., the deletion of a row that was added, or an update
+    /// of a cell from the value after this change to the value before it.
+    pub fn reverse(&self) -> Self {
+        match self {
+            Change::Update {
+                row,
+                column,
+                before,
+                after,
+            } => Change::Update {
+                row: *row,
+                column: column.clone(),
+                before: after.clone(),
+                after: before.clone(),
+            },
+            Change::Add { row, after } => Change::Delete {
+                row: *row,
+                after: *after,
+            },
+            Change::Move {
+                row,
+                from_after,
+                to_after,
+            } => Change::Move {
+                row: *row,
+                from_after: *to_after,
+                to_after: *from_after,
+            },
+            Change::Delete { row, after } => Change::Add {
+                row: *row,
+                after: *after,
+            },
+        }
+    }
 
-        let (message_id, message) = Relatable::_add_message(
-            user, table_name, &row, column, value, level, rule, message, &mut tx,
-        )?;
+    /// Converts a JSON string representing an array of changes to an array of [Change] structs.
+    pub fn many_from_str(content: &str) -> Result<Vec<Self>> {
+        tracing::trace!("Change::many_from_str({content:?})");
+        let json_content = match serde_json::from_str::<JsonValue>(content) {
+            Err(err) => return Err(err.into()),
+            Ok(JsonValue::Array(v)) => v,
+            Ok(_) => {
+                return Err(RelatableError::InputError(
+                    "The content parameter is not an array".to_string(),
+                )
+                .into());
+            }
+        };
 
-        // Commit the transaction:
-        tx.commit()?;
+        let mut changes = vec![];
+        for change_json in json_content.iter() {
+            let change_json = match change_json.as_object() {
+                Some(change_object) => JsonRow {
+                    content: change_object.clone(),
+                },
+                None => {
+                    return Err(RelatableError::InputError(format!(
+                        "Not an object: {change_json}"
+                    ))
+                    .into());
+                }
+            };
 
-        Ok((message_id, message))
+            let change_type = change_json.get_string("type")?;
+            let row = change_json.get_unsigned("row")?;
+            match change_type.as_str() {
+                "Update" => changes.push(Change::Update {
+                    row: row,
+                    column: change_json.get_string("column")?,
+                    before: change_json.get_value("before")?,
+                    after: change_json.get_value("after")?,
+                }),
+                "Add" => changes.push(Change::Add {
+                    row: row,
+                    after: change_json.get_unsigned("after")?,
+                }),
+                "Delete" => changes.push(Change::Delete {
+                    row: row,
+                    after: change_json.get_unsigned("after")?,
+                }),
+                "Move" => changes.push(Change::Move {
+                    row: row,
+                    from_after: change_json.get_unsigned("from_after")?,
+                    to_after: change_json.get_unsigned("to_after")?,
+                }),
+                _ => {
+                    return Err(RelatableError::InputError(format!(
+                        "Unrecognized change type for change: {change_json}"
+                    ))
+                    .into());
+                }
+            };
+        }
+        Ok(changes)
     }
 
-    /// Add a row to the given table
-    async fn _add_row(
-        &self,
-        mut conn: Option<DbActiveConnection>,
-        action: &ChangeAction,
-        table_name: &str,
-        user: &str,
-        new_row_id: Option<u64>,
-        after_id: Option<u64>,
-        row: &JsonRow,
-    ) -> Result<Row> {
-        tracing::trace!(
-            "Relatable::_add_row(conn, {action:?}, {user:?}, {new_row_id:?}, \
-                         {after_id:?}, {row:?})"
-        );
-
-        // Begin a transaction:
-        let mut tx = self.connection.begin(&mut conn).await?;
-
-        // Get the current database information for the table:
-        let table = Table::_get_table(table_name, &mut tx)?;
-        if !table.editable {
-            return Err(
-                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
-            );
+    /// Convers a [JsonRow] to a [Change]
+    pub fn from_json_row(json_row: &JsonRow) -> Result<Self> {
+        tracing::trace!("Change::from_json_row({json_row:?})");
+        match json_row.get_string("type")?.as_str() {
+            "Update" => Ok(Change::Update {
+                row: json_row.get_unsigned("row")?,
+                column: json_row.get_string("column")?,
+                before: json_row.get_value("before")?,
+                after: json_row.get_value("after")?,
+            }),
+            "Add" => Ok(Change::Add {
+                row: json_row.get_unsigned("row")?,
+                after: json_row.get_unsigned("after")?,
+            }),
+            "Move" => Ok(Change::Move {
+                row: json_row.get_unsigned("row")?,
+                from_after: json_row.get_unsigned("from_after")?,
+                to_after: json_row.get_unsigned("to_after")?,
+            }),
+            "Delete" => Ok(Change::Delete {
+                row: json_row.get_unsigned("row")?,
+                after: json_row.get_unsigned("after")?,
+            }),
+            _ => {
+                return Err(RelatableError::InputError(format!(
+                    "Unrecognized action type for change {json_row}"
+                ))
+                .into());
+            }
         }
+    }
+}
 
-        // Nullify the JSON row by setting any column values whose content matches the column's
-        // nulltype to Null:
-        let row = JsonRow::nullify(row, &table);
-
-        // Prepare a new row to be inserted using the JSON row as a base:
-        let mut new_row = Row::prepare_new(&table, Some(&row), &mut tx)?;
+/// An edit to be applied as part of a batch. See [Relatable::batch()].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "operation", rename_all = "snake_case")]
+pub enum BatchOperation {
+    SetValue {
+        table: String,
+        row: u64,
+        column: String,
+        value: JsonValue,
+    },
+    AddRow {
+        table: String,
+        /// The _id of the row after which the new row is to be placed, if not at the end
+        #[serde(default)]
+        after: Option<u64>,
+        /// The values of the new row, by column
+        values: JsonMap<String, JsonValue>,
+    },
+    DeleteRow {
+        table: String,
+        row: u64,
+    },
+    MoveRow {
+        table: String,
+        row: u64,
+        /// The _id of the row after which this one is to be moved
+        after: u64,
+    },
+}
 
-        // A new_row_id will have been passed if the row is being added as part of an undo/redo.
-        // In that case an after_id must have been passed as well but we leave the row order as
-        // is for now, since we are not assured that the old row order is actually still free in
-        // the table (recall that there is a unique constraint on _order). However the row_order
-        // currently assigned is at the end of the table so there should not be any conflicts.
-        if let Some(new_row_id) = new_row_id {
-            tracing::debug!("Changing new row ID to {new_row_id}");
-            new_row.id = new_row_id;
+impl BatchOperation {
+    /// Parse a line written in the same form as the corresponding rltbl command, i.e., one of:
+    /// `set value TABLE ROW COLUMN VALUE`, `add row [--after-id ROW] TABLE JSON`,
+    /// `delete row TABLE ROW`, or `move row TABLE ROW AFTER`. Words are split as they would be
+    /// by a POSIX shell, so values containing spaces must be quoted.
+    pub fn from_command(line: &str) -> Result<Self> {
+        tracing::trace!("BatchOperation::from_command({line:?})");
+        let invalid = |reason: &str| -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid command '{line}': {reason}")).into()
+        };
+        let words = shlex::split(line).ok_or(invalid("unbalanced quotes"))?;
+        let words = words.iter().map(|word| word.as_str()).collect::<Vec<_>>();
+        let parse_row = |row: &str| -> Result<u64> {
+            row.parse::<u64>()
+                .map_err(|_| invalid(&format!("'{row}' is not a row ID")))
+        };
+        match words.as_slice() {
+            ["set", "value", table, row, column, value] => Ok(Self::SetValue {
+                table: table.to_string(),
+                row: parse_row(row)?,
+                column: column.to_string(),
+                value: serde_json::from_str::<JsonValue>(value).unwrap_or(json!(value)),
+            }),
+            ["add", "row", "--after-id", after, table, values]
+            | ["add", "row", table, "--after-id", after, values] => Ok(Self::AddRow {
+                table: table.to_string(),
+                after: Some(parse_row(after)?),
+                values: Self::parse_values(values).map_err(|err| invalid(&err))?,
+            }),
+            ["add", "row", table, values] => Ok(Self::AddRow {
+                table: table.to_string(),
+                after: None,
+                values: Self::parse_values(values).map_err(|err| invalid(&err))?,
+            }),
+            ["delete", "row", table, row] => Ok(Self::DeleteRow {
+                table: table.to_string(),
+                row: parse_row(row)?,
+            }),
+            ["move", "row", table, row, after] => Ok(Self::MoveRow {
+                table: table.to_string(),
+                row: parse_row(row)?,
+                after: parse_row(after)?,
+            }),
+            _ => Err(invalid("unrecognized or incomplete command")),
         }
+    }
 
-        // Validate the row and add it to the table:
-        if self.validation_level != ValidationLevel::None {
-            new_row.validate_sql_types(&table, &mut tx)?;
-            for (_column, cell) in new_row.cells.iter_mut() {
-                if cell.has_sql_type_error() {
-                    cell.value = JsonValue::Null;
-                    cell.text = "".to_string(); // Should it be "null" instead of blank?
-                }
-            }
+    /// Parse a JSON object representing the values of a new row.
+    fn parse_values(values: &str) -> std::result::Result<JsonMap<String, JsonValue>, String> {
+        match serde_json::from_str::<JsonValue>(values) {
+            Ok(JsonValue::Object(values)) => Ok(values),
+            _ => Err(format!("{values} is not a JSON object")),
         }
-        let (sql, params) = new_row.as_insert(&table.name, &tx.kind());
-        tx.query(&sql, Some(&params))?;
+    }
 
-        // Optionally do full validation on the row after it has been inserted:
-        if self.validation_level == ValidationLevel::Full {
-            self._validate_row(&table, &new_row.id, &mut tx)?;
-            for table in &table._get_dependent_tables(None, &mut tx)? {
-                tracing::debug!("Validating dependent table '{}'", table.name);
-                self._validate_structure_for_table(table, &mut tx)?;
+    /// Parse the given content, which is either a JSON array of operations or a sequence of
+    /// commands (see [BatchOperation::from_command()]), one per line. Blank lines and lines
+    /// beginning with '#' are ignored.
+    pub fn many_from_str(content: &str) -> Result<Vec<Self>> {
+        tracing::trace!("BatchOperation::many_from_str({content:?})");
+        if content.trim_start().starts_with('[') {
+            return Ok(serde_json::from_str::<Vec<Self>>(content)?);
+        }
+        let mut operations = vec![];
+        for line in content.lines().map(|line| line.trim()) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
             }
+            operations.push(Self::from_command(line)?);
         }
+        Ok(operations)
+    }
+}
 
-        let after_id = match after_id {
-            None => Table::_get_previous_row_id(&table.name, new_row.id, &mut tx)?,
-            Some(after_id) => {
-                // Move the row to its assigned spot within the table:
-                tracing::debug!(
-                    "Moving new row {id} to after row {after_id} in '{table}'",
-                    id = new_row.id,
-                    table = table.name
-                );
-                let new_order = self._move_row(&mut tx, &table, new_row.id, after_id)?;
-                new_row.order = new_order;
-                after_id
-            }
-        };
+/// The validation messages of a single cell, as returned by [Relatable::set_values_with_messages()]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CellMessages {
+    pub table: String,
+    pub row: u64,
+    pub column: String,
+    pub messages: Vec<Message>,
+}
 
-        tracing::debug!(
-            "Added new row {id} to table '{table}' after row {after_id}",
-            id = new_row.id,
-            table = table.name
-        );
+/// An in-memory copy of a [relatable](crate) database, made by [Relatable::sandbox()], in which
+/// batches of edits can be tried out before they are either discarded or replayed against the
+/// original database.
+#[derive(Debug)]
+pub struct Sandbox {
+    /// The instance connected to the copy, which can be queried and validated like any other
+    pub rltbl: Relatable,
+    /// The batches that have been applied to the copy, each with its user and message
+    batches: Vec<(String, Option<String>, Vec<BatchOperation>)>,
+    /// The messages in the copy when it was made, by content hash
+    messages: IndexMap<String, JsonMap<String, JsonValue>>,
+}
 
-        // Prepare a changeset to be recorded, consisting of a single change record indicating
-        // the addition of one new row with the new_row's id and position in the table:
-        let changeset = ChangeSet {
-            action: *action,
-            table: table_name.to_string(),
-            user: user.to_string(),
-            description: "Add one row".to_string(),
-            changes: vec![Change::Add {
-                row: new_row.id,
-                after: after_id,
-            }],
-        };
+/// The validation messages that the edits made in a [Sandbox] have added and removed.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct MessageDelta {
+    pub added: Vec<JsonMap<String, JsonValue>>,
+    pub removed: Vec<JsonMap<String, JsonValue>>,
+}
 
-        // Use the changeset to prepare the user cursor:
-        self.prepare_user_cursor(&changeset, &mut tx)?;
+impl Sandbox {
+    /// Apply the given [BatchOperation]s to the copy on behalf of the given user, as
+    /// [Relatable::batch()] does, and remember them so that they can be replayed later.
+    pub async fn batch(
+        &mut self,
+        user: &str,
+        message: Option<&str>,
+        operations: &[BatchOperation],
+    ) -> Result<Option<u64>> {
+        tracing::trace!("Sandbox::batch({user:?}, {message:?}, {operations:?})");
+        let change_group = self.rltbl.batch(user, message, operations).await?;
+        self.batches.push((
+            user.to_string(),
+            message.map(|message| message.to_string()),
+            operations.to_vec(),
+        ));
+        Ok(change_group)
+    }
 
-        // Record the changes to the history table:
-        self.record_changeset(&changeset, &mut tx)?;
+    /// Returns the messages that have been added to and removed from the copy since it was made,
+    /// whether by the batches applied to it or by validating it.
+    pub async fn message_delta(&self) -> Result<MessageDelta> {
+        tracing::trace!("Sandbox::message_delta()");
+        let messages = Self::get_messages(&self.rltbl).await?;
+        Ok(MessageDelta {
+            added: messages
+                .iter()
+                .filter(|(hash, _)| !self.messages.contains_key(*hash))
+                .map(|(_, message)| message.clone())
+                .collect(),
+            removed: self
+                .messages
+                .iter()
+                .filter(|(hash, _)| !messages.contains_key(*hash))
+                .map(|(_, message)| message.clone())
+                .collect(),
+        })
+    }
 
-        // Commit the transaction:
-        tx.commit()?;
+    /// Apply the batches that have been applied to the copy, in the same order, to the given
+    /// instance, which should be connected to the original database, and return the change group
+    /// of each. Each batch is applied in its own transaction. Since rows are identified by their
+    /// _id, edits to the rows added in the copy will apply to different rows if the original
+    /// database has gained rows of its own since the copy was made.
+    pub async fn replay(self, rltbl: &Relatable) -> Result<Vec<Option<u64>>> {
+        tracing::trace!("Sandbox::replay({rltbl:?})");
+        let mut change_groups = vec![];
+        for (user, message, operations) in &self.batches {
+            change_groups.push(rltbl.batch(user, message.as_deref(), operations).await?);
+        }
+        Ok(change_groups)
+    }
 
-        Ok(new_row)
+    /// Discard the copy along with all of the edits made to it.
+    pub fn discard(self) {
+        tracing::trace!("Sandbox::discard()");
     }
 
-    /// Add a row to the given table
-    pub async fn add_row(
-        &self,
-        table_name: &str,
-        user: &str,
-        after_id: Option<u64>,
-        row: &JsonRow,
-    ) -> Result<Row> {
-        tracing::trace!("Relatable::add_row({table_name:?}, {user:?}, {after_id:?}, {row:?})");
-        let conn = self.connection.reconnect()?;
-        let new_row = self
-            ._add_row(
-                conn,
-                &ChangeAction::Do,
-                table_name,
-                user,
-                None,
-                after_id,
-                row,
-            )
-            .await?;
-        self.commit_to_git().await?;
-        Ok(new_row)
+    /// Get the messages in the database that the given instance is connected to, by content hash
+    async fn get_messages(
+        rltbl: &Relatable,
+    ) -> Result<IndexMap<String, JsonMap<String, JsonValue>>> {
+        let sql = r#"SELECT "table", "row", "column", "value", "level", "rule", "message", "_hash"
+                     FROM "message" ORDER BY "message_id""#;
+        let mut messages = IndexMap::new();
+        for mut row in rltbl.connection.query(sql, None).await? {
+            let hash = row.get_string("_hash")?;
+            row.content.shift_remove("_hash");
+            messages.insert(hash, row.content);
+        }
+        Ok(messages)
     }
+}
 
-    /// Delete a row from the table. Returns the number of rows deleted.
-    async fn _delete_row(
-        &self,
-        mut conn: Option<DbActiveConnection>,
-        action: &ChangeAction,
-        table_name: &str,
-        user: &str,
-        row: u64,
-    ) -> Result<usize> {
-        tracing::trace!(
-            "Relatable::_delete_row(conn, {action:?}, {table_name:?}, {user:?} \
-                         {row})"
-        );
-        // Begin a transaction:
-        let mut tx = self.connection.begin(&mut conn).await?;
+/// The kind of change that an [Operation] makes to a row
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OperationKind {
+    #[default]
+    Update,
+    Add,
+    Delete,
+    Move,
+}
 
-        // Get the current database information for the table:
-        let table = Table::_get_table(table_name, &mut tx)?;
-        if !table.editable {
-            return Err(
-                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
-            );
-        }
+impl FromStr for OperationKind {
+    type Err = anyhow::Error;
 
-        // Prepare a changeset to be recorded, consisting of a single change record indicating
-        // that a row with the given row number at the given table position has been deleted:
-        let changeset = ChangeSet {
-            action: *action,
-            table: table_name.to_string(),
-            user: user.to_string(),
-            description: "Delete one row".to_string(),
-            changes: vec![Change::Delete {
-                row: row,
-                after: Table::_get_previous_row_id(table_name, row, &mut tx)?,
-            }],
-        };
+    fn from_str(kind: &str) -> Result<Self> {
+        tracing::trace!("OperationKind::from_str({kind:?})");
+        match kind.to_lowercase().as_str() {
+            "update" => Ok(OperationKind::Update),
+            "add" => Ok(OperationKind::Add),
+            "delete" => Ok(OperationKind::Delete),
+            "move" => Ok(OperationKind::Move),
+            _ => Err(
+                RelatableError::InputError(format!("Unrecognized operation kind: {kind}")).into(),
+            ),
+        }
+    }
+}
 
-        // Use the changeset to prepare the user cursor:
-        self.prepare_user_cursor(&changeset, &mut tx)?;
+impl Display for OperationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperationKind::Update => write!(f, "update"),
+            OperationKind::Add => write!(f, "add"),
+            OperationKind::Delete => write!(f, "delete"),
+            OperationKind::Move => write!(f, "move"),
+        }
+    }
+}
 
-        // Delete the row:
-        let sql = format!(
-            r#"DELETE FROM "{}" WHERE "_id" = {sql_param} RETURNING 1 AS "deleted""#,
-            table.name,
-            sql_param = SqlParam::new(&self.connection.kind()).next()
-        );
-        let params = json!([row]);
-        tracing::debug!("Deleted row {row} from table {table_name}");
+/// A change to a single row, as recorded in the operation table. Each operation is tagged with
+/// the origin of the instance that made it, a counter that is incremented with every operation
+/// made by that origin, and a vector clock recording the latest counter that the instance had seen
+/// from every origin at the time the operation was made.
+///
+/// Since the instances assign _ids to the rows that they add independently, a row is identified
+/// by the origin of the instance that added it, or an empty string if the row was loaded rather
+/// than added, together with its _id in that instance. Positions are given in the same way, as
+/// the `[row_origin, row]` of the row that comes immediately before, with `["", 0]` standing for
+/// the beginning of the table.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Operation {
+    pub origin: String,
+    pub counter: u64,
+    pub clock: IndexMap<String, u64>,
+    pub datetime: String,
+    pub user: String,
+    pub table: String,
+    #[serde(default)]
+    pub kind: OperationKind,
+    #[serde(default)]
+    pub row_origin: String,
+    pub row: u64,
+    /// The column that was updated, `_order` for a move, or empty for an addition or deletion
+    pub column: String,
+    /// The new value of the cell for an update, the new position of the row, under `after`, for
+    /// a move, and its position and the values of its cells, under `after` and `cells`, for an
+    /// addition
+    pub value: JsonValue,
+}
 
-        // Delete any messages associated with the row
-        self._delete_message(&mut tx, table_name, Some(row), None, None, None)?;
-        tracing::debug!("Deleted messages for deleted row {row} of table {table_name}");
+impl Operation {
+    /// Convert the given row of the operation table into an [Operation]
+    pub fn from_json_row(row: &JsonRow) -> Result<Self> {
+        tracing::trace!("Operation::from_json_row({row:?})");
+        Ok(Self {
+            origin: row.get_string("origin")?,
+            counter: row.get_unsigned("counter")?,
+            clock: serde_json::from_str(&row.get_string("clock")?)?,
+            datetime: row.get_string("datetime")?,
+            user: row.get_string("user")?,
+            table: row.get_string("table")?,
+            kind: match row.get_optional_string("kind")? {
+                Some(kind) => kind.parse()?,
+                None => OperationKind::Update,
+            },
+            row_origin: row.get_optional_string("row_origin")?.unwrap_or_default(),
+            row: row.get_unsigned("row")?,
+            column: row.get_string("column")?,
+            value: serde_json::from_str(&row.get_string("value")?)?,
+        })
+    }
 
-        // Record the change to the history table:
-        self.record_changeset(&changeset, &mut tx)?;
+    /// Returns true if this operation causally precedes the given one, i.e., if the instance
+    /// that made the given operation had already seen this one.
+    pub fn happened_before(&self, other: &Operation) -> bool {
+        tracing::trace!("Operation::happened_before({other:?})");
+        other.clock.get(&self.origin).unwrap_or(&0) >= &self.counter
+    }
 
-        let num_deleted = tx.query(&sql, Some(&params))?.len();
-        if num_deleted < 1 {
-            tracing::warn!("No row found with _id {row} to delete");
-            // Roll back the changes to the history and change table. The reason we made these
-            // prior to the actual delete was so that we could record the row's position in the
-            // table before it was deleted.
-            tx.rollback()?;
+    /// Returns true if this operation should take precedence over the given one, in which case
+    /// its value should be the one assigned to the cell.
+    pub fn supersedes(&self, other: &Operation) -> bool {
+        tracing::trace!("Operation::supersedes({other:?})");
+        if other.happened_before(self) {
+            true
+        } else if self.happened_before(other) {
+            false
         } else {
-            // Commit the transaction:
-            tx.commit()?;
+            self.sort_key() > other.sort_key()
         }
+    }
 
-        Ok(num_deleted)
+    /// The key used to order concurrent operations deterministically when merging them.
+    fn sort_key(&self) -> (&str, &str, u64) {
+        (&self.datetime, &self.origin, self.counter)
     }
 
-    /// Delete a row from a given table
-    pub async fn delete_row(&self, table_name: &str, user: &str, row: u64) -> Result<usize> {
-        tracing::trace!("Relatable::delete_row({table_name:?}, {user:?}, {row})");
-        let conn = self.connection.reconnect()?;
-        let num_deleted = self
-            ._delete_row(conn, &ChangeAction::Do, table_name, user, row)
-            .await?;
-        if num_deleted > 0 {
-            self.commit_to_git().await?;
-        }
-        Ok(num_deleted)
+    /// The key used to order operations for applying them: since every operation has seen more
+    /// operations than any that happened before it, ordering by the total of the clock puts each
+    /// operation after those that it follows, e.g., an update to a row after the row's addition.
+    fn apply_key(&self) -> (u64, (&str, &str, u64)) {
+        (self.clock.values().sum(), self.sort_key())
     }
+}
 
-    /// Delete messages from the message table. Returns the number of messages deleted.
-    pub async fn delete_message(
-        &self,
-        table: &str,
-        row: Option<u64>,
-        column: Option<&str>,
-        target_rule: Option<&str>,
-        target_user: Option<&str>,
-    ) -> Result<usize> {
-        tracing::trace!(
-            "Relatable::delete_message({self:?}, {table:?}, {row:?}, {column:?}, \
-             {target_rule:?}, {target_user:?})"
-        );
+/// The changes made to a table since a given change, as served to the instances that mirror it.
+/// See [Relatable::get_change_feed()].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ChangeFeed {
+    pub table: String,
+    /// The ID of the most recent change to the table
+    pub change_id: u64,
+    /// The SQL types of the table's columns, by column name
+    pub columns: IndexMap<String, String>,
+    /// The current contents, including _id and _order, of the rows that have changed
+    pub rows: Vec<JsonMap<String, JsonValue>>,
+    /// The _ids of the rows that have changed and no longer exist
+    pub deleted: Vec<u64>,
+    /// The metrics of the changes to the table since the given change, by change_id, for those
+    /// whose metrics were recorded (see [ChangeMetrics])
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub metrics: IndexMap<u64, ChangeMetrics>,
+}
 
-        // Begin a transaction:
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
+/// The outcome of loading a table using [Relatable::load_tables()].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct TableLoad {
+    pub table: String,
+    pub path: String,
+    pub status: LoadStatus,
+    /// The number of rows loaded
+    pub rows: u64,
+    /// How long the load took, in seconds
+    pub seconds: f64,
+    /// Why the table wasn't loaded, if it wasn't
+    pub error: Option<String>,
+}
 
-        // Delete the messages using the transaction
-        let num_deleted =
-            self._delete_message(&mut tx, table, row, column, target_rule, target_user)?;
+/// Whether a table was loaded by [Relatable::load_tables()].
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadStatus {
+    #[default]
+    Loaded,
+    /// The table could not be loaded
+    Failed,
+    /// The table was not tried because a table it depends on was not loaded
+    Skipped,
+}
 
-        // Commit the transaction:
-        tx.commit()?;
+/// A problem with the configuration in the meta tables, found by [Relatable::check_config()].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ConfigProblem {
+    /// The meta table that the problem was found in
+    pub table: String,
+    /// The row of the meta table that the problem concerns, if there is one
+    pub row: Option<u64>,
+    pub column: String,
+    pub value: String,
+    pub level: String,
+    pub rule: String,
+    pub message: String,
+}
 
-        Ok(num_deleted)
+/// When each of the tables that are saved automatically, and that have changed without being
+/// saved since, was last changed (see [Relatable::autosave()])
+#[derive(Debug, Default)]
+struct Autosaves {
+    pending: HashMap<String, Instant>,
+}
+
+/// Convert the given text, from a block of pasted values (see [Relatable::paste()]), to a value
+/// of the SQL type of the given column, if it can be. Empty text is NULL in columns that are not
+/// of type TEXT, and text that cannot be converted is kept as it is, so that it will fail
+/// validation.
+fn pasted_value(text: &str, column: &Column) -> JsonValue {
+    let sql_type = column.get_sql_type();
+    let sql_type = sql::canonical_sql_type(&sql_type).unwrap_or(sql_type.as_str());
+    if text.is_empty() {
+        return match sql_type {
+            "TEXT" => json!(text),
+            _ => JsonValue::Null,
+        };
     }
+    match sql_type {
+        "INTEGER" => match text.trim().parse::<i64>() {
+            Ok(number) => json!(number),
+            Err(_) => json!(text),
+        },
+        "REAL" | "NUMERIC" => match text.trim().parse::<f64>() {
+            Ok(number) if number.is_finite() => json!(number),
+            _ => json!(text),
+        },
+        _ => json!(text),
+    }
+}
 
-    /// Delete messages from the message table using the given transaction. Returns the
-    /// number of messages deleted.
-    fn _delete_message(
-        &self,
-        tx: &mut DbTransaction<'_>,
-        table: &str,
-        row: Option<u64>,
-        column: Option<&str>,
-        target_rule: Option<&str>,
-        target_user: Option<&str>,
-    ) -> Result<usize> {
-        tracing::trace!(
-            "Relatable::_delete_message({self:?}, tx, {table:?}, {row:?}, {column:?}, \
-             {target_rule:?}, {target_user:?})"
-        );
+/// Extend the series of numbers or dates that begins with the given values, the second of which
+/// is optional, to the given length, e.g., 1, 3, 5, ... or 2024-01-31, 2024-02-01, ... The step
+/// is the difference between the two values, if both are of the same kind, and otherwise one
+/// (day). Numbers are rounded to as many decimal places as the first two have, and dates are
+/// written as text.
+pub fn fill_series(
+    first: &JsonValue,
+    second: Option<&JsonValue>,
+    len: usize,
+) -> Result<Vec<JsonValue>> {
+    let first_text = sql::json_to_string(first);
+    let second_text = second.map(sql::json_to_string).unwrap_or_default();
+    let parse_date = |text: &str| chrono::NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok();
+    if let Ok(start) = first_text.trim().parse::<f64>() {
+        let decimals = |text: &str| match text.trim().split_once('.') {
+            Some((_, fraction)) => fraction.len(),
+            None => 0,
+        };
+        let (step, places) = match second_text.trim().parse::<f64>() {
+            Ok(next) => (
+                next - start,
+                decimals(&first_text).max(decimals(&second_text)),
+            ),
+            Err(_) => (1.0, decimals(&first_text)),
+        };
+        let scale = 10_f64.powi(places as i32);
+        Ok((0..len)
+            .map(|i| {
+                let value = ((start + step * i as f64) * scale).round() / scale;
+                match places {
+                    0 => json!(value as i64),
+                    _ => json!(value),
+                }
+            })
+            .collect())
+    } else if let Some(start) = parse_date(&first_text) {
+        let step = match parse_date(&second_text) {
+            Some(next) => (next - start).num_days(),
+            None => 1,
+        };
+        Ok((0..len)
+            .map(|i| {
+                let date = start + chrono::Duration::days(step * i as i64);
+                json!(date.format("%Y-%m-%d").to_string())
+            })
+            .collect())
+    } else {
+        Err(RelatableError::InputError(format!(
+            "Cannot extend a series from '{first_text}', which is neither a number nor a date"
+        ))
+        .into())
+    }
+}
+
+/// Compute a checksum of the given rows of values that does not depend on their order, by
+/// hashing each row and then hashing the sorted hashes of the rows.
+pub fn checksum_rows(rows: &[Vec<String>]) -> String {
+    let mut hashes = rows
+        .iter()
+        .map(|row| format!("{:x}", Sha256::digest(json!(row).to_string().as_bytes())))
+        .collect::<Vec<_>>();
+    hashes.sort();
+    format!("{:x}", Sha256::digest(hashes.join("\n").as_bytes()))
+}
 
-        let mut sql_param = SqlParam::new(&self.connection.kind());
-        let mut sql = format!(
-            r#"DELETE FROM "message" WHERE "table" = {sql_param}"#,
-            sql_param = sql_param.next()
-        );
-        let mut params = vec![json!(table)];
+/// How a table and the file that it is loaded from and saved to have drifted apart since the
+/// table was last loaded or saved (see [Relatable::verify_table()])
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Drift {
+    /// Neither the table nor the file has changed
+    None,
+    /// The table has changed but has not been saved
+    Table,
+    /// The file has changed but has not been loaded
+    File,
+    /// Both the table and the file have changed
+    Both,
+    /// The table and the file differ, but which of them has changed is not known, since no
+    /// checksums were recorded when the table was last loaded or saved, or the file is missing
+    Unknown,
+}
 
-        if let Some(row) = row {
-            sql.push_str(&format!(
-                r#" AND "row" = {sql_param}"#,
-                sql_param = sql_param.next(),
-            ));
-            params.push(json!(row));
-        }
-        if let Some(column) = column {
-            sql.push_str(&format!(
-                r#" AND "column" = {sql_param}"#,
-                sql_param = sql_param.next()
-            ));
-            params.push(json!(column));
+impl Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::None => write!(f, "in sync"),
+            Drift::Table => write!(f, "the table has changed but has not been saved"),
+            Drift::File => write!(f, "the file has changed but has not been loaded"),
+            Drift::Both => write!(f, "both the table and the file have changed"),
+            Drift::Unknown => write!(f, "the table and the file differ"),
         }
-        if let Some(target_rule) = target_rule {
-            sql.push_str(&format!(
-                r#" AND "rule" LIKE {sql_param}"#,
-                sql_param = sql_param.next()
-            ));
-            params.push(json!(target_rule));
+    }
+}
+
+/// The outcome of [Relatable::verify_table()]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Verification {
+    pub table: String,
+    /// The path of the file that the table is loaded from and saved to, if it has one
+    pub path: Option<String>,
+    /// The checksum of the contents of the table
+    pub checksum: String,
+    /// The checksum of the rows of the file, if it exists
+    pub file_checksum: Option<String>,
+    /// The checksum of the contents of the table when it was last loaded or saved
+    pub loaded_checksum: Option<String>,
+    /// The checksum of the rows of the file when the table was last loaded or saved
+    pub loaded_file_checksum: Option<String>,
+    pub drift: Drift,
+}
+
+/// The outcome of one of the checks run by [Relatable::doctor].
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Diagnosis {
+    /// The name of the check
+    pub check: String,
+    /// Whether the check passed
+    pub ok: bool,
+    /// What was found
+    pub detail: String,
+    /// How to fix the problem, if the check failed
+    pub fix: Option<String>,
+}
+
+impl Diagnosis {
+    /// A diagnosis for a check that passed.
+    pub fn pass(check: &str, detail: &str) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: true,
+            detail: detail.to_string(),
+            fix: None,
         }
-        if let Some(target_user) = target_user {
-            sql.push_str(&format!(
-                r#" AND "added_by" = {sql_param}"#,
-                sql_param = sql_param.next()
-            ));
-            params.push(json!(target_user));
+    }
+
+    /// A diagnosis for a check that failed, with a suggested fix.
+    pub fn fail(check: &str, detail: &str, fix: &str) -> Self {
+        Self {
+            check: check.to_string(),
+            ok: false,
+            detail: detail.to_string(),
+            fix: Some(fix.to_string()),
         }
+    }
+}
 
-        sql.push_str(r#" RETURNING 1 AS "deleted""#);
-        let num_deleted = tx.query(&sql, Some(&json!(params)))?.len();
-        Ok(num_deleted)
+/// The last change that modified the value of a column of a row (see [Relatable::blame()])
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Blame {
+    /// The _id of the row
+    pub row: u64,
+    /// The current value of the column in the row
+    pub value: JsonValue,
+    /// The change, if the value has changed since the row was loaded
+    pub change_id: Option<u64>,
+    pub user: Option<String>,
+    /// One of 'do', 'undo', or 'redo'
+    pub action: Option<String>,
+    pub datetime: Option<String>,
+    pub message: Option<String>,
+    /// The metrics of the change, if they were recorded (see [ChangeMetrics])
+    #[serde(default, skip_deserializing)]
+    pub metrics: Option<ChangeMetrics>,
+}
+
+/// Write the given blame of a column (see [Relatable::blame()]) as TSV, with a header, one line
+/// per row.
+pub fn blame_to_tsv(blame: &[Blame]) -> Result<String> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(b'\t')
+        .quote_style(QuoteStyle::Never)
+        .from_writer(vec![]);
+    writer.write_record([
+        "row",
+        "value",
+        "change_id",
+        "user",
+        "action",
+        "datetime",
+        "message",
+    ])?;
+    for line in blame {
+        writer.write_record([
+            line.row.to_string(),
+            sql::json_to_string(&line.value),
+            line.change_id.map(|id| id.to_string()).unwrap_or_default(),
+            line.user.clone().unwrap_or_default(),
+            line.action.clone().unwrap_or_default(),
+            line.datetime.clone().unwrap_or_default(),
+            line.message.clone().unwrap_or_default(),
+        ])?;
     }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
 
-    /// Move a row and record the change in the change table
-    async fn _move_and_record_row(
-        &self,
-        mut conn: Option<DbActiveConnection>,
-        action: &ChangeAction,
-        table_name: &str,
-        user: &str,
-        id: u64,
-        after_id: u64,
-    ) -> Result<u64> {
-        tracing::trace!(
-            "Relatable::_move_and_record_row(conn, {action:?}, {table_name:?}, \
-                         {user:?}, {id}, {after_id})"
-        );
+/// The distinct values of a column among the rows of a select, and how many of the rows have
+/// each of them (see [Relatable::facets()])
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Facet {
+    pub column: String,
+    /// The values, from the most to the least common
+    pub values: Vec<FacetValue>,
+    /// Whether there are more values than were returned
+    pub truncated: bool,
+}
 
-        // Begin a transaction:
-        let mut tx = self.connection.begin(&mut conn).await?;
+/// The totals of the values of a numeric column among the rows of a select (see
+/// [Relatable::totals()])
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Total {
+    /// The sum of the column's values, or NULL if they are all NULL
+    pub sum: JsonValue,
+    /// The mean of the column's values, if they are not all NULL
+    pub avg: Option<f64>,
+}
 
-        // Get the current database information for the table:
-        let table = Table::_get_table(table_name, &mut tx)?;
-        if !table.editable {
-            return Err(
-                RelatableError::InputError(format!("{} is not editable.", table_name,)).into(),
-            );
-        }
+/// One of the values of a [Facet], with the number of rows that have it
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct FacetValue {
+    pub value: JsonValue,
+    pub count: u64,
+}
 
-        // Prepare a changeset to be recorded, consisting of a single change record indicating
-        // that a row has been displaced from somewhere to somewhere else.
-        let changeset = ChangeSet {
-            action: *action,
-            table: table_name.to_string(),
-            user: user.to_string(),
-            description: "Move one row".to_string(),
-            changes: vec![Change::Move {
-                row: id,
-                from_after: Table::_get_previous_row_id(table_name, id, &mut tx)?,
-                to_after: after_id,
-            }],
-        };
+/// Statistics about the values of a column of a table (see [Relatable::column_stats()])
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ColumnStats {
+    pub column: String,
+    /// The least of the column's values, or NULL if they are all NULL
+    pub min: JsonValue,
+    /// The greatest of the column's values, or NULL if they are all NULL
+    pub max: JsonValue,
+    /// The mean of the column's values, if it is numeric and they are not all NULL
+    pub mean: Option<f64>,
+    pub null_count: u64,
+    /// The number of distinct values, not counting NULL
+    pub distinct_count: u64,
+}
 
-        // Use the changeset to prepare the user cursor:
-        self.prepare_user_cursor(&changeset, &mut tx)?;
+/// The result of pasting a value into a cell of a table (see [Relatable::paste()])
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct PastedCell {
+    pub row: u64,
+    pub column: String,
+    pub before: JsonValue,
+    /// The pasted value, converted to the SQL type of the column if possible
+    pub after: JsonValue,
+    /// Whether the pasted value differs from the cell's previous value
+    pub changed: bool,
+    /// Any messages about the pasted value's SQL type
+    pub messages: Vec<Message>,
+}
 
-        // Move the row within the table:
-        let new_order = self._move_row(&mut tx, &table, id, after_id)?;
+/// Write the given statistics about the columns of a table (see [Relatable::column_stats()]) as
+/// TSV, with a header, one line per column.
+pub fn column_stats_to_tsv(stats: &[ColumnStats]) -> Result<String> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(b'\t')
+        .quote_style(QuoteStyle::Never)
+        .from_writer(vec![]);
+    writer.write_record([
+        "column",
+        "min",
+        "max",
+        "mean",
+        "null_count",
+        "distinct_count",
+    ])?;
+    for line in stats {
+        writer.write_record([
+            line.column.clone(),
+            sql::json_to_string(&line.min),
+            sql::json_to_string(&line.max),
+            line.mean.map(|mean| mean.to_string()).unwrap_or_default(),
+            line.null_count.to_string(),
+            line.distinct_count.to_string(),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
 
-        if new_order != 0 {
-            // Record the change to the history table:
-            self.record_changeset(&changeset, &mut tx)?;
+/// Describes a history of changes that have been done and undone.
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct History {
+    pub changes_done_stack: Vec<JsonRow>,
+    pub changes_undone_stack: Vec<JsonRow>,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Change::Update {
+                row,
+                column,
+                before,
+                after,
+            } => {
+                write!(
+                    f,
+                    "Update '{column}' in row {row} from {before} to {after}",
+                    before = sql::json_to_string(before),
+                    after = sql::json_to_string(after)
+                )
+            }
+            Change::Add { row, after } => {
+                write!(f, "Add row {row} after row {after}")
+            }
+            Change::Move {
+                row,
+                from_after,
+                to_after,
+            } => {
+                write!(
+                    f,
+                    "Move row {row} from after row {from_after} to after row {to_after}"
+                )
+            }
+            Change::Delete { row, after: _ } => write!(f, "Delete row {row}"),
         }
+    }
+}
 
-        // Commit the transaction:
-        tx.commit()?;
+// Ranges and Results
 
-        Ok(new_order)
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Range {
+    count: usize,
+    total: u64,
+    start: u64,
+    end: u64,
+    /// The cursor token for the page before this one, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<String>,
+    /// The cursor token for the page after this one, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next: Option<String>,
+    /// Whether the total is an estimate rather than an exact count (see
+    /// [Select::estimate_count])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    estimated: bool,
+    /// Whether fewer rows may have been returned than were asked for, because the select's limit
+    /// was reduced to the maximum page size (see [Select::max_limit()])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    limited: bool,
+}
+
+impl Range {
+    /// Get the cursor token for the page before this one, if there is one
+    pub fn previous(&self) -> Option<&str> {
+        self.previous.as_deref()
     }
 
-    /// Move a row to a different position in a given table
-    fn _move_row(
-        &self,
-        tx: &mut DbTransaction<'_>,
-        table: &Table,
-        id: u64,
-        after_id: u64,
-    ) -> Result<u64> {
-        tracing::trace!("Relatable::_move_row(tx, {table:?}, {id}, {after_id})");
-        fn get_row_order(tx: &mut DbTransaction<'_>, table: &Table, row_id: u64) -> Result<u64> {
-            let sql = format!(
-                r#"SELECT "_order" FROM "{}" WHERE "_id" = {sql_param}"#,
-                table.name,
-                sql_param = SqlParam::new(&tx.kind()).next()
-            );
-            let params = json!([row_id]);
-            let rows = tx.query(&sql, Some(&params))?;
-            if rows.is_empty() {
-                return Err(RelatableError::DataError(format!(
-                    "Unable to fetch _order for row {row_id} of table '{table}'",
-                    table = table.name
-                ))
-                .into());
-            }
-            match rows[0].content.get("_order").and_then(|o| o.as_u64()) {
-                Some(order) => Ok(order as u64),
-                None => {
-                    return Err(
-                        RelatableError::DataError("No integer '_order' in row".to_string()).into(),
-                    )
-                }
-            }
+    /// Get the cursor token for the page after this one, if there is one
+    pub fn next(&self) -> Option<&str> {
+        self.next.as_deref()
+    }
+
+    /// Whether the total is an estimate rather than an exact count
+    pub fn is_estimated(&self) -> bool {
+        self.estimated
+    }
+
+    /// Whether the select's limit was reduced to the maximum page size
+    pub fn is_limited(&self) -> bool {
+        self.limited
+    }
+}
+
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.estimated {
+            true => write!(f, "Rows {}-{} of ~{}", self.start, self.end, self.total),
+            false => write!(f, "Rows {}-{} of {}", self.start, self.end, self.total),
         }
+    }
+}
 
-        // Get the order, (A), of `after_id`:
-        let order_prev = {
-            if after_id > 0 {
-                let mut id_to_try = after_id;
-                let mut result = get_row_order(tx, table, id_to_try);
-                // This handles the case in which the after row has been deleted for some reason
-                // (this might happen if we are redoing).
-                while let Err(_) = result {
-                    if id_to_try == 0 {
-                        break;
-                    }
-                    tracing::debug!("Could not obtain _order for row {id_to_try}");
-                    id_to_try -= 1;
-                    tracing::debug!("Trying to find the _order of row {id_to_try}");
-                    result = get_row_order(tx, table, id_to_try);
-                }
-                result?
-            } else {
-                // It is not possible for a row to be assigned a order of zero. We allow it as a
-                // possible value of `after_id`, however, which is used as a special value that we
-                // should move the row identified by `id` to the beginning of the table.
-                0
-            }
-        };
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ResultSet {
+    pub select: Select,
+    pub statement: String,
+    pub parameters: Vec<JsonValue>,
+    pub range: Range,
+    pub table: Table,
+    /// The columns (and only the columns) used in the Select statement
+    pub columns: Vec<Column>,
+    /// The SQL type of each column in the result, as reported by the database driver
+    #[serde(default)]
+    pub types: IndexMap<String, String>,
+    pub rows: Vec<Row>,
+    /// The totals of the numeric columns over all of the rows that match the select, when they
+    /// were asked for (see [Select::totals])
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub totals: IndexMap<String, Total>,
+}
 
-        // Run a query to get the minimum order, (B), that is greater than (A).
-        let order_next = {
-            let sql = format!(
-                r#"SELECT MIN("_order") AS "_order" FROM "{}" WHERE "_order" > {sql_param}"#,
-                table.name,
-                sql_param = SqlParam::new(&tx.kind()).next()
-            );
-            let params = json!([order_prev]);
-            let rows = tx.query(&sql, Some(&params))?;
-            if rows.is_empty() {
-                return Err(RelatableError::DataError(format!(
-                    "Could not determine the minimum row order greater than {order_prev}"
-                ))
-                .into());
-            }
+impl ResultSet {
+    /// Write the result set to CSV, delimited as is usual in the locale of its select, if it has
+    /// one
+    pub fn to_csv(&self) -> String {
+        let writer = WriterBuilder::new()
+            .delimiter(self.select.locale.unwrap_or_default().csv_delimiter())
+            .from_writer(vec![]);
+        self.to_xsv(writer)
+    }
 
-            match rows[0].content.get("_order") {
-                Some(value) => match value {
-                    JsonValue::Null => {
-                        // The row_order will be null if we ask Relatable to move a row to
-                        // a position after the last row in the table.
-                        order_prev + NEW_ORDER_MULTIPLIER as u64
-                    }
-                    _ => match value.as_u64() {
-                        Some(order) => order as u64,
-                        None => {
-                            return Err(RelatableError::DataError(
-                                "Field '_order' in row is not an integer".to_string(),
-                            )
-                            .into());
-                        }
+    /// Write the result set to TSV
+    pub fn to_tsv(&self) -> String {
+        let writer = WriterBuilder::new()
+            .delimiter(b'\t')
+            .quote_style(QuoteStyle::Never)
+            .from_writer(vec![]);
+        self.to_xsv(writer)
+    }
+
+    /// Write the result set to XSV. Values are written in the locale of its select, if it has
+    /// one, or else in the locales of their columns, if they have them.
+    pub fn to_xsv(&self, mut writer: Writer<Vec<u8>>) -> String {
+        let header_row = &self
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<String>>();
+        writer.write_record(header_row.clone()).unwrap();
+        for row in &self.rows {
+            let record = row.cells.iter().map(|(name, cell)| {
+                match self.columns.iter().find(|column| column.name == *name) {
+                    Some(column) => match self.select.locale.or(column.locale) {
+                        Some(locale) => locale.format(&cell.text, &column.get_sql_type()),
+                        None => cell.text.clone(),
                     },
-                },
-                None => {
-                    return Err(RelatableError::DataError("No '_order' in row".to_string()).into());
+                    None => cell.text.clone(),
                 }
+            });
+            writer.write_record(record).unwrap();
+        }
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    /// Write the result set as an [Arrow IPC stream](https://arrow.apache.org/docs/format/Columnar.html#ipc-streaming-format),
+    /// e.g., for reading into a dataframe. The Arrow type of each column follows from its SQL
+    /// type: integers, numbers, booleans, and binary values get the corresponding Arrow types,
+    /// and everything else is written as text. Values that cannot be represented in the column's
+    /// Arrow type, e.g., invalid values, are written as nulls.
+    pub fn to_arrow(&self) -> Result<Vec<u8>> {
+        let mut fields = vec![];
+        let mut arrays: Vec<ArrayRef> = vec![];
+        for column in &self.columns {
+            let values = self
+                .rows
+                .iter()
+                .map(|row| {
+                    row.cells
+                        .get(&column.name)
+                        .map(|cell| cell.value.clone())
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+            let text = |value: &JsonValue| match value {
+                JsonValue::Null => None,
+                JsonValue::String(value) => Some(value.to_string()),
+                value => Some(value.to_string()),
+            };
+            let (data_type, array): (ArrowType, ArrayRef) =
+                match sql::canonical_sql_type(&column.get_sql_type()) {
+                    Some("INTEGER") => (
+                        ArrowType::Int64,
+                        Arc::new(Int64Array::from_iter(values.iter().map(
+                            |value| match value {
+                                JsonValue::String(value) => value.parse::<i64>().ok(),
+                                value => value.as_i64(),
+                            },
+                        ))),
+                    ),
+                    Some("NUMERIC") => (
+                        ArrowType::Float64,
+                        Arc::new(Float64Array::from_iter(values.iter().map(
+                            |value| match value {
+                                JsonValue::String(value) => value.parse::<f64>().ok(),
+                                value => value.as_f64(),
+                            },
+                        ))),
+                    ),
+                    Some("BOOLEAN") => (
+                        ArrowType::Boolean,
+                        Arc::new(BooleanArray::from_iter(values.iter().map(
+                            |value| match value {
+                                JsonValue::Number(value) => value.as_i64().map(|value| value != 0),
+                                value => value.as_bool(),
+                            },
+                        ))),
+                    ),
+                    Some("BLOB") => (
+                        ArrowType::Binary,
+                        Arc::new(BinaryArray::from_iter(values.iter().map(|value| {
+                            value
+                                .as_str()
+                                .and_then(|value| sql::decode_binary(value).ok())
+                        }))),
+                    ),
+                    _ => (
+                        ArrowType::Utf8,
+                        Arc::new(StringArray::from_iter(values.iter().map(text))),
+                    ),
+                };
+            fields.push(Field::new(&column.name, data_type, true));
+            arrays.push(array);
+        }
+
+        let schema = Arc::new(Schema::new(fields));
+        let batch = RecordBatch::try_new_with_options(
+            schema.clone(),
+            arrays,
+            &RecordBatchOptions::new().with_row_count(Some(self.rows.len())),
+        )?;
+        let mut writer = StreamWriter::try_new(vec![], &schema)?;
+        writer.write(&batch)?;
+        Ok(writer.into_inner()?)
+    }
+
+    /// Uses the given (unverified) printf-style format string and the given compiled regular
+    /// expression (which is used to verify the given format) to format the given cell.
+    fn format_cell_text_value(column_format: &str, format_regex: &Regex, cell: &str) -> String {
+        // If the cell is an empty string, just return it as is:
+        if cell == "" {
+            return "".to_string();
+        }
+
+        let conversion_spec = match format_regex.captures(column_format) {
+            Some(c) => c[1].to_lowercase(),
+            None => {
+                tracing::warn!("Illegal format: '{}'", column_format);
+                "s".to_string()
             }
         };
-
-        let mut new_order = {
-            if order_prev + 1 < order_next {
-                // If the next order is not occupied just use it:
-                order_prev + 1
-            } else {
-                // Otherwise, get all the orders that need to be moved. We sort the results in
-                // descending order so that when we later update each value, no duplicate key
-                // violations will ensue:
-                let upper_bound = (order_next as f32 / NEW_ORDER_MULTIPLIER as f32).ceil() as u64
-                    * NEW_ORDER_MULTIPLIER as u64;
-                let mut sql_param = SqlParam::new(&tx.kind());
-                let sql = format!(
-                    r#"SELECT "_order"
-                         FROM "{}"
-                        WHERE "_order" >= {sql_param_1} AND "_order" < {sql_param_2}
-                     ORDER BY "_order" DESC"#,
-                    table.name,
-                    sql_param_1 = sql_param.next(),
-                    sql_param_2 = sql_param.next()
-                );
-                let params = json!([order_next, upper_bound]);
-                let rows = tx.query(&sql, Some(&params))?;
-                if rows.is_empty() {
-                    return Err(RelatableError::DataError(
-                        "Could not determine the highest row order".to_string(),
-                    )
-                    .into());
-                }
-                let highest_order = match rows[0].content.get("_order").and_then(|o| o.as_u64()) {
-                    Some(order) => order as u64,
-                    None => {
-                        return Err(RelatableError::DataError(
-                            "No field '_order' in row or it is not an integer".to_string(),
-                        )
-                        .into())
+        let generic_error = format!("Error applying format '{}' to '{}'", column_format, cell);
+        match conversion_spec.as_str() {
+            "d" | "i" | "c" => match cell.parse::<isize>() {
+                Ok(cell) => match sprintf!(&column_format, cell) {
+                    Ok(cell) => {
+                        // For some reason sprintf converts signed ints to unsigned ints before
+                        // converting them to a string. So we have to workaround this here:
+                        let cell = cell.parse::<usize>().unwrap();
+                        let cell = cell as isize;
+                        cell.to_string()
                     }
-                };
-                if highest_order + 1 >= upper_bound {
-                    // Return an error
-                    return Err(RelatableError::DataError(format!(
-                        "Impossible to move row {} after row {}: No more room",
-                        id, after_id
-                    ))
-                    .into());
+                    Err(e) => {
+                        tracing::warn!("{}: {}", generic_error, e);
+                        cell.to_string()
+                    }
+                },
+                Err(e) => {
+                    tracing::warn!("{}: {}", generic_error, e);
+                    cell.to_string()
                 }
-
-                for row in rows {
-                    let current_order = match row.content.get("_order").and_then(|o| o.as_u64()) {
-                        Some(order) => order as u64,
-                        None => {
-                            return Err(RelatableError::DataError(
-                                "No field '_order' in row or it is not an integer".to_string(),
-                            )
-                            .into())
-                        }
-                    };
-                    let sql = format!(
-                        r#"UPDATE "{}"
-                              SET "_order" = "_order" + 1
-                            WHERE "_order" = {sql_param}"#,
-                        table.name,
-                        sql_param = SqlParam::new(&tx.kind()).next()
-                    );
-                    let params = json!([current_order]);
-                    tx.query(&sql, Some(&params))?;
+            },
+            "o" | "u" | "x" => match cell.parse::<usize>() {
+                Ok(cell) => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
+                Err(e) => {
+                    tracing::warn!("{}: {}", generic_error, e);
+                    cell.to_string()
                 }
-                // Now that we have made some room, we can use order_prev + 1,
-                // which should no longer be occupied:
-                order_prev + 1
+            },
+            "e" | "f" | "g" | "a" => match cell.parse::<f64>() {
+                Ok(cell) => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
+                Err(e) => {
+                    tracing::warn!("{}: {}", generic_error, e);
+                    cell.to_string()
+                }
+            },
+            "s" => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
+            _ => {
+                tracing::warn!(
+                    "Unsupported conversion specifier '{}' in column format '{}'",
+                    conversion_spec,
+                    column_format
+                );
+                cell.to_string()
             }
-        };
-
-        tracing::debug!(
-            "Updating _order in table '{table}' for row {id} to {new_order}",
-            table = table.name
-        );
-
-        let mut sql_param = SqlParam::new(&tx.kind());
-        let sql = format!(
-            r#"UPDATE "{}" SET "_order" = {sql_param_1}
-               WHERE "_id" = {sql_param_2}
-               RETURNING 1 AS "moved""#,
-            table.name,
-            sql_param_1 = sql_param.next(),
-            sql_param_2 = sql_param.next(),
-        );
-        let params = json!([new_order, id]);
-        if tx.query(&sql, Some(&params))?.len() < 1 {
-            tracing::warn!("Now row with _id {id} found to move");
-            // It is not possible for a row to have an order of zero. It is used here to
-            // represent the case where no row was actually moved to the caller.
-            new_order = 0;
         }
-        Ok(new_order)
     }
 
-    /// Change the _id of the given row in the given table.
-    fn _change_row_id(
-        &self,
-        tx: &mut DbTransaction<'_>,
-        table: &Table,
-        id: u64,
-        new_id: u64,
-    ) -> Result<()> {
-        tracing::trace!("Relatable::_change_row_id(tx, {table:?}, {id}, {new_id})");
-        let mut sql_param = SqlParam::new(&tx.kind());
-        let sql = format!(
-            r#"UPDATE "{table}"
-                  SET "_id" = {sql_param_1}, "_order" = {sql_param_2}
-                WHERE "_id" = {sql_param_3}
-            RETURNING "_id" AS "_id""#,
-            table = table.name,
-            sql_param_1 = sql_param.next(),
-            sql_param_2 = sql_param.next(),
-            sql_param_3 = sql_param.next(),
-        );
-        let params = json!([new_id, id, id * NEW_ORDER_MULTIPLIER as u64]);
-        tx.query_one(&sql, Some(&params))?
-            .ok_or(RelatableError::DataError(format!("No row with _id = {id}")))?
-            .get_unsigned("_id")?;
-        Ok(())
-    }
+    /// Write the result set to the console
+    pub fn to_console(&self) -> String {
+        let tw = TabWriter::new(vec![]);
+        let mut tw = tw.ansi(true);
+        tw.write(format!("{}\n", self.range).as_bytes())
+            .unwrap_or_default();
+        let header = &self
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<String>>();
+        tw.write(format!("{}\n", header.join("\t")).as_bytes())
+            .unwrap_or_default();
 
-    /// Move a row to a different position in a given table.
-    pub async fn move_row(
-        &self,
-        table_name: &str,
-        user: &str,
-        id: u64,
-        after_id: u64,
-    ) -> Result<u64> {
-        tracing::trace!("Relatable::move_row({table_name:?}, {user:?}, {after_id:?})");
-        let conn = self.connection.reconnect()?;
-        let new_order = self
-            ._move_and_record_row(conn, &ChangeAction::Do, table_name, user, id, after_id)
-            .await?;
-        if new_order != 0 {
-            self.commit_to_git().await?;
+        let format_regex = Regex::new(r#"^%.*([\w%])$"#).expect("Invalid regular expression");
+        let mut contains_errors = false;
+        for row in &self.rows {
+            let cells = row
+                .cells
+                .iter()
+                .map(|(column_name, cell)| {
+                    let value_to_print = {
+                        let column_format = match self.table.columns.get(column_name) {
+                            Some(column) if column.datatype.format == "" => "%s",
+                            Some(column) => &column.datatype.format,
+                            None => {
+                                tracing::warn!(
+                                    "Can't determine cell format. No column found: '{column_name}'"
+                                );
+                                "%s"
+                            }
+                        };
+                        ResultSet::format_cell_text_value(&column_format, &format_regex, &cell.text)
+                    };
+                    if cell.message_level() >= 2 {
+                        contains_errors = true;
+                        format!("{}", value_to_print.red())
+                    } else {
+                        value_to_print
+                    }
+                })
+                .collect::<Vec<_>>();
+            tw.write(format!("{}\n", cells.join("\t")).as_bytes())
+                .unwrap_or_default();
         }
-        Ok(new_order)
-    }
-
-    /// Validate all of the data in the given database table
-    pub async fn validate_table(&self, table: &Table) -> Result<()> {
-        tracing::trace!("Relatable::validate_table({self:?}, {table:?})");
-
-        // Reconnect and begin a transaction:
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
-
-        self._validate_table(table, &mut tx)?;
-
-        // Commit the transaction
-        tx.commit()?;
-
-        tracing::info!("Validated table '{}'", table.name);
-        Ok(())
+        tw.flush().expect("TabWriter to flush");
+        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
+        written
     }
+}
 
-    /// Validate all of the data in the given database table using the given transaction
-    fn _validate_table(&self, table: &Table, tx: &mut DbTransaction<'_>) -> Result<()> {
-        tracing::trace!("Relatable::_validate_table({self:?}, {table:?}, tx)");
-
-        // Validate each table column
-        for (_, column) in table.columns.iter() {
-            self._validate_column_optionally_for_row(column, None, tx)?;
+impl std::fmt::Display for ResultSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tw = TabWriter::new(vec![]);
+        tw.write(format!("{}\n", self.range).as_bytes())
+            .unwrap_or_default();
+        let header = &self
+            .columns
+            .iter()
+            .map(|c| c.name.clone())
+            .collect::<Vec<String>>();
+        tw.write(format!("{}\n", header.join("\t")).as_bytes())
+            .unwrap_or_default();
+        for row in &self.rows {
+            tw.write(format!("{}\n", row.to_strings().join("\t")).as_bytes())
+                .unwrap_or_default();
         }
-
-        tracing::debug!("Validated table '{}'", table.name);
-        Ok(())
+        tw.flush().expect("TabWriter to flush");
+        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
+        write!(f, "{written}")
     }
+}
 
-    /// Do datatype validation on all of the data in the given database table
-    pub async fn validate_datatype_for_table(&self, table: &Table) -> Result<()> {
-        tracing::trace!("Relatable::validate_datatype_for_table({self:?}, {table:?})");
+// Web Site Stuff
 
-        // Reconnect and begin a transaction:
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Site {
+    pub title: String,
+    pub root: String,
+    pub editable: bool,
+    pub user: Account,
+    pub users: IndexMap<String, UserCursor>,
+    pub tables: Vec<String>,
+}
 
-        self._validate_datatype_for_table(table, &mut tx)?;
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Account {
+    name: String,
+    color: String,
+}
 
-        // Commit the transaction
-        tx.commit()?;
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Cursor {
+    table: String,
+    row: u64,
+    column: String,
+}
 
-        tracing::info!("Validated datatype for table '{}'", table.name);
-        Ok(())
-    }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserCursor {
+    name: String,
+    color: String,
+    cursor: Cursor,
+    datetime: String,
+}
 
-    /// Do datatype validation on all of the data in the given table using the given database
-    /// transaction
-    fn _validate_datatype_for_table(
-        &self,
-        table: &Table,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!("Relatable::_validate_datatype_for_table({self:?}, {table:?}, tx)");
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Page {
+    pub path: String,
+    pub formats: IndexMap<String, String>,
+    pub tabs: Vec<Tab>,
+    /// The URL of the previous page of results, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<String>,
+    /// The URL of the next page of results, if there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<String>,
+}
 
-        // Validate each table column
-        for (_, column) in table.columns.iter() {
-            self._validate_datatype_for_column_and_optionally_for_row(column, None, tx)?;
-        }
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tab {
+    pub table: String,
+    pub active: bool,
+    pub url: String,
+    pub count: String,
+}
 
-        tracing::debug!("Validated datatype for table '{}'", table.name);
-        Ok(())
+#[cfg(test)]
+pub(crate) mod tests {
+    use async_std::task::block_on;
+    use pretty_assertions::assert_eq;
+    use rltbl::{sql::MEMORY_DB, table::generate_uuid};
+
+    use super::*;
+
+    /// Build a demonstration database, in memory and without caching, with the given number of
+    /// rows in its penguin table
+    pub(crate) fn memory_demo(size: usize) -> Relatable {
+        block_on(Relatable::build_demo(
+            Some(MEMORY_DB),
+            &true,
+            size,
+            &CachingStrategy::None,
+        ))
+        .unwrap()
     }
 
-    /// Do structure validation on all of the data in the given database table
-    pub async fn validate_structure_for_table(&self, table: &Table) -> Result<()> {
-        tracing::trace!("Relatable::validate_structure_for_table({self:?}, {table:?})");
-
-        // Reconnect and begin a transaction:
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
-
-        self._validate_structure_for_table(table, &mut tx)?;
-
-        // Commit the transaction
-        tx.commit()?;
-
-        tracing::info!("Validated structure for table '{}'", table.name);
-        Ok(())
-    }
+    #[test]
+    fn test_set_values_with_messages() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
 
-    /// Do structure validation on all of the data in the given database table using the given
-    /// database transation
-    fn _validate_structure_for_table(
-        &self,
-        table: &Table,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!("Relatable::_validate_structure_for_table({self:?}, {table:?}, tx)");
+        let rltbl = memory_demo(3);
 
-        // Validate each table column
-        for (_, column) in table.columns.iter() {
-            self._validate_structure_for_column_and_optionally_for_row(column, None, tx)?;
-        }
+        // Every row of the demo penguin table has the same study name:
+        let sql = r#"INSERT INTO "column" ("table", "column", "structure")
+                     VALUES ('penguin', 'study_name', 'unique')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        block_on(rltbl.validate_table(&table)).unwrap();
 
-        tracing::debug!("Validated structure for table '{}'", table.name);
-        Ok(())
+        // Changing the study name of row 1 removes its message and changes those of rows 2 and 3,
+        // which are still duplicates of each other:
+        let changeset = ChangeSet {
+            user: "rltbl".into(),
+            action: ChangeAction::Do,
+            table: "penguin".into(),
+            description: "Set study name".into(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 1,
+                column: "study_name".into(),
+                before: json!("FAKE123"),
+                after: json!("FAKE456"),
+            }],
+        };
+        let (changeset, cell_messages) =
+            block_on(rltbl.set_values_with_messages(&changeset)).unwrap();
+        assert_eq!(changeset.changes.len(), 1);
+        let cell_messages = cell_messages
+            .iter()
+            .map(|cell| {
+                let messages = cell
+                    .messages
+                    .iter()
+                    .map(|message| message.message.as_str())
+                    .collect::<Vec<_>>();
+                format!("{} {}: {}", cell.row, cell.column, messages.join(", "))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            cell_messages,
+            [
+                "1 study_name: ",
+                "2 study_name: study_name must be unique but 'FAKE123' is also used by row(s) 3 \
+                 (see table/penguin?_id=in.(3))",
+                "3 study_name: study_name must be unique but 'FAKE123' is also used by row(s) 2 \
+                 (see table/penguin?_id=in.(2))",
+            ]
+        );
     }
 
-    /// Validate the data in the given column associated with a table in the database
-    pub async fn validate_column(&self, column: &Column) -> Result<()> {
-        tracing::trace!("Relatable::validate_column({self:?}, {column:?})");
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
-        self._validate_column_optionally_for_row(column, None, &mut tx)?;
-        tx.commit()?;
-        tracing::info!("Validated column '{}.{}'", column.table, column.name);
-        Ok(())
-    }
+    #[test]
+    fn test_revalidate_for_datatype() {
+        let rltbl = memory_demo(3);
+        let sql = r#"INSERT INTO "datatype" ("datatype", "parent", "condition")
+                     VALUES ('main_study', 'study_name', 'equals(FAKE123)')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "column" SET "datatype" = 'main_study'
+                     WHERE "table" = 'penguin' AND "column" = 'study_name'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        let get_names = |columns: Vec<Column>| {
+            columns
+                .iter()
+                .map(|column| format!("{}.{}", column.table, column.name))
+                .collect::<Vec<_>>()
+        };
+        let get_rules = || {
+            let sql = r#"SELECT "row", "column", "rule" FROM "message" ORDER BY "row""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
 
-    /// Validate the value of the given column in the given row in the associated database
-    /// table
-    pub async fn validate_value(&self, column: &Column, row: &u64) -> Result<()> {
-        tracing::trace!("Relatable::validate_value({self:?}, {column:?}, {row})");
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
-        self._validate_column_optionally_for_row(column, Some(row), &mut tx)?;
-        tx.commit()?;
-        tracing::info!(
-            "Validated value at row {}, column '{}.{}'",
-            row,
-            column.table,
-            column.name
+        // Changing the condition of a datatype affects the columns that use it, either directly
+        // or through one of its descendants, and nothing else:
+        let sql = r#"UPDATE "datatype" SET "condition" = 'in(FAKE123, FAKE789)'
+                     WHERE "datatype" = 'study_name'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "penguin" SET "study_name" = 'FAKE456' WHERE "_id" = 2"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let columns = block_on(rltbl.revalidate_for_datatype("study_name")).unwrap();
+        assert_eq!(get_names(columns), ["penguin.study_name"]);
+        assert_eq!(get_rules(), [["2", "study_name", "datatype:study_name"]]);
+
+        let columns = block_on(rltbl.revalidate_for_datatype("decimal")).unwrap();
+        assert_eq!(
+            get_names(columns),
+            ["penguin.bill_length", "penguin.bill_depth"]
         );
-        Ok(())
-    }
-
-    /// Validate the given row of the given table
-    pub async fn validate_row(&self, table: &Table, row: &u64) -> Result<()> {
-        tracing::trace!("Relatable::validate_row({self:?}, {table:?}, {row})");
-        let mut conn = self.connection.reconnect()?;
-        let mut tx = self.connection.begin(&mut conn).await?;
-        self._validate_row(table, row, &mut tx)?;
-        tx.commit()?;
-        tracing::info!("Validated row {} of table '{}'", row, table.name);
-        Ok(())
+        let columns = block_on(rltbl.revalidate_for_datatype("nonexistent")).unwrap();
+        assert_eq!(get_names(columns), Vec::<String>::new());
     }
 
-    /// Validate the given row of the given table using the given database transaction
-    fn _validate_row(&self, table: &Table, row: &u64, tx: &mut DbTransaction<'_>) -> Result<()> {
-        tracing::trace!("Relatable::_validate_row({self:?}, {table:?}, {row}, tx)");
-        for (_, column) in table.columns.iter() {
-            self._validate_column_optionally_for_row(column, Some(row), tx)?;
-        }
-        tracing::debug!("Validated row {} of table '{}'", row, table.name);
-        Ok(())
-    }
+    #[test]
+    fn test_change_groups() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
 
-    /// Validate the datatype of the given column in its associated database table using the
-    /// given transaction. If `row` is given, only validate the column for that row.
-    fn _validate_datatype_for_column_and_optionally_for_row(
-        &self,
-        column: &Column,
-        row: Option<&u64>,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!(
-            "Relatable::_validate_datatype_for_column_and_optionally_for_row(\
-             {self:?}, {column:?}, {row:?}, tx)"
-        );
+        let rltbl = memory_demo(3);
 
-        let table_name = column.table.as_str();
+        let update = |table: &str, row: u64, column: &str, before: &str, after: &str| ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: table.to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row,
+                column: column.to_string(),
+                before: json!(before),
+                after: json!(after),
+            }],
+        };
+        let get_values = || {
+            let sql = r#"SELECT "species" FROM "penguin" WHERE "_id" = 1
+                         UNION ALL
+                         SELECT "island" FROM "island" WHERE "_id" = 3"#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join(""))
+                .collect::<Vec<_>>()
+        };
+        let before = get_values();
+
+        let changesets = block_on(rltbl.set_values_in_group(&vec![
+            update("penguin", 1, "species", &before[0], "Pygoscelis papua"),
+            update("island", 3, "island", &before[1], "Dream Island"),
+        ]))
+        .unwrap();
+        assert_eq!(changesets.len(), 2);
+        let after = vec!["Pygoscelis papua".to_string(), "Dream Island".to_string()];
+        assert_eq!(get_values(), after);
+        assert_eq!(block_on(rltbl.get_change_group(1)).unwrap(), Some(1));
+        assert_eq!(block_on(rltbl.get_change_group(2)).unwrap(), Some(1));
+
+        // A single undo reverts both tables, and a single redo reapplies both:
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(get_values(), before);
+        block_on(rltbl.redo("rltbl")).unwrap();
+        assert_eq!(get_values(), after);
+        assert!(block_on(rltbl.undo("rltbl")).unwrap().is_some());
+        assert_eq!(get_values(), before);
+        assert!(block_on(rltbl.undo("rltbl")).unwrap().is_none());
+
+        // A group that fails part of the way through changes nothing:
+        assert!(block_on(rltbl.set_values_in_group(&vec![
+            update("penguin", 1, "species", &before[0], "Pygoscelis papua"),
+            update("no_such_table", 1, "species", "", "Pygoscelis papua"),
+        ]))
+        .is_err());
+        assert_eq!(get_values(), before);
+    }
 
-        // Delete pre-existing datatype validation messages for this column and then
-        // validate the datatype conditions for each datatype in the column's datatype hierarchy.
-        self._delete_message(
-            tx,
-            table_name,
-            row.copied(),
-            Some(&column.name),
-            Some("datatype:%"),
-            Some("rltbl"),
-        )?;
+    #[test]
+    fn test_preview_undo() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
 
-        // Gather the datatypes to check: The column's datatype, plus any further datatypes in
-        // the datatype hierarchy:
-        let mut datatypes_to_check = vec![column.datatype.clone()];
-        datatypes_to_check.append(&mut column.datatype_hierarchy.clone());
+        let rltbl = memory_demo(3);
+        assert!(block_on(rltbl.preview_undo("rltbl")).unwrap().is_empty());
 
-        // Validate the column against each datatype in the hierarchy:
-        for datatype in datatypes_to_check {
-            let inserted = datatype.validate(column, row, tx)?;
-            if !inserted {
-                break;
-            }
-        }
+        let update = |row: u64, before: &str, after: &str| ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "island".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row,
+                column: "island".to_string(),
+                before: json!(before),
+                after: json!(after),
+            }],
+        };
+        let get_islands = || {
+            let sql = r#"SELECT "island" FROM "island" ORDER BY "_id""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.get_string("island").unwrap())
+                .collect::<Vec<_>>()
+        };
+        let before = get_islands();
+        block_on(rltbl.set_values(&update(1, &before[0], "Biscoe Island"))).unwrap();
+        block_on(rltbl.set_values_in_group(&vec![
+            update(2, &before[1], "Dream Island"),
+            update(3, &before[2], "Torgersen Island"),
+        ]))
+        .unwrap();
+        let after = get_islands();
+
+        // Both of the changes in the last group would be undone, last first, and nothing is:
+        let preview = block_on(rltbl.preview_undo("rltbl")).unwrap();
+        assert_eq!(get_islands(), after);
+        assert_eq!(preview.len(), 2);
+        assert!(preview
+            .iter()
+            .all(|changeset| changeset.action == ChangeAction::Undo));
+        assert_eq!(
+            preview[0].changes,
+            vec![Change::Update {
+                row: 3,
+                column: "island".to_string(),
+                before: json!("Torgersen Island"),
+                after: json!(before[2]),
+            }]
+        );
+        assert_eq!(
+            preview[1].changes,
+            update(2, "Dream Island", &before[1]).changes
+        );
 
-        tracing::debug!(
-            "Validated datatype for column: '{}.{}'{}",
-            column.table,
-            column.name,
-            match row {
-                None => "".to_string(),
-                Some(row) => format!(", row: {row}"),
-            }
+        // Once the group is undone, only the first change remains:
+        block_on(rltbl.undo("rltbl")).unwrap();
+        let preview = block_on(rltbl.preview_undo("rltbl")).unwrap();
+        assert_eq!(preview.len(), 1);
+        assert_eq!(
+            preview[0].changes,
+            update(1, "Biscoe Island", &before[0]).changes
         );
-        Ok(())
+
+        let add = Change::Add { row: 4, after: 3 };
+        assert_eq!(add.reverse(), Change::Delete { row: 4, after: 3 });
+        assert_eq!(add.reverse().reverse(), add);
     }
 
-    /// Validate the structure of the given column in its associated database table using the
-    /// given transaction. If `row` is given, only validate the column for that row.
-    fn _validate_structure_for_column_and_optionally_for_row(
-        &self,
-        column: &Column,
-        row: Option<&u64>,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!(
-            "Relatable::_validate_structure_for_column_and_optionally_for_row(\
-             {self:?}, {column:?}, {row:?}, tx)"
+    #[test]
+    fn test_column_stats() {
+        let rltbl = memory_demo(10);
+        let stats = block_on(rltbl.column_stats("penguin")).unwrap();
+        let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        assert_eq!(
+            stats.iter().map(|s| s.column.as_str()).collect::<Vec<_>>(),
+            table.columns.keys().map(|c| c.as_str()).collect::<Vec<_>>()
         );
 
-        let table_name = column.table.as_str();
+        let get_stats = |column: &str| stats.iter().find(|s| s.column == column).unwrap();
+        let sample_number = get_stats("sample_number");
+        assert_eq!(sample_number.min, json!(1));
+        assert_eq!(sample_number.max, json!(10));
+        assert_eq!(sample_number.mean, Some(5.5));
+        assert_eq!(sample_number.null_count, 0);
+        assert_eq!(sample_number.distinct_count, 10);
+
+        // Only numeric columns have a mean:
+        let island = get_stats("island");
+        let sql = r#"SELECT COUNT(DISTINCT "island") FROM "penguin""#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(island.distinct_count))
+        );
+        assert_eq!(island.mean, None);
 
-        // Delete pre-existing structure validation messages for this column and then re-validate
-        // the structure condition for this column and (optionally) row:
-        self._delete_message(
-            tx,
-            table_name,
-            row.copied(),
-            Some(&column.name),
-            Some("key:%"),
-            Some("rltbl"),
-        )?;
+        let sql = r#"UPDATE "penguin" SET "island" = NULL WHERE "_id" <= 3"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let stats = block_on(rltbl.column_stats("penguin")).unwrap();
+        let island = stats.iter().find(|s| s.column == "island").unwrap();
+        assert_eq!(island.null_count, 3);
 
-        // Validate the cell's structure condition:
-        if let Some(structure) = &column.structure {
-            structure.validate(column, row, tx)?;
-        }
+        assert!(block_on(rltbl.column_stats("no_such_table")).is_err());
+    }
 
-        tracing::debug!(
-            "Validated structure for column: '{}.{}'{}",
-            column.table,
-            column.name,
-            match row {
-                None => "".to_string(),
-                Some(row) => format!(", row: {row}"),
-            }
+    #[test]
+    fn test_fill_down() {
+        use rltbl::core::fill_series;
+
+        let rltbl = memory_demo(5);
+        let get_values = |column: &str| {
+            let sql = format!(r#"SELECT "{column}" FROM "penguin" ORDER BY "_order""#);
+            block_on(rltbl.connection.query(&sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join(""))
+                .collect::<Vec<_>>()
+        };
+
+        // The first value is copied to the others, as one change:
+        let islands = get_values("island");
+        let changeset =
+            block_on(rltbl.fill_down("penguin", "rltbl", "island", 2, 4, false)).unwrap();
+        let filled = get_values("island");
+        assert_eq!(filled[0], islands[0]);
+        assert_eq!(filled[1..4], vec![islands[1].clone(); 3]);
+        assert_eq!(filled[4], islands[4]);
+        assert!(changeset.changes.len() <= 2);
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(get_values("island"), islands);
+
+        // A series is extended by the difference between its first two values:
+        block_on(rltbl.fill_down("penguin", "rltbl", "sample_number", 2, 5, false)).unwrap();
+        assert_eq!(get_values("sample_number"), ["1", "2", "2", "2", "2"]);
+        let changeset =
+            block_on(rltbl.fill_down("penguin", "rltbl", "sample_number", 1, 5, true)).unwrap();
+        assert_eq!(get_values("sample_number"), ["1", "2", "3", "4", "5"]);
+        assert_eq!(changeset.changes.len(), 3);
+
+        // The range must be in the table's order:
+        assert!(block_on(rltbl.fill_down("penguin", "rltbl", "island", 4, 2, false)).is_err());
+        assert!(block_on(rltbl.fill_down("penguin", "rltbl", "island", 1, 3, true)).is_err());
+
+        let series = |first: JsonValue, second: Option<JsonValue>| {
+            fill_series(&first, second.as_ref(), 3).unwrap()
+        };
+        assert_eq!(series(json!(7), None), [json!(7), json!(8), json!(9)]);
+        assert_eq!(
+            series(json!("0.1"), Some(json!("0.25"))),
+            [json!(0.1), json!(0.25), json!(0.4)]
+        );
+        assert_eq!(
+            series(json!("2024-02-28"), None),
+            [
+                json!("2024-02-28"),
+                json!("2024-02-29"),
+                json!("2024-03-01")
+            ]
+        );
+        assert_eq!(
+            series(json!("2024-01-01"), Some(json!("2024-01-08"))),
+            [
+                json!("2024-01-01"),
+                json!("2024-01-08"),
+                json!("2024-01-15")
+            ]
         );
-        Ok(())
     }
 
-    /// Validate the given column in its associated database table using the given transaction.
-    /// If `row` is given, only validate the column for that row.
-    fn _validate_column_optionally_for_row(
-        &self,
-        column: &Column,
-        row: Option<&u64>,
-        tx: &mut DbTransaction<'_>,
-    ) -> Result<()> {
-        tracing::trace!(
-            "Relatable::_validate_column_optionally_for_row({self:?}, {column:?}, {row:?}, tx)"
+    #[test]
+    fn test_paste() {
+        let rltbl = memory_demo(5);
+        let get_values = |column: &str| {
+            let sql = format!(r#"SELECT "{column}" FROM "penguin" ORDER BY "_order""#);
+            block_on(rltbl.connection.query(&sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join(""))
+                .collect::<Vec<_>>()
+        };
+
+        // The block is mapped onto the columns and rows that follow its anchor, its values are
+        // converted to the types of their columns, and it is applied as one change group:
+        let species = get_values("species");
+        let cells = block_on(rltbl.paste(
+            "penguin",
+            "rltbl",
+            2,
+            "sample_number",
+            "12\tFoo\r\nbar\tBaz\r\n",
+        ))
+        .unwrap();
+        assert_eq!(cells.len(), 4);
+        assert_eq!(
+            cells
+                .iter()
+                .map(|cell| (cell.row, cell.column.as_str(), cell.after.clone()))
+                .collect::<Vec<_>>(),
+            [
+                (2, "sample_number", json!(12)),
+                (2, "species", json!("Foo")),
+                (3, "sample_number", json!("bar")),
+                (3, "species", json!("Baz")),
+            ]
         );
-        self._validate_datatype_for_column_and_optionally_for_row(column, row, tx)?;
-        self._validate_structure_for_column_and_optionally_for_row(column, row, tx)?;
-        tracing::debug!(
-            "Validated column: '{}.{}'{}",
-            column.table,
-            column.name,
-            match row {
-                None => "".to_string(),
-                Some(row) => format!(", row: {row}"),
+        assert!(cells[0].messages.is_empty() && cells[0].changed);
+        assert_eq!(cells[2].messages[0].rule, "sql_type:integer");
+        assert_eq!(get_values("sample_number"), ["1", "12", "", "4", "5"]);
+        assert_eq!(get_values("species")[1..3], ["Foo", "Baz"]);
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(get_values("sample_number"), ["1", "2", "3", "4", "5"]);
+        assert_eq!(get_values("species"), species);
+
+        // The block must be rectangular and must fit within the table:
+        assert!(block_on(rltbl.paste("penguin", "rltbl", 1, "species", "a\tb\nc")).is_err());
+        assert!(block_on(rltbl.paste("penguin", "rltbl", 5, "species", "a\nb")).is_err());
+        assert!(block_on(rltbl.paste("penguin", "rltbl", 1, "body_mass", "1\t2")).is_err());
+        assert!(block_on(rltbl.paste("penguin", "rltbl", 1, "nothing", "a")).is_err());
+    }
+
+    #[test]
+    fn test_idempotent() {
+        let rltbl = memory_demo(5);
+        let count = || {
+            let sql = r#"SELECT COUNT(1) FROM "penguin""#;
+            block_on(rltbl.connection.query_value(sql, None))
+                .unwrap()
+                .unwrap()
+        };
+        let row = JsonRow {
+            content: json!({"study_name": "FAKE123"})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let add_row = || rltbl.add_row("penguin", "rltbl", None, &row);
+
+        // An operation is run once under a given key, and is not run again when retried:
+        let added = block_on(rltbl.idempotent("rltbl", "abc", add_row)).unwrap();
+        assert!(added.is_some());
+        assert_eq!(count(), json!(6));
+        let retried = block_on(rltbl.idempotent("rltbl", "abc", add_row)).unwrap();
+        assert!(retried.is_none());
+        assert_eq!(count(), json!(6));
+        let changesets = block_on(rltbl.get_idempotent_changes("rltbl", "abc")).unwrap();
+        assert_eq!(changesets.len(), 1);
+        assert_eq!(changesets[0].table, "penguin");
+
+        // Keys are not shared between users, or between different keys:
+        assert!(block_on(rltbl.get_idempotent_changes("other", "abc"))
+            .unwrap()
+            .is_empty());
+        block_on(rltbl.idempotent("rltbl", "def", add_row))
+            .unwrap()
+            .unwrap();
+        assert_eq!(count(), json!(7));
+
+        // An operation that makes no changes records nothing, so it can be run again:
+        let none = block_on(rltbl.idempotent("rltbl", "ghi", || async { Ok(()) })).unwrap();
+        assert!(none.is_some());
+        assert!(block_on(rltbl.get_idempotent_changes("rltbl", "ghi"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_sandbox() {
+        use rltbl::core::BatchOperation;
+
+        let rltbl = memory_demo(3);
+        let operations =
+            BatchOperation::many_from_str("set value penguin 2 sample_number two").unwrap();
+        block_on(rltbl.batch("rltbl", None, &operations)).unwrap();
+        // Invalid values are stored as NULLs, and appear only in their messages:
+        let get_rows = |rltbl: &Relatable| {
+            let sql = r#"SELECT "_id", "sample_number" FROM "penguin" ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join(" "))
+                .collect::<Vec<_>>()
+        };
+        let count_messages = |rltbl: &Relatable| {
+            let sql = r#"SELECT COUNT(1) FROM "message""#;
+            block_on(rltbl.connection.query_value(sql, None))
+                .unwrap()
+                .unwrap()
+        };
+        let before = get_rows(&rltbl);
+        assert_eq!(before, ["1 1", "2 ", "3 3"]);
+        assert_eq!(count_messages(&rltbl), json!(1));
+
+        // Edits to the sandbox do not affect the original database:
+        let operations = BatchOperation::many_from_str(
+            r#"set value penguin 1 sample_number one
+               set value penguin 2 sample_number 2"#,
+        )
+        .unwrap();
+        let mut sandbox = block_on(rltbl.sandbox()).unwrap();
+        block_on(sandbox.batch("rltbl", Some("Try it"), &operations)).unwrap();
+        assert_eq!(get_rows(&sandbox.rltbl), ["1 ", "2 2", "3 3"]);
+        assert_eq!(get_rows(&rltbl), before);
+        assert_eq!(count_messages(&rltbl), json!(1));
+
+        // The delta shows the messages that the edits added and removed:
+        let delta = block_on(sandbox.message_delta()).unwrap();
+        let summarize = |messages: &Vec<serde_json::Map<String, JsonValue>>| {
+            messages
+                .iter()
+                .map(|message| {
+                    format!(
+                        "{} {} {}",
+                        message["row"], message["column"], message["value"]
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(summarize(&delta.added), [r#"1 "sample_number" "one""#]);
+        assert_eq!(summarize(&delta.removed), [r#"2 "sample_number" "two""#]);
+
+        // A discarded sandbox leaves the original database as it was:
+        sandbox.discard();
+        assert_eq!(get_rows(&rltbl), before);
+
+        // Replaying the edits applies them to the original database:
+        let mut sandbox = block_on(rltbl.sandbox()).unwrap();
+        block_on(sandbox.batch("rltbl", Some("Try it"), &operations)).unwrap();
+        let change_groups = block_on(sandbox.replay(&rltbl)).unwrap();
+        assert_eq!(change_groups.len(), 1);
+        assert_eq!(get_rows(&rltbl), ["1 ", "2 2", "3 3"]);
+        assert_eq!(count_messages(&rltbl), json!(1));
+    }
+
+    #[test]
+    fn test_batch() {
+        use rltbl::core::BatchOperation;
+
+        let rltbl = memory_demo(3);
+        let get_rows = || {
+            let sql = r#"SELECT "_id", "island" FROM "penguin" ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join(" "))
+                .collect::<Vec<_>>()
+        };
+        let before = get_rows();
+
+        let operations = BatchOperation::many_from_str(
+            r#"# Commands are written as they would be on the command line:
+               set value penguin 1 island "Dream Island"
+               add row --after-id 1 penguin '{"study_name": "FAKE456", "island": "Biscoe"}'
+
+               move row penguin 3 1
+               delete row penguin 2"#,
+        )
+        .unwrap();
+        assert_eq!(operations.len(), 4);
+        assert_eq!(
+            operations[0],
+            BatchOperation::SetValue {
+                table: "penguin".to_string(),
+                row: 1,
+                column: "island".to_string(),
+                value: json!("Dream Island"),
             }
         );
-        Ok(())
+        let change_group = block_on(rltbl.batch("rltbl", Some("Bulk edit"), &operations)).unwrap();
+        assert_eq!(change_group, Some(1));
+        let after = vec!["1 Dream Island", "3 Torgersen", "4 Biscoe"];
+        assert_eq!(get_rows(), after);
+
+        // If any operation fails, none of them are applied:
+        let operations = BatchOperation::many_from_str(
+            r#"[{"operation": "set_value", "table": "penguin", "row": 1, "column": "island",
+                 "value": "Biscoe"},
+                {"operation": "delete_row", "table": "penguin", "row": 99}]"#,
+        )
+        .unwrap();
+        assert!(block_on(rltbl.batch("rltbl", None, &operations)).is_err());
+        assert_eq!(get_rows(), after);
+
+        // The whole batch is undone at once:
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(get_rows(), before);
+        assert!(block_on(rltbl.undo("rltbl")).unwrap().is_none());
+
+        assert!(BatchOperation::from_command("set value penguin one island Dream").is_err());
+        assert!(BatchOperation::from_command("drop table penguin").is_err());
     }
 
-    /// Delete all entries from the cache corresponding to the given table, or clear it completely
-    /// if no table is given.
-    pub(crate) fn clear_cache(tx: &mut DbTransaction<'_>, table: Option<&str>) -> Result<()> {
-        let mut sql = r#"DELETE FROM "cache""#.to_string();
-        if let Some(table) = table {
-            let mut table = table.to_string();
-            tracing::debug!("Deleting entries for table '{table}' from cache");
-            match tx.kind() {
-                DbKind::Postgres => {
-                    // Note that the '?' is *not* being used as a parameter placeholder here
-                    // but a JSONB operator.
-                    sql.push_str(&format!(
-                        r#" WHERE "tables" ? {}"#,
-                        SqlParam::new(&tx.kind()).next()
-                    ));
-                }
-                DbKind::Sqlite => {
-                    sql.push_str(&format!(
-                        r#" WHERE "tables" LIKE {}"#,
-                        SqlParam::new(&tx.kind()).next()
-                    ));
-                    table = format!(r#"%"{table}"%"#);
-                }
-            };
-            let params = json!([table]);
-            tx.query(&sql, Some(&params))?;
-        } else {
-            tracing::debug!("Truncating cache");
-            tx.query(&sql, None)?;
+    #[test]
+    fn test_load_meta_table() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+
+        let rltbl = memory_demo(5);
+        let set_value = ChangeSet {
+            user: "alice".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 5,
+                column: "island".to_string(),
+                before: json!("Torgersen"),
+                after: json!("Dream"),
+            }],
+        };
+        block_on(rltbl.set_values(&set_value)).unwrap();
+
+        // History cannot be loaded before the changes that it refers to:
+        assert!(block_on(rltbl.load_meta_table("history", "test/fixtures/history.tsv")).is_err());
+
+        // The change and history fixtures are remapped to follow the existing change:
+        for (table, count) in [("change", 3), ("history", 3), ("message", 2)] {
+            let path = format!("test/fixtures/{table}.tsv");
+            assert_eq!(
+                block_on(rltbl.load_meta_table(table, &path)).unwrap(),
+                count
+            );
         }
+        let sql =
+            r#"SELECT "change_id", "user", "change_group" FROM "change" ORDER BY "change_id""#;
+        let changes = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, ["1 alice ", "2 alice ", "3 bob 3", "4 bob 3"]);
+        let sql = r#"SELECT "change_id", "row" FROM "history" ORDER BY "history_id""#;
+        let history = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join(" "))
+            .collect::<Vec<_>>();
+        assert_eq!(history, ["1 5", "2 1", "3 2", "4 2"]);
+        let sql = r#"SELECT COUNT(1) FROM "message" WHERE "_hash" IS NOT NULL"#;
+        let count = block_on(rltbl.connection.query_value(sql, None)).unwrap();
+        assert_eq!(count, Some(json!(2)));
+
+        // The same history cannot be loaded twice:
+        assert!(block_on(rltbl.load_meta_table("history", "test/fixtures/history.tsv")).is_err());
+
+        // The loaded history can be undone like any other:
+        let changeset = block_on(rltbl.undo("bob")).unwrap().unwrap();
+        assert_eq!(changeset.message, Some("Reclassify".to_string()));
+        let sql = r#"SELECT "species" FROM "penguin" WHERE "_id" = 2"#;
+        let species = block_on(rltbl.connection.query_value(sql, None)).unwrap();
+        assert_eq!(species, Some(json!("Pygoscelis papua")));
+
+        assert!(block_on(rltbl.load_meta_table("user", "test/fixtures/change.tsv")).is_err());
+        assert!(block_on(rltbl.load_meta_table("history", "test/fixtures/message.tsv")).is_err());
+    }
 
-        Ok(())
+    #[test]
+    fn test_row_templates() {
+        let rltbl = memory_demo(3);
+        let json_row = |value: JsonValue| JsonRow {
+            content: value.as_object().unwrap().clone(),
+        };
+
+        let defaults = json_row(json!({"study_name": "BREED1", "island": "Dream"}));
+        block_on(rltbl.set_template("penguin", "breeding-season", &defaults)).unwrap();
+        let templates = block_on(rltbl.get_templates("penguin")).unwrap();
+        assert_eq!(templates.keys().collect::<Vec<_>>(), ["breeding-season"]);
+        assert!(block_on(rltbl.get_templates("island")).unwrap().is_empty());
+
+        // Given values take precedence over the template's, but empty values do not:
+        let row = json_row(json!({"sample_number": 42, "island": ""}));
+        let row = block_on(rltbl.apply_template("penguin", "breeding-season", &row)).unwrap();
+        assert_eq!(
+            row.content,
+            json_row(json!({"sample_number": 42, "island": "Dream", "study_name": "BREED1"}))
+                .content
+        );
+        let row = block_on(rltbl.add_row("penguin", "rltbl", None, &row)).unwrap();
+        let sql = format!(
+            r#"SELECT "study_name", "island" FROM "penguin" WHERE "_id" = {}"#,
+            row.id
+        );
+        let values = block_on(rltbl.connection.query_one(&sql, None))
+            .unwrap()
+            .unwrap()
+            .to_strings();
+        assert_eq!(values, ["BREED1", "Dream"]);
+
+        // Saving a template again replaces it:
+        let defaults = json_row(json!({"island": "Biscoe"}));
+        block_on(rltbl.set_template("penguin", "breeding-season", &defaults)).unwrap();
+        let templates = block_on(rltbl.get_templates("penguin")).unwrap();
+        assert_eq!(templates["breeding-season"].content, defaults.content);
+
+        assert!(block_on(rltbl.apply_template("penguin", "moulting", &defaults)).is_err());
+        let defaults = json_row(json!({"no_such_column": "x"}));
+        assert!(block_on(rltbl.set_template("penguin", "bad", &defaults)).is_err());
     }
 
-    /// Delete all entries from the in-memory cache corresponding to the given table
-    pub(crate) fn clear_mem_cache(&self, table: &str) {
-        let table = format!("\"{table}\"");
-        let mut cache = CACHE.lock().expect("Could not lock cache");
-        let keys = cache
-            .keys()
-            .map(|k| k)
-            .cloned()
-            .collect::<HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-        for key in keys.iter() {
-            if key.tables.contains(&table) {
-                tracing::debug!("Removing {key:?} from cache");
-                cache.remove(key);
-            }
-        }
+    #[test]
+    fn test_materialized_change_id() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+        use rltbl::select::Select;
+
+        let mut rltbl = memory_demo(3);
+        rltbl.materialize_change_id = true;
+        block_on(rltbl.load_table("study", "test/round_trip/penguin.tsv", true, None));
+        let table = block_on(Table::get_table("study", &rltbl)).unwrap();
+        assert!(table.has_change_id);
+
+        let changeset = block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "study".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 2,
+                column: "species".to_string(),
+                before: json!("Pygoscelis adeliae"),
+                after: json!("Pygoscelis papua"),
+            }],
+        }))
+        .unwrap();
+        assert_eq!(changeset.changes.len(), 1);
+
+        // The trigger on the history table should have set the column for the updated row only:
+        let sql = r#"SELECT "_id", "_change_id" FROM "study" WHERE "_change_id" IS NOT NULL"#;
+        let rows = block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].to_strings(), vec!["2", "1"]);
+
+        // Select should use the materialized column rather than the history subquery:
+        let mut select = Select::from("study");
+        select.eq("_change_id", &1).unwrap();
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert!(!result.statement.contains("history"));
+        assert_eq!(result.rows.len(), 1);
     }
-}
 
-// Validation
+    #[test]
+    fn test_uuid_row_ids() {
+        use rltbl::select::Select;
+        use std::collections::HashSet;
+
+        let mut rltbl = memory_demo(3);
+        rltbl.uuid_row_ids = true;
+        block_on(rltbl.load_table("study", "test/round_trip/penguin.tsv", true, None));
+        let table = block_on(Table::get_table("study", &rltbl)).unwrap();
+        assert!(table.has_uuid);
+
+        // Loaded rows are given distinct UUIDs by the database:
+        let uuid_pattern = regex::Regex::new(
+            r"^[0-9a-f]{8}-[0-9a-f]{4}-4[0-9a-f]{3}-[89ab][0-9a-f]{3}-[0-9a-f]{12}$",
+        )
+        .unwrap();
+        let get_uuids = || {
+            let sql = r#"SELECT "_uuid" FROM "study" ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.get_string("_uuid").unwrap())
+                .collect::<Vec<_>>()
+        };
+        let uuids = get_uuids();
+        assert!(uuids.len() > 1);
+        assert!(uuids.iter().all(|uuid| uuid_pattern.is_match(uuid)));
+        assert_eq!(uuids.iter().collect::<HashSet<_>>().len(), uuids.len());
+        assert!(uuid_pattern.is_match(&generate_uuid()));
+
+        // New rows are given a UUID unless they already have one:
+        let merged = "0f0e0d0c-0b0a-4908-8706-050403020100";
+        let row = JsonRow {
+            content: json!({"_uuid": merged, "study_name": "FAKE123"})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let row = block_on(rltbl.add_row("study", "rltbl", None, &row)).unwrap();
+        assert_eq!(row.uuid.as_deref(), Some(merged));
+        let row = JsonRow {
+            content: json!({"study_name": "FAKE123"})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let new_row = block_on(rltbl.add_row("study", "rltbl", None, &row)).unwrap();
+        assert!(uuid_pattern.is_match(new_row.uuid.as_deref().unwrap()));
+
+        // Rows can be looked up by UUID as well as by _id:
+        let merged_id = block_on(Table::get_row_id("study", merged, &rltbl)).unwrap();
+        assert_eq!(
+            block_on(Table::get_row_id("study", &merged_id.to_string(), &rltbl)).unwrap(),
+            merged_id
+        );
+        assert!(block_on(Table::get_row_id("study", &generate_uuid(), &rltbl)).is_err());
+        assert!(block_on(Table::get_row_id("penguin", merged, &rltbl)).is_err());
+
+        // The default view passes the UUID through to selected rows:
+        let rows = block_on(rltbl.fetch(&Select::from("study"))).unwrap().rows;
+        assert_eq!(rows[0].uuid.as_ref(), Some(&uuids[0]));
+
+        // A deleted row keeps its UUID when it is restored:
+        block_on(rltbl.delete_row("study", "rltbl", merged_id)).unwrap();
+        assert!(!get_uuids().contains(&merged.to_string()));
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert!(get_uuids().contains(&merged.to_string()));
+    }
 
-/// The level at which Relatable will perform validation when adding to or modifying data in the
-/// database
-#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
-pub enum ValidationLevel {
-    /// Perform no validateion
-    None,
-    /// Perform only SQL type validation
-    SqlType,
-    /// Perform full validation
-    Full,
-}
+    #[test]
+    fn test_mirrors() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
 
-impl FromStr for ValidationLevel {
-    type Err = anyhow::Error;
+        let remote = memory_demo(3);
+        let local = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        let tables = ["penguin".to_string()];
+        block_on(local.add_mirror("central", "http://localhost:3000", &tables)).unwrap();
+        let get_rows = |rltbl: &Relatable| {
+            let sql = r#"SELECT "_id", "_order", "species", "island" FROM "penguin"
+                         ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+        let get_change_id = || {
+            let sql = r#"SELECT "change_id" FROM "mirror" WHERE "table" = 'penguin'"#;
+            let change_id = block_on(local.connection.query_value(sql, None)).unwrap();
+            sql::json_to_unsigned(&change_id.unwrap()).unwrap()
+        };
 
-    fn from_str(level: &str) -> Result<Self> {
-        tracing::trace!("ValidationLevel::from_str({level:?})");
-        match level.to_lowercase().as_str() {
-            "none" => Ok(ValidationLevel::None),
-            "sql_type" => Ok(ValidationLevel::SqlType),
-            "full" => Ok(ValidationLevel::Full),
-            _ => {
-                return Err(
-                    RelatableError::InputError(format!("Unrecognized level: {level}")).into(),
-                );
-            }
-        }
+        // The first pull copies the whole table:
+        let feed = block_on(remote.get_change_feed("penguin", None)).unwrap();
+        assert_eq!(feed.rows.len(), 3);
+        assert_eq!(
+            block_on(local.apply_change_feed("central", &feed)).unwrap(),
+            3
+        );
+        assert_eq!(get_rows(&local), get_rows(&remote));
+
+        // Subsequent pulls only copy the rows that have changed:
+        block_on(remote.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 1,
+                column: "island".to_string(),
+                before: json!(""),
+                after: json!("Dream"),
+            }],
+        }))
+        .unwrap();
+        block_on(remote.delete_row("penguin", "rltbl", 2)).unwrap();
+        let feed = block_on(remote.get_change_feed("penguin", Some(get_change_id()))).unwrap();
+        assert_eq!(feed.rows.len(), 1);
+        assert_eq!(feed.deleted, [2]);
+        assert_eq!(
+            block_on(local.apply_change_feed("central", &feed)).unwrap(),
+            2
+        );
+        assert_eq!(get_rows(&local), get_rows(&remote));
+        let feed = block_on(remote.get_change_feed("penguin", Some(get_change_id()))).unwrap();
+        assert!(feed.rows.is_empty() && feed.deleted.is_empty());
+
+        // The local copy is read-only, and local tables cannot be mirrored:
+        let table = block_on(Table::get_table("penguin", &local)).unwrap();
+        assert!(!table.editable);
+        assert!(block_on(local.delete_row("penguin", "rltbl", 1)).is_err());
+        assert!(block_on(remote.add_mirror("central", "http://localhost:3000", &tables)).is_err());
     }
-}
 
-// Changes and History
+    #[test]
+    fn test_load_tables() {
+        use rltbl::core::LoadStatus;
+
+        let rltbl = memory_demo(3);
+        let sql = r#"INSERT INTO "column" ("table", "column", "structure")
+                     VALUES ('visit', 'site', 'from(site.site)'),
+                            ('reading', 'visit', 'from(visit.visit)'),
+                            ('orphan', 'reading', 'from(broken.reading)')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let contents = [
+            ("site", "site\tname\ns1\tNorth\ns2\tSouth\n"),
+            ("visit", "visit\tsite\nv1\ts1\nv2\ts2\nv3\ts1\n"),
+            ("reading", "reading\tvisit\nr1\tv1\nr2\tv3\n"),
+            ("note", "note\nremember\n"),
+            ("orphan", "reading\nr1\n"),
+        ];
+        for (table, content) in contents {
+            std::fs::write(format!("build/test_load_tables_{table}.tsv"), content).unwrap();
+        }
+        let tables = ["reading", "visit", "site", "note"]
+            .iter()
+            .map(|table| {
+                (
+                    table.to_string(),
+                    format!("build/test_load_tables_{table}.tsv"),
+                )
+            })
+            .collect::<Vec<_>>();
 
-/// A set of changes made by a user to a table.
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct ChangeSet {
-    pub action: ChangeAction,
-    pub table: String,
-    pub user: String,
-    pub description: String,
-    pub changes: Vec<Change>,
-}
+        // Tables are loaded after the tables they depend on:
+        let names = tables.iter().map(|(table, _)| table.to_string()).collect();
+        let order = block_on(rltbl.get_load_order(&names)).unwrap();
+        assert_eq!(
+            order.keys().collect::<Vec<_>>(),
+            ["site", "note", "visit", "reading"]
+        );
+        assert_eq!(order["reading"], ["visit"]);
+        let loads = block_on(rltbl.load_tables(&tables, true, 2, None)).unwrap();
+        assert_eq!(
+            loads
+                .iter()
+                .map(|load| (load.table.as_str(), load.status, load.rows))
+                .collect::<Vec<_>>(),
+            [
+                ("reading", LoadStatus::Loaded, 2),
+                ("visit", LoadStatus::Loaded, 3),
+                ("site", LoadStatus::Loaded, 2),
+                ("note", LoadStatus::Loaded, 1),
+            ]
+        );
+        let sql = r#"SELECT COUNT(1) FROM "message" WHERE "table" IN ('visit', 'reading')"#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(0))
+        );
 
-impl ChangeSet {
-    /// Given a change, returns the a [Cursor] representing where the user's cursor
-    /// should be placed in the frontend.
-    fn to_cursor(&self) -> Result<Cursor> {
-        tracing::trace!("ChangeSet::to_cursor()");
-        let table = self.table.clone();
-        match self.changes.first() {
-            Some(change) => match change {
-                Change::Update {
-                    row,
-                    column,
-                    before: _,
-                    after: _,
-                } => Ok(Cursor {
-                    table,
-                    row: *row,
-                    column: column.to_string(),
-                }),
-                Change::Add { row, after: _ } => Ok(Cursor {
-                    table,
-                    row: *row,
-                    column: "".to_string(),
-                }),
-                Change::Move {
-                    row,
-                    from_after: _,
-                    to_after: _,
-                } => Ok(Cursor {
-                    table,
-                    row: *row,
-                    column: "".to_string(),
-                }),
-                Change::Delete { row, after: _ } => Ok(Cursor {
-                    table,
-                    row: *row,
-                    column: "".to_string(),
-                }),
-            },
-            None => Err(RelatableError::ChangeError("No changes in set".into()).into()),
+        // Tables that depend on a table that could not be loaded are skipped:
+        let tables = vec![
+            ("broken".to_string(), "build/no_such_file.tsv".to_string()),
+            (
+                "orphan".to_string(),
+                "build/test_load_tables_orphan.tsv".to_string(),
+            ),
+        ];
+        let loads = block_on(rltbl.load_tables(&tables, true, 2, None)).unwrap();
+        assert_eq!(
+            loads.iter().map(|load| load.status).collect::<Vec<_>>(),
+            [LoadStatus::Failed, LoadStatus::Skipped]
+        );
+        assert!(loads[0].error.is_some());
+        assert!(!block_on(Table::table_exists("orphan", &rltbl)).unwrap());
+
+        // Circular dependencies are an error:
+        let sql = r#"INSERT INTO "column" ("table", "column", "structure")
+                     VALUES ('site', 'name', 'from(reading.reading)')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert!(block_on(rltbl.get_load_order(&names)).is_err());
+    }
+
+    #[test]
+    fn test_resume_load() {
+        let rltbl = memory_demo(3);
+        let sql = r#"INSERT INTO "column" ("table", "column", "datatype")
+                     VALUES ('tally', 'count', 'integer')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let path = "build/test_resume_load.tsv";
+        std::fs::write(path, "name\tcount\na\t1\nb\tx\nc\t3\nd\ty\n").unwrap();
+        let query_values = |sql: &str| {
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.content.values().next().unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+        let get_loaded = || query_values(r#"SELECT "loaded" FROM "table" WHERE "table" = 'tally'"#);
+        let get_state = || {
+            (
+                query_values(r#"SELECT "_order" FROM "tally" ORDER BY "_id""#),
+                query_values(
+                    r#"SELECT "row" FROM "message" WHERE "table" = 'tally' ORDER BY "row""#,
+                ),
+            )
+        };
+
+        // Once a load is complete, there is nothing to resume:
+        block_on(rltbl.load_table("tally", path, true, None));
+        assert_eq!(get_loaded(), [JsonValue::Null]);
+        let complete = get_state();
+        assert_eq!(
+            complete,
+            (
+                vec![json!(1000), json!(2000), json!(3000), json!(4000)],
+                vec![json!(2), json!(4)]
+            )
+        );
+
+        // Simulate a load that was interrupted after the first two rows were committed:
+        for sql in [
+            r#"DELETE FROM "tally" WHERE "_id" > 2"#,
+            r#"DELETE FROM "message" WHERE "table" = 'tally' AND "row" > 2"#,
+            r#"UPDATE "table" SET "loaded" = 2 WHERE "table" = 'tally'"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
         }
+        let failures = block_on(rltbl.doctor())
+            .unwrap()
+            .into_iter()
+            .filter(|diagnosis| !diagnosis.ok)
+            .map(|diagnosis| (diagnosis.check, diagnosis.detail))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            [(
+                "loads".to_string(),
+                "Table(s) were only partly loaded: 'tally' (2 rows)".to_string()
+            )]
+        );
+
+        // Resuming the load adds only the remaining rows and their messages:
+        block_on(rltbl.resume_load_table("tally", path, None));
+        assert_eq!(get_loaded(), [JsonValue::Null]);
+        assert_eq!(get_state(), complete);
     }
-}
 
-/// The kind of action that is performed by a change
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
-pub enum ChangeAction {
-    Do,
-    Undo,
-    Redo,
-}
+    #[test]
+    fn test_column_renames() {
+        let mut rltbl = memory_demo(3);
+        let path = "build/test_column_renames.tsv";
+        std::fs::write(path, "Sample No\tIsland Name\tnotes\n1\tBiscoe\tfirst\n").unwrap();
+        rltbl.column_renames = IndexMap::from([
+            ("Sample No".to_string(), "sample_number".to_string()),
+            ("Island Name".to_string(), "island".to_string()),
+            ("Unused".to_string(), "unused".to_string()),
+        ]);
 
-impl FromStr for ChangeAction {
-    type Err = anyhow::Error;
+        // Only the renames that apply to the headers are reported:
+        let headers = ["Sample No", "Island Name", "notes"].map(|h| h.to_string());
+        let (renamed, applied) = rltbl.rename_headers(&headers).unwrap();
+        assert_eq!(renamed, ["sample_number", "island", "notes"]);
+        assert_eq!(
+            applied.keys().collect::<Vec<_>>(),
+            ["Sample No", "Island Name"]
+        );
 
-    fn from_str(action: &str) -> Result<Self> {
-        tracing::trace!("ChangeAction::from_str({action:?})");
-        match action.to_lowercase().as_str() {
-            "do" => Ok(ChangeAction::Do),
-            "undo" => Ok(ChangeAction::Undo),
-            "redo" => Ok(ChangeAction::Redo),
-            _ => {
-                return Err(
-                    RelatableError::InputError(format!("Unrecognized action: {action}")).into(),
-                );
-            }
-        }
+        let tables = vec![("legacy".to_string(), path.to_string())];
+        block_on(rltbl.load_tables(&tables, true, 1, None)).unwrap();
+        let rows = block_on(rltbl.connection.query(r#"SELECT * FROM "legacy""#, None)).unwrap();
+        assert_eq!(rows[0].content["sample_number"], json!("1"));
+        assert_eq!(rows[0].content["island"], json!("Biscoe"));
+        assert_eq!(rows[0].content["notes"], json!("first"));
+
+        // Renames that would result in duplicate column names are an error:
+        rltbl
+            .column_renames
+            .insert("notes".to_string(), "island".to_string());
+        assert!(rltbl.rename_headers(&headers).is_err());
     }
-}
 
-impl Display for ChangeAction {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            ChangeAction::Do => write!(f, "do"),
-            ChangeAction::Undo => write!(f, "undo"),
-            ChangeAction::Redo => write!(f, "redo"),
-        }
+    #[test]
+    fn test_load_messages() {
+        let rltbl = memory_demo(0);
+        let path = "build/test_load_messages.tsv";
+        std::fs::write(path, "name\tmass\nswan\t10\nwren\t0.01\n").unwrap();
+
+        // A load is only recorded in the change table when it has a message:
+        block_on(rltbl.load_table("bird", path, true, None));
+        block_on(rltbl.load_table("bird", path, true, Some("March import")));
+        let sql = r#"SELECT "user", "description", "message" FROM "change"
+                     WHERE "table" = 'bird'"#;
+        let changes = block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].get_string("user").unwrap(), "rltbl");
+        assert_eq!(changes[0].get_string("description").unwrap(), "Load 2 rows");
+        assert_eq!(changes[0].get_string("message").unwrap(), "March import");
+
+        // The record has no changes to undo:
+        let history = block_on(rltbl.get_user_history("rltbl", None)).unwrap();
+        assert!(history.changes_done_stack.is_empty());
     }
-}
-
-/// A change to a table in the database
-#[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum Change {
-    Update {
-        /// The id of the row that was updated
-        row: u64,
-        /// The column whose value was updated
-        column: String,
-        /// The value of the column before the change
-        before: JsonValue,
-        /// The value of the column after the change
-        after: JsonValue,
-    },
-    Add {
-        /// The id of the row that was added
-        row: u64,
-        /// The _id of the row whose _order this comes immediately after in the table
-        after: u64,
-    },
-    Move {
-        /// The id of the row that was moved
-        row: u64,
-        /// The row that this row came after before the change
-        from_after: u64,
-        /// The row that this row came after after the change
-        to_after: u64,
-    },
-    Delete {
-        /// The id of the row that was deleted
-        row: u64,
-        /// The _id of the row whose _order this row came immediately after in the table before
-        /// being deleted.
-        after: u64,
-    },
-}
 
-impl Change {
-    /// Converts a JSON string representing an array of changes to an array of [Change] structs.
-    pub fn many_from_str(content: &str) -> Result<Vec<Self>> {
-        tracing::trace!("Change::many_from_str({content:?})");
-        let json_content = match serde_json::from_str::<JsonValue>(content) {
-            Err(err) => return Err(err.into()),
-            Ok(JsonValue::Array(v)) => v,
-            Ok(_) => {
-                return Err(RelatableError::InputError(
-                    "The content parameter is not an array".to_string(),
-                )
-                .into());
-            }
+    #[test]
+    fn test_autosave() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+        use std::time::Duration;
+
+        let mut rltbl = memory_demo(0);
+        let path = "build/test_autosave.tsv";
+        std::fs::write(path, "name\tmass\nswan\t10\n").unwrap();
+        block_on(rltbl.load_table("bird", path, true, None));
+        block_on(rltbl.set_autosave("bird", true)).unwrap();
+        let set_mass = |rltbl: &Relatable, mass: &str| {
+            block_on(rltbl.set_values(&ChangeSet {
+                user: "rltbl".to_string(),
+                action: ChangeAction::Do,
+                table: "bird".to_string(),
+                description: "Set the mass".to_string(),
+                message: None,
+                changes: vec![Change::Update {
+                    row: 1,
+                    column: "mass".to_string(),
+                    before: json!("10"),
+                    after: json!(mass),
+                }],
+            }))
+            .unwrap();
         };
-
-        let mut changes = vec![];
-        for change_json in json_content.iter() {
-            let change_json = match change_json.as_object() {
-                Some(change_object) => JsonRow {
-                    content: change_object.clone(),
-                },
-                None => {
-                    return Err(RelatableError::InputError(format!(
-                        "Not an object: {change_json}"
-                    ))
-                    .into());
-                }
-            };
-
-            let change_type = change_json.get_string("type")?;
-            let row = change_json.get_unsigned("row")?;
-            match change_type.as_str() {
-                "Update" => changes.push(Change::Update {
-                    row: row,
-                    column: change_json.get_string("column")?,
-                    before: change_json.get_value("before")?,
-                    after: change_json.get_value("after")?,
-                }),
-                "Add" => changes.push(Change::Add {
-                    row: row,
-                    after: change_json.get_unsigned("after")?,
-                }),
-                "Delete" => changes.push(Change::Delete {
-                    row: row,
-                    after: change_json.get_unsigned("after")?,
-                }),
-                "Move" => changes.push(Change::Move {
-                    row: row,
-                    from_after: change_json.get_unsigned("from_after")?,
-                    to_after: change_json.get_unsigned("to_after")?,
-                }),
-                _ => {
-                    return Err(RelatableError::InputError(format!(
-                        "Unrecognized change type for change: {change_json}"
-                    ))
-                    .into());
-                }
-            };
-        }
-        Ok(changes)
+        let saved_mass = || std::fs::read_to_string(path).unwrap();
+
+        // By default, a table is saved as soon as it is changed:
+        set_mass(&rltbl, "11");
+        assert_eq!(saved_mass(), "name\tmass\nswan\t11\n");
+
+        // Otherwise it is saved once it has not been changed for the autosave interval:
+        rltbl.autosave_interval = Duration::from_secs(3600);
+        set_mass(&rltbl, "12");
+        assert!(block_on(rltbl.flush_autosaves()).unwrap().is_empty());
+        assert_eq!(saved_mass(), "name\tmass\nswan\t11\n");
+        assert_eq!(block_on(rltbl.save_pending_autosaves()).unwrap(), ["bird"]);
+        assert_eq!(saved_mass(), "name\tmass\nswan\t12\n");
     }
 
-    /// Convers a [JsonRow] to a [Change]
-    pub fn from_json_row(json_row: &JsonRow) -> Result<Self> {
-        tracing::trace!("Change::from_json_row({json_row:?})");
-        match json_row.get_string("type")?.as_str() {
-            "Update" => Ok(Change::Update {
-                row: json_row.get_unsigned("row")?,
-                column: json_row.get_string("column")?,
-                before: json_row.get_value("before")?,
-                after: json_row.get_value("after")?,
-            }),
-            "Add" => Ok(Change::Add {
-                row: json_row.get_unsigned("row")?,
-                after: json_row.get_unsigned("after")?,
-            }),
-            "Move" => Ok(Change::Move {
-                row: json_row.get_unsigned("row")?,
-                from_after: json_row.get_unsigned("from_after")?,
-                to_after: json_row.get_unsigned("to_after")?,
-            }),
-            "Delete" => Ok(Change::Delete {
-                row: json_row.get_unsigned("row")?,
-                after: json_row.get_unsigned("after")?,
-            }),
-            _ => {
-                return Err(RelatableError::InputError(format!(
-                    "Unrecognized action type for change {json_row}"
-                ))
-                .into());
-            }
-        }
+    #[test]
+    fn test_partition_table() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+        use rltbl::select::Select;
+
+        let rltbl = memory_demo(30);
+        let query_value = |sql: &str| block_on(rltbl.connection.query_value(sql, None)).unwrap();
+        let islands = r#"SELECT COUNT(DISTINCT "island") FROM "penguin""#;
+        let islands = query_value(islands).unwrap().as_u64().unwrap();
+        let dream = r#"SELECT COUNT(1) FROM "penguin" WHERE "island" = 'Dream'"#;
+        let dream = query_value(dream).unwrap().as_u64().unwrap();
+
+        // Each island gets a partition, along with the default partition:
+        let partitions = block_on(rltbl.partition_table("penguin", "island")).unwrap();
+        assert_eq!(partitions.len() as u64, islands + 1);
+        assert_eq!(partitions.last().unwrap().value, None);
+        assert!(block_on(rltbl.partition_table("penguin", "island")).is_err());
+        assert!(block_on(Table::table_exists("penguin", &rltbl)).unwrap());
+        let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        assert_eq!(table.partitions, partitions);
+
+        // Reads of the whole table read every partition, while reads that are filtered on the
+        // partition column only read the partitions that can match:
+        let result = block_on(rltbl.fetch(&Select::from("penguin"))).unwrap();
+        assert_eq!(result.rows.len(), 30);
+        let mut select = Select::from("penguin");
+        select.eq("island", &"Dream").unwrap();
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len() as u64, dream);
+        assert_eq!(block_on(rltbl.count(&result.select)).unwrap(), dream);
+        assert_eq!(result.select.get_partitions_to_read().len(), 1);
+        let partition = result.select.get_partitions_to_read()[0].clone();
+        assert_eq!(partition.value, Some(json!("Dream")));
+        assert!(result
+            .statement
+            .contains(&format!(r#"FROM (SELECT * FROM "{}")"#, partition.name)));
+        select.view_name = "penguin_default_view".to_string();
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len() as u64, dream);
+        assert!(result.statement.contains(&format!(
+            r#"FROM (SELECT * FROM "{}_default_view")"#,
+            partition.name
+        )));
+        let mut select = Select::from("penguin");
+        select.eq("island", &"Atlantis").unwrap();
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.select.get_partitions_to_read()[0].value, None);
+        assert_eq!(result.rows.len(), 0);
+
+        // Rows are added to, moved between, and deleted from the partitions they belong in:
+        let count = |partition: &str| {
+            query_value(&format!(r#"SELECT COUNT(1) FROM "{partition}""#))
+                .unwrap()
+                .as_u64()
+                .unwrap()
+        };
+        let mut row = JsonRow::from_strings(&vec!["island"]);
+        row.content.insert("island".to_string(), json!("Atlantis"));
+        let new_row = block_on(rltbl.add_row("penguin", "rltbl", None, &row)).unwrap();
+        assert_eq!(new_row.id, 31);
+        assert_eq!(count("penguin__default"), 1);
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 31,
+                column: "island".to_string(),
+                before: json!("Atlantis"),
+                after: json!("Dream"),
+            }],
+        }))
+        .unwrap();
+        assert_eq!(count("penguin__default"), 0);
+        assert_eq!(count(&partition.name), dream + 1);
+        block_on(rltbl.delete_row("penguin", "rltbl", 31)).unwrap();
+        assert_eq!(count(&partition.name), dream);
+        assert_eq!(
+            query_value(r#"SELECT COUNT(1) FROM "penguin""#),
+            Some(json!(30))
+        );
+
+        // A partitioned table is dropped along with its partitions:
+        let mut table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        block_on(table.drop_table(&rltbl)).unwrap();
+        assert!(!block_on(Table::table_exists("penguin", &rltbl)).unwrap());
+        assert!(!block_on(Table::table_exists(&partition.name, &rltbl)).unwrap());
     }
-}
 
-/// Describes a history of changes that have been done and undone.
-#[derive(Default, Debug, Serialize, Deserialize)]
-pub struct History {
-    pub changes_done_stack: Vec<JsonRow>,
-    pub changes_undone_stack: Vec<JsonRow>,
-}
+    #[test]
+    fn test_archive_rows() {
+        use rltbl::{
+            core::{Change, ChangeAction, ChangeSet},
+            select::{Format, QueryParams, Select},
+        };
 
-impl Display for Change {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Change::Update {
-                row,
-                column,
-                before,
-                after,
-            } => {
-                write!(
-                    f,
-                    "Update '{column}' in row {row} from {before} to {after}",
-                    before = sql::json_to_string(before),
-                    after = sql::json_to_string(after)
-                )
-            }
-            Change::Add { row, after } => {
-                write!(f, "Add row {row} after row {after}")
-            }
-            Change::Move {
+        let rltbl = memory_demo(30);
+        let query_value = |sql: &str| {
+            block_on(rltbl.connection.query_value(sql, None))
+                .unwrap()
+                .and_then(|value| value.as_u64())
+                .unwrap_or_default()
+        };
+        let dream = query_value(r#"SELECT COUNT(1) FROM "penguin" WHERE "island" = 'Dream'"#);
+        let row = query_value(r#"SELECT MIN("_id") FROM "penguin" WHERE "island" = 'Dream'"#);
+        let kept_row = query_value(r#"SELECT MIN("_id") FROM "penguin" WHERE "island" <> 'Dream'"#);
+
+        // Give one of the rows to be kept some history, and then one of the rows to be archived
+        // some history and a message:
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: kept_row,
+                column: "body_mass".to_string(),
+                before: JsonValue::Null,
+                after: json!(3000),
+            }],
+        }))
+        .unwrap();
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
                 row,
-                from_after,
-                to_after,
-            } => {
-                write!(
-                    f,
-                    "Move row {row} from after row {from_after} to after row {to_after}"
-                )
-            }
-            Change::Delete { row, after: _ } => write!(f, "Delete row {row}"),
+                column: "body_mass".to_string(),
+                before: JsonValue::Null,
+                after: json!(4000),
+            }],
+        }))
+        .unwrap();
+        block_on(rltbl.add_message(
+            "rltbl",
+            "penguin",
+            row,
+            "body_mass",
+            &json!(4000),
+            "warn",
+            "custom:heavy",
+            "Heavy penguin",
+        ))
+        .unwrap();
+
+        assert!(block_on(rltbl.archive_rows("penguin", &vec![])).is_err());
+        assert!(block_on(rltbl.archive_rows("history", &vec!["row = 1".to_string()])).is_err());
+        let filters = vec!["island = Dream".to_string()];
+        assert_eq!(
+            block_on(rltbl.archive_rows("penguin", &filters)).unwrap() as u64,
+            dream
+        );
+        assert_eq!(
+            block_on(rltbl.archive_rows("penguin", &filters)).unwrap(),
+            0
+        );
+
+        // The rows, along with their history and messages, are moved to the archives:
+        assert_eq!(query_value(r#"SELECT COUNT(1) FROM "penguin""#), 30 - dream);
+        assert_eq!(
+            query_value(r#"SELECT COUNT(1) FROM "penguin__archive""#),
+            dream
+        );
+        for table in ["history", "message"] {
+            let sql = format!(r#"SELECT COUNT(1) FROM "{table}" WHERE "row" = {row}"#);
+            assert_eq!(query_value(&sql), 0);
+            let sql = format!(r#"SELECT COUNT(1) FROM "{table}__archive" WHERE "row" = {row}"#);
+            assert!(query_value(&sql) > 0);
         }
-    }
-}
 
-// Ranges and Results
+        // Archived rows are only read when they are included:
+        let result = block_on(rltbl.fetch(&Select::from("penguin"))).unwrap();
+        assert_eq!(result.rows.len() as u64, 30 - dream);
+        let query_params =
+            serde_json::from_value::<QueryParams>(json!({"include_archived": "true"})).unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(
+            select.to_url("", &Format::Default).unwrap(),
+            "/penguin?include_archived=true"
+        );
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len(), 30);
+        assert!(result
+            .statement
+            .contains(r#"SELECT * FROM "penguin__archive_default_view""#));
+        let archived = result.rows.iter().find(|r| r.id == row).unwrap();
+        assert_eq!(archived.cells["body_mass"].messages.len(), 1);
+
+        // The change to the archived row is archived along with its history, so that the next
+        // undo reverts the change to the row that was kept:
+        assert_eq!(query_value(r#"SELECT COUNT(1) FROM "change__archive""#), 1);
+        let changeset = block_on(rltbl.undo("rltbl")).unwrap().unwrap();
+        assert!(matches!(
+            changeset.changes.as_slice(),
+            [Change::Update { row, .. }] if *row == kept_row
+        ));
+        let sql = format!(r#"SELECT "body_mass" FROM "penguin" WHERE "_id" = {kept_row}"#);
+        assert_eq!(
+            block_on(rltbl.connection.query_value(&sql, None)).unwrap(),
+            Some(JsonValue::Null)
+        );
+    }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct Range {
-    count: usize,
-    total: u64,
-    start: u64,
-    end: u64,
-}
+    #[test]
+    fn test_arrow_output() {
+        use arrow_array::{Array as _, Float64Array, Int64Array, StringArray};
+        use arrow_ipc::reader::StreamReader;
+        use arrow_schema::DataType;
+        use rltbl::{
+            core::{Change, ChangeAction, ChangeSet},
+            select::Select,
+        };
 
-impl std::fmt::Display for Range {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Rows {}-{} of {}", self.start, self.end, self.total)
+        let rltbl = memory_demo(3);
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 2,
+                column: "body_mass".to_string(),
+                before: json!(""),
+                after: json!("heavy"),
+            }],
+        }))
+        .unwrap();
+
+        // The Arrow types follow from the SQL types of the columns, and invalid values are nulls:
+        let result = block_on(rltbl.fetch(&Select::from("penguin"))).unwrap();
+        let arrow = result.to_arrow().unwrap();
+        let batches = StreamReader::try_new(arrow.as_slice(), None)
+            .unwrap()
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 3);
+        let schema = batch.schema();
+        let types = schema
+            .fields()
+            .iter()
+            .map(|field| (field.name().as_str(), field.data_type().clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            [
+                ("study_name", DataType::Utf8),
+                ("sample_number", DataType::Int64),
+                ("species", DataType::Utf8),
+                ("island", DataType::Utf8),
+                ("individual_id", DataType::Utf8),
+                ("bill_length", DataType::Float64),
+                ("bill_depth", DataType::Float64),
+                ("body_mass", DataType::Int64),
+            ]
+        );
+        let column = |name: &str| batch.column(schema.index_of(name).unwrap()).clone();
+        let sample_numbers = column("sample_number");
+        let sample_numbers = sample_numbers
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(sample_numbers.values().to_vec(), [1, 2, 3]);
+        let islands = column("island");
+        let islands = islands.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(islands.value(0), "Torgersen");
+        let bill_lengths = column("bill_length");
+        let bill_lengths = bill_lengths
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(bill_lengths.value(0), 44.6);
+        let body_masses = column("body_mass");
+        let body_masses = body_masses.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert!(body_masses.is_valid(0));
+        assert!(body_masses.is_null(1));
     }
-}
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct ResultSet {
-    pub select: Select,
-    pub statement: String,
-    pub parameters: Vec<JsonValue>,
-    pub range: Range,
-    pub table: Table,
-    /// The columns (and only the columns) used in the Select statement
-    pub columns: Vec<Column>,
-    pub rows: Vec<Row>,
-}
+    #[test]
+    fn test_search() {
+        use rltbl::{
+            core::{Change, ChangeAction, ChangeSet},
+            select::{Format, QueryParams, Select},
+        };
 
-impl ResultSet {
-    /// Write the result set to CSV
-    pub fn to_csv(&self) -> String {
-        let writer = WriterBuilder::new().from_writer(vec![]);
-        self.to_xsv(writer)
+        let rltbl = memory_demo(0);
+        std::fs::write(
+            "build/test_search.tsv",
+            "name\tnote\tsize\nalpha\tA small red fox\t1\nbeta\tA big brown bear\t2\n\
+             gamma\tThe red planet\t3\n",
+        )
+        .unwrap();
+        block_on(rltbl.load_table("animal", "build/test_search.tsv", true, None));
+        assert!(block_on(rltbl.add_search("animal", &["nope".to_string()])).is_err());
+        let columns = block_on(rltbl.add_search("animal", &vec![])).unwrap();
+        assert_eq!(columns, ["name", "note", "size"]);
+
+        let search = |query: &str| {
+            block_on(rltbl.fetch(&Select::from("animal").search(query)))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.cells.get("name").unwrap().text.clone())
+                .collect::<Vec<_>>()
+        };
+        // Rows must contain all of the words, in any column, and in any case:
+        assert_eq!(search("red"), ["alpha", "gamma"]);
+        assert_eq!(search("RED fox"), ["alpha"]);
+        assert_eq!(search("red 2"), Vec::<String>::new());
+        // Words are not read as query syntax:
+        assert_eq!(search(r#"bear" OR "red"#), Vec::<String>::new());
+
+        // The index is recreated when the table is reloaded:
+        block_on(rltbl.load_table("animal", "build/test_search.tsv", true, None));
+        assert_eq!(search("red"), ["alpha", "gamma"]);
+        assert_eq!(block_on(rltbl.rebuild_search(None)).unwrap(), ["animal"]);
+        assert_eq!(search("bear"), ["beta"]);
+
+        // The index is kept up to date as rows are changed, added, and deleted:
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "animal".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 2,
+                column: "note".to_string(),
+                before: json!("A big brown bear"),
+                after: json!("A big red bear"),
+            }],
+        }))
+        .unwrap();
+        assert_eq!(search("red"), ["alpha", "beta", "gamma"]);
+        let row = JsonRow {
+            content: json!({"name": "delta", "note": "A red herring", "size": 4})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        block_on(rltbl.add_row("animal", "rltbl", None, &row)).unwrap();
+        assert_eq!(search("herring"), ["delta"]);
+        block_on(rltbl.delete_row("animal", "rltbl", 1)).unwrap();
+        assert_eq!(search("red"), ["beta", "gamma", "delta"]);
+
+        // Searches are given in URLs by the search parameter:
+        let query_params =
+            serde_json::from_value::<QueryParams>(json!({"search": "red bear"})).unwrap();
+        let select = block_on(Select::from_path_and_query("animal", &query_params, &rltbl));
+        assert_eq!(
+            select.to_url("", &Format::Default).unwrap(),
+            "/animal?search=red bear"
+        );
+        assert_eq!(block_on(rltbl.fetch(&select)).unwrap().rows.len(), 1);
+
+        block_on(rltbl.remove_search("animal")).unwrap();
+        assert_eq!(block_on(rltbl.get_search_columns("animal")).unwrap(), None);
+        assert!(block_on(rltbl.fetch(&Select::from("animal").search("red"))).is_err());
     }
 
-    /// Write the result set to TSV
-    pub fn to_tsv(&self) -> String {
-        let writer = WriterBuilder::new()
-            .delimiter(b'\t')
-            .quote_style(QuoteStyle::Never)
-            .from_writer(vec![]);
-        self.to_xsv(writer)
+    #[test]
+    fn test_maintain_indexes() {
+        let rltbl = memory_demo(3);
+        assert_eq!(block_on(rltbl.maintain()).unwrap(), Vec::<String>::new());
+
+        block_on(
+            rltbl
+                .connection
+                .query(r#"DROP INDEX "history_table_row_change_id_idx""#, None),
+        )
+        .unwrap();
+        assert_eq!(
+            block_on(rltbl.maintain()).unwrap(),
+            vec!["history_table_row_change_id_idx"]
+        );
+        assert_eq!(block_on(rltbl.maintain()).unwrap(), Vec::<String>::new());
     }
 
-    /// Write the result set to XSV
-    pub fn to_xsv(&self, mut writer: Writer<Vec<u8>>) -> String {
-        let header_row = &self
-            .columns
-            .iter()
-            .map(|c| c.name.clone())
-            .collect::<Vec<String>>();
-        writer.write_record(header_row.clone()).unwrap();
-        for row in &self.rows {
-            writer.write_record(row.to_strings()).unwrap();
+    #[test]
+    fn test_init_migrates_older_layout() {
+        let path = "build/test_init_migrates_older_layout.db";
+        let rltbl = block_on(Relatable::build_demo(
+            Some(path),
+            &true,
+            3,
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+
+        // Revert the database to a layout from before the schema version was tracked:
+        for sql in [
+            r#"DROP TABLE "meta""#,
+            r#"DROP TABLE "template""#,
+            r#"DROP TABLE "operation""#,
+            r#"DROP TABLE "origin""#,
+            r#"DROP TABLE "clock""#,
+            r#"DROP INDEX "history_table_row_change_id_idx""#,
+            r#"ALTER TABLE "change" DROP COLUMN "change_group""#,
+            r#"ALTER TABLE "message" DROP COLUMN "_hash""#,
+            r#"ALTER TABLE "message" DROP COLUMN "_datetime""#,
+            r#"ALTER TABLE "table" DROP COLUMN "loaded""#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
         }
-        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+
+        let get_version = |rltbl: &Relatable| {
+            let mut conn = rltbl.connection.reconnect().unwrap();
+            let mut tx = block_on(rltbl.connection.begin(&mut conn)).unwrap();
+            Relatable::_get_schema_version(&mut tx).unwrap()
+        };
+        assert_eq!(get_version(&rltbl), 0);
+
+        // Initializing without --force upgrades the existing database and leaves its data alone:
+        let rltbl = block_on(Relatable::init(&false, Some(path), &CachingStrategy::None)).unwrap();
+        assert_eq!(get_version(&rltbl), sql::SCHEMA_VERSION);
+        assert!(block_on(Table::table_exists("operation", &rltbl)).unwrap());
+        assert!(block_on(Table::table_exists("template", &rltbl)).unwrap());
+        assert!(block_on(Table::table_exists("penguin", &rltbl)).unwrap());
+        assert_eq!(block_on(rltbl.maintain()).unwrap(), Vec::<String>::new());
+        let (columns, _) = block_on(Table::collect_column_info("change", &rltbl)).unwrap();
+        assert!(columns.iter().any(|column| column.name == "change_group"));
+        let (_, meta_columns) = block_on(Table::collect_column_info("message", &rltbl)).unwrap();
+        assert!(meta_columns.iter().any(|column| column.name == "_hash"));
+        assert!(meta_columns.iter().any(|column| column.name == "_datetime"));
+        let (columns, _) = block_on(Table::collect_column_info("table", &rltbl)).unwrap();
+        assert!(columns.iter().any(|column| column.name == "loaded"));
+
+        // Doing so again is a no-op:
+        assert_eq!(block_on(rltbl.migrate()).unwrap(), sql::SCHEMA_VERSION);
     }
 
-    /// Uses the given (unverified) printf-style format string and the given compiled regular
-    /// expression (which is used to verify the given format) to format the given cell.
-    fn format_cell_text_value(column_format: &str, format_regex: &Regex, cell: &str) -> String {
-        // If the cell is an empty string, just return it as is:
-        if cell == "" {
-            return "".to_string();
+    #[test]
+    fn test_doctor() {
+        let rltbl = memory_demo(3);
+        let get_failures = || {
+            block_on(rltbl.doctor())
+                .unwrap()
+                .into_iter()
+                .filter(|diagnosis| !diagnosis.ok)
+                .map(|diagnosis| (diagnosis.check, diagnosis.detail))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(get_failures(), vec![]);
+
+        for sql in [
+            r#"DROP TRIGGER "penguin_order""#,
+            r#"INSERT INTO "message" ("table", "row", "column", "value", "level", "rule", "message")
+               VALUES ('penguin', 1000, 'species', 'bar', 'error', 'custom:test', 'Test')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
         }
+        assert_eq!(
+            get_failures(),
+            vec![
+                (
+                    "triggers".to_string(),
+                    "Missing trigger(s): 'penguin_order'".to_string()
+                ),
+                (
+                    "orphans".to_string(),
+                    "Found messages about rows that do not exist: 1 for 'penguin'".to_string()
+                ),
+            ]
+        );
+    }
 
-        let conversion_spec = match format_regex.captures(column_format) {
-            Some(c) => c[1].to_lowercase(),
-            None => {
-                tracing::warn!("Illegal format: '{}'", column_format);
-                "s".to_string()
-            }
+    #[test]
+    fn test_check_config() {
+        let rltbl = memory_demo(3);
+        let get_problems = || {
+            block_on(rltbl.check_config())
+                .unwrap()
+                .into_iter()
+                .map(|problem| (problem.table, problem.row, problem.column, problem.rule))
+                .collect::<Vec<_>>()
         };
-        let generic_error = format!("Error applying format '{}' to '{}'", column_format, cell);
-        match conversion_spec.as_str() {
-            "d" | "i" | "c" => match cell.parse::<isize>() {
-                Ok(cell) => match sprintf!(&column_format, cell) {
-                    Ok(cell) => {
-                        // For some reason sprintf converts signed ints to unsigned ints before
-                        // converting them to a string. So we have to workaround this here:
-                        let cell = cell.parse::<usize>().unwrap();
-                        let cell = cell as isize;
-                        cell.to_string()
-                    }
-                    Err(e) => {
-                        tracing::warn!("{}: {}", generic_error, e);
-                        cell.to_string()
-                    }
-                },
-                Err(e) => {
-                    tracing::warn!("{}: {}", generic_error, e);
-                    cell.to_string()
-                }
-            },
-            "o" | "u" | "x" => match cell.parse::<usize>() {
-                Ok(cell) => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
-                Err(e) => {
-                    tracing::warn!("{}: {}", generic_error, e);
-                    cell.to_string()
-                }
-            },
-            "e" | "f" | "g" | "a" => match cell.parse::<f64>() {
-                Ok(cell) => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
-                Err(e) => {
-                    tracing::warn!("{}: {}", generic_error, e);
-                    cell.to_string()
-                }
-            },
-            "s" => sprintf!(&column_format, cell).unwrap_or(cell.to_string()),
-            _ => {
-                tracing::warn!(
-                    "Unsupported conversion specifier '{}' in column format '{}'",
-                    conversion_spec,
-                    column_format
-                );
-                cell.to_string()
-            }
+        assert_eq!(get_problems(), vec![]);
+
+        for sql in [
+            r#"INSERT INTO "table" ("table") VALUES ('column'), ('datatype'), ('ghost')"#,
+            r#"CREATE TABLE "stray" ("foo" TEXT)"#,
+            r#"UPDATE "column" SET "datatype" = 'foo' WHERE "column" = 'species'"#,
+            r#"UPDATE "column" SET "structure" = 'from(island.name)' WHERE "column" = 'island'"#,
+            r#"INSERT INTO "column" ("table", "column", "structure")
+               VALUES ('penguin', 'individual_id', 'from(')"#,
+            r#"INSERT INTO "datatype" ("datatype", "parent") VALUES ('a', 'b'), ('b', 'a')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
         }
+        let problem = |table: &str, row: Option<u64>, column: &str, rule: &str| {
+            (
+                table.to_string(),
+                row,
+                column.to_string(),
+                format!("config:{rule}"),
+            )
+        };
+        assert_eq!(
+            get_problems(),
+            vec![
+                problem("table", Some(5), "table", "missing-table"),
+                problem("table", None, "table", "unlisted-table"),
+                problem("datatype", Some(3), "parent", "datatype-cycle"),
+                problem("datatype", Some(4), "parent", "datatype-cycle"),
+                problem("column", Some(3), "datatype", "unknown-datatype"),
+                problem("column", Some(4), "structure", "unknown-structure"),
+                problem("column", Some(9), "column", "duplicate-column"),
+                problem("column", Some(9), "structure", "invalid-structure"),
+            ]
+        );
+
+        // Problems with a row of a listed table are recorded as messages, which the next check
+        // replaces:
+        let count_messages = || {
+            block_on(rltbl.connection.query_value(
+                r#"SELECT COUNT(1) FROM "message" WHERE "rule" LIKE 'config:%'"#,
+                None,
+            ))
+            .unwrap()
+            .unwrap()
+        };
+        assert_eq!(count_messages(), json!(6));
+        block_on(
+            rltbl
+                .connection
+                .query(r#"DELETE FROM "column" WHERE "_id" = 9"#, None),
+        )
+        .unwrap();
+        assert_eq!(get_problems().len(), 6);
+        assert_eq!(count_messages(), json!(4));
     }
 
-    /// Write the result set to the console
-    pub fn to_console(&self) -> String {
-        let tw = TabWriter::new(vec![]);
-        let mut tw = tw.ansi(true);
-        tw.write(format!("{}\n", self.range).as_bytes())
-            .unwrap_or_default();
-        let header = &self
-            .columns
-            .iter()
-            .map(|c| c.name.clone())
-            .collect::<Vec<String>>();
-        tw.write(format!("{}\n", header.join("\t")).as_bytes())
-            .unwrap_or_default();
+    #[test]
+    fn test_selftest() {
+        let observed = block_on(Relatable::selftest("build/test_selftest.db")).unwrap();
+        assert_eq!(
+            observed.keys().collect::<Vec<_>>(),
+            vec!["init", "load", "edit", "validate", "undo", "export"]
+        );
+        assert_eq!(observed["init"], "penguin, island");
+        assert_eq!(observed["load"], "10");
+        assert_eq!(observed["edit"], "Pygoscelis papua");
+        assert_eq!(
+            observed["validate"],
+            "2\tbody_mass\theavy\terror\tsql_type:integer"
+        );
+        assert_eq!(observed["undo"], "Pygoscelis adeliae\n3336\n");
+        assert_eq!(observed["export"].lines().count(), 11);
+    }
 
-        let format_regex = Regex::new(r#"^%.*([\w%])$"#).expect("Invalid regular expression");
-        let mut contains_errors = false;
-        for row in &self.rows {
-            let cells = row
-                .cells
+    #[test]
+    fn test_relatable_builder() {
+        use rltbl::core::RelatableBuilder;
+
+        // Nothing is connected to until the instance is first used:
+        let path = "build/test_relatable_builder.db";
+        let _ = std::fs::remove_file(path);
+        let lazy = RelatableBuilder::new()
+            .connection(path)
+            .readonly(true)
+            .default_user("alice")
+            .build();
+        assert!(block_on(lazy.get()).is_err());
+
+        block_on(Relatable::build_demo(
+            Some(path),
+            &true,
+            3,
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        let rltbl = block_on(lazy.get()).unwrap();
+        assert!(rltbl.readonly);
+        assert_eq!(rltbl.default_user, Some("alice".to_string()));
+
+        let clone = rltbl.try_clone().unwrap();
+        assert!(clone.readonly);
+        assert_eq!(
+            block_on(clone.list_tables()).unwrap(),
+            vec!["penguin", "island"]
+        );
+    }
+
+    #[test]
+    fn test_sync_operations() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+
+        let build = |path: &str| {
+            block_on(Relatable::build_demo(
+                Some(path),
+                &true,
+                3,
+                &CachingStrategy::None,
+            ))
+            .unwrap()
+        };
+        let local = build("build/test_sync_operations_local.db");
+        let peer = build("build/test_sync_operations_peer.db");
+
+        let set_value = |rltbl: &Relatable, row: u64, column: &str, after: &str| {
+            block_on(rltbl.set_values(&ChangeSet {
+                user: "rltbl".to_string(),
+                action: ChangeAction::Do,
+                table: "penguin".to_string(),
+                description: "Set one value".to_string(),
+                message: None,
+                changes: vec![Change::Update {
+                    row,
+                    column: column.to_string(),
+                    before: json!(""),
+                    after: json!(after),
+                }],
+            }))
+            .unwrap();
+        };
+        let get_rows = |rltbl: &Relatable| {
+            let sql = r#"SELECT "species", "island" FROM "penguin" ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
                 .iter()
-                .map(|(column_name, cell)| {
-                    let value_to_print = {
-                        let column_format = match self.table.columns.get(column_name) {
-                            Some(column) if column.datatype.format == "" => "%s",
-                            Some(column) => &column.datatype.format,
-                            None => {
-                                tracing::warn!(
-                                    "Can't determine cell format. No column found: '{column_name}'"
-                                );
-                                "%s"
-                            }
-                        };
-                        ResultSet::format_cell_text_value(&column_format, &format_regex, &cell.text)
-                    };
-                    if cell.message_level() >= 2 {
-                        contains_errors = true;
-                        format!("{}", value_to_print.red())
-                    } else {
-                        value_to_print
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+        let get_conflicts = |rltbl: &Relatable| {
+            let sql = r#"SELECT "row", "column" FROM "message"
+                         WHERE "rule" = 'sync:conflict'"#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+
+        // Edits to different cells are merged, and concurrent edits to the same cell result in a
+        // conflict on both sides that is resolved in the same way:
+        set_value(&local, 1, "species", "Pygoscelis papua");
+        set_value(&peer, 2, "species", "Pygoscelis antarcticus");
+        set_value(&local, 3, "island", "Dream");
+        set_value(&peer, 3, "island", "Biscoe");
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (2, 2));
+        assert_eq!(get_rows(&local), get_rows(&peer));
+        assert_eq!(get_rows(&local)[0][0], "Pygoscelis papua");
+        assert_eq!(get_rows(&local)[1][0], "Pygoscelis antarcticus");
+        assert_eq!(get_conflicts(&local), vec![vec!["3", "island"]]);
+        assert_eq!(get_conflicts(&peer), vec![vec!["3", "island"]]);
+
+        // Syncing again is a no-op, and an edit made after a sync supersedes the synced value
+        // without conflict, clearing the cell's conflict message like any other edit:
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (0, 0));
+        set_value(&peer, 3, "island", "Torgersen");
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (1, 0));
+        assert_eq!(get_rows(&local)[2][1], "Torgersen");
+        assert_eq!(get_conflicts(&local), Vec::<Vec<String>>::new());
+        assert_eq!(get_conflicts(&peer), Vec::<Vec<String>>::new());
+
+        // Rows added concurrently, which are given the same _id by each instance, are both kept,
+        // and are ordered in the same way by both instances, as are deleted and moved rows:
+        let add_row = |rltbl: &Relatable, species: &str| {
+            let row = JsonRow {
+                content: json!({"species": species}).as_object().unwrap().clone(),
+            };
+            block_on(rltbl.add_row("penguin", "rltbl", None, &row))
+                .unwrap()
+                .id
+        };
+        assert_eq!(add_row(&local, "Aptenodytes forsteri"), 4);
+        assert_eq!(add_row(&peer, "Eudyptes chrysocome"), 4);
+        block_on(local.delete_row("penguin", "rltbl", 2)).unwrap();
+        block_on(peer.move_row("penguin", "rltbl", 1, 3)).unwrap();
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (2, 2));
+        assert_eq!(get_rows(&local), get_rows(&peer));
+        assert_eq!(get_rows(&local).len(), 4);
+        assert_eq!(get_rows(&local)[1][0], "Pygoscelis papua");
+
+        // A change that is made to a row added by another instance refers to the right row, and
+        // a merged change can be undone like any other:
+        set_value(&peer, 5, "island", "Dream");
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (1, 0));
+        assert_eq!(get_rows(&local), get_rows(&peer));
+        block_on(local.undo("rltbl")).unwrap().unwrap();
+        assert_eq!(block_on(local.sync(&peer)).unwrap(), (0, 1));
+        assert_eq!(get_rows(&local), get_rows(&peer));
+        assert!(get_rows(&local).iter().all(|row| row[1] != "Dream"));
+    }
+
+    #[test]
+    fn test_hooks() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet, RelatableError};
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let mut rltbl = memory_demo(10);
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        rltbl.on_pre_insert(|_table, mut row| async move {
+            row.content.entry("study_name").or_insert(json!("FAKE123"));
+            Ok(row)
+        });
+        rltbl.on_post_update(|changeset| async move {
+            for change in &changeset.changes {
+                if let Change::Update { after, .. } = change {
+                    if after.as_u64().unwrap_or_default() > 10000 {
+                        return Err(RelatableError::InputError("Too heavy".to_string()).into());
                     }
-                })
-                .collect::<Vec<_>>();
-            tw.write(format!("{}\n", cells.join("\t")).as_bytes())
-                .unwrap_or_default();
-        }
-        tw.flush().expect("TabWriter to flush");
-        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
-        written
+                }
+            }
+            Ok(())
+        });
+        rltbl.on_pre_delete(|_table, row| async move {
+            match row {
+                1 => Err(RelatableError::InputError("Row 1 is protected".to_string()).into()),
+                _ => Ok(()),
+            }
+        });
+        rltbl.on_post_change(move |_changeset| {
+            let counter = counter.clone();
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let get_value = |sql: &str| block_on(rltbl.connection.query_value(sql, None)).unwrap();
+
+        // Pre-insert hooks augment the row to be inserted:
+        let mut row = JsonRow::new();
+        row.content.insert("island".to_string(), json!("Dream"));
+        let new_row = block_on(rltbl.add_row("penguin", "rltbl", None, &row)).unwrap();
+        assert_eq!(
+            get_value(&format!(
+                r#"SELECT "study_name" FROM "penguin" WHERE "_id" = {}"#,
+                new_row.id
+            )),
+            Some(json!("FAKE123"))
+        );
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        // Post-update hooks veto an update, which is then rolled back:
+        let set_body_mass = |body_mass: u64| {
+            block_on(rltbl.set_values(&ChangeSet {
+                user: "rltbl".to_string(),
+                action: ChangeAction::Do,
+                table: "penguin".to_string(),
+                description: "Set one value".to_string(),
+                message: None,
+                changes: vec![Change::Update {
+                    row: 2,
+                    column: "body_mass".to_string(),
+                    before: JsonValue::Null,
+                    after: json!(body_mass),
+                }],
+            }))
+        };
+        assert!(set_body_mass(20000).is_err());
+        assert!(set_body_mass(4000).is_ok());
+        assert_eq!(
+            get_value(r#"SELECT "body_mass" FROM "penguin" WHERE "_id" = 2"#),
+            Some(json!(4000))
+        );
+        // The post-change hooks, which run first, will have seen the vetoed update as well:
+        assert_eq!(changes.load(Ordering::SeqCst), 3);
+
+        // Pre-delete hooks veto a deletion:
+        assert!(block_on(rltbl.delete_row("penguin", "rltbl", 1)).is_err());
+        assert_eq!(
+            block_on(rltbl.delete_row("penguin", "rltbl", 2)).unwrap(),
+            1
+        );
+        assert_eq!(
+            get_value(r#"SELECT COUNT(1) FROM "penguin""#),
+            Some(json!(10))
+        );
+
+        // Post-change hooks also see undos:
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(changes.load(Ordering::SeqCst), 5);
     }
-}
 
-impl std::fmt::Display for ResultSet {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut tw = TabWriter::new(vec![]);
-        tw.write(format!("{}\n", self.range).as_bytes())
-            .unwrap_or_default();
-        let header = &self
-            .columns
-            .iter()
-            .map(|c| c.name.clone())
-            .collect::<Vec<String>>();
-        tw.write(format!("{}\n", header.join("\t")).as_bytes())
-            .unwrap_or_default();
-        for row in &self.rows {
-            tw.write(format!("{}\n", row.to_strings().join("\t")).as_bytes())
-                .unwrap_or_default();
+    #[test]
+    fn test_apply_caching_strategy() {
+        let mut rltbl = memory_demo(5);
+        block_on(rltbl.partition_table("penguin", "island")).unwrap();
+        let cache_triggers = |rltbl: &Relatable| {
+            let sql = r#"SELECT "name" FROM "sqlite_master"
+                         WHERE "type" = 'trigger' AND "sql" LIKE '%DELETE FROM "cache"%'
+                         ORDER BY "name""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.get_string("name").unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(cache_triggers(&rltbl), Vec::<String>::new());
+
+        // Only the tables whose triggers are not yet as the strategy requires are changed:
+        let tables = block_on(rltbl.apply_caching_strategy(&CachingStrategy::Trigger)).unwrap();
+        assert!(tables.contains(&"penguin".to_string()));
+        assert_eq!(rltbl.caching_strategy, CachingStrategy::Trigger);
+        let triggers = cache_triggers(&rltbl);
+        for trigger in ["penguin_insert", "penguin_update", "penguin_delete"] {
+            assert!(triggers.contains(&trigger.to_string()));
         }
-        tw.flush().expect("TabWriter to flush");
-        let written = String::from_utf8(tw.into_inner().unwrap()).unwrap();
-        write!(f, "{written}")
+        assert!(triggers.contains(&"island_cache_after_update".to_string()));
+        let tables = block_on(rltbl.apply_caching_strategy(&CachingStrategy::Trigger)).unwrap();
+        assert_eq!(tables, Vec::<String>::new());
+
+        // Changes through the triggers clear the cache:
+        let sql = r#"INSERT INTO "cache" ("tables", "statement", "parameters", "value")
+                     VALUES ('["penguin"]', 'SELECT 1', '[]', '[]')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "penguin" SET "species" = 'Gentoo' WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"SELECT COUNT(1) FROM "cache""#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(0))
+        );
+
+        // Triggers that are left from another strategy are reported, and dropped:
+        rltbl.caching_strategy = CachingStrategy::None;
+        let failures = block_on(rltbl.doctor())
+            .unwrap()
+            .into_iter()
+            .filter(|diagnosis| !diagnosis.ok && diagnosis.check == "caching")
+            .map(|diagnosis| diagnosis.detail)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            [
+                "Caching trigger(s) are left from another caching strategy: \
+                 'island_cache_after_insert', 'island_cache_after_update', \
+                 'island_cache_after_delete'"
+            ]
+        );
+        block_on(rltbl.apply_caching_strategy(&CachingStrategy::None)).unwrap();
+        assert_eq!(cache_triggers(&rltbl), Vec::<String>::new());
+        let select = rltbl::select::Select::from("penguin");
+        let penguins = block_on(rltbl.count(&select)).unwrap();
+        assert_eq!(penguins, 5);
     }
-}
 
-// Web Site Stuff
+    #[test]
+    fn test_write_path_change_capture() {
+        use rltbl::core::ChangeCaptureMode;
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Site {
-    pub title: String,
-    pub root: String,
-    pub editable: bool,
-    pub user: Account,
-    pub users: IndexMap<String, UserCursor>,
-    pub tables: Vec<String>,
-}
+        let rltbl = block_on(Relatable::init_with_change_capture(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::Trigger,
+            &ChangeCaptureMode::WritePath,
+        ))
+        .unwrap();
+        block_on(rltbl.create_demo_column_table(&true)).unwrap();
+        block_on(rltbl.create_demo_datatype_table(&true)).unwrap();
+        block_on(rltbl.create_penguin_table(None, &true, 5)).unwrap();
+        block_on(rltbl.create_island_table(None, &true)).unwrap();
+        assert_eq!(
+            block_on(rltbl.get_change_capture_mode()).unwrap(),
+            ChangeCaptureMode::WritePath
+        );
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct Account {
-    name: String,
-    color: String,
-}
+        // No triggers are created, but the _order of every row is filled in all the same:
+        let sql = r#"SELECT COUNT(1) FROM "sqlite_master" WHERE "type" = 'trigger'"#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(0))
+        );
+        for table in ["table", "column", "datatype", "island"] {
+            let sql = format!(r#"SELECT COUNT(1) FROM "{table}" WHERE "_order" IS NULL"#);
+            assert_eq!(
+                block_on(rltbl.connection.query_value(&sql, None)).unwrap(),
+                Some(json!(0))
+            );
+        }
+        let sql = r#"SELECT "_order" FROM "island" WHERE "island" = 'Dream'"#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(3000))
+        );
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Cursor {
-    table: String,
-    row: u64,
-    column: String,
-}
+        // The cache is cleared by the write paths instead of by triggers:
+        let sql = r#"INSERT INTO "cache" ("tables", "statement", "parameters", "value")
+                     VALUES ('["penguin"]', 'SELECT 1', '[]', '[]')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let mut row = JsonRow::from_strings(&vec!["island"]);
+        row.content.insert("island".to_string(), json!("Dream"));
+        let new_row = block_on(rltbl.add_row("penguin", "rltbl", None, &row)).unwrap();
+        assert_eq!(new_row.id, 6);
+        assert_eq!(new_row.order, 6000);
+        let sql = r#"SELECT COUNT(1) FROM "cache""#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(0))
+        );
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct UserCursor {
-    name: String,
-    color: String,
-    cursor: Cursor,
-    datetime: String,
-}
+        // Features that depend on triggers are refused, and none are reported missing:
+        assert!(block_on(rltbl.add_search("penguin", &[])).is_err());
+        let failures = block_on(rltbl.doctor())
+            .unwrap()
+            .into_iter()
+            .filter(|diagnosis| {
+                !diagnosis.ok && ["triggers", "caching"].contains(&diagnosis.check.as_str())
+            })
+            .collect::<Vec<_>>();
+        assert!(failures.is_empty(), "{failures:?}");
+    }
 
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub struct Page {
-    pub path: String,
-    pub formats: IndexMap<String, String>,
-    pub tabs: Vec<Tab>,
-}
+    #[test]
+    fn test_blame() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+
+        let rltbl = memory_demo(3);
+
+        // Nothing has changed since the table was loaded:
+        let blame = block_on(rltbl.blame("penguin", "species")).unwrap();
+        assert_eq!(blame.len(), 3);
+        assert!(blame.iter().all(|b| b.change_id.is_none()));
+
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: Some("Fix the species".to_string()),
+            changes: vec![Change::Update {
+                row: 2,
+                column: "species".to_string(),
+                before: blame[1].value.clone(),
+                after: json!("Pygoscelis papua"),
+            }],
+        }))
+        .unwrap();
+
+        // Only the changed row is attributed to the change:
+        let blame = block_on(rltbl.blame("penguin", "species")).unwrap();
+        assert_eq!(blame[1].row, 2);
+        assert_eq!(blame[1].value, json!("Pygoscelis papua"));
+        assert_eq!(blame[1].change_id, Some(1));
+        assert_eq!(blame[1].user, Some("rltbl".to_string()));
+        assert_eq!(blame[1].message, Some("Fix the species".to_string()));
+        assert!(blame[0].change_id.is_none());
+        assert!(blame[2].change_id.is_none());
+
+        // Other columns of the changed row are unaffected:
+        let blame = block_on(rltbl.blame("penguin", "island")).unwrap();
+        assert!(blame.iter().all(|b| b.change_id.is_none()));
+
+        assert!(block_on(rltbl.blame("penguin", "no_such_column")).is_err());
+    }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Tab {
-    pub table: String,
-    pub active: bool,
-    pub url: String,
-    pub count: String,
+    #[test]
+    fn test_change_metrics() {
+        use rltbl::core::{Change, ChangeAction, ChangeMetrics, ChangeSet};
+
+        let rltbl = memory_demo(3);
+        let set_body_mass = |after: JsonValue| {
+            block_on(rltbl.set_values(&ChangeSet {
+                user: "rltbl".to_string(),
+                action: ChangeAction::Do,
+                table: "penguin".to_string(),
+                description: "Set one value".to_string(),
+                message: None,
+                changes: vec![Change::Update {
+                    row: 1,
+                    column: "body_mass".to_string(),
+                    before: JsonValue::Null,
+                    after,
+                }],
+            }))
+            .unwrap();
+        };
+        let metrics = |change_id: u64| {
+            let sql = format!(
+                r#"SELECT "rows_affected", "messages_added", "messages_removed", "elapsed_ms"
+                     FROM "change" WHERE "change_id" = {change_id}"#
+            );
+            let record = block_on(rltbl.connection.query_one(&sql, None)).unwrap();
+            ChangeMetrics::from_record(&record.unwrap()).unwrap()
+        };
+
+        // An invalid value adds a message, which is removed once the value is fixed:
+        set_body_mass(json!("heavy"));
+        let added = metrics(1);
+        assert_eq!((added.rows_affected, added.messages_removed), (1, 0));
+        assert!(added.messages_added > 0);
+        set_body_mass(json!(3000));
+        let removed = metrics(2);
+        assert_eq!((removed.rows_affected, removed.messages_added), (1, 0));
+        assert_eq!(removed.messages_removed, added.messages_added);
+
+        block_on(rltbl.delete_row("penguin", "rltbl", 3)).unwrap();
+        assert_eq!(metrics(3).rows_affected, 1);
+
+        // The metrics are served with the changes to the table:
+        let feed = block_on(rltbl.get_change_feed("penguin", Some(1))).unwrap();
+        assert_eq!(feed.metrics.keys().collect::<Vec<_>>(), [&2, &3]);
+        assert_eq!(feed.metrics[&2], removed);
+        let blame = block_on(rltbl.blame("penguin", "body_mass")).unwrap();
+        assert_eq!(blame[0].metrics, Some(removed));
+    }
 }
@@ -27,6 +27,9 @@ pub mod cli;
 /// Web server
 pub mod web;
 
+/// Client for remote web servers
+pub mod remote;
+
 ///////////////////////////////////////////////////////////////////////////////
 // Global constants and other lookups
 ///////////////////////////////////////////////////////////////////////////////
@@ -0,0 +1,141 @@
+//! # rltbl/relatable
+//!
+//! This is [relatable](crate) (rltbl::[remote](crate::remote)).
+
+use crate as rltbl;
+use rltbl::{
+    cli::ValidationSummary,
+    core::{Blame, ChangeSet, ColumnStats, RelatableError, ResultSet, TableLoad},
+    select::{Format, Select},
+};
+
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+
+/// A client for the HTTP API of a remote rltbl server (see [rltbl::web]), which allows data to be
+/// read and changed without access to the server's database.
+#[derive(Clone, Debug)]
+pub struct Remote {
+    /// The root URL of the server, e.g., `https://example.com/rltbl`
+    pub url: String,
+}
+
+impl Remote {
+    /// Create a client for the server at the given root URL.
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Convert the response to a request to the server, or the error returned instead, to the
+    /// JSON value that the server responded with.
+    fn parse<T: DeserializeOwned>(
+        url: &str,
+        response: std::result::Result<ureq::Response, ureq::Error>,
+    ) -> Result<T> {
+        match response {
+            Ok(response) => Ok(response.into_json()?),
+            Err(ureq::Error::Status(status, response)) => {
+                let detail = response.into_string().unwrap_or_default();
+                let detail = format!("{url} responded with {status}: {detail}");
+                Err(match status {
+                    403 => RelatableError::UserError(detail),
+                    404 => RelatableError::MissingError(detail),
+                    _ => RelatableError::DataError(detail),
+                }
+                .into())
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Fetch the results of the given [Select] from the server.
+    pub fn fetch(&self, select: &Select) -> Result<ResultSet> {
+        tracing::trace!("Remote::fetch({select:?})");
+        let url = format!(
+            "{root}{path}",
+            root = self.url,
+            path = select.to_url("/table", &Format::Json)?
+        );
+        let content: JsonValue = Remote::parse(&url, ureq::get(&url).call())?;
+        match content.get("result") {
+            Some(result) => Ok(serde_json::from_value(result.clone())?),
+            None => Err(RelatableError::DataError(format!("No result found at {url}")).into()),
+        }
+    }
+
+    /// Get the value of the given column of the given row of the given table from the server.
+    pub fn get_value(&self, table: &str, row: u64, column: &str) -> Result<JsonValue> {
+        tracing::trace!("Remote::get_value({table}, {row}, {column})");
+        let mut select = Select::from(table);
+        select.eq("_id", &row)?;
+        let result = self.fetch(&select.limit(&1))?;
+        let row = result
+            .rows
+            .first()
+            .ok_or(RelatableError::MissingError(format!(
+                "No row {row} in table '{table}'"
+            )))?;
+        match row.cells.get(column) {
+            Some(cell) => Ok(cell.value.clone()),
+            None => Err(RelatableError::MissingError(format!(
+                "No column '{column}' in table '{table}'"
+            ))
+            .into()),
+        }
+    }
+
+    /// Get the last change to the given column of each row of the given table from the server (see
+    /// [Relatable::blame()](crate::core::Relatable::blame())).
+    pub fn blame(&self, table: &str, column: &str) -> Result<Vec<Blame>> {
+        tracing::trace!("Remote::blame({table}, {column})");
+        let url = format!("{root}/table/{table}/blame/{column}.json", root = self.url);
+        Remote::parse(&url, ureq::get(&url).call())
+    }
+
+    /// Get statistics about the values of each of the columns of the given table from the server
+    /// (see [Relatable::column_stats()](crate::core::Relatable::column_stats())).
+    pub fn column_stats(&self, table: &str) -> Result<Vec<ColumnStats>> {
+        tracing::trace!("Remote::column_stats({table})");
+        let url = format!("{root}/table/{table}/stats.json", root = self.url);
+        Remote::parse(&url, ureq::get(&url).call())
+    }
+
+    /// Apply the given [ChangeSet] on the server, returning the changes that were made.
+    pub fn set_values(&self, changeset: &ChangeSet) -> Result<ChangeSet> {
+        tracing::trace!("Remote::set_values({changeset:?})");
+        let url = format!(
+            "{root}/table/{table}",
+            root = self.url,
+            table = changeset.table
+        );
+        Remote::parse(&url, ureq::post(&url).send_json(json!(changeset)))
+    }
+
+    /// Load the given TSV content into the given table on the server, replacing the table if it
+    /// exists and `force` is set, and committing the change to git using the optional `message`.
+    pub fn load_table(
+        &self,
+        table: &str,
+        content: &str,
+        force: bool,
+        message: Option<&str>,
+    ) -> Result<TableLoad> {
+        tracing::trace!("Remote::load_table({table}, content, {force}, {message:?})");
+        let url = format!("{root}/load/{table}?force={force}", root = self.url);
+        let mut request = ureq::post(&url).set("Content-Type", "text/tab-separated-values");
+        if let Some(message) = message {
+            request = request.set("X-Change-Message", message);
+        }
+        Remote::parse(&url, request.send_string(content))
+    }
+
+    /// Validate the given table on the server, returning a summary of its messages.
+    pub fn validate_table(&self, table: &str) -> Result<ValidationSummary> {
+        tracing::trace!("Remote::validate_table({table})");
+        let url = format!("{root}/validate/{table}", root = self.url);
+        Remote::parse(&url, ureq::post(&url).call())
+    }
+}
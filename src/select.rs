@@ -4,16 +4,40 @@
 
 use crate::{
     core::{Page, Relatable, RelatableError, Tab, DEFAULT_LIMIT},
-    sql::{self, DbKind, SqlParam},
-    table::Table,
+    sql::{self, DbKind, SqlFormat, SqlParam},
+    table::{Cell, Collation, Column, Datatype, Locale, Partition, Row, Table},
 };
 use anyhow::Result;
+use base64::prelude::{Engine as _, BASE64_URL_SAFE_NO_PAD};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta, Utc};
 use enquote::unquote;
 use indexmap::IndexMap;
+use lazy_static::lazy_static;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, to_value, Value as JsonValue};
-use std::collections::{BTreeSet, HashSet};
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    sync::Mutex,
+    time::Duration,
+};
+
+/// The largest number of values of an `in` or `not in` filter that are bound as parameters of
+/// their own. The values of a longer list are bound together, as a single JSON array, so that
+/// any number of them can be given without exceeding the maximum number of parameters of a query
+/// (see [MAX_PARAMS_SQLITE](sql::MAX_PARAMS_SQLITE)).
+pub const MAX_IN_PARAMS: usize = 1000;
+
+/// The maximum number of entries in the [SQL_CACHE]. The cache is emptied once it is full.
+pub const SQL_CACHE_SIZE: usize = 1000;
+
+lazy_static! {
+    /// The SQL generated for each [Select] shape (see [Select::to_shape()]), keyed on the database
+    /// kind along with the JSON representation of the shape.
+    static ref SQL_CACHE: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    /// A date relative to the current one, or a time relative to the current one, e.g., `now-7d`
+    static ref RELATIVE_DATE: Regex = Regex::new(r"^(now|today)(?:([-+ ])(\d+)([smhdw]))?$").unwrap();
+}
 
 /// Represents a SELECT statement.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
@@ -22,10 +46,81 @@ pub struct Select {
     pub view_name: String,
     pub select: Vec<SelectField>,
     pub joins: Vec<Join>,
-    pub limit: usize,
+    /// The number of rows to return (see [Limit])
+    #[serde(default)]
+    pub limit: Limit,
     pub offset: usize,
     pub filters: Vec<Filter>,
+    /// Boolean combinations of filters, which are ANDed with each other and with the filters
+    #[serde(default)]
+    pub filter_exprs: Vec<FilterExpr>,
     pub order_by: Vec<(String, Order)>,
+    /// Whether to look up the display labels of the values of the [display_columns](Select::display_columns)
+    #[serde(default)]
+    pub display: bool,
+    /// The columns whose values are the keys of rows in tables that have a label column. These
+    /// are filled in by [Relatable::fetch()] when [display](Select::display) is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub display_columns: Vec<DisplayColumn>,
+    /// The collations of the columns of the table that have one (see [Collation]), which
+    /// determine how the select is ordered by them. These are filled in by [Relatable::fetch()].
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub collations: IndexMap<String, Collation>,
+    /// Only return the rows that come after this position in the table's default order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<PageCursor>,
+    /// Only return the rows that come before this position in the table's default order
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<PageCursor>,
+    /// The partitions of the table, if it is partitioned (see
+    /// [Relatable::partition_table()]). These are filled in by [Relatable::fetch()], and only
+    /// those that can match the select's filters on the partition column are read.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<Partition>,
+    /// Whether to also return the rows of the table that have been archived (see
+    /// [Relatable::archive_rows()]), which are otherwise left out
+    #[serde(default)]
+    pub include_archived: bool,
+    /// Whether to estimate the total number of rows of the results from the database's
+    /// statistics, which is much faster than counting them for a large table, rather than count
+    /// them exactly. Only a select of all of the rows of a table can be estimated (see
+    /// [Relatable::estimate_count()]), and the others are counted exactly regardless.
+    #[serde(default)]
+    pub estimate_count: bool,
+    /// Whether to also compute the sum and the mean of each numeric column over all of the rows
+    /// that match the select's filters, and not only those on the current page (see
+    /// [Relatable::totals()])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub totals: bool,
+    /// Return this many of the rows, chosen at random, instead of a page of them in order, e.g.,
+    /// to spot-check a large table (see [Select::sample()])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample: Option<usize>,
+    /// The percentage of the blocks of the table that PostgreSQL reads, using `TABLESAMPLE
+    /// SYSTEM`, before choosing the [sample](Select::sample) from the rows in them, rather than
+    /// reading every row. This is filled in by [Relatable::fetch()] for large tables.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sample_percent: Option<f64>,
+    /// Whether the [limit](Select::limit), or the size of the [sample](Select::sample), of this
+    /// select was reduced to a maximum page size (see [Select::max_limit()])
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub limited: bool,
+    /// The locale in which to export the results, e.g., as CSV (see
+    /// [ResultSet::to_csv()](crate::core::ResultSet::to_csv()))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locale: Option<Locale>,
+    /// The common table expressions defined in this select's WITH clause
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub with: Vec<Cte>,
+    /// Indicates whether the table has a materialized _change_id column, in which case it is used
+    /// in place of a subquery on the history table.
+    pub materialized_change_id: bool,
+    /// The longest that the queries run to fetch and count the rows of this select may run for,
+    /// after which they are cancelled and a
+    /// [TimeoutError](crate::core::RelatableError::TimeoutError) is returned. This is not part
+    /// of the select's SQL, and so is not serialized.
+    #[serde(skip)]
+    pub timeout: Option<Duration>,
 }
 
 impl Select {
@@ -34,7 +129,6 @@ impl Select {
         tracing::trace!("Select::from({table_name:?})");
         Self {
             table_name: table_name.to_string(),
-            limit: DEFAULT_LIMIT,
             ..Default::default()
         }
     }
@@ -48,99 +142,168 @@ impl Select {
     ) -> Self {
         tracing::trace!("Select::from_path_and_query({path:?}, {query_params:?})");
         let mut query_params = query_params.clone();
+
+        // A select that is given as a token (see Select::to_token()), e.g., q=eyJ0YWJsZV9u...,
+        // of which only the limit and offset may be given by the other parameters:
+        if let Some(token) = query_params.shift_remove("q") {
+            let base_table_name = path.split(".").next().unwrap_or_default();
+            match Select::from_token(&token) {
+                Ok(select) if select.table_name != base_table_name => tracing::warn!(
+                    "Ignoring select token for table '{}' at path '{path}'",
+                    select.table_name
+                ),
+                Ok(mut select) => {
+                    if let Some(limit) = query_params.get("limit").and_then(|x| x.parse().ok()) {
+                        select.limit = limit;
+                    }
+                    if let Some(offset) = query_params.get("offset").and_then(|x| x.parse().ok()) {
+                        select.offset = offset;
+                    }
+                    (select.view_name, select.materialized_change_id) =
+                        match Table::get_table(base_table_name, rltbl).await {
+                            Ok(table_config) => (table_config.view, table_config.has_change_id),
+                            Err(_) => (String::new(), false),
+                        };
+                    let max_limit = match rltbl.get_max_limit(base_table_name).await {
+                        Ok(_) if is_unlimited_export(path, &select.limit) => 0,
+                        Ok(max_limit) => max_limit,
+                        Err(error) => {
+                            tracing::warn!("{error}");
+                            rltbl.max_limit
+                        }
+                    };
+                    select.timeout = rltbl.query_timeout;
+                    return select.max_limit(&max_limit);
+                }
+                Err(error) => tracing::warn!("{error}"),
+            };
+        }
+
         let mut filters = Vec::new();
         let mut order_by = Vec::new();
         let mut select = vec![];
         if let Some(selects) = query_params.get("select") {
-            for s in selects.split(",") {
+            for s in split_top_level(selects) {
+                let s = s.as_str();
                 match s {
                     "count()" => select.push(SelectField::Expression {
                         expression: s.to_string(),
                         alias: String::new(),
                     }),
-                    _ => select.push(SelectField::Column {
-                        table: String::new(),
-                        column: s.to_string(),
-                        alias: String::new(),
-                    }),
+                    _ if s.ends_with("()") => match SelectField::aggregate_from_url(s) {
+                        Ok(field) => select.push(field),
+                        Err(error) => tracing::warn!("{error}"),
+                    },
+                    // Expressions are parsed, so that only safe ones are written into the SQL:
+                    _ if s.contains(|c: char| !c.is_alphanumeric() && c != '_' && c != '.') => {
+                        match SelectField::expression_from_url(s) {
+                            Ok(field) => select.push(field),
+                            Err(error) => tracing::warn!("{error}"),
+                        }
+                    }
+                    _ => {
+                        let (table, column) = split_qualified(s);
+                        select.push(SelectField::Column {
+                            table,
+                            column,
+                            alias: String::new(),
+                        })
+                    }
                 }
             }
         }
 
-        let limit: usize = query_params
-            .get("limit")
-            .and_then(|x| x.parse::<usize>().ok())
-            .unwrap_or(DEFAULT_LIMIT);
+        let limit = Limit::from_query_params(&query_params).unwrap_or_else(|error| {
+            tracing::warn!("{error}");
+            Limit::Default
+        });
         let offset: usize = query_params
             .get("offset")
             .and_then(|x| x.parse::<usize>().ok())
             .unwrap_or_default();
         if let Some(order) = query_params.get("order") {
             for item in order.split(",") {
-                if item.ends_with(".asc") {
-                    let column = item.replace(".asc", "");
-                    order_by.push((column, Order::ASC));
-                } else if item.ends_with(".desc") {
-                    let column = item.replace(".desc", "");
-                    order_by.push((column, Order::DESC));
-                } else {
-                    order_by.push((item.to_string(), Order::ASC));
-                }
+                order_by.push(Order::from_url(item));
             }
         }
 
+        // Cursors for keyset pagination, e.g., after=MTIzLjQ1:
+        let mut cursor = |name: &str| match query_params.shift_remove(name) {
+            Some(token) => match PageCursor::from_token(&token) {
+                Ok(cursor) => Some(cursor),
+                Err(error) => {
+                    tracing::warn!("{error}");
+                    None
+                }
+            },
+            None => None,
+        };
+        let after = cursor("after");
+        let before = cursor("before");
+        let display = query_params
+            .shift_remove("display")
+            .is_some_and(|display| display == "true");
+        let include_archived = query_params
+            .shift_remove("include_archived")
+            .is_some_and(|include_archived| include_archived == "true");
+        let estimate_count = query_params
+            .shift_remove("estimate_count")
+            .is_some_and(|estimate_count| estimate_count == "true");
+        let totals = query_params
+            .shift_remove("totals")
+            .is_some_and(|totals| totals == "true");
+        let sample = query_params
+            .shift_remove("sample")
+            .and_then(|sample| sample.parse::<usize>().ok());
+        let locale = match query_params.shift_remove("locale") {
+            Some(locale) => match locale.parse::<Locale>() {
+                Ok(locale) => Some(locale),
+                Err(error) => {
+                    tracing::warn!("{error}");
+                    None
+                }
+            },
+            None => None,
+        };
+
         query_params.shift_remove("limit");
         query_params.shift_remove("offset");
         query_params.shift_remove("order");
 
-        fn value_as_type(datatype: &Option<String>, column: &str, value: &str) -> JsonValue {
-            fn try_parse_as_int(value: &str) -> JsonValue {
-                match value.parse::<i64>() {
-                    Ok(signed) => json!(signed),
-                    _ => {
-                        tracing::warn!("Could not parse {value} as integer. Treating as string");
-                        JsonValue::String(value.to_string())
-                    }
-                }
-            }
-
-            fn try_parse_as_decimal(value: &str) -> JsonValue {
-                match value.parse::<f64>() {
-                    Ok(signed) => json!(signed),
-                    _ => {
-                        tracing::warn!("Could not parse {value} as decimal. Treating as string");
-                        JsonValue::String(value.to_string())
-                    }
-                }
-            }
-
-            if ["_id", "_order", "_change_id"].contains(&column) {
-                try_parse_as_int(value)
-            } else if ["_history", "_message"].contains(&column) {
-                JsonValue::String(value.to_string())
-            } else {
-                match datatype {
-                    Some(datatype) if datatype == "integer" => try_parse_as_int(value),
-                    Some(datatype) if datatype == "decimal" => try_parse_as_decimal(value),
-                    Some(datatype) if datatype == "text" => JsonValue::String(value.to_string()),
-                    Some(datatype) => {
-                        tracing::warn!(
-                            "Unsupported datatype: {datatype}. Treating {value} as string"
-                        );
-                        JsonValue::String(value.to_string())
-                    }
-                    None => JsonValue::String(value.to_string()),
-                }
+        // Boolean combinations of filters, e.g., or=(island.eq.Dream,sample_number.gt.5):
+        let mut filter_exprs = vec![];
+        for keyword in ["and", "or", "not.and", "not.or"] {
+            if let Some(list) = query_params.shift_remove(keyword) {
+                match FilterExpr::from_url(&format!("{keyword}{list}")) {
+                    Ok(expr) => filter_exprs.push(expr),
+                    Err(error) => tracing::warn!("{error}"),
+                };
             }
         }
 
         let base_table_name = path.split(".").next().unwrap_or_default();
-        let base_view_name = match Table::get_table(base_table_name, &rltbl).await {
-            Ok(table_config) => table_config.view,
-            Err(_) => String::new(),
-        };
+
+        // A full-text search of the table, e.g., search=Adelie Dream:
+        if let Some(query) = query_params.shift_remove("search") {
+            filters.push(Filter::Search {
+                table: String::new(),
+                search_table: base_table_name.to_string(),
+                query,
+            });
+        }
+        let (base_view_name, materialized_change_id) =
+            match Table::get_table(base_table_name, rltbl).await {
+                Ok(table_config) => (table_config.view, table_config.has_change_id),
+                Err(_) => (String::new(), false),
+            };
 
         for (lhs, pattern) in query_params {
+            // Filters on the values in JSON columns give their paths after an arrow, e.g.,
+            // _message->$[0].level=eq.error:
+            let (lhs, json_path) = match lhs.split_once("->") {
+                Some((lhs, path)) => (lhs.to_string(), Some(path.to_string())),
+                None => (lhs, None),
+            };
             let (table, column) = match lhs.split_once(".") {
                 Some((table, column)) => (table.to_string(), column.to_string()),
                 None => (String::new(), lhs),
@@ -154,154 +317,298 @@ impl Select {
                     .await
                     .expect("Can't get table '{table_name}'")
             };
-            if pattern.starts_with("like.") {
-                let value = &pattern.replace("like.", "");
-                match serde_json::from_str(value) {
-                    Ok(value) => filters.push(Filter::Like {
-                        table,
-                        column,
-                        value,
-                    }),
-                    Err(_) => filters.push(Filter::Like {
-                        table,
-                        column,
-                        value: JsonValue::String(value.to_string()),
-                    }),
-                }
-            } else {
-                let datatype = table_config.get_configured_column_attribute(&column, "datatype");
-                if pattern.starts_with("eq.") {
-                    let value = &pattern.replace("eq.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::Equal {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("not_eq.") {
-                    let value = &pattern.replace("not_eq.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::NotEqual {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("gt.") {
-                    let value = &pattern.replace("gt.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::GreaterThan {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("gte.") {
-                    let value = &pattern.replace("gte.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::GreaterThanOrEqual {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("lt.") {
-                    let value = &pattern.replace("lt.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::LessThan {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("lte.") {
-                    let value = &pattern.replace("lte.", "");
-                    let value = value_as_type(&datatype, &column, value);
-                    filters.push(Filter::LessThanOrEqual {
-                        table,
-                        column,
-                        value,
-                    })
-                } else if pattern.starts_with("is.") {
-                    let value = pattern.replace("is.", "");
-                    if value.to_lowercase() == "null" {
-                        filters.push(Filter::Is {
-                            table,
-                            column,
-                            value: JsonValue::Null,
-                        })
-                    } else {
-                        let value = value_as_type(&datatype, &column, &value);
-                        filters.push(Filter::Is {
-                            table,
-                            column,
-                            value,
-                        })
-                    }
-                } else if pattern.starts_with("is_not.") {
-                    let value = pattern.replace("is_not.", "");
-                    if value.to_lowercase() == "null" {
-                        filters.push(Filter::IsNot {
-                            table,
-                            column,
-                            value: JsonValue::Null,
-                        })
-                    } else {
-                        let value = value_as_type(&datatype, &column, &value);
-                        filters.push(Filter::IsNot {
-                            table,
-                            column,
-                            value,
-                        })
-                    }
-                } else if pattern.starts_with("in.") {
-                    let separator = Regex::new(r"\s*,\s*").unwrap();
-                    let values = pattern.replace("in.", "");
-                    let values = match values.strip_prefix("(").and_then(|s| s.strip_suffix(")")) {
-                        None => {
-                            tracing::warn!("invalid 'in' filter value {pattern}");
-                            ""
-                        }
-                        Some(s) => s,
-                    };
-                    let values = separator
-                        .split(values)
-                        .map(|v| value_as_type(&datatype, &column, v))
-                        .collect::<Vec<_>>();
-                    filters.push(Filter::In {
-                        table,
-                        column,
-                        value: json!(values),
-                    })
-                } else if pattern.starts_with("not_in.") {
-                    let separator = Regex::new(r"\s*,\s*").unwrap();
-                    let values = pattern.replace("not_in.", "");
-                    let values = match values.strip_prefix("(").and_then(|s| s.strip_suffix(")")) {
-                        None => {
-                            tracing::warn!("invalid 'not_in' filter value {pattern}");
-                            ""
-                        }
-                        Some(s) => s,
-                    };
-                    let values = separator
-                        .split(values)
-                        .map(|v| value_as_type(&datatype, &column, v))
-                        .collect::<Vec<_>>();
-                    filters.push(Filter::NotIn {
-                        table,
-                        column,
-                        value: json!(values),
-                    })
-                }
+            let datatype = table_config.get_configured_column_attribute(&column, "datatype");
+            let filter = match json_path {
+                Some(path) => url_json_path_filter(table, column, &path, &pattern),
+                None => url_filter(table, column, &pattern, &datatype),
+            };
+            if let Some(filter) = filter {
+                filters.push(filter);
             }
         }
 
-        Self {
+        // Interpret the values of the filters in the filter expressions according to the
+        // datatypes of their columns:
+        for expr in filter_exprs.iter_mut() {
+            for filter in expr.get_filters_mut() {
+                let table_name = match filter.get_table().as_str() {
+                    "" => base_table_name.to_string(),
+                    table => table.to_string(),
+                };
+                let table_config = Table::get_table(&table_name, rltbl)
+                    .await
+                    .expect("Can't get table '{table_name}'");
+                let datatype =
+                    table_config.get_configured_column_attribute(&filter.get_column(), "datatype");
+                filter.set_value_type(&datatype);
+            }
+        }
+
+        // No more rows may be asked for at once than the table's maximum page size, unless all
+        // of them are exported:
+        let max_limit = match rltbl.get_max_limit(base_table_name).await {
+            Ok(_) if is_unlimited_export(path, &limit) => 0,
+            Ok(max_limit) => max_limit,
+            Err(error) => {
+                tracing::warn!("{error}");
+                rltbl.max_limit
+            }
+        };
+
+        let mut new_select = Self {
             table_name: base_table_name.to_string(),
             view_name: base_view_name,
+            materialized_change_id,
             select,
             limit,
             offset,
             order_by,
+            after,
+            before,
+            display,
+            include_archived,
+            estimate_count,
+            totals,
+            sample,
+            locale,
             filters,
+            filter_exprs,
+            timeout: rltbl.query_timeout,
             ..Default::default()
         }
+        .max_limit(&max_limit);
+        new_select.set_nulltypes(rltbl).await;
+        new_select
+    }
+
+    /// Check this select against the configuration of its tables (see [Table::get_table()])
+    /// before it is run, and return the problems that would otherwise surface as errors from the
+    /// database: tables and columns that do not exist, including those of its joins, filters,
+    /// and subqueries, and filter values that are not of the SQL types of their columns. An
+    /// empty list means that no problems were found. Common table expressions are not checked.
+    pub async fn validate(&self, rltbl: &Relatable) -> Result<Vec<SelectProblem>> {
+        tracing::trace!("Select::validate({self:?})");
+        let mut problems = vec![];
+        let ctes = self
+            .with
+            .iter()
+            .map(|cte| cte.name().to_string())
+            .collect::<HashSet<_>>();
+
+        // The tables that the select refers to, by the names that it refers to them by. A CTE
+        // does not have a configuration, so its columns are not checked:
+        let mut tables: HashMap<String, Option<Table>> = HashMap::new();
+        let mut names = vec![(self.table_name.clone(), self.table_name.clone())];
+        for join in &self.joins {
+            names.push((join.name().to_string(), join.parts().2.to_string()));
+        }
+        for (name, table_name) in names {
+            if ctes.contains(&table_name) {
+                tables.insert(name, None);
+                continue;
+            }
+            let table = Table::get_table(&table_name, rltbl).await?;
+            if table.columns.is_empty() {
+                problems.push(SelectProblem {
+                    table: table_name.to_string(),
+                    rule: "missing_table".to_string(),
+                    message: format!("Table '{table_name}' does not exist"),
+                    ..Default::default()
+                });
+                tables.insert(name, None);
+            } else {
+                tables.insert(name, Some(table));
+            }
+        }
+        if !self.view_name.is_empty() {
+            let table = tables.get(&self.table_name).cloned().flatten();
+            tables.insert(self.view_name.clone(), table);
+        }
+        if !problems.is_empty() {
+            return Ok(problems);
+        }
+
+        // Unqualified columns may belong to any of the tables, and the aliases of the select's
+        // fields may also be ordered by. Columns that start with an underscore, e.g., _id, are
+        // meta columns, which are not configured:
+        let aliases = self
+            .select
+            .iter()
+            .filter_map(|field| match field {
+                SelectField::Column { alias, .. }
+                | SelectField::Expression { alias, .. }
+                | SelectField::Aggregate { alias, .. }
+                | SelectField::Window { alias, .. } => match alias.as_str() {
+                    "" => None,
+                    alias => Some(alias.to_string()),
+                },
+            })
+            .collect::<HashSet<_>>();
+        let find_column = |table: &str, column: &str| -> Result<Option<Column>, SelectProblem> {
+            let missing = |table: &str| SelectProblem {
+                table: table.to_string(),
+                column: column.to_string(),
+                rule: "missing_column".to_string(),
+                message: match table {
+                    "" => format!("Column '{column}' does not exist"),
+                    _ => format!("Column '{table}.{column}' does not exist"),
+                },
+                ..Default::default()
+            };
+            if column.starts_with("_") {
+                return Ok(None);
+            }
+            match table {
+                "" => {
+                    if tables.values().any(|table| table.is_none()) {
+                        return Ok(None);
+                    }
+                    tables
+                        .values()
+                        .flatten()
+                        .find_map(|table| table.columns.get(column).cloned())
+                        .map(Some)
+                        .ok_or_else(|| missing(table))
+                }
+                _ => match tables.get(table) {
+                    Some(Some(config)) => match config.columns.get(column) {
+                        Some(column) => Ok(Some(column.clone())),
+                        None => Err(missing(&config.name)),
+                    },
+                    Some(None) => Ok(None),
+                    None => Err(SelectProblem {
+                        table: table.to_string(),
+                        column: column.to_string(),
+                        rule: "missing_table".to_string(),
+                        message: format!("Table '{table}' is not part of the select"),
+                        ..Default::default()
+                    }),
+                },
+            }
+        };
+        let mut check = |table: &str, column: &str| {
+            if let Err(problem) = find_column(table, column) {
+                problems.push(problem);
+            }
+        };
+
+        for field in &self.select {
+            match field {
+                SelectField::Column { table, column, .. }
+                | SelectField::Aggregate { table, column, .. } => check(table, column),
+                SelectField::Window {
+                    partition_by,
+                    order_by,
+                    ..
+                } => {
+                    for column in partition_by.iter().chain(order_by.iter().map(|(c, _)| c)) {
+                        let (table, column) = split_qualified(column);
+                        check(&table, &column);
+                    }
+                }
+                SelectField::Expression { .. } => (),
+            };
+        }
+        for join in &self.joins {
+            let (left_table, left_column, _, right_column) = join.parts();
+            check(left_table, left_column);
+            check(join.name(), right_column);
+            for (left_column, right_column) in join.conditions().0 {
+                check(left_table, left_column);
+                check(join.name(), right_column);
+            }
+        }
+        for (column, _) in &self.order_by {
+            if !aliases.contains(column) {
+                let (table, column) = split_qualified(column);
+                check(&table, &column);
+            }
+        }
+
+        // The values of the filters must be of the SQL types of their columns, unless the filters
+        // are on the text view, or match patterns:
+        let mut filters = self.get_all_filters();
+        for join in &self.joins {
+            filters.extend(join.conditions().1);
+        }
+        let text_view = self.view_name == format!("{}_text_view", self.table_name);
+        let mut subqueries = vec![];
+        for filter in filters {
+            let (table, column, _, _) = filter.parts();
+            let config = match find_column(&table, &column) {
+                Ok(config) => config,
+                Err(problem) => {
+                    problems.push(problem);
+                    continue;
+                }
+            };
+            let values = match filter {
+                Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } => {
+                    subqueries.push(subquery);
+                    continue;
+                }
+                Filter::Like { .. }
+                | Filter::NotLike { .. }
+                | Filter::ILike { .. }
+                | Filter::Matches { .. }
+                | Filter::Search { .. }
+                | Filter::IsEmpty { .. }
+                | Filter::IsNotEmpty { .. }
+                | Filter::JsonPath { .. } => continue,
+                // Dates must be valid, whatever the SQL type of the column:
+                Filter::Before { value, .. }
+                | Filter::After { value, .. }
+                | Filter::On { value, .. } => {
+                    if let Err(error) = resolve_date(value) {
+                        problems.push(SelectProblem {
+                            table: table.to_string(),
+                            column: column.to_string(),
+                            value: value.to_string(),
+                            rule: "date".to_string(),
+                            message: error.to_string(),
+                        });
+                    }
+                    continue;
+                }
+                Filter::Between { low, high, .. } => vec![low.clone(), high.clone()],
+                Filter::In { value, .. } | Filter::NotIn { value, .. } => match value {
+                    JsonValue::Array(values) => values.clone(),
+                    value => vec![value.clone()],
+                },
+                Filter::Equal { value, .. }
+                | Filter::NotEqual { value, .. }
+                | Filter::GreaterThan { value, .. }
+                | Filter::GreaterThanOrEqual { value, .. }
+                | Filter::LessThan { value, .. }
+                | Filter::LessThanOrEqual { value, .. }
+                | Filter::Is { value, .. }
+                | Filter::IsNot { value, .. } => vec![value.clone()],
+            };
+            let Some(config) = config.filter(|_| !text_view) else {
+                continue;
+            };
+            for value in values {
+                let mut cell = Cell::from(&value);
+                cell.validate_sql_type(&config)?;
+                for message in cell.messages {
+                    problems.push(SelectProblem {
+                        table: config.table.clone(),
+                        column: config.name.clone(),
+                        value: sql::json_to_string(&value),
+                        rule: message.rule,
+                        message: format!(
+                            "Filter value '{}' for {}",
+                            sql::json_to_string(&value),
+                            message.message
+                        ),
+                    });
+                }
+            }
+        }
+        for subquery in subqueries {
+            problems.append(&mut Box::pin(subquery.validate(rltbl)).await?);
+        }
+        Ok(problems)
     }
 
     /// Get all the tables that are implicated in this select:
@@ -314,29 +621,36 @@ impl Select {
             }
         }
 
+        // The aliases of joined tables are not tables themselves, and the tables they refer to are
+        // the right tables of their joins:
+        let aliases = self
+            .joins
+            .iter()
+            .filter(|join| join.name() != join.parts().2)
+            .map(|join| join.name().to_string())
+            .collect::<HashSet<_>>();
+        let insert_unless_alias = |tables: &mut BTreeSet<String>, table: &str| {
+            if !aliases.contains(table) {
+                insert_when_non_empty(tables, table);
+            }
+        };
+
         insert_when_non_empty(&mut tables, &self.table_name);
         for field in &self.select {
             match field {
-                SelectField::Column { table, .. } => {
-                    insert_when_non_empty(&mut tables, table);
+                SelectField::Column { table, .. } | SelectField::Aggregate { table, .. } => {
+                    insert_unless_alias(&mut tables, table);
                 }
-                SelectField::Expression { .. } => (),
+                SelectField::Expression { .. } | SelectField::Window { .. } => (),
             };
         }
         for join in &self.joins {
-            match join {
-                Join::LeftJoin {
-                    left_table,
-                    right_table,
-                    ..
-                } => {
-                    insert_when_non_empty(&mut tables, &left_table);
-                    insert_when_non_empty(&mut tables, &right_table);
-                }
-            };
+            let (left_table, _, right_table, _) = join.parts();
+            insert_unless_alias(&mut tables, left_table);
+            insert_when_non_empty(&mut tables, right_table);
         }
-        for filter in &self.filters {
-            insert_when_non_empty(&mut tables, &filter.get_table());
+        for filter in self.get_all_filters() {
+            insert_unless_alias(&mut tables, &filter.get_table());
             match filter {
                 Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } => {
                     for table in subquery.get_tables() {
@@ -347,6 +661,15 @@ impl Select {
             };
         }
 
+        // The common table expressions are not tables themselves, and the tables they refer to
+        // are those of their selects:
+        for cte in &self.with {
+            tables.extend(cte.get_tables());
+        }
+        for cte in &self.with {
+            tables.remove(cte.name());
+        }
+
         tables
     }
 
@@ -396,7 +719,8 @@ impl Select {
         self
     }
 
-    /// Add an aliased expression to the SELECT clause of this select
+    /// Add an aliased expression to the SELECT clause of this select. The expression is written
+    /// into the SQL as it is, so it must not be taken from untrusted input: see [SelectExpr].
     pub fn select_expression(&mut self, expression: &str, alias: &str) -> &Self {
         self.select.push(SelectField::Expression {
             expression: expression.to_string(),
@@ -405,6 +729,98 @@ impl Select {
         self
     }
 
+    /// Add an aggregate of the given column, using the given function, to the SELECT clause of
+    /// this select. If the alias is empty, the name of the function is used. Any columns in the
+    /// SELECT clause that are not aggregated are grouped by.
+    pub fn select_aggregate(
+        &mut self,
+        function: &AggregateFunction,
+        column: &str,
+        alias: &str,
+    ) -> &Self {
+        self.select.push(SelectField::Aggregate {
+            function: *function,
+            table: String::new(),
+            column: column.to_string(),
+            alias: alias.to_string(),
+        });
+        self
+    }
+
+    /// Add the sum of the given column, with the given alias, to the SELECT clause of this select
+    pub fn sum(&mut self, column: &str, alias: &str) -> &Self {
+        self.select_aggregate(&AggregateFunction::Sum, column, alias)
+    }
+
+    /// Add the average of the given column, with the given alias, to the SELECT clause of this
+    /// select
+    pub fn avg(&mut self, column: &str, alias: &str) -> &Self {
+        self.select_aggregate(&AggregateFunction::Avg, column, alias)
+    }
+
+    /// Add the minimum of the given column, with the given alias, to the SELECT clause of this
+    /// select
+    pub fn min(&mut self, column: &str, alias: &str) -> &Self {
+        self.select_aggregate(&AggregateFunction::Min, column, alias)
+    }
+
+    /// Add the maximum of the given column, with the given alias, to the SELECT clause of this
+    /// select
+    pub fn max(&mut self, column: &str, alias: &str) -> &Self {
+        self.select_aggregate(&AggregateFunction::Max, column, alias)
+    }
+
+    /// Add the given window function, over the rows that have the same values of the given
+    /// partition_by columns, in the given order, with the given alias, to the SELECT clause of
+    /// this select. If the alias is empty, the name of the function is used.
+    pub fn select_window(
+        &mut self,
+        function: &WindowFunction,
+        partition_by: &Vec<&str>,
+        order_by: &Vec<(&str, Order)>,
+        alias: &str,
+    ) -> &Self {
+        self.select.push(SelectField::Window {
+            function: *function,
+            partition_by: partition_by
+                .iter()
+                .map(|column| column.to_string())
+                .collect(),
+            order_by: order_by
+                .iter()
+                .map(|(column, order)| (column.to_string(), order.clone()))
+                .collect(),
+            alias: alias.to_string(),
+        });
+        self
+    }
+
+    /// Determine whether this select's SELECT clause contains any aggregates
+    pub fn has_aggregates(&self) -> bool {
+        self.select
+            .iter()
+            .any(|field| matches!(field, SelectField::Aggregate { .. }))
+    }
+
+    /// Get the SQL for the columns that this select's results are grouped by, which, when there are
+    /// aggregates in its SELECT clause, are the columns in it that are not aggregated.
+    fn get_group_by(&self) -> Vec<String> {
+        match self.has_aggregates() {
+            false => vec![],
+            true => self
+                .select
+                .iter()
+                .filter_map(|field| match field {
+                    SelectField::Column { table, column, .. } => Some(match table.as_str() {
+                        "" => format!(r#""{column}""#),
+                        _ => format!(r#""{table}"."{column}""#),
+                    }),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
     /// Add all of the given table's columns to the SELECT clause of this select
     pub async fn select_all(&mut self, rltbl: &Relatable, table: &str) -> Result<&Self> {
         for column in rltbl.fetch_all_columns(&table).await? {
@@ -430,23 +846,287 @@ impl Select {
             left_column: left_column.to_string(),
             right_table: right_table.to_string(),
             right_column: right_column.to_string(),
+            alias: String::new(),
+            on: vec![],
+            filters: vec![],
         });
         self
     }
 
-    /// Order (ascending) this select by the given column
-    pub fn order_by(&mut self, column: &str) -> &Self {
-        tracing::trace!("Select::order_by({column:?})");
-        self.order_by = vec![(column.to_string(), Order::ASC)];
+    /// Add an INNER JOIN clause to this select with the given properties
+    pub fn inner_join(
+        &mut self,
+        left_table: &str,
+        left_column: &str,
+        right_table: &str,
+        right_column: &str,
+    ) -> &Self {
+        self.joins.push(Join::InnerJoin {
+            left_table: left_table.to_string(),
+            left_column: left_column.to_string(),
+            right_table: right_table.to_string(),
+            right_column: right_column.to_string(),
+            alias: String::new(),
+            on: vec![],
+            filters: vec![],
+        });
         self
     }
 
-    /// Limit the results by a given amount
-    pub fn limit(mut self, limit: &usize) -> Self {
-        tracing::trace!("Select::limit({limit})");
-        self.limit = *limit;
-        self
-    }
+    /// Add a RIGHT JOIN clause to this select with the given properties
+    pub fn right_join(
+        &mut self,
+        left_table: &str,
+        left_column: &str,
+        right_table: &str,
+        right_column: &str,
+    ) -> &Self {
+        self.joins.push(Join::RightJoin {
+            left_table: left_table.to_string(),
+            left_column: left_column.to_string(),
+            right_table: right_table.to_string(),
+            right_column: right_column.to_string(),
+            alias: String::new(),
+            on: vec![],
+            filters: vec![],
+        });
+        self
+    }
+
+    /// Add a FULL OUTER JOIN clause to this select with the given properties
+    pub fn full_join(
+        &mut self,
+        left_table: &str,
+        left_column: &str,
+        right_table: &str,
+        right_column: &str,
+    ) -> &Self {
+        self.joins.push(Join::FullJoin {
+            left_table: left_table.to_string(),
+            left_column: left_column.to_string(),
+            right_table: right_table.to_string(),
+            right_column: right_column.to_string(),
+            alias: String::new(),
+            on: vec![],
+            filters: vec![],
+        });
+        self
+    }
+
+    /// Add a LEFT JOIN clause to this select on the equality of each of the given pairs of left
+    /// and right columns, e.g., the columns of a multi-column natural key, and on the given
+    /// filters on the right table, e.g., `kind = active` (see [Select::filters()]).
+    pub fn left_join_on(
+        &mut self,
+        left_table: &str,
+        right_table: &str,
+        columns: &[(&str, &str)],
+        filters: &Vec<String>,
+    ) -> Result<&Self> {
+        tracing::trace!(
+            "Select::left_join_on({left_table}, {right_table}, {columns:?}, {filters:?})"
+        );
+        let (left_column, right_column, (on, filters)) =
+            Join::parse_conditions(right_table, columns, filters)?;
+        self.joins.push(Join::LeftJoin {
+            left_table: left_table.to_string(),
+            left_column,
+            right_table: right_table.to_string(),
+            right_column,
+            alias: String::new(),
+            on,
+            filters,
+        });
+        Ok(self)
+    }
+
+    /// Add an INNER JOIN clause to this select on the given pairs of columns and filters (see
+    /// [Select::left_join_on()]).
+    pub fn inner_join_on(
+        &mut self,
+        left_table: &str,
+        right_table: &str,
+        columns: &[(&str, &str)],
+        filters: &Vec<String>,
+    ) -> Result<&Self> {
+        tracing::trace!(
+            "Select::inner_join_on({left_table}, {right_table}, {columns:?}, {filters:?})"
+        );
+        let (left_column, right_column, (on, filters)) =
+            Join::parse_conditions(right_table, columns, filters)?;
+        self.joins.push(Join::InnerJoin {
+            left_table: left_table.to_string(),
+            left_column,
+            right_table: right_table.to_string(),
+            right_column,
+            alias: String::new(),
+            on,
+            filters,
+        });
+        Ok(self)
+    }
+
+    /// Add a RIGHT JOIN clause to this select on the given pairs of columns and filters (see
+    /// [Select::left_join_on()]).
+    pub fn right_join_on(
+        &mut self,
+        left_table: &str,
+        right_table: &str,
+        columns: &[(&str, &str)],
+        filters: &Vec<String>,
+    ) -> Result<&Self> {
+        tracing::trace!(
+            "Select::right_join_on({left_table}, {right_table}, {columns:?}, {filters:?})"
+        );
+        let (left_column, right_column, (on, filters)) =
+            Join::parse_conditions(right_table, columns, filters)?;
+        self.joins.push(Join::RightJoin {
+            left_table: left_table.to_string(),
+            left_column,
+            right_table: right_table.to_string(),
+            right_column,
+            alias: String::new(),
+            on,
+            filters,
+        });
+        Ok(self)
+    }
+
+    /// Add a FULL OUTER JOIN clause to this select on the given pairs of columns and filters (see
+    /// [Select::left_join_on()]).
+    pub fn full_join_on(
+        &mut self,
+        left_table: &str,
+        right_table: &str,
+        columns: &[(&str, &str)],
+        filters: &Vec<String>,
+    ) -> Result<&Self> {
+        tracing::trace!(
+            "Select::full_join_on({left_table}, {right_table}, {columns:?}, {filters:?})"
+        );
+        let (left_column, right_column, (on, filters)) =
+            Join::parse_conditions(right_table, columns, filters)?;
+        self.joins.push(Join::FullJoin {
+            left_table: left_table.to_string(),
+            left_column,
+            right_table: right_table.to_string(),
+            right_column,
+            alias: String::new(),
+            on,
+            filters,
+        });
+        Ok(self)
+    }
+
+    /// Refer to the right table of the join that was most recently added to this select by the
+    /// given alias, so that, e.g., a table can be joined to itself (see [Join::set_alias()]).
+    pub fn alias_join(&mut self, alias: &str) -> Result<&Self> {
+        tracing::trace!("Select::alias_join({alias:?})");
+        match self.joins.last_mut() {
+            Some(join) => {
+                join.set_alias(alias);
+                Ok(self)
+            }
+            None => Err(RelatableError::InputError(format!(
+                "There is no join to refer to by the alias '{alias}'"
+            ))
+            .into()),
+        }
+    }
+
+    /// Combine the rows of this select with those of the given select, removing duplicates (see
+    /// [CompoundSelect]).
+    pub fn union(&self, other: &Select) -> Result<CompoundSelect> {
+        CompoundSelect::from(self).union(other)
+    }
+
+    /// Combine the rows of this select with those of the given select, keeping duplicates (see
+    /// [CompoundSelect]).
+    pub fn union_all(&self, other: &Select) -> Result<CompoundSelect> {
+        CompoundSelect::from(self).union_all(other)
+    }
+
+    /// Define a common table expression with the given name, whose rows are those of the given
+    /// select, and which this select's FROM clause and joins can then refer to by name (see
+    /// [Cte]).
+    pub fn with(mut self, name: &str, select: &Select) -> Self {
+        tracing::trace!("Select::with({name:?}, {select:?})");
+        self.with.push(Cte::Select {
+            name: name.to_string(),
+            select: select.clone(),
+        });
+        self
+    }
+
+    /// Define a recursive common table expression with the given name and columns, whose rows
+    /// are those of the given compound select, which refers to the expression itself by name
+    /// (see [Cte]).
+    pub fn with_recursive(
+        mut self,
+        name: &str,
+        columns: &Vec<&str>,
+        select: &CompoundSelect,
+    ) -> Self {
+        tracing::trace!("Select::with_recursive({name:?}, {columns:?}, {select:?})");
+        self.with.push(Cte::Recursive {
+            name: name.to_string(),
+            columns: columns.iter().map(|column| column.to_string()).collect(),
+            select: select.clone(),
+        });
+        self
+    }
+
+    /// Order (ascending) this select by the given column. Note that [Select::to_sql()] always
+    /// appends `_order` as a final tie-breaker, so the order of the results is deterministic.
+    pub fn order_by(&mut self, column: &str) -> &Self {
+        tracing::trace!("Select::order_by({column:?})");
+        self.order_by = vec![(column.to_string(), Order::ASC)];
+        self
+    }
+
+    /// Order this select by the given column, in the given order, after any columns that it is
+    /// already ordered by.
+    pub fn add_order_by(&mut self, column: &str, order: &Order) -> &Self {
+        tracing::trace!("Select::add_order_by({column:?}, {order:?})");
+        self.order_by.push((column.to_string(), order.clone()));
+        self
+    }
+
+    /// Limit the results to the given number of rows. A limit of zero returns no rows at all (see
+    /// [Select::unlimited()]).
+    pub fn limit(mut self, limit: &usize) -> Self {
+        tracing::trace!("Select::limit({limit})");
+        self.limit = Limit::N(*limit);
+        self
+    }
+
+    /// Return all of the results, rather than a page of them (see [Limit::Unlimited])
+    pub fn unlimited(mut self) -> Self {
+        tracing::trace!("Select::unlimited()");
+        self.limit = Limit::Unlimited;
+        self
+    }
+
+    /// Reduce the limit of this select to the given maximum if it is greater, or if there is no
+    /// limit, as a server does for the selects made from requests, and mark the select as
+    /// [limited](Select::limited). The size of a [sample](Select::sample) is reduced in the same
+    /// way. A maximum of zero has no effect.
+    pub fn max_limit(mut self, max: &usize) -> Self {
+        tracing::trace!("Select::max_limit({max})");
+        if *max == 0 {
+            return self;
+        }
+        if self.limit.rows().is_none_or(|limit| limit > *max) {
+            self.limit = Limit::N(*max);
+            self.limited = true;
+        }
+        if let Some(sample) = self.sample.filter(|sample| sample > max) {
+            tracing::debug!("Reducing sample of {sample} rows to {max}");
+            self.sample = Some(*max);
+            self.limited = true;
+        }
+        self
+    }
 
     /// Offset the results by a given amount
     pub fn offset(mut self, offset: &usize) -> Self {
@@ -455,189 +1135,125 @@ impl Select {
         self
     }
 
-    /// Add the given filters to the select.
-    pub fn filters(mut self, filters: &Vec<String>) -> Result<Self> {
-        tracing::trace!("Select::filters({filters:?})");
-        let basic = r"[\w\-]";
-        let wildcarded = r"[\w\-%]";
-
-        // Symbolic operators:
-        let like = Regex::new(&format!(r#"^({basic}+)\s*~=\s*"?({wildcarded}+)"?$"#)).unwrap();
-        let eq = Regex::new(&format!(r#"^({basic}+)\s*=\s*"?({basic}+)"?$"#)).unwrap();
-        let not_eq = Regex::new(&format!(r#"^({basic}+)\s*!=\s*"?({basic}+)"?$"#)).unwrap();
-        let gt = Regex::new(&format!(r"^({basic}+)\s*>\s*({basic}+)$")).unwrap();
-        let gte = Regex::new(&format!(r"^({basic}+)\s*>=\s*({basic}+)$")).unwrap();
-        let lt = Regex::new(&format!(r"^({basic}+)\s*<\s*({basic}+)$")).unwrap();
-        let lte = Regex::new(&format!(r"^({basic}+)\s*<=\s*({basic}+)$")).unwrap();
-
-        // Word-like operators:
-        let is = Regex::new(&format!(r#"^({basic}+)\s+(IS|is)\s+"?({basic}+)"?$"#)).unwrap();
-        let is_not = Regex::new(&format!(
-            r#"^({basic}+)\s+(IS NOT|is not)\s+"?({basic}+)"?$"#
-        ))
-        .unwrap();
-        let is_in = Regex::new(&format!(
-            r#"^({basic}+)\s+(IN|in)\s+\(({basic}+(,\s*{basic}+)*)\)$"#
-        ))
-        .unwrap();
-        let is_not_in = Regex::new(&format!(
-            r#"^({basic}+)\s+(NOT IN|not in)\s+\(({basic}+(,\s*{basic}+)*)\)$"#
-        ))
-        .unwrap();
+    /// Include, in each cell whose column refers to the key column of a table that has a label
+    /// column, the label of the row with that key, for display in place of the cell's value.
+    pub fn display(mut self, labels: bool) -> Self {
+        tracing::trace!("Select::display({labels})");
+        self.display = labels;
+        self
+    }
 
-        fn parse_as_value(value: &str) -> Result<JsonValue> {
-            fn maybe_quote(value: &str) -> Result<JsonValue> {
-                if value.starts_with("\"") {
-                    let value = serde_json::from_str(&value)?;
-                    Ok(value)
-                } else {
-                    let value = serde_json::from_str(&format!(r#""{value}""#))?;
-                    Ok(value)
-                }
-            }
+    /// Also return the rows of the table that have been archived (see [Relatable::archive_rows()]).
+    pub fn include_archived(mut self, include_archived: bool) -> Self {
+        tracing::trace!("Select::include_archived({include_archived})");
+        self.include_archived = include_archived;
+        self
+    }
 
-            match value.parse::<i64>() {
-                Ok(signed) => Ok(json!(signed)),
-                _ => match value.parse::<f64>() {
-                    Ok(float) => Ok(json!(float)),
-                    _ => maybe_quote(value),
-                },
-            }
-        }
+    /// Estimate the total number of rows of the results, when possible, rather than count them
+    /// exactly (see [Select::estimate_count]).
+    pub fn estimate_count(mut self, estimate_count: bool) -> Self {
+        tracing::trace!("Select::estimate_count({estimate_count})");
+        self.estimate_count = estimate_count;
+        self
+    }
+
+    /// Also compute the totals of the numeric columns of the results (see [Select::totals]).
+    pub fn totals(mut self, totals: bool) -> Self {
+        tracing::trace!("Select::totals({totals})");
+        self.totals = totals;
+        self
+    }
+
+    /// Return the given number of the rows, chosen at random, instead of a page of them in order.
+    /// The select's order, limit, offset, and cursors are ignored, while its filters still apply.
+    pub fn sample(mut self, sample: usize) -> Self {
+        tracing::trace!("Select::sample({sample})");
+        self.sample = Some(sample);
+        self
+    }
+
+    /// Cancel the queries run to fetch and count the rows of this select once they have run for
+    /// longer than the given timeout (see [Select::timeout]).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        tracing::trace!("Select::timeout({timeout:?})");
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Export the results in the given locale (see
+    /// [ResultSet::to_csv()](crate::core::ResultSet::to_csv())).
+    pub fn locale(mut self, locale: &Locale) -> Self {
+        tracing::trace!("Select::locale({locale})");
+        self.locale = Some(*locale);
+        self
+    }
+
+    /// Return the rows that come after the given position in the table's default order, instead of
+    /// those before some other position or after some offset.
+    pub fn after(mut self, cursor: &PageCursor) -> Self {
+        tracing::trace!("Select::after({cursor:?})");
+        self.after = Some(cursor.clone());
+        self.before = None;
+        self.offset = 0;
+        self
+    }
+
+    /// Return the rows that come before the given position in the table's default order, instead
+    /// of those after some other position or after some offset.
+    pub fn before(mut self, cursor: &PageCursor) -> Self {
+        tracing::trace!("Select::before({cursor:?})");
+        self.before = Some(cursor.clone());
+        self.after = None;
+        self.offset = 0;
+        self
+    }
+
+    /// Determine whether the rows returned by this select are in the default order of its table,
+    /// which is required in order to page through them using [PageCursor]s.
+    pub fn has_default_order(&self) -> bool {
+        self.order_by.is_empty()
+            && self.joins.is_empty()
+            && !self.has_aggregates()
+            && self.sample.is_none()
+    }
+
+    /// Whether this select returns all of the rows of its table, and no others, so that their
+    /// number can be estimated from the database's statistics about the table.
+    pub fn selects_whole_table(&self) -> bool {
+        self.filters.is_empty()
+            && self.filter_exprs.is_empty()
+            && self.joins.is_empty()
+            && self.with.is_empty()
+            && self.partitions.is_empty()
+            && self.after.is_none()
+            && self.before.is_none()
+            && !self.include_archived
+            && !self.has_aggregates()
+    }
 
+    /// Add the given filters to the select, each of which is written in the filter language
+    /// (see [FilterExpr::parse()]), e.g., `species = Adelie`.
+    pub fn filters(mut self, filters: &Vec<String>) -> Result<Self> {
+        tracing::trace!("Select::filters({filters:?})");
         for filter in filters {
-            tracing::trace!("Applying filter: {filter}");
-            if like.is_match(&filter) {
-                let captures = like.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::Like {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if eq.is_match(&filter) {
-                let captures = eq.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::Equal {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if not_eq.is_match(&filter) {
-                let captures = not_eq.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::NotEqual {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if gt.is_match(&filter) {
-                let captures = gt.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::GreaterThan {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if gte.is_match(&filter) {
-                let captures = gte.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::GreaterThanOrEqual {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if lt.is_match(&filter) {
-                let captures = lt.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::LessThan {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if lte.is_match(&filter) {
-                let captures = lte.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(2).unwrap().as_str();
-                let value = parse_as_value(value)?;
-                self.filters.push(Filter::LessThanOrEqual {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if is.is_match(&filter) {
-                let captures = is.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(3).unwrap().as_str();
-                let value = match value.to_lowercase().as_str() {
-                    "null" => JsonValue::Null,
-                    _ => parse_as_value(value)?,
-                };
-                self.filters.push(Filter::Is {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if is_not.is_match(&filter) {
-                let captures = is_not.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let value = &captures.get(3).unwrap().as_str();
-                let value = match value.to_lowercase().as_str() {
-                    "null" => JsonValue::Null,
-                    _ => parse_as_value(value)?,
-                };
-                self.filters.push(Filter::IsNot {
-                    table: "".to_string(),
-                    column,
-                    value,
-                });
-            } else if is_in.is_match(&filter) {
-                let captures = is_in.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let values = &captures.get(3).unwrap().as_str();
-                let separator = Regex::new(r"\s*,\s*").unwrap();
-                let values = separator
-                    .split(values)
-                    .map(|v| serde_json::from_str::<JsonValue>(v).unwrap_or(json!(v.to_string())))
-                    .collect::<Vec<_>>();
-                self.filters.push(Filter::In {
-                    table: "".to_string(),
-                    column,
-                    value: json!(values),
-                });
-            } else if is_not_in.is_match(&filter) {
-                let captures = is_not_in.captures(&filter).unwrap();
-                let column = captures.get(1).unwrap().as_str().to_string();
-                let values = &captures.get(3).unwrap().as_str();
-                let separator = Regex::new(r"\s*,\s*").unwrap();
-                let values = separator
-                    .split(values)
-                    .map(|v| serde_json::from_str::<JsonValue>(v).unwrap_or(json!(v.to_string())))
-                    .collect::<Vec<_>>();
-                self.filters.push(Filter::NotIn {
-                    table: "".to_string(),
-                    column,
-                    value: json!(values),
-                });
-            } else {
-                return Err(RelatableError::ConfigError(format!("invalid filter {filter}")).into());
-            }
+            self.filters.push(FilterExpr::parse(filter)?);
         }
         Ok(self)
     }
 
+    /// Add a full-text search of this select's table, using its search index, for all of the
+    /// words in the given query
+    pub fn search(mut self, query: &str) -> Self {
+        tracing::trace!("Select::search({query:?})");
+        self.filters.push(Filter::Search {
+            table: String::new(),
+            search_table: self.table_name.clone(),
+            query: query.to_string(),
+        });
+        self
+    }
+
     /// Add a like filter for the given column on the given value, which may include '%' wildcards
     pub fn like<T>(mut self, column: &str, value: &T) -> Result<Self>
     where
@@ -652,6 +1268,78 @@ impl Select {
         Ok(self)
     }
 
+    /// Add a not-like filter for the given column on the given value, which may include '%'
+    /// wildcards
+    pub fn not_like<T>(mut self, column: &str, value: &T) -> Result<Self>
+    where
+        T: Serialize,
+    {
+        tracing::trace!("Select::not_like({column:?}, value)");
+        self.filters.push(Filter::NotLike {
+            table: "".to_string(),
+            column: column.to_string(),
+            value: to_value(value)?,
+        });
+        Ok(self)
+    }
+
+    /// Add a case-insensitive like filter for the given column on the given value, which may
+    /// include '%' wildcards
+    pub fn ilike<T>(mut self, column: &str, value: &T) -> Result<Self>
+    where
+        T: Serialize,
+    {
+        tracing::trace!("Select::ilike({column:?}, value)");
+        self.filters.push(Filter::ILike {
+            table: "".to_string(),
+            column: column.to_string(),
+            value: to_value(value)?,
+        });
+        Ok(self)
+    }
+
+    /// Add a filter for the given column on the given regular expression, which matches the
+    /// column's values anywhere unless it is anchored with '^' or '$'
+    pub fn matches(mut self, column: &str, pattern: &str) -> Result<Self> {
+        tracing::trace!("Select::matches({column:?}, {pattern:?})");
+        if let Err(error) = Regex::new(pattern) {
+            return Err(RelatableError::InputError(format!(
+                "Invalid regular expression '{pattern}': {error}"
+            ))
+            .into());
+        }
+        self.filters.push(Filter::Matches {
+            table: "".to_string(),
+            column: column.to_string(),
+            value: JsonValue::String(pattern.to_string()),
+        });
+        Ok(self)
+    }
+
+    /// Add a filter that compares the value at the given JSON path, e.g., `$[0].level`, in the
+    /// JSON text of the given column with the given value, using the given operator, which must
+    /// be one of the [JSON_PATH_OPERATORS].
+    pub fn json_path<T>(mut self, column: &str, path: &str, op: &str, value: &T) -> Result<Self>
+    where
+        T: Serialize,
+    {
+        tracing::trace!("Select::json_path({column:?}, {path:?}, {op:?}, value)");
+        json_path_steps(path)?;
+        if !JSON_PATH_OPERATORS.contains(&op) {
+            return Err(
+                RelatableError::InputError(format!("Invalid JSON path operator '{op}'")).into(),
+            );
+        }
+        self.filters.push(Filter::JsonPath {
+            table: "".to_string(),
+            column: column.to_string(),
+            path: path.to_string(),
+            op: op.to_string(),
+            value: to_value(value)?,
+        });
+        Ok(self)
+    }
+
     /// Add an equals filter on the given column and value.
     pub fn eq<T>(&mut self, column: &str, value: &T) -> Result<&Self>
     where
@@ -806,13 +1494,132 @@ impl Select {
         Ok(self)
     }
 
-    /// Add an in-subquery filter on the given column and value.
-    pub fn is_in_subquery(&mut self, column: &str, subquery: &Select) -> &Self {
-        tracing::trace!("Select::is_in_subquery({column:?}, {subquery:?})");
-        let target = match self.view_name.as_str() {
-            "" => &self.table_name,
-            _ => &self.view_name,
-        };
+    /// Add a between filter on the given column, matching the values from `low` to `high`,
+    /// inclusive.
+    pub fn between<T>(mut self, column: &str, low: &T, high: &T) -> Result<Self>
+    where
+        T: Serialize,
+    {
+        tracing::trace!("Select::between({column:?}, low, high)");
+        self.filters.push(Filter::Between {
+            table: "".to_string(),
+            column: column.to_string(),
+            low: to_value(low)?,
+            high: to_value(high)?,
+        });
+        Ok(self)
+    }
+
+    /// Add a filter on the given column matching the dates, or dates and times, before the given
+    /// one, which may be relative to the current date or time, e.g., `now-7d` (see
+    /// [resolve_date()]).
+    pub fn is_before(mut self, column: &str, value: &str) -> Result<Self> {
+        tracing::trace!("Select::is_before({column:?}, {value:?})");
+        let value = JsonValue::String(value.to_string());
+        resolve_date(&value)?;
+        self.filters.push(Filter::Before {
+            table: "".to_string(),
+            column: column.to_string(),
+            value,
+        });
+        Ok(self)
+    }
+
+    /// Add a filter on the given column matching the dates, or dates and times, after the given
+    /// one (see [Select::is_before()]).
+    pub fn is_after(mut self, column: &str, value: &str) -> Result<Self> {
+        tracing::trace!("Select::is_after({column:?}, {value:?})");
+        let value = JsonValue::String(value.to_string());
+        resolve_date(&value)?;
+        self.filters.push(Filter::After {
+            table: "".to_string(),
+            column: column.to_string(),
+            value,
+        });
+        Ok(self)
+    }
+
+    /// Add a filter on the given column matching the dates, or dates and times, on the date of the
+    /// given one (see [Select::is_before()]).
+    pub fn is_on(mut self, column: &str, value: &str) -> Result<Self> {
+        tracing::trace!("Select::is_on({column:?}, {value:?})");
+        let value = JsonValue::String(value.to_string());
+        resolve_date(&value)?;
+        self.filters.push(Filter::On {
+            table: "".to_string(),
+            column: column.to_string(),
+            value,
+        });
+        Ok(self)
+    }
+
+    /// Add a filter on the given column matching the rows whose values are NULL, or are allowed by
+    /// the column's nulltype, e.g., the empty string (see [Filter::IsEmpty]).
+    pub fn is_empty(mut self, column: &str) -> Self {
+        tracing::trace!("Select::is_empty({column:?})");
+        self.filters.push(Filter::IsEmpty {
+            table: "".to_string(),
+            column: column.to_string(),
+            values: vec![],
+        });
+        self
+    }
+
+    /// Add a filter on the given column matching the rows whose values are neither NULL nor
+    /// allowed by the column's nulltype (see [Filter::IsEmpty]).
+    pub fn is_not_empty(mut self, column: &str) -> Self {
+        tracing::trace!("Select::is_not_empty({column:?})");
+        self.filters.push(Filter::IsNotEmpty {
+            table: "".to_string(),
+            column: column.to_string(),
+            values: vec![],
+        });
+        self
+    }
+
+    /// Fill in the values that the nulltypes of the columns of this select's
+    /// [IsEmpty](Filter::IsEmpty) and [IsNotEmpty](Filter::IsNotEmpty) filters allow, from the
+    /// configuration of their tables. This is done by [Select::from_path_and_query()] and
+    /// [Relatable::fetch()]. Filters on tables that are not configured, e.g., the aliases of
+    /// joins, only match NULL values.
+    pub async fn set_nulltypes(&mut self, rltbl: &Relatable) {
+        tracing::trace!("Select::set_nulltypes({self:?})");
+        let base_table = self.table_name.clone();
+        let view_name = self.view_name.clone();
+        let mut filters = self.filters.iter_mut().collect::<Vec<_>>();
+        for expr in self.filter_exprs.iter_mut() {
+            filters.append(&mut expr.get_filters_mut());
+        }
+        let mut tables: HashMap<String, Option<Table>> = HashMap::new();
+        for filter in filters {
+            if !matches!(filter, Filter::IsEmpty { .. } | Filter::IsNotEmpty { .. }) {
+                continue;
+            }
+            let (table_name, column, _, _) = filter.parts();
+            let table_name = match table_name.as_str() {
+                "" => base_table.clone(),
+                name if name == view_name => base_table.clone(),
+                name => name.to_string(),
+            };
+            if !tables.contains_key(&table_name) {
+                let table = Table::get_table(&table_name, rltbl).await.ok();
+                tables.insert(table_name.clone(), table);
+            }
+            let nulltype = tables[&table_name]
+                .as_ref()
+                .and_then(|table| table.columns.get(&column))
+                .and_then(|column| column.nulltype.clone());
+            filter.set_nulltype(&nulltype);
+        }
+    }
+
+    /// Add an in-subquery filter on the given column and value.
+    pub fn is_in_subquery(&mut self, column: &str, subquery: &Select) -> &Self {
+        tracing::trace!("Select::is_in_subquery({column:?}, {subquery:?})");
+        let target = match self.view_name.as_str() {
+            "" => &self.table_name,
+            _ => &self.view_name,
+        };
         self.filters.push(Filter::InSubquery {
             table: target.to_string(),
             column: column.to_string(),
@@ -836,103 +1643,250 @@ impl Select {
         self
     }
 
+    /// Add the given filter expression to this select
+    pub fn filter_expr(mut self, expr: FilterExpr) -> Self {
+        tracing::trace!("Select::filter_expr({expr:?})");
+        self.filter_exprs.push(expr);
+        self
+    }
+
+    /// Add a group of filters, given in the same form as for [Select::filters()], at least one
+    /// of which must match.
+    pub fn or_group(self, filters: &Vec<String>) -> Result<Self> {
+        tracing::trace!("Select::or_group({filters:?})");
+        let group = Select::from(&self.table_name).filters(filters)?.filters;
+        Ok(self.filter_expr(FilterExpr::Or(
+            group
+                .into_iter()
+                .map(|filter| FilterExpr::Filter(Box::new(filter)))
+                .collect(),
+        )))
+    }
+
+    /// Add a group of filters, given in the same form as for [Select::filters()], which must not
+    /// all match.
+    pub fn not_group(self, filters: &Vec<String>) -> Result<Self> {
+        tracing::trace!("Select::not_group({filters:?})");
+        let group = Select::from(&self.table_name).filters(filters)?.filters;
+        Ok(self.filter_expr(FilterExpr::Not(Box::new(FilterExpr::And(
+            group
+                .into_iter()
+                .map(|filter| FilterExpr::Filter(Box::new(filter)))
+                .collect(),
+        )))))
+    }
+
+    /// Get all of the filters of this select, including those in its filter expressions
+    pub fn get_all_filters(&self) -> Vec<&Filter> {
+        let mut filters = self.filters.iter().collect::<Vec<_>>();
+        for expr in &self.filter_exprs {
+            filters.append(&mut expr.get_filters());
+        }
+        filters
+    }
+
     /// Convert the filter to a tuple consisting of an SQL string supported by the given database
     /// kind, and a vector of parameters that must be bound to the string before executing it.
     pub fn to_sql(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
         tracing::trace!("Select::to_sql({self:?}, {kind:?})");
-        let mut sql_param_gen = SqlParam::new(kind);
+        self.to_sql_with(&mut SqlParam::new(kind))
+    }
+
+    /// Convert the select to SQL, as [Select::to_sql()] does, written in the given format, e.g.,
+    /// on one line for logging (see [sql::format_sql()])
+    pub fn to_formatted_sql(
+        &self,
+        kind: &DbKind,
+        format: &SqlFormat,
+    ) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("Select::to_formatted_sql({self:?}, {kind:?}, {format:?})");
+        let (sql, params) = self.to_sql(kind)?;
+        Ok((sql::format_sql(&sql, format), params))
+    }
+
+    /// Convert this select to SQL, as [Select::to_sql()] does, generating its parameters with the
+    /// given generator, e.g., so that it can be combined with other statements that have
+    /// parameters of their own (see [CompoundSelect::to_sql()]).
+    fn to_sql_with(&self, sql_param_gen: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
         let mut lines = Vec::new();
         let mut params = Vec::new();
+
+        // The WITH clause:
+        let (with_sql, mut with_params) = self.get_with_sql(sql_param_gen)?;
+        if !with_sql.is_empty() {
+            lines.push(with_sql);
+            params.append(&mut with_params);
+        }
         let target = match self.view_name.as_str() {
             "" => &self.table_name,
             _ => &self.view_name,
         };
 
-        let get_change_sql = |sql_param_gen: &mut SqlParam| -> String {
-            format!(
-                r#"(SELECT MAX(change_id) FROM history
-                    WHERE "table" = {}
-                      AND "row" = "{}"._id
-                   ) AS _change_id"#,
-                sql_param_gen.next(),
-                target
-            )
-        };
-
-        // The SELECT clause:
+        // The SELECT clause, which is written on one line when all columns are selected, and
+        // otherwise with each of its items on a line of its own:
+        let mut items = vec![];
+        for filter in self.get_all_filters() {
+            let (_, c, _, _) = filter.parts();
+            if c == "_change_id" && !self.materialized_change_id {
+                items.push(format!(
+                    r#"(SELECT MAX(change_id) FROM history WHERE "table" = {} AND "row" = "{target}"._id) AS _change_id"#,
+                    sql_param_gen.next(),
+                ));
+                params.push(json!(self.table_name));
+            }
+        }
         if self.select.len() == 0 {
             if self.joins.len() > 0 {
-                lines.push(format!(r#"SELECT "{target}".*,"#));
+                items.insert(0, format!(r#""{target}".*"#));
             } else {
-                lines.push("SELECT *".to_string());
+                items.insert(0, "*".to_string());
             }
-            for filter in &self.filters {
-                let (_, c, _, _) = filter.parts();
-                if c == "_change_id" {
-                    lines.push(format!(", {}", get_change_sql(&mut sql_param_gen)));
-                    params.push(json!(self.table_name));
-                }
+            for display_column in &self.display_columns {
+                items.push(display_column.to_sql(target));
             }
         } else {
-            lines.push("SELECT".to_string());
-            for filter in &self.filters {
-                let (_, c, _, _) = filter.parts();
-                if c == "_change_id" {
-                    lines.push(get_change_sql(&mut sql_param_gen));
-                    params.push(json!(self.table_name));
-                }
-            }
             for field in &self.select {
-                if field.to_sql() == "" {
+                let field = field.to_sql(&sql_param_gen.kind);
+                if field.is_empty() {
                     return Err(RelatableError::InputError("Empty field name".to_string()).into());
                 }
-                let mut t = ",";
-                if field == self.select.last().unwrap() {
-                    t = "";
+                items.push(field);
+            }
+            if !self.has_aggregates() {
+                for display_column in &self.display_columns {
+                    let selected = self.select.iter().any(|field| match field {
+                        SelectField::Column { table, column, .. } => {
+                            *column == display_column.column
+                                && (table.is_empty() || *table == self.table_name)
+                        }
+                        _ => false,
+                    });
+                    if selected {
+                        items.push(display_column.to_sql(target));
+                    }
                 }
-
-                lines.push(format!(r#"  {field}{t}"#, field = field.to_sql()));
+            }
+        }
+        match items.as_slice() {
+            [item] if item == "*" => lines.push("SELECT *".to_string()),
+            _ => {
+                lines.push("SELECT".to_string());
+                lines.push(indent_sql(&items.join(",\n")));
             }
         }
 
-        // The FROM clause:
-        lines.push(format!(r#"FROM "{target}""#));
+        // The FROM clause. PostgreSQL can sample the blocks of a table, but not of a view:
+        let source = self.get_source_sql(target);
+        match self.sample_percent {
+            Some(percent)
+                if sql_param_gen.kind == DbKind::Postgres
+                    && source == format!(r#""{}""#, self.table_name) =>
+            {
+                lines.push(format!("FROM {source} TABLESAMPLE SYSTEM ({percent})"))
+            }
+            _ => lines.push(format!("FROM {source}")),
+        }
         for join in &self.joins {
-            lines.push(join.to_sql());
+            let (join_sql, mut join_params) = join.to_sql(sql_param_gen)?;
+            lines.push(join_sql);
+            params.append(&mut join_params);
         }
 
         // The WHERE clause:
         for (i, filter) in self.filters.iter().enumerate() {
-            let keyword = if i == 0 { "WHERE" } else { "  AND" };
             let mut filter = filter.clone();
             let (t, _, _, _) = filter.parts();
             if self.view_name != "" && t == self.table_name {
                 filter.set_table(&self.view_name);
             }
-            let (filter_sql, mut filter_params) = filter.to_sql(&mut sql_param_gen)?;
-            lines.push(format!("{keyword} {filter_sql}"));
-
-            // If the select is using the text view, the query parameters must all be changed
-            // to text:
-            if self.view_name == format!("{}_text_view", self.table_name) {
-                filter_params = filter_params
-                    .iter()
-                    .map(|param| match param {
-                        JsonValue::String(s) => json!(s),
-                        _ => json!(param.to_string()),
-                    })
-                    .collect::<Vec<_>>();
+            let (filter_sql, filter_params) = filter.to_sql(sql_param_gen)?;
+            lines.push(where_sql(i, &filter_sql));
+            params.append(&mut self.adapt_params_to_view(filter_params));
+        }
+        for (i, expr) in self.get_targeted_filter_exprs().iter().enumerate() {
+            let (expr_sql, expr_params) = expr.to_sql(sql_param_gen)?;
+            lines.push(where_sql(i + self.filters.len(), &expr_sql));
+            params.append(&mut self.adapt_params_to_view(expr_params));
+        }
+        // Keyset pagination, which does not apply to a sample. Note that the _id and _order
+        // columns are never converted to text, so the parameters for the cursors are not adapted
+        // to the view:
+        if (self.after.is_some() || self.before.is_some()) && self.sample.is_none() {
+            if !self.has_default_order() {
+                return Err(RelatableError::InputError(
+                    "Cursors can only be used with the default order of a table".to_string(),
+                )
+                .into());
             }
-            params.append(&mut filter_params);
+            let mut keyword = if self.filters.len() + self.filter_exprs.len() == 0 {
+                "WHERE"
+            } else {
+                "  AND"
+            };
+            for (cursor, operator) in [(&self.after, ">"), (&self.before, "<")] {
+                if let Some(cursor) = cursor {
+                    lines.push(format!(
+                        r#"{keyword} ("{target}"._order, "{target}"._id) {operator} ({}, {})"#,
+                        sql_param_gen.next(),
+                        sql_param_gen.next()
+                    ));
+                    params.append(&mut cursor.to_sql_params());
+                    keyword = "  AND";
+                }
+            }
+        }
+        // The GROUP BY clause:
+        let group_by = self.get_group_by();
+        if !group_by.is_empty() {
+            lines.push(format!("GROUP BY {}", group_by.join(", ")));
         }
-        if self.order_by.len() == 0 && self.joins.len() == 0 {
-            lines.push(format!(r#"ORDER BY "{target}"._order ASC"#));
+        // The ORDER BY clause. Since _order is unique, we always append it as a final tie-breaker
+        // so that the order of the returned rows (and therefore the page boundaries) is
+        // deterministic even when sorting by a column that contains duplicate values. Aggregated
+        // rows have no _order, however:
+        let has_aggregates = self.has_aggregates();
+        if let Some(sample) = self.sample {
+            lines.push("ORDER BY RANDOM()".to_string());
+            lines.push(format!("LIMIT {sample}"));
+            return Ok((lines.join("\n"), params));
         }
-        for (column, order) in &self.order_by {
-            lines.push(format!(r#"ORDER BY "{column}" {order:?}"#));
+        // A row of the target table may be joined to several rows of another table, which are
+        // then ordered by their _id:
+        let join_tiebreaks = self
+            .joins
+            .iter()
+            .map(|join| format!(r#""{}"._id ASC"#, join.name()))
+            .collect::<Vec<_>>();
+        if self.order_by.is_empty() && !has_aggregates {
+            // When paging backwards, fetch the rows nearest to the cursor, which are then returned
+            // in the opposite order (see [Relatable::fetch()]):
+            let mut order_items = match self.before {
+                Some(_) => vec![format!(r#""{target}"._order DESC"#)],
+                None => vec![format!(r#""{target}"._order ASC"#)],
+            };
+            order_items.extend(join_tiebreaks);
+            lines.push(format!("ORDER BY {}", order_items.join(", ")));
+        } else if !self.order_by.is_empty() {
+            let mut order_items = self
+                .order_by
+                .iter()
+                .flat_map(|(column, order)| match self.collations.get(column) {
+                    Some(collation) => {
+                        collation.to_sql(&format!(r#""{column}""#), order, &sql_param_gen.kind)
+                    }
+                    None => vec![format!(r#""{column}" {order}"#)],
+                })
+                .collect::<Vec<_>>();
+            if !has_aggregates {
+                if !self.order_by.iter().any(|(column, _)| column == "_order") {
+                    order_items.push(format!(r#""{target}"._order ASC"#));
+                }
+                order_items.extend(join_tiebreaks);
+            }
+            lines.push(format!("ORDER BY {}", order_items.join(", ")));
         }
-        if self.limit > 0 {
-            lines.push(format!("LIMIT {}", self.limit));
+        if let Some(limit) = self.limit.rows() {
+            lines.push(format!("LIMIT {limit}"));
         }
         if self.offset > 0 {
             lines.push(format!("OFFSET {}", self.offset));
@@ -953,35 +1907,297 @@ impl Select {
         let mut lines = Vec::new();
         let mut params = Vec::new();
         lines.push(r#"SELECT COUNT(1) AS "count""#.to_string());
-        lines.push(format!(r#"FROM "{target}""#));
-        for join in self.joins.clone() {
-            lines.push(join.to_sql());
+        lines.push(format!("FROM {}", self.get_source_sql(target)));
+        for join in &self.joins {
+            let (s, mut p) = join.to_sql(&mut SqlParam::new(kind))?;
+            lines.push(s);
+            params.append(&mut p);
         }
         for (i, filter) in self.filters.iter().enumerate() {
-            let keyword = if i == 0 { "WHERE" } else { "  AND" };
             let mut filter = filter.clone();
             let (t, _, _, _) = filter.parts();
             if self.view_name != "" && t == self.table_name {
                 filter.set_table(&self.view_name);
             }
             let (s, p) = filter.to_sql_count(kind)?;
-            lines.push(format!("{keyword} {s}"));
+            lines.push(where_sql(i, &s));
             params.append(&mut p.clone());
         }
+        for (i, expr) in self.get_targeted_filter_exprs().iter().enumerate() {
+            let (s, mut p) = expr.to_sql_count(kind)?;
+            lines.push(where_sql(i + self.filters.len(), &s));
+            params.append(&mut p);
+        }
+
+        // When aggregating, count the groups rather than the rows. Without any groups, there is
+        // always exactly one aggregated row:
+        if self.has_aggregates() {
+            let group_by = self.get_group_by();
+            if group_by.is_empty() {
+                return Ok((r#"SELECT 1 AS "count""#.to_string(), vec![]));
+            }
+            lines[0] = format!("SELECT {}", group_by.join(", "));
+            lines.push(format!("GROUP BY {}", group_by.join(", ")));
+            let inner = lines.join("\n");
+            lines = vec![
+                r#"SELECT COUNT(1) AS "count""#.to_string(),
+                format!(r#"FROM ({}) AS "groups""#, wrap_sql(&inner)),
+            ];
+        }
+
+        // The WITH clause, which must come first:
+        let (with_sql, mut with_params) = self.get_with_sql(&mut SqlParam::new(kind))?;
+        if !with_sql.is_empty() {
+            lines.insert(0, with_sql);
+        }
+        with_params.append(&mut self.adapt_params_to_view(params));
+        Ok((lines.join("\n"), with_params))
+    }
+
+    /// Get the WITH clause that defines this select's [common table expressions](Cte), or an
+    /// empty string if it has none, along with their parameters, in order.
+    fn get_with_sql(&self, sql_param_gen: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        if self.with.is_empty() {
+            return Ok((String::new(), vec![]));
+        }
+        let keyword = match self
+            .with
+            .iter()
+            .any(|cte| matches!(cte, Cte::Recursive { .. }))
+        {
+            true => "WITH RECURSIVE",
+            false => "WITH",
+        };
+        let mut ctes = vec![];
+        let mut params = vec![];
+        for cte in &self.with {
+            let (cte_sql, mut cte_params) = cte.to_sql(sql_param_gen)?;
+            ctes.push(cte_sql);
+            params.append(&mut cte_params);
+        }
+        Ok((format!("{keyword} {}", ctes.join(",\n")), params))
+    }
 
-        // If the select is using the text view, the query parameters must all be changed
-        // to text:
+    /// If this select is using the text view, change the given query parameters to text, as
+    /// they must all be, otherwise return them as they are.
+    fn adapt_params_to_view(&self, params: Vec<JsonValue>) -> Vec<JsonValue> {
         if self.view_name == format!("{}_text_view", self.table_name) {
-            params = params
+            params
                 .iter()
                 .map(|param| match param {
                     JsonValue::String(s) => json!(s),
                     _ => json!(param.to_string()),
                 })
-                .collect::<Vec<_>>();
+                .collect::<Vec<_>>()
+        } else {
+            params
         }
+    }
 
-        Ok((lines.join("\n"), params))
+    /// Get this select's filter expressions, with the filters on its table changed to filters on
+    /// its view, if it has one.
+    fn get_targeted_filter_exprs(&self) -> Vec<FilterExpr> {
+        let mut exprs = self.filter_exprs.clone();
+        if !self.view_name.is_empty() {
+            for expr in exprs.iter_mut() {
+                for filter in expr.get_filters_mut() {
+                    if filter.get_table() == self.table_name {
+                        filter.set_table(&self.view_name);
+                    }
+                }
+            }
+        }
+        exprs
+    }
+
+    /// Return a copy of this select without the values of its filters, so that selects which
+    /// differ only in those values, and therefore generate the same SQL, have the same shape.
+    pub fn to_shape(&self) -> Self {
+        let mut shape = self.clone();
+        shape.filters = self
+            .filters
+            .iter()
+            .map(|filter| filter.to_shape())
+            .collect();
+        shape.filter_exprs = self
+            .filter_exprs
+            .iter()
+            .map(|expr| expr.to_shape())
+            .collect();
+        shape.joins = self.joins.iter().map(|join| join.to_shape()).collect();
+        shape.with = self.with.iter().map(|cte| cte.to_shape()).collect();
+        shape.after = self.after.as_ref().map(|_| PageCursor::default());
+        shape.before = self.before.as_ref().map(|_| PageCursor::default());
+        // The partitions that are read depend on the values of the filters:
+        shape.partitions = self.get_partitions_to_read();
+        shape
+    }
+
+    /// Get the partitions of this select's table that can hold rows that match its filters on the
+    /// partition column: equal to, in, or (for the default partition) null.
+    pub fn get_partitions_to_read(&self) -> Vec<Partition> {
+        let mut partitions = self.partitions.clone();
+        let Some(column) = self.partitions.first().map(|partition| &partition.column) else {
+            return partitions;
+        };
+        for filter in &self.filters {
+            let (table, filter_column, _, _) = filter.parts();
+            if filter_column != *column
+                || !(table.is_empty() || table == self.table_name || table == self.view_name)
+            {
+                continue;
+            }
+            let values = match filter {
+                Filter::Equal { value, .. } => vec![value.clone()],
+                Filter::In {
+                    value: JsonValue::Array(values),
+                    ..
+                } => values.clone(),
+                Filter::Is {
+                    value: JsonValue::Null,
+                    ..
+                } => vec![JsonValue::Null],
+                _ => continue,
+            };
+            partitions.retain(|partition| {
+                values.iter().any(|value| match value {
+                    JsonValue::Null => partition.value.is_none(),
+                    _ => partition.accepts(value, &self.partitions),
+                })
+            });
+        }
+        partitions
+    }
+
+    /// Get the SQL for the source of the rows of this select, in its FROM clause: its target
+    /// (the table or one of its views), or, when the table is read either directly or through its
+    /// default view, the union of the sources of its rows that are to be read, i.e., those
+    /// partitions of a partitioned table that can match, and the table's archive when archived
+    /// rows are included, or the default views on them.
+    fn get_source_sql(&self, target: &str) -> String {
+        let partitions = self.get_partitions_to_read();
+        let all_partitions = partitions.len() == self.partitions.len();
+        if all_partitions && !self.include_archived {
+            return format!(r#""{target}""#);
+        }
+        let suffix = match target {
+            _ if *target == self.table_name => "",
+            _ if *target == format!("{}_default_view", self.table_name) => "_default_view",
+            _ => return format!(r#""{target}""#),
+        };
+        let mut sources = match all_partitions {
+            true => vec![target.to_string()],
+            false => partitions
+                .iter()
+                .map(|partition| format!("{}{suffix}", partition.name))
+                .collect(),
+        };
+        if self.include_archived {
+            sources.push(format!("{}{suffix}", sql::archive_name(&self.table_name)));
+        }
+        let union = sources
+            .iter()
+            .map(|source| format!(r#"SELECT * FROM "{source}""#))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ");
+        format!(r#"({union}) AS "{target}""#)
+    }
+
+    /// Collect the parameters that must be bound to the SQL generated by [Select::to_sql()], in
+    /// the same order, without generating the SQL itself.
+    pub fn to_sql_params(&self, kind: &DbKind) -> Result<Vec<JsonValue>> {
+        tracing::trace!("Select::to_sql_params({self:?}, {kind:?})");
+        let mut params = self.get_with_sql(&mut SqlParam::new(kind))?.1;
+        for filter in self.get_all_filters() {
+            let (_, c, _, _) = filter.parts();
+            if c == "_change_id" && !self.materialized_change_id {
+                params.push(json!(self.table_name));
+            }
+        }
+        for join in &self.joins {
+            params.append(&mut join.to_sql_params(kind)?);
+        }
+        for filter in &self.filters {
+            let filter_params = filter.to_sql_params(kind)?;
+            params.append(&mut self.adapt_params_to_view(filter_params));
+        }
+        for expr in &self.filter_exprs {
+            let expr_params = expr.to_sql_params(kind)?;
+            params.append(&mut self.adapt_params_to_view(expr_params));
+        }
+        if self.sample.is_none() {
+            for cursor in [&self.after, &self.before].into_iter().flatten() {
+                params.append(&mut cursor.to_sql_params());
+            }
+        }
+        Ok(params)
+    }
+
+    /// Convert the select to SQL, as [Select::to_sql()] does, but reuse the SQL generated
+    /// previously for a select of the same [shape](Select::to_shape()) if there is one, in which
+    /// case only the parameters need to be collected.
+    pub fn to_sql_cached(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("Select::to_sql_cached({self:?}, {kind:?})");
+        let key = format!("{kind:?} {}", serde_json::to_string(&self.to_shape())?);
+        let sql = SQL_CACHE
+            .lock()
+            .expect("Could not lock SQL cache")
+            .get(&key)
+            .cloned();
+        match sql {
+            Some(sql) => Ok((sql, self.to_sql_params(kind)?)),
+            None => {
+                let (sql, params) = self.to_sql(kind)?;
+                let mut cache = SQL_CACHE.lock().expect("Could not lock SQL cache");
+                if cache.len() >= SQL_CACHE_SIZE {
+                    cache.clear();
+                }
+                cache.insert(key, sql.clone());
+                Ok((sql, params))
+            }
+        }
+    }
+
+    /// Get the plan by which the database would run this select, as reported by
+    /// `EXPLAIN QUERY PLAN` in SQLite and by `EXPLAIN` in PostgreSQL, as a list of
+    /// [QueryPlanStep]s, each of which refers to the step that it is part of, if any.
+    pub async fn explain(&self, rltbl: &Relatable) -> Result<Vec<QueryPlanStep>> {
+        tracing::trace!("Select::explain({self:?}, {rltbl:?})");
+        let kind = rltbl.connection.kind();
+        let (statement, params) = self.to_sql(&kind)?;
+        let params = json!(params);
+        match kind {
+            DbKind::Sqlite => {
+                let statement = format!("EXPLAIN QUERY PLAN {statement}");
+                rltbl
+                    .connection
+                    .query(&statement, Some(&params))
+                    .await?
+                    .iter()
+                    .map(|row| {
+                        Ok(QueryPlanStep {
+                            id: row.get_unsigned("id")?,
+                            parent: match row.get_unsigned("parent")? {
+                                0 => None,
+                                parent => Some(parent),
+                            },
+                            detail: row.get_string("detail")?,
+                        })
+                    })
+                    .collect()
+            }
+            DbKind::Postgres => {
+                let statement = format!("EXPLAIN {statement}");
+                let lines = rltbl
+                    .connection
+                    .query(&statement, Some(&params))
+                    .await?
+                    .iter()
+                    .map(|row| row.get_string("QUERY PLAN"))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(QueryPlanStep::from_indented_lines(&lines))
+            }
+        }
     }
 
     /// Converts this select's filters to a map from column names to URL representations of their
@@ -999,16 +2215,10 @@ impl Select {
         if self.select.len() > 0 {
             let mut select_cols = vec![];
             for sfield in self.select.iter() {
-                match sfield {
-                    SelectField::Column { .. } => {
-                        select_cols.push(sfield.to_url());
-                    }
-                    SelectField::Expression { expression, .. } => {
-                        // Only include 'count()' expressions
-                        if expression == "count()" {
-                            select_cols.push(expression.to_string());
-                        }
-                    }
+                // Fields that cannot be written in URLs are left out:
+                match sfield.to_url() {
+                    Ok(url) => select_cols.push(url),
+                    Err(error) => tracing::warn!("{error}"),
                 };
             }
             if select_cols.len() > 0 {
@@ -1017,6 +2227,11 @@ impl Select {
         }
         if self.filters.len() > 0 {
             for filter in &self.filters {
+                // Searches are given by a parameter of their own:
+                if let Filter::Search { query, .. } = filter {
+                    params.insert("search".to_string(), query.to_string().into());
+                    continue;
+                }
                 let (table, column, _, _) = filter.parts();
 
                 if table != "" {
@@ -1035,21 +2250,72 @@ impl Select {
                     ))
                     .into());
                 }
-                let lhs = {
+                let mut lhs = {
                     match table.as_str() {
                         "" => format!(r#"{column}"#),
                         _ => format!(r#"{table}.{column}"#),
                     }
                 };
+                if let Some(path) = filter.get_json_path() {
+                    lhs = format!("{lhs}->{path}");
+                }
                 params.insert(lhs, filter.to_url()?.into());
             }
         }
-        if self.limit > 0 && self.limit != DEFAULT_LIMIT {
-            params.insert("limit".into(), self.limit.into());
+        for expr in &self.filter_exprs {
+            // Expressions that are not combinations of filters are wrapped in one:
+            let mut url = expr.to_url()?;
+            let keyword = match url.split_once("(") {
+                Some((keyword, _)) if ["and", "or", "not.and", "not.or"].contains(&keyword) => {
+                    keyword.to_string()
+                }
+                _ => {
+                    url = format!("and({url})");
+                    "and".to_string()
+                }
+            };
+            if params.contains_key(&keyword) {
+                return Err(RelatableError::InputError(format!(
+                    "Only one '{keyword}' filter expression can be given in a URL"
+                ))
+                .into());
+            }
+            params.insert(keyword.clone(), url[keyword.len()..].into());
+        }
+        match self.limit {
+            Limit::Default => (),
+            Limit::N(limit) if limit == DEFAULT_LIMIT => (),
+            limit => {
+                params.insert("limit".into(), limit.to_string().into());
+            }
         }
         if self.offset > 0 {
             params.insert("offset".into(), self.offset.into());
         }
+        if self.display {
+            params.insert("display".into(), "true".into());
+        }
+        if self.include_archived {
+            params.insert("include_archived".into(), "true".into());
+        }
+        if self.estimate_count {
+            params.insert("estimate_count".into(), "true".into());
+        }
+        if self.totals {
+            params.insert("totals".into(), "true".into());
+        }
+        if let Some(sample) = self.sample {
+            params.insert("sample".into(), sample.into());
+        }
+        if let Some(locale) = &self.locale {
+            params.insert("locale".into(), locale.to_string().into());
+        }
+        if let Some(cursor) = &self.after {
+            params.insert("after".into(), cursor.to_token().into());
+        }
+        if let Some(cursor) = &self.before {
+            params.insert("before".into(), cursor.to_token().into());
+        }
         Ok(params)
     }
 
@@ -1066,14 +2332,19 @@ impl Select {
         }
         let path = format!("{base}/{table_name}{format}");
 
-        if self.joins.len() > 0 {
-            return Err(RelatableError::InputError(
-                "Joins are unsupported in to_url()".to_string(),
-            )
-            .into());
-        }
-
-        let params = &self.to_params()?.clone();
+        // Selects that cannot be written in the other parameters, e.g., those with joins or with
+        // subquery filters, are written as a token instead:
+        let params = match self.joins.len() {
+            0 => self.to_params(),
+            _ => Err(RelatableError::InputError("Joins cannot be written in URLs".into()).into()),
+        };
+        let params = match params {
+            Ok(params) => params,
+            Err(error) => {
+                tracing::debug!("Writing select as a token: {error}");
+                return Ok(format!("{path}?q={}", self.to_token()?));
+            }
+        };
         if params.len() > 0 {
             let mut parts = vec![];
             for (column, value) in params.iter() {
@@ -1089,830 +2360,3915 @@ impl Select {
         }
     }
 
-    pub fn to_page(&self, root: &str, path: &str, tabs: &Vec<String>) -> Result<Page> {
-        tracing::trace!("Select::to_page({root}, {path})");
-        let base = format!("{root}/{path}");
-        let mut formats = IndexMap::new();
-        formats.insert("HTML".to_string(), self.to_url(&base, &Format::Html)?);
-        formats.insert("CSV".to_string(), self.to_url(&base, &Format::Csv)?);
-        formats.insert("TSV".to_string(), self.to_url(&base, &Format::Tsv)?);
-        formats.insert("JSON".to_string(), self.to_url(&base, &Format::Json)?);
-        formats.insert(
-            "JSON (Pretty)".to_string(),
-            self.to_url(&base, &Format::PrettyJson)?,
-        );
-        let tabs = tabs
-            .iter()
-            .map(|t| {
-                let mut s = self.clone();
-                s.table_name = t.clone();
-                let mut c = s.clone();
-                c.select_expression("count()", "count");
-                Tab {
-                    table: t.clone(),
-                    active: (t.as_str() == self.table_name),
-                    url: s.to_url(&base, &Format::Default).unwrap(),
-                    count: c.to_url(&base, &Format::ValueJson).unwrap(),
-                }
-            })
-            .collect();
+    /// Encode this select as an opaque token that can be given in the `q` parameter of a URL
+    /// (see [Select::from_path_and_query()]), for selects that cannot be written in the other
+    /// parameters, e.g., those with joins or subquery filters. The fields that are filled in by
+    /// [Relatable::fetch()] are left out.
+    pub fn to_token(&self) -> Result<String> {
+        tracing::trace!("Select::to_token()");
+        let mut select = self.clone();
+        select.clear_fetched();
+        Ok(BASE64_URL_SAFE_NO_PAD.encode(serde_json::to_string(&select)?))
+    }
 
-        Ok(Page {
-            path: path.to_string(),
-            formats,
-            tabs,
-        })
+    /// Decode a select from the given token (see [Select::to_token()]). Since the token may come
+    /// from anyone, every name in the select must be simple (see [sql::is_simple()]), and its
+    /// expressions must be [SelectExpr]s, which are parsed and written anew.
+    pub fn from_token(token: &str) -> Result<Self> {
+        tracing::trace!("Select::from_token({token:?})");
+        let invalid = |reason: String| -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid select token: {reason}")).into()
+        };
+        let decoded = BASE64_URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|error| invalid(error.to_string()))?;
+        let mut select: Select =
+            serde_json::from_slice(&decoded).map_err(|error| invalid(error.to_string()))?;
+        select
+            .sanitize()
+            .map_err(|error| invalid(error.to_string()))?;
+        select.clear_fetched();
+        Ok(select)
     }
-}
 
-/// A field in a [Select] clause.
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
-pub enum SelectField {
-    Column {
-        table: String,
-        column: String,
-        alias: String,
-    },
-    Expression {
-        expression: String,
-        alias: String,
-    },
-}
+    /// Clear the fields of this select that are filled in by [Relatable::fetch()]
+    fn clear_fetched(&mut self) {
+        self.display_columns.clear();
+        self.collations.clear();
+        self.partitions.clear();
+        self.sample_percent = None;
+        self.limited = false;
+        for filter in self.filters.iter_mut() {
+            if let Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } =
+                filter
+            {
+                subquery.clear_fetched();
+            }
+        }
+    }
 
-impl SelectField {
-    fn to_sql(&self) -> String {
-        match self {
-            SelectField::Column {
-                table,
-                column,
-                alias,
-            } => {
-                format!(
-                    "{table}{column}{alias}",
-                    table = match table.as_str() {
-                        "" => "".to_string(),
-                        _ => format!(r#""{table}"."#),
-                    },
-                    column = format!(r#""{column}""#),
-                    alias = match alias.as_str() {
-                        "" => "".to_string(),
-                        _ => format!(r#" AS "{alias}""#),
+    /// Check that every name in this select, and in its subqueries, is simple, and parse and
+    /// write anew each of its expressions, so that the select can safely be converted to SQL
+    /// even though it was not constructed by rltbl itself
+    fn sanitize(&mut self) -> Result<()> {
+        let check = |name: &str| -> Result<()> {
+            if name.is_empty() {
+                return Ok(());
+            }
+            for part in name.split(".") {
+                sql::is_simple(part).map_err(RelatableError::InputError)?;
+            }
+            Ok(())
+        };
+        let check_filter = |filter: &mut Filter| -> Result<()> {
+            let (table, column, _, _) = filter.parts();
+            check(&table)?;
+            check(&column)?;
+            match filter {
+                Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } => {
+                    subquery.sanitize()?
+                }
+                Filter::Search { search_table, .. } => check(search_table)?,
+                Filter::JsonPath { path, op, .. } => {
+                    json_path_steps(path)?;
+                    if !JSON_PATH_OPERATORS.contains(&op.as_str()) {
+                        return Err(RelatableError::InputError(format!(
+                            "Invalid JSON path operator '{op}'"
+                        ))
+                        .into());
                     }
-                )
+                }
+                _ => (),
+            };
+            Ok(())
+        };
+
+        if !self.with.is_empty() {
+            return Err(RelatableError::InputError(
+                "Common table expressions cannot be given in a token".to_string(),
+            )
+            .into());
+        }
+        check(&self.table_name)?;
+        check(&self.view_name)?;
+        for field in self.select.iter_mut() {
+            match field {
+                SelectField::Column {
+                    table,
+                    column,
+                    alias,
+                }
+                | SelectField::Aggregate {
+                    table,
+                    column,
+                    alias,
+                    ..
+                } => {
+                    check(table)?;
+                    check(column)?;
+                    check(alias)?;
+                }
+                SelectField::Expression { expression, alias } => {
+                    if expression != "count()" {
+                        *expression = SelectExpr::from_url(expression)?.to_sql();
+                    }
+                    check(alias)?;
+                }
+                SelectField::Window {
+                    partition_by,
+                    order_by,
+                    alias,
+                    ..
+                } => {
+                    for column in partition_by.iter() {
+                        check(column)?;
+                    }
+                    for (column, _) in order_by.iter() {
+                        check(column)?;
+                    }
+                    check(alias)?;
+                }
+            };
+        }
+        for join in self.joins.iter_mut() {
+            let (left_table, left_column, right_table, right_column) = join.parts();
+            for name in [
+                left_table,
+                left_column,
+                right_table,
+                right_column,
+                join.name(),
+            ] {
+                check(name)?;
             }
-            SelectField::Expression { expression, alias } => {
-                format!(
-                    "{expression}{alias}",
-                    alias = match alias.as_str() {
-                        "" => "".to_string(),
-                        _ => format!(r#" AS "{alias}""#),
+            let (on, _) = join.conditions();
+            for (left, right) in on {
+                check(left)?;
+                check(right)?;
+            }
+            match join {
+                Join::LeftJoin { filters, .. }
+                | Join::InnerJoin { filters, .. }
+                | Join::RightJoin { filters, .. }
+                | Join::FullJoin { filters, .. } => {
+                    for filter in filters.iter_mut() {
+                        check_filter(filter)?;
                     }
-                )
+                }
+            };
+        }
+        for filter in self.filters.iter_mut() {
+            check_filter(filter)?;
+        }
+        for expr in self.filter_exprs.iter_mut() {
+            for filter in expr.get_filters_mut() {
+                check_filter(filter)?;
             }
         }
+        for (column, _) in &self.order_by {
+            check(column)?;
+        }
+        Ok(())
     }
 
-    fn to_url(&self) -> String {
-        match self {
-            SelectField::Column {
-                table,
-                column,
-                alias,
-            } => {
-                if alias != "" {
-                    tracing::warn!("Alias '{alias}' unsupported in to_url()");
+    pub fn to_page(&self, root: &str, path: &str, tabs: &Vec<String>) -> Result<Page> {
+        tracing::trace!("Select::to_page({root}, {path})");
+        let base = format!("{root}/{path}");
+        let mut formats = IndexMap::new();
+        formats.insert("HTML".to_string(), self.to_url(&base, &Format::Html)?);
+        formats.insert("CSV".to_string(), self.to_url(&base, &Format::Csv)?);
+        formats.insert("TSV".to_string(), self.to_url(&base, &Format::Tsv)?);
+        formats.insert("JSON".to_string(), self.to_url(&base, &Format::Json)?);
+        formats.insert(
+            "JSON (Pretty)".to_string(),
+            self.to_url(&base, &Format::PrettyJson)?,
+        );
+        formats.insert("Arrow".to_string(), self.to_url(&base, &Format::Arrow)?);
+        let tabs = tabs
+            .iter()
+            .map(|t| {
+                let mut s = self.clone();
+                s.table_name = t.clone();
+                let mut c = s.clone();
+                c.select_expression("count()", "count");
+                Tab {
+                    table: t.clone(),
+                    active: (t.as_str() == self.table_name),
+                    url: s.to_url(&base, &Format::Default).unwrap(),
+                    count: c.to_url(&base, &Format::ValueJson).unwrap(),
                 }
-                format!(
-                    "{table}{column}",
-                    table = match table.as_str() {
-                        "" => "".to_string(),
-                        _ => format!("{table}."),
-                    },
-                    column = format!("{column}")
-                )
-            }
-            _ => panic!("Select Expressions are not supported"),
-        }
+            })
+            .collect();
+
+        Ok(Page {
+            path: path.to_string(),
+            formats,
+            tabs,
+            ..Default::default()
+        })
     }
 }
 
-/// Represents a database join
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub enum Join {
-    LeftJoin {
-        left_table: String,
-        left_column: String,
-        right_table: String,
-        right_column: String,
-    },
+/// The ways in which a [CompoundSelect] combines the rows of its selects
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Union {
+    /// Combine the rows, removing duplicates
+    Distinct,
+    /// Combine the rows, keeping duplicates
+    All,
 }
 
-impl Join {
-    pub fn to_sql(&self) -> String {
+impl std::fmt::Display for Union {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Join::LeftJoin {
-                left_table,
-                left_column,
-                right_table,
-                right_column,
-            } => {
-                let (t, lt, lc, rt, rc) = (
-                    &right_table,
-                    &left_table,
-                    &left_column,
-                    &right_table,
-                    &right_column,
-                );
-                format!(r#"LEFT JOIN "{t}" ON "{lt}"."{lc}" = "{rt}"."{rc}""#)
-            }
+            Union::Distinct => write!(f, "UNION"),
+            Union::All => write!(f, "UNION ALL"),
         }
     }
 }
 
+/// Represents a compound SELECT statement, which combines the rows of several [Select]s using
+/// UNION or UNION ALL. Each of the selects must list the columns that it selects, and they must
+/// all select the same number of columns. The orders, limits, and offsets of the selects
+/// themselves are not applied. Instead, the combined rows are ordered, limited, and offset by
+/// those of the compound select, which are initially those of the first select.
 #[derive(Clone, Debug, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum Filter {
-    Like {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    Equal {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    NotEqual {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    GreaterThan {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    GreaterThanOrEqual {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    LessThan {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    LessThanOrEqual {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    Is {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    IsNot {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    In {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    NotIn {
-        table: String,
-        column: String,
-        value: JsonValue,
-    },
-    InSubquery {
-        table: String,
-        column: String,
-        subquery: Select,
-    },
-    NotInSubquery {
-        table: String,
-        column: String,
-        subquery: Select,
-    },
+pub struct CompoundSelect {
+    pub select: Select,
+    pub unions: Vec<(Union, Select)>,
+    /// The columns of the combined rows to order them by
+    pub order_by: Vec<(String, Order)>,
+    pub limit: Limit,
+    pub offset: usize,
 }
-impl Filter {
-    pub fn set_table(&mut self, new_name: &str) -> &Self {
-        match self {
-            Filter::Like { table, .. }
-            | Filter::Equal { table, .. }
-            | Filter::NotEqual { table, .. }
-            | Filter::GreaterThan { table, .. }
-            | Filter::GreaterThanOrEqual { table, .. }
-            | Filter::LessThan { table, .. }
-            | Filter::LessThanOrEqual { table, .. }
-            | Filter::Is { table, .. }
-            | Filter::IsNot { table, .. }
-            | Filter::In { table, .. }
-            | Filter::NotIn { table, .. }
-            | Filter::InSubquery { table, .. }
-            | Filter::NotInSubquery { table, .. } => *table = new_name.to_string(),
-        };
-        self
+
+impl CompoundSelect {
+    /// Construct a CompoundSelect that, to begin with, returns the rows of the given select
+    pub fn from(select: &Select) -> Self {
+        tracing::trace!("CompoundSelect::from({select:?})");
+        Self {
+            select: select.clone(),
+            unions: vec![],
+            order_by: select.order_by.clone(),
+            limit: select.limit,
+            offset: select.offset,
+        }
     }
 
-    pub fn set_column(&mut self, new_name: &str) -> &Self {
-        match self {
-            Filter::Like { column, .. }
-            | Filter::Equal { column, .. }
-            | Filter::NotEqual { column, .. }
-            | Filter::GreaterThan { column, .. }
-            | Filter::GreaterThanOrEqual { column, .. }
-            | Filter::LessThan { column, .. }
-            | Filter::LessThanOrEqual { column, .. }
-            | Filter::Is { column, .. }
-            | Filter::IsNot { column, .. }
-            | Filter::In { column, .. }
-            | Filter::NotIn { column, .. }
-            | Filter::InSubquery { column, .. }
-            | Filter::NotInSubquery { column, .. } => *column = new_name.to_string(),
-        };
-        self
+    /// Combine the rows returned so far with those of the given select, removing duplicates
+    pub fn union(self, other: &Select) -> Result<Self> {
+        self.add(Union::Distinct, other)
     }
 
-    pub fn parts(&self) -> (String, String, String, JsonValue) {
-        tracing::trace!("Filter::parts()");
-        let (table, column, operator, value) = match self {
-            Filter::Like {
-                table,
-                column,
-                value,
-            } => (table, column, "like", value),
-            Filter::Equal {
-                table,
-                column,
-                value,
-            } => (table, column, "eq", value),
-            Filter::NotEqual {
-                table,
-                column,
-                value,
-            } => (table, column, "not_eq", value),
-            Filter::GreaterThan {
-                table,
-                column,
-                value,
-            } => (table, column, "gt", value),
-            Filter::GreaterThanOrEqual {
-                table,
-                column,
-                value,
-            } => (table, column, "gte", value),
-            Filter::LessThan {
-                table,
-                column,
-                value,
-            } => (table, column, "lt", value),
-            Filter::LessThanOrEqual {
-                table,
-                column,
-                value,
-            } => (table, column, "lte", value),
-            Filter::Is {
-                table,
-                column,
-                value,
-            } => (table, column, "is", value),
-            Filter::IsNot {
-                table,
-                column,
-                value,
-            } => (table, column, "is_not", value),
-            Filter::In {
-                table,
-                column,
-                value,
-            } => (table, column, "in", value),
-            Filter::NotIn {
-                table,
-                column,
-                value,
-            } => (table, column, "not_in", value),
-            Filter::InSubquery {
-                table,
-                column,
-                subquery,
-            } => (table, column, "in", &json!(subquery)),
-            Filter::NotInSubquery {
-                table,
-                column,
-                subquery,
-            } => (table, column, "not_in", &json!(subquery)),
-        };
-        (
-            table.to_string(),
-            column.to_string(),
-            operator.to_string(),
-            json!(value),
-        )
+    /// Combine the rows returned so far with those of the given select, keeping duplicates
+    pub fn union_all(self, other: &Select) -> Result<Self> {
+        self.add(Union::All, other)
     }
 
-    pub fn get_table(&self) -> String {
-        self.parts().0
+    fn add(mut self, union: Union, other: &Select) -> Result<Self> {
+        tracing::trace!("CompoundSelect::add({union:?}, {other:?})");
+        for select in [&self.select, other] {
+            if select.select.is_empty() {
+                return Err(RelatableError::InputError(format!(
+                    "The columns selected from '{}' must be listed to combine it with other \
+                     selects",
+                    select.table_name
+                ))
+                .into());
+            }
+            if select.after.is_some() || select.before.is_some() {
+                return Err(RelatableError::InputError(
+                    "Cursors cannot be used in selects that are combined with others".to_string(),
+                )
+                .into());
+            }
+        }
+        if self.select.select.len() != other.select.len() {
+            return Err(RelatableError::InputError(format!(
+                "Cannot combine a select of {} columns from '{}' with a select of {} columns \
+                 from '{}'",
+                self.select.select.len(),
+                self.select.table_name,
+                other.select.len(),
+                other.table_name
+            ))
+            .into());
+        }
+        self.unions.push((union, other.clone()));
+        Ok(self)
     }
 
-    pub fn get_column(&self) -> String {
-        self.parts().1
+    /// Order (ascending) the combined rows by the given column
+    pub fn order_by(mut self, column: &str) -> Self {
+        tracing::trace!("CompoundSelect::order_by({column:?})");
+        self.order_by = vec![(column.to_string(), Order::ASC)];
+        self
     }
 
-    pub fn get_operator(&self) -> String {
-        self.parts().2
+    /// Add the given column and [Order] to the ORDER BY clause of this compound select
+    pub fn add_order_by(mut self, column: &str, order: &Order) -> Self {
+        self.order_by.push((column.to_string(), order.clone()));
+        self
     }
 
-    pub fn get_value(&self) -> JsonValue {
-        self.parts().3
+    /// Limit the combined rows to the given number
+    pub fn limit(mut self, limit: &usize) -> Self {
+        tracing::trace!("CompoundSelect::limit({limit})");
+        self.limit = Limit::N(*limit);
+        self
     }
 
-    pub fn to_url(&self) -> Result<String> {
-        tracing::trace!("Filter::to_url()");
+    /// Return all of the combined rows
+    pub fn unlimited(mut self) -> Self {
+        tracing::trace!("CompoundSelect::unlimited()");
+        self.limit = Limit::Unlimited;
+        self
+    }
 
-        fn handle_string_value(token: &str) -> String {
-            let reserved = vec![':', ',', '.', '(', ')'];
-            if token.chars().all(char::is_numeric) || reserved.iter().any(|&c| token.contains(c)) {
-                if token.contains(char::is_whitespace) {
-                    format!("\"{}\"", token)
-                } else {
-                    token.to_string()
-                }
-            } else {
-                token.to_string()
+    /// Offset the combined rows by the given number
+    pub fn offset(mut self, offset: &usize) -> Self {
+        tracing::trace!("CompoundSelect::offset({offset})");
+        self.offset = *offset;
+        self
+    }
+
+    /// Get all the tables that are implicated in any of the combined selects
+    pub fn get_tables(&self) -> BTreeSet<String> {
+        let mut tables = self.select.get_tables();
+        for (_, select) in &self.unions {
+            tables.extend(select.get_tables());
+        }
+        tables
+    }
+
+    /// Return a copy of this compound select in which each of the combined selects has been
+    /// replaced by its [shape](Select::to_shape())
+    pub fn to_shape(&self) -> Self {
+        let mut shape = self.clone();
+        shape.select = self.select.to_shape();
+        shape.unions = self
+            .unions
+            .iter()
+            .map(|(union, select)| (*union, select.to_shape()))
+            .collect();
+        shape
+    }
+
+    /// Get the SQL for the combination of the selects, without its ORDER BY, LIMIT, and OFFSET
+    /// clauses, along with the parameters of all of the selects, in order.
+    fn to_union_sql(&self, sql_param_gen: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        let mut lines = vec![];
+        let mut params = vec![];
+        let selects = std::iter::once((None, &self.select)).chain(
+            self.unions
+                .iter()
+                .map(|(union, select)| (Some(union), select)),
+        );
+        for (union, select) in selects {
+            if let Some(union) = union {
+                lines.push(union.to_string());
             }
+            // The select is written without its ORDER BY, LIMIT, and OFFSET clauses:
+            let mut select = select.clone();
+            select.order_by = vec![];
+            select.limit = Limit::Unlimited;
+            select.offset = 0;
+            let (sql, mut select_params) = select.to_sql_with(sql_param_gen)?;
+            lines.extend(
+                sql.lines()
+                    .filter(|line| !line.starts_with("ORDER BY"))
+                    .map(|line| line.to_string()),
+            );
+            params.append(&mut select_params);
         }
+        Ok((lines.join("\n"), params))
+    }
 
-        let (_, _, operator, value) = self.parts();
-        let rhs = match &value {
-            JsonValue::Null => "null".to_string(),
-            JsonValue::String(string) => handle_string_value(&string),
-            JsonValue::Number(number) => format!("{number}"),
-            JsonValue::Array(vector) => {
-                let mut list = vec![];
-                for item in vector {
-                    match item {
-                        JsonValue::String(string) => list.push(handle_string_value(&string)),
-                        JsonValue::Number(number) => list.push(number.to_string()),
-                        _ => {
-                            return Err(RelatableError::DataError(format!(
-                                "Not all list items in {vector:?} are strings or numbers.",
-                            ))
-                            .into());
-                        }
-                    };
-                }
-                format!("({})", list.join(","))
+    /// Convert the compound select to a tuple consisting of an SQL string and a vector of
+    /// parameters that must be bound to the string before executing it.
+    pub fn to_sql(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("CompoundSelect::to_sql({self:?}, {kind:?})");
+        self.to_sql_with(&mut SqlParam::new(kind))
+    }
+
+    /// Convert the compound select to SQL, as [CompoundSelect::to_sql()] does, generating its
+    /// parameters with the given generator (see [Select::to_sql_with()]).
+    fn to_sql_with(&self, sql_param_gen: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        let (sql, params) = self.to_union_sql(sql_param_gen)?;
+        let mut lines = vec![sql];
+        if !self.order_by.is_empty() {
+            let order_items = self
+                .order_by
+                .iter()
+                .map(|(column, order)| format!(r#""{column}" {order}"#))
+                .collect::<Vec<_>>();
+            lines.push(format!("ORDER BY {}", order_items.join(", ")));
+        }
+        if let Some(limit) = self.limit.rows() {
+            lines.push(format!("LIMIT {limit}"));
+        }
+        if self.offset > 0 {
+            lines.push(format!("OFFSET {}", self.offset));
+        }
+        Ok((lines.join("\n"), params))
+    }
+
+    /// Generate a SQL statement consisting of a SELECT COUNT(*) over all of the rows that are
+    /// combined by this compound select
+    pub fn to_sql_count(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("CompoundSelect::to_sql_count({self:?}, {kind:?})");
+        let (sql, params) = self.to_union_sql(&mut SqlParam::new(kind))?;
+        let lines = [
+            r#"SELECT COUNT(1) AS "count""#.to_string(),
+            format!(r#"FROM ({}) AS "compound""#, wrap_sql(&sql)),
+        ];
+        Ok((lines.join("\n"), params))
+    }
+}
+
+/// A named common table expression (CTE), defined in the WITH clause of a [Select], whose FROM
+/// clause and joins can then refer to it by name as if it were a table. Note that a select that
+/// is not otherwise ordered is ordered by the `_order` column of its table, which a CTE that is
+/// selected from must then have.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Cte {
+    Select {
+        name: String,
+        select: Select,
+    },
+    /// A CTE whose compound select refers to the CTE itself, e.g., to walk a hierarchy
+    Recursive {
+        name: String,
+        /// The names of the CTE's columns
+        columns: Vec<String>,
+        select: CompoundSelect,
+    },
+}
+
+impl Cte {
+    /// Get the name by which this CTE is referred to
+    pub fn name(&self) -> &String {
+        match self {
+            Cte::Select { name, .. } | Cte::Recursive { name, .. } => name,
+        }
+    }
+
+    /// Get all the tables that are implicated in this CTE's select
+    pub fn get_tables(&self) -> BTreeSet<String> {
+        match self {
+            Cte::Select { select, .. } => select.get_tables(),
+            Cte::Recursive { select, .. } => select.get_tables(),
+        }
+    }
+
+    /// Return a copy of this CTE in which its select has been replaced by its shape (see
+    /// [Select::to_shape()])
+    pub fn to_shape(&self) -> Self {
+        match self {
+            Cte::Select { name, select } => Cte::Select {
+                name: name.clone(),
+                select: select.to_shape(),
+            },
+            Cte::Recursive {
+                name,
+                columns,
+                select,
+            } => Cte::Recursive {
+                name: name.clone(),
+                columns: columns.clone(),
+                select: select.to_shape(),
+            },
+        }
+    }
+
+    /// Convert this CTE to its definition in a WITH clause, along with its parameters
+    pub fn to_sql(&self, sql_param: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        let (name, (sql, params)) = match self {
+            Cte::Select { name, select } => {
+                (format!(r#""{name}""#), select.to_sql_with(sql_param)?)
             }
-            _ => {
-                if let Filter::InSubquery { .. } | Filter::NotInSubquery { .. } = self {
-                    tracing::error!("Subquery filters are unsupported: {self:?}");
-                }
-                return Err(RelatableError::DataError(format!(
-                    "RHS of Filter: {:?} is not a string, number, or list",
-                    self
-                ))
-                .into());
+            Cte::Recursive {
+                name,
+                columns,
+                select,
+            } => {
+                let columns = columns
+                    .iter()
+                    .map(|column| format!(r#""{column}""#))
+                    .collect::<Vec<_>>();
+                (
+                    format!(r#""{name}"({})"#, columns.join(", ")),
+                    select.to_sql_with(sql_param)?,
+                )
             }
         };
+        Ok((format!("{name} AS ({})", wrap_sql(&sql)), params))
+    }
+}
 
-        Ok(format!("{operator}.{rhs}"))
+/// A column of a [Select]'s table whose values are the keys, in the given key column, of the rows
+/// of another table, whose labels, in the given label column, are looked up for display.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DisplayColumn {
+    pub column: String,
+    pub table: String,
+    pub key_column: String,
+    pub label_column: String,
+}
+
+impl DisplayColumn {
+    /// Generate the SQL for a subquery that looks up the label of this column's value in the
+    /// given target table or view, named after the column with the prefix `_display_`.
+    pub fn to_sql(&self, target: &str) -> String {
+        format!(
+            r#"(SELECT "_display"."{label}" FROM "{table}" AS "_display" WHERE "_display"."{key}" = "{target}"."{column}" LIMIT 1) AS "_display_{column}""#,
+            label = self.label_column,
+            table = self.table,
+            key = self.key_column,
+            column = self.column,
+        )
     }
+}
 
-    pub fn to_sql(&self, sql_param: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
-        tracing::trace!("Filter::to_sql({sql_param:?})");
+/// A position in the default order of a table, given by the _order and _id of one of its rows,
+/// which can be used to page through the table by key (see [Select::after()] and
+/// [Select::before()]) rather than by offset, which is slow for large tables. In URLs, a cursor
+/// is given by an opaque token.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageCursor {
+    pub order: u64,
+    pub id: u64,
+}
 
-        fn generate_lhs(table: &str, column: &str) -> String {
-            match table {
-                "" => format!(r#""{column}""#),
-                _ => format!(r#""{table}"."{column}""#),
-            }
+impl PageCursor {
+    /// Get the position of the given row
+    pub fn from_row(row: &Row) -> Self {
+        Self {
+            order: row.order,
+            id: row.id,
         }
+    }
+
+    /// Parse a cursor from the given token, as generated by [PageCursor::to_token()]
+    pub fn from_token(token: &str) -> Result<Self> {
+        let invalid = || -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid cursor '{token}'")).into()
+        };
+        let decoded = BASE64_URL_SAFE_NO_PAD
+            .decode(token)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(invalid)?;
+        let (order, id) = decoded.split_once(".").ok_or_else(invalid)?;
+        Ok(Self {
+            order: order.parse().map_err(|_| invalid())?,
+            id: id.parse().map_err(|_| invalid())?,
+        })
+    }
+
+    /// Convert the cursor to an opaque token that is safe to use in URLs
+    pub fn to_token(&self) -> String {
+        BASE64_URL_SAFE_NO_PAD.encode(format!("{}.{}", self.order, self.id))
+    }
+
+    /// The parameters that must be bound to the SQL comparison with this cursor
+    fn to_sql_params(&self) -> Vec<JsonValue> {
+        vec![json!(self.order), json!(self.id)]
+    }
+}
+
+/// A field in a [Select] clause.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SelectField {
+    Column {
+        table: String,
+        column: String,
+        alias: String,
+    },
+    Expression {
+        expression: String,
+        alias: String,
+    },
+    Aggregate {
+        function: AggregateFunction,
+        table: String,
+        column: String,
+        alias: String,
+    },
+    /// A window function, computed for each row over the rows that have the same values of the
+    /// `partition_by` columns, in the given order. Where the position of NULL values in the order
+    /// is not given, they are put first in ascending order, as SQLite does, for either database,
+    /// so that the results do not depend on the database.
+    Window {
+        function: WindowFunction,
+        partition_by: Vec<String>,
+        order_by: Vec<(String, Order)>,
+        alias: String,
+    },
+}
+
+/// An aggregate function that can be used in a [SelectField::Aggregate].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AggregateFunction {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
 
+impl std::fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            Filter::Like {
-                table,
-                column,
-                value,
-            } => {
-                let value = match value {
-                    JsonValue::Bool(value) => value.to_string(),
-                    JsonValue::Number(value) => value.to_string(),
-                    JsonValue::String(value) => value.to_string(),
-                    JsonValue::Null => "NULL".to_string(),
-                    JsonValue::Array(value) => format!("{value:?}"),
-                    JsonValue::Object(value) => format!("{value:?}"),
-                };
-                let value = value.replace("*", "%");
-                Ok((
-                    format!(
-                        r#"{lhs} LIKE {sql_param}"#,
-                        lhs = generate_lhs(table, column),
-                        sql_param = sql_param.next()
-                    ),
-                    vec![json!(value)],
-                ))
+            AggregateFunction::Sum => write!(f, "sum"),
+            AggregateFunction::Avg => write!(f, "avg"),
+            AggregateFunction::Min => write!(f, "min"),
+            AggregateFunction::Max => write!(f, "max"),
+        }
+    }
+}
+
+impl std::str::FromStr for AggregateFunction {
+    type Err = anyhow::Error;
+
+    fn from_str(function: &str) -> Result<Self> {
+        match function.to_lowercase().as_str() {
+            "sum" => Ok(AggregateFunction::Sum),
+            "avg" => Ok(AggregateFunction::Avg),
+            "min" => Ok(AggregateFunction::Min),
+            "max" => Ok(AggregateFunction::Max),
+            _ => Err(RelatableError::InputError(format!(
+                "Unsupported aggregate function '{function}'"
+            ))
+            .into()),
+        }
+    }
+}
+
+/// A window function that can be used in a [SelectField::Window].
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum WindowFunction {
+    RowNumber,
+    Rank,
+    DenseRank,
+    PercentRank,
+    CumeDist,
+    /// Divide the rows into the given number of groups, as evenly as possible
+    Ntile(usize),
+}
+
+impl std::fmt::Display for WindowFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WindowFunction::RowNumber => write!(f, "row_number"),
+            WindowFunction::Rank => write!(f, "rank"),
+            WindowFunction::DenseRank => write!(f, "dense_rank"),
+            WindowFunction::PercentRank => write!(f, "percent_rank"),
+            WindowFunction::CumeDist => write!(f, "cume_dist"),
+            WindowFunction::Ntile(_) => write!(f, "ntile"),
+        }
+    }
+}
+
+impl SelectField {
+    /// Parse an aggregate given in a URL in the form `[ALIAS:][TABLE.]COLUMN.FUNCTION()`, e.g.,
+    /// `total_mass:body_mass.sum()`.
+    pub fn aggregate_from_url(field: &str) -> Result<Self> {
+        tracing::trace!("SelectField::aggregate_from_url({field:?})");
+        let invalid = || -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid aggregate '{field}'")).into()
+        };
+        let (alias, rest) = match field.split_once(":") {
+            Some((alias, rest)) => (alias, rest),
+            None => ("", field),
+        };
+        let (column, function) = rest
+            .strip_suffix("()")
+            .and_then(|rest| rest.rsplit_once("."))
+            .ok_or_else(invalid)?;
+        let (table, column) = match column.split_once(".") {
+            Some((table, column)) => (table, column),
+            None => ("", column),
+        };
+        for name in [alias, table, column] {
+            if !name.is_empty() {
+                sql::is_simple(name).map_err(|_| invalid())?;
             }
-            Filter::Equal {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} = {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::NotEqual {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} <> {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::GreaterThan {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} > {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::GreaterThanOrEqual {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} >= {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::LessThan {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} < {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::LessThanOrEqual {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} <= {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::Is {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} {is} {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    is = sql::is_clause(&sql_param.kind),
-                    sql_param = sql_param.next()
-                ),
-                vec![json!(value)],
-            )),
-            Filter::IsNot {
-                table,
-                column,
-                value,
-            } => Ok((
-                format!(
-                    r#"{lhs} {is_not} {sql_param}"#,
-                    lhs = generate_lhs(table, column),
-                    sql_param = sql_param.next(),
-                    is_not = sql::is_not_clause(&sql_param.kind)
-                ),
-                vec![json!(value)],
-            )),
-            Filter::In {
+        }
+        if column.is_empty() {
+            return Err(invalid());
+        }
+        Ok(SelectField::Aggregate {
+            function: function.parse()?,
+            table: table.to_string(),
+            column: column.to_string(),
+            alias: alias.to_string(),
+        })
+    }
+
+    /// Parse the given field, written in the syntax used in URLs, e.g.,
+    /// `kg:round(body_mass/1000,1)`, as an aliased [SelectExpr]. If no alias is given, the name of
+    /// the function called by the expression is used. A column, with or without an alias, e.g.,
+    /// `mass:body_mass`, is parsed as a [SelectField::Column].
+    pub fn expression_from_url(field: &str) -> Result<Self> {
+        tracing::trace!("SelectField::expression_from_url({field:?})");
+        let (alias, rest) = match field.split_once(":") {
+            Some((alias, rest)) if sql::is_simple(alias).is_ok() => (alias, rest),
+            _ => ("", field),
+        };
+        let expr = SelectExpr::from_url(rest)?;
+        match (&expr, alias) {
+            (SelectExpr::Column { table, column }, alias) => Ok(SelectField::Column {
+                table: table.to_string(),
+                column: column.to_string(),
+                alias: alias.to_string(),
+            }),
+            (SelectExpr::Function { name, .. }, "") => Ok(SelectField::Expression {
+                expression: expr.to_sql(),
+                alias: name.to_string(),
+            }),
+            (_, "") => Err(RelatableError::InputError(format!(
+                "Expression '{field}' requires an alias"
+            ))
+            .into()),
+            _ => Ok(SelectField::Expression {
+                expression: expr.to_sql(),
+                alias: alias.to_string(),
+            }),
+        }
+    }
+
+    fn to_sql(&self, kind: &DbKind) -> String {
+        match self {
+            SelectField::Column {
                 table,
                 column,
-                value,
+                alias,
             } => {
-                if let JsonValue::Array(values) = value {
-                    let lhs = generate_lhs(table, column);
-                    match render_values(values, sql_param) {
-                        Err(e) => {
-                            return Err(RelatableError::DataError(format!(
-                                "Error rendering 'in' filter: {e}"
-                            ))
-                            .into());
-                        }
-                        Ok((rhs, values)) => Ok((format!("{lhs} IN {rhs}"), values)),
+                format!(
+                    "{table}{column}{alias}",
+                    table = match table.as_str() {
+                        "" => "".to_string(),
+                        _ => format!(r#""{table}"."#),
+                    },
+                    column = format!(r#""{column}""#),
+                    alias = match alias.as_str() {
+                        "" => "".to_string(),
+                        _ => format!(r#" AS "{alias}""#),
                     }
-                } else {
-                    Err(RelatableError::DataError(format!("Invalid 'in' value: {value}")).into())
-                }
+                )
             }
-            Filter::NotIn {
-                table,
-                column,
-                value,
-            } => {
-                if let JsonValue::Array(values) = value {
-                    let lhs = generate_lhs(table, column);
-                    match render_values(values, sql_param) {
-                        Err(e) => {
-                            return Err(RelatableError::DataError(format!(
-                                "Error rendering 'not in' filter: {e}"
-                            ))
-                            .into());
-                        }
-                        Ok((rhs, values)) => Ok((format!("{lhs} NOT IN {rhs}"), values)),
+            SelectField::Expression { expression, alias } => {
+                format!(
+                    "{expression}{alias}",
+                    alias = match alias.as_str() {
+                        "" => "".to_string(),
+                        _ => format!(r#" AS "{alias}""#),
                     }
-                } else {
-                    Err(
-                        RelatableError::DataError(format!("Invalid 'not in' value: {value}"))
-                            .into(),
-                    )
-                }
+                )
             }
-            Filter::InSubquery {
+            SelectField::Aggregate {
+                function,
                 table,
                 column,
-                subquery,
+                alias,
             } => {
-                let (sql, params) = subquery.to_sql(&sql_param.kind)?;
-                let sql = sql.replace("\n", "\n  ");
-                let lhs = generate_lhs(table, column);
-                Ok((format!("{lhs} IN (\n  {sql}\n)"), params))
+                format!(
+                    r#"{function}({table}"{column}") AS "{alias}""#,
+                    function = function.to_string().to_uppercase(),
+                    table = match table.as_str() {
+                        "" => "".to_string(),
+                        _ => format!(r#""{table}"."#),
+                    },
+                    alias = match alias.as_str() {
+                        "" => function.to_string(),
+                        _ => alias.to_string(),
+                    }
+                )
             }
-            Filter::NotInSubquery {
-                table,
-                column,
-                subquery,
+            SelectField::Window {
+                function,
+                partition_by,
+                order_by,
+                alias,
             } => {
-                let (sql, params) = subquery.to_sql(&sql_param.kind)?;
-                let sql = sql.replace("\n", "\n  ");
-                let lhs = generate_lhs(table, column);
-                Ok((format!("{lhs} NOT IN (\n  {sql}\n)"), params))
+                let quote = |column: &String| match split_qualified(column) {
+                    (table, column) if table.is_empty() => format!(r#""{column}""#),
+                    (table, column) => format!(r#""{table}"."{column}""#),
+                };
+                let mut window = vec![];
+                if !partition_by.is_empty() {
+                    let columns = partition_by.iter().map(quote).collect::<Vec<_>>();
+                    window.push(format!("PARTITION BY {}", columns.join(", ")));
+                }
+                if !order_by.is_empty() {
+                    // PostgreSQL puts NULL values last in ascending order, unlike SQLite:
+                    let items = order_by
+                        .iter()
+                        .map(|(column, order)| {
+                            let order = match (kind, order) {
+                                (DbKind::Postgres, Order::ASC) => Order::AscNullsFirst,
+                                (DbKind::Postgres, Order::DESC) => Order::DescNullsLast,
+                                _ => order.clone(),
+                            };
+                            format!("{} {order}", quote(column))
+                        })
+                        .collect::<Vec<_>>();
+                    window.push(format!("ORDER BY {}", items.join(", ")));
+                }
+                format!(
+                    r#"{function}({argument}) OVER ({window}) AS "{alias}""#,
+                    function = function.to_string().to_uppercase(),
+                    argument = match function {
+                        WindowFunction::Ntile(groups) => groups.to_string(),
+                        _ => String::new(),
+                    },
+                    window = window.join(" "),
+                    alias = match alias.as_str() {
+                        "" => function.to_string(),
+                        _ => alias.to_string(),
+                    }
+                )
             }
         }
     }
 
-    /// Generate a SQL statement consisting of a SELECT COUNT(*) over the data that will bereturned
-    /// by the given [Select]
-    pub fn to_sql_count(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
-        tracing::trace!("Filter::to_sql_count({self:?}, {kind:?})");
+    /// Convert this field to the syntax used in URLs, in which an alias is written before the
+    /// field that it names, e.g., `mass:body_mass`, `total_mass:body_mass.sum()`, or
+    /// `kg:round(body_mass/1000,1)` (see [Select::from_path_and_query()]). Expressions that cannot
+    /// be parsed as a [SelectExpr], and window functions, cannot be written in URLs.
+    pub fn to_url(&self) -> Result<String> {
+        let alias = |alias: &str| match alias {
+            "" => "".to_string(),
+            _ => format!("{alias}:"),
+        };
+        let table = |table: &str| match table {
+            "" => "".to_string(),
+            _ => format!("{table}."),
+        };
         match self {
-            Filter::InSubquery {
-                table,
+            SelectField::Column {
+                table: t,
                 column,
-                subquery,
+                alias: a,
+            } => Ok(format!("{}{}{column}", alias(a), table(t))),
+            SelectField::Aggregate {
+                function,
+                table: t,
+                column,
+                alias: a,
+            } => Ok(format!("{}{}{column}.{function}()", alias(a), table(t))),
+            SelectField::Expression {
+                expression,
+                alias: a,
             } => {
-                if column == "" {
-                    return Err(RelatableError::InputError("Empty column name".to_string()).into());
+                if expression == "count()" && a.is_empty() {
+                    return Ok(expression.to_string());
+                }
+                match SelectExpr::from_url(expression) {
+                    Ok(expr) => Ok(format!("{}{}", alias(a), expr.to_url())),
+                    Err(_) => Err(RelatableError::InputError(format!(
+                        "Expression '{expression}' cannot be written in a URL"
+                    ))
+                    .into()),
                 }
-                let lhs = match table.as_str() {
-                    "" => format!(r#""{column}""#),
-                    _ => format!(r#""{table}"."{column}""#),
-                };
-                let (sql, params) = subquery.to_sql(kind)?;
-                let lines: Vec<&str> = sql
-                    .split("\n")
-                    .filter(|x| !x.starts_with("ORDER BY"))
-                    .filter(|x| !x.starts_with("LIMIT"))
-                    .filter(|x| !x.starts_with("OFFSET"))
-                    .collect();
-                let sql = lines.join("\n  ");
-                Ok((format!("{lhs} IN (\n  {sql}\n)"), params))
             }
-            _ => self.to_sql(&mut SqlParam::new(kind)),
+            SelectField::Window { alias: a, .. } => Err(RelatableError::InputError(format!(
+                "Window function '{a}' cannot be written in a URL"
+            ))
+            .into()),
         }
     }
 }
 
-/// Represents an ORDER BY clause in a SELECT statement.
-#[derive(Clone, Debug, Default, Serialize, Deserialize)]
-pub enum Order {
-    #[default]
-    ASC,
-    DESC,
-}
+/// The functions that may be called in a [SelectExpr]
+pub const SELECT_EXPR_FUNCTIONS: [&str; 12] = [
+    "abs", "coalesce", "length", "lower", "ltrim", "nullif", "replace", "round", "rtrim", "substr",
+    "trim", "upper",
+];
 
-pub type QueryParams = IndexMap<String, String>;
+/// The deepest that a [SelectExpr] may be nested
+const MAX_SELECT_EXPR_DEPTH: usize = 32;
 
-pub enum Format {
-    Html,
-    Csv,
-    Tsv,
-    Json,
-    ValueJson,
-    PrettyJson,
-    Default,
+/// A scalar expression that is computed for each row selected, e.g., `round(body_mass/1000,1)`.
+/// Unlike the expressions given to [Select::select_expression()], which are written into the SQL
+/// as they are, these are parsed and validated first, so that they can be accepted from URLs: only
+/// column references, numbers, strings in single quotes, NULL, the arithmetic operators, `||`,
+/// and calls to the [SELECT_EXPR_FUNCTIONS] may appear in them.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum SelectExpr {
+    Column {
+        table: String,
+        column: String,
+    },
+    Number(String),
+    Text(String),
+    Null,
+    Negate(Box<SelectExpr>),
+    Operation {
+        operator: String,
+        left: Box<SelectExpr>,
+        right: Box<SelectExpr>,
+    },
+    Function {
+        name: String,
+        arguments: Vec<SelectExpr>,
+    },
 }
 
-impl std::fmt::Display for Format {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        // TODO: This should be factored out.
-        let result = match self {
-            Format::Html => ".html",
-            Format::Csv => ".csv",
-            Format::Tsv => ".tsv",
-            Format::Json => ".json",
-            Format::ValueJson => ".value.json",
-            Format::PrettyJson => ".pretty.json",
-            Format::Default => "",
-        };
-        write!(f, "{result}")
-    }
+/// A token of a [SelectExpr]
+#[derive(Clone, Debug, PartialEq)]
+enum SelectExprToken {
+    Word(String),
+    Quoted(String),
+    Number(String),
+    Text(String),
+    Symbol(String),
 }
 
-impl TryFrom<&String> for Format {
-    fn try_from(path: &String) -> Result<Self> {
-        tracing::trace!("Format::try_from({path:?})");
-        let path = path.to_lowercase();
-        let format = if path.ends_with(".pretty.json") {
-            Format::PrettyJson
-        } else if path.ends_with(".value.json") {
-            Format::ValueJson
-        } else if path.ends_with(".json") {
-            Format::Json
-        } else if path.ends_with(".csv") {
-            Format::Csv
-        } else if path.ends_with(".tsv") {
-            Format::Tsv
-        } else if path.ends_with(".html") || path.ends_with(".htm") {
-            Format::Html
-        } else if path.contains(".") {
-            return Err(
-                RelatableError::FormatError(format!("Unknown format for path {path}")).into(),
-            );
-        } else {
-            Format::Default
+impl SelectExpr {
+    /// Parse the given expression, written either in the syntax used in URLs, e.g.,
+    /// `round(body_mass/1000,1)`, or as it is written in SQL by [SelectExpr::to_sql()]. Note that
+    /// `+` must be percent-encoded in URLs.
+    pub fn from_url(expression: &str) -> Result<Self> {
+        tracing::trace!("SelectExpr::from_url({expression:?})");
+        let invalid = |reason: &str| -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid expression '{expression}': {reason}"))
+                .into()
         };
-        Ok(format)
+        let tokens = SelectExpr::tokenize(expression).map_err(|reason| invalid(&reason))?;
+        let mut position = 0;
+        let expr = SelectExpr::parse_operation(&tokens, &mut position, 0, 0)
+            .map_err(|reason| invalid(&reason))?;
+        if position < tokens.len() {
+            return Err(invalid(&format!("unexpected {:?}", tokens[position])));
+        }
+        Ok(expr)
     }
 
-    type Error = anyhow::Error;
-}
-
-pub fn render_values(
-    options: &Vec<JsonValue>,
-    sql_param_gen: &mut SqlParam,
-) -> Result<(String, Vec<JsonValue>)> {
-    let mut sql_params = vec![];
-    let mut values = vec![];
-    let mut is_string_list = false;
-    for (i, option) in options.iter().enumerate() {
-        match option {
-            JsonValue::String(str_opt) => {
-                if i == 0 {
-                    is_string_list = true;
-                } else if !is_string_list {
-                    return Err(RelatableError::InputError(format!(
-                        "{:?} contains both text and numeric types.",
-                        options
-                    ))
-                    .into());
+    /// Split the given expression into tokens
+    fn tokenize(expression: &str) -> Result<Vec<SelectExprToken>, String> {
+        let mut tokens = vec![];
+        let mut chars = expression.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => (),
+                '\'' | '"' => {
+                    let mut text = String::new();
+                    loop {
+                        match chars.next() {
+                            // Quotes are escaped by doubling them:
+                            Some(next) if next == c && chars.peek() == Some(&c) => {
+                                chars.next();
+                                text.push(c);
+                            }
+                            Some(next) if next == c => break,
+                            Some(next) => text.push(next),
+                            None => return Err(format!("unterminated {c}")),
+                        }
+                    }
+                    tokens.push(match c {
+                        '"' => SelectExprToken::Quoted(text),
+                        _ => SelectExprToken::Text(text),
+                    });
                 }
-                sql_params.push(sql_param_gen.next());
-                let value = unquote(str_opt).unwrap_or(str_opt.clone());
-                values.push(format!("{value}").into())
+                c if c.is_ascii_digit() => {
+                    let mut number = c.to_string();
+                    while let Some(next) =
+                        chars.next_if(|next| next.is_ascii_digit() || *next == '.')
+                    {
+                        number.push(next);
+                    }
+                    if number.parse::<f64>().is_err() {
+                        return Err(format!("invalid number {number}"));
+                    }
+                    tokens.push(SelectExprToken::Number(number));
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let mut word = c.to_string();
+                    while let Some(next) =
+                        chars.next_if(|next| next.is_alphanumeric() || *next == '_')
+                    {
+                        word.push(next);
+                    }
+                    tokens.push(SelectExprToken::Word(word));
+                }
+                '|' if chars.next_if_eq(&'|').is_some() => {
+                    tokens.push(SelectExprToken::Symbol("||".to_string()))
+                }
+                '(' | ')' | ',' | '.' | '+' | '-' | '*' | '/' | '%' => {
+                    tokens.push(SelectExprToken::Symbol(c.to_string()))
+                }
+                _ => return Err(format!("unexpected '{c}'")),
             }
-            JsonValue::Number(_) => {
-                if i == 0 {
-                    is_string_list = false;
-                } else if is_string_list {
-                    return Err(RelatableError::InputError(format!(
-                        "{:?} contains both text and numeric types.",
-                        options
-                    ))
-                    .into());
+        }
+        Ok(tokens)
+    }
+
+    /// The precedence of the given binary operator, or of the given expression, if it is one
+    fn precedence(operator: &str) -> usize {
+        match operator {
+            "||" => 1,
+            "+" | "-" => 2,
+            "*" | "/" | "%" => 3,
+            _ => 4,
+        }
+    }
+
+    /// Parse the binary operations, of at least the given precedence, from the given position
+    /// in the given tokens.
+    fn parse_operation(
+        tokens: &Vec<SelectExprToken>,
+        position: &mut usize,
+        precedence: usize,
+        depth: usize,
+    ) -> Result<Self, String> {
+        let mut left = SelectExpr::parse_operand(tokens, position, depth)?;
+        while let Some(SelectExprToken::Symbol(operator)) = tokens.get(*position) {
+            let operator_precedence = SelectExpr::precedence(operator);
+            if operator_precedence > 3 || operator_precedence < precedence {
+                break;
+            }
+            *position += 1;
+            let right =
+                SelectExpr::parse_operation(tokens, position, operator_precedence + 1, depth)?;
+            left = SelectExpr::Operation {
+                operator: operator.to_string(),
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+        Ok(left)
+    }
+
+    /// Parse a single operand from the given position in the given tokens.
+    fn parse_operand(
+        tokens: &Vec<SelectExprToken>,
+        position: &mut usize,
+        depth: usize,
+    ) -> Result<Self, String> {
+        if depth > MAX_SELECT_EXPR_DEPTH {
+            return Err("nested too deeply".to_string());
+        }
+        let is_symbol = |position: usize, symbol: &str| {
+            tokens.get(position) == Some(&SelectExprToken::Symbol(symbol.to_string()))
+        };
+        let name_at = |position: usize| match tokens.get(position) {
+            Some(SelectExprToken::Word(name)) | Some(SelectExprToken::Quoted(name)) => {
+                Some(name.to_string())
+            }
+            _ => None,
+        };
+        let token = tokens.get(*position).ok_or("unexpected end")?;
+        *position += 1;
+        match token {
+            SelectExprToken::Number(number) => Ok(SelectExpr::Number(number.to_string())),
+            SelectExprToken::Text(text) => Ok(SelectExpr::Text(text.to_string())),
+            SelectExprToken::Symbol(symbol) if symbol == "-" => Ok(SelectExpr::Negate(Box::new(
+                SelectExpr::parse_operand(tokens, position, depth + 1)?,
+            ))),
+            SelectExprToken::Symbol(symbol) if symbol == "(" => {
+                let expr = SelectExpr::parse_operation(tokens, position, 0, depth + 1)?;
+                match is_symbol(*position, ")") {
+                    true => *position += 1,
+                    false => return Err("expected ')'".to_string()),
+                };
+                Ok(expr)
+            }
+            SelectExprToken::Word(word)
+                if word.eq_ignore_ascii_case("null") && !is_symbol(*position, "(") =>
+            {
+                Ok(SelectExpr::Null)
+            }
+            SelectExprToken::Word(word) if is_symbol(*position, "(") => {
+                let name = word.to_lowercase();
+                if !SELECT_EXPR_FUNCTIONS.contains(&name.as_str()) {
+                    return Err(format!("unknown function {word}"));
                 }
-                sql_params.push(sql_param_gen.next());
-                values.push(option.clone())
+                *position += 1;
+                let mut arguments = vec![];
+                while !is_symbol(*position, ")") {
+                    if !arguments.is_empty() {
+                        match is_symbol(*position, ",") {
+                            true => *position += 1,
+                            false => return Err("expected ',' or ')'".to_string()),
+                        };
+                    }
+                    arguments.push(SelectExpr::parse_operation(tokens, position, 0, depth + 1)?);
+                }
+                *position += 1;
+                Ok(SelectExpr::Function { name, arguments })
             }
-            _ => {
-                return Err(RelatableError::InputError(format!(
-                    "{:?} is not an array of strings or numbers.",
-                    options
-                ))
-                .into())
+            SelectExprToken::Word(name) | SelectExprToken::Quoted(name) => {
+                match (is_symbol(*position, "."), name_at(*position + 1)) {
+                    (true, Some(column)) => {
+                        *position += 2;
+                        Ok(SelectExpr::Column {
+                            table: name.to_string(),
+                            column,
+                        })
+                    }
+                    (true, None) => Err("expected a column after '.'".to_string()),
+                    (false, _) => Ok(SelectExpr::Column {
+                        table: String::new(),
+                        column: name.to_string(),
+                    }),
+                }
             }
+            SelectExprToken::Symbol(symbol) => Err(format!("unexpected '{symbol}'")),
+        }
+    }
+
+    /// Write this expression, using the given functions to write its column references and
+    /// strings, and the given separator between the arguments of functions and the operands of
+    /// operations.
+    fn render(
+        &self,
+        quote_name: &dyn Fn(&str) -> String,
+        function_name: &dyn Fn(&str) -> String,
+        separator: &str,
+    ) -> String {
+        let render = |expr: &SelectExpr| expr.render(quote_name, function_name, separator);
+        let precedence = |expr: &SelectExpr| match expr {
+            SelectExpr::Operation { operator, .. } => SelectExpr::precedence(operator),
+            _ => 4,
         };
+        match self {
+            SelectExpr::Column { table, column } => match table.as_str() {
+                "" => quote_name(column),
+                _ => format!("{}.{}", quote_name(table), quote_name(column)),
+            },
+            SelectExpr::Number(number) => number.to_string(),
+            SelectExpr::Text(text) => format!("'{}'", text.replace("'", "''")),
+            SelectExpr::Null => "NULL".to_string(),
+            // Negations are parenthesized so that they are never written as comments (`--`):
+            SelectExpr::Negate(operand) => match **operand {
+                SelectExpr::Negate(_) | SelectExpr::Operation { .. } => {
+                    format!("-({})", render(operand))
+                }
+                _ => format!("-{}", render(operand)),
+            },
+            SelectExpr::Operation {
+                operator,
+                left,
+                right,
+            } => {
+                let operator_precedence = SelectExpr::precedence(operator);
+                let left = match precedence(left) < operator_precedence {
+                    true => format!("({})", render(left)),
+                    false => render(left),
+                };
+                let negated = matches!(**right, SelectExpr::Negate(_)) && operator == "-";
+                let right = match precedence(right) <= operator_precedence || negated {
+                    true => format!("({})", render(right)),
+                    false => render(right),
+                };
+                match separator {
+                    "" => format!("{left}{operator}{right}"),
+                    _ => format!("{left} {operator} {right}"),
+                }
+            }
+            SelectExpr::Function { name, arguments } => format!(
+                "{}({})",
+                function_name(name),
+                arguments
+                    .iter()
+                    .map(render)
+                    .collect::<Vec<_>>()
+                    .join(&format!(",{separator}"))
+            ),
+        }
     }
-    Ok((format!("({})", sql_params.join(", ")), values))
-}
 
-pub async fn joined_query(
-    rltbl: &Relatable,
-    tableset_name: &str,
-    select: &Select,
-) -> Result<Select> {
-    let tables = select.get_tables();
-    if tables.len() == 1 {
-        return Ok(select.clone());
+    /// Convert this expression to SQL
+    pub fn to_sql(&self) -> String {
+        self.render(
+            &|name| format!(r#""{}""#, name.replace('"', r#""""#)),
+            &|name| name.to_uppercase(),
+            " ",
+        )
     }
 
-    let tables: Vec<JsonValue> = tables.into_iter().map(|x| json!(x)).collect();
-    let mut sql_param = sql::SqlParam::new(&rltbl.connection.kind());
-    let (value_string, value_list) = render_values(&tables, &mut sql_param).unwrap();
-
-    let sql = format!(
-        r#"WITH RECURSIVE ancestors(_order, left_table, left_column, right_table, right_column) AS (
-      SELECT _order, left_table, left_column, right_table, right_column
-      FROM tableset
-      WHERE right_table IN {value_string}
-      UNION
-      SELECT t._order, t.left_table, t.left_column, t.right_table, t.right_column
-      FROM ancestors AS a
-      JOIN tableset AS t ON a.left_table = t.right_table
-      WHERE t.tableset = '{tableset_name}'
-    )
-    SELECT *
-    FROM ancestors
-    WHERE _order >= (SELECT MIN(_order) FROM ancestors WHERE left_table IN {value_string})
-      AND _order <= (SELECT MAX(_order) FROM ancestors WHERE right_table IN {value_string})
-    ORDER BY _order"#
-    );
-    tracing::info!("SQL {sql}");
-    let mut three_value_lists = value_list.clone();
-    three_value_lists.extend(value_list.clone());
-    three_value_lists.extend(value_list.clone());
-    let params = json!(three_value_lists);
-    tracing::info!("PARAMS {params:?}");
-    let json_rows = rltbl.connection.query(&sql, Some(&params)).await?;
-    tracing::info!(
-        "TABLESET {} {json_rows:?}",
-        select.to_url("", &Format::Default)?
-    );
-    println!(
-        "TABLESET {} {json_rows:?}",
-        select.to_url("", &Format::Default)?
-    );
-    if json_rows.len() == 0 {
-        return Err(RelatableError::ConfigError(format!("empty tableset")).into());
+    /// Convert this expression to the syntax used in URLs (see [SelectExpr::from_url()])
+    pub fn to_url(&self) -> String {
+        self.render(
+            &|name| match sql::is_simple(name) {
+                Ok(_) => name.to_string(),
+                Err(_) => format!(r#""{}""#, name.replace('"', r#""""#)),
+            },
+            &|name| name.to_string(),
+            "",
+        )
     }
+}
 
-    let limit = select.limit;
-    let mut inner = select.clone();
-    inner.select = vec![];
-    let table_name = inner.table_name.clone();
-    let view_name = inner.view_name.clone();
+/// Represents a database join on the equality of the given left and right columns, and of any
+/// further pairs of columns given in `on`, for the rows that satisfy the given filters. Each kind
+/// of join is written the same way for SQLite and PostgreSQL. The right table may be given an
+/// alias, which qualified [SelectField]s and [Filter]s can then refer to, e.g., when joining a
+/// table to itself. Note that RIGHT and FULL OUTER joins require SQLite 3.39 or later, which is
+/// the case for the version of SQLite bundled with **rltbl**.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Join {
+    LeftJoin {
+        left_table: String,
+        left_column: String,
+        right_table: String,
+        right_column: String,
+        /// The name by which the right table is referred to, e.g., when joining a table to itself
+        #[serde(default)]
+        alias: String,
+        /// Further pairs of left and right columns whose values must be equal
+        #[serde(default)]
+        on: Vec<(String, String)>,
+        /// Filters on the joined tables that must also be satisfied
+        #[serde(default)]
+        filters: Vec<Filter>,
+    },
+    InnerJoin {
+        left_table: String,
+        left_column: String,
+        right_table: String,
+        right_column: String,
+        /// The name by which the right table is referred to, e.g., when joining a table to itself
+        #[serde(default)]
+        alias: String,
+        /// Further pairs of left and right columns whose values must be equal
+        #[serde(default)]
+        on: Vec<(String, String)>,
+        /// Filters on the joined tables that must also be satisfied
+        #[serde(default)]
+        filters: Vec<Filter>,
+    },
+    RightJoin {
+        left_table: String,
+        left_column: String,
+        right_table: String,
+        right_column: String,
+        /// The name by which the right table is referred to, e.g., when joining a table to itself
+        #[serde(default)]
+        alias: String,
+        /// Further pairs of left and right columns whose values must be equal
+        #[serde(default)]
+        on: Vec<(String, String)>,
+        /// Filters on the joined tables that must also be satisfied
+        #[serde(default)]
+        filters: Vec<Filter>,
+    },
+    FullJoin {
+        left_table: String,
+        left_column: String,
+        right_table: String,
+        right_column: String,
+        /// The name by which the right table is referred to, e.g., when joining a table to itself
+        #[serde(default)]
+        alias: String,
+        /// Further pairs of left and right columns whose values must be equal
+        #[serde(default)]
+        on: Vec<(String, String)>,
+        /// Filters on the joined tables that must also be satisfied
+        #[serde(default)]
+        filters: Vec<Filter>,
+    },
+}
 
-    let pkey = "_id".to_string();
-    inner.select_table_column(&table_name, &pkey);
+/// The further pairs of left and right columns, and the filters, that a [Join] is conditioned on
+type JoinConditions = (Vec<(String, String)>, Vec<Filter>);
 
-    // Find the primary key for this table.
-    // let mut pkey = String::new();
-    // for json_row in json_rows.clone() {
-    //     if table_name == json_row.get_string("left_table").unwrap() {
-    //         pkey = json_row.get_string("left_column").unwrap();
-    //         inner.select_table_column(&table_name, &pkey);
-    //         // println!("FOUND PKEY in {json_row:?}");
-    //     }
-    // }
-    // if table_name == json_rows.last().unwrap().get_string("right_table").unwrap() {
-    // }
-    //     inner.order_by(&pkey);
-    // } else {
-    //     inner.limit = 0;
-    // }
-    inner.order_by = vec![];
-    inner.limit = 0;
-    inner.view_name = String::new();
-    let json_row = json_rows.first().unwrap();
-    inner.table_name = json_row.get_string("left_table").unwrap();
-    let mut joined = HashSet::new();
-    for json_row in json_rows.iter() {
-        let left_table = json_row.get_string("left_table").unwrap();
-        let right_table = json_row.get_string("right_table").unwrap();
-        if &left_table == "" || &right_table == "" {
-            continue;
-        }
-        if joined.contains(&right_table) {
-            continue;
+impl Join {
+    /// Get the left table, left column, right table, and right column of this join
+    pub fn parts(&self) -> (&String, &String, &String, &String) {
+        match self {
+            Join::LeftJoin {
+                left_table,
+                left_column,
+                right_table,
+                right_column,
+                ..
+            }
+            | Join::InnerJoin {
+                left_table,
+                left_column,
+                right_table,
+                right_column,
+                ..
+            }
+            | Join::RightJoin {
+                left_table,
+                left_column,
+                right_table,
+                right_column,
+                ..
+            }
+            | Join::FullJoin {
+                left_table,
+                left_column,
+                right_table,
+                right_column,
+                ..
+            } => (left_table, left_column, right_table, right_column),
+        }
+    }
+
+    /// Get the further pairs of columns and the filters that this join is conditioned on
+    pub fn conditions(&self) -> (&Vec<(String, String)>, &Vec<Filter>) {
+        match self {
+            Join::LeftJoin { on, filters, .. }
+            | Join::InnerJoin { on, filters, .. }
+            | Join::RightJoin { on, filters, .. }
+            | Join::FullJoin { on, filters, .. } => (on, filters),
+        }
+    }
+
+    /// Get the name by which the right table of this join is referred to, i.e., its alias if it
+    /// has one and otherwise the name of the right table itself
+    pub fn name(&self) -> &String {
+        match self {
+            Join::LeftJoin {
+                right_table, alias, ..
+            }
+            | Join::InnerJoin {
+                right_table, alias, ..
+            }
+            | Join::RightJoin {
+                right_table, alias, ..
+            }
+            | Join::FullJoin {
+                right_table, alias, ..
+            } => match alias.as_str() {
+                "" => right_table,
+                _ => alias,
+            },
+        }
+    }
+
+    /// Refer to the right table of this join by the given alias. The join's filters on the right
+    /// table become filters on the alias.
+    pub fn set_alias(&mut self, new_alias: &str) -> &Self {
+        let right_table = self.name().to_string();
+        match self {
+            Join::LeftJoin { alias, .. }
+            | Join::InnerJoin { alias, .. }
+            | Join::RightJoin { alias, .. }
+            | Join::FullJoin { alias, .. } => *alias = new_alias.to_string(),
+        };
+        for filter in self.filters_mut() {
+            if filter.get_table() == right_table {
+                filter.set_table(new_alias);
+            }
+        }
+        self
+    }
+
+    fn filters_mut(&mut self) -> &mut Vec<Filter> {
+        match self {
+            Join::LeftJoin { filters, .. }
+            | Join::InnerJoin { filters, .. }
+            | Join::RightJoin { filters, .. }
+            | Join::FullJoin { filters, .. } => filters,
+        }
+    }
+
+    /// Split the given pairs of left and right columns into the first left column, the first right
+    /// column, and the remaining pairs, and parse the given filters on the given right table (see
+    /// [Select::filters()]).
+    fn parse_conditions(
+        right_table: &str,
+        columns: &[(&str, &str)],
+        filters: &Vec<String>,
+    ) -> Result<(String, String, JoinConditions)> {
+        let Some(((left_column, right_column), on)) = columns.split_first() else {
+            return Err(RelatableError::InputError(
+                "A join requires at least one pair of columns".to_string(),
+            )
+            .into());
+        };
+        let on = on
+            .iter()
+            .map(|(left, right)| (left.to_string(), right.to_string()))
+            .collect();
+        let mut filters = Select::from(right_table).filters(filters)?.filters;
+        for filter in filters.iter_mut() {
+            if filter.get_table() == "" {
+                filter.set_table(right_table);
+            }
+        }
+        Ok((
+            left_column.to_string(),
+            right_column.to_string(),
+            (on, filters),
+        ))
+    }
+
+    /// Convert the join to a tuple consisting of an SQL string and a vector of parameters that
+    /// must be bound to the string before executing it.
+    pub fn to_sql(&self, sql_param: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        let keyword = match self {
+            Join::LeftJoin { .. } => "LEFT JOIN",
+            Join::InnerJoin { .. } => "INNER JOIN",
+            Join::RightJoin { .. } => "RIGHT JOIN",
+            Join::FullJoin { .. } => "FULL OUTER JOIN",
+        };
+        let (lt, lc, rt, rc) = self.parts();
+        let name = self.name();
+        let (on, filters) = self.conditions();
+        let mut conditions = vec![format!(r#""{lt}"."{lc}" = "{name}"."{rc}""#)];
+        for (lc, rc) in on {
+            conditions.push(format!(r#""{lt}"."{lc}" = "{name}"."{rc}""#));
+        }
+        let mut params = vec![];
+        for filter in filters {
+            let (filter_sql, mut filter_params) = filter.to_sql(sql_param)?;
+            conditions.push(filter_sql);
+            params.append(&mut filter_params);
+        }
+        let target = match name == rt {
+            true => format!(r#""{rt}""#),
+            false => format!(r#""{rt}" AS "{name}""#),
+        };
+        Ok((
+            format!("{keyword} {target} ON {}", conditions.join(" AND ")),
+            params,
+        ))
+    }
+
+    /// Collect the parameters that must be bound to the SQL generated by [Join::to_sql()]
+    pub fn to_sql_params(&self, kind: &DbKind) -> Result<Vec<JsonValue>> {
+        let mut params = vec![];
+        for filter in self.conditions().1 {
+            params.append(&mut filter.to_sql_params(kind)?);
+        }
+        Ok(params)
+    }
+
+    /// Return a copy of this join without the values of its filters (see [Select::to_shape()])
+    pub fn to_shape(&self) -> Self {
+        let mut shape = self.clone();
+        for filter in shape.filters_mut() {
+            *filter = filter.to_shape();
+        }
+        shape
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Filter {
+    Like {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    NotLike {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    ILike {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    Matches {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    Equal {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    NotEqual {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    GreaterThan {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    GreaterThanOrEqual {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    LessThan {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    LessThanOrEqual {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    Is {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    IsNot {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    In {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    NotIn {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    Between {
+        table: String,
+        column: String,
+        low: JsonValue,
+        high: JsonValue,
+    },
+    InSubquery {
+        table: String,
+        column: String,
+        subquery: Select,
+    },
+    NotInSubquery {
+        table: String,
+        column: String,
+        subquery: Select,
+    },
+    /// A full-text search of the rows of `search_table`, using its search index (see
+    /// [Relatable::add_search()](crate::core::Relatable::add_search())), for all of the words in
+    /// `query`. Like the other filters, `table` qualifies the `_id` column that is filtered on.
+    Search {
+        table: String,
+        search_table: String,
+        query: String,
+    },
+    /// Rows whose dates (or dates and times) in `column` are before the date (or date and time)
+    /// given by `value`, which may be relative to the current one (see [resolve_date()]).
+    /// Dates are compared with dates, and times with times.
+    Before {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    /// Rows whose dates (or dates and times) in `column` are after `value` (see [Filter::Before])
+    After {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    /// Rows whose dates in `column`, ignoring any times, are the date of `value` (see
+    /// [Filter::Before])
+    On {
+        table: String,
+        column: String,
+        value: JsonValue,
+    },
+    /// Rows whose values in `column` are NULL, or are one of `values`, which are the values that
+    /// the column's nulltype allows, e.g., the empty string for the `empty` nulltype, since
+    /// curated tables often store empty strings rather than NULLs. The values are filled in from
+    /// the column's configuration (see [Select::set_nulltypes()]), and are compared as text.
+    IsEmpty {
+        table: String,
+        column: String,
+        values: Vec<String>,
+    },
+    /// Rows whose values in `column` are neither NULL nor one of `values` (see [Filter::IsEmpty])
+    IsNotEmpty {
+        table: String,
+        column: String,
+        values: Vec<String>,
+    },
+    /// A comparison, using `op`, one of the [JSON_PATH_OPERATORS], of `value` with the value at
+    /// `path` in the JSON text of `column`, e.g., `$[0].level` in the `_message` column.
+    JsonPath {
+        table: String,
+        column: String,
+        path: String,
+        op: String,
+        value: JsonValue,
+    },
+}
+impl Filter {
+    /// Return a copy of this filter without its value. Since an `in` filter has one parameter
+    /// per value, up to [MAX_IN_PARAMS] of them, it keeps the number of its values, up to one more
+    /// than that, and whether they are numbers, which decides how a longer list is unpacked.
+    pub fn to_shape(&self) -> Self {
+        let mut shape = self.clone();
+        match &mut shape {
+            Filter::In { value, .. } | Filter::NotIn { value, .. } => match value {
+                JsonValue::Array(values) => {
+                    values.truncate(MAX_IN_PARAMS + 1);
+                    for value in values.iter_mut() {
+                        *value = match value {
+                            JsonValue::Number(_) => json!(0),
+                            _ => JsonValue::Null,
+                        };
+                    }
+                }
+                _ => *value = JsonValue::Null,
+            },
+            Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } => {
+                *subquery = subquery.to_shape()
+            }
+            Filter::Between { low, high, .. } => {
+                *low = JsonValue::Null;
+                *high = JsonValue::Null;
+            }
+            Filter::Search { query, .. } => *query = String::new(),
+            // The values come from the configuration of the column, and are kept:
+            Filter::IsEmpty { .. } | Filter::IsNotEmpty { .. } => (),
+            // Dates and times are compared differently, and relative values are resolved when
+            // they are bound:
+            Filter::Before { value, .. }
+            | Filter::After { value, .. }
+            | Filter::On { value, .. } => {
+                *value = match resolve_date(value) {
+                    Ok((_, true)) => json!("today"),
+                    _ => json!("now"),
+                }
+            }
+            // Values are compared according to their JSON types, and nulls are not bound:
+            Filter::JsonPath { value, .. } => {
+                *value = match value {
+                    JsonValue::Null => JsonValue::Null,
+                    JsonValue::Number(_) => json!(0),
+                    JsonValue::Bool(_) => json!(false),
+                    _ => json!(""),
+                }
+            }
+            Filter::Like { value, .. }
+            | Filter::NotLike { value, .. }
+            | Filter::ILike { value, .. }
+            | Filter::Matches { value, .. }
+            | Filter::Equal { value, .. }
+            | Filter::NotEqual { value, .. }
+            | Filter::GreaterThan { value, .. }
+            | Filter::GreaterThanOrEqual { value, .. }
+            | Filter::LessThan { value, .. }
+            | Filter::LessThanOrEqual { value, .. }
+            | Filter::Is { value, .. }
+            | Filter::IsNot { value, .. } => *value = JsonValue::Null,
+        };
+        shape
+    }
+
+    /// Collect the parameters that must be bound to the SQL generated by [Filter::to_sql()], in
+    /// the same order, without generating the SQL itself.
+    pub fn to_sql_params(&self, kind: &DbKind) -> Result<Vec<JsonValue>> {
+        tracing::trace!("Filter::to_sql_params({kind:?})");
+        match self {
+            Filter::Like { value, .. }
+            | Filter::NotLike { value, .. }
+            | Filter::ILike { value, .. } => {
+                let value = match value {
+                    JsonValue::Bool(value) => value.to_string(),
+                    JsonValue::Number(value) => value.to_string(),
+                    JsonValue::String(value) => value.to_string(),
+                    JsonValue::Null => "NULL".to_string(),
+                    JsonValue::Array(value) => format!("{value:?}"),
+                    JsonValue::Object(value) => format!("{value:?}"),
+                };
+                Ok(vec![json!(value.replace("*", "%"))])
+            }
+            Filter::Matches { value, .. } => match value {
+                JsonValue::String(value) => Ok(vec![json!(value)]),
+                value => Ok(vec![json!(value.to_string())]),
+            },
+            Filter::Equal { value, .. }
+            | Filter::NotEqual { value, .. }
+            | Filter::GreaterThan { value, .. }
+            | Filter::GreaterThanOrEqual { value, .. }
+            | Filter::LessThan { value, .. }
+            | Filter::LessThanOrEqual { value, .. }
+            | Filter::Is { value, .. }
+            | Filter::IsNot { value, .. } => Ok(vec![json!(value)]),
+            Filter::Between { low, high, .. } => Ok(vec![json!(low), json!(high)]),
+            // In SQLite, each word is quoted so that it is not read as FTS5 query syntax:
+            Filter::Search { query, .. } => match kind {
+                DbKind::Sqlite => Ok(vec![json!(query
+                    .split_whitespace()
+                    .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+                    .collect::<Vec<_>>()
+                    .join(" "))]),
+                DbKind::Postgres => Ok(vec![json!(query)]),
+            },
+            Filter::In { value, .. } | Filter::NotIn { value, .. } => {
+                let operator = match self {
+                    Filter::In { .. } => "in",
+                    _ => "not in",
+                };
+                match value {
+                    JsonValue::Array(values) => {
+                        match render_values(values, &mut SqlParam::new(kind)) {
+                            Ok((_, values)) => Ok(values),
+                            Err(e) => Err(RelatableError::DataError(format!(
+                                "Error rendering '{operator}' filter: {e}"
+                            ))
+                            .into()),
+                        }
+                    }
+                    _ => Err(RelatableError::DataError(format!(
+                        "Invalid '{operator}' value: {value}"
+                    ))
+                    .into()),
+                }
+            }
+            Filter::InSubquery { subquery, .. } | Filter::NotInSubquery { subquery, .. } => {
+                subquery.to_sql_params(kind)
+            }
+            Filter::Before { value, .. }
+            | Filter::After { value, .. }
+            | Filter::On { value, .. } => Ok(vec![json!(resolve_date(value)?.0)]),
+            Filter::IsEmpty { values, .. } | Filter::IsNotEmpty { values, .. } => {
+                Ok(values.iter().map(|value| json!(value)).collect())
+            }
+            Filter::JsonPath { op, value, .. } => match (op.as_str(), value, kind) {
+                (_, JsonValue::Null, _) => Ok(vec![]),
+                ("like" | "not_like" | "ilike", JsonValue::String(value), _) => {
+                    Ok(vec![json!(value.replace("*", "%"))])
+                }
+                (_, JsonValue::Bool(value), DbKind::Sqlite) => Ok(vec![json!(*value as u8)]),
+                _ => Ok(vec![json!(value)]),
+            },
+        }
+    }
+
+    /// Interpret the value of this filter, which was given as text, e.g., in a URL, according to
+    /// the given datatype of its column.
+    pub fn set_value_type(&mut self, datatype: &Option<String>) -> &Self {
+        let column = self.get_column();
+        let set_type = |value: &mut JsonValue| {
+            if let JsonValue::String(text) = value {
+                *value = value_as_type(datatype, &column, &text.clone());
+            }
+        };
+        match self {
+            Filter::Like { .. }
+            | Filter::NotLike { .. }
+            | Filter::ILike { .. }
+            | Filter::Matches { .. }
+            | Filter::Search { .. }
+            | Filter::InSubquery { .. }
+            | Filter::NotInSubquery { .. }
+            | Filter::Before { .. }
+            | Filter::After { .. }
+            | Filter::On { .. }
+            | Filter::IsEmpty { .. }
+            | Filter::IsNotEmpty { .. }
+            | Filter::JsonPath { .. } => (),
+            Filter::In { value, .. } | Filter::NotIn { value, .. } => {
+                if let JsonValue::Array(values) = value {
+                    values.iter_mut().for_each(set_type);
+                }
+            }
+            Filter::Between { low, high, .. } => {
+                set_type(low);
+                set_type(high);
+            }
+            Filter::Equal { value, .. }
+            | Filter::NotEqual { value, .. }
+            | Filter::GreaterThan { value, .. }
+            | Filter::GreaterThanOrEqual { value, .. }
+            | Filter::LessThan { value, .. }
+            | Filter::LessThanOrEqual { value, .. }
+            | Filter::Is { value, .. }
+            | Filter::IsNot { value, .. } => set_type(value),
+        };
+        self
+    }
+
+    /// Set the values that count as empty for an [IsEmpty](Filter::IsEmpty) or
+    /// [IsNotEmpty](Filter::IsNotEmpty) filter to those that the given nulltype of its column
+    /// allows (see [Datatype::get_condition_values()]). Other filters are left as they are.
+    pub fn set_nulltype(&mut self, nulltype: &Option<Datatype>) -> &Self {
+        if let Filter::IsEmpty { values, .. } | Filter::IsNotEmpty { values, .. } = self {
+            *values = match nulltype {
+                Some(nulltype) => nulltype.get_condition_values(),
+                None => vec![],
+            };
+        }
+        self
+    }
+
+    pub fn set_table(&mut self, new_name: &str) -> &Self {
+        match self {
+            Filter::Like { table, .. }
+            | Filter::NotLike { table, .. }
+            | Filter::ILike { table, .. }
+            | Filter::Matches { table, .. }
+            | Filter::Equal { table, .. }
+            | Filter::NotEqual { table, .. }
+            | Filter::GreaterThan { table, .. }
+            | Filter::GreaterThanOrEqual { table, .. }
+            | Filter::LessThan { table, .. }
+            | Filter::LessThanOrEqual { table, .. }
+            | Filter::Is { table, .. }
+            | Filter::IsNot { table, .. }
+            | Filter::In { table, .. }
+            | Filter::NotIn { table, .. }
+            | Filter::Between { table, .. }
+            | Filter::Search { table, .. }
+            | Filter::InSubquery { table, .. }
+            | Filter::NotInSubquery { table, .. }
+            | Filter::Before { table, .. }
+            | Filter::After { table, .. }
+            | Filter::On { table, .. }
+            | Filter::IsEmpty { table, .. }
+            | Filter::IsNotEmpty { table, .. }
+            | Filter::JsonPath { table, .. } => *table = new_name.to_string(),
+        };
+        self
+    }
+
+    pub fn set_column(&mut self, new_name: &str) -> &Self {
+        match self {
+            Filter::Like { column, .. }
+            | Filter::NotLike { column, .. }
+            | Filter::ILike { column, .. }
+            | Filter::Matches { column, .. }
+            | Filter::Equal { column, .. }
+            | Filter::NotEqual { column, .. }
+            | Filter::GreaterThan { column, .. }
+            | Filter::GreaterThanOrEqual { column, .. }
+            | Filter::LessThan { column, .. }
+            | Filter::LessThanOrEqual { column, .. }
+            | Filter::Is { column, .. }
+            | Filter::IsNot { column, .. }
+            | Filter::In { column, .. }
+            | Filter::NotIn { column, .. }
+            | Filter::Between { column, .. }
+            | Filter::InSubquery { column, .. }
+            | Filter::NotInSubquery { column, .. }
+            | Filter::Before { column, .. }
+            | Filter::After { column, .. }
+            | Filter::On { column, .. }
+            | Filter::IsEmpty { column, .. }
+            | Filter::IsNotEmpty { column, .. }
+            | Filter::JsonPath { column, .. } => *column = new_name.to_string(),
+            // A search always filters on the _id column:
+            Filter::Search { .. } => (),
+        };
+        self
+    }
+
+    pub fn parts(&self) -> (String, String, String, JsonValue) {
+        tracing::trace!("Filter::parts()");
+        let range;
+        let id_column = "_id".to_string();
+        let (table, column, operator, value) = match self {
+            Filter::Like {
+                table,
+                column,
+                value,
+            } => (table, column, "like", value),
+            Filter::NotLike {
+                table,
+                column,
+                value,
+            } => (table, column, "not_like", value),
+            Filter::ILike {
+                table,
+                column,
+                value,
+            } => (table, column, "ilike", value),
+            Filter::Matches {
+                table,
+                column,
+                value,
+            } => (table, column, "matches", value),
+            Filter::Equal {
+                table,
+                column,
+                value,
+            } => (table, column, "eq", value),
+            Filter::NotEqual {
+                table,
+                column,
+                value,
+            } => (table, column, "not_eq", value),
+            Filter::GreaterThan {
+                table,
+                column,
+                value,
+            } => (table, column, "gt", value),
+            Filter::GreaterThanOrEqual {
+                table,
+                column,
+                value,
+            } => (table, column, "gte", value),
+            Filter::LessThan {
+                table,
+                column,
+                value,
+            } => (table, column, "lt", value),
+            Filter::LessThanOrEqual {
+                table,
+                column,
+                value,
+            } => (table, column, "lte", value),
+            Filter::Is {
+                table,
+                column,
+                value,
+            } => (table, column, "is", value),
+            Filter::IsNot {
+                table,
+                column,
+                value,
+            } => (table, column, "is_not", value),
+            Filter::In {
+                table,
+                column,
+                value,
+            } => (table, column, "in", value),
+            Filter::NotIn {
+                table,
+                column,
+                value,
+            } => (table, column, "not_in", value),
+            Filter::Between {
+                table,
+                column,
+                low,
+                high,
+            } => {
+                range = json!([low, high]);
+                (table, column, "between", &range)
+            }
+            Filter::InSubquery {
+                table,
+                column,
+                subquery,
+            } => (table, column, "in", &json!(subquery)),
+            Filter::NotInSubquery {
+                table,
+                column,
+                subquery,
+            } => (table, column, "not_in", &json!(subquery)),
+            Filter::Search { table, query, .. } => {
+                range = json!(query);
+                (table, &id_column, "search", &range)
+            }
+            Filter::Before {
+                table,
+                column,
+                value,
+            } => (table, column, "before", value),
+            Filter::After {
+                table,
+                column,
+                value,
+            } => (table, column, "after", value),
+            Filter::On {
+                table,
+                column,
+                value,
+            } => (table, column, "on", value),
+            Filter::IsEmpty { table, column, .. } => {
+                range = json!("empty");
+                (table, column, "is", &range)
+            }
+            Filter::IsNotEmpty { table, column, .. } => {
+                range = json!("empty");
+                (table, column, "is_not", &range)
+            }
+            Filter::JsonPath {
+                table,
+                column,
+                op,
+                value,
+                ..
+            } => (table, column, op.as_str(), value),
+        };
+        (
+            table.to_string(),
+            column.to_string(),
+            operator.to_string(),
+            json!(value),
+        )
+    }
+
+    pub fn get_table(&self) -> String {
+        self.parts().0
+    }
+
+    pub fn get_column(&self) -> String {
+        self.parts().1
+    }
+
+    pub fn get_operator(&self) -> String {
+        self.parts().2
+    }
+
+    pub fn get_value(&self) -> JsonValue {
+        self.parts().3
+    }
+
+    /// Whether the value of this filter is relative to the current date or time, so that the rows
+    /// that it matches can change although its table does not
+    pub fn is_relative(&self) -> bool {
+        match self {
+            Filter::Before { value, .. }
+            | Filter::After { value, .. }
+            | Filter::On { value, .. } => value
+                .as_str()
+                .is_some_and(|value| RELATIVE_DATE.is_match(value.trim())),
+            _ => false,
+        }
+    }
+
+    /// Get the JSON path of this filter, if it is a [Filter::JsonPath]
+    pub fn get_json_path(&self) -> Option<String> {
+        match self {
+            Filter::JsonPath { path, .. } => Some(path.to_string()),
+            _ => None,
+        }
+    }
+
+    pub fn to_url(&self) -> Result<String> {
+        tracing::trace!("Filter::to_url()");
+
+        fn handle_string_value(token: &str) -> String {
+            let reserved = vec![':', ',', '.', '(', ')'];
+            if token.chars().all(char::is_numeric) || reserved.iter().any(|&c| token.contains(c)) {
+                if token.contains(char::is_whitespace) {
+                    format!("\"{}\"", token)
+                } else {
+                    token.to_string()
+                }
+            } else {
+                token.to_string()
+            }
+        }
+
+        let (_, _, operator, value) = self.parts();
+        let rhs = match &value {
+            // The values of JSON path filters are read as JSON where they can be:
+            JsonValue::String(string)
+                if matches!(self, Filter::JsonPath { .. })
+                    && serde_json::from_str::<JsonValue>(string).is_ok() =>
+            {
+                value.to_string()
+            }
+            JsonValue::Bool(boolean) if matches!(self, Filter::JsonPath { .. }) => {
+                boolean.to_string()
+            }
+            JsonValue::Null => "null".to_string(),
+            JsonValue::String(string) => handle_string_value(&string),
+            JsonValue::Number(number) => format!("{number}"),
+            JsonValue::Array(vector) => {
+                let mut list = vec![];
+                for item in vector {
+                    match item {
+                        JsonValue::String(string) => list.push(handle_string_value(&string)),
+                        JsonValue::Number(number) => list.push(number.to_string()),
+                        _ => {
+                            return Err(RelatableError::DataError(format!(
+                                "Not all list items in {vector:?} are strings or numbers.",
+                            ))
+                            .into());
+                        }
+                    };
+                }
+                format!("({})", list.join(","))
+            }
+            _ => {
+                if let Filter::InSubquery { .. } | Filter::NotInSubquery { .. } = self {
+                    tracing::error!("Subquery filters are unsupported: {self:?}");
+                }
+                return Err(RelatableError::DataError(format!(
+                    "RHS of Filter: {:?} is not a string, number, or list",
+                    self
+                ))
+                .into());
+            }
+        };
+
+        Ok(format!("{operator}.{rhs}"))
+    }
+
+    pub fn to_sql(&self, sql_param: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("Filter::to_sql({sql_param:?})");
+
+        fn generate_lhs(table: &str, column: &str) -> String {
+            match table {
+                "" => format!(r#""{column}""#),
+                _ => format!(r#""{table}"."{column}""#),
+            }
+        }
+
+        match self {
+            Filter::Like { table, column, .. } => Ok((
+                format!(
+                    r#"{lhs} LIKE {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                self.to_sql_params(&sql_param.kind)?,
+            )),
+            Filter::NotLike { table, column, .. } => Ok((
+                format!(
+                    r#"{lhs} NOT LIKE {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                self.to_sql_params(&sql_param.kind)?,
+            )),
+            Filter::ILike { table, column, .. } => Ok((
+                match sql_param.kind {
+                    DbKind::Postgres => format!(
+                        r#"{lhs} ILIKE {sql_param}"#,
+                        lhs = generate_lhs(table, column),
+                        sql_param = sql_param.next()
+                    ),
+                    DbKind::Sqlite => format!(
+                        r#"LOWER({lhs}) LIKE LOWER({sql_param})"#,
+                        lhs = generate_lhs(table, column),
+                        sql_param = sql_param.next()
+                    ),
+                },
+                self.to_sql_params(&sql_param.kind)?,
+            )),
+            Filter::Matches { table, column, .. } => Ok((
+                match sql_param.kind {
+                    DbKind::Postgres => format!(
+                        r#"{lhs} ~ {sql_param}"#,
+                        lhs = generate_lhs(table, column),
+                        sql_param = sql_param.next()
+                    ),
+                    DbKind::Sqlite => format!(
+                        r#"{lhs} REGEXP {sql_param}"#,
+                        lhs = generate_lhs(table, column),
+                        sql_param = sql_param.next()
+                    ),
+                },
+                self.to_sql_params(&sql_param.kind)?,
+            )),
+            Filter::Equal {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} = {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::NotEqual {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} <> {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::GreaterThan {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} > {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::GreaterThanOrEqual {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} >= {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::LessThan {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} < {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::LessThanOrEqual {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} <= {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::Is {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} {is} {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    is = sql::is_clause(&sql_param.kind),
+                    sql_param = sql_param.next()
+                ),
+                vec![json!(value)],
+            )),
+            Filter::IsNot {
+                table,
+                column,
+                value,
+            } => Ok((
+                format!(
+                    r#"{lhs} {is_not} {sql_param}"#,
+                    lhs = generate_lhs(table, column),
+                    sql_param = sql_param.next(),
+                    is_not = sql::is_not_clause(&sql_param.kind)
+                ),
+                vec![json!(value)],
+            )),
+            Filter::In {
+                table,
+                column,
+                value,
+            } => {
+                if let JsonValue::Array(values) = value {
+                    let lhs = generate_lhs(table, column);
+                    match render_values(values, sql_param) {
+                        Err(e) => {
+                            return Err(RelatableError::DataError(format!(
+                                "Error rendering 'in' filter: {e}"
+                            ))
+                            .into());
+                        }
+                        Ok((rhs, values)) => Ok((format!("{lhs} IN {rhs}"), values)),
+                    }
+                } else {
+                    Err(RelatableError::DataError(format!("Invalid 'in' value: {value}")).into())
+                }
+            }
+            Filter::NotIn {
+                table,
+                column,
+                value,
+            } => {
+                if let JsonValue::Array(values) = value {
+                    let lhs = generate_lhs(table, column);
+                    match render_values(values, sql_param) {
+                        Err(e) => {
+                            return Err(RelatableError::DataError(format!(
+                                "Error rendering 'not in' filter: {e}"
+                            ))
+                            .into());
+                        }
+                        Ok((rhs, values)) => Ok((format!("{lhs} NOT IN {rhs}"), values)),
+                    }
+                } else {
+                    Err(
+                        RelatableError::DataError(format!("Invalid 'not in' value: {value}"))
+                            .into(),
+                    )
+                }
+            }
+            Filter::Search {
+                table,
+                search_table,
+                ..
+            } => {
+                let index = sql::search_index_name(search_table);
+                let subquery = match sql_param.kind {
+                    DbKind::Sqlite => format!(
+                        r#"SELECT "rowid" FROM "{index}" WHERE "{index}" MATCH {sql_param}"#,
+                        sql_param = sql_param.next()
+                    ),
+                    DbKind::Postgres => format!(
+                        r#"SELECT "_id" FROM "{index}"
+                           WHERE "document" @@ PLAINTO_TSQUERY('simple', {sql_param})"#,
+                        sql_param = sql_param.next()
+                    ),
+                };
+                Ok((
+                    format!(r#"{lhs} IN ({subquery})"#, lhs = generate_lhs(table, "_id")),
+                    self.to_sql_params(&sql_param.kind)?,
+                ))
+            }
+            Filter::Between {
+                table,
+                column,
+                low,
+                high,
+            } => Ok((
+                format!(
+                    r#"{lhs} BETWEEN {low_param} AND {high_param}"#,
+                    lhs = generate_lhs(table, column),
+                    low_param = sql_param.next(),
+                    high_param = sql_param.next()
+                ),
+                vec![json!(low), json!(high)],
+            )),
+            Filter::InSubquery {
+                table,
+                column,
+                subquery,
+            } => {
+                let (sql, params) = subquery.to_sql(&sql_param.kind)?;
+                let lhs = generate_lhs(table, column);
+                Ok((format!("{lhs} IN ({})", wrap_sql(&sql)), params))
+            }
+            Filter::NotInSubquery {
+                table,
+                column,
+                subquery,
+            } => {
+                let (sql, params) = subquery.to_sql(&sql_param.kind)?;
+                let lhs = generate_lhs(table, column);
+                Ok((format!("{lhs} NOT IN ({})", wrap_sql(&sql)), params))
+            }
+            Filter::Before {
+                table,
+                column,
+                value,
+            }
+            | Filter::After {
+                table,
+                column,
+                value,
+            }
+            | Filter::On {
+                table,
+                column,
+                value,
+            } => {
+                let (_, is_date) = resolve_date(value)?;
+                let operator = match self {
+                    Filter::Before { .. } => "<",
+                    Filter::After { .. } => ">",
+                    _ => "=",
+                };
+                let lhs = generate_lhs(table, column);
+                let rhs = sql_param.next();
+                // Both sides are converted, so that, e.g., dates are not compared as text with
+                // dates and times, and in SQLite, times with a 'T' separator with those without:
+                let (function, sql_type) = match is_date || operator == "=" {
+                    true => ("DATE", "DATE"),
+                    false => ("DATETIME", "TIMESTAMP"),
+                };
+                let sql = match sql_param.kind {
+                    DbKind::Sqlite => format!("{function}({lhs}) {operator} {function}({rhs})"),
+                    DbKind::Postgres => {
+                        format!("CAST({lhs} AS {sql_type}) {operator} CAST({rhs} AS {sql_type})")
+                    }
+                };
+                Ok((sql, self.to_sql_params(&sql_param.kind)?))
+            }
+            Filter::IsEmpty {
+                table,
+                column,
+                values,
+            }
+            | Filter::IsNotEmpty {
+                table,
+                column,
+                values,
+            } => {
+                let lhs = generate_lhs(table, column);
+                let is_empty = matches!(self, Filter::IsEmpty { .. });
+                let sql = match (values.is_empty(), is_empty) {
+                    (true, true) => format!("{lhs} IS NULL"),
+                    (true, false) => format!("{lhs} IS NOT NULL"),
+                    (false, _) => {
+                        // The values of the nulltype are text, and so is the column compared as:
+                        let rhs = values
+                            .iter()
+                            .map(|_| sql_param.next())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        match is_empty {
+                            true => format!("({lhs} IS NULL OR CAST({lhs} AS TEXT) IN ({rhs}))"),
+                            false => format!(
+                                "({lhs} IS NOT NULL AND CAST({lhs} AS TEXT) NOT IN ({rhs}))"
+                            ),
+                        }
+                    }
+                };
+                Ok((sql, self.to_sql_params(&sql_param.kind)?))
+            }
+            Filter::JsonPath {
+                table,
+                column,
+                path,
+                op,
+                value,
+            } => {
+                // The path is written from its steps, which are only ever simple keys and indexes:
+                let steps = json_path_steps(path)?;
+                let lhs = generate_lhs(table, column);
+                let lhs = match sql_param.kind {
+                    DbKind::Sqlite => {
+                        let path = steps
+                            .iter()
+                            .map(|step| match step {
+                                JsonValue::String(key) => format!(".{key}"),
+                                index => format!("[{index}]"),
+                            })
+                            .collect::<String>();
+                        format!("JSON_EXTRACT({lhs}, '${path}')")
+                    }
+                    // PostgreSQL extracts values as text, which must be cast to be compared with
+                    // numbers and booleans:
+                    DbKind::Postgres => {
+                        let step = |step: &JsonValue| match step {
+                            JsonValue::String(key) => format!("'{key}'"),
+                            index => index.to_string(),
+                        };
+                        let text = match steps.split_last() {
+                            None => format!("CAST({lhs} AS JSONB) #>> '{{}}'"),
+                            Some((last, steps)) => format!(
+                                "CAST({lhs} AS JSONB){} ->> {}",
+                                steps
+                                    .iter()
+                                    .map(|s| format!(" -> {}", step(s)))
+                                    .collect::<String>(),
+                                step(last)
+                            ),
+                        };
+                        match (op.as_str(), value) {
+                            ("like" | "not_like" | "ilike", _) => format!("({text})"),
+                            (_, JsonValue::Number(_)) => format!("CAST(({text}) AS NUMERIC)"),
+                            (_, JsonValue::Bool(_)) => format!("CAST(({text}) AS BOOLEAN)"),
+                            _ => format!("({text})"),
+                        }
+                    }
+                };
+                // Parameters are bound to SQLite as text, which must be cast to be compared with
+                // the numbers and booleans (stored as integers) that it extracts:
+                let rhs = match (value, &sql_param.kind) {
+                    (JsonValue::Null, _) => "NULL".to_string(),
+                    (JsonValue::Number(_), DbKind::Sqlite) => {
+                        format!("CAST({} AS NUMERIC)", sql_param.next())
+                    }
+                    (JsonValue::Bool(_), DbKind::Sqlite) => {
+                        format!("CAST({} AS INTEGER)", sql_param.next())
+                    }
+                    _ => sql_param.next(),
+                };
+                let sql = match op.as_str() {
+                    "eq" => format!("{lhs} = {rhs}"),
+                    "not_eq" => format!("{lhs} <> {rhs}"),
+                    "gt" => format!("{lhs} > {rhs}"),
+                    "gte" => format!("{lhs} >= {rhs}"),
+                    "lt" => format!("{lhs} < {rhs}"),
+                    "lte" => format!("{lhs} <= {rhs}"),
+                    "like" => format!("{lhs} LIKE {rhs}"),
+                    "not_like" => format!("{lhs} NOT LIKE {rhs}"),
+                    "ilike" => match sql_param.kind {
+                        DbKind::Sqlite => format!("LOWER({lhs}) LIKE LOWER({rhs})"),
+                        DbKind::Postgres => format!("{lhs} ILIKE {rhs}"),
+                    },
+                    "is" => format!("{lhs} {} {rhs}", sql::is_clause(&sql_param.kind)),
+                    "is_not" => format!("{lhs} {} {rhs}", sql::is_not_clause(&sql_param.kind)),
+                    op => {
+                        return Err(RelatableError::InputError(format!(
+                            "Invalid JSON path operator '{op}'"
+                        ))
+                        .into())
+                    }
+                };
+                Ok((sql, self.to_sql_params(&sql_param.kind)?))
+            }
+        }
+    }
+
+    /// Generate a SQL statement consisting of a SELECT COUNT(*) over the data that will bereturned
+    /// by the given [Select]
+    pub fn to_sql_count(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("Filter::to_sql_count({self:?}, {kind:?})");
+        match self {
+            Filter::InSubquery {
+                table,
+                column,
+                subquery,
+            } => {
+                if column == "" {
+                    return Err(RelatableError::InputError("Empty column name".to_string()).into());
+                }
+                let lhs = match table.as_str() {
+                    "" => format!(r#""{column}""#),
+                    _ => format!(r#""{table}"."{column}""#),
+                };
+                let (sql, params) = subquery.to_sql(kind)?;
+                let lines: Vec<&str> = sql
+                    .split("\n")
+                    .filter(|x| !x.starts_with("ORDER BY"))
+                    .filter(|x| !x.starts_with("LIMIT"))
+                    .filter(|x| !x.starts_with("OFFSET"))
+                    .collect();
+                Ok((
+                    format!("{lhs} IN ({})", wrap_sql(&lines.join("\n"))),
+                    params,
+                ))
+            }
+            _ => self.to_sql(&mut SqlParam::new(kind)),
+        }
+    }
+}
+
+/// Represents an ORDER BY clause in a SELECT statement. Where the position of NULL values is not
+/// given, it is left to the database: SQLite puts them first in ascending order, and PostgreSQL
+/// puts them last.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Order {
+    #[default]
+    ASC,
+    DESC,
+    AscNullsFirst,
+    AscNullsLast,
+    DescNullsFirst,
+    DescNullsLast,
+}
+
+impl Order {
+    /// Parse an item of the `order` URL parameter, e.g., `island`, `island.desc`, or
+    /// `island.desc.nullslast`, into a column and an [Order].
+    pub fn from_url(item: &str) -> (String, Self) {
+        let (item, nulls) = match item.rsplit_once(".") {
+            Some((rest, nulls)) if ["nullsfirst", "nullslast"].contains(&nulls) => {
+                (rest, Some(nulls))
+            }
+            _ => (item, None),
+        };
+        let (column, descending) = match item.rsplit_once(".") {
+            Some((column, "asc")) => (column, false),
+            Some((column, "desc")) => (column, true),
+            _ => (item, false),
+        };
+        let order = match (descending, nulls) {
+            (false, None) => Order::ASC,
+            (true, None) => Order::DESC,
+            (false, Some("nullsfirst")) => Order::AscNullsFirst,
+            (false, Some(_)) => Order::AscNullsLast,
+            (true, Some("nullsfirst")) => Order::DescNullsFirst,
+            (true, Some(_)) => Order::DescNullsLast,
+        };
+        (column.to_string(), order)
+    }
+
+    /// Returns true if this order is descending
+    pub fn is_descending(&self) -> bool {
+        match self {
+            Order::DESC | Order::DescNullsFirst | Order::DescNullsLast => true,
+            Order::ASC | Order::AscNullsFirst | Order::AscNullsLast => false,
+        }
+    }
+}
+
+impl std::fmt::Display for Order {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            Order::ASC => "ASC",
+            Order::DESC => "DESC",
+            Order::AscNullsFirst => "ASC NULLS FIRST",
+            Order::AscNullsLast => "ASC NULLS LAST",
+            Order::DescNullsFirst => "DESC NULLS FIRST",
+            Order::DescNullsLast => "DESC NULLS LAST",
+        };
+        write!(f, "{result}")
+    }
+}
+
+/// The number of rows that a [Select] returns. In a URL, the `limit` parameter is either a
+/// number of rows, which may be zero, or `none`, for all of them. In JSON, a limit is a number,
+/// or one of the strings `default` and `none`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "JsonValue", try_from = "JsonValue")]
+pub enum Limit {
+    /// The [default number](crate::core::DEFAULT_LIMIT) of rows
+    #[default]
+    Default,
+    /// All of the rows, without a LIMIT clause
+    Unlimited,
+    /// At most the given number of rows, and none at all if it is zero
+    N(usize),
+}
+
+impl Limit {
+    /// Get the limit given by the `limit` parameter of the given query parameters, if there is
+    /// one, or else the default limit. Returns an error for a limit that is neither a number of
+    /// rows nor `none`, e.g., `-1`, so that a server can reject the request.
+    pub fn from_query_params(query_params: &QueryParams) -> Result<Self> {
+        match query_params.get("limit") {
+            Some(limit) => limit.parse(),
+            None => Ok(Limit::Default),
+        }
+    }
+
+    /// Get the most rows that this limit allows, or None if it allows all of them
+    pub fn rows(&self) -> Option<usize> {
+        match self {
+            Limit::Default => Some(DEFAULT_LIMIT),
+            Limit::Unlimited => None,
+            Limit::N(limit) => Some(*limit),
+        }
+    }
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Default => write!(f, "default"),
+            Limit::Unlimited => write!(f, "none"),
+            Limit::N(limit) => write!(f, "{limit}"),
+        }
+    }
+}
+
+impl std::str::FromStr for Limit {
+    type Err = anyhow::Error;
+
+    fn from_str(limit: &str) -> Result<Self> {
+        match limit.trim() {
+            "default" => Ok(Limit::Default),
+            "none" => Ok(Limit::Unlimited),
+            rows => match rows.parse::<usize>() {
+                Ok(rows) => Ok(Limit::N(rows)),
+                Err(_) => Err(RelatableError::InputError(format!(
+                    "Invalid limit '{limit}': expected a number of rows, or 'none'"
+                ))
+                .into()),
+            },
+        }
+    }
+}
+
+impl From<Limit> for JsonValue {
+    fn from(limit: Limit) -> Self {
+        match limit {
+            Limit::N(limit) => json!(limit),
+            _ => json!(limit.to_string()),
+        }
+    }
+}
+
+impl TryFrom<JsonValue> for Limit {
+    type Error = anyhow::Error;
+
+    fn try_from(limit: JsonValue) -> Result<Self> {
+        match limit {
+            JsonValue::Null => Ok(Limit::Default),
+            JsonValue::Number(_) => limit.to_string().parse(),
+            JsonValue::String(limit) => limit.parse(),
+            _ => Err(RelatableError::InputError(format!("Invalid limit {limit}")).into()),
+        }
+    }
+}
+
+/// A problem with a [Select], found by [Select::validate()]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectProblem {
+    /// The table that the problem concerns
+    pub table: String,
+    /// The column that the problem concerns, if any
+    pub column: String,
+    /// The filter value that the problem concerns, if any
+    pub value: String,
+    /// The rule that was broken, e.g., `missing_column` or `sql_type:integer`
+    pub rule: String,
+    pub message: String,
+}
+
+/// One step of the plan by which the database runs a query (see [Select::explain()])
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryPlanStep {
+    /// The identifier of the step, which is unique within the plan
+    pub id: u64,
+    /// The identifier of the step that this step is part of, if it is part of one
+    pub parent: Option<u64>,
+    /// The database's description of the step
+    pub detail: String,
+}
+
+impl QueryPlanStep {
+    /// Convert the lines of a plan written as text, in which the steps that are part of another
+    /// are indented below it, as PostgreSQL does, to a list of steps numbered from 1.
+    pub fn from_indented_lines(lines: &[String]) -> Vec<Self> {
+        tracing::trace!("QueryPlanStep::from_indented_lines({lines:?})");
+        let mut steps = vec![];
+        // The indentation and identifier of each of the steps that the current line may be part
+        // of, from the outermost to the innermost:
+        let mut ancestors: Vec<(usize, u64)> = vec![];
+        for (i, line) in lines.iter().enumerate() {
+            let id = i as u64 + 1;
+            let detail = line.trim_start();
+            let indentation = line.len() - detail.len();
+            while ancestors
+                .last()
+                .is_some_and(|(ancestor_indentation, _)| *ancestor_indentation >= indentation)
+            {
+                ancestors.pop();
+            }
+            steps.push(QueryPlanStep {
+                id,
+                parent: ancestors.last().map(|(_, parent)| *parent),
+                detail: detail.trim_start_matches("->").trim().to_string(),
+            });
+            ancestors.push((indentation, id));
+        }
+        steps
+    }
+}
+
+pub type QueryParams = IndexMap<String, String>;
+
+pub enum Format {
+    Html,
+    Csv,
+    Tsv,
+    Json,
+    ValueJson,
+    PrettyJson,
+    PlanJson,
+    FacetsJson,
+    Arrow,
+    /// Only the number of rows that the select returns, as a bare number
+    Count,
+    Default,
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // TODO: This should be factored out.
+        let result = match self {
+            Format::Html => ".html",
+            Format::Csv => ".csv",
+            Format::Tsv => ".tsv",
+            Format::Json => ".json",
+            Format::ValueJson => ".value.json",
+            Format::PrettyJson => ".pretty.json",
+            Format::PlanJson => ".plan.json",
+            Format::FacetsJson => ".facets.json",
+            Format::Arrow => ".arrow",
+            Format::Count => ".count",
+            Format::Default => "",
+        };
+        write!(f, "{result}")
+    }
+}
+
+impl TryFrom<&String> for Format {
+    fn try_from(path: &String) -> Result<Self> {
+        tracing::trace!("Format::try_from({path:?})");
+        let path = path.to_lowercase();
+        let format = if path.ends_with(".pretty.json") {
+            Format::PrettyJson
+        } else if path.ends_with(".value.json") {
+            Format::ValueJson
+        } else if path.ends_with(".plan.json") {
+            Format::PlanJson
+        } else if path.ends_with(".facets.json") {
+            Format::FacetsJson
+        } else if path.ends_with(".json") {
+            Format::Json
+        } else if path.ends_with(".csv") {
+            Format::Csv
+        } else if path.ends_with(".tsv") {
+            Format::Tsv
+        } else if path.ends_with(".arrow") {
+            Format::Arrow
+        } else if path.ends_with(".count") {
+            Format::Count
+        } else if path.ends_with(".html") || path.ends_with(".htm") {
+            Format::Html
+        } else if path.contains(".") {
+            return Err(
+                RelatableError::FormatError(format!("Unknown format for path {path}")).into(),
+            );
+        } else {
+            Format::Default
+        };
+        Ok(format)
+    }
+
+    type Error = anyhow::Error;
+}
+
+/// A boolean combination of [Filter]s
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "content")]
+pub enum FilterExpr {
+    Filter(Box<Filter>),
+    And(Vec<FilterExpr>),
+    Or(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+}
+
+impl FilterExpr {
+    /// Parse the given condition, written in the filter language used by [Select::filters()] and
+    /// on the command line, e.g., `species = Adelie` or `sample_number in (1, 2, 3)`, into a
+    /// [Filter]. The grammar of a condition is:
+    ///
+    /// ```text
+    /// filter   = column operator value
+    /// column   = name [ "." name ]              a column, which may be qualified by a table
+    /// name     = { letter | digit | "_" | "-" }
+    /// operator = "=" | "!=" | "~=" | "!~=" | ">" | ">=" | "<" | "<="
+    ///          | "is" | "is not" | "in" | "not in" | "between" | "before" | "after" | "on"
+    /// value    = scalar                         for all but the following operators
+    ///          | "(" scalar { "," scalar } ")"  for in and not in
+    ///          | scalar "and" scalar            for between
+    /// scalar   = quoted | word
+    /// ```
+    ///
+    /// Whitespace may be given between any of these, and the operators and `and` that are words
+    /// may be written in any case. A quoted value is written in double quotes, with backslash
+    /// escapes as in JSON, e.g., `"Adelie \"Penguin\""`, and is always text. A word is any run of
+    /// characters other than whitespace, double quotes, parentheses, and commas, and is read as a
+    /// number if it is one, and as NULL if it is `null` and the operator is `is` or `is not`. A
+    /// word `empty` with `is` or `is not` gives a [Filter::IsEmpty] or [Filter::IsNotEmpty]. The
+    /// values of `before`, `after`, and `on` must be dates (see [resolve_date()]). An error
+    /// gives the position, counted in characters from 0, at which the condition is invalid.
+    pub fn parse(text: &str) -> Result<Filter> {
+        tracing::trace!("FilterExpr::parse({text:?})");
+        FilterParser::new(text).filter()
+    }
+
+    /// Parse the given expression, written in the syntax used in URLs, e.g.,
+    /// `or(island.eq.Dream,and(sample_number.gt.5,not.species.like.Adelie*))`, in which each
+    /// condition has the form `[TABLE.]COLUMN.OPERATOR.VALUE`, with one of the [URL_OPERATORS].
+    /// The values of the conditions are not interpreted according to the datatypes of their
+    /// columns: see [Select::from_path_and_query()].
+    pub fn from_url(expression: &str) -> Result<Self> {
+        tracing::trace!("FilterExpr::from_url({expression:?})");
+        let invalid = || -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid filter expression '{expression}'")).into()
+        };
+        if let Some(inner) = expression.strip_prefix("not.") {
+            return Ok(FilterExpr::Not(Box::new(FilterExpr::from_url(inner)?)));
+        }
+        for (keyword, is_or) in [("or(", true), ("and(", false)] {
+            if let Some(inner) = expression.strip_prefix(keyword) {
+                let inner = inner.strip_suffix(")").ok_or_else(invalid)?;
+                let exprs = split_top_level(inner)
+                    .iter()
+                    .map(|expr| FilterExpr::from_url(expr))
+                    .collect::<Result<Vec<_>>>()?;
+                return match is_or {
+                    true => Ok(FilterExpr::Or(exprs)),
+                    false => Ok(FilterExpr::And(exprs)),
+                };
+            }
+        }
+
+        // Anything else must be a condition. The operator is the first part of the condition,
+        // after the column, that is one of the URL_OPERATORS:
+        let parts = expression.split(".").collect::<Vec<_>>();
+        let i = (1..parts.len())
+            .find(|i| URL_OPERATORS.contains(&parts[*i]))
+            .ok_or_else(invalid)?;
+        let lhs = parts[..i].join(".");
+        let (lhs, json_path) = match lhs.split_once("->") {
+            Some((lhs, path)) => (lhs.to_string(), Some(path)),
+            None => (lhs.clone(), None),
+        };
+        let (table, column) = match lhs.split_once(".") {
+            Some((table, column)) => (table.to_string(), column.to_string()),
+            None => (String::new(), lhs.clone()),
+        };
+        let pattern = parts[i..].join(".");
+        let filter = match json_path {
+            Some(path) => url_json_path_filter(table, column, path, &pattern),
+            None => url_filter(table, column, &pattern, &None),
+        }
+        .ok_or_else(invalid)?;
+        Ok(FilterExpr::Filter(Box::new(filter)))
+    }
+
+    /// Convert this expression to the syntax used in URLs (see [FilterExpr::from_url()])
+    pub fn to_url(&self) -> Result<String> {
+        tracing::trace!("FilterExpr::to_url()");
+        let join = |exprs: &Vec<FilterExpr>| -> Result<String> {
+            Ok(exprs
+                .iter()
+                .map(|expr| expr.to_url())
+                .collect::<Result<Vec<_>>>()?
+                .join(","))
+        };
+        match self {
+            FilterExpr::Filter(filter) => {
+                let (table, mut column, _, _) = filter.parts();
+                if let Some(path) = filter.get_json_path() {
+                    column = format!("{column}->{path}");
+                }
+                match table.as_str() {
+                    "" => Ok(format!("{column}.{}", filter.to_url()?)),
+                    _ => Ok(format!("{table}.{column}.{}", filter.to_url()?)),
+                }
+            }
+            FilterExpr::And(exprs) => Ok(format!("and({})", join(exprs)?)),
+            FilterExpr::Or(exprs) => Ok(format!("or({})", join(exprs)?)),
+            FilterExpr::Not(expr) => Ok(format!("not.{}", expr.to_url()?)),
+        }
+    }
+
+    /// Get the filters that occur in this expression
+    pub fn get_filters(&self) -> Vec<&Filter> {
+        match self {
+            FilterExpr::Filter(filter) => vec![filter.as_ref()],
+            FilterExpr::And(exprs) | FilterExpr::Or(exprs) => {
+                exprs.iter().flat_map(|expr| expr.get_filters()).collect()
+            }
+            FilterExpr::Not(expr) => expr.get_filters(),
+        }
+    }
+
+    /// Get mutable references to the filters that occur in this expression
+    pub fn get_filters_mut(&mut self) -> Vec<&mut Filter> {
+        match self {
+            FilterExpr::Filter(filter) => vec![filter.as_mut()],
+            FilterExpr::And(exprs) | FilterExpr::Or(exprs) => exprs
+                .iter_mut()
+                .flat_map(|expr| expr.get_filters_mut())
+                .collect(),
+            FilterExpr::Not(expr) => expr.get_filters_mut(),
+        }
+    }
+
+    /// Return a copy of this expression in which every filter has been replaced by its
+    /// [shape](Filter::to_shape())
+    pub fn to_shape(&self) -> Self {
+        let mut shape = self.clone();
+        for filter in shape.get_filters_mut() {
+            *filter = filter.to_shape();
+        }
+        shape
+    }
+
+    /// Combine the SQL generated for each of the given expressions using the given function,
+    /// joining them with the given keyword. An empty conjunction is always true and an empty
+    /// disjunction is always false.
+    fn combine_sql(
+        exprs: &Vec<FilterExpr>,
+        keyword: &str,
+        to_sql: &mut impl FnMut(&FilterExpr) -> Result<(String, Vec<JsonValue>)>,
+    ) -> Result<(String, Vec<JsonValue>)> {
+        if exprs.is_empty() {
+            let sql = match keyword {
+                "AND" => "1 = 1",
+                _ => "1 = 0",
+            };
+            return Ok((sql.to_string(), vec![]));
+        }
+        let mut parts = vec![];
+        let mut params = vec![];
+        for expr in exprs {
+            let (sql, mut expr_params) = to_sql(expr)?;
+            parts.push(sql);
+            params.append(&mut expr_params);
+        }
+        Ok((format!("({})", parts.join(&format!(" {keyword} "))), params))
+    }
+
+    /// Convert the expression to a tuple consisting of an SQL string, using the given parameter
+    /// generator, and a vector of parameters that must be bound to the string.
+    pub fn to_sql(&self, sql_param: &mut SqlParam) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("FilterExpr::to_sql({sql_param:?})");
+        match self {
+            FilterExpr::Filter(filter) => filter.to_sql(sql_param),
+            FilterExpr::And(exprs) => {
+                FilterExpr::combine_sql(exprs, "AND", &mut |expr| expr.to_sql(sql_param))
+            }
+            FilterExpr::Or(exprs) => {
+                FilterExpr::combine_sql(exprs, "OR", &mut |expr| expr.to_sql(sql_param))
+            }
+            FilterExpr::Not(expr) => {
+                let (sql, params) = expr.to_sql(sql_param)?;
+                Ok((format!("NOT ({sql})"), params))
+            }
+        }
+    }
+
+    /// Convert the expression to SQL for use in a count of the rows that it matches, in the same
+    /// way as [Filter::to_sql_count()].
+    pub fn to_sql_count(&self, kind: &DbKind) -> Result<(String, Vec<JsonValue>)> {
+        tracing::trace!("FilterExpr::to_sql_count({kind:?})");
+        match self {
+            FilterExpr::Filter(filter) => filter.to_sql_count(kind),
+            FilterExpr::And(exprs) => {
+                FilterExpr::combine_sql(exprs, "AND", &mut |expr| expr.to_sql_count(kind))
+            }
+            FilterExpr::Or(exprs) => {
+                FilterExpr::combine_sql(exprs, "OR", &mut |expr| expr.to_sql_count(kind))
+            }
+            FilterExpr::Not(expr) => {
+                let (sql, params) = expr.to_sql_count(kind)?;
+                Ok((format!("NOT ({sql})"), params))
+            }
+        }
+    }
+
+    /// Collect the parameters that must be bound to the SQL generated by [FilterExpr::to_sql()],
+    /// in the same order, without generating the SQL itself.
+    pub fn to_sql_params(&self, kind: &DbKind) -> Result<Vec<JsonValue>> {
+        let mut params = vec![];
+        for filter in self.get_filters() {
+            params.append(&mut filter.to_sql_params(kind)?);
+        }
+        Ok(params)
+    }
+}
+
+/// A parser of the conditions of the filter language (see [FilterExpr::parse()]), which reads
+/// them from left to right, a character at a time.
+struct FilterParser<'a> {
+    text: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl<'a> FilterParser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            chars: text.chars().collect(),
+            position: 0,
+        }
+    }
+
+    /// An error saying what was expected at the current position
+    fn error(&self, expected: &str) -> anyhow::Error {
+        RelatableError::ConfigError(format!(
+            "Invalid filter '{}' at position {}: expected {expected}",
+            self.text, self.position
+        ))
+        .into()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.position < self.chars.len() && self.chars[self.position].is_whitespace() {
+            self.position += 1;
+        }
+    }
+
+    /// Read the characters from the current position for as long as they satisfy the predicate
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> String {
+        let start = self.position;
+        while self.position < self.chars.len() && predicate(self.chars[self.position]) {
+            self.position += 1;
+        }
+        self.chars[start..self.position].iter().collect()
+    }
+
+    /// Read the given symbol, if it comes next
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let symbol = symbol.chars().collect::<Vec<_>>();
+        let end = self.position + symbol.len();
+        if end <= self.chars.len() && self.chars[self.position..end] == symbol[..] {
+            self.position = end;
+            return true;
+        }
+        false
+    }
+
+    /// Read the next word, if it is the given keyword, in any case
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let start = self.position;
+        self.skip_whitespace();
+        if self.word().to_lowercase() == keyword {
+            return true;
+        }
+        self.position = start;
+        false
+    }
+
+    fn word(&mut self) -> String {
+        self.take_while(|c| !c.is_whitespace() && !['"', '(', ')', ','].contains(&c))
+    }
+
+    fn filter(&mut self) -> Result<Filter> {
+        self.skip_whitespace();
+        let is_name = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+        let mut column = self.take_while(is_name);
+        if column.is_empty() {
+            return Err(self.error("a column"));
+        }
+        let mut table = String::new();
+        if self.symbol(".") {
+            table = column;
+            column = self.take_while(is_name);
+            if column.is_empty() {
+                return Err(self.error("a column"));
+            }
+        }
+
+        self.skip_whitespace();
+        let operator = match ["!~=", "~=", "!=", ">=", "<=", "=", ">", "<"]
+            .into_iter()
+            .find(|symbol| self.symbol(symbol))
+        {
+            Some(symbol) => symbol.to_string(),
+            None => {
+                let start = self.position;
+                let operator = match self.word().to_lowercase().as_str() {
+                    "is" if self.keyword("not") => "is not".to_string(),
+                    "not" if self.keyword("in") => "not in".to_string(),
+                    operator @ ("is" | "in" | "between" | "before" | "after" | "on") => {
+                        operator.to_string()
+                    }
+                    _ => {
+                        self.position = start;
+                        return Err(self.error("an operator"));
+                    }
+                };
+                operator
+            }
+        };
+
+        self.skip_whitespace();
+        let filter = match operator.as_str() {
+            "in" | "not in" => {
+                if !self.symbol("(") {
+                    return Err(self.error("'('"));
+                }
+                let mut values = vec![self.scalar()?];
+                self.skip_whitespace();
+                while self.symbol(",") {
+                    values.push(self.scalar()?);
+                    self.skip_whitespace();
+                }
+                if !self.symbol(")") {
+                    return Err(self.error("',' or ')'"));
+                }
+                let value = json!(values);
+                match operator.as_str() {
+                    "in" => Filter::In {
+                        table,
+                        column,
+                        value,
+                    },
+                    _ => Filter::NotIn {
+                        table,
+                        column,
+                        value,
+                    },
+                }
+            }
+            "between" => {
+                let low = self.scalar()?;
+                if !self.keyword("and") {
+                    return Err(self.error("'and'"));
+                }
+                let high = self.scalar()?;
+                Filter::Between {
+                    table,
+                    column,
+                    low,
+                    high,
+                }
+            }
+            _ => {
+                let start = self.position;
+                let is_word = self.chars.get(self.position) != Some(&'"');
+                let mut value = self.scalar()?;
+                if operator.starts_with("is") && is_word && value.as_str() == Some("null") {
+                    value = JsonValue::Null;
+                }
+                if operator.starts_with("is") && is_word && value.as_str() == Some("empty") {
+                    let values = vec![];
+                    self.skip_whitespace();
+                    if self.position < self.chars.len() {
+                        return Err(self.error("the end of the filter"));
+                    }
+                    return Ok(match operator.as_str() {
+                        "is" => Filter::IsEmpty {
+                            table,
+                            column,
+                            values,
+                        },
+                        _ => Filter::IsNotEmpty {
+                            table,
+                            column,
+                            values,
+                        },
+                    });
+                }
+                if ["before", "after", "on"].contains(&operator.as_str())
+                    && resolve_date(&value).is_err()
+                {
+                    self.position = start;
+                    return Err(self.error("a date"));
+                }
+                match operator.as_str() {
+                    "=" => Filter::Equal {
+                        table,
+                        column,
+                        value,
+                    },
+                    "!=" => Filter::NotEqual {
+                        table,
+                        column,
+                        value,
+                    },
+                    "~=" => Filter::Like {
+                        table,
+                        column,
+                        value,
+                    },
+                    "!~=" => Filter::NotLike {
+                        table,
+                        column,
+                        value,
+                    },
+                    ">" => Filter::GreaterThan {
+                        table,
+                        column,
+                        value,
+                    },
+                    ">=" => Filter::GreaterThanOrEqual {
+                        table,
+                        column,
+                        value,
+                    },
+                    "<" => Filter::LessThan {
+                        table,
+                        column,
+                        value,
+                    },
+                    "<=" => Filter::LessThanOrEqual {
+                        table,
+                        column,
+                        value,
+                    },
+                    "is" => Filter::Is {
+                        table,
+                        column,
+                        value,
+                    },
+                    "is not" => Filter::IsNot {
+                        table,
+                        column,
+                        value,
+                    },
+                    "before" => Filter::Before {
+                        table,
+                        column,
+                        value,
+                    },
+                    "after" => Filter::After {
+                        table,
+                        column,
+                        value,
+                    },
+                    _ => Filter::On {
+                        table,
+                        column,
+                        value,
+                    },
+                }
+            }
+        };
+
+        self.skip_whitespace();
+        if self.position < self.chars.len() {
+            return Err(self.error("the end of the filter"));
+        }
+        Ok(filter)
+    }
+
+    /// Read a quoted value, which is text, or a word, which is a number if it can be read as one
+    fn scalar(&mut self) -> Result<JsonValue> {
+        self.skip_whitespace();
+        let start = self.position;
+        if self.symbol("\"") {
+            while self.position < self.chars.len() && self.chars[self.position] != '"' {
+                if self.chars[self.position] == '\\' {
+                    self.position += 1;
+                }
+                self.position += 1;
+            }
+            if !self.symbol("\"") {
+                return Err(self.error("'\"'"));
+            }
+            let quoted = self.chars[start..self.position].iter().collect::<String>();
+            return match serde_json::from_str::<String>(&quoted) {
+                Ok(value) => Ok(json!(value)),
+                Err(_) => {
+                    self.position = start;
+                    Err(self.error("a valid quoted value"))
+                }
+            };
+        }
+        let word = self.word();
+        if word.is_empty() {
+            return Err(self.error("a value"));
+        }
+        if let Ok(integer) = word.parse::<i64>() {
+            return Ok(json!(integer));
+        }
+        match word.parse::<f64>() {
+            Ok(float) if float.is_finite() => Ok(json!(float)),
+            _ => Ok(json!(word)),
+        }
+    }
+}
+
+/// Split the given list of filter expressions, separated by commas, at the commas that are not
+/// enclosed in parentheses or double quotes.
+fn split_top_level(list: &str) -> Vec<String> {
+    let mut items = vec![];
+    let mut item = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    for c in list.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                items.push(item.trim().to_string());
+                item.clear();
+                continue;
+            }
+            _ => (),
+        };
+        item.push(c);
+    }
+    if !item.trim().is_empty() {
+        items.push(item.trim().to_string());
+    }
+    items
+}
+
+/// Indent each of the lines of the given SQL by two spaces
+fn indent_sql(sql: &str) -> String {
+    sql.lines()
+        .map(|line| format!("  {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write the given condition as the one at the given position in a WHERE clause, with the lines
+/// of any subquery in it indented by two spaces more than the line that the condition starts on
+fn where_sql(position: usize, condition: &str) -> String {
+    match position {
+        0 => format!("WHERE {condition}"),
+        _ => format!("  AND {}", condition.replace("\n", "\n  ")),
+    }
+}
+
+/// Put the given SQL on lines of its own, indented, so that it can be written within parentheses,
+/// e.g., as a subquery
+fn wrap_sql(sql: &str) -> String {
+    format!("\n{}\n", indent_sql(sql))
+}
+
+/// Whether a request for the given path with the given limit is an export of all of the rows of
+/// a table, e.g., `/table/penguin.csv?limit=none`, to which no maximum page size applies
+fn is_unlimited_export(path: &str, limit: &Limit) -> bool {
+    *limit == Limit::Unlimited
+        && matches!(
+            Format::try_from(&path.to_string()),
+            Ok(Format::Csv | Format::Tsv | Format::Arrow)
+        )
+}
+
+/// Split the given column, which may be qualified by a table or alias, e.g., `mate.species`, into
+/// its table (empty when unqualified) and column.
+fn split_qualified(column: &str) -> (String, String) {
+    match column.split_once(".") {
+        Some((table, column)) => (table.to_string(), column.to_string()),
+        None => (String::new(), column.to_string()),
+    }
+}
+
+/// The operators that may be used in the filters given in URLs, e.g., `eq` in `island=eq.Dream`
+pub const URL_OPERATORS: [&str; 18] = [
+    "like", "not_like", "ilike", "matches", "eq", "not_eq", "gt", "gte", "lt", "lte", "is",
+    "is_not", "in", "not_in", "between", "before", "after", "on",
+];
+
+/// The operators that may be used in [Filter::JsonPath] filters
+pub const JSON_PATH_OPERATORS: [&str; 11] = [
+    "like", "not_like", "ilike", "eq", "not_eq", "gt", "gte", "lt", "lte", "is", "is_not",
+];
+
+/// Split the given JSON path, e.g., `$[0].level`, into its steps: the keys of objects, as strings,
+/// and the indexes of arrays, as numbers. Keys may only consist of letters, digits, and
+/// underscores.
+fn json_path_steps(path: &str) -> Result<Vec<JsonValue>> {
+    let invalid = || -> anyhow::Error {
+        RelatableError::InputError(format!("Invalid JSON path '{path}'")).into()
+    };
+    let mut rest = path.strip_prefix("$").ok_or_else(invalid)?;
+    let mut steps = vec![];
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("[") {
+            let (index, after) = after.split_once("]").ok_or_else(invalid)?;
+            steps.push(json!(index.parse::<u64>().map_err(|_| invalid())?));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(".") {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                return Err(invalid());
+            }
+            steps.push(json!(key));
+            rest = &after[end..];
+        } else {
+            return Err(invalid());
+        }
+    }
+    Ok(steps)
+}
+
+/// Resolve the given value of a date filter (see [Filter::Before]) to a date, written as
+/// `YYYY-MM-DD`, or a date and time, written as `YYYY-MM-DD HH:MM:SS`, along with whether it is a
+/// date. Besides a date or a date and time, the value may be the current date, `today`, or the
+/// current date and time in UTC, `now`, either of which may be followed by an offset in days (`d`)
+/// or weeks (`w`), or, for `now`, in hours (`h`), minutes (`m`), or seconds (`s`), e.g., `now-7d`.
+/// Since a '+' in a URL is read as a space, a space may be given instead, e.g., `today 1w`.
+pub fn resolve_date(value: &JsonValue) -> Result<(String, bool)> {
+    let invalid = || -> anyhow::Error {
+        RelatableError::InputError(format!("Invalid date or time {value}")).into()
+    };
+    let text = match value {
+        JsonValue::String(text) => unquote(text).unwrap_or(text.to_string()),
+        _ => return Err(invalid()),
+    };
+    let text = text.trim();
+    if let Some(captures) = RELATIVE_DATE.captures(text) {
+        let is_date = &captures[1] == "today";
+        let offset = match (captures.get(2), captures.get(3), captures.get(4)) {
+            (Some(sign), Some(amount), Some(unit)) => {
+                let amount = amount.as_str().parse::<i64>().map_err(|_| invalid())?;
+                let amount = match sign.as_str() {
+                    "-" => -amount,
+                    _ => amount,
+                };
+                match (unit.as_str(), is_date) {
+                    ("d", _) => TimeDelta::try_days(amount),
+                    ("w", _) => TimeDelta::try_weeks(amount),
+                    (_, true) => None,
+                    ("h", _) => TimeDelta::try_hours(amount),
+                    ("m", _) => TimeDelta::try_minutes(amount),
+                    _ => TimeDelta::try_seconds(amount),
+                }
+                .ok_or_else(invalid)?
+            }
+            _ => TimeDelta::zero(),
+        };
+        let moment = Utc::now()
+            .naive_utc()
+            .checked_add_signed(offset)
+            .ok_or_else(invalid)?;
+        return match is_date {
+            true => Ok((moment.format("%Y-%m-%d").to_string(), true)),
+            false => Ok((moment.format("%Y-%m-%d %H:%M:%S").to_string(), false)),
+        };
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Ok((date.format("%Y-%m-%d").to_string(), true));
+    }
+    for format in [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(moment) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok((moment.format("%Y-%m-%d %H:%M:%S").to_string(), false));
+        }
+    }
+    Err(invalid())
+}
+
+/// Interpret the given value, from a filter in a URL on the given column, according to the
+/// given datatype.
+fn value_as_type(datatype: &Option<String>, column: &str, value: &str) -> JsonValue {
+    fn try_parse_as_int(value: &str) -> JsonValue {
+        match value.parse::<i64>() {
+            Ok(signed) => json!(signed),
+            _ => {
+                tracing::warn!("Could not parse {value} as integer. Treating as string");
+                JsonValue::String(value.to_string())
+            }
+        }
+    }
+
+    fn try_parse_as_decimal(value: &str) -> JsonValue {
+        match value.parse::<f64>() {
+            Ok(signed) => json!(signed),
+            _ => {
+                tracing::warn!("Could not parse {value} as decimal. Treating as string");
+                JsonValue::String(value.to_string())
+            }
+        }
+    }
+
+    if ["_id", "_order", "_change_id"].contains(&column) {
+        try_parse_as_int(value)
+    } else if ["_history", "_message"].contains(&column) {
+        JsonValue::String(value.to_string())
+    } else {
+        match datatype {
+            Some(datatype) if datatype == "integer" => try_parse_as_int(value),
+            Some(datatype) if datatype == "decimal" => try_parse_as_decimal(value),
+            Some(datatype) if datatype == "text" => JsonValue::String(value.to_string()),
+            Some(datatype) => {
+                tracing::warn!("Unsupported datatype: {datatype}. Treating {value} as string");
+                JsonValue::String(value.to_string())
+            }
+            None => JsonValue::String(value.to_string()),
+        }
+    }
+}
+
+/// Convert the given pattern, e.g., `eq.error`, to a [Filter::JsonPath] on the given JSON path in
+/// the given table and column. The value is read as JSON if it can be, and as text otherwise.
+/// Returns None if the path is invalid or the operator is not one of the [JSON_PATH_OPERATORS].
+fn url_json_path_filter(
+    table: String,
+    column: String,
+    path: &str,
+    pattern: &str,
+) -> Option<Filter> {
+    let (op, value) = pattern.split_once(".")?;
+    if let Err(error) = json_path_steps(path) {
+        tracing::warn!("{error}");
+        return None;
+    }
+    if !JSON_PATH_OPERATORS.contains(&op) {
+        tracing::warn!("Invalid JSON path operator '{op}'");
+        return None;
+    }
+    Some(Filter::JsonPath {
+        table,
+        column,
+        path: path.to_string(),
+        op: op.to_string(),
+        value: serde_json::from_str(value).unwrap_or(JsonValue::String(value.to_string())),
+    })
+}
+
+/// Convert the given pattern, i.e., the value of a filter in a URL such as `eq.Dream`, to a
+/// [Filter] on the given table and column, interpreting its value according to the given
+/// datatype. Returns None if the pattern's operator is not one of the [URL_OPERATORS].
+fn url_filter(
+    table: String,
+    column: String,
+    pattern: &str,
+    datatype: &Option<String>,
+) -> Option<Filter> {
+    let (operator, value) = pattern.split_once(".")?;
+    let typed = || value_as_type(datatype, &column, value);
+    let nullable = || match value.to_lowercase().as_str() {
+        "null" => JsonValue::Null,
+        _ => typed(),
+    };
+    let list = || {
+        let separator = Regex::new(r"\s*,\s*").unwrap();
+        let values = match value.strip_prefix("(").and_then(|s| s.strip_suffix(")")) {
+            None => {
+                tracing::warn!("invalid '{operator}' filter value {pattern}");
+                ""
+            }
+            Some(s) => s,
+        };
+        let values = separator
+            .split(values)
+            .map(|v| value_as_type(datatype, &column, v))
+            .collect::<Vec<_>>();
+        json!(values)
+    };
+    let filter = match operator {
+        "like" => Filter::Like {
+            value: serde_json::from_str(value).unwrap_or(JsonValue::String(value.to_string())),
+            table,
+            column,
+        },
+        "not_like" => Filter::NotLike {
+            value: serde_json::from_str(value).unwrap_or(JsonValue::String(value.to_string())),
+            table,
+            column,
+        },
+        "ilike" => Filter::ILike {
+            value: serde_json::from_str(value).unwrap_or(JsonValue::String(value.to_string())),
+            table,
+            column,
+        },
+        "matches" => Filter::Matches {
+            value: JsonValue::String(value.to_string()),
+            table,
+            column,
+        },
+        "eq" => Filter::Equal {
+            value: typed(),
+            table,
+            column,
+        },
+        "not_eq" => Filter::NotEqual {
+            value: typed(),
+            table,
+            column,
+        },
+        "gt" => Filter::GreaterThan {
+            value: typed(),
+            table,
+            column,
+        },
+        "gte" => Filter::GreaterThanOrEqual {
+            value: typed(),
+            table,
+            column,
+        },
+        "lt" => Filter::LessThan {
+            value: typed(),
+            table,
+            column,
+        },
+        "lte" => Filter::LessThanOrEqual {
+            value: typed(),
+            table,
+            column,
+        },
+        // Empty values, whether NULL or allowed by the column's nulltype, e.g., is.empty:
+        "is" if value.eq_ignore_ascii_case("empty") => Filter::IsEmpty {
+            table,
+            column,
+            values: vec![],
+        },
+        "is_not" if value.eq_ignore_ascii_case("empty") => Filter::IsNotEmpty {
+            table,
+            column,
+            values: vec![],
+        },
+        "is" => Filter::Is {
+            value: nullable(),
+            table,
+            column,
+        },
+        "is_not" => Filter::IsNot {
+            value: nullable(),
+            table,
+            column,
+        },
+        "in" => Filter::In {
+            value: list(),
+            table,
+            column,
+        },
+        "not_in" => Filter::NotIn {
+            value: list(),
+            table,
+            column,
+        },
+        "between" => match list() {
+            JsonValue::Array(values) if values.len() == 2 => Filter::Between {
+                low: values[0].clone(),
+                high: values[1].clone(),
+                table,
+                column,
+            },
+            _ => {
+                tracing::warn!("invalid '{operator}' filter value {pattern}");
+                return None;
+            }
+        },
+        "before" | "after" | "on" => {
+            let value = JsonValue::String(value.to_string());
+            if let Err(error) = resolve_date(&value) {
+                tracing::warn!("{error}");
+                return None;
+            }
+            match operator {
+                "before" => Filter::Before {
+                    value,
+                    table,
+                    column,
+                },
+                "after" => Filter::After {
+                    value,
+                    table,
+                    column,
+                },
+                _ => Filter::On {
+                    value,
+                    table,
+                    column,
+                },
+            }
+        }
+        _ => return None,
+    };
+    Some(filter)
+}
+
+/// Render the given values of an `in` or `not in` filter as the right-hand side of the filter,
+/// along with the parameters to bind to it: either one parameter per value, or, for more than
+/// [MAX_IN_PARAMS] values, a single JSON array of them.
+pub fn render_values(
+    options: &Vec<JsonValue>,
+    sql_param_gen: &mut SqlParam,
+) -> Result<(String, Vec<JsonValue>)> {
+    let mut values = vec![];
+    let mut is_string_list = false;
+    for (i, option) in options.iter().enumerate() {
+        match option {
+            JsonValue::String(str_opt) => {
+                if i == 0 {
+                    is_string_list = true;
+                } else if !is_string_list {
+                    return Err(RelatableError::InputError(format!(
+                        "{:?} contains both text and numeric types.",
+                        options
+                    ))
+                    .into());
+                }
+                let value = unquote(str_opt).unwrap_or(str_opt.clone());
+                values.push(format!("{value}").into())
+            }
+            JsonValue::Number(_) => {
+                if i == 0 {
+                    is_string_list = false;
+                } else if is_string_list {
+                    return Err(RelatableError::InputError(format!(
+                        "{:?} contains both text and numeric types.",
+                        options
+                    ))
+                    .into());
+                }
+                values.push(option.clone())
+            }
+            _ => {
+                return Err(RelatableError::InputError(format!(
+                    "{:?} is not an array of strings or numbers.",
+                    options
+                ))
+                .into())
+            }
+        };
+    }
+
+    // Longer lists are bound as a single JSON array, which is unpacked by the database:
+    if values.len() > MAX_IN_PARAMS {
+        let sql_param = sql_param_gen.next();
+        let rhs = match (&sql_param_gen.kind, is_string_list) {
+            (DbKind::Sqlite, _) => format!(r#"(SELECT "value" FROM JSON_EACH({sql_param}))"#),
+            (DbKind::Postgres, true) => {
+                format!("(SELECT JSONB_ARRAY_ELEMENTS_TEXT({sql_param}::JSONB))")
+            }
+            (DbKind::Postgres, false) => {
+                format!("(SELECT JSONB_ARRAY_ELEMENTS_TEXT({sql_param}::JSONB)::NUMERIC)")
+            }
+        };
+        return Ok((rhs, vec![json!(json!(values).to_string())]));
+    }
+    let sql_params = values
+        .iter()
+        .map(|_| sql_param_gen.next())
+        .collect::<Vec<_>>();
+    Ok((format!("({})", sql_params.join(", ")), values))
+}
+
+pub async fn joined_query(
+    rltbl: &Relatable,
+    tableset_name: &str,
+    select: &Select,
+) -> Result<Select> {
+    let tables = select.get_tables();
+    if tables.len() == 1 {
+        return Ok(select.clone());
+    }
+
+    // Walk the tableset from the given tables back to its root, collecting the joins along the
+    // way, in order:
+    let tables: Vec<String> = tables.into_iter().collect();
+    let columns = vec![
+        "_order",
+        "left_table",
+        "left_column",
+        "right_table",
+        "right_column",
+    ];
+    let mut base = Select::from("tableset")
+        .unlimited()
+        .is_in("right_table", &tables)?;
+    base.select_columns(&columns);
+    let mut step = Select::from("ancestors").unlimited();
+    step.inner_join("ancestors", "left_table", "tableset", "right_table");
+    step.alias_join("t")?;
+    step.select_table_columns("t", &columns);
+    step.filters.push(Filter::Equal {
+        table: "t".to_string(),
+        column: "tableset".to_string(),
+        value: json!(tableset_name),
+    });
+    let ancestors = Select::from("ancestors").unlimited().with_recursive(
+        "ancestors",
+        &columns,
+        &base.union(&step)?,
+    );
+    let (sql, params) = ancestors.to_sql(&rltbl.connection.kind())?;
+    tracing::info!("SQL {sql}");
+    tracing::info!("PARAMS {params:?}");
+    let json_rows = rltbl.connection.query(&sql, Some(&json!(params))).await?;
+
+    // Keep only the joins from the first join out of one of the given tables to the last join
+    // into one of them:
+    let get_orders = |column: &str| {
+        json_rows
+            .iter()
+            .filter(|json_row| {
+                json_row
+                    .get_string(column)
+                    .is_ok_and(|table| tables.contains(&table))
+            })
+            .filter_map(|json_row| json_row.get_unsigned("_order").ok())
+            .collect::<Vec<_>>()
+    };
+    let json_rows = match (
+        get_orders("left_table").into_iter().min(),
+        get_orders("right_table").into_iter().max(),
+    ) {
+        (Some(min), Some(max)) => json_rows
+            .iter()
+            .filter(|json_row| {
+                json_row
+                    .get_unsigned("_order")
+                    .is_ok_and(|order| min <= order && order <= max)
+            })
+            .cloned()
+            .collect::<Vec<_>>(),
+        _ => vec![],
+    };
+    tracing::info!(
+        "TABLESET {} {json_rows:?}",
+        select.to_url("", &Format::Default)?
+    );
+    println!(
+        "TABLESET {} {json_rows:?}",
+        select.to_url("", &Format::Default)?
+    );
+    if json_rows.len() == 0 {
+        return Err(RelatableError::ConfigError(format!("empty tableset")).into());
+    }
+
+    let limit = select.limit;
+    let mut inner = select.clone();
+    inner.select = vec![];
+    let table_name = inner.table_name.clone();
+    let view_name = inner.view_name.clone();
+
+    let pkey = "_id".to_string();
+    inner.select_table_column(&table_name, &pkey);
+
+    // Find the primary key for this table.
+    // let mut pkey = String::new();
+    // for json_row in json_rows.clone() {
+    //     if table_name == json_row.get_string("left_table").unwrap() {
+    //         pkey = json_row.get_string("left_column").unwrap();
+    //         inner.select_table_column(&table_name, &pkey);
+    //         // println!("FOUND PKEY in {json_row:?}");
+    //     }
+    // }
+    // if table_name == json_rows.last().unwrap().get_string("right_table").unwrap() {
+    // }
+    //     inner.order_by(&pkey);
+    // } else {
+    //     inner.limit = 0;
+    // }
+    inner.order_by = vec![];
+    inner.limit = Limit::Unlimited;
+    inner.view_name = String::new();
+    let json_row = json_rows.first().unwrap();
+    inner.table_name = json_row.get_string("left_table").unwrap();
+    let mut joined = HashSet::new();
+    for json_row in json_rows.iter() {
+        let left_table = json_row.get_string("left_table").unwrap();
+        let right_table = json_row.get_string("right_table").unwrap();
+        if &left_table == "" || &right_table == "" {
+            continue;
+        }
+        if joined.contains(&right_table) {
+            continue;
         }
         joined.insert(right_table.clone());
         inner.left_join(
@@ -1922,605 +6278,2039 @@ pub async fn joined_query(
             &json_row.get_string("right_column").unwrap(),
         );
     }
-    let (sql, params) = inner.to_sql(&rltbl.connection.kind()).unwrap();
-    tracing::warn!("SQL {sql} PARAMS {params:?}");
-    Ok(Select {
-        table_name,
-        view_name,
-        filters: vec![Filter::InSubquery {
-            table: String::new(),
-            column: pkey.clone(),
-            subquery: inner.clone(),
-        }],
-        limit,
-        ..Default::default()
-    })
-}
+    let (sql, params) = inner.to_sql(&rltbl.connection.kind()).unwrap();
+    tracing::warn!("SQL {sql} PARAMS {params:?}");
+    Ok(Select {
+        table_name,
+        view_name,
+        filters: vec![Filter::InSubquery {
+            table: String::new(),
+            column: pkey.clone(),
+            subquery: inner.clone(),
+        }],
+        limit,
+        ..Default::default()
+    })
+}
+
+// Tests
+
+#[cfg(test)]
+mod tests {
+    use crate::core::{tests::memory_demo, ResultSet, MAX_LIMIT};
+    use crate::sql::{is_clause, is_not_clause, CachingStrategy, JsonRow, MEMORY_DB};
+    use async_std::task::block_on;
+    use pretty_assertions::assert_eq;
+    use serde_json::from_value;
+
+    use super::*;
+
+    #[test]
+    fn test_select_from_path_and_query() {
+        let rltbl = block_on(Relatable::build_demo(
+            Some(MEMORY_DB),
+            &true,
+            0,
+            &CachingStrategy::Trigger,
+        ))
+        .unwrap();
+        let sql_param = SqlParam::new(&rltbl.connection.kind()).next();
+        let base = "http://example.com";
+        let empty: Vec<JsonValue> = vec![];
+
+        // A basic URL
+        let url = "http://example.com/penguin";
+        let query_params = from_value(json!({})).unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT *
+FROM "penguin"
+ORDER BY "penguin"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin""#
+        );
+        assert_eq!(params, empty);
+
+        // A URL with a filter on an integer column:
+        let url = "http://example.com/penguin.json?sample_number=eq.5&limit=1&offset=2";
+        let query_params = from_value(json!({
+           "sample_number": "eq.5",
+           "limit": "1",
+           "offset": "2",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Json).unwrap());
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "sample_number" = {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1
+OFFSET 2"#
+            )
+        );
+        assert_eq!(params, vec![json!(5)]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "sample_number" = {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!(5)]);
+
+        // A URL with a filter on a string column and a value with a space
+        let url = "http://example.com/penguin?penguin.study_name=eq.FAKE 123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "eq.FAKE 123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("FAKE 123")]);
+
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("FAKE 123")]);
+
+        // A URL with an IS NULL filter
+        let url = "http://example.com/penguin?penguin.study_name=is.null&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "is.null",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" {is_clause} {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#,
+                is_clause = is_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![JsonValue::Null]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" {is_clause} {sql_param}"#,
+                is_clause = is_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![JsonValue::Null]);
+
+        // A URL with an IS NOT NULL filter
+        let url = "http://example.com/penguin?penguin.study_name=is_not.null&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "is_not.null",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" {is_not_clause} {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#,
+                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![JsonValue::Null]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" {is_not_clause} {sql_param}"#,
+                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![JsonValue::Null]);
+
+        // A URL with an IN filter
+        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
+        let sql_param_1 = sql_param_gen.next();
+        let sql_param_2 = sql_param_gen.next();
+        let url = "http://example.com/penguin?penguin.sample_number=in.(123,345)&limit=1";
+        let query_params = from_value(json!({
+           "penguin.sample_number": "in.(123,345)",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."sample_number" IN ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!(123), json!(345)]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."sample_number" IN ({sql_param_1}, {sql_param_2})"#
+            )
+        );
+        assert_eq!(params, vec![json!(123), json!(345)]);
+
+        // A URL with a NOT IN filter
+        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
+        let sql_param_1 = sql_param_gen.next();
+        let sql_param_2 = sql_param_gen.next();
+        let url = "http://example.com/penguin?penguin.sample_number=not_in.(123,345)&limit=1";
+        let query_params = from_value(json!({
+           "penguin.sample_number": "not_in.(123,345)",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."sample_number" NOT IN ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!(123), json!(345)]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."sample_number" NOT IN ({sql_param_1}, {sql_param_2})"#
+            )
+        );
+        assert_eq!(params, vec![json!(123), json!(345)]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (eq):
+        let url = "http://example.com/penguin?penguin.study_name=eq.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "eq.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like a real (eq):
+        let url = "http://example.com/penguin?penguin.study_name=eq.123.345&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "eq.123.345",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123.345")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" = {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123.345")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (not_eq):
+        let url = "http://example.com/penguin?penguin.study_name=not_eq.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "not_eq.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" <> {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" <> {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (like):
+        let url = "http://example.com/penguin?penguin.study_name=like.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "like.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" LIKE {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" LIKE {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (gt):
+        let url = "http://example.com/penguin?penguin.study_name=gt.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "gt.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" > {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" > {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (gte):
+        let url = "http://example.com/penguin?penguin.study_name=gte.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "gte.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" >= {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" >= {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (lt):
+        let url = "http://example.com/penguin?penguin.study_name=lt.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "lt.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" < {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" < {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (gte):
+        let url = "http://example.com/penguin?penguin.study_name=lte.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "lte.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" <= {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" <= {sql_param}"#
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (is):
+        let url = "http://example.com/penguin?penguin.study_name=is.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "is.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" {is_clause} {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#,
+                is_clause = is_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" {is_clause} {sql_param}"#,
+                is_clause = is_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (is_not):
+        let url = "http://example.com/penguin?penguin.study_name=is_not.123&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "is_not.123",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" {is_not_clause} {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 1"#,
+                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" {is_not_clause} {sql_param}"#,
+                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+            )
+        );
+        assert_eq!(params, vec![json!("123")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (in):
+        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
+        let sql_param_1 = sql_param_gen.next();
+        let sql_param_2 = sql_param_gen.next();
+        let url = "http://example.com/penguin?penguin.study_name=in.(123,345)&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "in.(123,345)",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" IN ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123"), json!("345")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" IN ({sql_param_1}, {sql_param_2})"#
+            )
+        );
+        assert_eq!(params, vec![json!("123"), json!("345")]);
+
+        // A URL with a filter on a string column and a value that looks like an integer (not_in):
+        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
+        let sql_param_1 = sql_param_gen.next();
+        let sql_param_2 = sql_param_gen.next();
+        let url = "http://example.com/penguin?penguin.study_name=not_in.(123,345)&limit=1";
+        let query_params = from_value(json!({
+           "penguin.study_name": "not_in.(123,345)",
+           "limit": "1",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "penguin"."study_name" NOT IN ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC
+LIMIT 1"#
+            )
+        );
+        assert_eq!(params, vec![json!("123"), json!("345")]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "penguin"."study_name" NOT IN ({sql_param_1}, {sql_param_2})"#
+            )
+        );
+        assert_eq!(params, vec![json!("123"), json!("345")]);
+
+        // A URL with a filter on the change ID
+        let url = "http://example.com/penguin?_change_id=gt.5";
+        let query_params = from_value(json!({
+           "_change_id": "gt.5",
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT
+  *,
+  (SELECT MAX(change_id) FROM history WHERE "table" = {sql_param} AND "row" = "penguin"._id) AS _change_id
+FROM "penguin"
+WHERE "_change_id" > {sql_param}
+ORDER BY "penguin"._order ASC
+LIMIT 100"#
+            ),
+        );
+        assert_eq!(params, vec![json!("penguin"), json!(5)]);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "_change_id" > {sql_param}"#
+            ),
+        );
+        assert_eq!(params, vec![json!(5)]);
+
+        // A URL that includes an expression
+        let url = "http://example.com/penguin?select=sample_number,count()";
+        let query_params = from_value(json!({
+            "select": "sample_number,count()"
+        }))
+        .unwrap();
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "sample_number",
+  count()
+FROM "penguin"
+ORDER BY "penguin"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin""#
+        );
+        assert_eq!(params, empty);
+    }
+
+    #[test]
+    fn test_select_methods() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::Trigger,
+        ))
+        .unwrap();
+        let drop_sql = r#"DROP TABLE IF EXISTS "penguin_test""#;
+        let create_sql = r#"CREATE TABLE "penguin_test" (
+    _id INTEGER,
+    _order INTEGER,
+    study_name TEXT,
+    sample_number INTEGER,
+    species TEXT,
+    island TEXT,
+    individual_id TEXT,
+    bill_length REAL,
+    bill_depth NUMERIC,
+    body_mass BIGINT
+)"#;
+        block_on(rltbl.connection.query(drop_sql, None)).unwrap();
+        block_on(rltbl.connection.query(create_sql, None)).unwrap();
+        let empty: Vec<JsonValue> = vec![];
+
+        // select_columns
+        let mut select = Select::from("penguin_test");
+        select.select_table_columns("penguin_test", &vec!["species", "island"]);
+        select.select_columns(&vec!["study_name", "body_mass"]);
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "penguin_test"."species",
+  "penguin_test"."island",
+  "study_name",
+  "body_mass"
+FROM "penguin_test"
+ORDER BY "penguin_test"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin_test""#
+        );
+        assert_eq!(params, empty);
+
+        // select_alias
+        let mut select = Select::from("penguin_test");
+        select.select_alias("penguin_test", "island", "location");
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "penguin_test"."island" AS "location"
+FROM "penguin_test"
+ORDER BY "penguin_test"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
+
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin_test""#
+        );
+        assert_eq!(params, empty);
+
+        // select_expression
+        let mut select = Select::from("penguin_test");
+        select.select_expression("CASE WHEN island = 'Biscoe' THEN 'BISCOE' END", "location");
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  CASE WHEN island = 'Biscoe' THEN 'BISCOE' END AS "location"
+FROM "penguin_test"
+ORDER BY "penguin_test"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
+
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin_test""#
+        );
+        assert_eq!(params, empty);
+
+        // select_all
+        let mut select = Select::from("penguin_test");
+        block_on(select.select_all(&rltbl, "penguin_test")).unwrap();
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "_id",
+  "_order",
+  "study_name",
+  "sample_number",
+  "species",
+  "island",
+  "individual_id",
+  "bill_length",
+  "bill_depth",
+  "body_mass"
+FROM "penguin_test"
+ORDER BY "penguin_test"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
 
-// Tests
+        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM "penguin_test""#
+        );
+        assert_eq!(params, empty);
 
-#[cfg(test)]
-mod tests {
-    use crate::sql::{is_clause, is_not_clause, CachingStrategy};
-    use async_std::task::block_on;
-    use pretty_assertions::assert_eq;
-    use serde_json::from_value;
+        // order_by (with _order appended as a tie-breaker)
+        let mut select = Select::from("penguin_test");
+        select.order_by("species");
 
-    use super::*;
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT *
+FROM "penguin_test"
+ORDER BY "species" ASC, "penguin_test"._order ASC
+LIMIT 100"#
+        );
+        assert_eq!(params, empty);
 
-    #[test]
-    fn test_select_from_path_and_query() {
-        let rltbl = block_on(Relatable::build_demo(
-            Some("build/test_select_from_path_and_query.db"),
-            &true,
-            0,
-            &CachingStrategy::Trigger,
-        ))
-        .unwrap();
-        let sql_param = SqlParam::new(&rltbl.connection.kind()).next();
-        let base = "http://example.com";
-        let empty: Vec<JsonValue> = vec![];
+        let mut select = Select::from("penguin_test");
+        select.order_by = vec![
+            ("species".to_string(), Order::DESC),
+            ("_order".to_string(), Order::DESC),
+        ];
 
-        // A basic URL
-        let url = "http://example.com/penguin";
-        let query_params = from_value(json!({})).unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
         let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             r#"SELECT *
-FROM "penguin"
-ORDER BY "penguin"._order ASC
+FROM "penguin_test"
+ORDER BY "species" DESC, "_order" DESC
 LIMIT 100"#
         );
         assert_eq!(params, empty);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+
+        // Multiple columns, with NULLs placed explicitly:
+        let mut select = Select::from("penguin_test");
+        select.add_order_by("species", &Order::DescNullsLast);
+        select.add_order_by("body_mass", &Order::AscNullsFirst);
+
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin""#
+            r#"SELECT *
+FROM "penguin_test"
+ORDER BY "species" DESC NULLS LAST, "body_mass" ASC NULLS FIRST, "penguin_test"._order ASC
+LIMIT 100"#
         );
         assert_eq!(params, empty);
 
-        // A URL with a filter on an integer column:
-        let url = "http://example.com/penguin.json?sample_number=eq.5&limit=1&offset=2";
-        let query_params = from_value(json!({
-           "sample_number": "eq.5",
-           "limit": "1",
-           "offset": "2",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
+        let insert_sql = r#"INSERT INTO "penguin_test" ("_id", "_order", "species", "body_mass")
+VALUES (1, 1000, 'Adelie', 3000), (2, 2000, NULL, 3500), (3, 3000, 'Gentoo', NULL),
+       (4, 4000, 'Gentoo', 4000), (5, 5000, 'Adelie', 3000)"#;
+        block_on(rltbl.connection.query(insert_sql, None)).unwrap();
+        let rows = block_on(rltbl.connection.query(&sql, None)).unwrap();
+        let ids = rows
+            .iter()
+            .map(|row| row.get_string("_id").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["3", "4", "1", "5", "2"]);
+
+        // The same ordering, given as a URL parameter:
+        let query_params =
+            from_value(json!({"order": "species.desc.nullslast,body_mass.nullsfirst"})).unwrap();
+        let from_url = block_on(Select::from_path_and_query(
+            "penguin_test",
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Json).unwrap());
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(from_url.order_by, select.order_by);
+
+        block_on(rltbl.connection.query(drop_sql, None)).unwrap();
+    }
+
+    #[test]
+    fn test_joins() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        for sql in [
+            r#"CREATE TABLE "join_left" ("_id" INTEGER, "_order" INTEGER, "key" TEXT)"#,
+            r#"INSERT INTO "join_left" VALUES (1, 1000, 'a'), (2, 2000, 'b')"#,
+            r#"CREATE TABLE "join_right" ("_id" INTEGER, "_order" INTEGER, "key" TEXT)"#,
+            r#"INSERT INTO "join_right" VALUES (1, 1000, 'b'), (2, 2000, 'c')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+
+        type AddJoin = for<'a> fn(&'a mut Select, &str, &str, &str, &str) -> &'a Select;
+        let join = |add_join: AddJoin| {
+            let mut select = Select::from("join_left");
+            select.select_alias("join_left", "key", "left_key");
+            select.select_alias("join_right", "key", "right_key");
+            add_join(&mut select, "join_left", "key", "join_right", "key");
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            let mut rows = block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join("|"))
+                .collect::<Vec<_>>();
+            rows.sort();
+            (sql, rows)
+        };
+
+        let (sql, rows) = join(Select::inner_join);
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "sample_number" = {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1
-OFFSET 2"#
+            r#"SELECT
+  "join_left"."key" AS "left_key",
+  "join_right"."key" AS "right_key"
+FROM "join_left"
+INNER JOIN "join_right" ON "join_left"."key" = "join_right"."key"
+ORDER BY "join_left"._order ASC, "join_right"._id ASC
+LIMIT 100"#
+        );
+        assert_eq!(rows, ["b|b"]);
+        assert_eq!(join(Select::left_join).1, ["a|", "b|b"]);
+        assert_eq!(join(Select::right_join).1, ["b|b", "|c"]);
+        assert_eq!(join(Select::full_join).1, ["a|", "b|b", "|c"]);
+
+        // Joins on several pairs of columns, and on constant conditions:
+        for sql in [
+            r#"CREATE TABLE "visit" ("_id" INTEGER, "_order" INTEGER, "site" TEXT, "day" INTEGER)"#,
+            r#"INSERT INTO "visit" VALUES (1, 1000, 'x', 1), (2, 2000, 'x', 2), (3, 3000, 'y', 1)"#,
+            r#"CREATE TABLE "sample" ("_id" INTEGER, "_order" INTEGER, "site" TEXT, "day" INTEGER,
+                                      "kind" TEXT)"#,
+            r#"INSERT INTO "sample" VALUES (1, 1000, 'x', 1, 'active'), (2, 2000, 'x', 2, 'stale'),
+                                           (3, 3000, 'y', 2, 'active')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let mut select = Select::from("visit");
+        select.select_table_columns("visit", &vec!["site", "day"]);
+        select.select_alias("sample", "kind", "kind");
+        select
+            .left_join_on(
+                "visit",
+                "sample",
+                &[("site", "site"), ("day", "day")],
+                &vec!["kind = active".to_string()],
             )
+            .unwrap();
+        let kind = rltbl.connection.kind();
+        let (sql, params) = select.to_sql(&kind).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "visit"."site",
+  "visit"."day",
+  "sample"."kind" AS "kind"
+FROM "visit"
+LEFT JOIN "sample" ON "visit"."site" = "sample"."site" AND "visit"."day" = "sample"."day" AND "sample"."kind" = ?
+ORDER BY "visit"._order ASC, "sample"._id ASC
+LIMIT 100"#
         );
-        assert_eq!(params, vec![json!(5)]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!("active")]);
+        assert_eq!(select.to_sql_params(&kind).unwrap(), params);
+        let rows = block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join("|"))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, ["x|1|active", "x|2|", "y|1|"]);
+        assert!(select
+            .clone()
+            .inner_join_on("visit", "sample", &[], &vec![])
+            .is_err());
+
+        // Rows sorted by a column with duplicate values, each joined to several rows, are still
+        // returned in a deterministic order:
+        let mut select = Select::from("visit");
+        select.select_alias("visit", "site", "visit_site");
+        select.select_table_column("visit", "day");
+        select.select_alias("sample", "kind", "kind");
+        select.left_join("visit", "site", "sample", "site");
+        select.order_by("visit_site");
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "sample_number" = {sql_param}"#
+            r#"SELECT
+  "visit"."site" AS "visit_site",
+  "visit"."day",
+  "sample"."kind" AS "kind"
+FROM "visit"
+LEFT JOIN "sample" ON "visit"."site" = "sample"."site"
+ORDER BY "visit_site" ASC, "visit"._order ASC, "sample"._id ASC
+LIMIT 100"#
+        );
+        let rows = block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join("|"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            [
+                "x|1|active",
+                "x|1|stale",
+                "x|2|active",
+                "x|2|stale",
+                "y|1|active"
+            ]
+        );
+
+        // Joins of a table to itself, using an alias:
+        for sql in [
+            r#"CREATE TABLE "bird" ("_id" INTEGER, "_order" INTEGER, "name" TEXT, "mate" TEXT,
+                                    "species" TEXT)"#,
+            r#"INSERT INTO "bird" VALUES (1, 1000, 'N1', 'N2', 'Adelie'),
+                                         (2, 2000, 'N2', 'N1', 'Gentoo'),
+                                         (3, 3000, 'N3', NULL, 'Adelie')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let mut select = Select::from("bird");
+        select.select_table_column("bird", "name");
+        select.select_alias("mate", "name", "mate_name");
+        select
+            .left_join_on(
+                "bird",
+                "bird",
+                &[("mate", "name")],
+                &vec!["species = Gentoo".to_string()],
             )
+            .unwrap();
+        select.alias_join("mate").unwrap();
+        let select = select
+            .filters(&vec!["bird.species = Adelie".to_string()])
+            .unwrap();
+        let (sql, params) = select.to_sql(&kind).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT
+  "bird"."name",
+  "mate"."name" AS "mate_name"
+FROM "bird"
+LEFT JOIN "bird" AS "mate" ON "bird"."mate" = "mate"."name" AND "mate"."species" = ?
+WHERE "bird"."species" = ?
+ORDER BY "bird"._order ASC, "mate"._id ASC
+LIMIT 100"#
         );
-        assert_eq!(params, vec![json!(5)]);
+        assert_eq!(params, vec![json!("Gentoo"), json!("Adelie")]);
+        let rows = block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join("|"))
+            .collect::<Vec<_>>();
+        assert_eq!(rows, ["N1|N2", "N3|"]);
+        assert_eq!(
+            select.get_tables().into_iter().collect::<Vec<_>>(),
+            ["bird"]
+        );
+        assert!(Select::from("bird").alias_join("mate").is_err());
+    }
 
-        // A URL with a filter on a string column and a value with a space
-        let url = "http://example.com/penguin?penguin.study_name=eq.FAKE 123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "eq.FAKE 123",
-           "limit": "1",
-        }))
+    #[test]
+    fn test_unions() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        for sql in [
+            r#"CREATE TABLE "bird" ("_id" INTEGER, "_order" INTEGER, "name" TEXT, "size" INTEGER)"#,
+            r#"INSERT INTO "bird" VALUES (1, 1000, 'wren', 1), (2, 2000, 'crow', 3),
+                                         (3, 3000, 'swan', 8)"#,
+            r#"CREATE TABLE "fish" ("_id" INTEGER, "_order" INTEGER, "name" TEXT, "size" INTEGER)"#,
+            r#"INSERT INTO "fish" VALUES (1, 1000, 'crow', 2), (2, 2000, 'pike', 6)"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let mut birds = Select::from("bird").gt("size", &2).unwrap();
+        birds.select_column("name");
+        let mut fish = Select::from("fish").lt("size", &5).unwrap();
+        fish.select_column("name");
+
+        let kind = rltbl.connection.kind();
+        let query = |(sql, params): (String, Vec<JsonValue>)| {
+            block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join("|"))
+                .collect::<Vec<_>>()
+        };
+        let union = birds.union(&fish).unwrap().order_by("name");
+        assert_eq!(query(union.to_sql(&kind).unwrap()), ["crow", "swan"]);
+        let union_all = birds.union_all(&fish).unwrap().order_by("name");
+        let (sql, params) = union_all.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+            r#"SELECT
+  "name"
+FROM "bird"
+WHERE "size" > ?
+UNION ALL
+SELECT
+  "name"
+FROM "fish"
+WHERE "size" < ?
+ORDER BY "name" ASC
+LIMIT 100"#
         );
-        assert_eq!(params, vec![json!("FAKE 123")]);
+        assert_eq!(params, [json!(2), json!(5)]);
+        assert_eq!(query((sql, params)), ["crow", "crow", "swan"]);
+        assert_eq!(
+            query(
+                union_all
+                    .clone()
+                    .limit(&1)
+                    .offset(&1)
+                    .to_sql(&kind)
+                    .unwrap()
+            ),
+            ["crow"]
+        );
+        assert_eq!(query(union.to_sql_count(&kind).unwrap()), ["2"]);
+        assert_eq!(query(union_all.to_sql_count(&kind).unwrap()), ["3"]);
+
+        // The parameters of the selects are numbered in order in PostgreSQL:
+        let (sql, _) = union_all.to_sql(&DbKind::Postgres).unwrap();
+        assert!(sql.contains(r#""size" > $1"#) && sql.contains(r#""size" < $2"#));
+
+        // Only selects that list the same number of columns can be combined:
+        let mut sizes = Select::from("fish");
+        sizes.select_columns(&vec!["name", "size"]);
+        assert!(birds.union(&sizes).is_err());
+        assert!(birds.union(&Select::from("fish")).is_err());
+        assert!(Select::from("bird").union_all(&fish).is_err());
+    }
 
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_windows() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        for sql in [
+            r#"CREATE TABLE "bird" ("_id" INTEGER, "_order" INTEGER, "name" TEXT, "kind" TEXT,
+                                    "size" INTEGER)"#,
+            r#"INSERT INTO "bird" VALUES (1, 1000, 'wren', 'small', 1), (2, 2000, 'tit', 'small', 1),
+                                         (3, 3000, 'robin', 'small', 2), (4, 4000, 'swan', 'big', 8),
+                                         (5, 5000, 'goose', 'big', NULL)"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let mut select = Select::from("bird");
+        select.select_column("name");
+        select.select_window(
+            &WindowFunction::Rank,
+            &vec!["kind"],
+            &vec![("size", Order::DESC)],
+            "",
+        );
+        select.select_window(
+            &WindowFunction::RowNumber,
+            &vec![],
+            &vec![("bird.size", Order::ASC), ("name", Order::ASC)],
+            "position",
+        );
+        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}"#
-            )
+            r#"SELECT
+  "name",
+  RANK() OVER (PARTITION BY "kind" ORDER BY "size" DESC) AS "rank",
+  ROW_NUMBER() OVER (ORDER BY "bird"."size" ASC, "name" ASC) AS "position"
+FROM "bird"
+ORDER BY "bird"._order ASC
+LIMIT 100"#
+        );
+        let rows = block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings().join("|"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            ["wren|2|3", "tit|2|2", "robin|1|4", "swan|1|5", "goose|2|1"]
         );
-        assert_eq!(params, vec![json!("FAKE 123")]);
 
-        // A URL with an IS NULL filter
-        let url = "http://example.com/penguin?penguin.study_name=is.null&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "is.null",
-           "limit": "1",
-        }))
+        // PostgreSQL is told to order NULL values as SQLite does:
+        let (sql, _) = select.to_sql(&DbKind::Postgres).unwrap();
+        assert!(sql.contains(r#"ORDER BY "size" DESC NULLS LAST"#));
+        assert!(sql.contains(r#"ORDER BY "bird"."size" ASC NULLS FIRST, "name" ASC NULLS FIRST"#));
+
+        let mut select = Select::from("bird");
+        select.select_window(&WindowFunction::Ntile(2), &vec![], &vec![], "half");
+        let (sql, _) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert!(sql.contains(r#"NTILE(2) OVER () AS "half""#));
+    }
+
+    #[test]
+    fn test_ctes() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        for sql in [
+            r#"CREATE TABLE "bird" ("_id" INTEGER, "_order" INTEGER, "name" TEXT, "size" INTEGER,
+                                    "parent" TEXT)"#,
+            r#"INSERT INTO "bird" VALUES (1, 1000, 'wren', 1, NULL), (2, 2000, 'crow', 3, 'wren'),
+                                         (3, 3000, 'swan', 8, 'crow'), (4, 4000, 'duck', 4, NULL)"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let kind = rltbl.connection.kind();
+        let query = |(sql, params): (String, Vec<JsonValue>)| {
+            block_on(rltbl.connection.query(&sql, Some(&json!(params))))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings().join("|"))
+                .collect::<Vec<_>>()
+        };
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // A CTE can be selected from and joined like a table:
+        let big = Select::from("bird").unlimited().gt("size", &2).unwrap();
+        let mut select = Select::from("big").unlimited().with("big", &big);
+        select.select_alias("big", "name", "child");
+        select.inner_join("big", "parent", "bird", "name");
+        select.select_alias("bird", "name", "parent");
+        select.order_by("child");
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" {is_clause} {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#,
-                is_clause = is_clause(&rltbl.connection.kind()),
-            )
+            r#"WITH "big" AS (
+  SELECT *
+  FROM "bird"
+  WHERE "size" > ?
+  ORDER BY "bird"._order ASC
+)
+SELECT
+  "big"."name" AS "child",
+  "bird"."name" AS "parent"
+FROM "big"
+INNER JOIN "bird" ON "big"."parent" = "bird"."name"
+ORDER BY "child" ASC, "big"._order ASC, "bird"._id ASC"#
         );
-        assert_eq!(params, vec![JsonValue::Null]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, [json!(2)]);
+        assert_eq!(query((sql, params)), ["crow|wren", "swan|crow"]);
+        assert_eq!(query(select.to_sql_count(&kind).unwrap()), ["2"]);
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" {is_clause} {sql_param}"#,
-                is_clause = is_clause(&rltbl.connection.kind()),
-            )
+            select.get_tables().into_iter().collect::<Vec<_>>(),
+            ["bird"]
         );
-        assert_eq!(params, vec![JsonValue::Null]);
 
-        // A URL with an IS NOT NULL filter
-        let url = "http://example.com/penguin?penguin.study_name=is_not.null&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "is_not.null",
-           "limit": "1",
-        }))
+        // A recursive CTE can walk the ancestors of a bird:
+        let columns = vec!["_order", "name", "parent"];
+        let mut base = Select::from("bird").unlimited();
+        base.select_columns(&columns);
+        base.eq("name", &"swan").unwrap();
+        let mut step = Select::from("ancestors").unlimited();
+        step.inner_join("ancestors", "parent", "bird", "name");
+        step.select_table_columns("bird", &columns);
+        let ancestors = Select::from("ancestors").unlimited().with_recursive(
+            "ancestors",
+            &columns,
+            &base.union(&step).unwrap(),
+        );
+        assert_eq!(
+            query(ancestors.to_sql(&kind).unwrap()),
+            ["1000|wren|", "2000|crow|wren", "3000|swan|crow"]
+        );
+        assert_eq!(
+            ancestors.to_sql_params(&kind).unwrap(),
+            ancestors.to_sql(&kind).unwrap().1
+        );
+    }
+
+    #[test]
+    fn test_subquery() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::Trigger,
+        ))
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let sql_param = SqlParam::new(&rltbl.connection.kind()).next();
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // Subquery select, filtered on a string:
+        let mut inner_select = Select::from("penguin").unlimited();
+        inner_select.select_table_column("penguin", "individual_id");
+        inner_select.left_join("penguin", "individual_id", "egg", "individual_id");
+        inner_select
+            .table_eq("penguin", "individual_id", &"N1")
+            .unwrap();
+        let mut outer_select = Select::from("penguin").unlimited();
+        outer_select.is_in_subquery("individual_id", &inner_select);
+
+        let tables = outer_select.get_tables().into_iter().collect::<Vec<_>>();
+        assert_eq!(tables, vec!["egg", "penguin"]);
+
+        let (sql, params) = outer_select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."study_name" {is_not_clause} {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#,
-                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+WHERE "penguin"."individual_id" IN (
+  SELECT
+    "penguin"."individual_id"
+  FROM "penguin"
+  LEFT JOIN "egg" ON "penguin"."individual_id" = "egg"."individual_id"
+  WHERE "penguin"."individual_id" = {sql_param}
+  ORDER BY "penguin"._order ASC, "egg"._id ASC
+)
+ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![JsonValue::Null]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!("N1")]);
+
+        let (sql, params) = outer_select.to_sql_count(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT COUNT(1) AS "count"
 FROM "penguin"
-WHERE "penguin"."study_name" {is_not_clause} {sql_param}"#,
-                is_not_clause = is_not_clause(&rltbl.connection.kind()),
+WHERE "penguin"."individual_id" IN (
+  SELECT
+    "penguin"."individual_id"
+  FROM "penguin"
+  LEFT JOIN "egg" ON "penguin"."individual_id" = "egg"."individual_id"
+  WHERE "penguin"."individual_id" = {sql_param}
+)"#
             )
         );
-        assert_eq!(params, vec![JsonValue::Null]);
+        assert_eq!(params, vec![json!("N1")]);
 
-        // A URL with an IN filter
-        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
-        let sql_param_1 = sql_param_gen.next();
-        let sql_param_2 = sql_param_gen.next();
-        let url = "http://example.com/penguin?penguin.sample_number=in.(123,345)&limit=1";
-        let query_params = from_value(json!({
-           "penguin.sample_number": "in.(123,345)",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        // Subquery select, filtered on an integer:
+        let mut inner_select = Select::from("penguin").unlimited();
+        inner_select.select_table_column("penguin", "sample_number");
+        inner_select.left_join("penguin", "sample_number", "egg", "sample_number");
+        inner_select
+            .table_eq("penguin", "sample_number", &27)
+            .unwrap();
+        let mut outer_select = Select::from("penguin").unlimited();
+        outer_select.is_in_subquery("sample_number", &inner_select);
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        let tables = outer_select.get_tables().into_iter().collect::<Vec<_>>();
+        assert_eq!(tables, vec!["egg", "penguin"]);
+
+        let (sql, params) = outer_select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."sample_number" IN ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
+WHERE "penguin"."sample_number" IN (
+  SELECT
+    "penguin"."sample_number"
+  FROM "penguin"
+  LEFT JOIN "egg" ON "penguin"."sample_number" = "egg"."sample_number"
+  WHERE "penguin"."sample_number" = {sql_param}
+  ORDER BY "penguin"._order ASC, "egg"._id ASC
+)
+ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![json!(123), json!(345)]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!(27)]);
+
+        let (sql, params) = outer_select.to_sql_count(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT COUNT(1) AS "count"
 FROM "penguin"
-WHERE "penguin"."sample_number" IN ({sql_param_1}, {sql_param_2})"#
+WHERE "penguin"."sample_number" IN (
+  SELECT
+    "penguin"."sample_number"
+  FROM "penguin"
+  LEFT JOIN "egg" ON "penguin"."sample_number" = "egg"."sample_number"
+  WHERE "penguin"."sample_number" = {sql_param}
+)"#
             )
         );
-        assert_eq!(params, vec![json!(123), json!(345)]);
+        assert_eq!(params, vec![json!(27)]);
+    }
 
-        // A URL with a NOT IN filter
-        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
-        let sql_param_1 = sql_param_gen.next();
-        let sql_param_2 = sql_param_gen.next();
-        let url = "http://example.com/penguin?penguin.sample_number=not_in.(123,345)&limit=1";
-        let query_params = from_value(json!({
-           "penguin.sample_number": "not_in.(123,345)",
-           "limit": "1",
-        }))
+    #[test]
+    fn test_filters() {
+        let rltbl = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::Trigger,
+        ))
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        let mut sql_param_generator = SqlParam::new(&rltbl.connection.kind());
+        let sql_param_1 = sql_param_generator.next();
+        let sql_param_2 = sql_param_generator.next();
+        let is_for_kind = is_clause(&rltbl.connection.kind());
+        let is_not_for_kind = is_not_clause(&rltbl.connection.kind());
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
+        // Test simple string filters
+        for (input_symbol, output_symbol) in [
+            ("~=", "LIKE"),
+            ("!~=", "NOT LIKE"),
+            ("=", "="),
+            ("!=", "<>"),
+            (">", ">"),
+            (">=", ">="),
+            ("<", "<"),
+            ("<=", "<="),
+            ("is", &is_for_kind),
+            ("is not", &is_not_for_kind),
+        ] {
+            let select = Select::from("penguin")
+                .unlimited()
+                .filters(&vec![format!("study_name {input_symbol} FAKE123")])
+                .unwrap();
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."sample_number" NOT IN ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
-        );
-        assert_eq!(params, vec![json!(123), json!(345)]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
+WHERE "study_name" {output_symbol} {sql_param_1}
+ORDER BY "penguin"._order ASC"#
+                )
+            );
+            assert_eq!(params, vec![json!("FAKE123")]);
+
+            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "study_name" {output_symbol} {sql_param_1}"#
+                )
+            );
+            assert_eq!(params, vec![json!("FAKE123")]);
+        }
+
+        // Test simple integer filters
+        for (input_symbol, output_symbol) in [
+            ("=", "="),
+            ("!=", "<>"),
+            (">", ">"),
+            (">=", ">="),
+            ("<", "<"),
+            ("<=", "<="),
+            ("is", &is_for_kind),
+            ("is not", &is_not_for_kind),
+        ] {
+            let select = Select::from("penguin")
+                .unlimited()
+                .filters(&vec![format!("sample_number {input_symbol} 2")])
+                .unwrap();
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT *
+FROM "penguin"
+WHERE "sample_number" {output_symbol} {sql_param_1}
+ORDER BY "penguin"._order ASC"#
+                )
+            );
+            assert_eq!(params, vec![json!(2)]);
+
+            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "sample_number" {output_symbol} {sql_param_1}"#
+                )
+            );
+            assert_eq!(params, vec![json!(2)]);
+        }
+
+        // Test list string filters
+        for (input_symbol, output_symbol) in [("in", "IN"), ("not in", "NOT IN")] {
+            let select = Select::from("penguin")
+                .unlimited()
+                .filters(&vec![format!(
+                    "study_name {input_symbol} (MIKE123, RICK123)"
+                )])
+                .unwrap();
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT *
+FROM "penguin"
+WHERE "study_name" {output_symbol} ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC"#
+                )
+            );
+            assert_eq!(params, vec![json!("MIKE123"), json!("RICK123")]);
+
+            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "study_name" {output_symbol} ({sql_param_1}, {sql_param_2})"#
+                )
+            );
+            assert_eq!(params, vec![json!("MIKE123"), json!("RICK123")]);
+        }
+
+        // Test list integer filters
+        for (input_symbol, output_symbol) in [("in", "IN"), ("not in", "NOT IN")] {
+            let select = Select::from("penguin")
+                .unlimited()
+                .filters(&vec![format!("sample_number {input_symbol} (1, 2)")])
+                .unwrap();
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."sample_number" NOT IN ({sql_param_1}, {sql_param_2})"#
-            )
-        );
-        assert_eq!(params, vec![json!(123), json!(345)]);
+WHERE "sample_number" {output_symbol} ({sql_param_1}, {sql_param_2})
+ORDER BY "penguin"._order ASC"#
+                )
+            );
+            assert_eq!(params, vec![json!(1), json!(2)]);
 
-        // A URL with a filter on a string column and a value that looks like an integer (eq):
-        let url = "http://example.com/penguin?penguin.study_name=eq.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "eq.123",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+            assert_eq!(
+                sql,
+                format!(
+                    r#"SELECT COUNT(1) AS "count"
+FROM "penguin"
+WHERE "sample_number" {output_symbol} ({sql_param_1}, {sql_param_2})"#
+                )
+            );
+            assert_eq!(params, vec![json!(1), json!(2)]);
+        }
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
+        // Test between filters, given as strings, by the builder method, and in URLs
+        let expected_sql = format!(
+            r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+WHERE "sample_number" BETWEEN {sql_param_1} AND {sql_param_2}
+ORDER BY "penguin"._order ASC"#
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        for select in [
+            Select::from("penguin")
+                .unlimited()
+                .filters(&vec!["sample_number between 1 and 10".to_string()])
+                .unwrap(),
+            Select::from("penguin")
+                .unlimited()
+                .between("sample_number", &1, &10)
+                .unwrap(),
+        ] {
+            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+            assert_eq!(sql, expected_sql);
+            assert_eq!(params, vec![json!(1), json!(10)]);
+        }
+        let filter = Filter::Between {
+            table: "".to_string(),
+            column: "sample_number".to_string(),
+            low: json!(1),
+            high: json!(10),
+        };
+        assert_eq!(filter.to_url().unwrap(), "between.(1,10)");
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}"#
+            url_filter(
+                "".to_string(),
+                "sample_number".to_string(),
+                "between.(1,10)",
+                &Some("integer".to_string())
             )
+            .map(|filter| filter.parts()),
+            Some(filter.parts())
         );
-        assert_eq!(params, vec![json!("123")]);
-
-        // A URL with a filter on a string column and a value that looks like a real (eq):
-        let url = "http://example.com/penguin?penguin.study_name=eq.123.345&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "eq.123.345",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert!(url_filter(
+            "".to_string(),
+            "sample_number".to_string(),
+            "between.(1,5,10)",
+            &Some("integer".to_string())
+        )
+        .is_none());
 
+        // Test case-insensitive like filters, by the builder method and in URLs
+        let select = Select::from("penguin")
+            .unlimited()
+            .ilike("island", &"dream*")
+            .unwrap();
         let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        let condition = match rltbl.connection.kind() {
+            DbKind::Postgres => format!(r#""island" ILIKE {sql_param_1}"#),
+            DbKind::Sqlite => format!(r#"LOWER("island") LIKE LOWER({sql_param_1})"#),
+        };
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
+WHERE {condition}
+ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![json!("123.345")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!("dream%")]);
+        let filter = &select.filters[0];
+        assert_eq!(filter.to_url().unwrap(), "ilike.dream*");
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" = {sql_param}"#
+            url_filter(
+                "".to_string(),
+                "island".to_string(),
+                "ilike.dream*",
+                &Some("text".to_string())
             )
+            .map(|filter| filter.parts()),
+            Some(filter.parts())
         );
-        assert_eq!(params, vec![json!("123.345")]);
-
-        // A URL with a filter on a string column and a value that looks like an integer (not_eq):
-        let url = "http://example.com/penguin?penguin.study_name=not_eq.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "not_eq.123",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
 
+        // Test regular expression filters, by the builder method and in URLs
+        let select = Select::from("penguin")
+            .unlimited()
+            .matches("individual_id", r"^N\d+A1$")
+            .unwrap();
         let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        let operator = match rltbl.connection.kind() {
+            DbKind::Postgres => "~",
+            DbKind::Sqlite => "REGEXP",
+        };
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."study_name" <> {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
+WHERE "individual_id" {operator} {sql_param_1}
+ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!(r"^N\d+A1$")]);
+        let filter = &select.filters[0];
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" <> {sql_param}"#
+            url_filter(
+                "".to_string(),
+                "individual_id".to_string(),
+                r"matches.^N\d+A1$",
+                &Some("text".to_string())
             )
+            .map(|filter| filter.parts()),
+            Some(filter.parts())
         );
-        assert_eq!(params, vec![json!("123")]);
-
-        // A URL with a filter on a string column and a value that looks like an integer (like):
-        let url = "http://example.com/penguin?penguin.study_name=like.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "like.123",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-
+        assert!(Select::from("penguin")
+            .matches("individual_id", "N(")
+            .is_err());
+
+        // Test not-like filters, by the builder method and in URLs
+        let select = Select::from("penguin")
+            .unlimited()
+            .not_like("island", &"Tor*")
+            .unwrap();
         let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."study_name" LIKE {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
+WHERE "island" NOT LIKE {sql_param_1}
+ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!("Tor%")]);
+        let filter = &select.filters[0];
+        assert_eq!(filter.to_url().unwrap(), "not_like.Tor*");
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" LIKE {sql_param}"#
+            url_filter(
+                "".to_string(),
+                "island".to_string(),
+                "not_like.Tor*",
+                &Some("text".to_string())
             )
+            .map(|filter| filter.parts()),
+            Some(filter.parts())
         );
-        assert_eq!(params, vec![json!("123")]);
-
-        // A URL with a filter on a string column and a value that looks like an integer (gt):
-        let url = "http://example.com/penguin?penguin.study_name=gt.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "gt.123",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // The filter language can be parsed on its own, with quoted values and escapes:
+        let parts = |text: &str| FilterExpr::parse(text).unwrap().parts();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" > {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
+            parts(r#"penguin.species = "Adelie \"Penguin\"""#),
+            (
+                "penguin".to_string(),
+                "species".to_string(),
+                "eq".to_string(),
+                json!(r#"Adelie "Penguin""#)
             )
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(parts("body_mass>=3000.5").3, json!(3000.5));
+        assert_eq!(parts(r#"sample_number = "5""#).3, json!("5"));
+        assert_eq!(parts("island IS NOT null").2, "is_not");
+        assert_eq!(parts("island is not null").3, JsonValue::Null);
+        assert_eq!(parts(r#"island is "null""#).3, json!("null"));
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" > {sql_param}"#
-            )
+            parts(r#"island not in (Dream, "Torgersen Island",3)"#).3,
+            json!(["Dream", "Torgersen Island", 3])
         );
-        assert_eq!(params, vec![json!("123")]);
-
-        // A URL with a filter on a string column and a value that looks like an integer (gte):
-        let url = "http://example.com/penguin?penguin.study_name=gte.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "gte.123",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert_eq!(parts("sample_number BETWEEN 1 AND 10").3, json!([1, 10]));
+        assert_eq!(parts("date_egg after today-7d").2, "after");
+
+        // Errors give the position at which the filter is invalid:
+        for (text, error) in [
+            ("", "at position 0: expected a column"),
+            ("species", "at position 7: expected an operator"),
+            ("species = (Adelie)", "at position 10: expected a value"),
+            (
+                "species = Adelie Gentoo",
+                "at position 17: expected the end of the filter",
+            ),
+            (r#"species = "Adelie"#, "at position 17: expected '"),
+            (
+                "sample_number in (1, 2",
+                "at position 22: expected ',' or ')'",
+            ),
+            (
+                "sample_number between 1 10",
+                "at position 23: expected 'and'",
+            ),
+            (
+                "date_egg before yesterday",
+                "at position 16: expected a date",
+            ),
+        ] {
+            let message = FilterExpr::parse(text).unwrap_err().to_string();
+            assert!(message.contains(error), "{message}");
+        }
+    }
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" >= {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+    #[test]
+    fn test_regex_filter() {
+        let rltbl = memory_demo(20);
+        let individual_ids = |select: Select| {
+            block_on(rltbl.fetch(&select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.cells.get("individual_id").unwrap().text.clone())
+                .collect::<Vec<_>>()
+        };
+        let all = individual_ids(Select::from("penguin"));
+        let matching = individual_ids(
+            Select::from("penguin")
+                .matches("individual_id", r"^N\d+A1$")
+                .unwrap(),
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert!(!matching.is_empty());
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" >= {sql_param}"#
-            )
+            matching,
+            all.into_iter()
+                .filter(|id| id.starts_with("N") && id.ends_with("A1"))
+                .collect::<Vec<_>>()
         );
-        assert_eq!(params, vec![json!("123")]);
 
-        // A URL with a filter on a string column and a value that looks like an integer (lt):
-        let url = "http://example.com/penguin?penguin.study_name=lt.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "lt.123",
-           "limit": "1",
-        }))
+        // Values of other types are matched as text:
+        let sample_numbers = block_on(
+            rltbl.fetch(
+                &Select::from("penguin")
+                    .matches("sample_number", "^1[0-9]$")
+                    .unwrap(),
+            ),
+        )
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert_eq!(sample_numbers.rows.len(), 10);
+    }
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" < {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
-        );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_aggregates() {
+        let rltbl = memory_demo(20);
+        let kind = rltbl.connection.kind();
+
+        // Aggregates are grouped by the columns that are not aggregated:
+        let mut select = Select::from("penguin");
+        select.select_column("island");
+        select.sum("body_mass", "total_mass");
+        select.max("bill_length", "");
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
+            r#"SELECT
+  "island",
+  SUM("body_mass") AS "total_mass",
+  MAX("bill_length") AS "max"
 FROM "penguin"
-WHERE "penguin"."study_name" < {sql_param}"#
-            )
+GROUP BY "island"
+LIMIT 100"#
+        );
+        assert_eq!(params, Vec::<JsonValue>::new());
+        let (sql, _) = select.to_sql_count(&kind).unwrap();
+        assert_eq!(
+            sql,
+            r#"SELECT COUNT(1) AS "count"
+FROM (
+  SELECT "island"
+  FROM "penguin"
+  GROUP BY "island"
+) AS "groups""#
         );
-        assert_eq!(params, vec![json!("123")]);
 
-        // A URL with a filter on a string column and a value that looks like an integer (gte):
-        let url = "http://example.com/penguin?penguin.study_name=lte.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "lte.123",
-           "limit": "1",
+        // Aggregates are written in URLs as they are in PostgREST:
+        let url = "/penguin?select=island,total_mass:body_mass.sum(),bill_length.max()";
+        assert_eq!(select.to_url("", &Format::Default).unwrap(), url);
+        let query_params = from_value::<QueryParams>(json!({
+            "select": "island,total_mass:body_mass.sum(),bill_length.max()",
         }))
         .unwrap();
-        let select = block_on(Select::from_path_and_query(
+        let parsed = block_on(Select::from_path_and_query(
             "penguin",
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(parsed.select, select.select);
+        assert!(SelectField::aggregate_from_url("body_mass.median()").is_err());
+        assert!(SelectField::aggregate_from_url(".sum()").is_err());
+
+        // The aggregates are computed by the database:
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        let sql = r#"SELECT COUNT(DISTINCT "island") FROM "penguin""#;
+        let islands = block_on(rltbl.connection.query_value(sql, None))
+            .unwrap()
+            .unwrap();
+        assert_eq!(json!(result.rows.len()), islands);
+        let total = result
+            .rows
+            .iter()
+            .map(|row| row.cells.get("total_mass").unwrap().value.as_i64().unwrap())
+            .sum::<i64>();
+        let sql = r#"SELECT SUM("body_mass") FROM "penguin""#;
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" <= {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!(total))
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+
+        let mut select = Select::from("penguin");
+        select.avg("sample_number", "");
+        select.min("sample_number", "first");
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].cells.get("avg").unwrap().value, json!(10.5));
+        assert_eq!(result.rows[0].cells.get("first").unwrap().value, json!(1));
+    }
+
+    #[test]
+    fn test_select_exprs() {
+        let rltbl = memory_demo(5);
+        let kind = rltbl.connection.kind();
+
+        // Expressions are parsed, and written in SQL and in URLs with as few parentheses as
+        // their operators require:
+        let expr = SelectExpr::from_url("round(body_mass/1000,1)").unwrap();
+        assert_eq!(expr.to_sql(), r#"ROUND("body_mass" / 1000, 1)"#);
+        assert_eq!(SelectExpr::from_url(&expr.to_sql()).unwrap(), expr);
+        assert_eq!(SelectExpr::from_url("--1").unwrap().to_sql(), "-(-1)");
+        assert_eq!(SelectExpr::from_url("1--1").unwrap().to_url(), "1-(-1)");
+        let expr = SelectExpr::from_url("(1-penguin.sample_number)*-2||'it''s'").unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" <= {sql_param}"#
-            )
+            expr.to_sql(),
+            r#"(1 - "penguin"."sample_number") * -2 || 'it''s'"#
         );
-        assert_eq!(params, vec![json!("123")]);
+        assert_eq!(expr.to_url(), "(1-penguin.sample_number)*-2||'it''s'");
+        assert_eq!(SelectExpr::from_url("1-(2-3)").unwrap().to_url(), "1-(2-3)");
+
+        // Only column references, literals, operators, and whitelisted functions are accepted:
+        for expression in [
+            "sqlite_version()",
+            "body_mass; DROP TABLE penguin",
+            "upper(species",
+            "species)",
+            "'unterminated",
+            "body_mass /* comment */",
+            "a.",
+            "upper(species) AS x",
+        ] {
+            assert!(SelectExpr::from_url(expression).is_err(), "{expression}");
+        }
+        let nested = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert!(SelectExpr::from_url(&nested).is_err());
 
-        // A URL with a filter on a string column and a value that looks like an integer (is):
-        let url = "http://example.com/penguin?penguin.study_name=is.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "is.123",
-           "limit": "1",
+        // Expressions are written in URLs with an alias, or that of the function called:
+        let query_params = from_value::<QueryParams>(json!({
+            "select": "study_name,kg:round(body_mass/1000,1),upper(replace(species,' ','_'))",
         }))
         .unwrap();
         let select = block_on(Select::from_path_and_query(
@@ -2528,38 +8318,23 @@ WHERE "penguin"."study_name" <= {sql_param}"#
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        let (sql, _) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            format!(
-                r#"SELECT *
+            r#"SELECT
+  "study_name",
+  ROUND("body_mass" / 1000, 1) AS "kg",
+  UPPER(REPLACE("species", ' ', '_')) AS "upper"
 FROM "penguin"
-WHERE "penguin"."study_name" {is_clause} {sql_param}
 ORDER BY "penguin"._order ASC
-LIMIT 1"#,
-                is_clause = is_clause(&rltbl.connection.kind()),
-            )
+LIMIT 100"#
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" {is_clause} {sql_param}"#,
-                is_clause = is_clause(&rltbl.connection.kind()),
-            )
+            select.to_params().unwrap().get("select").unwrap(),
+            "study_name,kg:round(body_mass/1000,1),upper:upper(replace(species,' ','_'))"
         );
-        assert_eq!(params, vec![json!("123")]);
-
-        // A URL with a filter on a string column and a value that looks like an integer (is_not):
-        let url = "http://example.com/penguin?penguin.study_name=is_not.123&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "is_not.123",
-           "limit": "1",
+        let query_params = from_value::<QueryParams>(json!({
+            "select": "study_name,body_mass/1000,sqlite_version()",
         }))
         .unwrap();
         let select = block_on(Select::from_path_and_query(
@@ -2567,584 +8342,1259 @@ WHERE "penguin"."study_name" {is_clause} {sql_param}"#,
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert_eq!(select.select.len(), 1);
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        let mut select = Select::from("penguin");
+        let field = SelectField::expression_from_url("kg:round(body_mass/1000,1)").unwrap();
+        select.select.push(field);
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        let sql = r#"SELECT ROUND("body_mass" / 1000, 1) FROM "penguin" ORDER BY "_order" LIMIT 1"#;
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" {is_not_clause} {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 1"#,
-                is_not_clause = is_not_clause(&rltbl.connection.kind()),
-            )
+            Some(result.rows[0].cells.get("kg").unwrap().value.clone()),
+            block_on(rltbl.connection.query_value(sql, None)).unwrap()
         );
-        assert_eq!(params, vec![json!("123")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+    }
+
+    #[test]
+    fn test_select_field_urls() {
+        let rltbl = memory_demo(5);
+
+        // Columns, aggregates, and expressions are written with their aliases, if any, and are
+        // read back as they were:
+        let mut select = Select::from("penguin");
+        select.select_column("study_name");
+        select.select_table_column("penguin", "island");
+        select.select_alias("", "body_mass", "mass");
+        select.select_alias("penguin", "species", "kind");
+        select
+            .select
+            .push(SelectField::expression_from_url("kg:round(body_mass/1000,1)").unwrap());
+        select
+            .select
+            .push(SelectField::expression_from_url("upper(species)").unwrap());
+        let params = select.to_params().unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" {is_not_clause} {sql_param}"#,
-                is_not_clause = is_not_clause(&rltbl.connection.kind()),
-            )
+            params.get("select").unwrap(),
+            "study_name,penguin.island,mass:body_mass,kind:penguin.species,\
+             kg:round(body_mass/1000,1),upper:upper(species)"
         );
-        assert_eq!(params, vec![json!("123")]);
+        let query_params = from_value::<QueryParams>(json!(params)).unwrap();
+        let parsed = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(parsed.select, select.select);
+
+        // Expressions that are written into the SQL as they are, and window functions, cannot be
+        // written in URLs, and are left out:
+        let mut select = Select::from("penguin");
+        select.select_column("study_name");
+        select.select_expression("sqlite_version()", "version");
+        select.select.push(SelectField::Window {
+            function: WindowFunction::RowNumber,
+            partition_by: vec![],
+            order_by: vec![],
+            alias: "n".to_string(),
+        });
+        assert!(select.select[1].to_url().is_err());
+        assert!(select.select[2].to_url().is_err());
+        assert_eq!(
+            select.to_params().unwrap().get("select").unwrap(),
+            "study_name"
+        );
+    }
 
-        // A URL with a filter on a string column and a value that looks like an integer (in):
-        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
-        let sql_param_1 = sql_param_gen.next();
-        let sql_param_2 = sql_param_gen.next();
-        let url = "http://example.com/penguin?penguin.study_name=in.(123,345)&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "in.(123,345)",
-           "limit": "1",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
+    #[test]
+    fn test_select_tokens() {
+        let rltbl = memory_demo(5);
+        let kind = rltbl.connection.kind();
+
+        // Selects with joins or subquery filters are written as tokens, and are read back as
+        // they were:
+        let mut inner_select = Select::from("penguin").unlimited();
+        inner_select.select_table_column("penguin", "individual_id");
+        inner_select
+            .table_eq("penguin", "island", &"Biscoe")
+            .unwrap();
+        let mut select = Select::from("penguin");
+        select.select_table_columns("penguin", &vec!["study_name", "individual_id"]);
+        select.select_alias("other", "species", "other_species");
+        select.left_join("penguin", "individual_id", "penguin", "individual_id");
+        if let Join::LeftJoin { alias, .. } = &mut select.joins[0] {
+            *alias = "other".to_string();
+        }
+        select.is_in_subquery("individual_id", &inner_select);
+        let url = select.to_url("", &Format::Json).unwrap();
+        let (path, token) = url.split_once("?q=").unwrap();
+        assert_eq!(path, "/penguin.json");
+        let query_params = from_value::<QueryParams>(json!({"q": token})).unwrap();
+        let parsed = block_on(Select::from_path_and_query(
+            "penguin.json",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(parsed.to_sql(&kind).unwrap(), select.to_sql(&kind).unwrap());
+
+        // The limit and offset can be given alongside the token:
+        let query_params =
+            from_value::<QueryParams>(json!({"q": token, "limit": "2", "offset": "1"})).unwrap();
+        let parsed = block_on(Select::from_path_and_query(
             "penguin",
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert_eq!((parsed.limit, parsed.offset), (Limit::N(2), 1));
+        assert_eq!(parsed.joins.len(), 1);
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // Tokens for other tables are ignored:
+        let parsed = block_on(Select::from_path_and_query(
+            "column",
+            &from_value::<QueryParams>(json!({"q": token})).unwrap(),
+            &rltbl,
+        ));
+        assert!(parsed.joins.is_empty());
+
+        // Tokens with names that are not simple, or with expressions that are not SelectExprs,
+        // are rejected:
+        let mut select = Select::from("penguin");
+        select.select_column(r#"study_name" FROM "user"; --"#);
+        assert!(Select::from_token(&select.to_token().unwrap()).is_err());
+        let mut select = Select::from("penguin");
+        select.select_expression("sqlite_version()", "version");
+        assert!(Select::from_token(&select.to_token().unwrap()).is_err());
+        let mut select = Select::from("penguin");
+        select.select_expression(r#"ROUND("body_mass" / 1000, 1)"#, "kg");
+        assert!(Select::from_token(&select.to_token().unwrap()).is_ok());
+        assert!(Select::from_token("not a token").is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let rltbl = memory_demo(5);
+        let rules = |select: &Select| {
+            block_on(select.validate(&rltbl))
+                .unwrap()
+                .into_iter()
+                .map(|problem| format!("{}.{} {}", problem.table, problem.column, problem.rule))
+                .collect::<Vec<_>>()
+        };
+
+        // A select of columns that exist, filtered on values of their types, has no problems:
+        let mut select = Select::from("penguin")
+            .is_in("island", &["Biscoe", "Dream"])
+            .unwrap();
+        select.select_column("species");
+        select.select_alias("", "body_mass", "mass");
+        select.left_join("penguin", "study_name", "table", "table");
+        select.eq("sample_number", &5).unwrap();
+        select.order_by("mass");
+        assert!(rules(&select).is_empty());
+
+        // Tables that do not exist are reported before anything else:
+        assert_eq!(rules(&Select::from("nothing")), ["nothing. missing_table"]);
+
+        // So are columns that do not exist, wherever they are given, and filter values that are
+        // not of the types of their columns, including those in subqueries:
+        let mut inner_select = Select::from("penguin").unlimited();
+        inner_select.select_column("individual_id");
+        inner_select.eq("bill_width", &5).unwrap();
+        let mut select = Select::from("penguin");
+        select.select_column("wingspan");
+        select.left_join("penguin", "study", "table", "table");
+        select.table_eq("other", "species", &"Adelie").unwrap();
+        select.eq("sample_number", &"five").unwrap();
+        select.is_in_subquery("individual_id", &inner_select);
+        select.order_by("mass");
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" IN ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+            rules(&select),
+            [
+                ".wingspan missing_column",
+                "penguin.study missing_column",
+                ".mass missing_column",
+                "other.species missing_table",
+                "penguin.sample_number sql_type:integer",
+                ".bill_width missing_column",
+            ]
         );
-        assert_eq!(params, vec![json!("123"), json!("345")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+
+        // The problems are reported in place of the database's error when the select is fetched:
+        let error = block_on(rltbl.fetch(&select)).unwrap_err().to_string();
+        assert!(error.contains("Column 'wingspan' does not exist"));
+        assert!(error.contains("Filter value 'five' for sample_number must be of type integer"));
+    }
+
+    #[test]
+    fn test_raw_selects() {
+        let rltbl = memory_demo(150);
+        let param = match rltbl.connection.kind() {
+            DbKind::Sqlite => "?",
+            DbKind::Postgres => "$1",
+        };
+        let sql = format!(
+            r#"SELECT "study_name", "sample_number" FROM "penguin" WHERE "island" = {param}"#
+        );
+        let params = vec![json!("Biscoe")];
+        let rows = block_on(rltbl.query_select_raw(&sql, &params)).unwrap();
+        let total = block_on(rltbl.count_select_raw(&sql, &params)).unwrap();
+        let biscoe =
+            block_on(rltbl.count(&Select::from("penguin").eq("island", &"Biscoe").unwrap()))
+                .unwrap();
+        assert_eq!(total, biscoe);
+        assert_eq!(rows.len() as u64, total.min(rltbl.default_limit as u64));
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" IN ({sql_param_1}, {sql_param_2})"#
-            )
+            rows[0].cells.keys().collect::<Vec<_>>(),
+            ["study_name", "sample_number"]
         );
-        assert_eq!(params, vec![json!("123"), json!("345")]);
 
-        // A URL with a filter on a string column and a value that looks like an integer (not_in):
-        let mut sql_param_gen = SqlParam::new(&rltbl.connection.kind());
-        let sql_param_1 = sql_param_gen.next();
-        let sql_param_2 = sql_param_gen.next();
-        let url = "http://example.com/penguin?penguin.study_name=not_in.(123,345)&limit=1";
-        let query_params = from_value(json!({
-           "penguin.study_name": "not_in.(123,345)",
-           "limit": "1",
+        // Strings and quoted names may contain anything:
+        let sql = r#"SELECT 'DROP; --' AS "delete", 1 AS "(""update"")""#;
+        let rows = block_on(rltbl.query_select_raw(sql, &vec![])).unwrap();
+        assert_eq!(rows[0].cells.get("delete").unwrap().text, "DROP; --");
+
+        // Anything but a single read-only query is rejected:
+        for sql in [
+            r#"SELECT * FROM "penguin"; DROP TABLE "penguin""#,
+            r#"DELETE FROM "penguin""#,
+            r#"WITH "gone" AS (DELETE FROM "penguin" RETURNING *) SELECT * FROM "gone""#,
+            r#"SELECT * FROM "penguin" -- comment"#,
+            r#"SELECT * FROM "penguin") AS "a", (SELECT 1"#,
+            r#"SELECT * FROM "penguin" FOR UPDATE"#,
+            r#"SELECT 'unterminated"#,
+            r#"PRAGMA table_info("penguin")"#,
+        ] {
+            assert!(
+                block_on(rltbl.query_select_raw(sql, &vec![])).is_err(),
+                "{sql}"
+            );
+        }
+        assert_eq!(
+            block_on(rltbl.count(&Select::from("penguin"))).unwrap(),
+            150
+        );
+
+        // The connections that ran them can still change the database afterwards:
+        let delete_sql = r#"DELETE FROM "penguin" WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(delete_sql, None)).unwrap();
+        assert_eq!(
+            block_on(rltbl.count(&Select::from("penguin"))).unwrap(),
+            149
+        );
+    }
+
+    #[test]
+    fn test_fetch_as() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Penguin {
+            #[serde(rename = "_id")]
+            id: u64,
+            sample_number: u32,
+            island: String,
+            bill_depth: f32,
+            body_mass: Option<i64>,
+        }
+
+        let rltbl = memory_demo(3);
+        let sql = r#"UPDATE "penguin" SET "body_mass" = NULL WHERE "_id" = 2"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let penguins =
+            block_on(rltbl.fetch_as::<Penguin>(&Select::from("penguin").limit(&2))).unwrap();
+        assert_eq!(penguins.len(), 2);
+        assert_eq!(penguins[0].id, 1);
+        assert_eq!(penguins[0].sample_number, 1);
+        assert!(penguins[0].body_mass.is_some());
+        assert_eq!(penguins[1].body_mass, None);
+        let rows = block_on(rltbl.fetch_rows(&Select::from("penguin").limit(&1))).unwrap();
+        assert_eq!(penguins[0].island, rows[0].get_string("island").unwrap());
+
+        // Values are converted to the types that the fields expect, where this loses nothing:
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Values {
+            integer: i64,
+            small: u8,
+            float: f64,
+            flag: bool,
+            text: String,
+            missing: Option<String>,
+        }
+        let row = |content: JsonValue| JsonRow {
+            content: content.as_object().unwrap().clone(),
+        };
+        let values = row(json!({
+            "integer": "42",
+            "small": 3.0,
+            "float": 7,
+            "flag": 1,
+            "text": 12.5,
+            "missing": null,
         }))
+        .deserialize::<Values>()
+        .unwrap();
+        assert_eq!(
+            values,
+            Values {
+                integer: 42,
+                small: 3,
+                float: 7.0,
+                flag: true,
+                text: "12.5".to_string(),
+                missing: None,
+            }
+        );
+        for (column, value) in [
+            ("integer", json!(2.5)),
+            ("small", json!(300)),
+            ("small", json!(-1.0)),
+            ("flag", json!(2)),
+            ("integer", json!(null)),
+        ] {
+            let mut content = json!({
+                "integer": 1, "small": 1, "float": 1, "flag": 0, "text": "", "missing": null
+            });
+            content[column] = value;
+            assert!(
+                row(content.clone()).deserialize::<Values>().is_err(),
+                "{content}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let rltbl = memory_demo(10);
+        let mut select = Select::from("penguin");
+        select.eq("island", &"Dream").unwrap();
+        let plan = block_on(select.explain(&rltbl)).unwrap();
+        assert!(!plan.is_empty());
+        assert!(plan.iter().any(|step| step.detail.contains("penguin")));
+        for step in &plan {
+            if let Some(parent) = step.parent {
+                assert!(plan.iter().any(|other| other.id == parent), "{plan:?}");
+            }
+        }
+        assert!(matches!(
+            Format::try_from(&"penguin.plan.json".to_string()),
+            Ok(Format::PlanJson)
+        ));
+
+        // Plans written as text are nested by their indentation, as in PostgreSQL:
+        let lines = [
+            "Limit  (cost=1.05..1.06 rows=1 width=72)",
+            "  ->  Sort  (cost=1.05..1.06 rows=1 width=72)",
+            "        Sort Key: _order",
+            "        ->  Seq Scan on penguin  (cost=0.00..1.04 rows=1 width=72)",
+            "              Filter: (island = 'Dream'::text)",
+        ]
+        .map(|line| line.to_string());
+        let plan = QueryPlanStep::from_indented_lines(&lines);
+        assert_eq!(
+            plan.iter()
+                .map(|step| (step.id, step.parent))
+                .collect::<Vec<_>>(),
+            [
+                (1, None),
+                (2, Some(1)),
+                (3, Some(2)),
+                (4, Some(2)),
+                (5, Some(4))
+            ]
+        );
+        assert_eq!(
+            plan[3].detail,
+            "Seq Scan on penguin  (cost=0.00..1.04 rows=1 width=72)"
+        );
+    }
+
+    #[test]
+    fn test_totals() {
+        let rltbl = memory_demo(20);
+
+        // The numeric columns are totalled over all of the matching rows, not just the page:
+        let select = Select::from("penguin").limit(&5).totals(true);
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len(), 5);
+        let total = &result.totals["sample_number"];
+        assert_eq!((total.sum.clone(), total.avg), (json!(210), Some(10.5)));
+        assert!(result.totals.contains_key("body_mass"));
+        assert!(!result.totals.contains_key("island"));
+
+        // The filters apply, and only the selected columns are totalled:
+        let mut select = Select::from("penguin")
+            .lte("sample_number", &4)
+            .unwrap()
+            .totals(true);
+        select.select_column("sample_number");
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.totals.keys().collect::<Vec<_>>(), ["sample_number"]);
+        assert_eq!(result.totals["sample_number"].sum, json!(10));
+
+        // Totals are asked for in URLs, and are only returned when they are asked for:
+        assert!(select
+            .to_url("", &Format::Json)
+            .unwrap()
+            .contains("totals=true"));
+        let result = block_on(rltbl.fetch(&select.totals(false))).unwrap();
+        assert!(result.totals.is_empty());
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let rltbl = memory_demo(10);
+        // The nulltype of individual_id is 'empty', so its empty strings count as empty values:
+        block_on(rltbl.connection.query(
+            r#"UPDATE "penguin" SET "individual_id" = '' WHERE "sample_number" = 1"#,
+            None,
+        ))
+        .unwrap();
+        block_on(rltbl.connection.query(
+            r#"UPDATE "penguin" SET "individual_id" = NULL WHERE "sample_number" = 2"#,
+            None,
+        ))
         .unwrap();
+        let sample_numbers = |select: &Select| {
+            block_on(rltbl.fetch(select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.cells["sample_number"].value.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let select = Select::from("penguin").is_empty("individual_id");
+        assert_eq!(sample_numbers(&select), [json!(1), json!(2)]);
+        let select = Select::from("penguin").is_not_empty("individual_id");
+        assert_eq!(sample_numbers(&select).len(), 8);
+
+        // A column without a nulltype has no empty values other than NULL:
+        let select = Select::from("penguin").is_empty("island");
+        assert!(sample_numbers(&select).is_empty());
+
+        // The filters are given in URLs and in the filter language:
+        let select = Select::from("penguin").is_empty("individual_id");
+        let url = select.to_url("", &Format::Json).unwrap();
+        assert!(url.contains("individual_id=is.empty"));
+        let query_params = QueryParams::from([("individual_id".into(), "is_not.empty".into())]);
         let select = block_on(Select::from_path_and_query(
             "penguin",
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
+        assert!(matches!(
+            select.filters.as_slice(),
+            [Filter::IsNotEmpty { column, values, .. }]
+                if column == "individual_id" && values == &[""]
+        ));
+        let filter = FilterExpr::parse("individual_id is empty").unwrap();
+        assert!(matches!(filter, Filter::IsEmpty { .. }));
+        let filter = FilterExpr::parse(r#"individual_id is "empty""#).unwrap();
+        assert!(matches!(filter, Filter::Is { .. }));
+    }
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_count_format() {
+        let rltbl = memory_demo(10);
+        assert!(matches!(
+            Format::try_from(&"penguin.count".to_string()),
+            Ok(Format::Count)
+        ));
+
+        // The rows that the select's filters match are counted, whatever its limit:
+        let query_params = QueryParams::from([
+            ("sample_number".into(), "lte.4".into()),
+            ("limit".into(), "2".into()),
+        ]);
+        let select = block_on(Select::from_path_and_query(
+            "penguin.count",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(select.table_name, "penguin");
+        assert_eq!(block_on(rltbl.count(&select)).unwrap(), 4);
+        let url = select.to_url("/table", &Format::Count).unwrap();
+        assert!(url.starts_with("/table/penguin.count?"));
+    }
+
+    #[test]
+    fn test_estimate_count() {
+        let rltbl = memory_demo(10);
+        let select = Select::from("penguin").estimate_count(true);
+
+        // Without statistics, the rows are counted exactly:
+        assert_eq!(block_on(rltbl.estimate_count(&select)).unwrap(), None);
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert!(!result.range.is_estimated());
+
+        block_on(rltbl.connection.query("ANALYZE", None)).unwrap();
+        assert_eq!(block_on(rltbl.estimate_count(&select)).unwrap(), Some(10));
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert!(result.range.is_estimated());
+        assert_eq!(result.range.to_string(), "Rows 1-10 of ~10");
+
+        // A filtered select cannot be estimated:
+        let mut filtered = select.clone();
+        filtered.eq("island", &"Dream").unwrap();
+        assert_eq!(block_on(rltbl.estimate_count(&filtered)).unwrap(), None);
+        let result = block_on(rltbl.fetch(&filtered)).unwrap();
+        assert!(!result.range.is_estimated());
+    }
+
+    #[test]
+    fn test_facets() {
+        let rltbl = memory_demo(20);
+
+        // The values are counted on every page, from the most common:
+        let select = Select::from("penguin").limit(&1);
+        let facets = block_on(rltbl.facets("penguin", &["island", "species"], &select)).unwrap();
+        assert_eq!(facets.len(), 2);
+        assert_eq!(facets[0].column, "island");
+        assert!(!facets[0].truncated);
+        let sql = r#"SELECT "island" AS "value", COUNT(1) AS "count" FROM "penguin"
+                     GROUP BY "island" ORDER BY "count" DESC, "value""#;
+        let expected = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get_value("value").unwrap(),
+                    row.get_unsigned("count").unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let actual = facets[0]
+            .values
+            .iter()
+            .map(|facet| (facet.value.clone(), facet.count))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+        assert_eq!(
+            facets[1]
+                .values
+                .iter()
+                .map(|facet| facet.count)
+                .sum::<u64>(),
+            20
+        );
+
+        // Only the rows that match the filters are counted:
+        let mut filtered = Select::from("penguin");
+        filtered.eq("island", &"Dream").unwrap();
+        let count = block_on(rltbl.count(&filtered)).unwrap();
+        let facets = block_on(rltbl.facets("penguin", &["island"], &filtered)).unwrap();
+        assert_eq!(facets[0].values.len(), 1);
+        assert_eq!(facets[0].values[0].value, json!("Dream"));
+        assert_eq!(facets[0].values[0].count, count);
+
+        // Every column is counted when none are given:
+        let facets = block_on(rltbl.facets("penguin", &[], &filtered)).unwrap();
+        assert!(facets.iter().any(|facet| facet.column == "sample_number"));
+
+        assert!(block_on(rltbl.facets("penguin", &["no_such_column"], &select)).is_err());
+        assert!(block_on(rltbl.facets("island", &["island"], &select)).is_err());
+        assert!(matches!(
+            Format::try_from(&"penguin.facets.json".to_string()),
+            Ok(Format::FacetsJson)
+        ));
+
+        // Cached facets are the same as those counted afresh, until the table changes:
+        let rltbl = block_on(Relatable::build_demo(
+            Some(MEMORY_DB),
+            &true,
+            20,
+            &CachingStrategy::Trigger,
+        ))
+        .unwrap();
+        let facets = block_on(rltbl.facets_cached("penguin", &["island"], &filtered)).unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."study_name" NOT IN ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC
-LIMIT 1"#
-            )
+            facets,
+            block_on(rltbl.facets("penguin", &["island"], &filtered)).unwrap()
         );
-        assert_eq!(params, vec![json!("123"), json!("345")]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."study_name" NOT IN ({sql_param_1}, {sql_param_2})"#
-            )
+            block_on(rltbl.facets_cached("penguin", &["island"], &filtered)).unwrap(),
+            facets
         );
-        assert_eq!(params, vec![json!("123"), json!("345")]);
+        let sql = r#"UPDATE "penguin" SET "island" = 'Dream' WHERE "island" <> 'Dream'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let facets = block_on(rltbl.facets_cached("penguin", &["island"], &filtered)).unwrap();
+        assert_eq!(facets[0].values[0].count, 20);
+    }
 
-        // A URL with a filter on the change ID
-        let url = "http://example.com/penguin?_change_id=gt.5";
-        let query_params = from_value(json!({
-           "_change_id": "gt.5",
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
-            "penguin",
-            &query_params,
-            &rltbl,
-        ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_max_limit() {
+        let rltbl = memory_demo(20);
+        let from_url = |params: JsonValue| {
+            let query_params = from_value::<QueryParams>(params).unwrap();
+            block_on(Select::from_path_and_query(
+                "penguin",
+                &query_params,
+                &rltbl,
+            ))
+        };
+
+        // A request may not ask for more rows than the server's maximum, or for all of them:
+        let select = from_url(json!({"limit": "10000000"}));
+        assert_eq!((select.limit, select.limited), (Limit::N(MAX_LIMIT), true));
+        let select = from_url(json!({"limit": "none"}));
+        assert_eq!((select.limit, select.limited), (Limit::N(MAX_LIMIT), true));
+        let select = from_url(json!({"limit": "10"}));
+        assert_eq!((select.limit, select.limited), (Limit::N(10), false));
+
+        // A table may have a lower maximum of its own, which also applies to samples:
+        block_on(rltbl.set_max_limit("penguin", Some(5))).unwrap();
+        assert_eq!(block_on(rltbl.get_max_limit("penguin")).unwrap(), 5);
+        let result = block_on(rltbl.fetch(&from_url(json!({"limit": "10"})))).unwrap();
+        assert_eq!(result.rows.len(), 5);
+        assert!(result.range.is_limited());
+        let result = block_on(rltbl.fetch(&from_url(json!({"limit": "5"})))).unwrap();
+        assert!(!result.range.is_limited());
+        let select = from_url(json!({"sample": "8"}));
+        assert_eq!((select.sample, select.limited), (Some(5), true));
+
+        block_on(rltbl.set_max_limit("penguin", None)).unwrap();
+        assert_eq!(block_on(rltbl.get_max_limit("penguin")).unwrap(), MAX_LIMIT);
+        assert!(block_on(rltbl.set_max_limit("nothing", Some(5))).is_err());
+
+        // Selects made in Rust are not limited unless they are given a maximum:
+        let select = Select::from("penguin").unlimited();
+        assert!(!select.limited);
+        let select = select.max_limit(&3);
+        assert_eq!((select.limit, select.limited), (Limit::N(3), true));
+    }
+
+    #[test]
+    fn test_limit() {
+        let rltbl = memory_demo(20);
+        let from_url = |path: &str, params: JsonValue| {
+            let query_params = from_value::<QueryParams>(params).unwrap();
+            block_on(Select::from_path_and_query(path, &query_params, &rltbl))
+        };
+
+        // A limit is a number of rows, which may be zero, or 'none', and anything else is an
+        // error, which a server reports as a bad request:
+        assert_eq!("5".parse::<Limit>().unwrap(), Limit::N(5));
+        assert_eq!("0".parse::<Limit>().unwrap(), Limit::N(0));
+        assert_eq!("none".parse::<Limit>().unwrap(), Limit::Unlimited);
+        assert!("-1".parse::<Limit>().is_err());
+        assert!("all".parse::<Limit>().is_err());
+        let query_params = from_value::<QueryParams>(json!({"limit": "-1"})).unwrap();
+        assert!(Limit::from_query_params(&query_params).is_err());
+        let query_params = from_value::<QueryParams>(json!({})).unwrap();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-, (SELECT MAX(change_id) FROM history
-                    WHERE "table" = {sql_param}
-                      AND "row" = "penguin"._id
-                   ) AS _change_id
-FROM "penguin"
-WHERE "_change_id" > {sql_param}
-ORDER BY "penguin"._order ASC
-LIMIT 100"#
-            ),
+            Limit::from_query_params(&query_params).unwrap(),
+            Limit::Default
         );
-        assert_eq!(params, vec![json!("penguin"), json!(5)]);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+
+        // A limit of zero returns no rows, but still counts them:
+        let select = Select::from("penguin").limit(&0);
+        let (sql, _) = select.to_sql(&DbKind::Sqlite).unwrap();
+        assert!(sql.contains("LIMIT 0"));
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len(), 0);
+        assert_eq!(to_value(&result.range).unwrap()["total"], json!(20));
+        let (sql, _) = Select::from("penguin")
+            .unlimited()
+            .to_sql(&DbKind::Sqlite)
+            .unwrap();
+        assert!(!sql.contains("LIMIT"));
+        let (sql, _) = Select::from("penguin").to_sql(&DbKind::Sqlite).unwrap();
+        assert!(sql.contains(&format!("LIMIT {DEFAULT_LIMIT}")));
+
+        // Limits are written in URLs and tokens, and read back:
+        let select = Select::from("penguin").unlimited();
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "_change_id" > {sql_param}"#
-            ),
+            select.to_url("/table", &Format::Default).unwrap(),
+            "/table/penguin?limit=none"
         );
-        assert_eq!(params, vec![json!(5)]);
+        let select = Select::from_token(&select.to_token().unwrap()).unwrap();
+        assert_eq!(select.limit, Limit::Unlimited);
+
+        // An export of a whole table is not limited to a page, but a page of it is:
+        block_on(rltbl.set_max_limit("penguin", Some(5))).unwrap();
+        let select = from_url("penguin.csv", json!({"limit": "none"}));
+        assert_eq!((select.limit, select.limited), (Limit::Unlimited, false));
+        assert_eq!(block_on(rltbl.fetch(&select)).unwrap().rows.len(), 20);
+        let select = from_url("penguin.json", json!({"limit": "none"}));
+        assert_eq!((select.limit, select.limited), (Limit::N(5), true));
+        let select = from_url("penguin.csv", json!({"limit": "10"}));
+        assert_eq!((select.limit, select.limited), (Limit::N(5), true));
+    }
 
-        // A URL that includes an expression
-        let url = "http://example.com/penguin?select=sample_number,count()";
-        let query_params = from_value(json!({
-            "select": "sample_number,count()"
-        }))
-        .unwrap();
-        let select = block_on(Select::from_path_and_query(
+    #[test]
+    fn test_sample() {
+        let rltbl = memory_demo(20);
+
+        // The order, offset, and cursors of a sample are ignored, but its filters are not:
+        let mut select = Select::from("penguin").offset(&10).sample(5);
+        select.order_by("sample_number");
+        let (sql, _) = select.to_sql(&DbKind::Sqlite).unwrap();
+        assert!(sql.ends_with("ORDER BY RANDOM()\nLIMIT 5"), "{sql}");
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows.len(), 5);
+        assert_eq!(result.range.to_string(), "Rows 1-5 of 20");
+        let ids = result
+            .rows
+            .iter()
+            .map(|row| row.id)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(ids.len(), 5);
+
+        let mut filtered = Select::from("penguin").sample(50);
+        filtered.eq("island", &"Dream").unwrap();
+        let result = block_on(rltbl.fetch(&filtered)).unwrap();
+        assert_eq!(
+            result.rows.len() as u64,
+            block_on(rltbl.count(&filtered)).unwrap()
+        );
+        assert!(result
+            .rows
+            .iter()
+            .all(|row| row.cells.get("island").unwrap().value == json!("Dream")));
+
+        // Large tables in PostgreSQL are sampled by block:
+        let mut select = Select::from("penguin").sample(5);
+        select.sample_percent = Some(0.5);
+        let (sql, _) = select.to_sql(&DbKind::Postgres).unwrap();
+        assert!(
+            sql.contains(r#"FROM "penguin" TABLESAMPLE SYSTEM (0.5)"#),
+            "{sql}"
+        );
+        let (sql, _) = select.to_sql(&DbKind::Sqlite).unwrap();
+        assert!(!sql.contains("TABLESAMPLE"), "{sql}");
+
+        // Samples are written in URLs:
+        assert_eq!(
+            Select::from("penguin")
+                .sample(5)
+                .to_url("", &Format::Default)
+                .unwrap(),
+            "/penguin?sample=5"
+        );
+        let query_params = from_value::<QueryParams>(json!({"sample": "5"})).unwrap();
+        let parsed = block_on(Select::from_path_and_query(
             "penguin",
             &query_params,
             &rltbl,
         ));
-        assert_eq!(url, select.to_url(&base, &Format::Default).unwrap());
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(parsed.sample, Some(5));
+    }
+
+    #[test]
+    fn test_sql_cache() {
+        let kind = DbKind::Sqlite;
+        let select = |pattern: &str, numbers: &[u64], view: &str| {
+            let mut select = Select::from("penguin")
+                .like("study_name", &pattern)
+                .unwrap()
+                .is_in("sample_number", &numbers)
+                .unwrap()
+                .filters(&vec!["_change_id > 5".to_string()])
+                .unwrap();
+            select.view_name = view.to_string();
+            select
+        };
+
+        // Selects that differ only in their values have the same shape and share their SQL, but
+        // not their parameters:
+        let first = select("FAKE*", &[1, 2], "");
+        let second = select("REAL*", &[3, 4], "");
         assert_eq!(
-            sql,
-            r#"SELECT
-  "sample_number",
-  count()
-FROM "penguin"
-ORDER BY "penguin"._order ASC
-LIMIT 100"#
+            serde_json::to_string(&first.to_shape()).unwrap(),
+            serde_json::to_string(&second.to_shape()).unwrap()
         );
-        assert_eq!(params, empty);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        for select in [&first, &second, &first] {
+            assert_eq!(
+                select.to_sql_cached(&kind).unwrap(),
+                select.to_sql(&kind).unwrap()
+            );
+        }
         assert_eq!(
-            sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin""#
+            second.to_sql_params(&kind).unwrap(),
+            vec![
+                json!("penguin"),
+                json!("REAL%"),
+                json!(3),
+                json!(4),
+                json!(5)
+            ]
         );
-        assert_eq!(params, empty);
+
+        // Selects that differ in the number of values of an in filter, or in their view, do not:
+        for select in [
+            select("FAKE*", &[1, 2, 3], ""),
+            select("FAKE*", &[1, 2], "penguin_text_view"),
+        ] {
+            assert_ne!(
+                serde_json::to_string(&select.to_shape()).unwrap(),
+                serde_json::to_string(&first.to_shape()).unwrap()
+            );
+            assert_eq!(
+                select.to_sql_cached(&kind).unwrap(),
+                select.to_sql(&kind).unwrap()
+            );
+        }
     }
 
     #[test]
-    fn test_select_methods() {
-        let rltbl = block_on(Relatable::init(
-            &true,
-            Some("build/test_select_methods.db"),
-            &CachingStrategy::Trigger,
-        ))
-        .unwrap();
-        let drop_sql = r#"DROP TABLE IF EXISTS "penguin_test""#;
-        let create_sql = r#"CREATE TABLE "penguin_test" (
-    _id INTEGER,
-    _order INTEGER,
-    study_name TEXT,
-    sample_number INTEGER,
-    species TEXT,
-    island TEXT,
-    individual_id TEXT,
-    bill_length REAL,
-    bill_depth NUMERIC,
-    body_mass BIGINT
-)"#;
-        block_on(rltbl.connection.query(drop_sql, None)).unwrap();
-        block_on(rltbl.connection.query(create_sql, None)).unwrap();
-        let empty: Vec<JsonValue> = vec![];
-
-        // select_columns
-        let mut select = Select::from("penguin_test");
-        select.select_table_columns("penguin_test", &vec!["species", "island"]);
-        select.select_columns(&vec!["study_name", "body_mass"]);
+    fn test_large_in_filters() {
+        let rltbl = memory_demo(10);
+        let kind = rltbl.connection.kind();
+        let sql_param = SqlParam::new(&kind).next();
+        let fetch = |select: &Select| {
+            block_on(rltbl.fetch(select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.id)
+                .collect::<Vec<_>>()
+        };
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // Lists of more values than there can be parameters of a query are bound as a single
+        // JSON array, for both the in and the not in filters, and together with other filters:
+        let numbers = (3..100_000).collect::<Vec<_>>();
+        let select = Select::from("penguin")
+            .is_in("sample_number", &numbers)
+            .unwrap()
+            .is_not_in("sample_number", &numbers[5..].to_vec())
+            .unwrap()
+            .unlimited();
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            r#"SELECT
-  "penguin_test"."species",
-  "penguin_test"."island",
-  "study_name",
-  "body_mass"
-FROM "penguin_test"
-ORDER BY "penguin_test"._order ASC
-LIMIT 100"#
+            format!(
+                r#"SELECT *
+FROM "penguin"
+WHERE "sample_number" IN (SELECT "value" FROM JSON_EACH({sql_param}))
+  AND "sample_number" NOT IN (SELECT "value" FROM JSON_EACH({sql_param}))
+ORDER BY "penguin"._order ASC"#
+            )
         );
-        assert_eq!(params, empty);
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(select.to_sql_params(&kind).unwrap(), params);
+        assert_eq!(fetch(&select), [3, 4, 5, 6, 7]);
+
+        // So are lists of text:
+        let ids = (1..25_000)
+            .flat_map(|i| [format!("N{i}A1"), format!("N{i}A2")])
+            .collect::<Vec<_>>();
+        let select = Select::from("penguin")
+            .is_in("individual_id", &ids[8..].to_vec())
+            .unwrap()
+            .unlimited();
+        assert_eq!(fetch(&select), [9, 10]);
+
+        // Lists longer than the limit have the same shape, whatever their length, and shorter
+        // lists are bound value by value as before:
+        let select = |n: usize| {
+            Select::from("penguin")
+                .is_in("sample_number", &numbers[..n].to_vec())
+                .unwrap()
+        };
         assert_eq!(
-            sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin_test""#
+            serde_json::to_string(&select(MAX_IN_PARAMS + 1).to_shape()).unwrap(),
+            serde_json::to_string(&select(MAX_IN_PARAMS * 2).to_shape()).unwrap()
         );
-        assert_eq!(params, empty);
-
-        // select_alias
-        let mut select = Select::from("penguin_test");
-        select.select_alias("penguin_test", "island", "location");
-
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-        assert_eq!(
-            sql,
-            r#"SELECT
-  "penguin_test"."island" AS "location"
-FROM "penguin_test"
-ORDER BY "penguin_test"._order ASC
-LIMIT 100"#
+        // But not lists of text, which PostgreSQL unpacks differently:
+        assert_ne!(
+            serde_json::to_string(&select(MAX_IN_PARAMS + 1).to_shape()).unwrap(),
+            serde_json::to_string(
+                &Select::from("penguin")
+                    .is_in("sample_number", &ids[..MAX_IN_PARAMS + 1].to_vec())
+                    .unwrap()
+                    .to_shape()
+            )
+            .unwrap()
         );
-        assert_eq!(params, empty);
-
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
         assert_eq!(
-            sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin_test""#
+            select(MAX_IN_PARAMS).to_sql(&kind).unwrap().1.len(),
+            MAX_IN_PARAMS
         );
-        assert_eq!(params, empty);
+    }
 
-        // select_expression
-        let mut select = Select::from("penguin_test");
-        select.select_expression("CASE WHEN island = 'Biscoe' THEN 'BISCOE' END", "location");
+    #[test]
+    fn test_sql_formats() {
+        let rltbl = memory_demo(5);
+        let kind = rltbl.connection.kind();
+        let sql_param = SqlParam::new(&kind).next();
+        let mut inner_select = Select::from("penguin").unlimited();
+        inner_select.select_column("individual_id");
+        inner_select.eq("island", &"Biscoe's").unwrap();
+        let mut select = Select::from("penguin")
+            .filters(&vec!["_change_id > 0".to_string()])
+            .unwrap();
+        select.left_join("penguin", "study_name", "table", "table");
+        select.is_in_subquery("individual_id", &inner_select);
+        select.select_expression(r#"'a  (b)'"#, "text");
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+        // Each item of the SELECT clause is on a line of its own, and subqueries are indented:
+        let (pretty, params) = select.to_formatted_sql(&kind, &SqlFormat::Pretty).unwrap();
         assert_eq!(
-            sql,
-            r#"SELECT
-  CASE WHEN island = 'Biscoe' THEN 'BISCOE' END AS "location"
-FROM "penguin_test"
-ORDER BY "penguin_test"._order ASC
+            pretty,
+            format!(
+                r#"SELECT
+  (SELECT MAX(change_id) FROM history WHERE "table" = {sql_param} AND "row" = "penguin"._id) AS _change_id,
+  'a  (b)' AS "text"
+FROM "penguin"
+LEFT JOIN "table" ON "penguin"."study_name" = "table"."table"
+WHERE "_change_id" > {sql_param}
+  AND "penguin"."individual_id" IN (
+    SELECT
+      "individual_id"
+    FROM "penguin"
+    WHERE "island" = {sql_param}
+    ORDER BY "penguin"._order ASC
+  )
+ORDER BY "penguin"._order ASC, "table"._id ASC
 LIMIT 100"#
+            )
         );
-        assert_eq!(params, empty);
+        assert_eq!(pretty, select.to_sql(&kind).unwrap().0);
 
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        // The same statement can be written on one line, leaving quoted text as it is:
+        let (compact, compact_params) =
+            select.to_formatted_sql(&kind, &SqlFormat::Compact).unwrap();
         assert_eq!(
-            sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin_test""#
+            compact,
+            format!(
+                r#"SELECT (SELECT MAX(change_id) FROM history WHERE "table" = {sql_param} AND "row" = "penguin"._id) AS _change_id, 'a  (b)' AS "text" FROM "penguin" LEFT JOIN "table" ON "penguin"."study_name" = "table"."table" WHERE "_change_id" > {sql_param} AND "penguin"."individual_id" IN (SELECT "individual_id" FROM "penguin" WHERE "island" = {sql_param} ORDER BY "penguin"._order ASC) ORDER BY "penguin"._order ASC, "table"._id ASC LIMIT 100"#
+            )
         );
-        assert_eq!(params, empty);
-
-        // select_all
-        let mut select = Select::from("penguin_test");
-        block_on(select.select_all(&rltbl, "penguin_test")).unwrap();
+        assert_eq!(compact_params, params);
+
+        // All of the columns of the joined select are selected when no fields are given:
+        select.select.clear();
+        let (sql, params) = select.to_sql(&kind).unwrap();
+        assert!(sql.starts_with("SELECT\n  \"penguin\".*,\n  (SELECT MAX(change_id)"));
+        for sql in [sql.clone(), sql::format_sql(&sql, &SqlFormat::Compact)] {
+            block_on(rltbl.connection.query(&sql, Some(&json!(params)))).unwrap();
+        }
+    }
 
-        let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_keyset_pagination() {
+        let rltbl = memory_demo(25);
+        let kind = rltbl.connection.kind();
+
+        // Cursors are compared with the _order and _id of each row:
+        let cursor = PageCursor { order: 5000, id: 5 };
+        let mut select = Select::from("penguin").limit(&10).after(&cursor);
+        select.eq("island", &"Dream").unwrap();
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
-            r#"SELECT
-  "_id",
-  "_order",
-  "study_name",
-  "sample_number",
-  "species",
-  "island",
-  "individual_id",
-  "bill_length",
-  "bill_depth",
-  "body_mass"
-FROM "penguin_test"
-ORDER BY "penguin_test"._order ASC
-LIMIT 100"#
+            r#"SELECT *
+FROM "penguin"
+WHERE "island" = ?
+  AND ("penguin"._order, "penguin"._id) > (?, ?)
+ORDER BY "penguin"._order ASC
+LIMIT 10"#
         );
-        assert_eq!(params, empty);
+        assert_eq!(params, vec![json!("Dream"), json!(5000), json!(5)]);
+        assert_eq!(select.to_sql_params(&kind).unwrap(), params);
+
+        // In URLs, cursors are given by opaque tokens:
+        let token = cursor.to_token();
+        assert_eq!(PageCursor::from_token(&token).unwrap(), cursor);
+        assert!(PageCursor::from_token("not a cursor").is_err());
+        let url = format!("/penguin?island=eq.Dream&limit=10&after={token}");
+        assert_eq!(select.to_url("", &Format::Default).unwrap(), url);
+        let query_params = from_value::<QueryParams>(json!({
+            "island": "eq.Dream",
+            "limit": "10",
+            "after": token,
+        }))
+        .unwrap();
+        let parsed = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(parsed.after, Some(cursor));
+
+        // Cursors cannot be combined with another order:
+        let mut ordered = Select::from("penguin").after(&PageCursor::default());
+        ordered.order_by("island");
+        assert!(ordered.to_sql(&kind).is_err());
+
+        // Page forwards, and then backwards, through the table:
+        let ids = |result: &ResultSet| result.rows.iter().map(|row| row.id).collect::<Vec<_>>();
+        let select = Select::from("penguin").limit(&10);
+        let first = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(first.range.previous(), None);
+        let next = PageCursor::from_token(first.range.next().unwrap()).unwrap();
+        let second = block_on(rltbl.fetch(&select.clone().after(&next))).unwrap();
+        assert_eq!(ids(&second), (11..=20).collect::<Vec<_>>());
+        assert_eq!(second.range.to_string(), "Rows 11-20 of 25");
+        let next = PageCursor::from_token(second.range.next().unwrap()).unwrap();
+        let third = block_on(rltbl.fetch(&select.clone().after(&next))).unwrap();
+        assert_eq!(ids(&third), (21..=25).collect::<Vec<_>>());
+        assert_eq!(third.range.to_string(), "Rows 21-25 of 25");
+        assert_eq!(third.range.next(), None);
+        let previous = PageCursor::from_token(third.range.previous().unwrap()).unwrap();
+        let back = block_on(rltbl.fetch(&select.clone().before(&previous))).unwrap();
+        assert_eq!(ids(&back), ids(&second));
+        assert_eq!(back.range.to_string(), "Rows 11-20 of 25");
+    }
 
-        let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
+    #[test]
+    fn test_json_path_filters() {
+        let rltbl = memory_demo(5);
+        for (row, level) in [(2, "error"), (4, "warning")] {
+            block_on(rltbl.add_message(
+                "tester",
+                "penguin",
+                row,
+                "species",
+                &json!("Adelie"),
+                level,
+                "custom",
+                "A message",
+            ))
+            .unwrap();
+        }
+        let fetch = |select: &Select| {
+            block_on(rltbl.fetch(select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.id)
+                .collect::<Vec<_>>()
+        };
+
+        // The values in JSON columns, such as the _message column of the default view, are
+        // extracted by path and compared with the given value:
+        let mut select = Select::from("penguin")
+            .json_path("_message", "$[0].level", "eq", &"error")
+            .unwrap();
+        select.view_name = "penguin_default_view".to_string();
+        assert_eq!(fetch(&select), [2]);
+        let mut select = Select::from("penguin")
+            .json_path("_message", "$[0].level", "like", &"warn*")
+            .unwrap();
+        select.view_name = "penguin_default_view".to_string();
+        assert_eq!(fetch(&select), [4]);
+
+        // Filters on JSON paths are written to and read from URLs with the path after an arrow:
+        let url = select.to_url("", &Format::Json).unwrap();
+        assert_eq!(url, "/penguin.json?_message->$[0].level=like.warn*");
+        let query_params =
+            from_value::<QueryParams>(json!({"_message->$[0].level": "is_not.null"})).unwrap();
+        let parsed = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(fetch(&parsed), [2, 4]);
+        let parsed = block_on(Select::from_path_and_query(
+            "penguin",
+            &from_value::<QueryParams>(json!({"or": "(_message->$[0].level.eq.error)"})).unwrap(),
+            &rltbl,
+        ));
+        assert_eq!(fetch(&parsed), [2]);
+
+        // In PostgreSQL, values are extracted as text, and cast to be compared with numbers, while
+        // in SQLite, the parameters, which are bound as text, are cast instead:
+        let filter = Filter::JsonPath {
+            table: "penguin".to_string(),
+            column: "_history".to_string(),
+            path: "$[0].body_mass.value".to_string(),
+            op: "gt".to_string(),
+            value: json!(3000),
+        };
+        let (sql, params) = filter
+            .to_sql(&mut SqlParam::new(&DbKind::Postgres))
+            .unwrap();
+        assert_eq!(
+            sql,
+            r#"CAST((CAST("penguin"."_history" AS JSONB) -> 0 -> 'body_mass' ->> 'value') AS NUMERIC) > $1"#
+        );
+        assert_eq!(params, [json!(3000)]);
+        let (sql, _) = filter.to_sql(&mut SqlParam::new(&DbKind::Sqlite)).unwrap();
         assert_eq!(
             sql,
-            r#"SELECT COUNT(1) AS "count"
-FROM "penguin_test""#
+            r#"JSON_EXTRACT("penguin"."_history", '$[0].body_mass.value') > CAST(? AS NUMERIC)"#
         );
-        assert_eq!(params, empty);
 
-        block_on(rltbl.connection.query(drop_sql, None)).unwrap();
+        // Paths with anything other than simple keys and indexes are rejected:
+        assert!(Select::from("penguin")
+            .json_path("_message", "$[0].level') OR (1", "eq", &"error")
+            .is_err());
+        assert!(Select::from("penguin")
+            .json_path("_message", "$.level", "in", &"error")
+            .is_err());
     }
 
     #[test]
-    fn test_subquery() {
-        let rltbl = block_on(Relatable::init(
-            &true,
-            Some("build/test_subquery.db"),
-            &CachingStrategy::Trigger,
-        ))
+    fn test_date_filters() {
+        let rltbl = memory_demo(0);
+        let today = chrono::Utc::now().format("%Y-%m-%d");
+        let path = "build/test_date_filters.tsv";
+        std::fs::write(
+            path,
+            format!(
+                "name\tday\ttime\n\
+                 a\t2020-01-01\t2020-01-01 08:00:00\n\
+                 b\t2020-01-02\t2020-01-02T12:30:00\n\
+                 c\t{today}\t{today} 00:00:00\n\
+                 d\t2999-01-01\t2999-01-01 00:00:00\n"
+            ),
+        )
         .unwrap();
-        let sql_param = SqlParam::new(&rltbl.connection.kind()).next();
+        block_on(rltbl.load_table("event", path, true, None));
+        let fetch = |select: &Select| {
+            block_on(rltbl.fetch(select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.cells["name"].text.clone())
+                .collect::<Vec<_>>()
+        };
 
-        // Subquery select, filtered on a string:
-        let mut inner_select = Select::from("penguin").limit(&0);
-        inner_select.select_table_column("penguin", "individual_id");
-        inner_select.left_join("penguin", "individual_id", "egg", "individual_id");
-        inner_select
-            .table_eq("penguin", "individual_id", &"N1")
+        // Dates are compared with dates, and dates and times with dates and times, however the
+        // latter are written:
+        let select = Select::from("event")
+            .is_before("day", "2020-01-02")
             .unwrap();
-        let mut outer_select = Select::from("penguin").limit(&0);
-        outer_select.is_in_subquery("individual_id", &inner_select);
-
-        let tables = outer_select.get_tables().into_iter().collect::<Vec<_>>();
-        assert_eq!(tables, vec!["egg", "penguin"]);
-
-        let (sql, params) = outer_select.to_sql(&rltbl.connection.kind()).unwrap();
+        assert_eq!(fetch(&select), ["a"]);
+        let select = Select::from("event").is_after("day", "2020-01-01").unwrap();
+        assert_eq!(fetch(&select), ["b", "c", "d"]);
+        let select = Select::from("event").is_on("time", "2020-01-02").unwrap();
+        assert_eq!(fetch(&select), ["b"]);
+        let select = Select::from("event")
+            .is_after("time", "2020-01-02 12:00")
+            .unwrap();
+        assert_eq!(fetch(&select), ["b", "c", "d"]);
+
+        // Dates and times can be relative to the current ones:
+        let select = Select::from("event").is_after("day", "today-1d").unwrap();
+        assert_eq!(fetch(&select), ["c", "d"]);
+        let select = Select::from("event").is_before("time", "now+1w").unwrap();
+        assert_eq!(fetch(&select), ["a", "b", "c"]);
+        assert!(select.filters[0].is_relative());
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT *
-FROM "penguin"
-WHERE "penguin"."individual_id" IN (
-  SELECT
-    "penguin"."individual_id"
-  FROM "penguin"
-  LEFT JOIN "egg" ON "penguin"."individual_id" = "egg"."individual_id"
-  WHERE "penguin"."individual_id" = {sql_param}
-)
-ORDER BY "penguin"._order ASC"#
-            )
+            resolve_date(&json!("today 1w")).unwrap(),
+            resolve_date(&json!("today+1w")).unwrap()
         );
-        assert_eq!(params, vec![json!("N1")]);
-
-        let (sql, params) = outer_select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert!(resolve_date(&json!("yesterday")).is_err());
+        assert!(resolve_date(&json!("today-12h")).is_err());
         assert_eq!(
-            sql,
-            format!(
-                r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "penguin"."individual_id" IN (
-  SELECT
-    "penguin"."individual_id"
-  FROM "penguin"
-  LEFT JOIN "egg" ON "penguin"."individual_id" = "egg"."individual_id"
-  WHERE "penguin"."individual_id" = {sql_param}
-)"#
-            )
+            resolve_date(&json!("2020-01-02T12:30")).unwrap(),
+            ("2020-01-02 12:30:00".to_string(), false)
         );
-        assert_eq!(params, vec![json!("N1")]);
 
-        // Subquery select, filtered on an integer:
-        let mut inner_select = Select::from("penguin").limit(&0);
-        inner_select.select_table_column("penguin", "sample_number");
-        inner_select.left_join("penguin", "sample_number", "egg", "sample_number");
-        inner_select
-            .table_eq("penguin", "sample_number", &27)
+        // Date filters can be given in URLs:
+        let url = select.to_url("", &Format::Json).unwrap();
+        assert_eq!(url, "/event.json?time=before.now+1w");
+        let query_params = from_value::<QueryParams>(json!({
+            "day": "after.today-7d",
+            "or": "(day.on.2020-01-01,day.on.2999-01-01)"
+        }))
+        .unwrap();
+        let parsed = block_on(Select::from_path_and_query("event", &query_params, &rltbl));
+        assert_eq!(fetch(&parsed), ["d"]);
+
+        // In PostgreSQL, both sides are cast:
+        let (sql, _) = Select::from("event")
+            .is_after("time", "2020-01-02 12:00")
+            .unwrap()
+            .to_sql(&DbKind::Postgres)
             .unwrap();
-        let mut outer_select = Select::from("penguin").limit(&0);
-        outer_select.is_in_subquery("sample_number", &inner_select);
+        assert!(sql.contains(r#"WHERE CAST("time" AS TIMESTAMP) > CAST($1 AS TIMESTAMP)"#));
+    }
 
-        let tables = outer_select.get_tables().into_iter().collect::<Vec<_>>();
-        assert_eq!(tables, vec!["egg", "penguin"]);
+    #[test]
+    fn test_filter_exprs() {
+        let rltbl = memory_demo(10);
+        let kind = rltbl.connection.kind();
+        let mut sql_param_gen = SqlParam::new(&kind);
+        let (p1, p2, p3) = (
+            sql_param_gen.next(),
+            sql_param_gen.next(),
+            sql_param_gen.next(),
+        );
 
-        let (sql, params) = outer_select.to_sql(&rltbl.connection.kind()).unwrap();
+        // Groups of filters are ANDed with the select's other filters:
+        let select = Select::from("penguin")
+            .unlimited()
+            .filters(&vec!["species = Adelie".to_string()])
+            .unwrap()
+            .or_group(&vec![
+                "island = Dream".to_string(),
+                "sample_number > 5".to_string(),
+            ])
+            .unwrap();
+        let (sql, params) = select.to_sql(&kind).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT *
 FROM "penguin"
-WHERE "penguin"."sample_number" IN (
-  SELECT
-    "penguin"."sample_number"
-  FROM "penguin"
-  LEFT JOIN "egg" ON "penguin"."sample_number" = "egg"."sample_number"
-  WHERE "penguin"."sample_number" = {sql_param}
-)
+WHERE "species" = {p1}
+  AND ("island" = {p2} OR "sample_number" > {p3})
 ORDER BY "penguin"._order ASC"#
             )
         );
-        assert_eq!(params, vec![json!(27)]);
-
-        let (sql, params) = outer_select.to_sql_count(&rltbl.connection.kind()).unwrap();
+        assert_eq!(params, vec![json!("Adelie"), json!("Dream"), json!(5)]);
+        let select = Select::from("penguin")
+            .unlimited()
+            .not_group(&vec!["island = Dream".to_string()])
+            .unwrap();
+        let (sql, _) = select.to_sql_count(&kind).unwrap();
         assert_eq!(
             sql,
             format!(
                 r#"SELECT COUNT(1) AS "count"
 FROM "penguin"
-WHERE "penguin"."sample_number" IN (
-  SELECT
-    "penguin"."sample_number"
-  FROM "penguin"
-  LEFT JOIN "egg" ON "penguin"."sample_number" = "egg"."sample_number"
-  WHERE "penguin"."sample_number" = {sql_param}
-)"#
+WHERE NOT (("island" = {p1}))"#
             )
         );
-        assert_eq!(params, vec![json!(27)]);
-    }
 
-    #[test]
-    fn test_filters() {
-        let rltbl = block_on(Relatable::init(
-            &true,
-            Some("build/test_filters.db"),
-            &CachingStrategy::Trigger,
-        ))
+        // Expressions can be nested, and are written in URLs as they are in PostgREST:
+        let url = "or(island.eq.Dream,and(sample_number.gt.5,not.species.like.Adelie*))";
+        let expr = FilterExpr::from_url(url).unwrap();
+        assert_eq!(expr.to_url().unwrap(), url);
+        let (sql, params) = expr.to_sql(&mut SqlParam::new(&kind)).unwrap();
+        assert_eq!(
+            sql,
+            format!(
+                r#"("island" = {p1} OR ("sample_number" > {p2} AND NOT ("species" LIKE {p3})))"#
+            )
+        );
+        assert_eq!(params, vec![json!("Dream"), json!("5"), json!("Adelie%")]);
+        assert!(FilterExpr::from_url("or(island.eq.Dream").is_err());
+        assert!(FilterExpr::from_url("island.equals.Dream").is_err());
+
+        // Values in URLs are interpreted according to the datatypes of their columns:
+        let query_params = from_value::<QueryParams>(json!({
+            "or": "(island.eq.Dream,sample_number.in.(1,2))",
+            "limit": "100",
+        }))
         .unwrap();
-        let mut sql_param_generator = SqlParam::new(&rltbl.connection.kind());
-        let sql_param_1 = sql_param_generator.next();
-        let sql_param_2 = sql_param_generator.next();
-        let is_for_kind = is_clause(&rltbl.connection.kind());
-        let is_not_for_kind = is_not_clause(&rltbl.connection.kind());
-
-        // Test simple string filters
-        for (input_symbol, output_symbol) in [
-            ("~=", "LIKE"),
-            ("=", "="),
-            ("!=", "<>"),
-            (">", ">"),
-            (">=", ">="),
-            ("<", "<"),
-            ("<=", "<="),
-            ("is", &is_for_kind),
-            ("is not", &is_not_for_kind),
-        ] {
-            let select = Select::from("penguin")
-                .limit(&0)
-                .filters(&vec![format!("study_name {input_symbol} FAKE123")])
-                .unwrap();
-            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT *
-FROM "penguin"
-WHERE "study_name" {output_symbol} {sql_param_1}
-ORDER BY "penguin"._order ASC"#
-                )
-            );
-            assert_eq!(params, vec![json!("FAKE123")]);
-
-            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "study_name" {output_symbol} {sql_param_1}"#
-                )
-            );
-            assert_eq!(params, vec![json!("FAKE123")]);
-        }
-
-        // Test simple integer filters
-        for (input_symbol, output_symbol) in [
-            ("=", "="),
-            ("!=", "<>"),
-            (">", ">"),
-            (">=", ">="),
-            ("<", "<"),
-            ("<=", "<="),
-            ("is", &is_for_kind),
-            ("is not", &is_not_for_kind),
-        ] {
-            let select = Select::from("penguin")
-                .limit(&0)
-                .filters(&vec![format!("sample_number {input_symbol} 2")])
-                .unwrap();
-            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT *
-FROM "penguin"
-WHERE "sample_number" {output_symbol} {sql_param_1}
-ORDER BY "penguin"._order ASC"#
-                )
-            );
-            assert_eq!(params, vec![json!(2)]);
-
-            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "sample_number" {output_symbol} {sql_param_1}"#
-                )
-            );
-            assert_eq!(params, vec![json!(2)]);
-        }
-
-        // Test list string filters
-        for (input_symbol, output_symbol) in [("in", "IN"), ("not in", "NOT IN")] {
-            let select = Select::from("penguin")
-                .limit(&0)
-                .filters(&vec![format!(
-                    "study_name {input_symbol} (MIKE123, RICK123)"
-                )])
-                .unwrap();
-            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT *
-FROM "penguin"
-WHERE "study_name" {output_symbol} ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC"#
-                )
-            );
-            assert_eq!(params, vec![json!("MIKE123"), json!("RICK123")]);
-
-            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "study_name" {output_symbol} ({sql_param_1}, {sql_param_2})"#
-                )
-            );
-            assert_eq!(params, vec![json!("MIKE123"), json!("RICK123")]);
-        }
-
-        // Test list integer filters
-        for (input_symbol, output_symbol) in [("in", "IN"), ("not in", "NOT IN")] {
-            let select = Select::from("penguin")
-                .limit(&0)
-                .filters(&vec![format!("sample_number {input_symbol} (1, 2)")])
-                .unwrap();
-            let (sql, params) = select.to_sql(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT *
-FROM "penguin"
-WHERE "sample_number" {output_symbol} ({sql_param_1}, {sql_param_2})
-ORDER BY "penguin"._order ASC"#
-                )
-            );
-            assert_eq!(params, vec![json!(1), json!(2)]);
-
-            let (sql, params) = select.to_sql_count(&rltbl.connection.kind()).unwrap();
-            assert_eq!(
-                sql,
-                format!(
-                    r#"SELECT COUNT(1) AS "count"
-FROM "penguin"
-WHERE "sample_number" {output_symbol} ({sql_param_1}, {sql_param_2})"#
-                )
-            );
-            assert_eq!(params, vec![json!(1), json!(2)]);
-        }
+        let select = block_on(Select::from_path_and_query(
+            "penguin",
+            &query_params,
+            &rltbl,
+        ));
+        assert_eq!(
+            select.to_sql_params(&kind).unwrap(),
+            vec![json!("Dream"), json!(1), json!(2)]
+        );
+        assert_eq!(
+            select.to_url("", &Format::Default).unwrap(),
+            "/penguin?or=(island.eq.Dream,sample_number.in.(1,2))"
+        );
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert!(result.rows.len() > 0);
+        assert!(result.rows.iter().all(|row| {
+            let island = &row.cells.get("island").unwrap().value;
+            let number = &row.cells.get("sample_number").unwrap().value;
+            island == "Dream" || *number == json!(1) || *number == json!(2)
+        }));
     }
 
     #[test]
     fn test_tablesets() {
         let rltbl = block_on(Relatable::init(
             &true,
-            Some("build/test_tablesets.db"),
+            Some(MEMORY_DB),
             &CachingStrategy::None,
         ))
         .unwrap();
@@ -3331,6 +9781,7 @@ WHERE "_id" IN (
   FROM "A"
   LEFT JOIN "B" ON "A"."a" = "B"."a"
   WHERE "B"."b" = {sql_param}
+  ORDER BY "A"._order ASC, "B"._id ASC
 )
 ORDER BY "A_default_view"._order ASC
 LIMIT 100"#
@@ -3374,6 +9825,7 @@ WHERE "_id" IN (
   FROM "B"
   LEFT JOIN "B2C" ON "B"."b" = "B2C"."b"
   WHERE "B"."b" = {sql_param}
+  ORDER BY "B"._order ASC, "B2C"._id ASC
 )
 ORDER BY "B2C"._order ASC
 LIMIT 100"#
@@ -3417,6 +9869,7 @@ WHERE "_id" IN (
   FROM "B"
   LEFT JOIN "D" ON "B"."b" = "D"."b"
   WHERE "B"."b" = {sql_param}
+  ORDER BY "B"._order ASC, "D"._id ASC
 )
 ORDER BY "D"._order ASC
 LIMIT 100"#
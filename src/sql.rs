@@ -10,21 +10,30 @@
 ////////////////////////////////////
 use crate as rltbl;
 use rltbl::{
-    core::{self, RelatableError, NEW_ORDER_MULTIPLIER},
-    table::{Column, Table},
+    core::{self, ChangeCapture, ChangeCaptureMode, RelatableError, NEW_ORDER_MULTIPLIER},
+    table::{Column, Partition, Structure, Table},
 };
 
 ////////////////////////////////////
 // External imports
 ////////////////////////////////////
 use anyhow::Result;
-use async_std::task::block_on;
+use base64::prelude::{Engine as _, BASE64_STANDARD};
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{value::MapDeserializer, DeserializeOwned, IntoDeserializer, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 use serde_json::{json, Map as JsonMap, Value as JsonValue};
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::Display,
+    future::Future,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
 
 ////////////////////////////////////
 // Database-driver-specific imports
@@ -32,15 +41,21 @@ use std::{fmt::Display, str::FromStr};
 #[cfg(feature = "rusqlite")]
 use rusqlite;
 
+#[cfg(feature = "rusqlite")]
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "sqlx")]
+use async_std::task::block_on;
+
 #[cfg(feature = "sqlx")]
 use bigdecimal::{BigDecimal, ToPrimitive};
 
 #[cfg(feature = "sqlx")]
 use sqlx::{
-    any::{install_default_drivers, Any, AnyArguments, AnyRow},
+    any::{install_default_drivers, Any, AnyArguments, AnyPoolOptions, AnyRow},
     postgres::{PgArguments, PgConnectOptions, PgPool, PgPoolOptions, PgRow, Postgres},
     query::Query,
-    Acquire as _, AnyPool, Column as _, Row as _, Transaction, TypeInfo as _,
+    Acquire as _, AnyPool, Column as _, Row as _, Transaction, TypeInfo as _, ValueRef as _,
 };
 
 /// A 'simple' database name
@@ -54,6 +69,14 @@ lazy_static! {
 /// Maximum number of database connections.
 pub static MAX_DB_CONNECTIONS: u32 = 5;
 
+/// The connection string that requests a private, in-memory SQLite database
+pub static MEMORY_DB: &str = ":memory:";
+
+lazy_static! {
+    /// Counter used to give each in-memory database a distinct shared-cache name
+    static ref MEMORY_DB_COUNTER: AtomicUsize = AtomicUsize::new(0);
+}
+
 /// The [maximum number of parameters](https://www.sqlite.org/limits.html#max_variable_number)
 /// that can be bound to a SQLite query
 pub static MAX_PARAMS_SQLITE: usize = 32766;
@@ -139,6 +162,51 @@ pub enum DbKind {
     Sqlite,
 }
 
+/// The isolation level of a transaction (see [DbConnection::begin_with()]). PostgreSQL's default
+/// is [IsolationLevel::ReadCommitted]. SQLite has only one writer at a time, and its
+/// transactions are always serializable, whatever level is asked for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    /// Transactions that succeed have the same effect as they would have had if they had been
+    /// run one at a time. In PostgreSQL, a transaction that cannot be serialized fails, and
+    /// should be retried (see [is_serialization_failure()]).
+    Serializable,
+}
+
+impl Display for IsolationLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IsolationLevel::ReadCommitted => write!(f, "read committed"),
+            IsolationLevel::RepeatableRead => write!(f, "repeatable read"),
+            IsolationLevel::Serializable => write!(f, "serializable"),
+        }
+    }
+}
+
+impl FromStr for IsolationLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(level: &str) -> Result<Self> {
+        match level.to_lowercase().replace(['_', '-'], " ").as_str() {
+            "read committed" => Ok(IsolationLevel::ReadCommitted),
+            "repeatable read" => Ok(IsolationLevel::RepeatableRead),
+            "serializable" => Ok(IsolationLevel::Serializable),
+            _ => Err(RelatableError::InputError(format!(
+                "Unrecognized isolation level: '{level}'. Supported isolation levels are: \
+                 'read committed', 'repeatable read', and 'serializable'"
+            ))
+            .into()),
+        }
+    }
+}
+
+/// The most times that an operation whose transaction could not be serialized is run again
+/// (see [is_serialization_failure()])
+pub static SERIALIZATION_RETRIES: usize = 5;
+
 /// Used to generate database-specific parameter placeholder strings for binding to SQL statements
 #[derive(Clone, Copy, Debug)]
 pub struct SqlParam {
@@ -194,7 +262,7 @@ impl SqlParam {
 
 /// Represents a database connection pool
 #[cfg(feature = "sqlx")]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum DbPool {
     Sqlite(AnyPool),
     Postgres(PgPool),
@@ -213,8 +281,11 @@ pub enum DbConnection {
     #[cfg(feature = "sqlx")]
     Sqlx(DbPool, DbKind),
 
+    /// The path (or URI) of the database and, for in-memory databases, a connection that is
+    /// held open for as long as any clone of this connection exists, since SQLite discards an
+    /// in-memory database as soon as its last connection is closed.
     #[cfg(feature = "rusqlite")]
-    Rusqlite(String),
+    Rusqlite(String, Option<Arc<Mutex<rusqlite::Connection>>>),
 }
 
 impl DbConnection {
@@ -225,7 +296,7 @@ impl DbConnection {
             #[cfg(feature = "sqlx")]
             DbConnection::Sqlx(_, kind) => *kind,
             #[cfg(feature = "rusqlite")]
-            DbConnection::Rusqlite(_) => DbKind::Sqlite,
+            DbConnection::Rusqlite(..) => DbKind::Sqlite,
         }
     }
 
@@ -264,24 +335,42 @@ impl DbConnection {
                 // suppress the compiler warnings about the unused rusqlite connection.
                 #[allow(unused_variables)]
                 #[cfg(feature = "rusqlite")]
-                let tuple = (
-                    DbConnection::Rusqlite(database.to_string()),
-                    Some(DbActiveConnection::Rusqlite(rusqlite::Connection::open(
-                        database,
-                    )?)),
-                );
+                let tuple = match is_memory_db(database) {
+                    true => {
+                        // Every connection that is opened with ":memory:" gets its own, empty
+                        // database, so instead we use a uniquely named, shared-cache, in-memory
+                        // database that all of our reconnections will be able to see:
+                        let uri = format!("file:{}?mode=memory&cache=shared", memory_db_name());
+                        let keeper = open_rusqlite(&uri)?;
+                        (
+                            DbConnection::Rusqlite(uri.clone(), Some(Arc::new(Mutex::new(keeper)))),
+                            Some(DbActiveConnection::Rusqlite(open_rusqlite(&uri)?)),
+                        )
+                    }
+                    false => (
+                        DbConnection::Rusqlite(database.to_string(), None),
+                        Some(DbActiveConnection::Rusqlite(open_rusqlite(database)?)),
+                    ),
+                };
 
                 #[cfg(feature = "sqlx")]
                 let tuple = {
-                    let url = {
-                        if database.starts_with("sqlite://") {
-                            database.to_string()
-                        } else {
-                            format!("sqlite://{database}?mode=rwc")
+                    install_default_drivers();
+                    #[cfg(feature = "rusqlite")]
+                    add_sqlx_sqlite_functions()?;
+                    let pool = match is_memory_db(database) {
+                        true => connect_sqlx_memory_db(&memory_db_name()).await?,
+                        false => {
+                            let url = {
+                                if database.starts_with("sqlite://") {
+                                    database.to_string()
+                                } else {
+                                    format!("sqlite://{database}?mode=rwc")
+                                }
+                            };
+                            AnyPool::connect(&url).await?
                         }
                     };
-                    install_default_drivers();
-                    let pool = AnyPool::connect(&url).await?;
                     let connection = DbConnection::Sqlx(DbPool::Sqlite(pool), DbKind::Sqlite);
                     (connection, None)
                 };
@@ -291,6 +380,68 @@ impl DbConnection {
         }
     }
 
+    /// Returns a new connection to the same database that can be used independently of this one,
+    /// for instance by another server worker. Connection pools are shared with the new connection.
+    pub fn try_clone(&self) -> Result<Self> {
+        tracing::trace!("DbConnection::try_clone()");
+        match self {
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(pool, kind) => Ok(DbConnection::Sqlx(pool.clone(), *kind)),
+            #[cfg(feature = "rusqlite")]
+            DbConnection::Rusqlite(path, keeper) => {
+                // Make sure that the database can still be opened:
+                open_rusqlite(path)?;
+                Ok(DbConnection::Rusqlite(path.clone(), keeper.clone()))
+            }
+        }
+    }
+
+    /// Copy the current database into a new, in-memory SQLite database (see [MEMORY_DB]), and
+    /// return a connection to the copy. Only SQLite databases are supported.
+    pub async fn copy_to_memory(&self) -> Result<Self> {
+        tracing::trace!("DbConnection::copy_to_memory()");
+        match self {
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(DbPool::Sqlite(pool), _) => {
+                // The copy is connected to first, so that it still exists once it has been
+                // written to, and closed, by VACUUM INTO:
+                let name = memory_db_name();
+                let copy = connect_sqlx_memory_db(&name).await?;
+                sqlx::query(&format!(
+                    "VACUUM INTO 'file:{name}?mode=memory&cache=shared'"
+                ))
+                .execute(pool)
+                .await?;
+                Ok(DbConnection::Sqlx(DbPool::Sqlite(copy), DbKind::Sqlite))
+            }
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(DbPool::Postgres(_), _) => Err(RelatableError::InputError(
+                "Only SQLite databases can be copied into memory".to_string(),
+            )
+            .into()),
+            #[cfg(feature = "rusqlite")]
+            DbConnection::Rusqlite(path, _) => {
+                let source = open_rusqlite(path)?;
+                let (copy, target) = DbConnection::connect(MEMORY_DB).await?;
+                let mut target = match target {
+                    Some(DbActiveConnection::Rusqlite(target)) => target,
+                    _ => {
+                        return Err(RelatableError::DataError(
+                            "Unable to connect to a new in-memory database".to_string(),
+                        )
+                        .into())
+                    }
+                };
+                rusqlite::backup::Backup::new(&source, &mut target)?.run_to_completion(
+                    1000,
+                    std::time::Duration::ZERO,
+                    None,
+                )?;
+                Ok(copy)
+            }
+        }
+    }
+
     /// Reconnect to the current database
     pub fn reconnect(&self) -> Result<Option<DbActiveConnection>> {
         tracing::trace!("DbConnection::reconnect()");
@@ -298,10 +449,47 @@ impl DbConnection {
             #[cfg(feature = "sqlx")]
             DbConnection::Sqlx(_, _) => Ok(None),
             #[cfg(feature = "rusqlite")]
-            DbConnection::Rusqlite(path) => Ok(Some(DbActiveConnection::Rusqlite(
-                rusqlite::Connection::open(path)?,
-            ))),
+            DbConnection::Rusqlite(path, _) => {
+                Ok(Some(DbActiveConnection::Rusqlite(open_rusqlite(path)?)))
+            }
+        }
+    }
+
+    /// Close the pool of connections to the database, if there is one, waiting for the
+    /// connections that are in use to be returned to it. Other connections are opened as needed,
+    /// and closed once they are no longer used, so there is nothing else to close.
+    pub async fn close(&self) {
+        tracing::trace!("DbConnection::close()");
+        match self {
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(db_pool, _) => match db_pool {
+                DbPool::Sqlite(pool) => pool.close().await,
+                DbPool::Postgres(pool) => pool.close().await,
+            },
+            #[cfg(feature = "rusqlite")]
+            DbConnection::Rusqlite(..) => (),
+        }
+    }
+
+    /// Begin a transaction with the given isolation level. Only PostgreSQL transactions are
+    /// affected, since SQLite transactions are always serializable (see [IsolationLevel]).
+    pub async fn begin_with<'a>(
+        &self,
+        conn: &'a mut Option<DbActiveConnection>,
+        level: IsolationLevel,
+    ) -> Result<DbTransaction<'a>> {
+        tracing::trace!("DbConnection::begin_with({self:?}, {conn:?}, {level:?})");
+        let mut tx = self.begin(conn).await?;
+        if tx.kind() == DbKind::Postgres {
+            tx.query(
+                &format!(
+                    "SET TRANSACTION ISOLATION LEVEL {}",
+                    level.to_string().to_uppercase()
+                ),
+                None,
+            )?;
         }
+        Ok(tx)
     }
 
     /// Begin a transaction
@@ -323,7 +511,7 @@ impl DbConnection {
                 }
             },
             #[cfg(feature = "rusqlite")]
-            DbConnection::Rusqlite(_) => match conn {
+            DbConnection::Rusqlite(..) => match conn {
                 None => {
                     return Err(RelatableError::InputError(
                         "Can't begin Rusqlite transaction: No connection provided".to_string(),
@@ -343,37 +531,139 @@ impl DbConnection {
     /// statements should be limited to those that will return a sane number of rows.
     pub async fn query(&self, statement: &str, params: Option<&JsonValue>) -> Result<Vec<JsonRow>> {
         tracing::trace!("DbConnection::query({self:?}, {statement}, {params:?})");
+        let (rows, _) = self.query_with_types(statement, params).await?;
+        Ok(rows)
+    }
+
+    /// Like [DbConnection::query()], but also return a map from each column in the result to
+    /// the SQL type reported for it by the database driver.
+    pub async fn query_with_types(
+        &self,
+        statement: &str,
+        params: Option<&JsonValue>,
+    ) -> Result<(Vec<JsonRow>, IndexMap<String, String>)> {
+        tracing::trace!("DbConnection::query_with_types({self:?}, {statement}, {params:?})");
+        self.query_with_types_and_timeout(statement, params, None)
+            .await
+    }
+
+    /// Like [DbConnection::query()], but if the statement runs for longer than the given timeout,
+    /// if any, it is cancelled and a [RelatableError::TimeoutError] is returned.
+    pub async fn query_with_timeout(
+        &self,
+        statement: &str,
+        params: Option<&JsonValue>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<JsonRow>> {
+        tracing::trace!(
+            "DbConnection::query_with_timeout({self:?}, {statement}, {params:?}, {timeout:?})"
+        );
+        let (rows, _) = self
+            .query_with_types_and_timeout(statement, params, timeout)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Like [DbConnection::query_with_types()], but if the statement runs for longer than the
+    /// given timeout, if any, it is cancelled and a [RelatableError::TimeoutError] is returned.
+    /// PostgreSQL cancels the statement itself, using `statement_timeout`, while a statement run
+    /// in SQLite through rusqlite is interrupted once the timeout has passed, and one run through
+    /// sqlx is abandoned.
+    pub async fn query_with_types_and_timeout(
+        &self,
+        statement: &str,
+        params: Option<&JsonValue>,
+        timeout: Option<Duration>,
+    ) -> Result<(Vec<JsonRow>, IndexMap<String, String>)> {
+        tracing::trace!(
+            "DbConnection::query_with_types_and_timeout({self:?}, {statement}, {params:?}, \
+             {timeout:?})"
+        );
         if !valid_params(params) {
             tracing::warn!("Invalid parameter argument");
-            return Ok(vec![]);
+            return Ok((vec![], IndexMap::new()));
         }
         match self {
             #[cfg(feature = "sqlx")]
             DbConnection::Sqlx(db_pool, _) => match db_pool {
                 DbPool::Sqlite(pool) => {
                     let query = prepare_sqlx_sqlite_query(&statement, params)?;
+                    let sqlx_rows = match timeout {
+                        None => query.fetch_all(pool).await?,
+                        Some(timeout) => async_std::future::timeout(timeout, query.fetch_all(pool))
+                            .await
+                            .map_err(|_| timeout_error(timeout))??,
+                    };
+                    let types = get_sqlx_column_types(&sqlx_rows);
                     let mut rows = vec![];
-                    for row in query.fetch_all(pool).await? {
+                    for row in sqlx_rows {
                         rows.push(JsonRow::try_from(row)?);
                     }
-                    Ok(rows)
+                    Ok((rows, types))
                 }
                 DbPool::Postgres(pool) => {
                     let query = prepare_sqlx_pg_query(&statement, params)?;
+                    let sqlx_rows = match timeout {
+                        None => query.fetch_all(pool).await?,
+                        Some(timeout) => {
+                            // The timeout is set for the statement's transaction only:
+                            let mut tx = pool.begin().await?;
+                            sqlx::query::<Postgres>(&format!(
+                                "SET LOCAL statement_timeout = {}",
+                                timeout.as_millis()
+                            ))
+                            .execute(&mut *tx)
+                            .await?;
+                            let sqlx_rows = query.fetch_all(&mut *tx).await.map_err(|error| {
+                                match error.as_database_error().and_then(|e| e.code()) {
+                                    // The code of the error raised when a query is cancelled:
+                                    Some(code) if code == "57014" => timeout_error(timeout),
+                                    _ => error.into(),
+                                }
+                            })?;
+                            tx.commit().await?;
+                            sqlx_rows
+                        }
+                    };
+                    let types = get_sqlx_column_types(&sqlx_rows);
                     let mut rows = vec![];
-                    for row in query.fetch_all(pool).await? {
+                    for row in sqlx_rows {
                         rows.push(JsonRow::try_from(row)?);
                     }
-                    Ok(rows)
+                    Ok((rows, types))
                 }
             },
             #[cfg(feature = "rusqlite")]
-            DbConnection::Rusqlite(path) => {
+            DbConnection::Rusqlite(path, _) => {
                 let conn = self.reconnect()?;
                 match conn {
                     Some(DbActiveConnection::Rusqlite(conn)) => {
                         let mut stmt = conn.prepare(&statement)?;
-                        submit_rusqlite_statement(&mut stmt, params)
+                        let Some(timeout) = timeout else {
+                            return submit_rusqlite_statement(&mut stmt, params);
+                        };
+                        // Interrupt the statement if it has not finished by the time that the
+                        // timeout has passed, which is signalled by the sender being dropped:
+                        let handle = conn.get_interrupt_handle();
+                        let (sender, receiver) = std::sync::mpsc::channel::<()>();
+                        let watchdog = std::thread::spawn(move || {
+                            if let Err(std::sync::mpsc::RecvTimeoutError::Timeout) =
+                                receiver.recv_timeout(timeout)
+                            {
+                                handle.interrupt();
+                            }
+                        });
+                        let result = submit_rusqlite_statement(&mut stmt, params);
+                        drop(sender);
+                        let _ = watchdog.join();
+                        result.map_err(|error| match error.downcast_ref::<rusqlite::Error>() {
+                            Some(rusqlite::Error::SqliteFailure(failure, _))
+                                if failure.code == rusqlite::ErrorCode::OperationInterrupted =>
+                            {
+                                timeout_error(timeout)
+                            }
+                            _ => error,
+                        })
                     }
                     None => Err(RelatableError::DataError(format!(
                         "Unable to connect to the db at '{path}'"
@@ -418,20 +708,59 @@ impl DbConnection {
         strategy: &CachingStrategy,
     ) -> Result<Vec<JsonRow>> {
         tracing::trace!("cache({sql}, {params:?}, {strategy:?})");
+        self.cache_with_timeout(sql, params, tables, strategy, None)
+            .await
+    }
+
+    /// Attempt to use the cache to query, as [DbConnection::cache()] does, but if the query has to
+    /// be run and it runs for longer than the given timeout, if any, return a
+    /// [RelatableError::TimeoutError] (see [DbConnection::query_with_timeout()]).
+    pub async fn cache_with_timeout(
+        &self,
+        sql: &str,
+        params: Option<&JsonValue>,
+        tables: &Vec<String>,
+        strategy: &CachingStrategy,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<JsonRow>> {
+        tracing::trace!("cache_with_timeout({sql}, {params:?}, {strategy:?}, {timeout:?})");
 
         // Do not cache queries to these special tables,
         // because change to them are not recorded in the usual way.
         for t in vec!["message", "history", "change", "user"] {
             if tables.contains(&t.to_string()) {
-                return self.query(&sql, params).await;
+                return self.query_with_timeout(sql, params, timeout).await;
             }
         }
+        self.cache_with(
+            sql,
+            params,
+            tables,
+            strategy,
+            self.query_with_timeout(sql, params, timeout),
+        )
+        .await
+    }
+
+    /// Look up the rows cached under the given statement and parameters for the given tables,
+    /// using the given caching strategy. On a cache miss, await the given future to compute the
+    /// rows and add them to the cache, where they remain until one of the tables is changed.
+    pub async fn cache_with(
+        &self,
+        statement: &str,
+        params: Option<&JsonValue>,
+        tables: &Vec<String>,
+        strategy: &CachingStrategy,
+        compute: impl Future<Output = Result<Vec<JsonRow>>>,
+    ) -> Result<Vec<JsonRow>> {
+        tracing::trace!("cache_with({statement}, {params:?}, {tables:?}, {strategy:?})");
 
         async fn _cache(
             conn: &DbConnection,
             tables: &Vec<String>,
             sql: &str,
             params: Option<&JsonValue>,
+            compute: impl Future<Output = Result<Vec<JsonRow>>>,
         ) -> Result<Vec<JsonRow>> {
             let tables = tables
                 .iter()
@@ -487,7 +816,7 @@ impl DbConnection {
                 }
                 None => {
                     tracing::debug!("Cache miss for tables {tables}");
-                    let json_rows = conn.query(sql, params).await?;
+                    let json_rows = compute.await?;
                     let json_rows_content = json_rows
                         .iter()
                         .map(|r| r.content.clone())
@@ -526,23 +855,11 @@ impl DbConnection {
         }
 
         match strategy {
-            CachingStrategy::None => self.query(sql, params).await,
+            CachingStrategy::None => compute.await,
             CachingStrategy::TruncateAll | CachingStrategy::Truncate | CachingStrategy::Trigger => {
-                _cache(self, tables, sql, params).await
+                _cache(self, tables, statement, params, compute).await
             }
             CachingStrategy::Memory(cache_size) => {
-                let mut cache = core::CACHE.lock().expect("Could not lock cache");
-                let keys = cache.keys().map(|key| key.clone()).collect::<Vec<_>>();
-
-                for (i, key) in keys.iter().enumerate().rev() {
-                    if i >= *cache_size {
-                        tracing::debug!("Removing {key:?} ({i}th entry) from cache");
-                        cache.remove(&key);
-                    } else {
-                        break;
-                    }
-                }
-
                 let tables = tables
                     .iter()
                     .map(|t| json!(t).to_string())
@@ -550,29 +867,33 @@ impl DbConnection {
                     .join(", ");
                 let mem_key = MemoryCacheKey {
                     tables: tables.to_string(),
-                    statement: sql.to_string(),
+                    statement: statement.to_string(),
                     parameters: format!("{params:?}"),
                 };
-                match cache.get(&mem_key) {
-                    Some(json_rows) => {
-                        tracing::debug!("Cache hit for tables {tables}");
-                        Ok(json_rows.to_vec())
+                {
+                    let mut cache = core::CACHE.lock().expect("Could not lock cache");
+                    let keys = cache.keys().cloned().collect::<Vec<_>>();
+                    for (i, key) in keys.iter().enumerate().rev() {
+                        if i >= *cache_size {
+                            tracing::debug!("Removing {key:?} ({i}th entry) from cache");
+                            cache.remove(key);
+                        } else {
+                            break;
+                        }
                     }
-                    None => {
-                        tracing::debug!("Cache miss for tables {tables}");
-                        // Why is a block_on() call needed here but not above?
-                        let json_rows = block_on(self.query(sql, params))?;
-                        cache.insert(
-                            MemoryCacheKey {
-                                tables: tables.to_string(),
-                                statement: sql.to_string(),
-                                parameters: format!("{params:?}"),
-                            },
-                            json_rows.to_vec(),
-                        );
-                        Ok(json_rows)
+                    if let Some(json_rows) = cache.get(&mem_key) {
+                        tracing::debug!("Cache hit for tables {tables}");
+                        return Ok(json_rows.to_vec());
                     }
                 }
+
+                // The cache must not be locked while the rows are computed, since computing them
+                // may itself involve the cache:
+                tracing::debug!("Cache miss for tables {tables}");
+                let json_rows = compute.await?;
+                let mut cache = core::CACHE.lock().expect("Could not lock cache");
+                cache.insert(mem_key, json_rows.to_vec());
+                Ok(json_rows)
             }
         }
     }
@@ -638,6 +959,22 @@ impl DbTransaction<'_> {
         Ok(())
     }
 
+    /// Set whether the database rejects the statements that are run from now on in this
+    /// transaction that would change it. In PostgreSQL, the transaction is made read-only, which
+    /// must be done before any other statement is run in it, and lasts until it ends. In SQLite,
+    /// the connection is made query-only, which lasts, even once the transaction has ended, until
+    /// it is set otherwise, so a connection that may be reused must be set back.
+    pub fn set_read_only(&mut self, read_only: bool) -> Result<()> {
+        tracing::trace!("DbTransaction::set_read_only({self:?}, {read_only})");
+        match (self.kind(), read_only) {
+            (DbKind::Postgres, true) => self.query("SET TRANSACTION READ ONLY", None)?,
+            (DbKind::Postgres, false) => vec![],
+            (DbKind::Sqlite, true) => self.query("PRAGMA query_only = ON", None)?,
+            (DbKind::Sqlite, false) => self.query("PRAGMA query_only = OFF", None)?,
+        };
+        Ok(())
+    }
+
     /// Given a generic SQL string with placeholders and a list of parameters to interpolate into
     /// the string, return a vector of [JsonRow]s. Note that since this returns a vector,
     /// statements should be limited to those that will return a sane number of rows.
@@ -670,7 +1007,8 @@ impl DbTransaction<'_> {
             #[cfg(feature = "rusqlite")]
             DbTransaction::Rusqlite(tx) => {
                 let mut stmt = tx.prepare(&statement)?;
-                submit_rusqlite_statement(&mut stmt, params)
+                let (rows, _) = submit_rusqlite_statement(&mut stmt, params)?;
+                Ok(rows)
             }
         }
     }
@@ -705,6 +1043,119 @@ impl DbTransaction<'_> {
 // Database-specific utilities and functions
 ///////////////////////////////////////////////////////////////////////////////
 
+/// Returns true if the given connection string requests an in-memory SQLite database, which is
+/// never written to disk and which disappears once the last [DbConnection] to it is dropped.
+pub fn is_memory_db(database: &str) -> bool {
+    database == MEMORY_DB
+}
+
+/// Connect to the shared-cache, in-memory SQLite database with the given name using sqlx. Such a
+/// database lasts only as long as at least one connection to it is open, so the pool never
+/// closes its idle connections.
+#[cfg(feature = "sqlx")]
+async fn connect_sqlx_memory_db(name: &str) -> Result<AnyPool> {
+    let url = format!("sqlite:file:{name}?mode=memory&cache=shared");
+    Ok(AnyPoolOptions::new()
+        .min_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect(&url)
+        .await?)
+}
+
+/// Returns a name for a new in-memory database that is distinct from the names of all of the
+/// other in-memory databases in this process
+fn memory_db_name() -> String {
+    format!(
+        "rltbl-memory-{}-{}",
+        std::process::id(),
+        MEMORY_DB_COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+/// Open a rusqlite connection to the SQLite database at the given path, registering the
+/// functions that rltbl needs (see [add_rusqlite_functions()]).
+#[cfg(feature = "rusqlite")]
+pub fn open_rusqlite(path: &str) -> Result<rusqlite::Connection> {
+    let connection = rusqlite::Connection::open(path)?;
+    add_rusqlite_functions(&connection)?;
+    Ok(connection)
+}
+
+/// Register, on the given rusqlite connection, the `regexp()` function that SQLite uses to
+/// evaluate `X REGEXP Y` but does not itself define. The function is true if the (regular
+/// expression) pattern Y matches the value X, which is matched as text, and NULL if X is NULL.
+/// The `natural_key()` function, which orders the columns whose
+/// [Collation](crate::table::Collation) is natural, is registered as well.
+#[cfg(feature = "rusqlite")]
+fn add_rusqlite_functions(connection: &rusqlite::Connection) -> rusqlite::Result<()> {
+    use rusqlite::{functions::FunctionFlags, types::ValueRef};
+    use std::sync::Arc;
+
+    connection.create_scalar_function(
+        "regexp",
+        2,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |context| {
+            // Compile each pattern only once per statement:
+            let regex: Arc<Regex> = context.get_or_create_aux(0, |pattern| {
+                Regex::new(pattern.as_str()?)
+                    .map_err(|error| rusqlite::Error::UserFunctionError(error.into()))
+            })?;
+            let text = match context.get_raw(1) {
+                ValueRef::Null | ValueRef::Blob(_) => return Ok(None),
+                ValueRef::Integer(value) => value.to_string(),
+                ValueRef::Real(value) => value.to_string(),
+                ValueRef::Text(value) => String::from_utf8_lossy(value).to_string(),
+            };
+            Ok(Some(regex.is_match(&text)))
+        },
+    )?;
+    connection.create_scalar_function(
+        "natural_key",
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |context| {
+            let text = match context.get_raw(0) {
+                ValueRef::Null | ValueRef::Blob(_) => return Ok(None),
+                ValueRef::Integer(value) => value.to_string(),
+                ValueRef::Real(value) => value.to_string(),
+                ValueRef::Text(value) => String::from_utf8_lossy(value).to_string(),
+            };
+            Ok(Some(rltbl::table::Collation::natural_key(&text)))
+        },
+    )
+}
+
+/// Register the functions that rltbl needs (see [add_rusqlite_functions()]) on every SQLite
+/// connection that sqlx opens from now on. The sqlx driver offers no way to define functions,
+/// but it links the same SQLite library as rusqlite does, so the functions are added by an
+/// automatic extension of that library, which SQLite runs whenever a connection is opened.
+/// When rltbl is built without the rusqlite feature, the SQLite connections that sqlx opens
+/// have none of these functions.
+#[cfg(all(feature = "sqlx", feature = "rusqlite"))]
+fn add_sqlx_sqlite_functions() -> Result<()> {
+    use rusqlite::{
+        auto_extension::{init_auto_extension, register_auto_extension},
+        ffi,
+    };
+    use std::os::raw::{c_char, c_int};
+
+    unsafe extern "C" fn init(
+        db: *mut ffi::sqlite3,
+        pz_err_msg: *mut *mut c_char,
+        _: *const ffi::sqlite3_api_routines,
+    ) -> c_int {
+        init_auto_extension(db, pz_err_msg, |connection| {
+            add_rusqlite_functions(&connection)
+        })
+    }
+
+    // SQLite ignores an extension that has already been registered:
+    unsafe { register_auto_extension(init)? };
+    Ok(())
+}
+
 /// Given a SQL string (whose syntax is appropriate for the given database kind) that may
 /// include placeholders representing bound parameters, and (optionally) a vector with the
 /// parameter values corresponding to each placeholder, combine this information into an
@@ -744,8 +1195,14 @@ pub fn interpolate_sql(sql: &str, params: Option<&JsonValue>, kind: &DbKind) ->
                     ))
                     .into())
                 }
-                Some(param) => {
-                    match param {
+                Some(param) => match get_binary_param(param) {
+                    Some(bytes) => match kind {
+                        DbKind::Sqlite => final_sql.push_str(&format!("X'{}'", hex::encode(bytes))),
+                        DbKind::Postgres => {
+                            final_sql.push_str(&format!(r"'\x{}'", hex::encode(bytes)))
+                        }
+                    },
+                    None => match param {
                         JsonValue::String(param) => final_sql.push_str(&format!("'{param}'")),
                         JsonValue::Number(param) => final_sql.push_str(&format!("{param}")),
                         JsonValue::Bool(param) => final_sql.push_str(&param.to_string()),
@@ -754,8 +1211,8 @@ pub fn interpolate_sql(sql: &str, params: Option<&JsonValue>, kind: &DbKind) ->
                         // We should never get a NULL in the parameter list, actually, but we
                         // handle it anyway.
                         JsonValue::Null => final_sql.push_str(&"NULL".to_string()),
-                    };
-                }
+                    },
+                },
             };
             param_index += 1;
         } else {
@@ -767,6 +1224,54 @@ pub fn interpolate_sql(sql: &str, params: Option<&JsonValue>, kind: &DbKind) ->
     Ok(final_sql)
 }
 
+/// Helper function to determine whether the given SQL is a single query, which can be run as a
+/// subquery: it must begin with SELECT, WITH, or VALUES, and, outside of its strings and quoted
+/// names, must not contain semicolons, comments, or unbalanced parentheses. Whether the query is
+/// read-only is left to the database to enforce (see [DbTransaction::set_read_only()]).
+pub fn is_single_query(sql: &str) -> Result<(), String> {
+    tracing::trace!("is_single_query({sql})");
+    // Blank out the strings and quoted names, whose contents are not SQL:
+    let mut unquoted = String::new();
+    let mut quote = None;
+    for c in sql.chars() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => continue,
+            _ => (),
+        };
+        unquoted.push(c);
+    }
+    if quote.is_some() {
+        return Err(format!("Unterminated quotation in '{sql}'"));
+    }
+    for forbidden in [";", "--", "/*"] {
+        if unquoted.contains(forbidden) {
+            return Err(format!("Forbidden '{forbidden}' in '{sql}'"));
+        }
+    }
+    let mut depth = 0;
+    for c in unquoted.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return Err(format!("Unbalanced parentheses in '{sql}'")),
+            ')' => depth -= 1,
+            _ => (),
+        };
+    }
+    if depth != 0 {
+        return Err(format!("Unbalanced parentheses in '{sql}'"));
+    }
+    let first_word = unquoted
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .find(|word| !word.is_empty())
+        .map(|word| word.to_lowercase());
+    match first_word.as_deref() {
+        Some("select" | "with" | "values") => Ok(()),
+        _ => Err(format!("Not a SELECT query: '{sql}'")),
+    }
+}
+
 /// Helper function to determine whether the given name is 'simple', as defined by
 /// [DB_OBJECT_MATCH_STR]
 pub fn is_simple(db_object_name: &str) -> Result<(), String> {
@@ -800,21 +1305,78 @@ pub fn is_not_clause(db_kind: &DbKind) -> String {
     }
 }
 
+/// The layouts in which the SQL generated for a [Select](crate::select::Select) can be written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SqlFormat {
+    /// Each clause on a line of its own, and each subquery on lines of its own, indented by two
+    /// spaces more than the line that it belongs to
+    #[default]
+    Pretty,
+    /// All on one line, with single spaces between words, e.g., for logs
+    Compact,
+}
+
+/// Write the given SQL, as generated in the [Pretty](SqlFormat::Pretty) format, in the given
+/// format. Whitespace within quoted strings and identifiers is left as it is.
+pub fn format_sql(sql: &str, format: &SqlFormat) -> String {
+    tracing::trace!("format_sql({sql:?}, {format:?})");
+    if *format == SqlFormat::Pretty {
+        return sql
+            .lines()
+            .map(|line| line.trim_end())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+    let mut compact = String::new();
+    let mut quote = None;
+    let mut space = false;
+    for c in sql.chars() {
+        match quote {
+            Some(q) => {
+                compact.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None if c.is_whitespace() => space = true,
+            None => {
+                // Spaces are not written just inside parentheses, nor before commas:
+                if space && !compact.is_empty() && !compact.ends_with('(') && !"),".contains(c) {
+                    compact.push(' ');
+                }
+                space = false;
+                if c == '\'' || c == '"' {
+                    quote = Some(c);
+                }
+                compact.push(c);
+            }
+        }
+    }
+    compact
+}
+
 // TODO (maybe): Possibly define a new enum called DbQuery and save some lines of code by
 // refactoring prepare_sqlx_sqlite_query() and prepare_sqlx_pg_query() into one function that
 // accepts a DbQuery, unless doing that makes things unnecessarily complicated in other ways.
 
 /// Given an SQL string that has been bound to the given parameter vector, construct a database
-/// query and return it.
+/// query and return it. The query is not cached by the connection that runs it, since sqlx would
+/// cache the columns that it returns along with it, which go stale, and cause sqlx to panic, once
+/// another connection adds a column to a table that the query selects all of the columns of.
 #[cfg(feature = "sqlx")]
 pub fn prepare_sqlx_sqlite_query<'a>(
     statement: &'a str,
     params: Option<&'a JsonValue>,
 ) -> Result<Query<'a, Any, AnyArguments<'a>>> {
     tracing::trace!("prepare_sqlx_query({statement}, {params:?})");
-    let mut query = sqlx::query::<Any>(&statement);
+    let mut query = sqlx::query::<Any>(&statement).persistent(false);
     if let Some(params) = params {
         for param in params.as_array().unwrap() {
+            if let Some(bytes) = get_binary_param(param) {
+                query = query.bind(bytes);
+                continue;
+            }
             match param {
                 JsonValue::Number(n) => match n.as_i64() {
                     Some(p) => query = query.bind(p),
@@ -842,6 +1404,10 @@ pub fn prepare_sqlx_pg_query<'a>(
     let mut query = sqlx::query::<Postgres>(&statement);
     if let Some(params) = params {
         for param in params.as_array().unwrap() {
+            if let Some(bytes) = get_binary_param(param) {
+                query = query.bind(bytes);
+                continue;
+            }
             match param {
                 JsonValue::Number(n) => match n.as_i64() {
                     Some(p) => query = query.bind(p),
@@ -858,12 +1424,39 @@ pub fn prepare_sqlx_pg_query<'a>(
     Ok(query)
 }
 
-/// Execute the given rusqlite statement
+/// Whether the given error is the failure of a PostgreSQL transaction that could not be
+/// serialized with the transactions that ran alongside it, or that was chosen to break a
+/// deadlock, so that running it again may succeed.
+#[cfg_attr(not(feature = "sqlx"), allow(unused_variables))]
+pub fn is_serialization_failure(error: &anyhow::Error) -> bool {
+    #[cfg(feature = "sqlx")]
+    if let Some(error) = error.downcast_ref::<sqlx::Error>() {
+        // The codes of serialization_failure and deadlock_detected:
+        return error
+            .as_database_error()
+            .and_then(|error| error.code())
+            .is_some_and(|code| code == "40001" || code == "40P01");
+    }
+    false
+}
+
+/// The error returned when a query runs for longer than the given timeout
+fn timeout_error(timeout: Duration) -> anyhow::Error {
+    RelatableError::TimeoutError(format!(
+        "The query was cancelled after running for longer than {}ms",
+        timeout.as_millis()
+    ))
+    .into()
+}
+
+/// Execute the given rusqlite statement, returning the rows along with the SQL type of each
+/// column. Columns without a declared type (e.g. expressions) are given the storage class of
+/// their first non-null value, if there is one.
 #[cfg(feature = "rusqlite")]
 fn submit_rusqlite_statement(
     stmt: &mut rusqlite::Statement<'_>,
     params: Option<&JsonValue>,
-) -> Result<Vec<JsonRow>> {
+) -> Result<(Vec<JsonRow>, IndexMap<String, String>)> {
     tracing::trace!("submit_rusqlite_statement({stmt:?}, {params:?})");
     let column_names = stmt
         .column_names()
@@ -871,14 +1464,24 @@ fn submit_rusqlite_statement(
         .map(|c| c.to_string())
         .collect::<Vec<_>>();
     let column_names = column_names.iter().map(|c| c.as_str()).collect::<Vec<_>>();
+    let mut types = IndexMap::new();
+    for column in stmt.columns() {
+        if let Some(decl_type) = column.decl_type() {
+            types.insert(column.name().to_string(), decl_type.to_uppercase());
+        }
+    }
 
     if let Some(params) = params {
         for (i, param) in params.as_array().unwrap().iter().enumerate() {
+            // Binding must begin with 1 rather than 0:
+            if let Some(bytes) = get_binary_param(param) {
+                stmt.raw_bind_parameter(i + 1, bytes)?;
+                continue;
+            }
             let param = match param {
                 JsonValue::String(s) => s,
                 _ => &param.to_string(),
             };
-            // Binding must begin with 1 rather than 0:
             stmt.raw_bind_parameter(i + 1, param)?;
         }
     }
@@ -886,9 +1489,59 @@ fn submit_rusqlite_statement(
 
     let mut result = Vec::new();
     while let Some(row) = rows.next()? {
+        for column_name in &column_names {
+            if types.contains_key(*column_name) {
+                continue;
+            }
+            let storage_class = match row.get_ref(*column_name) {
+                Ok(rusqlite::types::ValueRef::Integer(_)) => "INTEGER",
+                Ok(rusqlite::types::ValueRef::Real(_)) => "REAL",
+                Ok(rusqlite::types::ValueRef::Text(_)) => "TEXT",
+                Ok(rusqlite::types::ValueRef::Blob(_)) => "BLOB",
+                _ => continue,
+            };
+            types.insert(column_name.to_string(), storage_class.to_string());
+        }
         result.push(JsonRow::from_rusqlite(&column_names, row));
     }
-    Ok(result)
+
+    // Keep the types in the same order as the columns:
+    let types = column_names
+        .iter()
+        .filter_map(|name| {
+            types
+                .get(*name)
+                .map(|sql_type| (name.to_string(), sql_type.to_string()))
+        })
+        .collect();
+    Ok((result, types))
+}
+
+/// Get the SQL type of each column of the given sqlx rows, as reported by the driver. Columns
+/// that have no declared type, e.g., expressions, are given the type of their first non-null
+/// value, if there is one.
+#[cfg(feature = "sqlx")]
+fn get_sqlx_column_types<R: sqlx::Row>(rows: &[R]) -> IndexMap<String, String>
+where
+    usize: sqlx::ColumnIndex<R>,
+{
+    let Some(first) = rows.first() else {
+        return IndexMap::new();
+    };
+    let mut types = IndexMap::new();
+    for (i, column) in first.columns().iter().enumerate() {
+        let sql_type = match column.type_info().is_null() {
+            false => Some(column.type_info().name().to_uppercase()),
+            true => rows.iter().find_map(|row| match row.try_get_raw(i) {
+                Ok(value) if !value.is_null() => Some(value.type_info().name().to_uppercase()),
+                _ => None,
+            }),
+        };
+        if let Some(sql_type) = sql_type {
+            types.insert(column.name().to_string(), sql_type);
+        }
+    }
+    types
 }
 
 /// Validate that the given parameters are in the form of a JSON Array.
@@ -916,22 +1569,120 @@ fn extract_value(rows: &Vec<JsonRow>) -> Option<JsonValue> {
     }
 }
 
+/// The SQL types that [rltbl](crate) knows how to validate, in their canonical form.
+pub const SQL_TYPES: [&str; 9] = [
+    "TEXT",
+    "INTEGER",
+    "NUMERIC",
+    "BOOLEAN",
+    "DATE",
+    "TIMESTAMP",
+    "UUID",
+    "JSONB",
+    "BLOB",
+];
+
+/// Map the given SQL type, which may be written in the dialect of either SQLite or PostgreSQL,
+/// and may include a length or precision, e.g., `VARCHAR(10)`, to one of the canonical
+/// [SQL_TYPES], or return None if it is not recognized.
+pub fn canonical_sql_type(sql_type: &str) -> Option<&'static str> {
+    let sql_type = sql_type.trim().to_lowercase();
+    let base = match sql_type.split_once('(') {
+        Some((base, _)) => base.trim(),
+        None => sql_type.as_str(),
+    };
+    match base {
+        "text" => Some("TEXT"),
+        "int" | "integer" | "tinyint" | "smallint" | "mediumint" | "bigint" => Some("INTEGER"),
+        "real" | "decimal" | "numeric" | "double precision" => Some("NUMERIC"),
+        "bool" | "boolean" => Some("BOOLEAN"),
+        "date" => Some("DATE"),
+        "datetime" | "timestamptz" => Some("TIMESTAMP"),
+        "uuid" => Some("UUID"),
+        "json" | "jsonb" => Some("JSONB"),
+        "blob" | "bytea" => Some("BLOB"),
+        base if base.starts_with("real")
+            || base.starts_with("numeric")
+            || base.starts_with("decimal") =>
+        {
+            Some("NUMERIC")
+        }
+        base if base.starts_with("varchar") || base.starts_with("character") => Some("TEXT"),
+        base if base.starts_with("timestamp") => Some("TIMESTAMP"),
+        _ => None,
+    }
+}
+
+/// Render the given SQL type in the dialect of the given database. SQLite has no native UUID or
+/// JSON types, so values of those types are stored as text, and PostgreSQL calls binary values
+/// BYTEA. Types that are not one of the canonical [SQL_TYPES] are rendered as given.
+pub fn render_sql_type(sql_type: &str, db_kind: &DbKind) -> String {
+    match (canonical_sql_type(sql_type), db_kind) {
+        (Some("UUID"), DbKind::Sqlite) | (Some("JSONB"), DbKind::Sqlite) => "TEXT".to_string(),
+        (Some("BLOB"), DbKind::Postgres) => "BYTEA".to_string(),
+        (Some(canonical), _) => canonical.to_string(),
+        (None, _) => sql_type.to_string(),
+    }
+}
+
+/// Determine whether every value of the SQL type `inferred` can be stored in a column of the SQL
+/// type `explicit`. Text can be stored as anything and anything can be stored as text, integers
+/// can be stored as numbers, and dates as timestamps.
+pub fn sql_types_compatible(inferred: &str, explicit: &str) -> Result<bool> {
+    let canonical = |sql_type: &str| -> Result<&'static str> {
+        canonical_sql_type(sql_type)
+            .ok_or(RelatableError::InputError(format!("Unsupported SQL type: '{sql_type}'")).into())
+    };
+    Ok(matches!(
+        (canonical(inferred)?, canonical(explicit)?),
+        ("TEXT", _) | (_, "TEXT") | ("INTEGER", "NUMERIC") | ("DATE", "TIMESTAMP")
+    ) || canonical(inferred)? == canonical(explicit)?)
+}
+
 /////////////////
 // Functions for generating DDL
 ////////////////
 
 /// Generate DDL to create the given table in the database. If `force` is set, drop the table
-/// first.
+/// first. The `from()` structure of any column that has a [foreign_key](Column::foreign_key) is
+/// rendered as a FOREIGN KEY constraint, and a unique index is created on the column that it
+/// references, which the database requires of a referenced column. If the table has
+/// [partitions](Table::partitions), they are created along with it: in PostgreSQL, as the
+/// partitions of a table that is partitioned by list, and in SQLite, as separate tables that are
+/// combined by a view with the name of the table (see [generate_sqlite_partitioned_table_ddl()]).
+/// Note that uniqueness is only enforced within each partition of a table. The triggers that
+/// maintain the table's meta columns and clear the cache are added by the given
+/// [ChangeCapture], and it is an error for a table to require triggers for which it has no use.
 pub fn generate_table_ddl(
     table: &Table,
     force: bool,
     db_kind: &DbKind,
     caching_strategy: &CachingStrategy,
+    change_capture: &dyn ChangeCapture,
 ) -> Result<Vec<String>> {
-    tracing::trace!("generate_table_ddl({table:?}, {force}, {db_kind:?}, {caching_strategy:?})");
+    tracing::trace!(
+        "generate_table_ddl({table:?}, {force}, {db_kind:?}, {caching_strategy:?}, \
+         {change_capture:?})"
+    );
+    if let ChangeCaptureMode::WritePath = change_capture.mode() {
+        if table.has_meta && table.has_change_id {
+            return Err(RelatableError::InputError(format!(
+                "Table '{}' cannot have a materialized _change_id column without triggers",
+                table.name
+            ))
+            .into());
+        }
+        if !table.partitions.is_empty() && *db_kind == DbKind::Sqlite {
+            return Err(RelatableError::InputError(format!(
+                "Table '{}' cannot be partitioned in SQLite without triggers",
+                table.name
+            ))
+            .into());
+        }
+    }
     if table.has_meta {
         for (cname, col) in table.columns.iter() {
-            if cname == "_id" || cname == "_order" {
+            if cname == "_id" || cname == "_order" || cname == "_change_id" {
                 return Err(RelatableError::InputError(format!(
                     "column {cname} conflicts with has_meta == {has_meta}",
                     has_meta = table.has_meta,
@@ -948,8 +1699,33 @@ pub fn generate_table_ddl(
             }
         }
     }
+    let partitioned = !table.partitions.is_empty();
+    if partitioned {
+        if let DbKind::Sqlite = db_kind {
+            return generate_sqlite_partitioned_table_ddl(table, force, caching_strategy);
+        }
+    }
 
     let mut ddl = vec![];
+    let mut referenced_columns = vec![];
+    let mut foreign_key_clauses = IndexMap::new();
+    for (cname, col) in table.columns.iter() {
+        if let Some(foreign_key) = &col.foreign_key {
+            let Some(Structure::From(s_table, s_column)) = &col.structure else {
+                return Err(RelatableError::InputError(format!(
+                    "Column '{table}.{cname}' has a foreign key but no from() structure",
+                    table = table.name,
+                ))
+                .into());
+            };
+            let s_table = s_table.as_deref().unwrap_or(&table.name);
+            foreign_key_clauses.insert(cname.to_string(), foreign_key.to_sql(s_table, s_column));
+            if !referenced_columns.contains(&(s_table, s_column)) {
+                referenced_columns.push((s_table, s_column));
+            }
+        }
+    }
+
     let mut column_clauses = vec![];
     for (cname, col) in table.columns.iter() {
         if col.table != table.name {
@@ -959,13 +1735,21 @@ pub fn generate_table_ddl(
             ))
             .into());
         }
-        let sql_type = col.datatype.infer_sql_type(&col.datatype_hierarchy);
+        col.check_sql_type()?;
+        let sql_type = render_sql_type(&col.get_sql_type(), db_kind);
         let clause = format!(
-            r#""{cname}" {sql_type}{unique}"#,
-            unique = match col.unique {
+            r#""{cname}" {sql_type}{unique}{foreign_key}"#,
+            // A column of this table that is referenced by a foreign key must be unique:
+            unique = match (col.unique || referenced_columns.contains(&(&table.name, cname)))
+                && !partitioned
+            {
                 true => " UNIQUE",
                 false => "",
             },
+            foreign_key = match foreign_key_clauses.get(cname) {
+                Some(clause) => format!(" {clause}"),
+                None => "".to_string(),
+            },
         );
         column_clauses.push(clause);
     }
@@ -979,36 +1763,335 @@ pub fn generate_table_ddl(
         }
     }
 
+    // A column of another table that is referenced by a foreign key must already be unique when
+    // this table is created:
+    for (s_table, s_column) in referenced_columns {
+        if s_table != table.name {
+            ddl.push(generate_referenced_index_ddl(s_table, s_column));
+        }
+    }
+
     let mut sql = format!(r#"CREATE TABLE "{}" ( "#, table.name);
     if table.has_meta {
-        sql.push_str(match db_kind {
-            DbKind::Sqlite => {
+        sql.push_str(match (db_kind, partitioned) {
+            (DbKind::Sqlite, _) => {
                 "_id INTEGER PRIMARY KEY AUTOINCREMENT, \
                  _order INTEGER UNIQUE, "
             }
-            DbKind::Postgres => {
+            // The unique constraints of a partitioned table must include the partition column:
+            (DbKind::Postgres, true) => "_id SERIAL, _order BIGINT, ",
+            (DbKind::Postgres, false) => {
                 "_id SERIAL PRIMARY KEY, \
                  _order BIGINT UNIQUE, "
             }
         });
+        if table.has_change_id {
+            sql.push_str("_change_id BIGINT, ");
+        }
+        if table.has_uuid {
+            sql.push_str(&format!(
+                "_uuid TEXT{unique} NOT NULL DEFAULT {}, ",
+                uuid_default_expr(db_kind),
+                unique = if partitioned { "" } else { " UNIQUE" }
+            ));
+        }
     }
     sql.push_str(&format!(" {})", column_clauses.join(", ")));
+    if let Some(partition) = table.partitions.first() {
+        sql.push_str(&format!(r#" PARTITION BY LIST ("{}")"#, partition.column));
+    }
     ddl.push(sql);
-
+    for partition in &table.partitions {
+        ddl.push(format!(
+            r#"CREATE TABLE "{partition}" PARTITION OF "{table}" {values}"#,
+            partition = partition.name,
+            table = table.name,
+            values = match &partition.value {
+                Some(value) => format!("FOR VALUES IN ({})", partition_literal(value)?),
+                None => "DEFAULT".to_string(),
+            }
+        ));
+    }
+
     // Add triggers for metacolumns if they are present:
     if table.has_meta {
-        add_metacolumn_trigger_ddl(&mut ddl, &table.name, db_kind);
+        change_capture.add_metacolumn_ddl(&mut ddl, &table.name, db_kind);
+        if table.has_change_id {
+            ddl.push(format!(
+                r#"CREATE INDEX "{table}__change_id_idx" ON "{table}"("_change_id")"#,
+                table = table.name
+            ));
+            add_change_id_trigger_ddl(&mut ddl, &table.name, force, db_kind);
+        }
     }
 
     // Add triggers for updating the "cache" and "table" tables whenever this table is
     // changed, if the Trigger caching strategy has been specified:
-    if let CachingStrategy::Trigger = caching_strategy {
-        add_caching_trigger_ddl(&mut ddl, &table.name, db_kind);
+    change_capture.add_caching_ddl(&mut ddl, &table.name, db_kind, caching_strategy);
+
+    Ok(ddl)
+}
+
+/// Generate the DDL to create the given partitioned table in SQLite, which has no partitioned
+/// tables of its own. Each partition is created as a separate table, and these are combined by a
+/// view with the name of the table. Triggers on the view route the rows that are inserted into it
+/// to the partitions that they belong in, and update and delete them there, moving a row to
+/// another partition when its value of the partition column changes. If `force` is set, drop the
+/// view and the partitions first.
+fn generate_sqlite_partitioned_table_ddl(
+    table: &Table,
+    force: bool,
+    caching_strategy: &CachingStrategy,
+) -> Result<Vec<String>> {
+    tracing::trace!(
+        "generate_sqlite_partitioned_table_ddl({table:?}, {force}, {caching_strategy:?})"
+    );
+    let mut ddl = vec![];
+    if force {
+        ddl.push(format!(r#"DROP VIEW IF EXISTS "{}""#, table.name));
+    }
+
+    // The partitions have the same columns as the table. The _change_id column of each is kept
+    // up to date through the view, by the trigger on the history table for the table itself:
+    for partition in &table.partitions {
+        let partition_table = Table {
+            name: partition.name.to_string(),
+            columns: table
+                .columns
+                .iter()
+                .map(|(name, column)| {
+                    let column = Column {
+                        table: partition.name.to_string(),
+                        ..column.clone()
+                    };
+                    (name.to_string(), column)
+                })
+                .collect(),
+            has_change_id: false,
+            partitions: vec![],
+            ..table.clone()
+        };
+        ddl.append(&mut generate_table_ddl(
+            &partition_table,
+            force,
+            &DbKind::Sqlite,
+            &CachingStrategy::None,
+            &core::TriggerCapture,
+        )?);
+        if table.has_change_id {
+            ddl.push(format!(
+                r#"ALTER TABLE "{partition}" ADD COLUMN "_change_id" BIGINT"#,
+                partition = partition.name
+            ));
+            ddl.push(format!(
+                r#"CREATE INDEX "{partition}__change_id_idx" ON "{partition}"("_change_id")"#,
+                partition = partition.name
+            ));
+        }
+    }
+
+    let (_, column_list) = sqlite_partition_columns(table);
+    ddl.push(format!(
+        r#"CREATE VIEW "{table}" AS {union}"#,
+        table = table.name,
+        union = table
+            .partitions
+            .iter()
+            .map(|partition| format!(r#"SELECT {column_list} FROM "{}""#, partition.name))
+            .collect::<Vec<_>>()
+            .join(" UNION ALL ")
+    ));
+
+    ddl.append(&mut generate_sqlite_partition_trigger_ddl(
+        table,
+        caching_strategy,
+    )?);
+
+    if table.has_change_id {
+        add_change_id_trigger_ddl(&mut ddl, &table.name, force, &DbKind::Sqlite);
+    }
+    Ok(ddl)
+}
+
+/// Get the columns of the view that combines the partitions of the given partitioned table in
+/// SQLite (see [generate_sqlite_partitioned_table_ddl()]), and their quoted, comma-separated list.
+fn sqlite_partition_columns(table: &Table) -> (Vec<String>, String) {
+    let mut columns = vec!["_id".to_string(), "_order".to_string()];
+    if table.has_change_id {
+        columns.push("_change_id".to_string());
+    }
+    if table.has_uuid {
+        columns.push("_uuid".to_string());
+    }
+    columns.extend(table.columns.keys().cloned());
+    let column_list = columns
+        .iter()
+        .map(|column| format!(r#""{column}""#))
+        .collect::<Vec<_>>()
+        .join(", ");
+    (columns, column_list)
+}
+
+/// Generate the DDL to create the triggers on the view that combines the partitions of the given
+/// partitioned table in SQLite, which route the rows inserted into, updated in, and deleted from
+/// the view to its partitions, and, for the Trigger caching strategy, clear the cache of the
+/// view's rows (see [generate_sqlite_partitioned_table_ddl()]).
+pub(crate) fn generate_sqlite_partition_trigger_ddl(
+    table: &Table,
+    caching_strategy: &CachingStrategy,
+) -> Result<Vec<String>> {
+    tracing::trace!("generate_sqlite_partition_trigger_ddl({table:?}, {caching_strategy:?})");
+    let mut ddl = vec![];
+    let (columns, column_list) = sqlite_partition_columns(table);
+    let new_values = |uuid_default: bool| {
+        columns
+            .iter()
+            .map(|column| match column.as_str() {
+                // Rows inserted without an _id are numbered after the rows of all partitions:
+                "_id" => format!(
+                    r#"COALESCE(NEW."_id", (SELECT COALESCE(MAX("seq"), 0) + 1
+                                            FROM "sqlite_sequence"
+                                            WHERE "name" IN ({partitions})))"#,
+                    partitions = table
+                        .partitions
+                        .iter()
+                        .map(|partition| format!("'{}'", partition.name))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                "_uuid" if uuid_default => format!(
+                    r#"COALESCE(NEW."_uuid", {})"#,
+                    uuid_default_expr(&DbKind::Sqlite)
+                ),
+                _ => format!(r#"NEW."{column}""#),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut insert_statements = vec![];
+    let mut update_statements = vec![];
+    let mut delete_statements = vec![];
+    for partition in &table.partitions {
+        let name = &partition.name;
+        let belongs = partition_condition(partition, &table.partitions, "NEW.")?;
+        insert_statements.push(format!(
+            r#"INSERT INTO "{name}" ({column_list})
+               SELECT {values} WHERE {belongs};"#,
+            values = new_values(true)
+        ));
+        update_statements.push(format!(
+            r#"UPDATE "{name}" SET ({column_list}) = ({values})
+               WHERE "_id" = OLD."_id" AND {belongs};"#,
+            values = new_values(false)
+        ));
+        update_statements.push(format!(
+            r#"INSERT INTO "{name}" ({column_list})
+               SELECT {values} WHERE {belongs}
+               AND NOT EXISTS (SELECT 1 FROM "{name}" WHERE "_id" = OLD."_id");"#,
+            values = new_values(false)
+        ));
+        update_statements.push(format!(
+            r#"DELETE FROM "{name}" WHERE "_id" = OLD."_id" AND NOT ({belongs});"#
+        ));
+        delete_statements.push(format!(r#"DELETE FROM "{name}" WHERE "_id" = OLD."_id";"#));
+    }
+    for (operation, statements) in [
+        ("insert", insert_statements),
+        ("update", update_statements),
+        ("delete", delete_statements),
+    ] {
+        let cache_statement = match caching_strategy {
+            CachingStrategy::Trigger => {
+                format!(
+                    r#"DELETE FROM "cache" WHERE "tables" LIKE '%"{}"%';"#,
+                    table.name
+                )
+            }
+            _ => "".to_string(),
+        };
+        ddl.push(format!(
+            r#"CREATE TRIGGER "{table}_{operation}"
+               INSTEAD OF {keyword} ON "{table}"
+               BEGIN
+                 {statements}
+                 {cache_statement}
+               END"#,
+            table = table.name,
+            keyword = operation.to_uppercase(),
+            statements = statements.join("\n"),
+        ));
     }
 
     Ok(ddl)
 }
 
+/// Render the given value of the column that a table is partitioned by as an SQL literal. Only
+/// numbers and strings are supported.
+pub fn partition_literal(value: &JsonValue) -> Result<String> {
+    match value {
+        JsonValue::Number(number) => Ok(number.to_string()),
+        JsonValue::String(string) => Ok(format!("'{}'", string.replace("'", "''"))),
+        _ => Err(
+            RelatableError::InputError(format!("Unsupported value for a partition: {value}"))
+                .into(),
+        ),
+    }
+}
+
+/// Generate an SQL condition that is true when a row, whose columns are qualified with the given
+/// prefix, e.g., `NEW.`, belongs in the given partition, which is one of the given partitions of
+/// its table.
+pub fn partition_condition(
+    partition: &Partition,
+    partitions: &[Partition],
+    prefix: &str,
+) -> Result<String> {
+    let column = format!(r#"{prefix}"{}""#, partition.column);
+    match &partition.value {
+        Some(value) => Ok(format!("{column} = {}", partition_literal(value)?)),
+        None => {
+            let values = partitions
+                .iter()
+                .filter_map(|partition| partition.value.as_ref())
+                .map(partition_literal)
+                .collect::<Result<Vec<_>>>()?;
+            match values.is_empty() {
+                true => Ok("1 = 1".to_string()),
+                false => Ok(format!(
+                    "({column} IS NULL OR {column} NOT IN ({}))",
+                    values.join(", ")
+                )),
+            }
+        }
+    }
+}
+
+/// Generate DDL to create the unique index that the database requires on the given column of the
+/// given table when it is referenced by a foreign key, unless the index already exists.
+pub fn generate_referenced_index_ddl(table: &str, column: &str) -> String {
+    format!(
+        r#"CREATE UNIQUE INDEX IF NOT EXISTS "{table}_{column}_key_idx"
+           ON "{table}"("{column}")"#
+    )
+}
+
+/// Returns an SQL expression that generates a random (version 4) UUID, suitable for use as the
+/// default value of a table's _uuid column.
+pub fn uuid_default_expr(db_kind: &DbKind) -> String {
+    match db_kind {
+        DbKind::Sqlite => r#"(LOWER(
+                               HEX(RANDOMBLOB(4)) || '-' ||
+                               HEX(RANDOMBLOB(2)) || '-4' ||
+                               SUBSTR(HEX(RANDOMBLOB(2)), 2) || '-' ||
+                               SUBSTR('89AB', 1 + (ABS(RANDOM()) % 4), 1) ||
+                               SUBSTR(HEX(RANDOMBLOB(2)), 2) || '-' ||
+                               HEX(RANDOMBLOB(6))
+                             ))"#
+        .to_string(),
+        DbKind::Postgres => "(gen_random_uuid()::TEXT)".to_string(),
+    }
+}
+
 /// Add triggers for updating the meta columns, _id, and _order, of the given table.
 pub fn add_metacolumn_trigger_ddl(ddl: &mut Vec<String>, table: &str, db_kind: &DbKind) {
     let update_stmt = format!(
@@ -1066,6 +2149,60 @@ pub fn add_metacolumn_trigger_ddl(ddl: &mut Vec<String>, table: &str, db_kind: &
     };
 }
 
+/// Add a trigger on the history table to keep the materialized _change_id column of the given
+/// table up to date. If `force` is set, drop any existing trigger first.
+pub fn add_change_id_trigger_ddl(
+    ddl: &mut Vec<String>,
+    table: &str,
+    force: bool,
+    db_kind: &DbKind,
+) {
+    let update_stmt =
+        format!(r#"UPDATE "{table}" SET "_change_id" = NEW."change_id" WHERE "_id" = NEW."row";"#);
+    match db_kind {
+        DbKind::Sqlite => {
+            // Triggers on the history table are not dropped along with the data table:
+            if force {
+                ddl.push(format!(r#"DROP TRIGGER IF EXISTS "{table}_change_id""#));
+            }
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{table}_change_id"
+                   AFTER INSERT ON "history"
+                   WHEN NEW."table" = '{table}'
+                     BEGIN
+                       {update_stmt}
+                     END"#
+            ));
+        }
+        DbKind::Postgres => {
+            if force {
+                ddl.push(format!(
+                    r#"DROP TRIGGER IF EXISTS "{table}_change_id" ON "history""#
+                ));
+            }
+            ddl.push(format!(
+                r#"CREATE OR REPLACE FUNCTION "update_change_id_{table}"()
+                     RETURNS TRIGGER
+                     LANGUAGE PLPGSQL
+                   AS
+                   $$
+                   BEGIN
+                     {update_stmt}
+                     RETURN NEW;
+                   END;
+                   $$"#
+            ));
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{table}_change_id"
+                   AFTER INSERT ON "history"
+                   FOR EACH ROW
+                   WHEN (NEW."table" = '{table}')
+                   EXECUTE FUNCTION "update_change_id_{table}"()"#
+            ));
+        }
+    };
+}
+
 /// Add a trigger to update the query cache for the given table.
 pub fn add_caching_trigger_ddl(ddl: &mut Vec<String>, table: &str, db_kind: &DbKind) {
     match db_kind {
@@ -1126,19 +2263,114 @@ pub fn add_caching_trigger_ddl(ddl: &mut Vec<String>, table: &str, db_kind: &DbK
     };
 }
 
-/// Generate the DDL for creating the default view on the given table,
+/// Drop the triggers, if they exist, that update the query cache for the given table (see
+/// [add_caching_trigger_ddl()]).
+pub fn drop_caching_trigger_ddl(ddl: &mut Vec<String>, table: &str, db_kind: &DbKind) {
+    match db_kind {
+        DbKind::Sqlite => {
+            for event in ["insert", "update", "delete"] {
+                ddl.push(format!(
+                    r#"DROP TRIGGER IF EXISTS "{table}_cache_after_{event}""#
+                ));
+            }
+        }
+        DbKind::Postgres => {
+            // Dropping the function also drops the triggers that execute it:
+            ddl.push(format!(
+                r#"DROP FUNCTION IF EXISTS "clean_cache_for_{table}"() CASCADE"#
+            ));
+        }
+    };
+}
+
+/// The options that determine the shape of a default view (see [generate_default_view_ddl()]).
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct ViewOptions<'a> {
+    /// The source of the view's rows, i.e., one of the table's partitions or its archive, if the
+    /// view is not on the table itself
+    pub source: Option<&'a str>,
+    /// Whether the table has a materialized _change_id column
+    pub has_change_id: bool,
+    /// Whether the table has a _uuid column
+    pub has_uuid: bool,
+    /// Whether the table is in wide mode
+    pub wide: bool,
+}
+
+/// Generate the DDL for creating the default view on the given table, or, if the given options
+/// name another source of the table's rows, i.e., one of its partitions or its archive, the
+/// default view on that source, which reads its rows along with the history and messages recorded
+/// for them under the name of the table. The history and messages of archived rows are read from
+/// the archives of the history and message tables (see [archive_name()]). The view on a `wide`
+/// table leaves its _history and _message columns empty, since aggregating them for every row of
+/// a table with hundreds of columns is slow; they are looked up for one row at a time instead
+/// (see [generate_row_annotation_sql()]).
 pub(crate) fn generate_default_view_ddl(
     table_name: &str,
     id_col: &str,
     order_col: &str,
     columns: &Vec<Column>,
+    options: &ViewOptions,
     kind: &DbKind,
 ) -> Vec<String> {
     tracing::trace!(
-        "generate_default_view_ddl({table_name}, {id_col}, {order_col}, {columns:?}, {kind:?})"
+        "generate_default_view_ddl({table_name}, {id_col}, {order_col}, {columns:?}, \
+         {options:?}, {kind:?})"
     );
-    let view_name = format!("{table_name}_default_view");
+    let ViewOptions {
+        source,
+        has_change_id,
+        has_uuid,
+        wide,
+    } = *options;
+    let source = source.unwrap_or(table_name);
+    let view_name = format!("{source}_default_view");
+    let (history, message) = match source == archive_name(table_name) {
+        true => (archive_name("history"), archive_name("message")),
+        false => ("history".to_string(), "message".to_string()),
+    };
+    // Use the materialized _change_id column if there is one, otherwise look it up in the
+    // history table:
+    let change_id_expr = match has_change_id {
+        true => r#""_change_id""#.to_string(),
+        false => format!(
+            r#"(SELECT "change_id"
+                FROM "{history}"
+                WHERE "table" = '{table_name}'
+                AND "row" = {id_col}
+                ORDER BY "change_id" DESC
+                LIMIT 1
+               )"#
+        ),
+    };
+    // Pass the _uuid column through to the view if the table has one:
+    let column_list = {
+        let mut column_list = vec![];
+        if has_uuid {
+            column_list.push(r#""_uuid""#.to_string());
+        }
+        for column in columns {
+            column_list.push(format!(r#""{}""#, column.name));
+        }
+        column_list.join(", ")
+    };
     // Note that '?' parameters are not allowed in views so we must hard code them:
+    let (history_expr, message_expr) = match wide {
+        true => match kind {
+            DbKind::Sqlite => ("NULL".to_string(), "NULL".to_string()),
+            DbKind::Postgres => ("NULL::TEXT".to_string(), "NULL::TEXT".to_string()),
+        },
+        false => {
+            let row = match kind {
+                DbKind::Sqlite => id_col.to_string(),
+                DbKind::Postgres => format!(r#""{id_col}""#),
+            };
+            (
+                history_expr(&history, table_name, &row, kind),
+                message_expr(&message, table_name, &row, kind),
+            )
+        }
+    };
     match kind {
         DbKind::Sqlite => vec![
             format!(r#"DROP VIEW IF EXISTS "{}""#, view_name),
@@ -1147,49 +2379,13 @@ pub(crate) fn generate_default_view_ddl(
                      SELECT
                        {id_col} AS _id,
                        {order_col} AS _order,
-                       (SELECT "change_id"
-                        FROM "history"
-                        WHERE "table" = '{table}'
-                        AND "row" = {id_col}
-                        ORDER BY "change_id" DESC
-                        LIMIT 1
-                       ) AS _change_id,
-                       (SELECT '[' || GROUP_CONCAT("after") || ']'
-                          FROM (
-                            SELECT "after"
-                            FROM "history"
-                            WHERE "table" = '{table}'
-                            AND "after" IS NOT NULL
-                            AND "row" = {id_col}
-                            ORDER BY "history_id"
-                         )
-                       ) AS "_history",
-                       (SELECT NULLIF(
-                          JSON_GROUP_ARRAY(
-                            JSON_OBJECT(
-                              'column', "column",
-                              'value', "value",
-                              'level', "level",
-                              'rule', "rule",
-                              'message', "message"
-                            )
-                          ),
-                          '[]'
-                        ) AS "_message"
-                          FROM "message"
-                          WHERE "table" = '{table}'
-                          AND "row" = {id_col}
-                          ORDER BY "column", "message_id"
-                       ) AS "_message",
+                       {change_id_expr} AS _change_id,
+                       {history_expr} AS "_history",
+                       {message_expr} AS "_message",
                        {columns}
-                     FROM "{table}""#,
-                table = table_name,
+                     FROM "{source}""#,
                 view = view_name,
-                columns = columns
-                    .iter()
-                    .map(|c| format!(r#""{}""#, c.name))
-                    .collect::<Vec<_>>()
-                    .join(", "),
+                columns = column_list,
             ),
         ],
         DbKind::Postgres => vec![format!(
@@ -1197,43 +2393,127 @@ pub(crate) fn generate_default_view_ddl(
                  SELECT
                    "{id_col}" AS _id,
                    "{order_col}" AS _order,
-                   (
-                     SELECT "change_id"
-                     FROM "history"
-                     WHERE "table" = '{table}'
-                     AND "row" = {id_col}
-                     ORDER BY "change_id" DESC
-                     LIMIT 1
-                   ) AS _change_id,
-                   (
-                     SELECT ('['::TEXT || string_agg(h.after, ','::TEXT)) || ']'::TEXT
-                     FROM ( SELECT "history"."after"
-                            FROM "history"
-                            WHERE "history"."table" = '{table}'
-                            AND "after" IS DISTINCT FROM NULL
-                            AND "row" = "{id_col}"
-                            ORDER BY "history_id" ) h
-                   ) AS "_history",
-                   (
-                     SELECT json_agg(m.*)::TEXT AS json_agg
-                     FROM ( SELECT "message"."column",
-                                   "message"."value",
-                                   "message"."level",
-                                   "message"."rule",
-                                   "message"."message"
-                            FROM "message"
-                     WHERE "message"."table" = '{table}' AND "message"."row" = "{id_col}"
-                     ORDER BY "message"."column", "message"."message_id") m
-                   ) AS "_message",
+                   {change_id_expr} AS _change_id,
+                   {history_expr} AS "_history",
+                   {message_expr} AS "_message",
                    {columns}
-                     FROM "{table}""#,
-            table = table_name,
+                     FROM "{source}""#,
             view = view_name,
-            columns = columns
-                .iter()
-                .map(|c| format!(r#""{}""#, c.name))
-                .collect::<Vec<_>>()
-                .join(", "),
+            columns = column_list,
+        )],
+    }
+}
+
+/// Generate the SQL expression that aggregates the history recorded in the given history table for
+/// the given row of the given table, as a JSON array of the rows' values after each change.
+fn history_expr(history: &str, table: &str, row: &str, kind: &DbKind) -> String {
+    match kind {
+        DbKind::Sqlite => format!(
+            r#"(SELECT '[' || GROUP_CONCAT("after") || ']'
+                  FROM (
+                    SELECT "after"
+                    FROM "{history}"
+                    WHERE "table" = '{table}'
+                    AND "after" IS NOT NULL
+                    AND "row" = {row}
+                    ORDER BY "history_id"
+                 )
+               )"#
+        ),
+        DbKind::Postgres => format!(
+            r#"(
+                 SELECT ('['::TEXT || string_agg(h.after, ','::TEXT)) || ']'::TEXT
+                 FROM ( SELECT "{history}"."after"
+                        FROM "{history}"
+                        WHERE "{history}"."table" = '{table}'
+                        AND "after" IS DISTINCT FROM NULL
+                        AND "row" = {row}
+                        ORDER BY "history_id" ) h
+               )"#
+        ),
+    }
+}
+
+/// Generate the SQL expression that aggregates the messages recorded in the given message table
+/// for the given row of the given table, as a JSON array of objects.
+fn message_expr(message: &str, table: &str, row: &str, kind: &DbKind) -> String {
+    match kind {
+        DbKind::Sqlite => format!(
+            r#"(SELECT NULLIF(
+                  JSON_GROUP_ARRAY(
+                    JSON_OBJECT(
+                      'column', "column",
+                      'value', "value",
+                      'level', "level",
+                      'rule', "rule",
+                      'message', "message"
+                    )
+                  ),
+                  '[]'
+                ) AS "_message"
+                  FROM "{message}"
+                  WHERE "table" = '{table}'
+                  AND "row" = {row}
+                  ORDER BY "column", "message_id"
+               )"#
+        ),
+        DbKind::Postgres => format!(
+            r#"(
+                 SELECT json_agg(m.*)::TEXT AS json_agg
+                 FROM ( SELECT "{message}"."column",
+                               "{message}"."value",
+                               "{message}"."level",
+                               "{message}"."rule",
+                               "{message}"."message"
+                        FROM "{message}"
+                 WHERE "{message}"."table" = '{table}' AND "{message}"."row" = {row}
+                 ORDER BY "{message}"."column", "{message}"."message_id") m
+               )"#
+        ),
+    }
+}
+
+/// Generate the SQL that looks up the _history and _message columns of the given row of the given
+/// table, which the default view on a wide table leaves empty (see
+/// [generate_default_view_ddl()]).
+pub(crate) fn generate_row_annotation_sql(table_name: &str, row: u64, kind: &DbKind) -> String {
+    tracing::trace!("generate_row_annotation_sql({table_name}, {row}, {kind:?})");
+    let row = row.to_string();
+    format!(
+        r#"SELECT {history_expr} AS "_history", {message_expr} AS "_message""#,
+        history_expr = history_expr("history", table_name, &row, kind),
+        message_expr = message_expr("message", table_name, &row, kind),
+    )
+}
+
+/// The name of the table that holds the archived rows of the given table, which may also be the
+/// history or message table (see
+/// [Relatable::archive_rows()](crate::core::Relatable::archive_rows)).
+pub fn archive_name(table: &str) -> String {
+    format!("{table}__archive")
+}
+
+/// Generate the DDL for creating a view with the given name that combines the rows of the given
+/// sources, e.g., the default views on the partitions of a table, which must all have the same
+/// columns.
+pub(crate) fn generate_partitioned_view_ddl(
+    view_name: &str,
+    sources: &[String],
+    kind: &DbKind,
+) -> Vec<String> {
+    tracing::trace!("generate_partitioned_view_ddl({view_name}, {sources:?}, {kind:?})");
+    let union = sources
+        .iter()
+        .map(|source| format!(r#"SELECT * FROM "{source}""#))
+        .collect::<Vec<_>>()
+        .join(" UNION ALL ");
+    match kind {
+        DbKind::Sqlite => vec![
+            format!(r#"DROP VIEW IF EXISTS "{view_name}""#),
+            format!(r#"CREATE VIEW "{view_name}" AS {union}"#),
+        ],
+        DbKind::Postgres => vec![format!(
+            r#"CREATE OR REPLACE VIEW "{view_name}" AS {union}"#
         )],
     }
 }
@@ -1399,7 +2679,14 @@ pub(crate) fn generate_text_view_ddl(
     let mut inner_columns = columns
         .iter()
         .map(|column| {
-            let column_cast = {
+            let is_binary = canonical_sql_type(&column.get_sql_type()) == Some("BLOB");
+            let column_cast = if is_binary {
+                // Binary values are shown in hexadecimal:
+                match kind {
+                    DbKind::Sqlite => format!(r#"LOWER(HEX("{}"))"#, column.name),
+                    DbKind::Postgres => format!(r#"ENCODE("{}", 'hex')"#, column.name),
+                }
+            } else {
                 let (flag_opt, width_opt, precision_opt, format_type) =
                     split_sprintf_format(column.datatype.format.as_ref());
                 if *kind == DbKind::Sqlite {
@@ -1423,6 +2710,13 @@ pub(crate) fn generate_text_view_ddl(
                     }
                 }
             };
+            // Values are written in the column's locale, if it has one:
+            let column_cast = match &column.locale {
+                Some(locale) => {
+                    locale.to_sql(&column.name, &column.get_sql_type(), &column_cast, kind)
+                }
+                None => column_cast,
+            };
             format!(
                 r#"CASE
                      WHEN "{column}" {is_clause} NULL THEN (
@@ -1486,8 +2780,12 @@ pub(crate) fn generate_text_view_ddl(
     ]
 }
 /// Generate the DDL used to create the table table. If `force` is set, drop the table first
-pub fn generate_table_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
-    tracing::trace!("generate_table_table_ddl({force}, {db_kind:?})");
+pub fn generate_table_table_ddl(
+    force: bool,
+    db_kind: &DbKind,
+    change_capture: &dyn ChangeCapture,
+) -> Vec<String> {
+    tracing::trace!("generate_table_table_ddl({force}, {db_kind:?}, {change_capture:?})");
     let mut ddl = vec![];
     if force {
         if let DbKind::Postgres = db_kind {
@@ -1504,12 +2802,18 @@ pub fn generate_table_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
              "_id" {pkey_clause},
              "_order" BIGINT UNIQUE,
              "table" TEXT UNIQUE,
-             "path" TEXT UNIQUE
+             "path" TEXT UNIQUE,
+             "loaded" BIGINT,
+             "checksum" TEXT,
+             "file_checksum" TEXT,
+             "autosave" BOOLEAN,
+             "max_limit" BIGINT,
+             "wide" BOOLEAN
            )"#
     ));
 
-    // Add metacolumn triggers before returning the DDL:
-    add_metacolumn_trigger_ddl(&mut ddl, "table", db_kind);
+    // Add metacolumn triggers, if changes are captured by triggers, before returning the DDL:
+    change_capture.add_metacolumn_ddl(&mut ddl, "table", db_kind);
     ddl
 }
 
@@ -1576,6 +2880,13 @@ pub fn generate_change_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
                       "action" TEXT NOT NULL,
                       "table" TEXT NOT NULL,
                       "description" TEXT,
+                      "message" TEXT,
+                      "change_group" INTEGER,
+                      "idempotency_key" TEXT,
+                      "rows_affected" BIGINT,
+                      "messages_added" BIGINT,
+                      "messages_removed" BIGINT,
+                      "elapsed_ms" BIGINT,
                       "content" TEXT,
                       FOREIGN KEY ("user") REFERENCES "user"("name")
                     )"#
@@ -1596,6 +2907,13 @@ pub fn generate_change_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
                      "action" TEXT NOT NULL,
                      "table" TEXT NOT NULL,
                      "description" TEXT,
+                     "message" TEXT,
+                     "change_group" INTEGER,
+                     "idempotency_key" TEXT,
+                     "rows_affected" BIGINT,
+                     "messages_added" BIGINT,
+                     "messages_removed" BIGINT,
+                     "elapsed_ms" BIGINT,
                      "content" TEXT,
                      FOREIGN KEY ("user") REFERENCES "user"("name")
                    )"#
@@ -1661,6 +2979,8 @@ pub fn generate_message_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String>
                       "level" TEXT,
                       "rule" TEXT,
                       "message" TEXT,
+                      "_hash" TEXT,
+                      "_datetime" TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                       FOREIGN KEY ("table") REFERENCES "table"("table")
                     )"#
             .to_string()]
@@ -1683,6 +3003,8 @@ pub fn generate_message_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String>
                      "level" TEXT,
                      "rule" TEXT,
                      "message" TEXT,
+                     "_hash" TEXT,
+                     "_datetime" TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
                      FOREIGN KEY ("table") REFERENCES "table"("table")
                    )"#
             ));
@@ -1691,19 +3013,442 @@ pub fn generate_message_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String>
     }
 }
 
+/// The version of the layout of the meta tables that is created by this release of
+/// [rltbl](crate). Whenever the layout changes, this must be incremented and a corresponding
+/// migration added to [Relatable::migrate](crate::core::Relatable::migrate).
+pub const SCHEMA_VERSION: u64 = 17;
+
+/// Generate the DDL used to create the meta table, which records the schema version of the
+/// database, and to set the version to [SCHEMA_VERSION]. If `force` is set, drop the table first
+pub fn generate_meta_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_meta_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "meta" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "meta" (
+             "key" TEXT PRIMARY KEY,
+             "value" TEXT
+           )"#
+        .to_string(),
+    );
+    ddl.push(format!(
+        r#"INSERT INTO "meta" ("key", "value") VALUES ('schema_version', '{SCHEMA_VERSION}')"#
+    ));
+    ddl
+}
+
+/// Generate the DDL used to create the origin table, which holds the identifier used to tag the
+/// operations recorded by this instance in the operation table. If `force` is set, drop the table
+/// first
+pub fn generate_origin_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_origin_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "origin" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "origin" (
+             "origin" TEXT PRIMARY KEY
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// Generate the DDL used to create the template table, which stores named sets of default cell
+/// values to be used when adding new rows to a table. If `force` is set, drop the table first
+pub fn generate_template_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_template_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "template" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "template" (
+             "template" TEXT NOT NULL,
+             "table" TEXT NOT NULL,
+             "column" TEXT NOT NULL,
+             "value" TEXT,
+             PRIMARY KEY ("template", "table", "column"),
+             FOREIGN KEY ("table") REFERENCES "table"("table")
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// Generate the DDL used to create the operation table, which logs every change to a row, tagged
+/// with the origin and vector clock of the instance that made it. Rows are identified across
+/// instances by the origin of the instance that added them (empty for loaded rows) and their _id
+/// in that instance, while `local_row` records the _id of the row in this instance. If `force` is
+/// set, drop the table first
+pub fn generate_operation_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_operation_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "operation" CASCADE"#.to_string());
+        }
+    }
+    let pkey_clause = match db_kind {
+        DbKind::Sqlite => "INTEGER PRIMARY KEY AUTOINCREMENT",
+        DbKind::Postgres => "SERIAL PRIMARY KEY",
+    };
+    ddl.push(format!(
+        r#"CREATE TABLE "operation" (
+             "operation_id" {pkey_clause},
+             "origin" TEXT NOT NULL,
+             "counter" BIGINT NOT NULL,
+             "clock" TEXT NOT NULL,
+             "datetime" TEXT NOT NULL,
+             "user" TEXT,
+             "table" TEXT NOT NULL,
+             "kind" TEXT,
+             "row_origin" TEXT,
+             "row" BIGINT NOT NULL,
+             "column" TEXT NOT NULL,
+             "value" TEXT,
+             "local_row" BIGINT,
+             UNIQUE ("origin", "counter")
+           )"#
+    ));
+    ddl
+}
+
+/// Generate the DDL used to create the clock table, which records the highest counter of the
+/// operations from each origin in the operation table, i.e., the vector clock of this instance.
+/// If `force` is set, drop the table first
+pub fn generate_clock_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_clock_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "clock" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "clock" (
+             "origin" TEXT PRIMARY KEY,
+             "counter" BIGINT NOT NULL
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// Generate the DDL used to create the mirror table, which records, for each of the remote
+/// instances that this one mirrors, the tables that are copied from it and the last change to
+/// each that has been pulled (NULL if none has been). If `force` is set, drop the table first
+pub fn generate_mirror_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_mirror_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "mirror" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "mirror" (
+             "mirror" TEXT NOT NULL,
+             "url" TEXT NOT NULL,
+             "table" TEXT NOT NULL UNIQUE,
+             "change_id" BIGINT,
+             PRIMARY KEY ("mirror", "table")
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// Generate the DDL used to create the search table, which records the tables that have a
+/// full-text search index, and the columns that are indexed (a JSON list). If `force` is set,
+/// drop the table first
+pub fn generate_search_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_search_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "search" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "search" (
+             "table" TEXT PRIMARY KEY,
+             "columns" TEXT NOT NULL
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// Generate the DDL used to create the partition table, which records, for each table that is
+/// partitioned by one of its columns, the tables that hold its partitions and the value of the
+/// column in the rows of each (a JSON value, or NULL for the default partition). If `force` is
+/// set, drop the table first
+pub fn generate_partition_table_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_partition_table_ddl({force}, {db_kind:?})");
+    let mut ddl = vec![];
+    if force {
+        if let DbKind::Postgres = db_kind {
+            ddl.push(r#"DROP TABLE IF EXISTS "partition" CASCADE"#.to_string());
+        }
+    }
+    ddl.push(
+        r#"CREATE TABLE "partition" (
+             "partition" TEXT PRIMARY KEY,
+             "table" TEXT NOT NULL,
+             "column" TEXT NOT NULL,
+             "value" TEXT
+           )"#
+        .to_string(),
+    );
+    ddl
+}
+
+/// The name of the full-text search index on the given table: an FTS5 virtual table in SQLite,
+/// or a table of tsvectors in PostgreSQL.
+pub fn search_index_name(table: &str) -> String {
+    format!("{table}_search")
+}
+
+/// Generate the DDL used to (re)create the full-text search index on the given columns of the
+/// given table, populate it with the table's current rows, and add the triggers that keep it up
+/// to date when rows are added, updated, and deleted.
+pub fn generate_search_index_ddl(table: &str, columns: &[String], db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_search_index_ddl({table}, {columns:?}, {db_kind:?})");
+    let index = search_index_name(table);
+    let mut ddl = generate_drop_search_index_ddl(table, db_kind);
+    let column_list = |prefix: &str| {
+        columns
+            .iter()
+            .map(|column| format!(r#"{prefix}"{column}""#))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match db_kind {
+        DbKind::Sqlite => {
+            // The index is an external content FTS5 table, which reads the indexed values from
+            // the table itself, so the triggers must give it the old values of deleted rows:
+            ddl.push(format!(
+                r#"CREATE VIRTUAL TABLE "{index}" USING fts5(
+                     {columns}, content = '{table}', content_rowid = '_id'
+                   )"#,
+                columns = column_list(""),
+            ));
+            ddl.push(format!(
+                r#"INSERT INTO "{index}" ("{index}") VALUES ('rebuild')"#
+            ));
+            let insert = format!(
+                r#"INSERT INTO "{index}" ("rowid", {columns})
+                   VALUES (NEW."_id", {values});"#,
+                columns = column_list(""),
+                values = column_list("NEW."),
+            );
+            let delete = format!(
+                r#"INSERT INTO "{index}" ("{index}", "rowid", {columns})
+                   VALUES ('delete', OLD."_id", {values});"#,
+                columns = column_list(""),
+                values = column_list("OLD."),
+            );
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{index}_after_insert"
+                   AFTER INSERT ON "{table}"
+                   BEGIN
+                     {insert}
+                   END"#
+            ));
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{index}_after_update"
+                   AFTER UPDATE ON "{table}"
+                   BEGIN
+                     {delete}
+                     {insert}
+                   END"#
+            ));
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{index}_after_delete"
+                   AFTER DELETE ON "{table}"
+                   BEGIN
+                     {delete}
+                   END"#
+            ));
+        }
+        DbKind::Postgres => {
+            let document = |prefix: &str| {
+                format!(
+                    "TO_TSVECTOR('simple', CONCAT_WS(' ', {values}))",
+                    values = columns
+                        .iter()
+                        .map(|column| format!(r#"{prefix}"{column}"::TEXT"#))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+            ddl.push(format!(
+                r#"CREATE TABLE "{index}" (
+                     "_id" BIGINT PRIMARY KEY,
+                     "document" TSVECTOR
+                   )"#
+            ));
+            ddl.push(format!(
+                r#"CREATE INDEX "{index}_idx" ON "{index}" USING GIN ("document")"#
+            ));
+            ddl.push(format!(
+                r#"INSERT INTO "{index}" ("_id", "document")
+                   SELECT "_id", {document} FROM "{table}""#,
+                document = document(""),
+            ));
+            ddl.push(format!(
+                r#"CREATE OR REPLACE FUNCTION "update_search_{table}"()
+                     RETURNS TRIGGER
+                     LANGUAGE PLPGSQL
+                   AS
+                   $$
+                   BEGIN
+                     IF TG_OP = 'DELETE' THEN
+                       DELETE FROM "{index}" WHERE "_id" = OLD."_id";
+                       RETURN OLD;
+                     END IF;
+                     INSERT INTO "{index}" ("_id", "document")
+                     VALUES (NEW."_id", {document})
+                     ON CONFLICT ("_id") DO UPDATE SET "document" = EXCLUDED."document";
+                     RETURN NEW;
+                   END;
+                   $$"#,
+                document = document("NEW."),
+            ));
+            ddl.push(format!(
+                r#"CREATE TRIGGER "{index}_after_change"
+                   AFTER INSERT OR UPDATE OR DELETE ON "{table}"
+                   FOR EACH ROW
+                   EXECUTE FUNCTION "update_search_{table}"()"#
+            ));
+        }
+    };
+    ddl
+}
+
+/// Generate the DDL used to drop the full-text search index on the given table, along with the
+/// triggers that maintain it.
+pub fn generate_drop_search_index_ddl(table: &str, db_kind: &DbKind) -> Vec<String> {
+    tracing::trace!("generate_drop_search_index_ddl({table}, {db_kind:?})");
+    let index = search_index_name(table);
+    match db_kind {
+        DbKind::Sqlite => vec![
+            format!(r#"DROP TRIGGER IF EXISTS "{index}_after_insert""#),
+            format!(r#"DROP TRIGGER IF EXISTS "{index}_after_update""#),
+            format!(r#"DROP TRIGGER IF EXISTS "{index}_after_delete""#),
+            format!(r#"DROP TABLE IF EXISTS "{index}""#),
+        ],
+        // Dropping the function also drops the trigger that executes it:
+        DbKind::Postgres => vec![
+            format!(r#"DROP FUNCTION IF EXISTS "update_search_{table}"() CASCADE"#),
+            format!(r#"DROP TABLE IF EXISTS "{index}""#),
+        ],
+    }
+}
+
 /// Generate the DDL used to create all of the required meta tables. If `force` is set, drop the
 /// tables first
-pub fn generate_meta_tables_ddl(force: bool, db_kind: &DbKind) -> Vec<String> {
-    tracing::trace!("generate_meta_tables_ddl({force}, {db_kind:?})");
-    let mut ddl = generate_table_table_ddl(force, db_kind);
+pub fn generate_meta_tables_ddl(
+    force: bool,
+    db_kind: &DbKind,
+    change_capture: &dyn ChangeCapture,
+) -> Vec<String> {
+    tracing::trace!("generate_meta_tables_ddl({force}, {db_kind:?}, {change_capture:?})");
+    let mut ddl = generate_meta_table_ddl(force, db_kind);
+    ddl.append(&mut generate_table_table_ddl(
+        force,
+        db_kind,
+        change_capture,
+    ));
     ddl.append(&mut generate_cache_table_ddl(force, db_kind));
     ddl.append(&mut generate_user_table_ddl(force, db_kind));
     ddl.append(&mut generate_change_table_ddl(force, db_kind));
     ddl.append(&mut generate_history_table_ddl(force, db_kind));
     ddl.append(&mut generate_message_table_ddl(force, db_kind));
+    ddl.append(&mut generate_origin_table_ddl(force, db_kind));
+    ddl.append(&mut generate_operation_table_ddl(force, db_kind));
+    ddl.append(&mut generate_clock_table_ddl(force, db_kind));
+    ddl.append(&mut generate_template_table_ddl(force, db_kind));
+    ddl.append(&mut generate_mirror_table_ddl(force, db_kind));
+    ddl.append(&mut generate_search_table_ddl(force, db_kind));
+    ddl.append(&mut generate_partition_table_ddl(force, db_kind));
+    for (index, table, columns) in META_TABLE_INDEXES {
+        ddl.push(generate_index_ddl(index, table, columns));
+    }
     ddl
 }
 
+/// The meta tables that [rltbl](crate) creates and maintains itself, as opposed to the
+/// configuration tables (column, datatype, rule, and severity_override), which are loaded by users.
+pub const META_TABLES: [&str; 14] = [
+    "cache",
+    "change",
+    "clock",
+    "history",
+    "message",
+    "meta",
+    "mirror",
+    "operation",
+    "origin",
+    "partition",
+    "search",
+    "table",
+    "template",
+    "user",
+];
+
+/// The configuration tables, which are loaded by users.
+pub const CONFIG_TABLES: [&str; 4] = ["column", "datatype", "rule", "severity_override"];
+
+/// The indexes that are maintained on the meta tables, given as tuples of the form
+/// (index name, table name, indexed columns).
+pub const META_TABLE_INDEXES: [(&str, &str, &[&str]); 4] = [
+    (
+        "history_table_row_change_id_idx",
+        "history",
+        &["table", "row", "change_id"],
+    ),
+    (
+        "message_table_row_column_idx",
+        "message",
+        &["table", "row", "column"],
+    ),
+    (
+        "operation_table_row_origin_row_idx",
+        "operation",
+        &["table", "row_origin", "row", "column"],
+    ),
+    (
+        "operation_table_local_row_idx",
+        "operation",
+        &["table", "local_row"],
+    ),
+];
+
+/// Generate the DDL used to create an index with the given name on the given columns of the given
+/// table, unless it already exists.
+pub fn generate_index_ddl(index: &str, table: &str, columns: &[&str]) -> String {
+    tracing::trace!("generate_index_ddl({index}, {table}, {columns:?})");
+    format!(
+        r#"CREATE INDEX IF NOT EXISTS "{index}" ON "{table}"({columns})"#,
+        columns = columns
+            .iter()
+            .map(|column| format!(r#""{column}""#))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 // Utilities for dealing with JSON representations of database rows.
 ///////////////////////////////////////////////////////////////////////////////
@@ -1750,6 +3495,55 @@ pub fn json_to_unsigned(value: &JsonValue) -> Result<u64> {
     }
 }
 
+/// The key of the JSON object that is used to pass a binary value as a query parameter.
+/// See [binary_param()].
+pub const BINARY_PARAM_KEY: &str = "$binary";
+
+/// Encode the given bytes as a base64 string, which is how binary values are represented in JSON
+pub fn encode_binary(bytes: &[u8]) -> String {
+    BASE64_STANDARD.encode(bytes)
+}
+
+/// Decode the given base64 representation of a binary value
+pub fn decode_binary(value: &str) -> Result<Vec<u8>> {
+    BASE64_STANDARD.decode(value).map_err(|err| {
+        RelatableError::DataError(format!(
+            "'{value}' is not a valid base64 binary value: {err}"
+        ))
+        .into()
+    })
+}
+
+/// Wrap the given bytes in a query parameter that will be bound as a binary value (a BLOB in
+/// SQLite, or a BYTEA in PostgreSQL) rather than as text.
+pub fn binary_param(bytes: &[u8]) -> JsonValue {
+    json!({BINARY_PARAM_KEY: encode_binary(bytes)})
+}
+
+/// If the given query parameter was created using [binary_param()], return its bytes.
+fn get_binary_param(param: &JsonValue) -> Option<Vec<u8>> {
+    match param {
+        JsonValue::Object(object) if object.len() == 1 => match object.get(BINARY_PARAM_KEY) {
+            Some(JsonValue::String(value)) => decode_binary(value).ok(),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Convert the given value of a column with the given SQL type to a query parameter. Values of
+/// BLOB columns are represented in JSON as base64 strings, which are bound as binary values.
+/// Any other value, including a BLOB value that is not valid base64, is bound as it is.
+pub fn to_sql_param(value: &JsonValue, sql_type: &str) -> JsonValue {
+    match (canonical_sql_type(sql_type), value) {
+        (Some("BLOB"), JsonValue::String(text)) => match decode_binary(text) {
+            Ok(bytes) => binary_param(&bytes),
+            Err(_) => value.clone(),
+        },
+        _ => value.clone(),
+    }
+}
+
 // From https://stackoverflow.com/a/78372188
 pub trait VecInto<D> {
     fn vec_into(self) -> Vec<D>;
@@ -1854,6 +3648,17 @@ impl JsonRow {
         }
     }
 
+    /// Get the value of the given column from the row and convert it to a string before returning
+    /// it, or return None if the value is NULL
+    pub fn get_optional_string(&self, column_name: &str) -> Result<Option<String>> {
+        tracing::trace!("JsonRow::get_optional_string({self:?}, {column_name})");
+        match self.content.get(column_name) {
+            Some(JsonValue::Null) => Ok(None),
+            Some(value) => Ok(Some(json_to_string(value))),
+            None => Err(RelatableError::DataError("missing value".to_string()).into()),
+        }
+    }
+
     /// Get the value of the given column from the row and convert it to an unsigned integer
     /// before returning it
     pub fn get_unsigned(&self, column_name: &str) -> Result<u64> {
@@ -1865,6 +3670,24 @@ impl JsonRow {
         }
     }
 
+    /// Deserialize the row into a value of the given type, e.g., a struct with a field for each
+    /// of the columns that are of interest. Since the values of a column are not always returned
+    /// by the database as the type that the field expects, integers are accepted for floating
+    /// point fields, floating point numbers without a fractional part and numeric strings for
+    /// integer fields, 0 and 1 for boolean fields, and numbers and booleans for string fields.
+    /// NULL values become `None` for optional fields.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        tracing::trace!("JsonRow::deserialize({self:?})");
+        let deserializer = MapDeserializer::new(
+            self.content
+                .iter()
+                .map(|(column, value)| (column.as_str(), RowValueDeserializer(value))),
+        );
+        T::deserialize(deserializer).map_err(|err: serde_json::Error| {
+            RelatableError::DataError(format!("Could not deserialize row {self}: {err}")).into()
+        })
+    }
+
     /// Initialize a new row from the given list of column names and set all values to
     /// [JsonValue::Null]
     pub fn from_strings(strings: &Vec<&str>) -> Self {
@@ -1912,11 +3735,11 @@ impl JsonRow {
                     rusqlite::types::ValueRef::Null => JsonValue::Null,
                     rusqlite::types::ValueRef::Integer(value) => JsonValue::from(value),
                     rusqlite::types::ValueRef::Real(value) => JsonValue::from(value),
-                    rusqlite::types::ValueRef::Text(value)
-                    | rusqlite::types::ValueRef::Blob(value) => {
+                    rusqlite::types::ValueRef::Text(value) => {
                         let value = std::str::from_utf8(value).unwrap_or_default();
                         JsonValue::from(value)
                     }
+                    rusqlite::types::ValueRef::Blob(value) => JsonValue::from(encode_binary(value)),
                 },
                 Err(_) => JsonValue::Null,
             };
@@ -1962,6 +3785,12 @@ impl TryFrom<AnyRow> for JsonRow {
                     value = JsonValue::from(x);
                 }
             }
+            if value.is_null() {
+                let x: Result<Vec<u8>, sqlx::Error> = row.try_get(column.ordinal());
+                if let Ok(x) = x {
+                    value = JsonValue::from(encode_binary(&x));
+                }
+            }
             content.insert(column.name().into(), value);
         }
         Ok(Self { content })
@@ -2030,6 +3859,13 @@ impl TryFrom<PgRow> for JsonRow {
                         Err(_) => JsonValue::Null,
                     }
                 }
+                "BYTEA" => {
+                    let value: Result<Vec<u8>, sqlx::Error> = row.try_get(column.ordinal());
+                    match value {
+                        Ok(value) => JsonValue::from(encode_binary(&value)),
+                        Err(_) => JsonValue::Null,
+                    }
+                }
                 unsupported => {
                     tracing::warn!(
                         "Got unsupported column '{}' with type '{}'",
@@ -2067,20 +3903,222 @@ impl From<JsonRow> for Vec<String> {
     }
 }
 
+/// Deserializes the value of a column of a [JsonRow], converting between the types of value
+/// that the database may return for the column and the type that is expected of it (see
+/// [JsonRow::deserialize()]). Arrays and objects are deserialized as they are.
+struct RowValueDeserializer<'a>(&'a JsonValue);
+
+impl<'de> IntoDeserializer<'de, serde_json::Error> for RowValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+/// Implements the methods of [RowValueDeserializer] that deserialize integers of the given
+/// types, given the methods of the visitor to which they are passed.
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident: $type:ty),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                match self.0 {
+                    JsonValue::Number(number) if number.is_f64() => {
+                        let in_range = |number: &f64| {
+                            number.fract() == 0.0
+                                && *number >= <$type>::MIN as f64
+                                && *number <= <$type>::MAX as f64
+                        };
+                        match number.as_f64().filter(in_range) {
+                            Some(number) => visitor.$visit(number as $type),
+                            None => self.0.$method(visitor),
+                        }
+                    }
+                    JsonValue::String(string) => match string.trim().parse::<$type>() {
+                        Ok(number) => visitor.$visit(number),
+                        Err(_) => self.0.$method(visitor),
+                    },
+                    JsonValue::Bool(flag) => visitor.$visit(*flag as $type),
+                    _ => self.0.$method(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for RowValueDeserializer<'de> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_any(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Number(number) if number.as_i64() == Some(0) => visitor.visit_bool(false),
+            JsonValue::Number(number) if number.as_i64() == Some(1) => visitor.visit_bool(true),
+            JsonValue::String(string) => match string.to_lowercase().as_str() {
+                "true" | "1" => visitor.visit_bool(true),
+                "false" | "0" => visitor.visit_bool(false),
+                _ => self.0.deserialize_bool(visitor),
+            },
+            _ => self.0.deserialize_bool(visitor),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i64: i64,
+        deserialize_i16 => visit_i64: i64,
+        deserialize_i32 => visit_i64: i64,
+        deserialize_i64 => visit_i64: i64,
+        deserialize_u8 => visit_u64: u64,
+        deserialize_u16 => visit_u64: u64,
+        deserialize_u32 => visit_u64: u64,
+        deserialize_u64 => visit_u64: u64
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Number(number) => match number.as_f64() {
+                Some(number) => visitor.visit_f64(number),
+                None => self.0.deserialize_f64(visitor),
+            },
+            JsonValue::String(string) => match string.trim().parse::<f64>() {
+                Ok(number) => visitor.visit_f64(number),
+                Err(_) => self.0.deserialize_f64(visitor),
+            },
+            _ => self.0.deserialize_f64(visitor),
+        }
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_char(visitor)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Number(_) | JsonValue::Bool(_) => {
+                visitor.visit_string(json_to_string(self.0))
+            }
+            _ => self.0.deserialize_string(visitor),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_byte_buf(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            JsonValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_unit(visitor)
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_map(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_enum(name, variants, visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.0.deserialize_identifier(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
 // Tests
 
 #[cfg(test)]
 mod tests {
-    use crate::{core::Relatable, select::Select, sql::CachingStrategy};
+    use crate::{
+        core::{tests::memory_demo, Relatable, RelatableError},
+        select::Select,
+        sql::{CachingStrategy, DbConnection, IsolationLevel, MEMORY_DB},
+    };
+    use anyhow::Result;
     use async_std::task::block_on;
     use pretty_assertions::assert_eq;
+    use serde_json::json;
 
     // use super::*;
 
     #[test]
     fn test_cache() {
         let rltbl = block_on(Relatable::build_demo(
-            Some("build/test_cache.db"),
+            Some(MEMORY_DB),
             &true,
             10,
             &CachingStrategy::Trigger,
@@ -2099,4 +4137,271 @@ mod tests {
         let count = block_on(rltbl.count(&select)).unwrap();
         assert_eq!(count, 5);
     }
+
+    #[test]
+    fn test_memory_db() {
+        use serde_json::json;
+
+        let rltbl = memory_demo(10);
+        let count = || block_on(rltbl.count(&Select::from("penguin"))).unwrap();
+        assert_eq!(count(), 10);
+
+        // Changes made through one connection are seen by every other connection to the same
+        // in-memory database, including clones:
+        let sql = r#"DELETE FROM "penguin" WHERE "_id" > 4"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert_eq!(count(), 4);
+        let clone = rltbl.try_clone().unwrap();
+        assert_eq!(block_on(clone.count(&Select::from("penguin"))).unwrap(), 4);
+
+        // Each in-memory database is independent of the others:
+        let other = block_on(Relatable::init(
+            &true,
+            Some(MEMORY_DB),
+            &CachingStrategy::None,
+        ))
+        .unwrap();
+        let sql = r#"SELECT COUNT(1) FROM "table" WHERE "table" = 'penguin'"#;
+        assert_eq!(
+            block_on(other.connection.query_value(sql, None)).unwrap(),
+            Some(json!(0))
+        );
+
+        // Nothing is written to disk:
+        assert!(!std::path::Path::new(MEMORY_DB).exists());
+    }
+
+    #[test]
+    fn test_response_cache() {
+        use serde_json::json;
+
+        let rltbl = block_on(Relatable::build_demo(
+            Some(MEMORY_DB),
+            &true,
+            3,
+            &CachingStrategy::Trigger,
+        ))
+        .unwrap();
+        let select = Select::from("penguin");
+        let url = "table/penguin?limit=3";
+        let get_island = || {
+            let result = block_on(rltbl.fetch_cached(&select, url)).unwrap();
+            result.rows[0].cells.get("island").unwrap().text.clone()
+        };
+        let count_cached = || {
+            let sql =
+                r#"SELECT COUNT(1) FROM "cache" WHERE "statement" = 'GET table/penguin?limit=3'"#;
+            block_on(rltbl.connection.query_value(sql, None))
+                .unwrap()
+                .unwrap()
+        };
+
+        // The result is cached, and is the same as an uncached fetch:
+        let island = get_island();
+        assert_eq!(count_cached(), json!(1));
+        assert_eq!(get_island(), island);
+        let cached = block_on(rltbl.fetch_cached(&select, url)).unwrap();
+        let fetched = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(json!(cached.rows), json!(fetched.rows));
+
+        // Changes to the table are seen, whether or not they are recorded in the history:
+        let sql = r#"UPDATE "penguin" SET "island" = 'Atlantis' WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert_eq!(count_cached(), json!(0));
+        assert_eq!(get_island(), "Atlantis");
+        // So are messages, which are added without a change being recorded:
+        block_on(rltbl.add_message(
+            "rltbl",
+            "penguin",
+            1,
+            "island",
+            &json!("Atlantis"),
+            "error",
+            "custom:island",
+            "No such island",
+        ))
+        .unwrap();
+        assert_eq!(count_cached(), json!(0));
+        get_island();
+        assert_eq!(count_cached(), json!(1));
+    }
+
+    #[test]
+    fn test_result_types() {
+        use indexmap::indexmap;
+
+        let rltbl = memory_demo(3);
+
+        // The sqlx driver reports integers as BIGINT:
+        let integer = match rltbl.connection {
+            #[cfg(feature = "sqlx")]
+            DbConnection::Sqlx(..) => "BIGINT",
+            #[cfg(feature = "rusqlite")]
+            DbConnection::Rusqlite(..) => "INTEGER",
+        };
+
+        let mut select = Select::from("penguin");
+        select.select_columns(&vec!["study_name", "sample_number"]);
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(
+            result.types,
+            indexmap! {
+                "study_name".to_string() => "TEXT".to_string(),
+                "sample_number".to_string() => integer.to_string(),
+            }
+        );
+
+        // Expressions, which have no declared type, are given the type of their values:
+        let sql = r#"SELECT COUNT(1) AS "count", 'x' AS "x", NULL AS "y" FROM "penguin""#;
+        let (_, types) = block_on(rltbl.connection.query_with_types(sql, None)).unwrap();
+        assert_eq!(
+            types,
+            indexmap! {
+                "count".to_string() => integer.to_string(),
+                "x".to_string() => "TEXT".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_sql_types() {
+        use crate::{
+            core::TriggerCapture,
+            sql::{
+                canonical_sql_type, generate_table_ddl, render_sql_type, sql_types_compatible,
+                DbKind,
+            },
+            table::{Column, Datatype, Table},
+        };
+
+        assert_eq!(canonical_sql_type("varchar(10)"), Some("TEXT"));
+        assert_eq!(canonical_sql_type("NUMERIC(5, 2)"), Some("NUMERIC"));
+        assert_eq!(canonical_sql_type("bool"), Some("BOOLEAN"));
+        assert_eq!(
+            canonical_sql_type("timestamp without time zone"),
+            Some("TIMESTAMP")
+        );
+        assert_eq!(canonical_sql_type("json"), Some("JSONB"));
+        assert_eq!(canonical_sql_type("bytea"), Some("BLOB"));
+        assert_eq!(canonical_sql_type("geometry"), None);
+
+        assert_eq!(render_sql_type("uuid", &DbKind::Sqlite), "TEXT");
+        assert_eq!(render_sql_type("uuid", &DbKind::Postgres), "UUID");
+        assert_eq!(render_sql_type("jsonb", &DbKind::Postgres), "JSONB");
+        assert_eq!(render_sql_type("date", &DbKind::Sqlite), "DATE");
+        assert_eq!(render_sql_type("BLOB", &DbKind::Sqlite), "BLOB");
+        assert_eq!(render_sql_type("BLOB", &DbKind::Postgres), "BYTEA");
+
+        assert!(sql_types_compatible("TEXT", "DATE").unwrap());
+        assert!(sql_types_compatible("INTEGER", "NUMERIC").unwrap());
+        assert!(sql_types_compatible("DATE", "TIMESTAMP").unwrap());
+        assert!(!sql_types_compatible("NUMERIC", "INTEGER").unwrap());
+        assert!(!sql_types_compatible("INTEGER", "UUID").unwrap());
+        assert!(!sql_types_compatible("INTEGER", "BLOB").unwrap());
+        assert!(sql_types_compatible("INTEGER", "GEOMETRY").is_err());
+
+        // The DDL for a table honors the explicit SQL types of its columns, if they are
+        // compatible with their datatypes:
+        let column = |name: &str, datatype: &str, sql_type: Option<&str>| Column {
+            name: name.to_string(),
+            table: "event".to_string(),
+            datatype: Datatype {
+                name: datatype.to_string(),
+                ..Default::default()
+            },
+            sql_type: sql_type.map(|sql_type| sql_type.to_string()),
+            ..Default::default()
+        };
+        let mut table = Table {
+            name: "event".to_string(),
+            has_meta: false,
+            ..Default::default()
+        };
+        for column in [
+            column("id", "text", Some("UUID")),
+            column("day", "text", Some("DATE")),
+            column("count", "integer", None),
+        ] {
+            table.columns.insert(column.name.to_string(), column);
+        }
+        let ddl = generate_table_ddl(
+            &table,
+            false,
+            &DbKind::Postgres,
+            &CachingStrategy::None,
+            &TriggerCapture,
+        )
+        .unwrap();
+        assert!(ddl[0].contains(r#""id" UUID, "day" DATE, "count" INTEGER"#));
+        let ddl = generate_table_ddl(
+            &table,
+            false,
+            &DbKind::Sqlite,
+            &CachingStrategy::None,
+            &TriggerCapture,
+        )
+        .unwrap();
+        assert!(ddl[0].contains(r#""id" TEXT, "day" DATE, "count" INTEGER"#));
+
+        let bad = column("count", "integer", Some("DATE"));
+        table.columns.insert("count".to_string(), bad);
+        let error = generate_table_ddl(
+            &table,
+            false,
+            &DbKind::Sqlite,
+            &CachingStrategy::None,
+            &TriggerCapture,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("not compatible with its datatype 'integer' (INTEGER)"));
+    }
+
+    #[test]
+    fn test_query_timeout() {
+        let rltbl = memory_demo(10);
+
+        // A query that never finishes is cancelled once its timeout has passed:
+        let sql = r#"WITH RECURSIVE "n"("i") AS (SELECT 1 UNION ALL SELECT "i" + 1 FROM "n")
+                     SELECT COUNT(1) FROM "n""#;
+        let timeout = Some(std::time::Duration::from_millis(100));
+        let error = block_on(rltbl.connection.query_with_timeout(sql, None, timeout)).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref(),
+            Some(crate::core::RelatableError::TimeoutError(_))
+        ));
+
+        // A query that finishes in time is not affected:
+        let select = Select::from("penguin").timeout(std::time::Duration::from_secs(10));
+        assert_eq!(block_on(rltbl.count(&select)).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_isolation_level() {
+        let level = "REPEATABLE_READ".parse::<IsolationLevel>().unwrap();
+        assert_eq!(level, IsolationLevel::RepeatableRead);
+        assert_eq!(IsolationLevel::Serializable.to_string(), "serializable");
+        assert!("snapshot".parse::<IsolationLevel>().is_err());
+
+        let rltbl = memory_demo(10);
+        let mut conn = rltbl.connection.reconnect().unwrap();
+        let mut tx = block_on(
+            rltbl
+                .connection
+                .begin_with(&mut conn, IsolationLevel::Serializable),
+        )
+        .unwrap();
+        let count = tx.query_value(r#"SELECT COUNT(1) FROM "penguin""#, None);
+        assert_eq!(count.unwrap(), Some(json!(10)));
+        tx.commit().unwrap();
+
+        // Only the failures of transactions to serialize are retried:
+        let mut runs = 0;
+        let result: Result<()> = block_on(rltbl.retry_serialization_failures(|| {
+            runs += 1;
+            async { Err(RelatableError::DataError("not retried".to_string()).into()) }
+        }));
+        assert!(result.is_err());
+        assert_eq!(runs, 1);
+    }
 }
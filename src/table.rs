@@ -5,10 +5,12 @@
 use crate::{self as rltbl};
 
 use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use indexmap::IndexMap;
 use lazy_static::lazy_static;
 use rltbl::{
-    core::{Relatable, RelatableError, NEW_ORDER_MULTIPLIER},
+    core::{open_delimited, Relatable, RelatableError, NEW_ORDER_MULTIPLIER},
+    select::Order,
     sql::{self, DbKind, DbTransaction, JsonRow, SqlParam},
 };
 use serde::{Deserialize, Serialize};
@@ -30,6 +32,24 @@ pub struct Table {
     pub editable: bool,
     /// Indicates whether the table has the _id and _order meta columns enabled:
     pub has_meta: bool,
+    /// Indicates whether the table has a materialized _change_id column, maintained by a trigger
+    /// on the history table:
+    pub has_change_id: bool,
+    /// Indicates whether the table has a _uuid column, which identifies each row independently of
+    /// its _id, so that rows can be merged with those of other databases:
+    pub has_uuid: bool,
+    /// The partitions that hold the rows of the table, if it is partitioned by one of its columns
+    /// (see [Relatable::partition_table()]):
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<Partition>,
+    /// Indicates whether some of the table's rows have been archived (see
+    /// [Relatable::archive_rows()]):
+    #[serde(default)]
+    pub has_archive: bool,
+    /// Whether the table has been set to be in wide mode, or not, or, if neither, whether it is
+    /// in wide mode depends on how many columns it has (see [Table::is_wide()]):
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wide: Option<bool>,
 }
 
 impl Default for Table {
@@ -41,6 +61,11 @@ impl Default for Table {
             columns: IndexMap::new(),
             editable: true,
             has_meta: true,
+            has_change_id: false,
+            has_uuid: false,
+            partitions: vec![],
+            has_archive: false,
+            wide: None,
         }
     }
 }
@@ -85,30 +110,61 @@ impl Table {
             None => 0,
         };
 
+        let (columns, meta_columns) = Table::_collect_column_info(table_name, tx)?;
         Ok(Table {
             name: table_name.to_string(),
             view,
             change_id,
-            columns: Table::_collect_column_info(table_name, tx)?
-                .0
+            columns: columns
                 .into_iter()
                 .map(|column| (column.name.clone(), column))
                 .collect::<IndexMap<_, _>>(),
+            has_change_id: meta_columns
+                .iter()
+                .any(|column| column.name == "_change_id"),
+            has_uuid: meta_columns.iter().any(|column| column.name == "_uuid"),
+            editable: !Table::_is_mirrored(table_name, tx)?,
+            partitions: Partition::_get_partitions(table_name, tx)?,
+            // The history and message tables have archives of their own, which hold the history
+            // and messages of the archived rows of other tables:
+            has_archive: !sql::META_TABLES.contains(&table_name)
+                && Table::_table_exists(&sql::archive_name(table_name), tx)?,
+            wide: Table::_get_wide_mode(table_name, tx)?,
             ..Default::default()
         })
     }
 
-    /// Drop the given table in the database
+    /// Drop the given table in the database. When the table is partitioned, its partitions are
+    /// dropped along with it.
     pub async fn drop_table(&mut self, rltbl: &Relatable) -> Result<()> {
         tracing::trace!("Table::drop_data_tables({self:?}, {rltbl:?})");
-        let sql = match rltbl.connection.kind() {
+        let mut statements = vec![];
+        match rltbl.connection.kind() {
             DbKind::Postgres => {
-                format!(r#"DROP TABLE IF EXISTS "{}" CASCADE"#, self.name)
+                statements.push(format!(r#"DROP TABLE IF EXISTS "{}" CASCADE"#, self.name))
+            }
+            DbKind::Sqlite if self.partitions.is_empty() => {
+                statements.push(format!(r#"DROP TABLE IF EXISTS "{}""#, self.name))
+            }
+            // In SQLite, a partitioned table is a view on the tables that hold its partitions:
+            DbKind::Sqlite => {
+                statements.push(format!(r#"DROP VIEW IF EXISTS "{}""#, self.name));
+                for partition in &self.partitions {
+                    statements.push(format!(r#"DROP TABLE IF EXISTS "{}""#, partition.name));
+                }
             }
-            DbKind::Sqlite => format!(r#"DROP TABLE IF EXISTS "{}""#, self.name),
         };
+        if !self.partitions.is_empty() {
+            statements.push(format!(
+                r#"DELETE FROM "partition" WHERE "table" = '{}'"#,
+                self.name
+            ));
+            self.partitions.clear();
+        }
+        for sql in statements {
+            rltbl.connection.query(&sql, None).await?;
+        }
         tracing::info!("Dropped table '{}'", self.name);
-        rltbl.connection.query(&sql, None).await?;
         Ok(())
     }
 
@@ -162,6 +218,113 @@ impl Table {
                 )
             }
         };
+        match tx.query_value(&sql, Some(&params))? {
+            // In SQLite, a partitioned table is a view on the tables that hold its partitions:
+            None if tx.kind() == DbKind::Sqlite && table_name != "partition" => {
+                Ok(!Partition::_get_partitions(table_name, tx)?.is_empty())
+            }
+            None => Ok(false),
+            Some(_) => Ok(true),
+        }
+    }
+
+    /// Determine, using the given transaction, whether the given table is a read-only copy of a
+    /// table in a remote instance, i.e., whether it is listed in the mirror table.
+    pub fn _is_mirrored(table_name: &str, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Table::_is_mirrored({table_name}, tx)");
+        if !Table::_table_exists("mirror", tx)? {
+            return Ok(false);
+        }
+        let sql = format!(
+            r#"SELECT 1 FROM "mirror" WHERE "table" = {sql_param} LIMIT 1"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        match tx.query_value(&sql, Some(&json!([table_name])))? {
+            None => Ok(false),
+            Some(_) => Ok(true),
+        }
+    }
+
+    /// Get whether the given table has been set to be in wide mode, or not, using the given
+    /// transaction (see [Relatable::set_wide_mode()]).
+    pub fn _get_wide_mode(table_name: &str, tx: &mut DbTransaction<'_>) -> Result<Option<bool>> {
+        tracing::trace!("Table::_get_wide_mode({table_name}, tx)");
+        if !Table::_table_exists("table", tx)? {
+            return Ok(None);
+        }
+        let sql = format!(
+            r#"SELECT CAST("wide" AS INT) AS "wide" FROM "table" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        match tx.query_value(&sql, Some(&json!([table_name])))? {
+            Some(JsonValue::Bool(wide)) => Ok(Some(wide)),
+            Some(JsonValue::Number(wide)) => Ok(Some(wide.as_i64() != Some(0))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Determine whether this table is in wide mode, in which case its default view leaves the
+    /// _history and _message columns empty, so that they are looked up for one row at a time
+    /// (see [Relatable::get_row_annotations()]). Unless the table has been set to be in wide
+    /// mode, or not, it is in wide mode when it has more than
+    /// [wide_column_limit](Relatable::wide_column_limit) columns.
+    pub fn is_wide(&self, rltbl: &Relatable) -> bool {
+        match self.wide {
+            Some(wide) => wide,
+            None => rltbl.wide_column_limit > 0 && self.columns.len() > rltbl.wide_column_limit,
+        }
+    }
+
+    /// Determine whether a trigger with the given name exists in the database, using the given
+    /// transaction.
+    pub fn _trigger_exists(trigger_name: &str, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Table::_trigger_exists({trigger_name}, tx)");
+        let sql_param = SqlParam::new(&tx.kind()).next();
+        let sql = match tx.kind() {
+            DbKind::Sqlite => format!(
+                r#"SELECT 1 FROM "sqlite_master"
+                   WHERE "type" = 'trigger' AND "name" = {sql_param} LIMIT 1"#
+            ),
+            DbKind::Postgres => format!(
+                r#"SELECT 1 FROM "information_schema"."triggers"
+                   WHERE "trigger_name" = {sql_param}
+                     AND "trigger_schema" IN (
+                       SELECT REGEXP_SPLIT_TO_TABLE("setting", ', ')
+                       FROM "pg_settings"
+                       WHERE "name" = 'search_path'
+                     )
+                   LIMIT 1"#
+            ),
+        };
+        let params = json!([trigger_name]);
+        match tx.query_value(&sql, Some(&params))? {
+            None => Ok(false),
+            Some(_) => Ok(true),
+        }
+    }
+
+    /// Determine whether an index with the given name exists in the database, using the given
+    /// transaction.
+    pub fn _index_exists(index_name: &str, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Table::_index_exists({index_name}, tx)");
+        let sql_param = SqlParam::new(&tx.kind()).next();
+        let sql = match tx.kind() {
+            DbKind::Sqlite => format!(
+                r#"SELECT 1 FROM "sqlite_master"
+                   WHERE "type" = 'index' AND "name" = {sql_param} LIMIT 1"#
+            ),
+            DbKind::Postgres => format!(
+                r#"SELECT 1 FROM "pg_indexes"
+                   WHERE "indexname" = {sql_param}
+                     AND "schemaname" IN (
+                       SELECT REGEXP_SPLIT_TO_TABLE("setting", ', ')
+                       FROM "pg_settings"
+                       WHERE "name" = 'search_path'
+                     )
+                   LIMIT 1"#
+            ),
+        };
+        let params = json!([index_name]);
         match tx.query_value(&sql, Some(&params))? {
             None => Ok(false),
             Some(_) => Ok(true),
@@ -262,7 +425,10 @@ impl Table {
         let mut dependent_tables: Vec<Table> = vec![];
         for row in &tx.query(&sql, Some(&params))? {
             let Structure::From(structure_table, structure_column) =
-                Structure::from_str(&row.get_string("structure")?)?;
+                Structure::from_str(&row.get_string("structure")?)?
+            else {
+                continue;
+            };
             if let Some(structure_table) = structure_table {
                 if structure_table == self.name {
                     match column {
@@ -292,6 +458,75 @@ impl Table {
         Ok(dependent_tables)
     }
 
+    /// Get the dependencies of each of this table's columns, i.e., which columns, in this or other
+    /// tables, it depends on through its structure or the rules of this table, and which
+    /// columns depend, directly or indirectly, on it. After a column is edited, its dependents
+    /// are the columns that must be validated again.
+    pub async fn get_dependencies(
+        &self,
+        rltbl: &Relatable,
+    ) -> Result<IndexMap<String, ColumnDependencies>> {
+        tracing::trace!("Table::get_dependencies({self:?}, {rltbl:?})");
+        let mut conn = rltbl.connection.reconnect()?;
+        let mut tx = rltbl.connection.begin(&mut conn).await?;
+        let dependencies = self._get_dependencies(&mut tx)?;
+        tx.commit()?;
+        Ok(dependencies)
+    }
+
+    /// Get the dependencies of each of this table's columns using the given transaction (see
+    /// [Table::get_dependencies()]).
+    pub fn _get_dependencies(
+        &self,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<IndexMap<String, ColumnDependencies>> {
+        tracing::trace!("Table::_get_dependencies({self:?}, tx)");
+        let dependency = |table: &str, column: &str, kind: &str| ColumnDependency {
+            table: table.to_string(),
+            column: column.to_string(),
+            kind: kind.to_string(),
+        };
+        let mut dependencies = IndexMap::new();
+        for column in self.columns.values() {
+            let mut column_dependencies = ColumnDependencies::default();
+            if let Some(Structure::From(table, from_column)) = &column.structure {
+                let table = table.as_deref().unwrap_or(&self.name);
+                column_dependencies
+                    .depends_on
+                    .push(dependency(table, from_column, "structure"));
+            }
+            for dependent in column._get_dependent_columns(tx)? {
+                let dependent = dependency(&dependent.table, &dependent.name, "structure");
+                if !column_dependencies.dependents.contains(&dependent) {
+                    column_dependencies.dependents.push(dependent);
+                }
+            }
+            dependencies.insert(column.name.clone(), column_dependencies);
+        }
+
+        // Rules only ever relate two columns of the same table:
+        for rule in Rule::_get_rules(&self.name, tx)? {
+            if rule.when_column == rule.then_column {
+                continue;
+            }
+            let (when, then) = (
+                dependency(&self.name, &rule.when_column, "rule"),
+                dependency(&self.name, &rule.then_column, "rule"),
+            );
+            if let Some(column_dependencies) = dependencies.get_mut(&rule.then_column) {
+                if !column_dependencies.depends_on.contains(&when) {
+                    column_dependencies.depends_on.push(when);
+                }
+            }
+            if let Some(column_dependencies) = dependencies.get_mut(&rule.when_column) {
+                if !column_dependencies.dependents.contains(&then) {
+                    column_dependencies.dependents.push(then);
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
     /// Set the view for the table to the given view type (accepted types are "default" and "text"),
     /// after first ensuring that a view of the given type exists, creating it if necessary.
     pub async fn set_view(&mut self, rltbl: &Relatable, view_type: &str) -> Result<&Self> {
@@ -338,15 +573,72 @@ impl Table {
         tracing::debug!(r#"Creating default view "{view_name}" with columns {columns:?}"#);
 
         let (id_col, order_col) = self.get_id_order_columns(&meta_columns);
+        let options = sql::ViewOptions {
+            source: None,
+            has_change_id: meta_columns
+                .iter()
+                .any(|column| column.name == "_change_id"),
+            has_uuid: meta_columns.iter().any(|column| column.name == "_uuid"),
+            wide: self.is_wide(rltbl),
+        };
 
-        for sql in sql::generate_default_view_ddl(
-            &self.name,
-            id_col,
-            order_col,
-            &columns,
-            &rltbl.connection.kind(),
-        ) {
-            rltbl.connection.query(&sql, None).await?;
+        // The default view on a partitioned table combines the default views on its partitions,
+        // which can also be read separately (see [Select::partitions](crate::select::Select)):
+        let mut sources = vec![None];
+        if !self.partitions.is_empty() {
+            sources = self
+                .partitions
+                .iter()
+                .map(|partition| Some(partition.name.as_str()))
+                .collect();
+        }
+        for partition in sources {
+            let options = sql::ViewOptions {
+                source: partition,
+                ..options
+            };
+            for sql in sql::generate_default_view_ddl(
+                &self.name,
+                id_col,
+                order_col,
+                &columns,
+                &options,
+                &rltbl.connection.kind(),
+            ) {
+                rltbl.connection.query(&sql, None).await?;
+            }
+        }
+        // Archived rows are read through a default view of their own (see
+        // [Select::include_archived](crate::select::Select)):
+        if self.has_archive {
+            let archive = sql::archive_name(&self.name);
+            let options = sql::ViewOptions {
+                source: Some(&archive),
+                ..options
+            };
+            for sql in sql::generate_default_view_ddl(
+                &self.name,
+                id_col,
+                order_col,
+                &columns,
+                &options,
+                &rltbl.connection.kind(),
+            ) {
+                rltbl.connection.query(&sql, None).await?;
+            }
+        }
+        if !self.partitions.is_empty() {
+            for sql in sql::generate_partitioned_view_ddl(
+                &view_name,
+                &self
+                    .partitions
+                    .iter()
+                    .map(|partition| format!("{}_default_view", partition.name))
+                    .collect::<Vec<_>>(),
+                &rltbl.connection.kind(),
+            ) {
+                rltbl.connection.query(&sql, None).await?;
+            }
         }
 
         // Set the table's view name to the default view:
@@ -418,8 +710,36 @@ impl Table {
             Ok(IndexMap::new())
         } else {
             let sql = match Table::_table_exists("datatype", tx)? {
-                true => format!(
-                    r#"SELECT
+                true => {
+                    // The sql_type column of the column table and the level column of the
+                    // datatype table are optional:
+                    let sql_type_select = match Table::_has_column("column", "sql_type", tx)? {
+                        true => r#"c."sql_type","#,
+                        false => "",
+                    };
+                    let foreign_key_select = match Table::_has_column("column", "foreign_key", tx)?
+                    {
+                        true => r#"c."foreign_key","#,
+                        false => "",
+                    };
+                    let locale_select = match Table::_has_column("column", "locale", tx)? {
+                        true => r#"c."locale","#,
+                        false => "",
+                    };
+                    let collation_select = match Table::_has_column("column", "collation", tx)? {
+                        true => r#"c."collation","#,
+                        false => "",
+                    };
+                    let transform_select = match Table::_has_column("column", "transform", tx)? {
+                        true => r#"c."transform","#,
+                        false => "",
+                    };
+                    let level_select = match Table::_has_column("datatype", "level", tx)? {
+                        true => r#", d."level" AS "datatype_level""#,
+                        false => "",
+                    };
+                    format!(
+                        r#"SELECT
                          c."table",
                          c."column",
                          c."label",
@@ -427,16 +747,22 @@ impl Table {
                          c."nulltype",
                          c."datatype",
                          c."structure",
+                         {sql_type_select}
+                         {foreign_key_select}
+                         {locale_select}
+                         {collation_select}
+                         {transform_select}
                          d."description" AS "datatype_description",
                          d."parent" AS "datatype_parent",
                          d."condition" AS "datatype_condition",
                          d."sql_type" AS "datatype_sql_type",
-                         d."format" AS "datatype_format"
+                         d."format" AS "datatype_format"{level_select}
                        FROM "column" c
                          LEFT JOIN "datatype" d ON c."datatype" = d."datatype"
                        WHERE c."table" = {sql_param}"#,
-                    sql_param = SqlParam::new(&tx.kind()).next()
-                ),
+                        sql_param = SqlParam::new(&tx.kind()).next()
+                    )
+                }
                 false => format!(
                     r#"SELECT * FROM "column" WHERE "table" = {sql_param}"#,
                     sql_param = SqlParam::new(&tx.kind()).next()
@@ -469,6 +795,7 @@ impl Table {
                             .unwrap_or_default(),
                         sql_type: json_col.get_string("datatype_sql_type").unwrap_or_default(),
                         format: json_col.get_string("datatype_format").unwrap_or_default(),
+                        level: json_col.get_string("datatype_level").unwrap_or_default(),
                     },
                 };
                 let nulltype = match json_col.get_string("nulltype").ok() {
@@ -487,6 +814,37 @@ impl Table {
                     Some(structure) if structure == "" => None,
                     Some(structure) => Some(Structure::from_str(&structure)?),
                 };
+                let foreign_key = match json_col.get_string("foreign_key").ok() {
+                    None => None,
+                    Some(foreign_key) if foreign_key.is_empty() => None,
+                    Some(foreign_key) => Some(ForeignKey::from_str(&foreign_key)?),
+                };
+                let locale = match json_col.get_string("locale").ok() {
+                    None => None,
+                    Some(locale) if locale.is_empty() => None,
+                    Some(locale) => Some(Locale::from_str(&locale)?),
+                };
+                let collation = match json_col.get_string("collation").ok() {
+                    None => None,
+                    Some(collation) if collation.is_empty() => None,
+                    Some(collation) => Some(Collation::from_str(&collation)?),
+                };
+                let transforms = match json_col.get_string("transform").ok() {
+                    None => vec![],
+                    // An invalid transform, which `rltbl check config` reports, should not
+                    // prevent the table from being read, so its column is left untransformed:
+                    Some(transforms) => match Transform::parse_list(&transforms) {
+                        Ok(transforms) => transforms,
+                        Err(error) => {
+                            tracing::warn!(
+                                "Ignoring the invalid transforms '{transforms}' of column '{}' \
+                                 of table '{table_name}': {error}",
+                                json_col.get_string("column").unwrap_or_default()
+                            );
+                            vec![]
+                        }
+                    },
+                };
                 let column_name = json_col.get_string("column")?;
                 let column = Column {
                     name: column_name.clone(),
@@ -497,6 +855,14 @@ impl Table {
                     datatype: datatype,
                     nulltype: nulltype,
                     structure: structure,
+                    sql_type: match json_col.get_string("sql_type").unwrap_or_default() {
+                        sql_type if sql_type.is_empty() => None,
+                        sql_type => Some(sql_type),
+                    },
+                    foreign_key,
+                    locale,
+                    collation,
+                    transforms,
                     ..Default::default()
                 };
                 columns.insert(column_name, column);
@@ -506,6 +872,15 @@ impl Table {
         }
     }
 
+    /// Query the database through the given [DbTransaction] to determine whether the given table
+    /// has a column with the given name.
+    pub fn _has_column(table: &str, column: &str, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Table::_has_column({table}, {column}, tx)");
+        Ok(Table::get_db_table_columns(table, tx)?
+            .iter()
+            .any(|c| c.get_string("name").unwrap_or_default() == column))
+    }
+
     /// Query the database for the column names associated with the given table and their
     /// datatypes
     fn get_db_table_columns(table: &str, tx: &mut DbTransaction<'_>) -> Result<Vec<JsonRow>> {
@@ -514,7 +889,7 @@ impl Table {
             DbKind::Sqlite => {
                 let sql = format!(
                     r#"SELECT "name", "type" AS "datatype", "pk"
-                       FROM pragma_table_info("{table}") ORDER BY "cid""#
+                       FROM pragma_table_info('{table}') ORDER BY "cid""#
                 );
                 let mut columns_info = vec![];
                 for column_info in tx.query(&sql, None)? {
@@ -528,13 +903,13 @@ impl Table {
                         column_info.content.insert("unique".to_string(), json!(0));
                         let sql = format!(
                             r#"SELECT "name", "unique"
-                               FROM PRAGMA_INDEX_LIST("{table}")"#
+                               FROM PRAGMA_INDEX_LIST('{table}')"#
                         );
                         for index_info in tx.query(&sql, None)? {
                             if index_info.get_unsigned("unique")? == 1 {
                                 let idx_name = index_info.get_string("name")?;
                                 let sql = format!(
-                                    r#"SELECT "name" FROM PRAGMA_INDEX_INFO("{idx_name}")"#
+                                    r#"SELECT "name" FROM PRAGMA_INDEX_INFO('{idx_name}')"#
                                 );
                                 if let Some(idx_cname) = tx.query_value(&sql, None)? {
                                     if idx_cname == column_info.get_string("name")? {
@@ -719,6 +1094,13 @@ impl Table {
                         structure: column_columns
                             .get(&column_name)
                             .and_then(|col| col.structure.clone()),
+                        sql_type: column_columns
+                            .get(&column_name)
+                            .and_then(|col| col.sql_type.clone()),
+                        locale: column_columns.get(&column_name).and_then(|col| col.locale),
+                        collation: column_columns
+                            .get(&column_name)
+                            .and_then(|col| col.collation),
                         name: column_name,
                         table: table_name.to_string(),
                         primary_key: db_column.get_unsigned("pk")? == 1,
@@ -814,6 +1196,52 @@ impl Table {
         tx.query_one(&sql, Some(&params))
     }
 
+    /// Returns the _id of the row of the given table identified by the given key, which is either
+    /// the row's _id or, if the table has a _uuid column, its UUID.
+    pub async fn get_row_id(table: &str, key: &str, rltbl: &Relatable) -> Result<u64> {
+        tracing::trace!("Table::get_row_id({table}, {key}, {rltbl:?})");
+        let mut conn = rltbl.connection.reconnect()?;
+        // Begin a transaction:
+        let mut tx = rltbl.connection.begin(&mut conn).await?;
+
+        let row_id = Table::_get_row_id(table, key, &mut tx)?;
+
+        // Commit the transaction:
+        tx.commit()?;
+
+        Ok(row_id)
+    }
+
+    /// Returns the _id of the row of the given table identified by the given key, which is either
+    /// the row's _id or, if the table has a _uuid column, its UUID, using the given transaction.
+    pub fn _get_row_id(table: &str, key: &str, tx: &mut DbTransaction<'_>) -> Result<u64> {
+        tracing::trace!("Table::_get_row_id({table}, {key}, tx)");
+        if let Ok(row_id) = key.parse::<u64>() {
+            return Ok(row_id);
+        }
+        if !Table::_has_column(table, "_uuid", tx)? {
+            return Err(RelatableError::InputError(format!(
+                "Invalid row '{key}': table '{table}' does not have UUID row identifiers"
+            ))
+            .into());
+        }
+        let sql = format!(
+            r#"SELECT "_id" FROM "{table}" WHERE "_uuid" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([key.to_lowercase()]);
+        match tx.query_value(&sql, Some(&params))? {
+            Some(value) => value.as_u64().ok_or(
+                RelatableError::DataError(format!("Invalid _id for row '{key}' of '{table}'"))
+                    .into(),
+            ),
+            None => Err(RelatableError::MissingError(format!(
+                "No row in '{table}' with UUID '{key}'"
+            ))
+            .into()),
+        }
+    }
+
     /// Determine what the next created row id for the given table will be
     pub async fn get_next_id(&self, rltbl: &Relatable) -> Result<u64> {
         tracing::trace!("Table::get_next_id({self:?}, {rltbl:?})");
@@ -833,11 +1261,24 @@ impl Table {
     pub fn _get_next_id(&self, tx: &mut DbTransaction<'_>) -> Result<u64> {
         tracing::trace!("Table::_get_next_id({self:?}, tx)");
         let current_row_id = match tx.kind() {
-            DbKind::Sqlite => {
+            DbKind::Sqlite if self.partitions.is_empty() => {
                 let sql = r#"SELECT seq FROM sqlite_sequence WHERE name = ?"#;
                 let params = json!([self.name]);
                 tx.query_value(sql, Some(&params))?
             }
+            // The rows of all of the partitions of a table are numbered in a single sequence:
+            DbKind::Sqlite => {
+                let sql = format!(
+                    r#"SELECT MAX(seq) FROM sqlite_sequence WHERE name IN ({sql_params})"#,
+                    sql_params = SqlParam::new(&tx.kind()).get_as_list(self.partitions.len())
+                );
+                let params = json!(self
+                    .partitions
+                    .iter()
+                    .map(|partition| partition.name.to_string())
+                    .collect::<Vec<_>>());
+                tx.query_value(&sql, Some(&params))?
+            }
             DbKind::Postgres => {
                 let sql = format!(
                     // Note that in the case of postgres an _id column is required.
@@ -873,6 +1314,30 @@ impl Table {
         }
     }
 
+    /// Returns the _id of the row that comes immediately after the given row in the given table,
+    /// or after the beginning of the table if the given row is 0, or None if there is no such row.
+    pub fn _get_next_row_id(
+        table: &str,
+        row: u64,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Option<u64>> {
+        tracing::trace!("Table::_get_next_row_id({table}, {row}, tx)");
+        let curr_row_order = match row {
+            0 => 0,
+            _ => Table::_get_row_order(table, row, tx)?,
+        };
+        let sql = format!(
+            r#"SELECT "_id" FROM "{table}" WHERE "_order" > {sql_param}
+               ORDER BY "_order" LIMIT 1"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([curr_row_order]);
+        match tx.query_one(&sql, Some(&params))? {
+            Some(json_row) => Ok(Some(json_row.get_unsigned("_id")?)),
+            None => Ok(None),
+        }
+    }
+
     /// Returns the value of the _order column of the given row from the given table using the
     /// given transaction.
     fn _get_row_order(table: &str, row: u64, tx: &mut DbTransaction<'_>) -> Result<u64> {
@@ -905,9 +1370,55 @@ pub struct Column {
     pub datatype_hierarchy: Vec<Datatype>,
     pub nulltype: Option<Datatype>,
     pub structure: Option<Structure>,
+    /// An explicit SQL type for the column, from the (optional) sql_type column of the column
+    /// table, which overrides the SQL type inferred from its datatype
+    pub sql_type: Option<String>,
+    /// Whether, and how, the column's `from()` structure is enforced as a FOREIGN KEY, from the
+    /// (optional) foreign_key column of the column table
+    pub foreign_key: Option<ForeignKey>,
+    /// The locale in which the column's values are written in its text view and files, from the
+    /// (optional) locale column of the column table
+    pub locale: Option<Locale>,
+    /// How the column's values are ordered, from the (optional) collation column of the column
+    /// table
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collation: Option<Collation>,
+    /// The transforms to apply, in order, to the column's values when they are loaded, before
+    /// they are validated, from the (optional) transform column of the column table
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<Transform>,
 }
 
 impl Column {
+    /// Return the SQL type of this column: either its explicit [sql_type](Column::sql_type), or
+    /// else the SQL type inferred from its datatype.
+    pub fn get_sql_type(&self) -> String {
+        match &self.sql_type {
+            Some(sql_type) => sql_type.to_string(),
+            None => self.datatype.infer_sql_type(&self.datatype_hierarchy),
+        }
+    }
+
+    /// Check that this column's explicit [sql_type](Column::sql_type), if it has one, is
+    /// compatible with the SQL type inferred from its datatype, i.e., that every value of the
+    /// datatype can be stored in a column of that type.
+    pub fn check_sql_type(&self) -> Result<()> {
+        if let Some(sql_type) = &self.sql_type {
+            let inferred = self.datatype.infer_sql_type(&self.datatype_hierarchy);
+            if !sql::sql_types_compatible(&inferred, sql_type)? {
+                return Err(RelatableError::InputError(format!(
+                    "SQL type '{sql_type}' of column '{table}.{column}' is not compatible with \
+                     its datatype '{datatype}' ({inferred})",
+                    table = self.table,
+                    column = self.name,
+                    datatype = self.datatype.name,
+                ))
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     /// Get the columns, either from the same or from another table, that depend on this column,
     /// using the given transaction
     pub fn _get_dependent_columns(&self, tx: &mut DbTransaction<'_>) -> Result<Vec<Self>> {
@@ -932,7 +1443,10 @@ impl Column {
         for row in &tx.query(&sql, None)? {
             let dependent_table = Table::_get_table(&row.get_string("table")?, tx)?;
             let Structure::From(structure_table, structure_column) =
-                Structure::from_str(&row.get_string("structure")?)?;
+                Structure::from_str(&row.get_string("structure")?)?
+            else {
+                continue;
+            };
             let structure_table = structure_table.unwrap_or(dependent_table.name.to_string());
             if structure_table == self.table && structure_column == self.name {
                 let dependent_column = row.get_string("column")?;
@@ -974,9 +1488,53 @@ pub struct Datatype {
     pub condition: String,
     pub sql_type: String,
     pub format: String,
+    /// The level of the messages generated when a value fails this datatype's condition. When
+    /// this is empty, the level defaults to "error".
+    pub level: String,
 }
 
 impl Datatype {
+    /// Return the level of the messages generated when a value fails this datatype's condition
+    pub fn get_level(&self) -> &str {
+        match self.level.as_str() {
+            "" => "error",
+            level => level,
+        }
+    }
+
+    /// Return the values that satisfy this datatype's condition, when it is an `equals(...)` or an
+    /// `in(...)` condition, e.g., the empty string for the built-in `empty` datatype. For any other
+    /// condition, the list is empty.
+    pub fn get_condition_values(&self) -> Vec<String> {
+        let unquoted_re = regex::Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#).unwrap();
+        let condition = self.condition.trim();
+        let values = match condition {
+            condition if condition.starts_with("equals(") => {
+                let re = regex::Regex::new(r"equals\((.+?)\)").unwrap();
+                match re.captures(condition) {
+                    Some(captures) => vec![captures[1].to_string()],
+                    None => vec![],
+                }
+            }
+            condition if condition.starts_with("in(") => {
+                let re = regex::Regex::new(r"in\((.+?)\)").unwrap();
+                let list_separator = regex::Regex::new(r"\s*,\s*").unwrap();
+                match re.captures(condition) {
+                    Some(captures) => list_separator
+                        .split(&captures[1])
+                        .map(|item| item.to_string())
+                        .collect(),
+                    None => vec![],
+                }
+            }
+            _ => vec![],
+        };
+        values
+            .iter()
+            .map(|value| unquoted_re.replace(value, "$unquoted").to_string())
+            .collect()
+    }
+
     /// Return the SQL type corresponding to the given datatype, or to one of its parents if it
     /// has no sql_type.
     pub fn infer_sql_type(&self, dt_hierarchy: &Vec<Datatype>) -> String {
@@ -990,25 +1548,11 @@ impl Datatype {
             parent.infer_sql_type(&ancestors)
         } else {
             // Handle built-in types:
-            let sql_type = match self.name.to_lowercase().as_str() {
-                "text" => "TEXT",
-                "int" | "integer" | "tinyint" | "smallint" | "mediumint" | "bigint" => "INTEGER",
-                "real" | "decimal" | "numeric" => "NUMERIC",
-                datatype
-                    if (datatype.starts_with("real")
-                        || datatype.starts_with("numeric")
-                        || datatype.starts_with("decimal")) =>
-                {
-                    "NUMERIC"
-                }
-                datatype
-                    if (datatype.starts_with("varchar") || datatype.starts_with("character")) =>
-                {
-                    "TEXT"
-                }
-                datatype if BUILTIN_DATATYPES.contains(&datatype) => "TEXT",
-                unknown => {
-                    tracing::warn!("Cannot infer SQL type for unknown datatype '{unknown}'");
+            let sql_type = match sql::canonical_sql_type(&self.name) {
+                Some(sql_type) => sql_type,
+                None if BUILTIN_DATATYPES.contains(&self.name.to_lowercase().as_str()) => "TEXT",
+                None => {
+                    tracing::warn!("Cannot infer SQL type for unknown datatype '{}'", self.name);
                     "TEXT"
                 }
             };
@@ -1156,6 +1700,7 @@ impl Datatype {
                         condition: dt_row.get_string("condition")?,
                         sql_type: dt_row.get_string("sql_type")?,
                         format: dt_row.get_string("format")?,
+                        level: dt_row.get_string("level").unwrap_or_default(),
                     },
                 );
             }
@@ -1185,6 +1730,42 @@ impl Datatype {
         }
     }
 
+    /// Get the columns, configured in the column table, whose datatype is either the given
+    /// datatype or one of its descendants, i.e., the columns whose validation depends on it,
+    /// using the given transaction
+    pub fn _get_dependent_columns(
+        datatype: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<Vec<Column>> {
+        tracing::trace!("Datatype::_get_dependent_columns({datatype}, tx)");
+
+        if !Table::_table_exists("column", tx)? {
+            tracing::debug!("No column table found");
+            return Ok(vec![]);
+        }
+
+        let sql = format!(
+            r#"SELECT DISTINCT "table" FROM "column" WHERE "datatype" {is_not} NULL
+               ORDER BY "table""#,
+            is_not = sql::is_not_clause(&tx.kind())
+        );
+        let mut dependent_columns = vec![];
+        for row in &tx.query(&sql, None)? {
+            let table = Table::_get_table(&row.get_string("table")?, tx)?;
+            for column in table.columns.values() {
+                if column.datatype.name == datatype
+                    || column
+                        .datatype_hierarchy
+                        .iter()
+                        .any(|dt| dt.name == datatype)
+                {
+                    dependent_columns.push(column.clone());
+                }
+            }
+        }
+        Ok(dependent_columns)
+    }
+
     /// Get all of this datatype's ancestors
     pub async fn get_all_ancestors(&self, rltbl: &Relatable) -> Result<Vec<Self>> {
         tracing::trace!("Datatype::get_all_ancestors({self:?}, {rltbl:?})");
@@ -1220,6 +1801,7 @@ impl Datatype {
                                 condition: row.get_string("condition").unwrap_or_default(),
                                 sql_type: row.get_string("sql_type").unwrap_or_default(),
                                 format: row.get_string("format").unwrap_or_default(),
+                                level: row.get_string("level").unwrap_or_default(),
                             },
                         );
                     }
@@ -1260,141 +1842,131 @@ impl Datatype {
         build_hierarchy(&datatypes, &self.name, &self.name)
     }
 
-    /// Validate a column of a database table, optionally only for the given row, using the
-    /// given transaction. Returns true whenever messages are inserted to the message table as a
-    /// result of validation, and false otherwise.
+    /// Validate a column of a database table against this datatype's condition, optionally only
+    /// for the given row, using the given transaction. Rows that already have a datatype message
+    /// for the column are skipped, so that when the datatypes in a column's hierarchy are
+    /// validated from the most general to the most specific, each invalid value is reported only
+    /// once, for the most general datatype that it fails. `passing`, if given, is the most specific
+    /// ancestor of this datatype that the value satisfies, and is named in the message. Returns
+    /// true whenever messages are inserted to the message table as a result of validation, and
+    /// false otherwise.
     pub fn validate(
         &self,
         column: &Column,
         row: Option<&u64>,
+        passing: Option<&Datatype>,
         tx: &mut DbTransaction<'_>,
     ) -> Result<bool> {
-        tracing::trace!("Datatype::validate({self:?}, {column:?}, {row:?}, tx)");
+        tracing::trace!("Datatype::validate({self:?}, {column:?}, {row:?}, {passing:?}, tx)");
         let table_name = column.table.as_str();
         let column_name = column.name.as_str();
         let unquoted_re = regex::Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#)?;
-        let mut messages_were_added = false;
+        let mut sql_param_gen = SqlParam::new(&tx.kind());
+        let message = match passing {
+            Some(passing) => format!(
+                "{column_name} is not a valid {}, but is a {}",
+                self.name, passing.name
+            ),
+            None => format!("{column_name} is not a valid {}", self.name),
+        };
+        let rule = format!("datatype:{}", self.name);
+        let level = SeverityOverride::_get_level(table_name, &rule, self.get_level(), tx)?;
+        let mut params = vec![
+            json!(table_name),
+            json!(column_name),
+            json!(level),
+            json!(rule),
+            json!(message),
+        ];
+        let mut sql = format!(
+            r#"INSERT INTO "message"
+                 ("added_by", "table", "row", "column", "value", "level", "rule", "message")
+               SELECT
+                 'rltbl' AS "added_by",
+                 {sql_param_1} AS "table",
+                 "_id" AS "row",
+                 {sql_param_2} AS "column",
+                 "{column_name}" AS "value",
+                 {sql_param_3} AS "level",
+                 {sql_param_4} AS "rule",
+                 {sql_param_5} AS "message"
+               FROM "{table_name}""#,
+            sql_param_1 = sql_param_gen.next(),
+            sql_param_2 = sql_param_gen.next(),
+            sql_param_3 = sql_param_gen.next(),
+            sql_param_4 = sql_param_gen.next(),
+            sql_param_5 = sql_param_gen.next(),
+        );
+
+        // Add the WHERE clause corresponding to the datatype's condition:
         match self.condition.as_str() {
-            "" => (),
+            "" => return Ok(false),
             condition if condition.starts_with("equals(") => {
                 let re = regex::Regex::new(r"equals\((.+?)\)")?;
-                if let Some(captures) = re.captures(condition) {
-                    let condition = &captures[1];
-                    let condition = unquoted_re.replace(&condition, "$unquoted");
-                    let mut sql_param_gen = SqlParam::new(&tx.kind());
-                    let mut sql = format!(
-                        r#"INSERT INTO "message"
-                             ("added_by", "table", "row", "column", "value", "level", "rule",
-                              "message")
-                           SELECT
-                             'rltbl' AS "added_by",
-                             {sql_param_1} AS "table",
-                             "_id" AS "row",
-                             {sql_param_2} AS "column",
-                             "{column_name}" AS "value",
-                             'error' AS "level",
-                             {sql_param_3} AS "rule",
-                             {sql_param_4} AS "message"
-                           FROM "{table_name}"
-                           WHERE "{column_name}" != {sql_param_5}"#,
-                        sql_param_1 = sql_param_gen.next(),
-                        sql_param_2 = sql_param_gen.next(),
-                        sql_param_3 = sql_param_gen.next(),
-                        sql_param_4 = sql_param_gen.next(),
-                        sql_param_5 = sql_param_gen.next(),
-                    );
-                    let params;
-                    match row {
-                        Some(row) => {
-                            sql.push_str(&format!(
-                                r#" AND "_id" = {sql_param}"#,
-                                sql_param = sql_param_gen.next()
-                            ));
-                            params = json!([
-                                table_name,
-                                column_name,
-                                format!("datatype:{}", column.datatype.name),
-                                format!("{column_name} must be a {}", column.datatype.name),
-                                condition,
-                                row
-                            ]);
-                        }
-                        None => {
-                            params = json!([
-                                table_name,
-                                column_name,
-                                format!("datatype:{}", column.datatype.name),
-                                format!("{column_name} must be a {}", column.datatype.name),
-                                condition
-                            ]);
-                        }
-                    };
-                    sql.push_str(r#" RETURNING 1 AS "inserted""#);
-                    if let Some(_) = tx.query_one(&sql, Some(&params))? {
-                        messages_were_added = true;
+                match re.captures(condition) {
+                    Some(captures) => {
+                        let condition = &captures[1];
+                        let condition = unquoted_re.replace(condition, "$unquoted");
+                        sql.push_str(&format!(
+                            r#" WHERE "{column_name}" != {sql_param}"#,
+                            sql_param = sql_param_gen.next()
+                        ));
+                        params.push(json!(condition));
                     }
-                }
+                    None => return Ok(false),
+                };
             }
             condition if condition.starts_with("in(") => {
-                let re = regex::Regex::new(r"in\((.+?)\)").unwrap();
-                if let Some(captures) = re.captures(condition) {
-                    let list_separator = regex::Regex::new(r"\s*,\s*").unwrap();
-                    let condition_list_str = &captures[1];
-                    let condition_list = list_separator
-                        .split(condition_list_str)
-                        .map(|item| unquoted_re.replace(item, "$unquoted"))
-                        .collect::<Vec<_>>();
-                    let mut sql_param_gen = SqlParam::new(&tx.kind());
-                    let mut sql = format!(
-                        r#"INSERT INTO "message"
-                             ("added_by", "table", "row", "column", "value", "level", "rule",
-                              "message")
-                           SELECT
-                             'rltbl' AS "added_by",
-                             {sql_param_1} AS "table",
-                             "_id" AS "row",
-                             {sql_param_2} AS "column",
-                             "{column_name}" AS "value",
-                             'error' AS "level",
-                             {sql_param_3} AS "rule",
-                             {sql_param_4} AS "message"
-                           FROM "{table_name}"
-                           WHERE "{column_name}" NOT IN ({sql_param_5})"#,
-                        sql_param_1 = sql_param_gen.next(),
-                        sql_param_2 = sql_param_gen.next(),
-                        sql_param_3 = sql_param_gen.next(),
-                        sql_param_4 = sql_param_gen.next(),
-                        sql_param_5 = sql_param_gen.get_as_list(condition_list.len()),
-                    );
-                    let mut params = json!([
-                        table_name,
-                        column_name,
-                        format!("datatype:{}", column.datatype.name),
-                        format!("{column_name} must be a {}", column.datatype.name),
-                    ]);
-                    for item in &condition_list {
-                        if let JsonValue::Array(ref mut v) = params {
-                            v.push(json!(item));
-                        }
-                    }
-                    if let Some(row) = row {
+                let re = regex::Regex::new(r"in\((.+?)\)")?;
+                match re.captures(condition) {
+                    Some(captures) => {
+                        let list_separator = regex::Regex::new(r"\s*,\s*")?;
+                        let condition_list = list_separator
+                            .split(&captures[1])
+                            .map(|item| unquoted_re.replace(item, "$unquoted").to_string())
+                            .collect::<Vec<_>>();
                         sql.push_str(&format!(
-                            r#" AND "_id" = {sql_param}"#,
-                            sql_param = sql_param_gen.next()
+                            r#" WHERE "{column_name}" NOT IN ({sql_params})"#,
+                            sql_params = sql_param_gen.get_as_list(condition_list.len())
                         ));
-                        if let JsonValue::Array(ref mut v) = params {
-                            v.push(json!(row));
+                        for item in &condition_list {
+                            params.push(json!(item));
                         }
                     }
-                    sql.push_str(r#" RETURNING 1 AS "inserted""#);
-                    if let Some(_) = tx.query_one(&sql, Some(&params))? {
-                        messages_were_added = true;
-                    }
-                }
+                    None => return Ok(false),
+                };
+            }
+            invalid => {
+                tracing::warn!("Unrecognized datatype condition '{invalid}'");
+                return Ok(false);
             }
-            invalid => tracing::warn!("Unrecognized datatype condition '{invalid}'"),
         };
 
+        // Skip any rows that have already been flagged for a more general datatype:
+        sql.push_str(&format!(
+            r#" AND NOT EXISTS (
+                  SELECT 1 FROM "message" m
+                  WHERE m."table" = {sql_param_1}
+                    AND m."row" = "{table_name}"."_id"
+                    AND m."column" = {sql_param_2}
+                    AND m."rule" LIKE 'datatype:%'
+                )"#,
+            sql_param_1 = sql_param_gen.next(),
+            sql_param_2 = sql_param_gen.next(),
+        ));
+        params.push(json!(table_name));
+        params.push(json!(column_name));
+
+        if let Some(row) = row {
+            sql.push_str(&format!(
+                r#" AND "_id" = {sql_param}"#,
+                sql_param = sql_param_gen.next()
+            ));
+            params.push(json!(row));
+        }
+        sql.push_str(r#" RETURNING 1 AS "inserted""#);
+        let messages_were_added = tx.query_one(&sql, Some(&json!(params)))?.is_some();
+
         tracing::debug!(
             "Validated datatype '{}' for column '{}.{}' (row: {:?}) {}",
             self.name,
@@ -1402,8 +1974,8 @@ impl Datatype {
             column.name,
             row,
             match messages_were_added {
-                false => "with messages added.",
-                true => "with no messages added.",
+                true => "with messages added.",
+                false => "with no messages added.",
             }
         );
         Ok(messages_were_added)
@@ -1414,6 +1986,8 @@ impl Datatype {
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Structure {
     From(Option<String>, String),
+    Unique,
+    Primary,
 }
 
 impl Structure {
@@ -1449,9 +2023,9 @@ impl Structure {
                              "_id" AS "row",
                              {sql_param_2} AS "column",
                              "{c_column}" AS "value",
-                             'error' AS "level",
-                             {sql_param_3} AS "rule",
-                             {sql_param_4} AS "message"
+                             {sql_param_3} AS "level",
+                             {sql_param_4} AS "rule",
+                             {sql_param_5} AS "message"
                            FROM "{c_table}"
                            WHERE "{c_column}" NOT IN (
                                SELECT "{s_column}" FROM "{s_table}"
@@ -1460,7 +2034,9 @@ impl Structure {
                     sql_param_2 = sql_param_gen.next(),
                     sql_param_3 = sql_param_gen.next(),
                     sql_param_4 = sql_param_gen.next(),
+                    sql_param_5 = sql_param_gen.next(),
                 );
+                let level = SeverityOverride::_get_level(c_table, "key:foreign", "error", tx)?;
                 let params;
                 match row {
                     Some(row) => {
@@ -1471,6 +2047,7 @@ impl Structure {
                         params = json!([
                             c_table,
                             c_column,
+                            level,
                             format!("key:foreign"),
                             format!("{c_column} must be in {s_table}.{s_column}"),
                             row
@@ -1480,6 +2057,7 @@ impl Structure {
                         params = json!([
                             c_table,
                             c_column,
+                            level,
                             format!("key:foreign"),
                             format!("{c_column} must be in {s_table}.{s_column}"),
                         ]);
@@ -1490,15 +2068,87 @@ impl Structure {
                     messages_were_added = true;
                 }
             }
-        };
-
-        tracing::debug!(
-            "Validated structure '{}' for column '{}.{}' (row: {:?}) {}",
-            self,
-            column.table,
-            column.name,
-            row,
-            match messages_were_added {
+            Structure::Unique | Structure::Primary => {
+                let c_table = &column.table;
+                let c_column = &column.name;
+                let rule = match self {
+                    Structure::Primary => "key:primary",
+                    _ => "key:unique",
+                };
+                // Join the table to itself to find, for each row, the other rows that share its
+                // value, and aggregate their ids into the message so that the conflicting rows
+                // can be looked up (e.g., using the filter table/{c_table}?_id=in.(...)).
+                let (ids_list, ids_filter) = match tx.kind() {
+                    DbKind::Sqlite => (
+                        r#"GROUP_CONCAT(t2."_id", ', ' ORDER BY t2."_id")"#,
+                        r#"GROUP_CONCAT(t2."_id", ',' ORDER BY t2."_id")"#,
+                    ),
+                    DbKind::Postgres => (
+                        r#"STRING_AGG(t2."_id"::TEXT, ', ' ORDER BY t2."_id")"#,
+                        r#"STRING_AGG(t2."_id"::TEXT, ',' ORDER BY t2."_id")"#,
+                    ),
+                };
+                let mut sql_param_gen = SqlParam::new(&tx.kind());
+                let mut sql = format!(
+                    r#"INSERT INTO "message"
+                         ("added_by", "table", "row", "column", "value", "level", "rule",
+                          "message")
+                       SELECT
+                         'rltbl' AS "added_by",
+                         {sql_param_1} AS "table",
+                         t1."_id" AS "row",
+                         {sql_param_2} AS "column",
+                         t1."{c_column}" AS "value",
+                         {sql_param_3} AS "level",
+                         {sql_param_4} AS "rule",
+                         {sql_param_5} || CAST(t1."{c_column}" AS TEXT) || {sql_param_6}
+                           || {ids_list} || {sql_param_7} || {ids_filter} || '))' AS "message"
+                       FROM "{c_table}" t1
+                         JOIN "{c_table}" t2
+                           ON t1."{c_column}" = t2."{c_column}" AND t1."_id" <> t2."_id""#,
+                    sql_param_1 = sql_param_gen.next(),
+                    sql_param_2 = sql_param_gen.next(),
+                    sql_param_3 = sql_param_gen.next(),
+                    sql_param_4 = sql_param_gen.next(),
+                    sql_param_5 = sql_param_gen.next(),
+                    sql_param_6 = sql_param_gen.next(),
+                    sql_param_7 = sql_param_gen.next(),
+                );
+                let level = SeverityOverride::_get_level(c_table, rule, "error", tx)?;
+                let mut params = json!([
+                    c_table,
+                    c_column,
+                    level,
+                    rule,
+                    format!("{c_column} must be unique but '"),
+                    "' is also used by row(s) ",
+                    format!(" (see table/{c_table}?_id=in.("),
+                ]);
+                if let Some(row) = row {
+                    sql.push_str(&format!(
+                        r#" WHERE t1."_id" = {sql_param}"#,
+                        sql_param = sql_param_gen.next()
+                    ));
+                    if let JsonValue::Array(ref mut v) = params {
+                        v.push(json!(row));
+                    }
+                }
+                sql.push_str(&format!(
+                    r#" GROUP BY t1."_id", t1."{c_column}" RETURNING 1 AS "inserted""#
+                ));
+                if tx.query_one(&sql, Some(&params))?.is_some() {
+                    messages_were_added = true;
+                }
+            }
+        };
+
+        tracing::debug!(
+            "Validated structure '{}' for column '{}.{}' (row: {:?}) {}",
+            self,
+            column.table,
+            column.name,
+            row,
+            match messages_were_added {
                 false => "with messages added.",
                 true => "with no messages added.",
             }
@@ -1512,7 +2162,11 @@ impl FromStr for Structure {
 
     fn from_str(structure: &str) -> Result<Self> {
         tracing::trace!("Structure::from_str({structure})");
-        if structure.starts_with("from(") {
+        if structure == "unique" {
+            Ok(Structure::Unique)
+        } else if structure == "primary" {
+            Ok(Structure::Primary)
+        } else if structure.starts_with("from(") {
             let re = regex::Regex::new(r"from\(((.+?)\.)?(.+?)\)")?;
             let unquoted_re = regex::Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#)?;
             match re.captures(structure) {
@@ -1548,16 +2202,1135 @@ impl Display for Structure {
                 None => write!(f, "from({s_column})"),
                 Some(s_table) => write!(f, "from({s_table}.{s_column})"),
             },
+            Structure::Unique => write!(f, "unique"),
+            Structure::Primary => write!(f, "primary"),
+        }
+    }
+}
+
+/// The action taken on the rows that refer to a row of another table through a [ForeignKey] when
+/// that row is deleted, or the value referred to is updated.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ForeignKeyAction {
+    #[default]
+    NoAction,
+    Restrict,
+    Cascade,
+    SetNull,
+    SetDefault,
+}
+
+impl FromStr for ForeignKeyAction {
+    type Err = anyhow::Error;
+
+    fn from_str(action: &str) -> Result<Self> {
+        match action.to_lowercase().as_str() {
+            "no action" => Ok(ForeignKeyAction::NoAction),
+            "restrict" => Ok(ForeignKeyAction::Restrict),
+            "cascade" => Ok(ForeignKeyAction::Cascade),
+            "set null" => Ok(ForeignKeyAction::SetNull),
+            "set default" => Ok(ForeignKeyAction::SetDefault),
+            _ => Err(
+                RelatableError::InputError(format!("Invalid foreign key action: '{action}'"))
+                    .into(),
+            ),
+        }
+    }
+}
+
+impl Display for ForeignKeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForeignKeyAction::NoAction => write!(f, "no action"),
+            ForeignKeyAction::Restrict => write!(f, "restrict"),
+            ForeignKeyAction::Cascade => write!(f, "cascade"),
+            ForeignKeyAction::SetNull => write!(f, "set null"),
+            ForeignKeyAction::SetDefault => write!(f, "set default"),
+        }
+    }
+}
+
+/// Represents the enforcement of a column's `from()` [Structure] by the database, as a FOREIGN
+/// KEY constraint, rather than by validation messages alone. This is configured in the
+/// (optional) foreign_key column of the column table, either as `enforce`, or as a list of
+/// `on delete ACTION` and `on update ACTION` clauses, e.g., `on delete cascade on update
+/// restrict`, where each ACTION is one of `no action` (the default), `restrict`, `cascade`,
+/// `set null`, or `set default`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ForeignKey {
+    pub on_delete: ForeignKeyAction,
+    pub on_update: ForeignKeyAction,
+}
+
+impl ForeignKey {
+    /// Render the SQL for the FOREIGN KEY constraint that references the given column of the
+    /// given table, to be appended to the definition of the referring column.
+    pub fn to_sql(&self, table: &str, column: &str) -> String {
+        format!(
+            r#"REFERENCES "{table}"("{column}") ON DELETE {on_delete} ON UPDATE {on_update}"#,
+            on_delete = self.on_delete.to_string().to_uppercase(),
+            on_update = self.on_update.to_string().to_uppercase(),
+        )
+    }
+}
+
+impl FromStr for ForeignKey {
+    type Err = anyhow::Error;
+
+    fn from_str(foreign_key: &str) -> Result<Self> {
+        tracing::trace!("ForeignKey::from_str({foreign_key})");
+        let foreign_key = foreign_key.trim().to_lowercase();
+        if foreign_key == "enforce" {
+            return Ok(ForeignKey::default());
+        }
+        let re = regex::Regex::new(
+            r"^(\s*on\s+(delete|update)\s+(no\s+action|restrict|cascade|set\s+null|set\s+default))+\s*$",
+        )?;
+        if !re.is_match(&foreign_key) {
+            return Err(RelatableError::InputError(format!(
+                "Invalid foreign key: '{foreign_key}'"
+            ))
+            .into());
+        }
+        let clause_re = regex::Regex::new(
+            r"on\s+(delete|update)\s+(no\s+action|restrict|cascade|set\s+null|set\s+default)",
+        )?;
+        let mut parsed = ForeignKey::default();
+        for captures in clause_re.captures_iter(&foreign_key) {
+            let action = captures[2].split_whitespace().collect::<Vec<_>>().join(" ");
+            let action = ForeignKeyAction::from_str(&action)?;
+            match &captures[1] {
+                "delete" => parsed.on_delete = action,
+                _ => parsed.on_update = action,
+            };
+        }
+        Ok(parsed)
+    }
+}
+
+impl Display for ForeignKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ForeignKey {
+                on_delete: ForeignKeyAction::NoAction,
+                on_update: ForeignKeyAction::NoAction,
+            } => write!(f, "enforce"),
+            _ => write!(
+                f,
+                "on delete {} on update {}",
+                self.on_delete, self.on_update
+            ),
+        }
+    }
+}
+
+/// The conventions for writing numbers and dates in a locale. The values of a column that is
+/// given a locale in the (optional) locale column of the column table are written in it in the
+/// column's text view, and so in the file that its table is saved to, and are read in it when
+/// the table is loaded. Exports can also be requested in a locale, e.g., with `locale=de` in a
+/// URL. Values that are written in a locale are always read back as they were, and values that
+/// cannot be read in a locale, e.g., values that are already written in the ISO conventions, are
+/// read as they are.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Locale {
+    /// The conventions in which values are stored: a decimal point and ISO 8601 dates
+    #[default]
+    Iso,
+    EnUs,
+    EnGb,
+    De,
+    Fr,
+    Es,
+    It,
+    Nl,
+}
+
+impl Locale {
+    /// The character that separates the integer and fractional parts of numbers
+    pub fn decimal_separator(&self) -> char {
+        match self {
+            Locale::Iso | Locale::EnUs | Locale::EnGb => '.',
+            Locale::De | Locale::Fr | Locale::Es | Locale::It | Locale::Nl => ',',
+        }
+    }
+
+    /// The format of dates, in the syntax of [chrono::format::strftime]
+    pub fn date_format(&self) -> &'static str {
+        match self {
+            Locale::Iso => "%Y-%m-%d",
+            Locale::EnUs => "%m/%d/%Y",
+            Locale::EnGb | Locale::Fr | Locale::Es | Locale::It => "%d/%m/%Y",
+            Locale::De => "%d.%m.%Y",
+            Locale::Nl => "%d-%m-%Y",
+        }
+    }
+
+    /// The character that separates the fields of CSV files, which is a semicolon wherever the
+    /// decimal separator is a comma
+    pub fn csv_delimiter(&self) -> u8 {
+        match self.decimal_separator() {
+            ',' => b';',
+            _ => b',',
+        }
+    }
+
+    /// Write the given value, of the given SQL type, in this locale
+    pub fn format(&self, value: &str, sql_type: &str) -> String {
+        match sql::canonical_sql_type(sql_type) {
+            Some("NUMERIC") if value.parse::<f64>().is_ok() => {
+                value.replace('.', &self.decimal_separator().to_string())
+            }
+            Some("DATE") => match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                Ok(date) => date.format(self.date_format()).to_string(),
+                Err(_) => value.to_string(),
+            },
+            _ => value.to_string(),
+        }
+    }
+
+    /// Read the given text, of the given SQL type, in this locale, returning the value in the
+    /// conventions in which values are stored, or the text as it is if it cannot be read.
+    pub fn parse(&self, text: &str, sql_type: &str) -> String {
+        match sql::canonical_sql_type(sql_type) {
+            Some("NUMERIC") => {
+                let value = text.replace(self.decimal_separator(), ".");
+                match value.parse::<f64>() {
+                    Ok(_) => value,
+                    Err(_) => text.to_string(),
+                }
+            }
+            Some("DATE") => match NaiveDate::parse_from_str(text, self.date_format()) {
+                Ok(date) => date.format("%Y-%m-%d").to_string(),
+                Err(_) => text.to_string(),
+            },
+            _ => text.to_string(),
+        }
+    }
+
+    /// Generate the SQL that writes the given column, of the given SQL type, in this locale,
+    /// given the SQL that writes it as text otherwise.
+    pub(crate) fn to_sql(self, column: &str, sql_type: &str, text: &str, kind: &DbKind) -> String {
+        match sql::canonical_sql_type(sql_type) {
+            Some("NUMERIC") if self.decimal_separator() != '.' => {
+                format!("REPLACE({text}, '.', '{}')", self.decimal_separator())
+            }
+            Some("DATE") if self != Locale::Iso => match kind {
+                DbKind::Sqlite => {
+                    format!(
+                        r#"COALESCE(STRFTIME('{}', "{column}"), {text})"#,
+                        self.date_format()
+                    )
+                }
+                DbKind::Postgres => format!(
+                    r#"TO_CHAR("{column}", '{}')"#,
+                    self.date_format()
+                        .replace("%d", "DD")
+                        .replace("%m", "MM")
+                        .replace("%Y", "YYYY")
+                ),
+            },
+            _ => text.to_string(),
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = anyhow::Error;
+
+    fn from_str(locale: &str) -> Result<Self> {
+        match locale.trim().to_lowercase().replace('-', "_").as_str() {
+            "iso" => Ok(Locale::Iso),
+            "en_us" => Ok(Locale::EnUs),
+            "en_gb" => Ok(Locale::EnGb),
+            "de" => Ok(Locale::De),
+            "fr" => Ok(Locale::Fr),
+            "es" => Ok(Locale::Es),
+            "it" => Ok(Locale::It),
+            "nl" => Ok(Locale::Nl),
+            _ => Err(RelatableError::InputError(format!(
+                "Unsupported locale '{locale}'. Supported locales are: iso, en_US, en_GB, de, \
+                 fr, es, it, nl"
+            ))
+            .into()),
+        }
+    }
+}
+
+impl Display for Locale {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Locale::Iso => write!(f, "iso"),
+            Locale::EnUs => write!(f, "en_US"),
+            Locale::EnGb => write!(f, "en_GB"),
+            Locale::De => write!(f, "de"),
+            Locale::Fr => write!(f, "fr"),
+            Locale::Es => write!(f, "es"),
+            Locale::It => write!(f, "it"),
+            Locale::Nl => write!(f, "nl"),
+        }
+    }
+}
+
+/// How the values of a column are ordered when a select is ordered by it, from the (optional)
+/// collation column of the column table. The values of a column without a collation are ordered
+/// as the database orders values of its SQL type, e.g., lexicographically for text, so that `10`
+/// comes before `9`. Values that are equal according to a collation are ordered as they would be
+/// without it, so that the order is the same in SQLite and PostgreSQL.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Collation {
+    /// Ignoring case, e.g., `adelie` before `Chinstrap`
+    CaseInsensitive,
+    /// By the number that each value begins with, e.g., `9` before `10.5`. Values that do not
+    /// begin with a number come after those that do, unless the position of NULL values is given
+    /// (see [Order]).
+    Numeric,
+    /// Comparing the runs of digits in values as numbers, e.g., `N9A1` before `N10A1`. In SQLite
+    /// this requires the `natural_key()` function (see [sql::open_rusqlite()]).
+    Natural,
+}
+
+impl Collation {
+    /// The width to which runs of digits are padded with zeros in natural sort keys
+    const NATURAL_KEY_WIDTH: usize = 20;
+
+    /// Generate the items of an ORDER BY clause that order the given column, written in SQL, in
+    /// the given order according to this collation
+    pub fn to_sql(&self, column: &str, order: &Order, kind: &DbKind) -> Vec<String> {
+        let text = format!("CAST({column} AS TEXT)");
+        let key = match (self, kind) {
+            (Collation::CaseInsensitive, _) => format!("LOWER({text})"),
+            (Collation::Numeric, DbKind::Sqlite) => {
+                let text = format!("TRIM({text})");
+                format!(
+                    "CASE WHEN {text} GLOB '[0-9]*' OR {text} GLOB '[-+.][0-9]*' \
+                     OR {text} GLOB '[-+].[0-9]*' THEN CAST({text} AS REAL) END"
+                )
+            }
+            (Collation::Numeric, DbKind::Postgres) => {
+                format!(r"CAST(SUBSTRING({text} FROM '^\s*([-+]?(\d+\.?\d*|\.\d+))') AS NUMERIC)")
+            }
+            (Collation::Natural, DbKind::Sqlite) => format!("NATURAL_KEY({column})"),
+            (Collation::Natural, DbKind::Postgres) => format!(
+                "(SELECT STRING_AGG(CASE WHEN m.part[1] ~ '^\\d' AND LENGTH(m.part[1]) < {width} \
+                 THEN LPAD(m.part[1], {width}, '0') ELSE m.part[1] END, '' ORDER BY m.n) \
+                 FROM REGEXP_MATCHES({text}, '\\d+|\\D+', 'g') WITH ORDINALITY AS m(part, n))",
+                width = Collation::NATURAL_KEY_WIDTH
+            ),
+        };
+        let key_order = match (self, order) {
+            (Collation::Numeric, Order::ASC) => Order::AscNullsLast,
+            (Collation::Numeric, Order::DESC) => Order::DescNullsLast,
+            _ => order.clone(),
+        };
+        vec![format!("{key} {key_order}"), format!("{column} {order}")]
+    }
+
+    /// Generate the key by which the given text is ordered naturally, in which each run of
+    /// digits is padded with zeros, so that the keys of texts can be compared lexicographically
+    pub fn natural_key(text: &str) -> String {
+        lazy_static! {
+            static ref DIGITS: regex::Regex = regex::Regex::new(r"\d+").unwrap();
+        }
+        DIGITS
+            .replace_all(text, |captures: &regex::Captures| {
+                format!(
+                    "{:0>width$}",
+                    &captures[0],
+                    width = Collation::NATURAL_KEY_WIDTH
+                )
+            })
+            .to_string()
+    }
+}
+
+impl FromStr for Collation {
+    type Err = anyhow::Error;
+
+    fn from_str(collation: &str) -> Result<Self> {
+        match collation.trim().to_lowercase().as_str() {
+            "case_insensitive" => Ok(Collation::CaseInsensitive),
+            "numeric" => Ok(Collation::Numeric),
+            "natural" => Ok(Collation::Natural),
+            _ => Err(RelatableError::InputError(format!(
+                "Unsupported collation '{collation}'. Supported collations are: \
+                 case_insensitive, numeric, natural"
+            ))
+            .into()),
+        }
+    }
+}
+
+impl Display for Collation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Collation::CaseInsensitive => write!(f, "case_insensitive"),
+            Collation::Numeric => write!(f, "numeric"),
+            Collation::Natural => write!(f, "natural"),
+        }
+    }
+}
+
+/// Represents a conditional rule, as defined in the (optional) rule table, of the form: when the
+/// value of `when_column` satisfies `when_condition`, then the value of `then_column` must satisfy
+/// `then_condition`. Currently supported `when` conditions are `equals(...)` and `in(...)`, and
+/// the only supported `then` condition is `required`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Rule {
+    pub table: String,
+    pub when_column: String,
+    pub when_condition: String,
+    pub then_column: String,
+    pub then_condition: String,
+    pub level: String,
+    pub description: String,
+}
+
+impl Rule {
+    /// Get the rules defined for the given table in the rule table
+    pub async fn get_rules(table_name: &str, rltbl: &Relatable) -> Result<Vec<Self>> {
+        tracing::trace!("Rule::get_rules({table_name}, {rltbl:?})");
+        let mut conn = rltbl.connection.reconnect()?;
+        let mut tx = rltbl.connection.begin(&mut conn).await?;
+        let rules = Rule::_get_rules(table_name, &mut tx)?;
+        tx.commit()?;
+        Ok(rules)
+    }
+
+    /// Get the rules defined for the given table in the rule table using the given transaction.
+    /// When the rule table does not exist, returns an empty list.
+    pub fn _get_rules(table_name: &str, tx: &mut DbTransaction<'_>) -> Result<Vec<Self>> {
+        tracing::trace!("Rule::_get_rules({table_name}, tx)");
+        if !Table::_table_exists("rule", tx)? {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            r#"SELECT * FROM "rule" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([table_name]);
+        let mut rules = vec![];
+        for row in tx.query(&sql, Some(&params))? {
+            rules.push(Rule {
+                table: row.get_string("table")?,
+                when_column: row.get_string("when_column")?,
+                when_condition: row.get_string("when_condition")?,
+                then_column: row.get_string("then_column")?,
+                then_condition: row.get_string("then_condition")?,
+                level: match row.get_string("level").unwrap_or_default().as_str() {
+                    "" => "error".to_string(),
+                    level => level.to_string(),
+                },
+                description: row.get_string("description").unwrap_or_default(),
+            });
+        }
+        Ok(rules)
+    }
+
+    /// Parse this rule's `when` condition, returning the list of values that will trigger it.
+    fn get_when_values(&self) -> Result<Vec<String>> {
+        tracing::trace!("Rule::get_when_values({self:?})");
+        let unquoted_re = regex::Regex::new(r#"^['"](?P<unquoted>.*)['"]$"#)?;
+        let condition = self.when_condition.as_str();
+        let invalid = || -> anyhow::Error {
+            RelatableError::InputError(format!("Invalid rule condition: '{condition}'")).into()
+        };
+        if condition.starts_with("equals(") {
+            let re = regex::Regex::new(r"equals\((.+?)\)")?;
+            let captures = re.captures(condition).ok_or_else(invalid)?;
+            Ok(vec![unquoted_re
+                .replace(&captures[1], "$unquoted")
+                .to_string()])
+        } else if condition.starts_with("in(") {
+            let re = regex::Regex::new(r"in\((.+?)\)")?;
+            let captures = re.captures(condition).ok_or_else(invalid)?;
+            let list_separator = regex::Regex::new(r"\s*,\s*")?;
+            Ok(list_separator
+                .split(&captures[1])
+                .map(|item| unquoted_re.replace(item, "$unquoted").to_string())
+                .collect::<Vec<_>>())
+        } else {
+            Err(invalid())
+        }
+    }
+
+    /// Validate the rule's table against this rule, optionally only for the given row, using
+    /// the given transaction. Returns true whenever messages are inserted to the message table as
+    /// a result of validation, and false otherwise. A rule whose conditions are not supported is
+    /// skipped, with a warning, so that it does not prevent the table's other rules and columns
+    /// from being validated.
+    pub fn validate(&self, row: Option<&u64>, tx: &mut DbTransaction<'_>) -> Result<bool> {
+        tracing::trace!("Rule::validate({self:?}, {row:?}, tx)");
+        if self.then_condition != "required" {
+            tracing::warn!(
+                "Skipping rule on '{}' of table '{}' with unsupported condition: '{}'",
+                self.then_column,
+                self.table,
+                self.then_condition
+            );
+            return Ok(false);
+        }
+        let table = &self.table;
+        let when_column = &self.when_column;
+        let then_column = &self.then_column;
+        let when_values = match self.get_when_values() {
+            Ok(when_values) => when_values,
+            Err(_) => {
+                tracing::warn!(
+                    "Skipping rule on '{when_column}' of table '{table}' with invalid condition: \
+                     '{}'",
+                    self.when_condition
+                );
+                return Ok(false);
+            }
+        };
+        let message = match self.description.as_str() {
+            "" => match when_values.len() {
+                1 => format!(
+                    "{then_column} is required when {when_column} is '{}'",
+                    when_values[0]
+                ),
+                _ => format!(
+                    "{then_column} is required when {when_column} is one of {}",
+                    when_values
+                        .iter()
+                        .map(|v| format!("'{v}'"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            },
+            description => description.to_string(),
+        };
+
+        let level = SeverityOverride::_get_level(
+            table,
+            &format!("rule:{}", self.then_condition),
+            &self.level,
+            tx,
+        )?;
+        let mut sql_param_gen = SqlParam::new(&tx.kind());
+        let mut sql = format!(
+            r#"INSERT INTO "message"
+                 ("added_by", "table", "row", "column", "value", "level", "rule", "message")
+               SELECT
+                 'rltbl' AS "added_by",
+                 {sql_param_1} AS "table",
+                 "_id" AS "row",
+                 {sql_param_2} AS "column",
+                 "{then_column}" AS "value",
+                 {sql_param_3} AS "level",
+                 {sql_param_4} AS "rule",
+                 {sql_param_5} AS "message"
+               FROM "{table}"
+               WHERE CAST("{when_column}" AS TEXT) IN ({sql_param_list})
+                 AND ("{then_column}" {is} NULL OR CAST("{then_column}" AS TEXT) = '')"#,
+            sql_param_1 = sql_param_gen.next(),
+            sql_param_2 = sql_param_gen.next(),
+            sql_param_3 = sql_param_gen.next(),
+            sql_param_4 = sql_param_gen.next(),
+            sql_param_5 = sql_param_gen.next(),
+            sql_param_list = sql_param_gen.get_as_list(when_values.len()),
+            is = sql::is_clause(&tx.kind()),
+        );
+        let mut params = vec![
+            json!(table),
+            json!(then_column),
+            json!(level),
+            json!(format!("rule:{}", self.then_condition)),
+            json!(message),
+        ];
+        for value in &when_values {
+            params.push(json!(value));
+        }
+        if let Some(row) = row {
+            sql.push_str(&format!(
+                r#" AND "_id" = {sql_param}"#,
+                sql_param = sql_param_gen.next()
+            ));
+            params.push(json!(row));
+        }
+        sql.push_str(r#" RETURNING 1 AS "inserted""#);
+        let messages_were_added = tx.query_one(&sql, Some(&json!(params)))?.is_some();
+
+        tracing::debug!(
+            "Validated rule '{} {}' => '{} {}' for table '{}' (row: {:?}) {}",
+            self.when_column,
+            self.when_condition,
+            self.then_column,
+            self.then_condition,
+            self.table,
+            row,
+            match messages_were_added {
+                true => "with messages added.",
+                false => "with no messages added.",
+            }
+        );
+        Ok(messages_were_added)
+    }
+}
+
+/// A column that another column depends on, or that depends on another column (see
+/// [Table::get_dependencies()]), along with the `kind` of the dependency: either `structure`,
+/// for a `from()` structure, or `rule`, for a [Rule] whose `when` condition is on the one column
+/// and whose `then` condition is on the other.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnDependency {
+    pub table: String,
+    pub column: String,
+    pub kind: String,
+}
+
+/// The dependencies of a column: the columns whose values determine the allowed values of this
+/// column, and the columns whose allowed values are determined by the values of this column.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnDependencies {
+    pub depends_on: Vec<ColumnDependency>,
+    pub dependents: Vec<ColumnDependency>,
+}
+
+/// One of the tables that hold the rows of a table that is partitioned by the values of one of its
+/// columns (see [Relatable::partition_table()]).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Partition {
+    /// The name of the table that holds the rows of this partition
+    pub name: String,
+    /// The name of the partitioned table
+    pub table: String,
+    /// The column that the table is partitioned by
+    pub column: String,
+    /// The value of the column in the rows of this partition, or None for the default partition,
+    /// which holds the rows whose value has no partition of its own (including NULL)
+    pub value: Option<JsonValue>,
+}
+
+impl Partition {
+    /// Get the partitions of the given table using the given transaction. When the table is not
+    /// partitioned, or the partition table does not exist, returns an empty list.
+    pub fn _get_partitions(table_name: &str, tx: &mut DbTransaction<'_>) -> Result<Vec<Self>> {
+        tracing::trace!("Partition::_get_partitions({table_name}, tx)");
+        if !Table::_table_exists("partition", tx)? {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            r#"SELECT "partition", "column", "value" FROM "partition"
+               WHERE "table" = {sql_param}
+               ORDER BY "value" IS NULL, "partition""#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let mut partitions = vec![];
+        for row in tx.query(&sql, Some(&json!([table_name])))? {
+            let value = match row.get_value("value")? {
+                JsonValue::Null => None,
+                value => Some(serde_json::from_str(&sql::json_to_string(&value))?),
+            };
+            partitions.push(Partition {
+                name: row.get_string("partition")?,
+                table: table_name.to_string(),
+                column: row.get_string("column")?,
+                value,
+            });
+        }
+        Ok(partitions)
+    }
+
+    /// Determine whether the given value of the partition column belongs in this partition, which
+    /// is one of the given partitions of its table. Values are compared as text, since the values
+    /// of filters are not always interpreted according to the datatype of their column.
+    pub fn accepts(&self, value: &JsonValue, partitions: &[Partition]) -> bool {
+        let value = sql::json_to_string(value);
+        let matches = |partition: &Partition| {
+            partition
+                .value
+                .as_ref()
+                .is_some_and(|partition_value| sql::json_to_string(partition_value) == value)
+        };
+        match self.value {
+            Some(_) => matches(self),
+            None => !partitions.iter().any(matches),
+        }
+    }
+}
+
+/// The supported message levels, in increasing order of severity
+pub const MESSAGE_LEVELS: [&str; 3] = ["info", "warn", "error"];
+
+/// Represents an override, as defined in the (optional) severity_override table, of the message
+/// level to use when validation of the given table fails the given rule. The rule may either be
+/// the full name of a rule, e.g., `datatype:word`, or end in the SQL wildcard character `%`, e.g.,
+/// `datatype:%`, in which case it applies to every rule that begins with the given prefix.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SeverityOverride {
+    pub table: String,
+    pub rule: String,
+    pub level: String,
+}
+
+impl SeverityOverride {
+    /// Get the severity overrides defined for the given table
+    pub async fn get_overrides(table_name: &str, rltbl: &Relatable) -> Result<Vec<Self>> {
+        tracing::trace!("SeverityOverride::get_overrides({table_name}, {rltbl:?})");
+        let mut conn = rltbl.connection.reconnect()?;
+        let mut tx = rltbl.connection.begin(&mut conn).await?;
+        let overrides = SeverityOverride::_get_overrides(table_name, &mut tx)?;
+        tx.commit()?;
+        Ok(overrides)
+    }
+
+    /// Get the severity overrides defined for the given table using the given transaction. When
+    /// the severity_override table does not exist, returns an empty list.
+    pub fn _get_overrides(table_name: &str, tx: &mut DbTransaction<'_>) -> Result<Vec<Self>> {
+        tracing::trace!("SeverityOverride::_get_overrides({table_name}, tx)");
+        if !Table::_table_exists("severity_override", tx)? {
+            return Ok(vec![]);
+        }
+        let sql = format!(
+            r#"SELECT * FROM "severity_override" WHERE "table" = {sql_param}"#,
+            sql_param = SqlParam::new(&tx.kind()).next()
+        );
+        let params = json!([table_name]);
+        let mut overrides = vec![];
+        for row in tx.query(&sql, Some(&params))? {
+            let level = row.get_string("level")?;
+            if !MESSAGE_LEVELS.contains(&level.as_str()) {
+                tracing::warn!("Ignoring severity override with unsupported level '{level}'");
+                continue;
+            }
+            overrides.push(SeverityOverride {
+                table: row.get_string("table")?,
+                rule: row.get_string("rule")?,
+                level,
+            });
+        }
+        Ok(overrides)
+    }
+
+    /// Determine the level of a message for the given rule, given the list of overrides for the
+    /// table being validated. When more than one override matches the rule, the most specific
+    /// (i.e., the longest) wins. When none match, `default` is returned.
+    pub fn resolve(overrides: &[Self], rule: &str, default: &str) -> String {
+        tracing::trace!("SeverityOverride::resolve({overrides:?}, {rule}, {default})");
+        overrides
+            .iter()
+            .filter(|o| match o.rule.strip_suffix('%') {
+                Some(prefix) => rule.starts_with(prefix),
+                None => o.rule == rule,
+            })
+            .max_by_key(|o| o.rule.len())
+            .map(|o| o.level.to_string())
+            .unwrap_or_else(|| default.to_string())
+    }
+
+    /// Look up the level of a message for the given rule, as it applies to the given table,
+    /// using the given transaction.
+    pub fn _get_level(
+        table_name: &str,
+        rule: &str,
+        default: &str,
+        tx: &mut DbTransaction<'_>,
+    ) -> Result<String> {
+        tracing::trace!("SeverityOverride::_get_level({table_name}, {rule}, {default}, tx)");
+        let overrides = SeverityOverride::_get_overrides(table_name, tx)?;
+        Ok(SeverityOverride::resolve(&overrides, rule, default))
+    }
+}
+
+/// The transforms that a [Mapping], or the (optional) transform column of the column table, can
+/// apply to the values of a column when they are loaded (see [Transform])
+pub const MAPPING_TRANSFORMS: [&str; 6] = [
+    "trim",
+    "lowercase",
+    "uppercase",
+    "strip_prefix('TEXT')",
+    "strip_suffix('TEXT')",
+    "date('FORMAT')",
+];
+
+/// Represents a transform that is applied to the values of a column as they are loaded, before
+/// they are validated. Transforms are written as their names, followed, for those that take one,
+/// by a quoted argument in parentheses, e.g., `strip_prefix('ID:')`, or `date('%m/%d/%Y')`, which
+/// reads dates in the given format, in the syntax of [chrono::format::strftime], and rewrites them
+/// as `%Y-%m-%d`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Transform {
+    Trim,
+    Lowercase,
+    Uppercase,
+    StripPrefix(String),
+    StripSuffix(String),
+    Date(String),
+}
+
+impl Transform {
+    /// Parse the given comma-separated list of transforms, e.g., the value of the transform column
+    /// of the column table. Commas inside of quoted arguments do not separate transforms.
+    pub fn parse_list(transforms: &str) -> Result<Vec<Self>> {
+        tracing::trace!("Transform::parse_list({transforms})");
+        Transform::split_list(transforms)
+            .iter()
+            .map(|transform| Transform::from_str(transform))
+            .collect()
+    }
+
+    /// Split the given comma-separated list of transforms, without parsing them.
+    pub fn split_list(transforms: &str) -> Vec<String> {
+        let mut split = vec![];
+        let mut current = String::new();
+        let mut quote = None;
+        for c in transforms.chars() {
+            match (c, quote) {
+                (',', None) => split.push(std::mem::take(&mut current)),
+                ('\'' | '"', None) => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                (c, Some(q)) if c == q => {
+                    quote = None;
+                    current.push(c);
+                }
+                _ => current.push(c),
+            };
+        }
+        split.push(current);
+        split
+            .into_iter()
+            .map(|transform| transform.trim().to_string())
+            .filter(|transform| !transform.is_empty())
+            .collect()
+    }
+
+    /// Apply this transform to the given value, returning an error if the value cannot be
+    /// transformed, e.g., if it is not a date in the expected format.
+    pub fn apply(&self, value: &str) -> Result<String> {
+        match self {
+            Transform::Trim => Ok(value.trim().to_string()),
+            Transform::Lowercase => Ok(value.to_lowercase()),
+            Transform::Uppercase => Ok(value.to_uppercase()),
+            Transform::StripPrefix(prefix) => Ok(value
+                .strip_prefix(prefix.as_str())
+                .unwrap_or(value)
+                .to_string()),
+            Transform::StripSuffix(suffix) => Ok(value
+                .strip_suffix(suffix.as_str())
+                .unwrap_or(value)
+                .to_string()),
+            Transform::Date(_) if value.is_empty() => Ok(value.to_string()),
+            Transform::Date(format) => match NaiveDate::parse_from_str(value.trim(), format) {
+                Ok(date) => Ok(date.format("%Y-%m-%d").to_string()),
+                Err(_) => Err(RelatableError::DataError(format!(
+                    "'{value}' is not a date in the format '{format}'"
+                ))
+                .into()),
+            },
+        }
+    }
+
+    /// Apply the given transforms, in order, to the given raw value, returning the transformed
+    /// value, along with a message recording the raw value if the transforms changed it, or the
+    /// raw value, along with a warning, if one of them could not be applied.
+    pub fn apply_all(transforms: &[Transform], raw: &str) -> (String, Option<Message>) {
+        let mut value = raw.to_string();
+        for transform in transforms {
+            match transform.apply(&value) {
+                Ok(transformed) => value = transformed,
+                Err(error) => {
+                    let message = Message {
+                        value: json!(raw),
+                        level: "warn".to_string(),
+                        rule: format!("transform:{}", transform.name()),
+                        message: format!("Could not apply {transform}: {error}"),
+                    };
+                    return (raw.to_string(), Some(message));
+                }
+            };
+        }
+        match value == raw {
+            true => (value, None),
+            false => {
+                let message = Message {
+                    value: json!(raw),
+                    level: "info".to_string(),
+                    rule: "transform".to_string(),
+                    message: format!(
+                        "Loaded as '{value}' from '{raw}' by {}",
+                        transforms
+                            .iter()
+                            .map(|transform| transform.to_string())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                (value, Some(message))
+            }
+        }
+    }
+
+    /// The name of this transform, without its argument
+    pub fn name(&self) -> &str {
+        match self {
+            Transform::Trim => "trim",
+            Transform::Lowercase => "lowercase",
+            Transform::Uppercase => "uppercase",
+            Transform::StripPrefix(_) => "strip_prefix",
+            Transform::StripSuffix(_) => "strip_suffix",
+            Transform::Date(_) => "date",
+        }
+    }
+}
+
+impl FromStr for Transform {
+    type Err = anyhow::Error;
+
+    fn from_str(transform: &str) -> Result<Self> {
+        tracing::trace!("Transform::from_str({transform})");
+        let invalid = || -> anyhow::Error {
+            RelatableError::InputError(format!(
+                "Unsupported transform '{transform}'. Supported transforms are: {}",
+                MAPPING_TRANSFORMS.join(", ")
+            ))
+            .into()
+        };
+        let transform = transform.trim();
+        let (name, argument) = match transform.split_once('(') {
+            None => (transform, None),
+            Some((name, rest)) => {
+                let argument = rest.trim().strip_suffix(')').ok_or_else(invalid)?.trim();
+                let argument = ['\'', '"']
+                    .iter()
+                    .find_map(|quote| {
+                        argument
+                            .strip_prefix(*quote)
+                            .and_then(|argument| argument.strip_suffix(*quote))
+                    })
+                    .ok_or_else(invalid)?;
+                (name.trim(), Some(argument.to_string()))
+            }
+        };
+        match (name, argument) {
+            ("trim", None) => Ok(Transform::Trim),
+            ("lowercase", None) => Ok(Transform::Lowercase),
+            ("uppercase", None) => Ok(Transform::Uppercase),
+            ("strip_prefix", Some(prefix)) => Ok(Transform::StripPrefix(prefix)),
+            ("strip_suffix", Some(suffix)) => Ok(Transform::StripSuffix(suffix)),
+            ("date", Some(format)) => Ok(Transform::Date(format)),
+            _ => Err(invalid()),
         }
     }
 }
 
+impl TryFrom<String> for Transform {
+    type Error = anyhow::Error;
+
+    fn try_from(transform: String) -> Result<Self> {
+        Transform::from_str(&transform)
+    }
+}
+
+impl From<Transform> for String {
+    fn from(transform: Transform) -> Self {
+        transform.to_string()
+    }
+}
+
+impl Display for Transform {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Transform::StripPrefix(argument)
+            | Transform::StripSuffix(argument)
+            | Transform::Date(argument) => write!(f, "{}('{argument}')", self.name()),
+            _ => write!(f, "{}", self.name()),
+        }
+    }
+}
+
+/// Represents a reusable mapping from the columns of an external source file, such as a
+/// recurring feed, to the columns of a table. Each mapped column of the table takes its value
+/// either from a column of the source, optionally transformed, or from a constant.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Mapping {
+    /// The table that the source is mapped to
+    pub table: String,
+    /// The mapped columns of the table
+    pub columns: Vec<MappedColumn>,
+}
+
+/// Represents the mapping of a single column of a table in a [Mapping]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MappedColumn {
+    /// The column of the table
+    pub target: String,
+    /// The column of the source from which the target column takes its value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The constant value of the target column
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub constant: Option<String>,
+    /// The transforms (see [MAPPING_TRANSFORMS]) to apply, in order, to the source value
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub transforms: Vec<String>,
+}
+
+impl Mapping {
+    /// Returns a new [Mapping] from the given source headers to the given columns of the given
+    /// table, in which each column is mapped to the source column with the same name, ignoring
+    /// case and punctuation. Columns without such a source column are left unmapped.
+    pub fn infer(table: &str, columns: &[String], headers: &[String]) -> Self {
+        tracing::trace!("Mapping::infer({table}, {columns:?}, {headers:?})");
+        let normalize = |name: &str| {
+            name.chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+                .to_lowercase()
+        };
+        let columns = columns
+            .iter()
+            .filter_map(|column| {
+                headers
+                    .iter()
+                    .find(|header| normalize(header) == normalize(column))
+                    .map(|header| MappedColumn {
+                        target: column.to_string(),
+                        source: Some(header.to_string()),
+                        ..Default::default()
+                    })
+            })
+            .collect();
+        Self {
+            table: table.to_string(),
+            columns,
+        }
+    }
+
+    /// Read a [Mapping] from the YAML file at the given path
+    pub fn from_path(path: &str) -> Result<Self> {
+        tracing::trace!("Mapping::from_path({path})");
+        let yaml = std::fs::read_to_string(path)?;
+        let mapping: Mapping = serde_yaml::from_str(&yaml)?;
+        mapping.check()?;
+        Ok(mapping)
+    }
+
+    /// Serialize this [Mapping] to YAML
+    pub fn to_yaml(&self) -> Result<String> {
+        tracing::trace!("Mapping::to_yaml({self:?})");
+        self.check()?;
+        Ok(serde_yaml::to_string(self)?)
+    }
+
+    /// Check that every column of this [Mapping] is mapped either to a source column or to a
+    /// constant, but not both, and that all of its transforms are supported.
+    pub fn check(&self) -> Result<()> {
+        tracing::trace!("Mapping::check({self:?})");
+        for column in &self.columns {
+            let target = &column.target;
+            if column.source.is_some() == column.constant.is_some() {
+                return Err(RelatableError::InputError(format!(
+                    "Column '{target}' must be mapped to either a source column or a constant"
+                ))
+                .into());
+            }
+            for transform in &column.transforms {
+                if let Err(error) = Transform::from_str(transform) {
+                    return Err(RelatableError::InputError(format!(
+                        "Invalid transform for column '{target}': {error}"
+                    ))
+                    .into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the headers and records of the source file at the given path, which is read as a CSV
+    /// file if its extension is `.csv`, and as a TSV file otherwise, unless another delimiter or
+    /// encoding is detected (see [open_delimited()]).
+    pub fn read_source(path: &str) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+        tracing::trace!("Mapping::read_source({path})");
+        let delimiter = match path.to_lowercase().ends_with(".csv") {
+            true => b',',
+            false => b'\t',
+        };
+        let (reader, delimiter) = open_delimited(path, None, None, delimiter)?;
+        let mut rdr = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(reader);
+        let headers = rdr
+            .headers()?
+            .iter()
+            .map(|header| header.to_string())
+            .collect::<Vec<_>>();
+        let mut records = vec![];
+        for record in rdr.records() {
+            records.push(record?.iter().map(|value| value.to_string()).collect());
+        }
+        Ok((headers, records))
+    }
+
+    /// Apply this [Mapping] to the given record, read from a source file with the given headers,
+    /// and return the resulting values of the mapped columns.
+    pub fn apply(&self, headers: &[String], record: &[String]) -> Result<JsonRow> {
+        tracing::trace!("Mapping::apply({self:?}, {headers:?}, {record:?})");
+        let mut json_row = JsonRow::new();
+        for column in &self.columns {
+            let value = match (&column.source, &column.constant) {
+                (Some(source), _) => {
+                    let index = headers.iter().position(|header| header == source).ok_or(
+                        RelatableError::InputError(format!(
+                            "Source column '{source}' (mapped to '{target}') not found",
+                            target = column.target
+                        )),
+                    )?;
+                    let mut value = record.get(index).cloned().unwrap_or_default();
+                    for transform in &column.transforms {
+                        value = Transform::from_str(transform)?.apply(&value)?;
+                    }
+                    value
+                }
+                (None, Some(constant)) => constant.to_string(),
+                (None, None) => {
+                    return Err(RelatableError::InputError(format!(
+                        "Column '{target}' is not mapped",
+                        target = column.target
+                    ))
+                    .into())
+                }
+            };
+            json_row
+                .content
+                .insert(column.target.to_string(), JsonValue::String(value));
+        }
+        Ok(json_row)
+    }
+}
+
+/// Generate a random (version 4) UUID for a row
+pub fn generate_uuid() -> String {
+    tracing::trace!("generate_uuid()");
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
 /// Represents a row from some table
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct Row {
     pub id: u64,
     pub order: u64,
     pub change_id: u64,
+    /// The row's UUID, if its table has a _uuid column
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
     pub cells: IndexMap<String, Cell>,
 }
 
@@ -1596,6 +3369,11 @@ impl Row {
         row.id = table._get_next_id(tx)?;
         row.order = NEW_ORDER_MULTIPLIER as u64 * row.id;
         row.change_id = table.change_id;
+        // Keep any UUID that the row was given, e.g., by another database it is being merged
+        // from, and otherwise generate one:
+        if table.has_uuid && row.uuid.is_none() {
+            row.uuid = Some(generate_uuid());
+        }
         tracing::debug!("Prepared a new row: {row:?}");
         Ok(row)
     }
@@ -1607,49 +3385,53 @@ impl Row {
         self.cells.values().map(|cell| cell.text.clone()).collect()
     }
 
-    /// Generate an insert statement and a [JsonValue] representing an [Array](JsonValue::Array) of
-    /// parameters that need to be bound to the statement before it is executed.
-    pub fn as_insert(&self, table: &str, db_kind: &DbKind) -> (String, JsonValue) {
+    /// Generate an insert statement into the given table and a [JsonValue] representing an
+    /// [Array](JsonValue::Array) of parameters that need to be bound to the statement before it
+    /// is executed.
+    pub fn as_insert(&self, table: &Table, db_kind: &DbKind) -> (String, JsonValue) {
         tracing::trace!("Row::as_insert({table:?})");
         let id = self.id;
         let order = self.order;
-        let quoted_column_names = self
-            .cells
-            .keys()
-            .map(|k| format!(r#""{k}""#))
-            .collect::<Vec<_>>();
+        let mut quoted_column_names = vec![r#""_id""#.to_string(), r#""_order""#.to_string()];
+        if self.uuid.is_some() {
+            quoted_column_names.push(r#""_uuid""#.to_string());
+        }
+        quoted_column_names.append(
+            &mut self
+                .cells
+                .keys()
+                .map(|k| format!(r#""{k}""#))
+                .collect::<Vec<_>>(),
+        );
 
         let mut sql_param_gen = SqlParam::new(db_kind);
         let (value_placeholders, params) = {
             let mut params = vec![json!(id), json!(order)];
             let mut value_placeholders = vec![sql_param_gen.next(), sql_param_gen.next()];
-            for cell in self.cells.values() {
+            if let Some(uuid) = &self.uuid {
+                value_placeholders.push(sql_param_gen.next());
+                params.push(json!(uuid));
+            }
+            for (column, cell) in self.cells.iter() {
                 if cell.value == JsonValue::Null {
                     value_placeholders.push("NULL".to_string());
                 } else {
+                    let sql_type = table.get_config_for_column(column).get_sql_type();
                     value_placeholders.push(sql_param_gen.next());
-                    params.push(cell.value.clone());
+                    params.push(sql::to_sql_param(&cell.value, &sql_type));
                 }
             }
             (value_placeholders, params)
         };
 
-        let sql = if quoted_column_names.len() == 0 {
-            format!(
-                r#"INSERT INTO "{table}"
-                   ("_id", "_order")
-                   VALUES ({column_values})"#,
-                column_values = value_placeholders.join(", ")
-            )
-        } else {
-            format!(
-                r#"INSERT INTO "{table}"
-                   ("_id", "_order", {quoted_column_names})
-                   VALUES ({column_values})"#,
-                quoted_column_names = quoted_column_names.join(", "),
-                column_values = value_placeholders.join(", "),
-            )
-        };
+        let sql = format!(
+            r#"INSERT INTO "{table}"
+               ({quoted_column_names})
+               VALUES ({column_values})"#,
+            table = table.name,
+            quoted_column_names = quoted_column_names.join(", "),
+            column_values = value_placeholders.join(", "),
+        );
         (sql, json!(params))
     }
 
@@ -1660,9 +3442,11 @@ impl Row {
         table: &Table,
         tx: &mut DbTransaction<'_>,
     ) -> Result<&Self> {
+        let overrides = SeverityOverride::_get_overrides(&table.name, tx)?;
         for (column, cell) in self.cells.iter_mut() {
             let column_details = table.get_config_for_column(column);
             cell.validate_sql_type(&column_details)?;
+            cell.apply_severity_overrides(&overrides);
             for message in cell.messages.iter() {
                 let (msg_id, msg) = Relatable::_add_message(
                     "rltbl",
@@ -1708,6 +3492,11 @@ impl From<JsonRow> for Row {
             .get("_change_id")
             .and_then(|i| i.as_u64())
             .unwrap_or_default() as u64;
+        let uuid = row
+            .content
+            .get("_uuid")
+            .and_then(|u| u.as_str())
+            .map(|u| u.to_string());
         let mut cells: IndexMap<String, Cell> = row
             .content
             .iter()
@@ -1752,10 +3541,23 @@ impl From<JsonRow> for Row {
             }
         }
 
+        // The display labels of the cells' values, if they were looked up:
+        for (key, label) in &row.content {
+            if let Some(column) = key.strip_prefix("_display_") {
+                if let Some(cell) = cells.get_mut(column) {
+                    cell.display = match label {
+                        JsonValue::Null => None,
+                        label => Some(sql::json_to_string(label)),
+                    };
+                }
+            }
+        }
+
         Self {
             id,
             order,
             change_id,
+            uuid,
             cells,
         }
     }
@@ -1767,6 +3569,10 @@ pub struct Cell {
     pub value: JsonValue,
     pub text: String,
     pub messages: Vec<Message>,
+    /// The label to display in place of the cell's value, if the value is the key of a row in
+    /// another table that has a label column (see [Select::display()](crate::select::Select::display))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
 }
 
 impl From<&JsonValue> for Cell {
@@ -1781,6 +3587,7 @@ impl From<&JsonValue> for Cell {
                 value => format!("{value}"),
             },
             messages: vec![],
+            display: None,
         }
     }
 }
@@ -1792,7 +3599,11 @@ impl Cell {
         tracing::trace!("Cell::validate_sql_type({self:?}, {column:?})");
 
         fn invalidate(cell: &mut Cell, column: &Column) {
-            let datatype = &column.datatype.name;
+            // Name the explicit SQL type of the column, if it has one, otherwise its datatype:
+            let datatype = match &column.sql_type {
+                Some(sql_type) => sql_type.to_lowercase(),
+                None => column.datatype.name.to_string(),
+            };
             cell.messages.push(Message {
                 value: cell.value.clone(),
                 level: "error".to_string(),
@@ -1801,11 +3612,14 @@ impl Cell {
             });
         }
 
-        match column
-            .datatype
-            .infer_sql_type(&column.datatype_hierarchy)
-            .as_str()
-        {
+        let text = match &self.value {
+            JsonValue::String(value) => Some(value.to_string()),
+            _ => None,
+        };
+        let is_null = self.value == JsonValue::Null;
+
+        let sql_type = column.get_sql_type();
+        match sql::canonical_sql_type(&sql_type).unwrap_or(sql_type.as_str()) {
             "INTEGER" => match &mut self.value {
                 JsonValue::Number(number) => match number.to_string().parse::<i64>() {
                     Ok(_) => (),
@@ -1822,6 +3636,49 @@ impl Cell {
                 JsonValue::Null => (),
                 _ => invalidate(self, column),
             },
+            "BOOLEAN" => match &self.value {
+                JsonValue::Bool(_) | JsonValue::Null => (),
+                JsonValue::Number(number) if matches!(number.as_u64(), Some(0) | Some(1)) => (),
+                JsonValue::String(value)
+                    if ["true", "false", "t", "f", "1", "0"]
+                        .contains(&value.to_lowercase().as_str()) => {}
+                _ => invalidate(self, column),
+            },
+            "DATE" => {
+                let valid = text.is_some_and(|v| NaiveDate::parse_from_str(&v, "%Y-%m-%d").is_ok());
+                if !is_null && !valid {
+                    invalidate(self, column);
+                }
+            }
+            "TIMESTAMP" => {
+                let valid = text.is_some_and(|v| {
+                    DateTime::parse_from_rfc3339(&v).is_ok()
+                        || ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f"]
+                            .iter()
+                            .any(|format| NaiveDateTime::parse_from_str(&v, format).is_ok())
+                });
+                if !is_null && !valid {
+                    invalidate(self, column);
+                }
+            }
+            "UUID" => {
+                let uuid = regex::Regex::new(
+                    r"^(?i)[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}$",
+                )?;
+                if !is_null && !text.is_some_and(|v| uuid.is_match(&v)) {
+                    invalidate(self, column);
+                }
+            }
+            "JSONB" => {
+                if text.is_some_and(|v| serde_json::from_str::<JsonValue>(&v).is_err()) {
+                    invalidate(self, column);
+                }
+            }
+            "BLOB" => {
+                if !is_null && text.is_none_or(|v| sql::decode_binary(&v).is_err()) {
+                    invalidate(self, column);
+                }
+            }
             "TEXT" => (),
             unsupported => {
                 return Err(RelatableError::InputError(format!(
@@ -1834,6 +3691,15 @@ impl Cell {
         Ok(self)
     }
 
+    /// Adjust the levels of this cell's [messages](Cell::messages) according to the given list of
+    /// [severity overrides](SeverityOverride) for the table that the cell belongs to.
+    pub fn apply_severity_overrides(&mut self, overrides: &[SeverityOverride]) -> &Self {
+        for message in self.messages.iter_mut() {
+            message.level = SeverityOverride::resolve(overrides, &message.rule, &message.level);
+        }
+        self
+    }
+
     /// Report the maximum [error level](Message::level) associated with this cell's
     /// [messages](Cell::messages), where 0 represents no error, 1 represents the presence of
     /// at least one warning message, and 2 represents the presence of at least one error message.
@@ -1887,7 +3753,9 @@ pub struct Message {
 
 #[cfg(test)]
 mod tests {
+    use async_std::task::block_on;
     use pretty_assertions::assert_eq;
+    use rltbl::core::tests::memory_demo;
 
     use super::*;
 
@@ -1918,11 +3786,51 @@ mod tests {
                 id: 1,
                 order: 1000,
                 change_id: 0,
+                uuid: None,
                 cells
             }
         )
     }
 
+    #[test]
+    fn test_validate_sql_type() {
+        let is_valid = |sql_type: &str, value: JsonValue| {
+            let column = Column {
+                name: "foo".to_string(),
+                table: "bar".to_string(),
+                datatype: Datatype {
+                    name: "text".to_string(),
+                    ..Default::default()
+                },
+                sql_type: Some(sql_type.to_string()),
+                ..Default::default()
+            };
+            let mut cell = Cell::from(&value);
+            cell.validate_sql_type(&column).unwrap();
+            !cell.has_sql_type_error()
+        };
+
+        assert!(is_valid("BOOLEAN", json!(true)));
+        assert!(is_valid("BOOLEAN", json!("false")));
+        assert!(is_valid("BOOLEAN", json!(1)));
+        assert!(!is_valid("BOOLEAN", json!("maybe")));
+        assert!(is_valid("DATE", json!("2024-02-29")));
+        assert!(!is_valid("DATE", json!("2023-02-29")));
+        assert!(is_valid("TIMESTAMP", json!("2024-02-29 12:30:00")));
+        assert!(is_valid("TIMESTAMP", json!("2024-02-29T12:30:00.5Z")));
+        assert!(!is_valid("TIMESTAMP", json!("noon")));
+        assert!(is_valid(
+            "UUID",
+            json!("3F2504E0-4F89-11D3-9A0C-0305E82C3301")
+        ));
+        assert!(!is_valid("UUID", json!("3F2504E0")));
+        assert!(is_valid("JSONB", json!(r#"{"a": [1, 2]}"#)));
+        assert!(!is_valid("JSONB", json!("{a")));
+        assert!(is_valid("BLOB", json!("aGVsbG8=")));
+        assert!(!is_valid("BLOB", json!("not base64!")));
+        assert!(is_valid("DATE", JsonValue::Null));
+    }
+
     #[test]
     fn test_json_to_row_messages() {
         let json_blob = json!({
@@ -1954,6 +3862,7 @@ mod tests {
                     rule: "test rule".to_string(),
                     message: "Test message 'FOO'".to_string(),
                 }],
+                ..Default::default()
             },
         );
         assert_eq!(
@@ -1962,8 +3871,968 @@ mod tests {
                 id: 1,
                 order: 1000,
                 change_id: 0,
+                uuid: None,
                 cells
             }
         )
     }
+
+    #[test]
+    fn test_unique_messages() {
+        let rltbl = memory_demo(3);
+
+        // Every row of the demo penguin table has the same study name:
+        let mut table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        table.columns.get_mut("study_name").unwrap().structure = Some(Structure::Unique);
+        block_on(rltbl.validate_structure_for_table(&table)).unwrap();
+
+        let sql = r#"SELECT "row", "rule", "message" FROM "message"
+                     WHERE "column" = 'study_name' AND "rule" LIKE 'key:%'
+                     ORDER BY "row""#;
+        let messages = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                vec![
+                    "1",
+                    "key:unique",
+                    "study_name must be unique but 'FAKE123' is also used by row(s) 2, 3 \
+                     (see table/penguin?_id=in.(2,3))"
+                ],
+                vec![
+                    "2",
+                    "key:unique",
+                    "study_name must be unique but 'FAKE123' is also used by row(s) 1, 3 \
+                     (see table/penguin?_id=in.(1,3))"
+                ],
+                vec![
+                    "3",
+                    "key:unique",
+                    "study_name must be unique but 'FAKE123' is also used by row(s) 1, 2 \
+                     (see table/penguin?_id=in.(1,2))"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_column_dependencies() {
+        let rltbl = memory_demo(3);
+        for sql in [
+            r#"INSERT INTO "column" ("table", "column", "structure")
+               VALUES ('penguin', 'individual_id', 'from(penguin.sample_number)')"#,
+            r#"CREATE TABLE "rule" (
+                 "table" TEXT,
+                 "when_column" TEXT,
+                 "when_condition" TEXT,
+                 "then_column" TEXT,
+                 "then_condition" TEXT,
+                 "level" TEXT,
+                 "description" TEXT
+               )"#,
+            r#"INSERT INTO "rule" VALUES
+                 ('penguin', 'species', 'equals(''Pygoscelis adeliae'')',
+                  'individual_id', 'required', NULL, NULL)"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+
+        let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        let dependencies = block_on(table.get_dependencies(&rltbl)).unwrap();
+        let dependency = |column: &str, kind: &str| ColumnDependency {
+            table: "penguin".to_string(),
+            column: column.to_string(),
+            kind: kind.to_string(),
+        };
+        assert_eq!(
+            dependencies["individual_id"],
+            ColumnDependencies {
+                depends_on: vec![
+                    dependency("sample_number", "structure"),
+                    dependency("species", "rule")
+                ],
+                dependents: vec![],
+            }
+        );
+        assert_eq!(
+            dependencies["sample_number"].dependents,
+            vec![dependency("individual_id", "structure")]
+        );
+        assert_eq!(
+            dependencies["species"].dependents,
+            vec![dependency("individual_id", "rule")]
+        );
+        assert_eq!(dependencies["study_name"], ColumnDependencies::default());
+
+        // Columns may also depend on other tables:
+        assert_eq!(
+            dependencies["island"].depends_on,
+            vec![ColumnDependency {
+                table: "island".to_string(),
+                column: "island".to_string(),
+                kind: "structure".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_display_labels() {
+        use rltbl::select::Select;
+
+        let rltbl = memory_demo(3);
+
+        // Without a label column in the island table, there is nothing to display:
+        let select = Select::from("penguin").display(true);
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        assert_eq!(result.rows[0].cells["island"].display, None);
+
+        for sql in [
+            r#"ALTER TABLE "island" ADD COLUMN "label" TEXT"#,
+            r#"UPDATE "island" SET "label" = 'The island of ' || "island""#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        let result = block_on(rltbl.fetch(&select)).unwrap();
+        for row in &result.rows {
+            let cell = &row.cells["island"];
+            assert_eq!(cell.display, Some(format!("The island of {}", cell.text)));
+            assert_eq!(row.cells["species"].display, None);
+            assert!(!row.cells.contains_key("_display_island"));
+        }
+
+        // Labels are only looked up on request:
+        let result = block_on(rltbl.fetch(&Select::from("penguin"))).unwrap();
+        assert_eq!(result.rows[0].cells["island"].display, None);
+    }
+
+    #[test]
+    fn test_datatype_hierarchy_messages() {
+        let rltbl = memory_demo(3);
+
+        let sql = r#"INSERT INTO "datatype" ("datatype", "parent", "condition")
+                     VALUES ('main_study', 'study_name', 'equals(FAKE456)')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "penguin" SET "study_name" = 'BOGUS' WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        let mut table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        let column = table.columns.get_mut("study_name").unwrap();
+        column.datatype = block_on(Datatype::get_datatype("main_study", &rltbl))
+            .unwrap()
+            .unwrap();
+        column.datatype_hierarchy = block_on(column.datatype.get_all_ancestors(&rltbl)).unwrap();
+        block_on(rltbl.validate_datatype_for_table(&table)).unwrap();
+
+        let sql = r#"SELECT "row", "rule", "message" FROM "message"
+                     WHERE "column" = 'study_name'
+                     ORDER BY "row""#;
+        let messages = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| row.to_strings())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                vec![
+                    "1",
+                    "datatype:study_name",
+                    "study_name is not a valid study_name, but is a text"
+                ],
+                vec![
+                    "2",
+                    "datatype:main_study",
+                    "study_name is not a valid main_study, but is a study_name"
+                ],
+                vec![
+                    "3",
+                    "datatype:main_study",
+                    "study_name is not a valid main_study, but is a study_name"
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rule_messages() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+
+        let rltbl = memory_demo(3);
+
+        let sql = r#"CREATE TABLE "rule" (
+                       "table" TEXT,
+                       "when_column" TEXT,
+                       "when_condition" TEXT,
+                       "then_column" TEXT,
+                       "then_condition" TEXT,
+                       "level" TEXT,
+                       "description" TEXT
+                     )"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        // Rules with unsupported conditions are skipped:
+        let sql = r#"INSERT INTO "rule" VALUES
+                       ('penguin', 'island', 'equals(''Biscoe'')', 'sex', 'forbidden', NULL, NULL),
+                       ('penguin', 'island', 'like(''B%'')', 'sex', 'required', NULL, NULL),
+                       ('penguin', 'species', 'equals(''Pygoscelis adeliae'')',
+                        'individual_id', 'required', NULL, NULL)"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "penguin" SET "individual_id" = NULL WHERE "_id" = 2"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        let get_messages = || {
+            let sql = r#"SELECT "row", "column", "rule", "message" FROM "message"
+                         ORDER BY "row""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+        let expected_message = |row: &str| {
+            vec![
+                row.to_string(),
+                "individual_id".to_string(),
+                "rule:required".to_string(),
+                "individual_id is required when species is 'Pygoscelis adeliae'".to_string(),
+            ]
+        };
+
+        // Batch validation:
+        let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+        block_on(rltbl.validate_table(&table)).unwrap();
+        assert_eq!(get_messages(), vec![expected_message("2")]);
+
+        // Edit-time validation:
+        let set_value = |row: u64, before: &str, after: &str| {
+            block_on(rltbl.set_values(&ChangeSet {
+                user: "rltbl".to_string(),
+                action: ChangeAction::Do,
+                table: "penguin".to_string(),
+                description: "Set one value".to_string(),
+                message: None,
+                changes: vec![Change::Update {
+                    row,
+                    column: "individual_id".to_string(),
+                    before: json!(before),
+                    after: json!(after),
+                }],
+            }))
+            .unwrap();
+        };
+        set_value(2, "", "N2A2");
+        set_value(3, "N2A1", "");
+        assert_eq!(get_messages(), vec![expected_message("3")]);
+    }
+
+    #[test]
+    fn test_severity_overrides() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet};
+
+        let rltbl = memory_demo(3);
+        let sql = r#"UPDATE "penguin" SET "study_name" = 'BOGUS' WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        let get_levels = || {
+            let sql = r#"SELECT "row", "rule", "level" FROM "message" ORDER BY "row", "rule""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+        let validate = || {
+            let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+            block_on(rltbl.validate_table(&table)).unwrap();
+        };
+
+        // Without any overrides, datatype failures are errors:
+        validate();
+        assert_eq!(get_levels(), vec![["1", "datatype:study_name", "error"]]);
+
+        // The datatype table may declare a default level for the datatype:
+        let sql = r#"ALTER TABLE "datatype" ADD COLUMN "level" TEXT"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"UPDATE "datatype" SET "level" = 'info' WHERE "datatype" = 'study_name'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        validate();
+        assert_eq!(get_levels(), vec![["1", "datatype:study_name", "info"]]);
+
+        // Overrides for the table take precedence, and the most specific override wins:
+        let sql = r#"CREATE TABLE "severity_override" (
+                       "table" TEXT,
+                       "rule" TEXT,
+                       "level" TEXT
+                     )"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"INSERT INTO "severity_override" VALUES
+                       ('penguin', 'datatype:%', 'warn'),
+                       ('penguin', 'sql_type:integer', 'warn'),
+                       ('penguin', 'key:%', 'bogus'),
+                       ('study', 'datatype:study_name', 'error')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        validate();
+        assert_eq!(get_levels(), vec![["1", "datatype:study_name", "warn"]]);
+
+        let sql = r#"INSERT INTO "severity_override" VALUES
+                       ('penguin', 'datatype:study_name', 'error')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        validate();
+        assert_eq!(get_levels(), vec![["1", "datatype:study_name", "error"]]);
+
+        // Overrides also apply to the SQL type messages generated at edit time:
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 2,
+                column: "sample_number".to_string(),
+                before: json!(2),
+                after: json!("two"),
+            }],
+        }))
+        .unwrap();
+        assert_eq!(
+            get_levels(),
+            vec![
+                ["1", "datatype:study_name", "error"],
+                ["2", "sql_type:integer", "warn"]
+            ]
+        );
+    }
+
+    #[test]
+    fn test_message_identity() {
+        let rltbl = memory_demo(3);
+        let sql = r#"UPDATE "penguin" SET "study_name" = 'BOGUS' WHERE "_id" = 1"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        block_on(rltbl.add_message(
+            "mike",
+            "penguin",
+            1,
+            "study_name",
+            &json!("BOGUS"),
+            "info",
+            "custom",
+            "this is a bogus study",
+        ))
+        .unwrap();
+
+        let get_messages = || {
+            let sql = r#"SELECT "message_id", "row", "rule",
+                                CAST("_datetime" AS TEXT) AS "_datetime"
+                         FROM "message"
+                         ORDER BY "message_id""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.to_strings())
+                .collect::<Vec<_>>()
+        };
+        let validate = || {
+            let table = block_on(Table::get_table("penguin", &rltbl)).unwrap();
+            block_on(rltbl.validate_table(&table)).unwrap();
+        };
+
+        validate();
+        let messages = get_messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1][1..3], ["1", "datatype:study_name"]);
+
+        // Re-running validation neither duplicates the message nor changes its ID, but does
+        // refresh its timestamp:
+        let sql = r#"UPDATE "message" SET "_datetime" = '2000-01-01 00:00:00'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        validate();
+        let revalidated = get_messages();
+        assert_eq!(revalidated.len(), 2);
+        assert_eq!(revalidated[0], ["1", "1", "custom", "2000-01-01 00:00:00"]);
+        assert_eq!(revalidated[1][0..3], messages[1][0..3]);
+        assert_ne!(revalidated[1][3], "2000-01-01 00:00:00");
+
+        // Resolved messages are deleted and new ones are added:
+        for sql in [
+            r#"UPDATE "penguin" SET "study_name" = 'FAKE123' WHERE "_id" = 1"#,
+            r#"UPDATE "penguin" SET "study_name" = 'BOGUS' WHERE "_id" = 2"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        validate();
+        let messages = get_messages();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0][0..3], ["1", "1", "custom"]);
+        assert_eq!(messages[1][1..3], ["2", "datatype:study_name"]);
+        assert_ne!(messages[1][0], revalidated[1][0]);
+
+        // Every message has a content hash:
+        let sql = r#"SELECT COUNT(1) FROM "message" WHERE "_hash" IS NULL"#;
+        let unhashed = block_on(rltbl.connection.query_value(sql, None)).unwrap();
+        assert_eq!(unhashed, Some(json!(0)));
+    }
+
+    #[test]
+    fn test_mappings() {
+        let rltbl = memory_demo(3);
+        let path = "build/test_mappings.csv";
+        std::fs::write(
+            path,
+            "Sample Number,Species,Isle\n101,  Pygoscelis papua ,dream\n102,,biscoe\n",
+        )
+        .unwrap();
+        let (headers, records) = Mapping::read_source(path).unwrap();
+        assert_eq!(headers, ["Sample Number", "Species", "Isle"]);
+        assert_eq!(records.len(), 2);
+
+        // Columns are inferred by name, ignoring case and punctuation:
+        let columns = ["study_name", "sample_number", "species", "island"].map(String::from);
+        let mut mapping = Mapping::infer("penguin", &columns, &headers);
+        let sources = mapping
+            .columns
+            .iter()
+            .map(|c| (c.target.as_str(), c.source.as_deref().unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [("sample_number", "Sample Number"), ("species", "Species")]
+        );
+
+        mapping.columns[1].transforms = vec!["trim".to_string()];
+        mapping.columns.push(MappedColumn {
+            target: "island".to_string(),
+            source: Some("Isle".to_string()),
+            transforms: vec!["uppercase".to_string()],
+            ..Default::default()
+        });
+        mapping.columns.push(MappedColumn {
+            target: "study_name".to_string(),
+            constant: Some("FAKE456".to_string()),
+            ..Default::default()
+        });
+        let yaml_path = "build/test_mappings.yaml";
+        std::fs::write(yaml_path, mapping.to_yaml().unwrap()).unwrap();
+        assert_eq!(Mapping::from_path(yaml_path).unwrap(), mapping);
+        assert_eq!(
+            mapping.apply(&headers, &records[0]).unwrap().content,
+            json!({"sample_number": "101", "species": "Pygoscelis papua", "island": "DREAM",
+                   "study_name": "FAKE456"})
+            .as_object()
+            .unwrap()
+            .clone()
+        );
+
+        // Invalid mappings are rejected:
+        let mut invalid = mapping.clone();
+        invalid.columns[0].transforms = vec!["reverse".to_string()];
+        assert!(invalid.check().is_err());
+        invalid.columns[0].transforms = vec![];
+        invalid.columns[0].constant = Some("1".to_string());
+        assert!(invalid.check().is_err());
+        let mut invalid = mapping.clone();
+        invalid.columns[0].target = "no_such_column".to_string();
+        assert!(block_on(rltbl.load_mapped(&invalid, path, "rltbl", None)).is_err());
+
+        // The rows of the source are added as a single change, which can be undone at once:
+        let count = block_on(rltbl.load_mapped(&mapping, path, "rltbl", Some("Feed"))).unwrap();
+        assert_eq!(count, 2);
+        let get_islands = || {
+            let sql = r#"SELECT "island" FROM "penguin" ORDER BY "_order""#;
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.get_string("island").unwrap())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(get_islands()[3..], ["DREAM", "BISCOE"]);
+        block_on(rltbl.undo("rltbl")).unwrap();
+        assert_eq!(get_islands().len(), 3);
+    }
+
+    #[test]
+    fn test_foreign_keys() {
+        assert_eq!(
+            ForeignKey::from_str("enforce").unwrap(),
+            ForeignKey::default()
+        );
+        let foreign_key = ForeignKey::from_str("ON DELETE set  null on update cascade").unwrap();
+        assert_eq!(
+            foreign_key,
+            ForeignKey {
+                on_delete: ForeignKeyAction::SetNull,
+                on_update: ForeignKeyAction::Cascade,
+            }
+        );
+        assert_eq!(
+            foreign_key.to_string(),
+            "on delete set null on update cascade"
+        );
+        assert!(ForeignKey::from_str("on delete explode").is_err());
+
+        let rltbl = memory_demo(3);
+        for sql in [
+            r#"ALTER TABLE "column" ADD COLUMN "foreign_key" TEXT"#,
+            r#"INSERT INTO "column" ("table", "column", "structure", "foreign_key")
+               VALUES ('visit', 'site', 'from(site.site)', 'on update cascade')"#,
+        ] {
+            block_on(rltbl.connection.query(sql, None)).unwrap();
+        }
+        for (table, content) in [
+            ("site", "site\tname\ns1\tNorth\ns2\tSouth\n"),
+            ("visit", "visit\tsite\nv1\ts1\nv2\ts2\n"),
+        ] {
+            let path = format!("build/test_foreign_keys_{table}.tsv");
+            std::fs::write(&path, content).unwrap();
+            block_on(rltbl.load_table(table, &path, true, None));
+        }
+        let query_values = |sql: &str| {
+            block_on(rltbl.connection.query(sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| row.content.values().next().unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Values that are not in the referenced column are rejected by the database:
+        let sql = r#"INSERT INTO "visit" ("visit", "site") VALUES ('v3', 's3')"#;
+        assert!(block_on(rltbl.connection.query(sql, None)).is_err());
+
+        // Updates to the referenced column are cascaded:
+        let sql = r#"UPDATE "site" SET "site" = 's9' WHERE "site" = 's1'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        assert_eq!(
+            query_values(r#"SELECT "site" FROM "visit" ORDER BY "_id""#),
+            [json!("s9"), json!("s2")]
+        );
+
+        // Reloading the referenced table keeps the unique index that the foreign key requires:
+        let path = "build/test_foreign_keys_visit.tsv";
+        block_on(rltbl.connection.query(r#"DELETE FROM "visit""#, None)).unwrap();
+        block_on(rltbl.load_table("site", "build/test_foreign_keys_site.tsv", true, None));
+        block_on(rltbl.load_table("visit", path, true, None));
+        assert_eq!(
+            query_values(r#"SELECT "site" FROM "visit" ORDER BY "_id""#),
+            [json!("s1"), json!("s2")]
+        );
+    }
+
+    #[test]
+    fn test_binary_values() {
+        use rltbl::{
+            core::{Change, ChangeAction, ChangeSet},
+            select::Select,
+        };
+
+        let rltbl = memory_demo(3);
+        let sql = r#"INSERT INTO "datatype" ("datatype", "parent", "sql_type")
+                     VALUES ('binary', 'text', 'BLOB')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"INSERT INTO "column" ("table", "column", "datatype")
+                     VALUES ('file', 'content', 'binary')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        // Binary values are loaded from, and shown in the text view as, hexadecimal, but are
+        // stored as bytes and represented in JSON as base64:
+        std::fs::write(
+            "build/test_binary_values.tsv",
+            "name\tcontent\nhello\t68656c6c6f\n",
+        )
+        .unwrap();
+        block_on(rltbl.load_table("file", "build/test_binary_values.tsv", true, None));
+        let sql = r#"SELECT TYPEOF("content") AS "type" FROM "file""#;
+        assert_eq!(
+            block_on(rltbl.connection.query_value(sql, None)).unwrap(),
+            Some(json!("blob"))
+        );
+        let get_content = |view: &str| {
+            let mut select = Select::from("file");
+            select.view_name = format!("file_{view}_view");
+            let result = block_on(rltbl.fetch(&select)).unwrap();
+            result
+                .rows
+                .iter()
+                .map(|row| row.cells.get("content").unwrap().text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(get_content("default"), ["aGVsbG8="]);
+        assert_eq!(get_content("text"), ["68656c6c6f"]);
+
+        // New and updated values are given in base64 and bound as bytes:
+        let row = JsonRow {
+            content: json!({"name": "bytes", "content": sql::encode_binary(&[0, 159, 255])})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let row = block_on(rltbl.add_row("file", "rltbl", None, &row)).unwrap();
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "file".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 1,
+                column: "content".to_string(),
+                before: json!("aGVsbG8="),
+                after: json!(sql::encode_binary(b"bye")),
+            }],
+        }))
+        .unwrap();
+        assert_eq!(get_content("text"), ["627965", "009fff"]);
+        assert_eq!(
+            block_on(rltbl.get_cell_bytes("file", &row.id, "content")).unwrap(),
+            Some(vec![0, 159, 255])
+        );
+        assert_eq!(
+            block_on(rltbl.get_cell_bytes("file", &row.id, "name")).unwrap(),
+            Some(b"bytes".to_vec())
+        );
+
+        // Values that are not base64 are not valid binary values:
+        let row = JsonRow {
+            content: json!({"name": "bad", "content": "not base64!"})
+                .as_object()
+                .unwrap()
+                .clone(),
+        };
+        let row = block_on(rltbl.add_row("file", "rltbl", None, &row)).unwrap();
+        assert!(row.cells.get("content").unwrap().has_sql_type_error());
+        assert_eq!(
+            block_on(rltbl.get_cell_bytes("file", &row.id, "content")).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_locales() {
+        use rltbl::select::Select;
+
+        assert_eq!(Locale::from_str("en-US").unwrap(), Locale::EnUs);
+        assert!(Locale::from_str("klingon").is_err());
+        assert_eq!(Locale::De.format("10.5", "NUMERIC"), "10,5");
+        assert_eq!(Locale::De.parse("10,5", "NUMERIC"), "10.5");
+        assert_eq!(Locale::De.parse("ten", "NUMERIC"), "ten");
+        assert_eq!(Locale::EnUs.format("2026-10-17", "DATE"), "10/17/2026");
+        assert_eq!(Locale::Fr.parse("17/10/2026", "DATE"), "2026-10-17");
+        assert_eq!(Locale::Iso.format("10.5", "NUMERIC"), "10.5");
+
+        let mut rltbl = memory_demo(0);
+        let sql = r#"INSERT INTO "column" ("table", "column", "datatype")
+                     VALUES ('bird', 'mass', 'decimal')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        // CSV files are delimited and their values are read as is usual in the locale:
+        rltbl.locale = Locale::De;
+        let path = "build/test_locales.csv";
+        std::fs::write(path, "name;mass\nswan;10,5\nwren;0,01\n").unwrap();
+        block_on(rltbl.load_table("bird", path, true, None));
+        let sql = r#"SELECT "mass" FROM "bird" ORDER BY "_order""#;
+        let masses = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| row.get_string("mass").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(masses, ["10.5", "0.01"]);
+
+        // Exports are written in the locale of the select, if there is one:
+        let result = block_on(rltbl.fetch(&Select::from("bird"))).unwrap();
+        assert_eq!(result.to_csv(), "name,mass\nswan,10.5\nwren,0.01\n");
+        let result = block_on(rltbl.fetch(&Select::from("bird").locale(&Locale::De))).unwrap();
+        assert_eq!(result.to_csv(), "name;mass\nswan;10,5\nwren;0,01\n");
+    }
+
+    #[test]
+    fn test_transforms() {
+        assert_eq!(
+            Transform::parse_list("trim, strip_prefix('ID:'), date('%d, %m %Y')").unwrap(),
+            [
+                Transform::Trim,
+                Transform::StripPrefix("ID:".to_string()),
+                Transform::Date("%d, %m %Y".to_string())
+            ]
+        );
+        assert_eq!(
+            Transform::from_str(r#"strip_suffix("kg")"#)
+                .unwrap()
+                .to_string(),
+            "strip_suffix('kg')"
+        );
+        assert!(Transform::from_str("reverse").is_err());
+        assert!(Transform::from_str("strip_prefix").is_err());
+        assert!(Transform::from_str("trim('x')").is_err());
+        assert_eq!(
+            Transform::Date("%m/%d/%Y".to_string())
+                .apply("10/17/2026")
+                .unwrap(),
+            "2026-10-17"
+        );
+
+        let rltbl = memory_demo(0);
+        let sql = r#"ALTER TABLE "column" ADD COLUMN "transform" TEXT"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"INSERT INTO "column" ("table", "column", "datatype", "transform")
+                     VALUES ('sample', 'id', 'text', 'trim, strip_prefix(''ID:'')'),
+                            ('sample', 'collected', 'date', 'date(''%m/%d/%Y'')')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        // The values are transformed before they are validated, or else left as they are:
+        let path = "build/test_transforms.tsv";
+        std::fs::write(
+            path,
+            "id\tcollected\n ID:1\t10/17/2026\n2\t2026-10-18\nID:3\t\n",
+        )
+        .unwrap();
+        block_on(rltbl.load_table("sample", path, true, None));
+        let sql = r#"SELECT "id", CAST("collected" AS TEXT) AS "collected"
+                     FROM "sample" ORDER BY "_order""#;
+        let values = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| {
+                (
+                    row.get_string("id").unwrap(),
+                    row.get_string("collected").unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("1".to_string(), "2026-10-17".to_string()),
+                ("2".to_string(), "2026-10-18".to_string()),
+                ("3".to_string(), "".to_string()),
+            ]
+        );
+
+        // The raw values are kept in messages, along with the values that could not be
+        // transformed:
+        let sql = r#"SELECT "row", "column", "value", "level", "rule" FROM "message"
+                     WHERE "table" = 'sample' AND "rule" LIKE 'transform%'
+                     ORDER BY "row", "column""#;
+        let messages = block_on(rltbl.connection.query(sql, None))
+            .unwrap()
+            .iter()
+            .map(|row| {
+                ["row", "column", "value", "level", "rule"]
+                    .iter()
+                    .map(|column| row.get_string(column).unwrap())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                "1 collected 10/17/2026 info transform",
+                "1 id  ID:1 info transform",
+                "2 collected 2026-10-18 warn transform:date",
+                "3 id ID:3 info transform",
+            ]
+        );
+
+        // Invalid transforms are reported as configuration problems:
+        let sql = r#"UPDATE "column" SET "transform" = 'reverse' WHERE "column" = 'id'"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let problems = block_on(rltbl.check_config()).unwrap();
+        assert!(problems
+            .iter()
+            .any(|problem| problem.rule == "config:invalid-transform"));
+
+        // Such transforms are ignored when the table is read:
+        let table = block_on(Table::get_table("sample", &rltbl)).unwrap();
+        assert!(table.columns["id"].transforms.is_empty());
+    }
+
+    #[test]
+    fn test_collations() {
+        use rltbl::select::{Order, Select};
+
+        assert_eq!(Collation::from_str("natural").unwrap(), Collation::Natural);
+        assert_eq!(Collation::CaseInsensitive.to_string(), "case_insensitive");
+        assert!(Collation::from_str("reverse").is_err());
+        assert!(Collation::natural_key("N9A1") < Collation::natural_key("N10A1"));
+
+        let rltbl = memory_demo(0);
+        let sql = r#"ALTER TABLE "column" ADD COLUMN "collation" TEXT"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+        let sql = r#"INSERT INTO "column" ("table", "column", "collation")
+                     VALUES ('item', 'code', 'natural'),
+                            ('item', 'label', 'case_insensitive'),
+                            ('item', 'size', 'numeric')"#;
+        block_on(rltbl.connection.query(sql, None)).unwrap();
+
+        let path = "build/test_collations.tsv";
+        std::fs::write(
+            path,
+            "code\tlabel\tsize\nN10A1\tb\t10\nN9A2\tA\t9\nn2\tc\tlarge\n\
+             N1A1\ta\t2.5\nN9A1\tB\t-1\n",
+        )
+        .unwrap();
+        block_on(rltbl.load_table("item", path, true, None));
+
+        let fetch = |column: &str, order: &Order| {
+            let mut select = Select::from("item");
+            select.add_order_by(column, order);
+            block_on(rltbl.fetch(&select))
+                .unwrap()
+                .rows
+                .iter()
+                .map(|row| row.cells[column].text.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            fetch("code", &Order::ASC),
+            ["N1A1", "N9A1", "N9A2", "N10A1", "n2"]
+        );
+        assert_eq!(fetch("label", &Order::ASC), ["A", "a", "B", "b", "c"]);
+        assert_eq!(
+            fetch("size", &Order::ASC),
+            ["-1", "2.5", "9", "10", "large"]
+        );
+        assert_eq!(
+            fetch("size", &Order::DESC),
+            ["10", "9", "2.5", "-1", "large"]
+        );
+    }
+
+    #[test]
+    fn test_dialects() {
+        use rltbl::core::Encoding;
+
+        assert_eq!(Encoding::detect(b"caf\xc3\xa9"), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"caf\xe9"), Encoding::Latin1);
+        assert_eq!(Encoding::detect(b"c\0a\0f\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::detect(b"\xfe\xff\0c"), Encoding::Utf16Be);
+        assert_eq!(Encoding::from_str("ISO-8859-1").unwrap(), Encoding::Latin1);
+        assert_eq!(Encoding::Latin1.decode(b"caf\xe9"), "café");
+
+        let mut rltbl = memory_demo(0);
+        let query_values = |rltbl: &Relatable, table: &str| {
+            let sql = format!(r#"SELECT "name", "place" FROM "{table}" ORDER BY "_order""#);
+            block_on(rltbl.connection.query(&sql, None))
+                .unwrap()
+                .iter()
+                .map(|row| {
+                    format!(
+                        "{}|{}",
+                        row.get_string("name").unwrap(),
+                        row.get_string("place").unwrap()
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        // A Latin-1 CSV file delimited by semicolons, as is usual in Europe:
+        let path = "build/test_dialects_latin.csv";
+        std::fs::write(
+            path,
+            b"name;place\nJos\xe9;\"Z\xfcrich; CH\"\nAnna;Gen\xe8ve\n",
+        )
+        .unwrap();
+        block_on(rltbl.load_table("latin", path, true, None));
+        assert_eq!(
+            query_values(&rltbl, "latin"),
+            ["José|Zürich; CH", "Anna|Genève"]
+        );
+
+        // A UTF-16 TSV file with a byte order mark:
+        let path = "build/test_dialects_wide.tsv";
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in "name\tplace\nJosé\tZürich, CH\n".encode_utf16() {
+            bytes.extend(unit.to_le_bytes());
+        }
+        std::fs::write(path, bytes).unwrap();
+        block_on(rltbl.load_table("wide", path, true, None));
+        assert_eq!(query_values(&rltbl, "wide"), ["José|Zürich, CH"]);
+
+        // The delimiter and encoding can also be given explicitly:
+        let path = "build/test_dialects_pipe.txt";
+        std::fs::write(path, "name|place\nJosé|Zürich\n").unwrap();
+        rltbl.delimiter = Some(b'|');
+        rltbl.encoding = Some(Encoding::Utf8);
+        block_on(rltbl.load_table("pipe", path, true, None));
+        assert_eq!(query_values(&rltbl, "pipe"), ["José|Zürich"]);
+    }
+
+    #[test]
+    fn test_wide_mode() {
+        use rltbl::core::{Change, ChangeAction, ChangeSet, DEFAULT_WIDE_COLUMN_LIMIT};
+
+        let mut rltbl = memory_demo(3);
+        // An invalid value gives the row a message:
+        block_on(rltbl.set_values(&ChangeSet {
+            user: "rltbl".to_string(),
+            action: ChangeAction::Do,
+            table: "penguin".to_string(),
+            description: "Set one value".to_string(),
+            message: None,
+            changes: vec![Change::Update {
+                row: 1,
+                column: "body_mass".to_string(),
+                before: JsonValue::Null,
+                after: json!("heavy"),
+            }],
+        }))
+        .unwrap();
+        let get_row = |rltbl: &Relatable| {
+            let mut table = block_on(Table::get_table("penguin", rltbl)).unwrap();
+            block_on(table.ensure_default_view_created(rltbl)).unwrap();
+            let sql = r#"SELECT "_id", "_history", "_message" FROM "penguin_default_view"
+                         WHERE "_id" = 1"#;
+            let row = block_on(rltbl.connection.query_one(sql, None))
+                .unwrap()
+                .unwrap();
+            (table, row)
+        };
+
+        let (table, row) = get_row(&rltbl);
+        assert!(!table.is_wide(&rltbl));
+        assert!(row.get_optional_string("_message").unwrap().is_some());
+        assert!(row.get_optional_string("_history").unwrap().is_some());
+
+        // The view on a wide table leaves the history and messages to be looked up by row:
+        rltbl.wide_column_limit = 2;
+        let (table, mut row) = get_row(&rltbl);
+        assert!(table.is_wide(&rltbl));
+        assert_eq!(row.get_optional_string("_message").unwrap(), None);
+        assert_eq!(row.get_optional_string("_history").unwrap(), None);
+        block_on(rltbl.get_row_annotations(&table, &mut row)).unwrap();
+        assert!(row.get_string("_message").unwrap().contains("body_mass"));
+        assert!(row.get_string("_history").unwrap().contains("heavy"));
+
+        // A table's own setting takes precedence over its number of columns:
+        block_on(rltbl.set_wide_mode("penguin", Some(false))).unwrap();
+        let (table, row) = get_row(&rltbl);
+        assert_eq!(table.wide, Some(false));
+        assert!(row.get_optional_string("_message").unwrap().is_some());
+        rltbl.wide_column_limit = DEFAULT_WIDE_COLUMN_LIMIT;
+        block_on(rltbl.set_wide_mode("penguin", Some(true))).unwrap();
+        let (table, row) = get_row(&rltbl);
+        assert!(table.is_wide(&rltbl));
+        assert_eq!(row.get_optional_string("_message").unwrap(), None);
+        assert!(block_on(rltbl.set_wide_mode("nothing", None)).is_err());
+
+        // Tables with more columns than the warning limit are diagnosed unless they are wide:
+        let get_failures = |rltbl: &Relatable| {
+            block_on(rltbl.doctor())
+                .unwrap()
+                .into_iter()
+                .filter(|diagnosis| !diagnosis.ok && diagnosis.check == "columns")
+                .map(|diagnosis| diagnosis.detail)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(get_failures(&rltbl), Vec::<String>::new());
+        block_on(rltbl.set_wide_mode("penguin", None)).unwrap();
+        rltbl.column_warning_limit = 2;
+        let failures = get_failures(&rltbl);
+        assert!(failures.iter().any(|detail| detail.contains("'penguin'")));
+        block_on(rltbl.set_wide_mode("penguin", Some(true))).unwrap();
+        let failures = get_failures(&rltbl);
+        assert!(failures.iter().all(|detail| !detail.contains("'penguin'")));
+    }
 }
@@ -373,6 +373,7 @@ async fn main() {
                                     action: ChangeAction::Do,
                                     table: table.to_string(),
                                     description: "Set one value".to_string(),
+                                    message: None,
                                     changes: vec![Change::Update {
                                         row: row_to_update,
                                         column: "study_name".to_string(),
@@ -4,13 +4,16 @@
 
 use crate::{self as rltbl, core::ResultSet};
 use rltbl::{
-    cli::Cli,
-    core::{ChangeSet, Cursor, Relatable, RelatableError},
-    select::{joined_query, Format, QueryParams, Select},
-    sql::{CachingStrategy, JsonRow, SqlParam},
+    cli::{Cli, ValidationSummary},
+    core::{
+        blame_to_tsv, column_stats_to_tsv, Change, ChangeSet, Cursor, PastedCell, Relatable,
+        RelatableError, DEFAULT_AUTOSAVE_INTERVAL,
+    },
+    select::{joined_query, Format, Limit, PageCursor, QueryParams, Select},
+    sql::{self, CachingStrategy, JsonRow, SqlParam},
     table::{Row, Table},
 };
-use std::io::Write;
+use std::{future::Future, io::Write};
 
 use anyhow::Result;
 use async_std::sync::Arc;
@@ -34,6 +37,15 @@ fn forbid() -> Response<Body> {
     (StatusCode::FORBIDDEN, Html(format!("403 Forbidden"))).into_response()
 }
 
+fn get_400(error: &anyhow::Error) -> Response<Body> {
+    tracing::error!("400 {error:?}");
+    (
+        StatusCode::BAD_REQUEST,
+        Html(format!("400 Bad Request: {error}")),
+    )
+        .into_response()
+}
+
 fn get_404(error: &anyhow::Error) -> Response<Body> {
     tracing::error!("404 {error:?}");
     (
@@ -44,6 +56,10 @@ fn get_404(error: &anyhow::Error) -> Response<Body> {
 }
 
 fn get_500(error: &anyhow::Error) -> Response<Body> {
+    // A query that was cancelled for running too long is the database's fault, not the server's:
+    if let Some(RelatableError::TimeoutError(_)) = error.downcast_ref() {
+        return get_504(error);
+    }
     tracing::error!("500 {error:?}");
     (
         StatusCode::INTERNAL_SERVER_ERROR,
@@ -52,6 +68,15 @@ fn get_500(error: &anyhow::Error) -> Response<Body> {
         .into_response()
 }
 
+fn get_504(error: &anyhow::Error) -> Response<Body> {
+    tracing::error!("504 {error:?}");
+    (
+        StatusCode::GATEWAY_TIMEOUT,
+        Html(format!("504 Gateway Timeout: {error}")),
+    )
+        .into_response()
+}
+
 async fn get_root(State(rltbl): State<Arc<Relatable>>) -> impl IntoResponse {
     tracing::info!("request root");
     let default = "table";
@@ -107,10 +132,41 @@ async fn respond(rltbl: &Relatable, format: &Format, content: &JsonValue) -> Res
             )
             .into(),
         ),
+        Format::Arrow => get_500(
+            &RelatableError::FormatError(
+                "Arrow format should be handled before `respond()`".to_string(),
+            )
+            .into(),
+        ),
+        Format::PlanJson => get_500(
+            &RelatableError::FormatError(
+                "Plan format should be handled before `respond()`".to_string(),
+            )
+            .into(),
+        ),
+        Format::FacetsJson => get_500(
+            &RelatableError::FormatError(
+                "Facets format should be handled before `respond()`".to_string(),
+            )
+            .into(),
+        ),
+        Format::Count => get_500(
+            &RelatableError::FormatError(
+                "Count format should be handled before `respond()`".to_string(),
+            )
+            .into(),
+        ),
     };
     response
 }
 
+/// Respond with the given number of rows, as plain text, e.g., for the count format.
+fn respond_count(count: u64) -> Response<Body> {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, "text/plain".parse().unwrap());
+    (headers, count.to_string()).into_response()
+}
+
 fn respond_csv(result: ResultSet) -> Response<Body> {
     let mut headers = HeaderMap::new();
     headers.insert(header::CONTENT_TYPE, "text/csv".parse().unwrap());
@@ -126,44 +182,190 @@ fn respond_tsv(result: ResultSet) -> Response<Body> {
     (headers, result.to_tsv()).into_response()
 }
 
-fn get_username(session: Session<SessionNullPool>) -> String {
+fn respond_arrow(result: ResultSet) -> Response<Body> {
+    let arrow = match result.to_arrow() {
+        Ok(arrow) => arrow,
+        Err(error) => return get_500(&error),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "application/vnd.apache.arrow.stream".parse().unwrap(),
+    );
+    (headers, arrow).into_response()
+}
+
+/// Normalize the URL of a request for data, given its path and query parameters, so that
+/// requests that differ only in the order of their query parameters share cached results.
+fn normalize_url(path: &str, query_params: &QueryParams) -> String {
+    let mut query_params = query_params.iter().collect::<Vec<_>>();
+    query_params.sort();
+    match query_params.is_empty() {
+        true => path.to_string(),
+        false => format!(
+            "{path}?{query}",
+            query = query_params
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect::<Vec<_>>()
+                .join("&")
+        ),
+    }
+}
+
+/// Get the user from the RLTBL_USER environment variable, or else the session, or else use the
+/// default user of the given instance, if it has one.
+fn get_username(rltbl: &Relatable, session: Session<SessionNullPool>) -> String {
     let username = std::env::var("RLTBL_USER").unwrap_or_default();
     if username != "" {
         return username;
     }
-    session.get("username").unwrap_or_default()
+    match session.get::<String>("username") {
+        Some(username) if !username.is_empty() => username,
+        _ => rltbl.default_user.clone().unwrap_or_default(),
+    }
+}
+
+/// Get the idempotency key of the request, from its Idempotency-Key header or else its
+/// idempotency_key query parameter, if it has one (see [Relatable::idempotent()]).
+fn get_idempotency_key(headers: &HeaderMap, query_params: &QueryParams) -> Option<String> {
+    headers
+        .get("Idempotency-Key")
+        .and_then(|key| key.to_str().ok())
+        .or(query_params.get("idempotency_key").map(|key| key.as_str()))
+        .filter(|key| !key.trim().is_empty())
+        .map(|key| key.to_string())
+}
+
+/// Run the given operation on behalf of the given user under the given idempotency key, if
+/// there is one, and otherwise simply run it. Returns None if the operation was not run because
+/// changes have already been made under the key (see [Relatable::idempotent()]).
+async fn run_idempotent<T, F, Fut>(
+    rltbl: &Relatable,
+    user: &str,
+    key: &Option<String>,
+    operation: F,
+) -> Result<Option<T>>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    match key {
+        Some(key) => rltbl.idempotent(user, key, operation).await,
+        None => operation().await.map(Some),
+    }
+}
+
+/// Respond to a request that was not run again because changes had already been made under its
+/// idempotency key with the given body, marked with an Idempotent-Replayed header.
+fn replayed(body: JsonValue) -> Response<Body> {
+    (
+        StatusCode::OK,
+        [("Idempotent-Replayed", "true")],
+        Json(body),
+    )
+        .into_response()
 }
 
 async fn get_table(
     State(rltbl): State<Arc<Relatable>>,
     Path(path): Path<String>,
-    Query(query_params): Query<QueryParams>,
+    Query(mut query_params): Query<QueryParams>,
     session: Session<SessionNullPool>,
 ) -> Response<Body> {
     // tracing::info!("get_table({rltbl:?}, {path}, {query_params:?})");
 
-    let username = get_username(session);
+    // Resources that belong to a table, e.g., /table/penguin/dependencies.json:
+    if let Some((table, resource)) = path.split_once("/") {
+        let username = get_username(&rltbl, session);
+        return get_table_resource(&rltbl, table, resource, &query_params, &username).await;
+    }
+
+    let username = get_username(&rltbl, session);
     if username.trim() != "" {
         init_user(&rltbl, &username).await;
     }
-    let select = Select::from_path_and_query(&path, &query_params, &rltbl).await;
+    // The columns to count the values of, in the facets format, which is not a filter:
+    let facets = query_params.shift_remove("facets");
+    if let Err(error) = Limit::from_query_params(&query_params) {
+        return get_400(&error);
+    }
+    let mut select = Select::from_path_and_query(&path, &query_params, &rltbl).await;
     let format = match Format::try_from(&path) {
         Ok(format) => format,
         Err(error) => return get_404(&error),
     };
-    let result = match rltbl.fetch(&select).await {
+    // Only the rows are counted, without fetching them, e.g., for dashboards that poll the
+    // number of rows of a table: /table/penguin.count?island=eq.Dream
+    if let Format::Count = format {
+        return match rltbl.count(&select).await {
+            Ok(count) => respond_count(count),
+            Err(error) => get_500(&error),
+        };
+    }
+    // Pages show the labels, rather than the keys, of rows in other tables:
+    if matches!(format, Format::Html | Format::Default) {
+        select.display = true;
+    }
+    // The plan of a select is returned instead of its rows, e.g., to debug a slow view:
+    if let Format::PlanJson = format {
+        return match select.explain(&rltbl).await {
+            Ok(plan) => Json(plan).into_response(),
+            Err(error) => get_500(&error),
+        };
+    }
+    // The counts of the values of columns among the rows, e.g., for the options of a filter, are
+    // returned instead of the rows: /table/penguin.facets.json?facets=island,species
+    if let Format::FacetsJson = format {
+        let columns = facets.unwrap_or_default();
+        let columns = columns
+            .split(",")
+            .map(|column| column.trim())
+            .filter(|column| !column.is_empty())
+            .collect::<Vec<_>>();
+        return match rltbl
+            .facets_cached(&select.table_name, &columns, &select)
+            .await
+        {
+            Ok(facets) => Json(facets).into_response(),
+            Err(error) => match error.downcast_ref() {
+                Some(RelatableError::InputError(_)) => get_404(&error),
+                _ => get_500(&error),
+            },
+        };
+    }
+    let url = normalize_url(&format!("table/{path}"), &query_params);
+    let result = match rltbl.fetch_cached(&select, &url).await {
         Ok(result) => result,
         Err(error) => return get_500(&error),
     };
     match format {
         Format::Csv => return respond_csv(result),
         Format::Tsv => return respond_tsv(result),
+        Format::Arrow => return respond_arrow(result),
         _ => (),
     }
     let site = rltbl.get_site(&username).await;
+    let templates = get_template_names(&rltbl, &result.table.name).await;
+    let mut page = select
+        .to_page(&rltbl.root, "table", &vec![])
+        .unwrap_or_default();
+    // Link to the pages before and after this one using cursors:
+    let base = format!("{}/table", rltbl.root);
+    let cursor_url = |token: Option<&str>, before: bool| {
+        let cursor = PageCursor::from_token(token?).ok()?;
+        let select = match before {
+            true => select.clone().before(&cursor),
+            false => select.clone().after(&cursor),
+        };
+        select.to_url(&base, &format).ok()
+    };
+    page.previous = cursor_url(result.range.previous(), true);
+    page.next = cursor_url(result.range.next(), false);
     let content = json!({
         "site": site,
-        "page": select.to_page(&rltbl.root, "table", &vec![]).unwrap_or_default(),
+        "page": page,
+        "templates": templates,
         "result": result
     });
     respond(&rltbl, &format, &content).await
@@ -172,8 +374,10 @@ async fn get_table(
 async fn post_table(
     State(rltbl): State<Arc<Relatable>>,
     Path(path): Path<String>,
+    Query(query_params): Query<QueryParams>,
     _session: Session<SessionNullPool>,
-    ExtractJson(changeset): ExtractJson<ChangeSet>,
+    headers: HeaderMap,
+    ExtractJson(mut changeset): ExtractJson<ChangeSet>,
 ) -> Response<Body> {
     tracing::info!("post_table([rltbl], {path}, {changeset:?})");
     if rltbl.readonly {
@@ -192,7 +396,7 @@ async fn post_table(
 
     // WARN: We need to check that the user matches!
     // let user = changeset.user.clone();
-    // let username = get_username(session);
+    // let username = get_username(&rltbl, session);
     // if username != user {
     //     return get_500(
     //         &RelatableError::InputError(format!(
@@ -202,12 +406,204 @@ async fn post_table(
     //     );
     // }
 
-    match rltbl.set_values(&changeset).await {
-        Ok(_) => "POST successful".into_response(),
+    // A message given in the X-Change-Message header takes precedence over any message given
+    // in the changeset itself:
+    if let Some(message) = headers
+        .get("X-Change-Message")
+        .and_then(|m| m.to_str().ok())
+        .filter(|m| !m.trim().is_empty())
+    {
+        changeset.message = Some(message.to_string());
+    }
+
+    // Along with the changeset that was applied, respond with the current messages of every cell
+    // whose messages were changed by it, so that the grid can redraw them:
+    let key = get_idempotency_key(&headers, &query_params);
+    let user = changeset.user.clone();
+    match run_idempotent(&rltbl, &user, &key, || {
+        rltbl.set_values_with_messages(&changeset)
+    })
+    .await
+    {
+        Ok(Some((changeset, messages))) => {
+            let mut response = json!(changeset);
+            response["messages"] = json!(messages);
+            Json(response).into_response()
+        }
+        Ok(None) => match rltbl
+            .get_idempotent_changes(&user, key.as_deref().unwrap_or_default())
+            .await
+        {
+            Ok(changesets) => {
+                let mut response = json!(changesets.first());
+                response["messages"] = json!([]);
+                replayed(response)
+            }
+            Err(error) => get_500(&error),
+        },
         Err(error) => get_500(&error),
     }
 }
 
+/// Load the TSV content in the body of the request into the given table, as the signed-in user,
+/// for clients, such as [rltbl::remote::Remote], that have no access to the database. Only the
+/// tables that are listed in the table table, other than the meta tables, can be loaded, and the
+/// content only replaces the file that the table was loaded from once it has been loaded (see
+/// [Relatable::load_table_content()]). An existing table is only replaced if the `force` query
+/// parameter is `true`.
+async fn post_load(
+    State(rltbl): State<Arc<Relatable>>,
+    Path(table): Path<String>,
+    Query(query_params): Query<QueryParams>,
+    session: Session<SessionNullPool>,
+    headers: HeaderMap,
+    body: String,
+) -> Response<Body> {
+    tracing::info!("post_load([rltbl], {table}, {query_params:?})");
+    if rltbl.readonly {
+        return forbid();
+    }
+    if let Err(error) = sql::is_simple(&table) {
+        return get_404(&RelatableError::InputError(error).into());
+    }
+    let force = query_params.get("force").map(|force| force.as_str()) == Some("true");
+    let message = headers
+        .get("X-Change-Message")
+        .and_then(|m| m.to_str().ok())
+        .filter(|m| !m.trim().is_empty());
+    let user = get_username(&rltbl, session);
+    match rltbl
+        .load_table_content(&user, &table, &body, force, message)
+        .await
+    {
+        Ok(load) => Json(load).into_response(),
+        Err(error) => get_500(&error),
+    }
+}
+
+/// Fill the given column of the given range of rows of the given table, as the signed-in user,
+/// copying the value of the first row, or, given `series=true`, extending the series that the
+/// first two rows begin (see [Relatable::fill_down()]). Responds with the changes that were made.
+async fn post_fill_down(
+    State(rltbl): State<Arc<Relatable>>,
+    Path((table, column, from_row, to_row)): Path<(String, String, String, String)>,
+    Query(query_params): Query<QueryParams>,
+    session: Session<SessionNullPool>,
+    headers: HeaderMap,
+) -> Response<Body> {
+    tracing::info!("post_fill_down([rltbl], {table}, {column}, {from_row}, {to_row})");
+    if rltbl.readonly {
+        return forbid();
+    }
+    let mut rows = vec![];
+    for row_id in [from_row, to_row] {
+        match Table::get_row_id(&table, &row_id, &rltbl).await {
+            Ok(row_id) => rows.push(row_id),
+            Err(error) => return get_404(&error),
+        }
+    }
+    let series = query_params.get("series").map(|series| series.as_str()) == Some("true");
+    let username = get_username(&rltbl, session);
+    let key = get_idempotency_key(&headers, &query_params);
+    match run_idempotent(&rltbl, &username, &key, || {
+        rltbl.fill_down(&table, &username, &column, rows[0], rows[1], series)
+    })
+    .await
+    {
+        Ok(Some(changeset)) => Json(changeset).into_response(),
+        Ok(None) => match rltbl
+            .get_idempotent_changes(&username, key.as_deref().unwrap_or_default())
+            .await
+        {
+            Ok(changesets) => replayed(json!(changesets.first())),
+            Err(error) => get_500(&error),
+        },
+        Err(error) => match error.downcast_ref() {
+            Some(RelatableError::InputError(_)) => get_404(&error),
+            _ => get_500(&error),
+        },
+    }
+}
+
+/// Paste the block of tab-separated values in the request body into the given table, starting
+/// at the given row and column, and respond with the result for each pasted cell. A request that
+/// is replayed under the same idempotency key is answered with the cells that were changed.
+async fn post_paste(
+    State(rltbl): State<Arc<Relatable>>,
+    Path((table, row, column)): Path<(String, String, String)>,
+    Query(query_params): Query<QueryParams>,
+    session: Session<SessionNullPool>,
+    headers: HeaderMap,
+    body: String,
+) -> Response<Body> {
+    tracing::info!("post_paste([rltbl], {table}, {row}, {column})");
+    if rltbl.readonly {
+        return forbid();
+    }
+    let row = match Table::get_row_id(&table, &row, &rltbl).await {
+        Ok(row) => row,
+        Err(error) => return get_404(&error),
+    };
+    let username = get_username(&rltbl, session);
+    let key = get_idempotency_key(&headers, &query_params);
+    match run_idempotent(&rltbl, &username, &key, || {
+        rltbl.paste(&table, &username, row, &column, &body)
+    })
+    .await
+    {
+        Ok(Some(cells)) => Json(cells).into_response(),
+        Ok(None) => match rltbl
+            .get_idempotent_changes(&username, key.as_deref().unwrap_or_default())
+            .await
+        {
+            Ok(changesets) => replayed(json!(changesets
+                .iter()
+                .flat_map(|changeset| changeset.changes.iter())
+                .filter_map(|change| match change {
+                    Change::Update {
+                        row,
+                        column,
+                        before,
+                        after,
+                    } => Some(PastedCell {
+                        row: *row,
+                        column: column.to_string(),
+                        before: before.clone(),
+                        after: after.clone(),
+                        changed: true,
+                        messages: vec![],
+                    }),
+                    _ => None,
+                })
+                .collect::<Vec<_>>())),
+            Err(error) => get_500(&error),
+        },
+        Err(error) => match error.downcast_ref() {
+            Some(RelatableError::InputError(_)) => get_404(&error),
+            _ => get_500(&error),
+        },
+    }
+}
+
+/// Validate the given table and respond with a summary of its messages.
+async fn post_validate(
+    State(rltbl): State<Arc<Relatable>>,
+    Path(table_name): Path<String>,
+) -> Response<Body> {
+    tracing::info!("post_validate([rltbl], {table_name})");
+    if rltbl.readonly {
+        return forbid();
+    }
+    let table = match Table::get_table(&table_name, &rltbl).await {
+        Ok(table) => table,
+        Err(error) => return get_404(&error),
+    };
+    if let Err(error) = rltbl.validate_table(&table).await {
+        return get_500(&error);
+    }
+    Json(ValidationSummary::collect(&rltbl, &table_name, None, None).await).into_response()
+}
+
 async fn get_tableset(
     State(rltbl): State<Arc<Relatable>>,
     Path((tableset_name, path)): Path<(String, String)>,
@@ -219,16 +615,23 @@ async fn get_tableset(
         Ok(format) => format,
         Err(error) => return get_404(&error),
     };
+    if let Err(error) = Limit::from_query_params(&query_params) {
+        return get_400(&error);
+    }
 
     let select = Select::from_path_and_query(&path, &query_params, &rltbl).await;
     // tracing::info!("SELECT {select:?}",);
 
-    if matches!(format, Format::ValueJson) {
+    if matches!(format, Format::ValueJson | Format::Count) {
+        let respond = |count: u64| match format {
+            Format::Count => respond_count(count),
+            _ => Json(count).into_response(),
+        };
         let sel = match joined_query(&rltbl, &tableset_name, &select).await {
             Ok(select) => select,
             Err(error) => match error.downcast_ref() {
                 Some(RelatableError::ConfigError(e)) => match e.as_str() {
-                    "empty tableset" => return Json(json!(0)).into_response(),
+                    "empty tableset" => return respond(0),
                     _ => return get_500(&error),
                 },
                 _ => {
@@ -240,11 +643,23 @@ async fn get_tableset(
             Ok(count) => count,
             Err(error) => return get_500(&error),
         };
-        return Json(value).into_response();
+        return respond(value);
+    }
+
+    if let Format::PlanJson = format {
+        let plan = match joined_query(&rltbl, &tableset_name, &select).await {
+            Ok(select) => select.explain(&rltbl).await,
+            Err(error) => Err(error),
+        };
+        return match plan {
+            Ok(plan) => Json(plan).into_response(),
+            Err(error) => get_500(&error),
+        };
     }
 
+    let url = normalize_url(&format!("tableset/{tableset_name}/{path}"), &query_params);
     let mut result = match joined_query(&rltbl, &tableset_name, &select).await {
-        Ok(sel) => match rltbl.fetch(&sel).await {
+        Ok(sel) => match rltbl.fetch_cached(&sel, &url).await {
             Ok(result) => result,
             Err(error) => return get_500(&error),
         },
@@ -276,10 +691,11 @@ async fn get_tableset(
     match format {
         Format::Csv => return respond_csv(result),
         Format::Tsv => return respond_tsv(result),
+        Format::Arrow => return respond_arrow(result),
         _ => (),
     }
 
-    let username = get_username(session);
+    let username = get_username(&rltbl, session);
     if username.trim() != "" {
         init_user(&rltbl, &username).await;
     }
@@ -303,9 +719,11 @@ async fn get_tableset(
         tabset.push(table.clone());
     }
 
+    let templates = get_template_names(&rltbl, &result.table.name).await;
     let content = json!({
        "site": site,
        "page": select.to_page(&rltbl.root, &format!("tableset/{tableset_name}"), &tabset).unwrap_or_default(),
+       "templates": templates,
        "result": result
     });
     respond(&rltbl, &format, &content).await
@@ -382,7 +800,7 @@ async fn post_cursor(
     ExtractJson(cursor): ExtractJson<Cursor>,
 ) -> Response<Body> {
     // tracing::info!("post_cursor({cursor:?})");
-    let username = get_username(session);
+    let username = get_username(&rltbl, session);
     tracing::debug!("post_cursor({cursor:?}, {username})");
     // TODO: sanitize the cursor JSON.
     let mut sql_param = SqlParam::new(&rltbl.connection.kind());
@@ -405,10 +823,14 @@ async fn post_cursor(
 async fn get_row_menu(
     State(rltbl): State<Arc<Relatable>>,
     session: Session<SessionNullPool>,
-    Path((table_name, row_id)): Path<(String, u64)>,
+    Path((table_name, row_id)): Path<(String, String)>,
 ) -> Response<Body> {
     tracing::info!("get_row_menu({table_name}, {row_id})");
-    let username = get_username(session);
+    let row_id = match Table::get_row_id(&table_name, &row_id, &rltbl).await {
+        Ok(row_id) => row_id,
+        Err(error) => return get_404(&error),
+    };
+    let username = get_username(&rltbl, session);
     let site = rltbl.get_site(&username).await;
     let table = match Table::get_table(&table_name, &rltbl).await {
         Ok(table) => table,
@@ -427,7 +849,11 @@ async fn get_row_menu(
         .await
     {
         Ok(row) => match row {
-            Some(row) => row.into(),
+            // Wide tables' views leave the row's history and messages to be looked up here:
+            Some(mut row) => match rltbl.get_row_annotations(&table, &mut row).await {
+                Ok(_) => row.into(),
+                Err(error) => return get_500(&error),
+            },
             None => {
                 return get_404(
                     &RelatableError::MissingError(format!(
@@ -439,7 +865,8 @@ async fn get_row_menu(
         },
         Err(error) => return get_500(&error),
     };
-    match rltbl.render("row_menu.html", context! {site, table, row}) {
+    let templates = get_template_names(&rltbl, &table_name).await;
+    match rltbl.render("row_menu.html", context! {site, table, row, templates}) {
         Ok(html) => Html(html).into_response(),
         Err(error) => return get_500(&error),
     }
@@ -452,7 +879,7 @@ async fn get_column_menu(
     Query(query_params): Query<QueryParams>,
 ) -> Response<Body> {
     tracing::info!("get_column_menu({table_name}, {column})");
-    let username = get_username(session);
+    let username = get_username(&rltbl, session);
     let select = Select::from_path_and_query(&table_name, &query_params, &rltbl).await;
     let mut operator = String::new();
     let mut value = json!("");
@@ -467,7 +894,10 @@ async fn get_column_menu(
     }
     for (c, o) in select.order_by {
         if c == column {
-            order = format!("{o:?}");
+            order = match o.is_descending() {
+                true => "DESC".to_string(),
+                false => "ASC".to_string(),
+            };
         }
     }
     let site = rltbl.get_site(&username).await;
@@ -488,7 +918,7 @@ async fn get_cell_menu(
     Path((table_name, row_id, column)): Path<(String, u64, String)>,
 ) -> Response<Body> {
     tracing::info!("get_cell_menu({table_name}, {row_id}, {column})");
-    let username = get_username(session);
+    let username = get_username(&rltbl, session);
     let site = rltbl.get_site(&username).await;
     let table = match Table::get_table(&table_name, &rltbl).await {
         Ok(table) => table,
@@ -507,7 +937,11 @@ async fn get_cell_menu(
         .await
     {
         Ok(row) => match row {
-            Some(row) => row.into(),
+            // Wide tables' views leave the row's history and messages to be looked up here:
+            Some(mut row) => match rltbl.get_row_annotations(&table, &mut row).await {
+                Ok(_) => row.into(),
+                Err(error) => return get_500(&error),
+            },
             None => {
                 return get_404(
                     &RelatableError::MissingError(format!(
@@ -561,6 +995,249 @@ async fn get_cell_options(
     Json(json!(values)).into_response()
 }
 
+/// Serve the raw contents of the given cell as an attachment, e.g., to download a binary value.
+async fn get_cell(
+    State(rltbl): State<Arc<Relatable>>,
+    Path((table, row_id, column)): Path<(String, u64, String)>,
+) -> Response<Body> {
+    tracing::info!("get_cell({table}, {row_id}, {column})");
+    let bytes = match rltbl.get_cell_bytes(&table, &row_id, &column).await {
+        Ok(bytes) => bytes.unwrap_or_default(),
+        Err(error) => return get_404(&error),
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::CONTENT_TYPE,
+        "application/octet-stream".parse().unwrap(),
+    );
+    let disposition = format!(r#"attachment; filename="{table}_{row_id}_{column}""#);
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        disposition
+            .parse()
+            .unwrap_or(header::HeaderValue::from_static("attachment")),
+    );
+    (headers, bytes).into_response()
+}
+
+/// Serve the changes made to the given table since the change given by the `after` query
+/// parameter, or, if it is absent, all of the table's rows, for the instances that mirror it.
+async fn get_changes(
+    State(rltbl): State<Arc<Relatable>>,
+    Path(table): Path<String>,
+    Query(query_params): Query<QueryParams>,
+) -> Response<Body> {
+    tracing::info!("get_changes({table}, {query_params:?})");
+    let after = match query_params.get("after").map(|after| after.parse::<u64>()) {
+        None => None,
+        Some(Ok(after)) => Some(after),
+        Some(Err(error)) => return get_404(&error.into()),
+    };
+    match rltbl.get_change_feed(&table, after).await {
+        Ok(feed) => Json(feed).into_response(),
+        Err(error) => get_404(&error),
+    }
+}
+
+/// Describe the changes that the next undo by the signed-in user would make, without making them
+/// (see [Relatable::preview_undo()]).
+async fn get_undo_preview(
+    State(rltbl): State<Arc<Relatable>>,
+    session: Session<SessionNullPool>,
+) -> Response<Body> {
+    let username = get_username(&rltbl, session);
+    tracing::info!("get_undo_preview({username})");
+    match rltbl.preview_undo(&username).await {
+        Ok(changesets) => Json(changesets).into_response(),
+        Err(error) => get_500(&error),
+    }
+}
+
+/// Serve the given resource belonging to the given table, for the given user. The resources are
+/// `dependencies.json`: the dependencies among the table's columns and those of other tables (see
+/// [Table::get_dependencies()]), which tell clients which dropdowns to refresh and which cells to
+/// check again after an edit, `blame/{column}`: the last change to the column of each row (see
+/// [Relatable::blame()]), as a page, or as JSON or TSV, and `stats`: statistics about the values
+/// of each of the table's columns (see [Relatable::column_stats()]), as JSON or TSV.
+async fn get_table_resource(
+    rltbl: &Relatable,
+    table: &str,
+    resource: &str,
+    query_params: &QueryParams,
+    username: &str,
+) -> Response<Body> {
+    tracing::info!("get_table_resource({table}, {resource})");
+    if let Some(column) = resource.strip_prefix("blame/") {
+        return get_blame(rltbl, table, column, username).await;
+    }
+    if let Some(column) = resource.strip_prefix("facets/") {
+        return get_facet(rltbl, table, column, query_params).await;
+    }
+    if resource.split(".").next() == Some("stats") {
+        return get_column_stats(rltbl, table, resource).await;
+    }
+    let format = match Format::try_from(&resource.to_string()) {
+        Ok(format @ (Format::Json | Format::PrettyJson))
+            if resource.split(".").next() == Some("dependencies") =>
+        {
+            format
+        }
+        _ => {
+            return get_404(
+                &RelatableError::InputError(format!("Unknown resource 'table/{table}/{resource}'"))
+                    .into(),
+            )
+        }
+    };
+    let table = match Table::get_table(table, rltbl).await {
+        Ok(table) => table,
+        Err(error) => return get_404(&error),
+    };
+    match table.get_dependencies(rltbl).await {
+        Ok(dependencies) => {
+            let content = json!({"table": table.name, "columns": dependencies});
+            respond(rltbl, &format, &content).await
+        }
+        Err(error) => get_500(&error),
+    }
+}
+
+/// Serve the counts of the distinct values of the given column among the rows of the given table
+/// that match the filters in the given query parameters, e.g.,
+/// /table/penguin/facets/species.json?island=eq.Dream, as JSON.
+async fn get_facet(
+    rltbl: &Relatable,
+    table: &str,
+    column: &str,
+    query_params: &QueryParams,
+) -> Response<Body> {
+    tracing::info!("get_facet({table}, {column}, {query_params:?})");
+    let format = match Format::try_from(&column.to_string()) {
+        Ok(Format::Default) => Format::Json,
+        Ok(format @ (Format::Json | Format::PrettyJson)) => format,
+        _ => {
+            return get_404(
+                &RelatableError::InputError(format!("Unknown format for facet of '{column}'"))
+                    .into(),
+            )
+        }
+    };
+    let column = column.split(".").next().unwrap_or_default();
+    let select = Select::from_path_and_query(table, query_params, rltbl).await;
+    let facet = match rltbl.facets_cached(table, &[column], &select).await {
+        Ok(mut facets) => facets.remove(0),
+        Err(error) => match error.downcast_ref() {
+            Some(RelatableError::InputError(_)) => return get_404(&error),
+            _ => return get_500(&error),
+        },
+    };
+    match format {
+        Format::PrettyJson => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+            (headers, to_string_pretty(&facet).unwrap_or_default()).into_response()
+        }
+        _ => Json(facet).into_response(),
+    }
+}
+
+/// Serve the last change to the given column of each row of the given table, e.g.,
+/// /table/penguin/blame/species.html, as a page, or as JSON or TSV, for the given user.
+async fn get_blame(rltbl: &Relatable, table: &str, column: &str, username: &str) -> Response<Body> {
+    tracing::info!("get_blame({table}, {column})");
+    let format = match Format::try_from(&column.to_string()) {
+        Ok(format @ (Format::Html | Format::Default | Format::Json | Format::PrettyJson)) => format,
+        Ok(Format::Tsv) => Format::Tsv,
+        _ => {
+            return get_404(
+                &RelatableError::InputError(format!("Unknown format for blame of '{column}'"))
+                    .into(),
+            )
+        }
+    };
+    let column = column.split(".").next().unwrap_or_default();
+    let blame = match rltbl.blame(table, column).await {
+        Ok(blame) => blame,
+        Err(error) => return get_404(&error),
+    };
+    match format {
+        Format::Tsv => {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                "text/tab-separated-values".parse().unwrap(),
+            );
+            match blame_to_tsv(&blame) {
+                Ok(tsv) => (headers, tsv).into_response(),
+                Err(error) => get_500(&error),
+            }
+        }
+        Format::Json => Json(blame).into_response(),
+        Format::PrettyJson => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+            (headers, to_string_pretty(&blame).unwrap_or_default()).into_response()
+        }
+        _ => {
+            let site = rltbl.get_site(username).await;
+            let table = json!({"name": table});
+            match rltbl.render("blame.html", context! {site, table, column, blame}) {
+                Ok(html) => Html(html).into_response(),
+                Err(error) => get_500(&error),
+            }
+        }
+    }
+}
+
+/// Serve statistics about the values of each of the columns of the given table, e.g.,
+/// /table/penguin/stats.tsv, as JSON (the default) or TSV.
+async fn get_column_stats(rltbl: &Relatable, table: &str, resource: &str) -> Response<Body> {
+    tracing::info!("get_column_stats({table}, {resource})");
+    let format = match Format::try_from(&resource.to_string()) {
+        Ok(Format::Default) => Format::Json,
+        Ok(format @ (Format::Json | Format::PrettyJson | Format::Tsv)) => format,
+        _ => {
+            return get_404(
+                &RelatableError::InputError(format!("Unknown format for '{resource}'")).into(),
+            )
+        }
+    };
+    let stats = match rltbl.column_stats(table).await {
+        Ok(stats) => stats,
+        Err(error) => return get_404(&error),
+    };
+    match format {
+        Format::Tsv => {
+            let mut headers = HeaderMap::new();
+            headers.insert(
+                header::CONTENT_TYPE,
+                "text/tab-separated-values".parse().unwrap(),
+            );
+            match column_stats_to_tsv(&stats) {
+                Ok(tsv) => (headers, tsv).into_response(),
+                Err(error) => get_500(&error),
+            }
+        }
+        Format::PrettyJson => {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, "application/json".parse().unwrap());
+            (headers, to_string_pretty(&stats).unwrap_or_default()).into_response()
+        }
+        _ => Json(stats).into_response(),
+    }
+}
+
+/// Get the names of the row templates defined for the given table.
+async fn get_template_names(rltbl: &Relatable, table: &str) -> Vec<String> {
+    match rltbl.get_templates(table).await {
+        Ok(templates) => templates.keys().cloned().collect(),
+        Err(error) => {
+            tracing::warn!("Error getting templates for '{table}': {error:?}");
+            vec![]
+        }
+    }
+}
+
 async fn previous_row_id(rltbl: &Relatable, table: &str, row_id: &u64) -> u64 {
     let sql = format!(
         r#"SELECT "_id", MAX("_order") FROM "{table}"
@@ -581,32 +1258,52 @@ async fn previous_row_id(rltbl: &Relatable, table: &str, row_id: &u64) -> u64 {
 async fn add_row_before(
     State(rltbl): State<Arc<Relatable>>,
     session: Session<SessionNullPool>,
-    Path((table, row_id)): Path<(String, u64)>,
+    Path((table, row_id)): Path<(String, String)>,
+    Query(query_params): Query<QueryParams>,
+    headers: HeaderMap,
 ) -> Response<Body> {
     tracing::info!("add_row_before({table}, {row_id})");
-    let username = get_username(session);
+    let row_id = match Table::get_row_id(&table, &row_id, &rltbl).await {
+        Ok(row_id) => row_id,
+        Err(error) => return get_404(&error),
+    };
+    let username = get_username(&rltbl, session);
     let after_id = previous_row_id(&rltbl, &table, &row_id).await;
-    return add_row(&rltbl, &username, &table, Some(after_id)).await;
+    let template = query_params.get("template");
+    let key = get_idempotency_key(&headers, &query_params);
+    return add_row(&rltbl, &username, &table, Some(after_id), template, &key).await;
 }
 
 async fn add_row_after(
     State(rltbl): State<Arc<Relatable>>,
     session: Session<SessionNullPool>,
-    Path((table, row_id)): Path<(String, u64)>,
+    Path((table, row_id)): Path<(String, String)>,
+    Query(query_params): Query<QueryParams>,
+    headers: HeaderMap,
 ) -> Response<Body> {
     tracing::info!("add_row_after({table}, {row_id})");
-    let username = get_username(session);
-    return add_row(&rltbl, &username, &table, Some(row_id)).await;
+    let row_id = match Table::get_row_id(&table, &row_id, &rltbl).await {
+        Ok(row_id) => row_id,
+        Err(error) => return get_404(&error),
+    };
+    let username = get_username(&rltbl, session);
+    let template = query_params.get("template");
+    let key = get_idempotency_key(&headers, &query_params);
+    return add_row(&rltbl, &username, &table, Some(row_id), template, &key).await;
 }
 
 async fn add_row_end(
     State(rltbl): State<Arc<Relatable>>,
     session: Session<SessionNullPool>,
     Path(table): Path<String>,
+    Query(query_params): Query<QueryParams>,
+    headers: HeaderMap,
 ) -> Response<Body> {
     tracing::info!("add_row_end({table})");
-    let username = get_username(session);
-    return add_row(&rltbl, &username, &table, None).await;
+    let username = get_username(&rltbl, session);
+    let template = query_params.get("template");
+    let key = get_idempotency_key(&headers, &query_params);
+    return add_row(&rltbl, &username, &table, None, template, &key).await;
 }
 
 async fn add_row(
@@ -614,6 +1311,8 @@ async fn add_row(
     username: &str,
     table: &str,
     after_id: Option<u64>,
+    template: Option<&String>,
+    key: &Option<String>,
 ) -> Response<Body> {
     if rltbl.readonly {
         return forbid().into();
@@ -628,8 +1327,19 @@ async fn add_row(
             .map(|c| (c.name.clone(), json!(String::new())))
             .collect(),
     };
-    match rltbl.add_row(&table, &username, after_id, &json_row).await {
-        Ok(row) => {
+    let json_row = match template {
+        Some(template) => match rltbl.apply_template(table, template, &json_row).await {
+            Ok(json_row) => json_row,
+            Err(error) => return get_404(&error),
+        },
+        None => json_row,
+    };
+    match run_idempotent(rltbl, username, key, || {
+        rltbl.add_row(table, username, after_id, &json_row)
+    })
+    .await
+    {
+        Ok(Some(row)) => {
             // tracing::info!("Added row {row:?}");
             let offset = rltbl
                 .connection
@@ -649,6 +1359,10 @@ async fn add_row(
             let url = format!("{}/table/{table}?offset={offset}", rltbl.root);
             return Redirect::temporary(url.as_str()).into_response();
         }
+        Ok(None) => {
+            let url = format!("{}/table/{table}", rltbl.root);
+            Redirect::temporary(url.as_str()).into_response()
+        }
         Err(error) => return get_500(&error),
     }
 }
@@ -656,17 +1370,28 @@ async fn add_row(
 async fn delete_row(
     State(rltbl): State<Arc<Relatable>>,
     session: Session<SessionNullPool>,
-    Path((table, row_id)): Path<(String, u64)>,
+    Path((table, row_id)): Path<(String, String)>,
+    Query(query_params): Query<QueryParams>,
+    headers: HeaderMap,
 ) -> Response<Body> {
     tracing::info!("add_row_after({table}, {row_id})");
     if rltbl.readonly {
         return forbid().into();
     }
+    let row_id = match Table::get_row_id(&table, &row_id, &rltbl).await {
+        Ok(row_id) => row_id,
+        Err(error) => return get_404(&error),
+    };
 
-    let username = get_username(session);
+    let username = get_username(&rltbl, session);
     let prev = previous_row_id(&rltbl, &table, &row_id).await;
-    match rltbl.delete_row(&table, &username, row_id).await {
-        Ok(_) => {
+    let key = get_idempotency_key(&headers, &query_params);
+    match run_idempotent(&rltbl, &username, &key, || {
+        rltbl.delete_row(&table, &username, row_id)
+    })
+    .await
+    {
+        Ok(Some(_)) => {
             let offset = rltbl
                 .connection
                 .query_value(
@@ -686,6 +1411,10 @@ async fn delete_row(
             let url = format!("{}/table/{table}?offset={offset}", rltbl.root);
             Redirect::temporary(url.as_str()).into_response()
         }
+        Ok(None) => {
+            let url = format!("{}/table/{table}", rltbl.root);
+            Redirect::temporary(url.as_str()).into_response()
+        }
         Err(error) => return get_500(&error),
     }
 }
@@ -718,15 +1447,73 @@ pub async fn build_app(shared_state: Arc<Relatable>) -> Router {
         .route("/add-row-before/{table}/{row_id}", get(add_row_before))
         .route("/add-row-after/{table}/{row_id}", get(add_row_after))
         .route("/delete-row/{table}/{row_id}", get(delete_row))
+        .route("/cell/{table}/{row_id}/{column}", get(get_cell))
+        .route("/changes/{table}", get(get_changes))
+        .route("/undo/preview", get(get_undo_preview))
+        .route("/load/{table}", post(post_load))
+        .route("/validate/{table}", post(post_validate))
+        .route(
+            "/fill-down/{table}/{column}/{from_row}/{to_row}",
+            post(post_fill_down),
+        )
+        .route("/paste/{table}/{row}/{column}", post(post_paste))
         .layer(SessionLayer::new(session_store))
         .with_state(shared_state)
 }
 
-#[tokio::main]
-pub async fn app(rltbl: Relatable, host: &str, port: &u16, timeout: &usize) -> Result<String> {
+/// Run the server for the given instance at the given host and port, for the given number of
+/// seconds, or, if it is 0, until it is asked to stop, waiting at most `drain_timeout` seconds
+/// for the requests in progress to finish. If some are still in progress then, the tables whose
+/// automatic saves are pending are saved, and an error is returned without waiting for them.
+pub fn app(
+    rltbl: Relatable,
+    host: &str,
+    port: &u16,
+    timeout: &usize,
+    drain_timeout: &u64,
+) -> Result<String> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(run_app(rltbl, host, port, timeout, drain_timeout));
+    // Dropping the runtime would wait for the requests that are still in progress, if any, so it
+    // is shut down without them instead. Their transactions are rolled back by the database:
+    runtime.shutdown_background();
+    result
+}
+
+async fn run_app(
+    rltbl: Relatable,
+    host: &str,
+    port: &u16,
+    timeout: &usize,
+    drain_timeout: &u64,
+) -> Result<String> {
     let shared_state = Arc::new(rltbl);
 
-    let app = build_app(shared_state).await;
+    // Set once the server has been asked to stop:
+    let (stop, stopping) = tokio::sync::watch::channel(false);
+
+    // Save the tables whose automatic saves are pending once they have not been changed for
+    // the autosave interval, until the server is asked to stop. They are checked several times
+    // per interval, so that they are saved soon after they are due:
+    let autosaver = shared_state.clone();
+    let mut autosaver_stopping = stopping.clone();
+    let autosaves = tokio::spawn(async move {
+        let period = (autosaver.autosave_interval / 4).max(std::time::Duration::from_millis(50));
+        let mut interval = tokio::time::interval(period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = autosaver_stopping.wait_for(|stopping| *stopping) => break,
+            }
+            if let Err(err) = autosaver.flush_autosaves().await {
+                tracing::warn!("Error saving tables automatically: {err}");
+            }
+        }
+    });
+
+    let app = build_app(shared_state.clone()).await;
 
     // Create a `TcpListener` using tokio.
     let addr = format!("{host}:{port}");
@@ -736,28 +1523,71 @@ pub async fn app(rltbl: Relatable, host: &str, port: &u16, timeout: &usize) -> R
         listener.local_addr()?
     );
 
-    // Run the server with graceful shutdown
-    if *timeout == 0 {
-        println!("Press Control-C to quit.");
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_on_signal())
-            .await
-            .unwrap();
-    } else {
-        println!("Running server for {timeout}s.");
-        axum::serve(listener, app)
-            .with_graceful_shutdown(shutdown_on_timeout(*timeout))
-            .await
-            .unwrap();
+    // Run the server with graceful shutdown: once it is asked to stop, it accepts no new
+    // requests, and waits for those in progress to finish, for at most `drain_timeout` seconds.
+    let timeout = *timeout;
+    let shutdown = async move {
+        if timeout == 0 {
+            println!("Press Control-C to quit.");
+            shutdown_on_signal().await;
+        } else {
+            println!("Running server for {timeout}s.");
+            shutdown_on_timeout(timeout).await;
+        }
+        println!("Waiting for the requests in progress to finish...");
+        let _ = stop.send(true);
+    };
+    let server = axum::serve(listener, app).with_graceful_shutdown(shutdown);
+    let mut drain_stopping = stopping.clone();
+    let drain_timeout = *drain_timeout;
+    let deadline = async move {
+        let _ = drain_stopping.wait_for(|stopping| *stopping).await;
+        tokio::time::sleep(std::time::Duration::from_secs(drain_timeout)).await;
+    };
+    let drained = tokio::select! {
+        result = server => {
+            result?;
+            true
+        }
+        _ = deadline => false,
+    };
+
+    // Let any automatic save that is under way finish, then save the tables whose automatic
+    // saves are still pending:
+    if let Err(err) = autosaves.await {
+        tracing::warn!("Error stopping automatic saves: {err}");
     }
+    shared_state.save_pending_autosaves().await?;
 
+    // The connections that the requests in progress are using would never be returned to the
+    // pool, so it is not closed when the server is stopped without them:
+    if !drained {
+        return Err(RelatableError::TimeoutError(format!(
+            "Requests were still in progress after {drain_timeout}s; stopping without them"
+        ))
+        .into());
+    }
+    shared_state.close().await?;
     Ok("Stopping Relatable server...".into())
 }
 
-pub async fn serve(cli: &Cli, host: &str, port: &u16, timeout: &usize) -> Result<()> {
+pub async fn serve(
+    cli: &Cli,
+    host: &str,
+    port: &u16,
+    timeout: &usize,
+    query_timeout: &u64,
+    max_limit: &usize,
+    drain_timeout: &u64,
+) -> Result<()> {
     tracing::debug!("serve({host}, {port})");
-    let rltbl = Relatable::connect(None, &cli.caching).await?;
-    app(rltbl, host, port, timeout)?;
+    let mut rltbl = Relatable::connect(None, &cli.caching).await?;
+    if *query_timeout > 0 {
+        rltbl.query_timeout = Some(std::time::Duration::from_millis(*query_timeout));
+    }
+    rltbl.max_limit = *max_limit;
+    rltbl.autosave_interval = std::time::Duration::from_millis(DEFAULT_AUTOSAVE_INTERVAL);
+    app(rltbl, host, port, timeout, drain_timeout)?;
     Ok(())
 }
 
@@ -893,3 +1723,78 @@ async fn serialize_response(response: Response<Body>) -> Vec<u8> {
 
     output
 }
+
+#[cfg(test)]
+mod tests {
+    use async_std::task::block_on;
+    use pretty_assertions::assert_eq;
+    use rltbl::core::tests::memory_demo;
+
+    use super::*;
+
+    #[test]
+    fn test_post_load() {
+        let mut rltbl = memory_demo(5);
+        rltbl.default_user = Some("alice".to_string());
+        let path = "build/test_post_load.tsv";
+        let upload_path = "build/.upload.test_post_load.tsv";
+        let sql = format!(r#"UPDATE "table" SET "path" = '{path}' WHERE "table" = 'penguin'"#);
+        block_on(rltbl.connection.query(&sql, None)).unwrap();
+        std::fs::write(path, "original").unwrap();
+        let rltbl = Arc::new(rltbl);
+        let mut router = block_on(build_app(rltbl.clone()));
+        let mut post = |uri: &str, body: &str| {
+            let request = axum::http::Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header("X-Change-Message", "Upload")
+                .body(body.to_string())
+                .unwrap();
+            block_on(router.call(request)).unwrap().status()
+        };
+        let query_value = |sql: &str| block_on(rltbl.connection.query_value(sql, None)).unwrap();
+        let content = "study_name\tsample_number\nFAKE\t1\nFAKE\t2\n";
+
+        // The meta tables, and tables that are not listed in the table table, cannot be loaded:
+        let changes = query_value(r#"SELECT COUNT(1) FROM "change""#);
+        for table in ["change", "table", "no_such_table"] {
+            assert_eq!(
+                post(&format!("/load/{table}?force=true"), content),
+                StatusCode::INTERNAL_SERVER_ERROR
+            );
+            assert!(!std::path::Path::new(&format!("{table}.tsv")).exists());
+        }
+        assert_eq!(query_value(r#"SELECT COUNT(1) FROM "change""#), changes);
+        assert!(query_value(r#"SELECT 1 FROM "table" WHERE "table" = 'penguin'"#).is_some());
+
+        // An existing table is only replaced when forced, and a failed load leaves its file as it
+        // was:
+        assert_eq!(
+            post("/load/penguin", content),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(
+            post("/load/penguin?force=true", ""),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "original");
+        assert!(!std::path::Path::new(upload_path).exists());
+
+        // A successful load replaces the file and is recorded as the signed-in user:
+        assert_eq!(post("/load/penguin?force=true", content), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(path).unwrap(), content);
+        assert!(!std::path::Path::new(upload_path).exists());
+        assert_eq!(
+            query_value(r#"SELECT "path" FROM "table" WHERE "table" = 'penguin'"#),
+            Some(json!(path))
+        );
+        assert_eq!(
+            query_value(r#"SELECT COUNT(1) FROM "penguin""#),
+            Some(json!(2))
+        );
+        assert_eq!(
+            query_value(r#"SELECT "user" FROM "change" WHERE "message" = 'Upload'"#),
+            Some(json!("alice"))
+        );
+    }
+}